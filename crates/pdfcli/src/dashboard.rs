@@ -0,0 +1,388 @@
+//! `--tui`: a live dashboard of a run over many files, drawn on stderr in
+//! place of per-file lines.
+//!
+//! It shows the status of each file, throughput, how busy the workers are,
+//! and the most recent errors, fed by [`pdfcore::Progress`] events. The
+//! dashboard is redrawn in place a few times a second with `crossterm`; the
+//! last frame stays on screen when the run is over.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex, PoisonError},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use anyhow::bail;
+use crossterm::{
+    cursor,
+    event::{self, Event},
+    queue,
+    style::Print,
+    terminal::{self, ClearType},
+};
+
+use crate::format_bytes;
+
+/// How often the dashboard is redrawn.
+const REDRAW_EVERY: Duration = Duration::from_millis(250);
+
+/// Errors kept for the "recent errors" section.
+const RECENT_ERRORS: usize = 5;
+
+/// A dashboard on stderr, until dropped.
+pub(crate) struct Dashboard {
+    state: Arc<Mutex<State>>,
+    /// Dropping it tells the drawer to draw the last frame and stop.
+    stop: Option<mpsc::Sender<()>>,
+    drawer: Option<JoinHandle<()>>,
+}
+
+impl Dashboard {
+    /// Start drawing the dashboard of `title` with `workers` workers.
+    pub(crate) fn start(title: impl Into<String>, workers: usize) -> anyhow::Result<Self> {
+        if !io::stderr().is_terminal() {
+            bail!("--tui draws on stderr; run it in a terminal");
+        }
+        let state = Arc::new(Mutex::new(State::new(title.into(), workers)));
+        let (stop, stopped) = mpsc::channel::<()>();
+        let drawn = Arc::clone(&state);
+        let drawer = thread::spawn(move || {
+            let mut height = 0;
+            // The size is read once, then taken from resize events (keys
+            // typed meanwhile are read and dropped with them).
+            let mut size = terminal_size();
+            loop {
+                let last = !matches!(
+                    stopped.recv_timeout(REDRAW_EVERY),
+                    Err(mpsc::RecvTimeoutError::Timeout)
+                );
+                while event::poll(Duration::ZERO).unwrap_or(false) {
+                    if let Ok(Event::Resize(cols, rows)) = event::read() {
+                        size = (usize::from(rows), usize::from(cols));
+                    }
+                }
+                let lines = lock(&drawn).draw(size);
+                let mut stderr = io::stderr().lock();
+                let _ = redraw(&mut stderr, height, &lines, last);
+                height = lines.len();
+                if last {
+                    break;
+                }
+            }
+        });
+        Ok(Self {
+            state,
+            stop: Some(stop),
+            drawer: Some(drawer),
+        })
+    }
+
+    /// Where the run reports its progress.
+    pub(crate) fn progress(&self) -> pdfcore::Progress {
+        let state = Arc::clone(&self.state);
+        pdfcore::Progress::new(move |event| lock(&state).update(event))
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(drawer) = self.drawer.take() {
+            let _ = drawer.join();
+        }
+    }
+}
+
+/// A dashboard of `title` if `tui`, with the progress to report to it
+/// (reported nowhere without one).
+pub(crate) fn start_if(
+    tui: bool,
+    title: &str,
+    workers: usize,
+) -> anyhow::Result<(Option<Dashboard>, pdfcore::Progress)> {
+    if !tui {
+        return Ok((None, pdfcore::Progress::default()));
+    }
+    let dashboard = Dashboard::start(title, workers)?;
+    let progress = dashboard.progress();
+    Ok((Some(dashboard), progress))
+}
+
+/// Rows and columns of the terminal; 24 x 80 if unknown.
+fn terminal_size() -> (usize, usize) {
+    terminal::size()
+        .ok()
+        .filter(|&(cols, rows)| rows > 0 && cols > 0)
+        .map_or((24, 80), |(cols, rows)| {
+            (usize::from(rows), usize::from(cols))
+        })
+}
+
+fn lock(state: &Mutex<State>) -> std::sync::MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Replace the `height` lines drawn last with `lines`; the `last` frame
+/// ends with a newline so later output starts below it.
+fn redraw(out: &mut impl Write, height: usize, lines: &[String], last: bool) -> io::Result<()> {
+    queue!(out, cursor::MoveToColumn(0))?;
+    if height > 1 {
+        queue!(
+            out,
+            cursor::MoveUp(u16::try_from(height - 1).unwrap_or(u16::MAX))
+        )?;
+    }
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            queue!(out, Print("\n"), cursor::MoveToColumn(0))?;
+        }
+        queue!(out, Print(line), terminal::Clear(ClearType::UntilNewLine))?;
+    }
+    // Clear what is left of a taller frame.
+    queue!(out, terminal::Clear(ClearType::FromCursorDown))?;
+    if last {
+        queue!(out, Print("\n"))?;
+    }
+    out.flush()
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Status {
+    Queued,
+    Running { worker: usize, since: Instant },
+    Done(Duration),
+    Failed(Duration),
+    Skipped,
+}
+
+struct FileRow {
+    input: PathBuf,
+    status: Status,
+    /// When the status last changed, in events.
+    changed: u64,
+}
+
+#[derive(Default)]
+struct Worker {
+    /// The file being processed and since when.
+    current: Option<(PathBuf, Instant)>,
+    /// Time spent on files that are done.
+    busy: Duration,
+}
+
+struct State {
+    title: String,
+    started: Instant,
+    files: Vec<FileRow>,
+    index: HashMap<PathBuf, usize>,
+    workers: Vec<Worker>,
+    done: usize,
+    failed: usize,
+    bytes: u64,
+    errors: VecDeque<String>,
+    events: u64,
+}
+
+impl State {
+    fn new(title: String, workers: usize) -> Self {
+        Self {
+            title,
+            started: Instant::now(),
+            files: Vec::new(),
+            index: HashMap::new(),
+            workers: (0..workers.max(1)).map(|_| Worker::default()).collect(),
+            done: 0,
+            failed: 0,
+            bytes: 0,
+            errors: VecDeque::new(),
+            events: 0,
+        }
+    }
+
+    fn update(&mut self, event: pdfcore::ProgressEvent) {
+        use pdfcore::ProgressEvent as E;
+        self.events += 1;
+        let now = Instant::now();
+        let (input, status) = match event {
+            E::Queued { input } => (input, Status::Queued),
+            E::Skipped { input } => (input, Status::Skipped),
+            E::Started { input, worker } => {
+                if worker >= self.workers.len() {
+                    self.workers.resize_with(worker + 1, Worker::default);
+                }
+                self.workers[worker].current = Some((input.clone(), now));
+                (input, Status::Running { worker, since: now })
+            }
+            E::Finished {
+                input,
+                worker,
+                bytes,
+                elapsed,
+            } => {
+                self.done += 1;
+                self.bytes += bytes;
+                self.worker_idle(worker, elapsed);
+                (input, Status::Done(elapsed))
+            }
+            E::Failed {
+                input,
+                worker,
+                error,
+                elapsed,
+            } => {
+                self.failed += 1;
+                self.worker_idle(worker, elapsed);
+                if self.errors.len() == RECENT_ERRORS {
+                    self.errors.pop_front();
+                }
+                self.errors
+                    .push_back(format!("{}: {error}", file_name(&input)));
+                (input, Status::Failed(elapsed))
+            }
+        };
+        let changed = self.events;
+        if let Some(&i) = self.index.get(&input) {
+            self.files[i].status = status;
+            self.files[i].changed = changed;
+        } else {
+            self.index.insert(input.clone(), self.files.len());
+            self.files.push(FileRow {
+                input,
+                status,
+                changed,
+            });
+        }
+    }
+
+    fn worker_idle(&mut self, worker: usize, elapsed: Duration) {
+        if let Some(worker) = self.workers.get_mut(worker) {
+            worker.current = None;
+            worker.busy += elapsed;
+        }
+    }
+
+    /// The dashboard for a terminal of `rows` x `cols`.
+    #[allow(clippy::cast_precision_loss)] // rates for display
+    fn draw(&self, (rows, cols): (usize, usize)) -> Vec<String> {
+        let now = Instant::now();
+        let wall = now.duration_since(self.started);
+        let secs = wall.as_secs_f64().max(0.001);
+        let count =
+            |pick: fn(&Status) -> bool| self.files.iter().filter(|row| pick(&row.status)).count();
+        let running = count(|s| matches!(s, Status::Running { .. }));
+        let queued = count(|s| matches!(s, Status::Queued));
+
+        let mut lines = vec![
+            format!(
+                "{}: {} done, {} failed, {running} running, {queued} queued  [{}]",
+                self.title,
+                self.done,
+                self.failed,
+                clock(wall)
+            ),
+            format!(
+                "throughput: {:.1} files/s, {}/s",
+                (self.done + self.failed) as f64 / secs,
+                format_bytes(
+                    u64::try_from(u128::from(self.bytes) * 1000 / wall.as_millis().max(1))
+                        .unwrap_or(u64::MAX)
+                )
+            ),
+        ];
+
+        let busy: Duration = self
+            .workers
+            .iter()
+            .map(|w| w.busy + w.current.as_ref().map_or(Duration::ZERO, |(_, t)| now - *t))
+            .sum();
+        let capacity = wall.as_secs_f64() * self.workers.len() as f64;
+        let utilization = if capacity > 0.0 {
+            (busy.as_secs_f64() / capacity * 100.0).min(100.0)
+        } else {
+            0.0
+        };
+        lines.push(format!(
+            "workers: {} of {} busy, {utilization:.0}% utilized",
+            self.workers.iter().filter(|w| w.current.is_some()).count(),
+            self.workers.len()
+        ));
+        for (i, worker) in self.workers.iter().enumerate() {
+            lines.push(match &worker.current {
+                Some((input, since)) => format!(
+                    "  #{:<3} {} ({})",
+                    i + 1,
+                    file_name(input),
+                    seconds(now - *since)
+                ),
+                None => format!("  #{:<3} idle", i + 1),
+            });
+        }
+
+        if !self.errors.is_empty() {
+            lines.push("recent errors:".to_string());
+            lines.extend(self.errors.iter().map(|e| format!("  {e}")));
+        }
+
+        // Running files first, then the latest to finish, then the next up.
+        let mut shown: Vec<&FileRow> = self.files.iter().collect();
+        shown.sort_by_key(|row| match row.status {
+            Status::Running { .. } => (0, 0),
+            Status::Done(_) | Status::Failed(_) | Status::Skipped => (1, u64::MAX - row.changed),
+            Status::Queued => (2, 0),
+        });
+        let room = rows.saturating_sub(1).saturating_sub(lines.len() + 1);
+        if room > 0 && !shown.is_empty() {
+            lines.push("files:".to_string());
+            for row in shown.into_iter().take(room) {
+                lines.push(match row.status {
+                    Status::Queued => format!("  queued   {}", file_name(&row.input)),
+                    Status::Skipped => format!("  skipped  {}", file_name(&row.input)),
+                    Status::Running { worker, since } => format!(
+                        "  running  {} (#{}, {})",
+                        file_name(&row.input),
+                        worker + 1,
+                        seconds(now - since)
+                    ),
+                    Status::Done(took) => {
+                        format!("  done     {} ({})", file_name(&row.input), seconds(took))
+                    }
+                    Status::Failed(took) => {
+                        format!("  FAILED   {} ({})", file_name(&row.input), seconds(took))
+                    }
+                });
+            }
+        }
+
+        lines.truncate(rows.saturating_sub(1).max(1));
+        // Lines that wrapped would break redrawing in place.
+        let width = cols.saturating_sub(1).max(1);
+        lines
+            .into_iter()
+            .map(|line| line.chars().take(width).collect())
+            .collect()
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// `1:02:03` or `2:03`.
+fn clock(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+fn seconds(elapsed: Duration) -> String {
+    format!("{:.1}s", elapsed.as_secs_f64())
+}
@@ -2,20 +2,96 @@ use std::{
     fs,
     io::{self, Write},
     path::{Path, PathBuf},
-    process,
+    process, thread,
+    time::{Duration, Instant},
 };
 
 use anyhow::{bail, Context};
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{ArgAction, CommandFactory, Parser, Subcommand, ValueEnum};
+use pdfcore::json_string;
+
+mod dashboard;
+mod open;
+mod pick;
+mod remote;
+mod serve;
+mod watch;
 
 #[derive(Debug, Parser)]
 #[command(
     name = "pdfcli",
     version,
     about = "CLI wrapper around PDF utilities",
-    after_help = "EXAMPLES:\n  pdfcli info file.pdf\n  pdfcli info file.pdf --json\n\n  pdfcli merge -o merged.pdf a.pdf b.pdf c.pdf\n\n  pdfcli split-pages input.pdf --out-dir pages/\n  pdfcli split-pages input.pdf --out-dir pages/ --pattern 'page-%d.pdf'\n\n  pdfcli extract-text input.pdf --stdout\n  pdfcli extract-text input.pdf -o out.txt\n\n  pdfcli rotate input.pdf -o rotated.pdf --degrees 90\n  pdfcli rotate input.pdf -o rotated.pdf --degrees 180 --pages '1-3'\n\n  pdfcli compress input.pdf -o small.pdf --preset ebook\n\nNOTES:\n  - Some commands rely on external tools (qpdf, pdftotext, ghostscript).\n  - Tool locations can be overridden with env vars: PDFCLI_QPDF, PDFCLI_PDFTOTEXT, PDFCLI_GS\n"
+    after_help = "EXAMPLES:\n  pdfcli info file.pdf\n  pdfcli info file.pdf --json\n  pdfcli info scan.pdf --pages-only\n  pdfcli info locked.pdf --password secret\n  pdfcli info 'archive/*.pdf' --format csv > inventory.csv\n  pdfcli fonts file.pdf\n  pdfcli size-report file.pdf\n  pdfcli objects file.pdf\n  pdfcli ink-coverage brochure.pdf --json\n  pdfcli stats manuscript.pdf --json\n  pdfcli page-sizes input.pdf\n  pdfcli page-sizes input.pdf --fix --to a4 -o uniform.pdf\n  pdfcli show-object file.pdf 12\n  pdfcli links file.pdf --json\n  pdfcli links rewrite file.pdf -o out.pdf --map old-domain.com=new-domain.com\n  pdfcli links strip file.pdf -o out.pdf --external-only\n  pdfcli barcodes scans.pdf --json\n  pdfcli dests list file.pdf --json\n  pdfcli dests add file.pdf -o out.pdf --name chapter2 --page 14\n  pdfcli boxes show file.pdf --json\n  pdfcli boxes file.pdf -o out.pdf --set trim=20,20,575,822 --pages 1-4\n  pdfcli boxes file.pdf -o cropped.pdf --set crop=36,36,559,806 --open\n  pdfcli permissions file.pdf\n  pdfcli permissions set file.pdf -o locked.pdf --no-print --no-copy --owner-password secret\n  pdfcli revisions signed.pdf\n  pdfcli revisions extract signed.pdf --rev 0 -o original.pdf\n  pdfcli fingerprint report.pdf --json\n  pdfcli analyze scans.pdf --min-dpi 300\n  pdfcli content file.pdf --page 3\n  pdfcli qdf file.pdf -o debug.pdf\n  pdfcli xfa dump form.pdf -o form.xml\n  pdfcli embed-fonts file.pdf -o embedded.pdf\n\n  pdfcli merge -o merged.pdf a.pdf b.pdf c.pdf\n  pdfcli merge -o merged.pdf scans/*.pdf --keep-going\n  pdfcli merge -o catalog.pdf generated/*.pdf --normalize\n  pdfcli pick input.pdf -o picked.pdf\n  pdfcli header-footer input.pdf -o out.pdf --header-left '{title}' --footer-right '{date} \u{2022} {n}/{total}'\n  pdfcli sign contract.pdf --pkcs12 me.p12 --password secret -o signed.pdf --visible --page 1 --rect 350,50,550,110\n  pdfcli sign contract.pdf --pkcs12 me.p12 -o signed.pdf --tsa-url http://timestamp.example.com\n  pdfcli signatures signed.pdf --ca-file roots.pem\n  pdfcli sign-visual contract.pdf --image sig.png --page 3 --at 400,120 --date-format '%Y-%m-%d' -o signed.pdf\n  pdfcli bates production/*.pdf --out-dir stamped/ --prefix ABC --start 1000 --digits 6\n\n  pdfcli split-pages input.pdf --out-dir pages/\n  pdfcli split-pages input.pdf --out-dir pages/ --pattern 'page-%d.pdf'\n  pdfcli split-pages input.pdf --out-dir pages/ --manifest pages/manifest.json\n  pdfcli split scans.pdf --out-dir invoices/ --name-from-text --pattern 'Invoice Number: (\\S+)'\n  pdfcli split batch.pdf --separator qr --out-dir jobs/\n  pdfcli split input.pdf --max-bytes 9MB --out-dir parts/\n\n  pdfcli extract-text input.pdf --stdout\n  pdfcli extract-text input.pdf -o out.txt\n  pdfcli --engine native extract-text input.pdf --stdout\n  pdfcli extract-text 'archive/*.pdf' --format jsonl > pages.jsonl\n  pdfcli extract-text input.pdf -o out.txt --encoding utf-16le --bom --eol crlf\n  pdfcli extract-tables report.pdf --out-dir tables/ --format csv\n  pdfcli from-text notes.md -o notes.pdf --font-size 11 --paper a4\n  pdfcli from-html report.html -o report.pdf --paper letter --margin 15mm\n  pdfcli convert minutes.docx -o minutes.pdf\n  pdfcli from-ps report.ps -o report.pdf --preset printer\n  pdfcli from-tiff fax.tif -o fax.pdf\n  pdfcli from-eml message.eml --extract-pdfs --out-dir ./\n  pdfcli from-eml message.msg --extract-pdfs --merge attachments.pdf\n\n  pdfcli render input.pdf --out-dir images/ --dpi 300\n  pdfcli thumbnail 'library/*.pdf' --out-dir covers/ --cache-dir ~/.cache/pdfcli\n  pdfcli to-svg paper.pdf --page 2 -o figure.svg\n  pdfcli to-ps input.pdf -o out.ps --pages 1-3\n  pdfcli to-ps input.pdf -o figure.eps --pages 2 --eps\n  pdfcli to-tiff input.pdf -o fax.tif --dpi 200 --compression g4\n  pdfcli print input.pdf --printer OfficeLaser --copies 2 --pages 1-4 --duplex\n\n  pdfcli rotate input.pdf -o rotated.pdf --degrees 90\n  pdfcli rotate input.pdf -o rotated.pdf --degrees 180 --pages '1-3'\n  pdfcli rotate scan.pdf -o upright.pdf --degrees auto\n  pdfcli rotate input.pdf -o rotated.pdf --spec '1-3:90,4:180,5-:270'\n\n  pdfcli compress input.pdf -o small.pdf --preset ebook\n  pdfcli compress input.pdf -o small.pdf --json\n  pdfcli compress archive.zip -o archive-compressed.zip --keep-going\n  pdfcli optimize catalog.pdf -o smaller.pdf --dedupe\n  pdfcli --engine native optimize edited.pdf -o clean.pdf --gc\n  pdfcli recompress-images scans.pdf -o small.pdf --max-dpi 150 --jpeg-quality 70\n  pdfcli transcode-images scans.pdf -o compatible.pdf --from jpx --to jpeg\n\n  pdfcli convert-color input.pdf -o gray.pdf --to gray\n\n  pdfcli run 'flatten | rotate --degrees 90 | compress --preset ebook' input.pdf -o out.pdf\n  pdfcli watch ./inbox --do 'compress --preset ebook' --out-dir ./outbox\n  pdfcli serve --listen 127.0.0.1:8080\n\n  pdfcli court-ready input.pdf -o filing.pdf --max-size 25MB\n  pdfcli print-ready input.pdf -o press.pdf --paper a4 --bleed 3mm --cmyk --crop-marks\n  pdfcli bleed input.pdf -o press.pdf --bleed 3mm --crop-marks --registration-marks\n  pdfcli to-pdfx input.pdf -o press.pdf --standard x4 --icc ISOcoated_v2_eci.icc\n  pdfcli a11y-prep scan.pdf -o tagged.pdf --lang en-US --title 'Annual Report'\n  pdfcli a11y-check input.pdf --json\n  pdfcli set-lang input.pdf -o out.pdf --lang ar-EG --direction r2l\n  pdfcli infer-title scan.pdf --apply -o titled.pdf\n  pdfcli open-action handbook.pdf -o out.pdf --dest toc\n  pdfcli open-action untrusted.pdf -o safe.pdf --remove\n  pdfcli viewer-prefs input.pdf -o out.pdf --page-mode outlines --page-layout two-up --open-at 5 --fit width --hide-toolbar\n\n  pdfcli sanitize upload.pdf -o clean.pdf\n  pdfcli sanitize upload.pdf --dry-run --json\n  pdfcli --dry-run court-ready input.pdf -o filing.pdf\n\n  pdfcli meta diff draft.pdf final.pdf --json > patch.json\n  pdfcli meta apply input.pdf --patch patch.json -o out.pdf\n  pdfcli strip-metadata input.pdf -o public.pdf --keep Title\n  pdfcli regen-id input.pdf -o out.pdf --from-content-hash\n  pdfcli redact input.pdf -o out.pdf --page 2 --rect 100,500,300,530\n  pdfcli redact input.pdf -o out.pdf --text 'SSN: \\d{3}-\\d{2}-\\d{4}' --regex\n\n  pdfcli enforce --policy policy.toml 'incoming/**/*.pdf'\n  pdfcli enforce --policy policy.toml 'incoming/**/*.pdf' --quarantine rejected/\n  pdfcli enforce --policy policy.toml 'incoming/**/*.pdf' --jobs 8 --tui\n\n  pdfcli doctor\n  pdfcli doctor --require qpdf,gs\n\n  pdfcli config show\n  pdfcli config set defaults.compress_preset ebook\n  pdfcli config set tools.qpdf /opt/qpdf/bin/qpdf\n\n  pdfcli completions bash > /etc/bash_completion.d/pdfcli\n  pdfcli manpage --out-dir man/man1\n\nNOTES:\n  - Some commands rely on external tools (qpdf, pdftotext, ghostscript, ocrmypdf, mutool, wkhtmltopdf, chrome, soffice, openssl, curl, pdftocairo, lp, sumatrapdf).\n  - Tool locations can be overridden with env vars: PDFCLI_QPDF, PDFCLI_PDFTOTEXT, PDFCLI_GS, PDFCLI_OCRMYPDF, PDFCLI_MUTOOL, PDFCLI_WKHTMLTOPDF, PDFCLI_CHROME, PDFCLI_SOFFICE, PDFCLI_OPENSSL, PDFCLI_CURL, PDFCLI_PDFTOCAIRO, PDFCLI_LP, PDFCLI_SUMATRAPDF\n  - merge, rotate, and extract-text fall back to a native engine when their tool is missing; --engine sets the preferred engines.\n  - Builds with the `pdfium` feature can render and extract text in process (--engine pdfium); set PDFCLI_PDFIUM to the PDFium library if it is not on the library path.\n  - Defaults (compress preset, output directory, jobs, engines, tool paths) are read from ~/.config/pdfcli/config.toml (or --config / PDFCLI_CONFIG), then PDFCLI_* env vars; flags override both.\n  - --timeout 120s kills a tool that runs longer (with any processes it started) and removes its partial output.\n  - --sandbox runs tools with 2 GiB of memory, 5 minutes of CPU, 1 GiB per written file, and a private temp directory (Unix only).\n  - --deterministic makes pure Rust operations write byte-identical output for identical input.\n  - --low-memory memory-maps the files that info, fonts, and links inspect and keeps only their structure, as is done for files of 256 MiB or more.\n  - Intermediate files go to PDFCLI_TMPDIR (default: the system temp directory) and are removed afterwards.\n  - Use -v to see the external commands run, -vv to also see their output.\n  - --dry-run prints the commands and operations a command would perform without writing anything.\n  - Warnings (tool warnings, damaged objects that were skipped, metadata that could not be decoded exactly) are printed as warning: lines; --strict turns them into an error.\n\nEXIT CODES:\n  0 success; 1 other failure; 2 usage; 3 invalid_argument; 4 input_not_found; 5 pdf_parse;\n  6 missing_tool; 7 tool_too_old; 8 tool_failed; 9 fonts_not_embedded; 10 io; 11 pdfium;\n  12 timeout; 13 encrypted; 14 some_inputs_failed; 15 all_inputs_failed;\n  16 warnings (with --strict)\n  With several inputs, --keep-going processes the rest after a failure and exits 14 or 15;\n  --fail-fast stops at the first failure and exits with its code.\n  With --json-errors, failures are printed to stderr as\n  {\"error\":{\"code\",\"exit_code\",\"message\",\"tool\",\"status\",\"hint\"}}, and warnings as\n  {\"warning\":{\"code\",\"message\"}}.\n"
 )]
+#[allow(clippy::struct_excessive_bools)] // independent global flags
 struct Cli {
+    /// Log external commands, timings, and tool versions (-vv adds tool output)
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Validate inputs and print the commands and operations that would run,
+    /// without writing anything
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Engines to prefer, in order (comma-separated: qpdf, gs, pdftotext, pdftocairo, mutool, pdfium, native)
+    #[arg(long, global = true, value_delimiter = ',')]
+    engine: Vec<String>,
+
+    /// Report failures as a JSON object on stderr (see EXIT CODES)
+    #[arg(long, global = true)]
+    json_errors: bool,
+
+    /// Configuration file (default: `PDFCLI_CONFIG`, else ~/.config/pdfcli/config.toml)
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Kill external tools that run longer than this (e.g. 120s, 5m, 1h; a
+    /// bare number is seconds)
+    #[arg(long, global = true, value_name = "DURATION", value_parser = parse_duration)]
+    timeout: Option<Duration>,
+
+    /// Run external tools with memory, CPU, and file size limits and a private
+    /// temporary directory (Unix), for untrusted input
+    #[arg(long, global = true)]
+    sandbox: bool,
+
+    /// Memory-map files when inspecting them (info, fonts, links) and keep
+    /// only the document structure; automatic for files of 256 MiB or more
+    #[arg(long, global = true)]
+    low_memory: bool,
+
+    /// Write byte-identical output for identical input (pure Rust operations):
+    /// no timestamps, and a document ID derived from the content
+    #[arg(long, global = true)]
+    deterministic: bool,
+
+    /// With several inputs, process the rest after one fails and report the
+    /// failures at the end (default for info, extract-text, and thumbnail)
+    #[arg(long, global = true, conflicts_with = "fail_fast")]
+    keep_going: bool,
+
+    /// With several inputs, stop at the first one that fails (default for
+    /// merge)
+    #[arg(long, global = true)]
+    fail_fast: bool,
+
+    /// Treat warnings (tool warnings, repaired input, lossy metadata) as
+    /// errors: exit with code 16 when any was raised
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// Show a live dashboard of per-file status, throughput, workers, and
+    /// recent errors on stderr instead of per-file lines (info and
+    /// extract-text on several files, thumbnail, enforce, watch)
+    #[arg(long, global = true)]
+    tui: bool,
+
+    /// Open the written file in the default viewer when the command succeeds
+    #[arg(long, global = true)]
+    open: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -24,6 +100,30 @@ struct Cli {
 enum Commands {
     /// Print basic information about a PDF (pure Rust; no external tools).
     Info {
+        /// PDF paths or glob patterns; several files are summarized one per line
+        #[arg(required = true)]
+        inputs: Vec<String>,
+
+        /// Output machine-readable JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Only print the page count (fast, even on very large files)
+        #[arg(long)]
+        pages_only: bool,
+
+        /// Password to decrypt an encrypted PDF for full information
+        #[arg(long)]
+        password: Option<String>,
+
+        /// Summarize each file on one line (path, pages, size, version,
+        /// encrypted, title); the default for several files is `table`
+        #[arg(long, value_enum, conflicts_with_all = ["json", "pages_only"])]
+        format: Option<InfoFormat>,
+    },
+
+    /// List fonts used by a PDF with embedding/subset status (pure Rust).
+    Fonts {
         /// Input PDF path
         input: PathBuf,
 
@@ -32,59 +132,269 @@ enum Commands {
         json: bool,
     },
 
-    /// Merge multiple PDFs into a single output PDF (requires qpdf).
-    Merge {
-        /// Output PDF path
-        #[arg(short, long)]
-        output: PathBuf,
+    /// Show what is taking up space in a PDF, by object category (pure Rust).
+    SizeReport {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Group pages by the size and orientation they are shown at, and list
+    /// the pages that differ from the rest (pure Rust).
+    ///
+    /// With --fix --to <paper>, pages of any other size are scaled to fit the
+    /// paper, centered, in the orientation they are shown in.
+    PageSizes {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output machine-readable JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Scale the pages that are not --to onto it
+        #[arg(long, requires_all = ["to", "output"])]
+        fix: bool,
+
+        /// Paper size for --fix (a3, a4, a5, letter, legal, tabloid)
+        #[arg(long, value_parser = parse_paper_size, requires = "fix")]
+        to: Option<pdfcore::PaperSize>,
+
+        /// Output PDF path for --fix
+        #[arg(short, long, requires = "fix")]
+        output: Option<PathBuf>,
 
         /// Overwrite output if it exists
         #[arg(long)]
         force: bool,
+    },
 
-        /// Input PDFs (in order)
+    /// Estimate the cyan, magenta, yellow, and black ink each page takes,
+    /// for print costing (requires ghostscript).
+    ///
+    /// Percentages are of the page area covered by each ink at full
+    /// strength, as ghostscript separates the colors without the printer's
+    /// profile.
+    InkCoverage {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Count the words and characters of each page and in total, estimate
+    /// the reading time, and detect the language.
+    ///
+    /// Words are runs of non-whitespace holding a letter or digit; a
+    /// language is shown only when there is enough text to be sure of it.
+    Stats {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Pages to count (e.g. 1-4, 3, 5-; default: all)
+        #[arg(long, value_parser = parse_page_selection)]
+        pages: Option<pdfcore::PageSelection>,
+
+        /// Reading speed for the reading time, in words per minute
+        #[arg(long, default_value_t = pdfcore::READING_WORDS_PER_MINUTE)]
+        wpm: u32,
+
+        /// Output machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List every object with its type and stream length (pure Rust).
+    Objects {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Dump one object, decoding its stream (pure Rust).
+    ///
+    /// Stream data is written to stdout after the dictionary.
+    ShowObject {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Object number
+        object: u32,
+
+        /// Print stream data as stored, without removing filters
+        #[arg(long)]
+        raw: bool,
+    },
+
+    /// List link annotations with their targets, or edit them (pure Rust).
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Links {
+        #[command(subcommand)]
+        action: Option<LinksCommand>,
+
+        /// Input PDF path
         #[arg(required = true)]
-        inputs: Vec<PathBuf>,
+        input: Option<PathBuf>,
+
+        /// Output machine-readable JSON
+        #[arg(long)]
+        json: bool,
     },
 
-    /// Split a PDF into one PDF per page (requires qpdf).
-    SplitPages {
+    /// Find and read the QR codes, Code 128, and EAN barcodes printed on each page
+    /// (renders pages with ghostscript, mutool, or pdfium).
+    Barcodes {
         /// Input PDF path
         input: PathBuf,
 
-        /// Directory to write pages into
+        /// Output machine-readable JSON
         #[arg(long)]
-        out_dir: PathBuf,
+        json: bool,
+    },
+
+    /// List or add named destinations, the targets of `file.pdf#name` links (pure Rust).
+    Dests {
+        #[command(subcommand)]
+        action: DestsCommand,
+    },
+
+    /// List a file's incremental-update revisions, or extract an earlier one (pure Rust).
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Revisions {
+        #[command(subcommand)]
+        action: Option<RevisionsCommand>,
+
+        /// Input PDF path
+        #[arg(required = true)]
+        input: Option<PathBuf>,
 
-        /// Output pattern, must contain %d (page number, 1-based)
+        /// Output machine-readable JSON
         #[arg(long)]
-        pattern: Option<String>,
+        json: bool,
+    },
 
-        /// Overwrite existing files (best-effort; may still fail if tool refuses)
+    /// Analyze a document's content (pure Rust).
+    ///
+    /// Lists every image drawn, with its pixel size, the size it is drawn
+    /// at, and the resolution that gives, and flags the pages with images
+    /// below --min-dpi.
+    Analyze {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Flag images drawn at a lower resolution than this
+        #[arg(long, value_name = "DPI", default_value_t = 200.0)]
+        min_dpi: f32,
+
+        /// Output machine-readable JSON
         #[arg(long)]
-        force: bool,
+        json: bool,
     },
 
-    /// Extract text from a PDF (requires pdftotext).
-    ExtractText {
+    /// Print a hash of each page's content and resources, to see which pages
+    /// changed between versions of a document (pure Rust).
+    ///
+    /// Rewriting, recompressing, or renumbering the objects of a file leaves
+    /// the hashes as they are.
+    Fingerprint {
         /// Input PDF path
         input: PathBuf,
 
-        /// Output text file path
-        #[arg(short, long, conflicts_with = "stdout")]
+        /// Output machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show what an encrypted PDF permits, or encrypt it with new permissions (requires qpdf).
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Permissions {
+        #[command(subcommand)]
+        action: Option<PermissionsCommand>,
+
+        /// Input PDF path
+        #[arg(required = true)]
+        input: Option<PathBuf>,
+
+        /// Output machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Set or remove page boxes (media, crop, bleed, trim, art), or show them
+    /// with `boxes show` (pure Rust).
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Boxes {
+        #[command(subcommand)]
+        action: Option<BoxesCommand>,
+
+        /// Input PDF path
+        #[arg(required = true)]
+        input: Option<PathBuf>,
+
+        /// Output PDF path
+        #[arg(short, long, required = true)]
         output: Option<PathBuf>,
 
-        /// Write extracted text to stdout
+        /// Overwrite output if it exists
         #[arg(long)]
-        stdout: bool,
+        force: bool,
+
+        /// Set a box to x1,y1,x2,y2 in points, e.g. trim=20,20,575,822 (repeatable)
+        #[arg(long, value_name = "BOX=RECT", value_parser = parse_box_assignment)]
+        set: Vec<(pdfcore::PageBox, pdfcore::Rect)>,
+
+        /// Remove a box so it defaults to the crop box again (repeatable)
+        #[arg(long, value_name = "BOX", value_parser = parse_page_box)]
+        unset: Vec<pdfcore::PageBox>,
+
+        /// Pages to change (e.g. 1-4, 3, 5-; default: all)
+        #[arg(long, value_parser = parse_page_selection)]
+        pages: Option<pdfcore::PageSelection>,
+    },
+
+    /// Work with XFA (XML Forms Architecture) forms (pure Rust).
+    Xfa {
+        #[command(subcommand)]
+        action: XfaCommand,
+    },
+
+    /// Print a page's content stream, one operator per line (pure Rust).
+    Content {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Page number (1-based)
+        #[arg(long, default_value_t = 1)]
+        page: u32,
+    },
+
+    /// Write an uncompressed copy for inspection in a text editor.
+    ///
+    /// Uses `qpdf --qdf` when available; otherwise streams are decompressed
+    /// in pure Rust.
+    Qdf {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
 
         /// Overwrite output if it exists
         #[arg(long)]
         force: bool,
     },
 
-    /// Rotate pages in a PDF (requires qpdf).
-    Rotate {
+    /// Embed and subset all fonts, then verify none are left unembedded (requires ghostscript).
+    EmbedFonts {
         /// Input PDF path
         input: PathBuf,
 
@@ -95,18 +405,87 @@ enum Commands {
         /// Overwrite output if it exists
         #[arg(long)]
         force: bool,
+    },
+
+    /// Merge multiple PDFs into a single output PDF (qpdf, mutool, or native).
+    Merge {
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Rewrite the merged PDF with the highest PDF version of the
+        /// inputs, a rebuilt cross-reference table, and fonts and images
+        /// embedded identically by several inputs stored once
+        #[arg(long)]
+        normalize: bool,
+
+        /// Input PDFs (in order)
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+    },
+
+    /// Stamp Bates numbers on every page, numbering on from one file to the next (pure Rust).
+    ///
+    /// Each stamped file is written to --out-dir under its own name, and a CSV
+    /// manifest maps each file to its first and last number.
+    Bates {
+        /// Input PDFs (in production order)
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Directory to write the stamped files into (default: the configured
+        /// output directory)
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+
+        /// Text before the number, e.g. ABC
+        #[arg(long, default_value = "")]
+        prefix: String,
+
+        /// Number of the first page
+        #[arg(long, default_value_t = 1)]
+        start: u64,
+
+        /// Digits of the number, zero-padded
+        #[arg(long, default_value_t = 6)]
+        digits: usize,
+
+        /// Where the number goes on each page
+        #[arg(long, value_enum, default_value = "bottom-right")]
+        position: StampPositionCli,
 
-        /// Rotation degrees (90, 180, 270)
+        /// Font size in points
+        #[arg(long, default_value_t = 10.0)]
+        font_size: f32,
+
+        /// Distance from the edges of the page, e.g. 0.25in or 6mm
+        #[arg(long, value_parser = parse_length, default_value = "0.25in")]
+        margin: f32,
+
+        /// Where to write the CSV manifest (default: bates.csv in --out-dir)
+        #[arg(long, value_name = "PATH")]
+        manifest: Option<PathBuf>,
+
+        /// Write into a non-empty --out-dir, overwriting files
         #[arg(long)]
-        degrees: RotateDegrees,
+        force: bool,
 
-        /// Page range (currently only a single inclusive range like '1-3')
+        /// Print the number ranges as machine-readable JSON
         #[arg(long)]
-        pages: Option<String>,
+        json: bool,
     },
 
-    /// Compress/optimize a PDF (requires ghostscript).
-    Compress {
+    /// Sign a document with a certificate from a PKCS #12 file (requires
+    /// openssl).
+    ///
+    /// Writes an ETSI.CAdES.detached signature over the whole file.
+    /// Documents that are already signed are refused, since signing rewrites
+    /// the file.
+    Sign {
         /// Input PDF path
         input: PathBuf,
 
@@ -118,275 +497,7298 @@ enum Commands {
         #[arg(long)]
         force: bool,
 
-        /// Compression preset
-        #[arg(long, value_enum, default_value_t = CompressPresetCli::Default)]
-        preset: CompressPresetCli,
+        /// PKCS #12 file (.p12 or .pfx) with the certificate and private key
+        #[arg(long, value_name = "PATH")]
+        pkcs12: PathBuf,
+
+        /// Password of the PKCS #12 file
+        #[arg(long, default_value = "")]
+        password: String,
+
+        /// Show the signature on a page (see --page and --rect)
+        #[arg(long, requires = "rect")]
+        visible: bool,
+
+        /// Page of a visible signature (1-based)
+        #[arg(long, default_value_t = 1, requires = "visible")]
+        page: u32,
+
+        /// Rectangle of a visible signature, as x1,y1,x2,y2 in points
+        #[arg(long, value_parser = parse_rect, requires = "visible")]
+        rect: Option<pdfcore::Rect>,
+
+        /// Why the document is signed
+        #[arg(long)]
+        reason: Option<String>,
+
+        /// Where it is signed
+        #[arg(long)]
+        location: Option<String>,
+
+        /// Certify the document, allowing only these changes afterwards
+        #[arg(long, value_name = "CHANGES")]
+        certify: Option<CertificationCli>,
+
+        /// Timestamp the signature at this RFC 3161 timestamping authority
+        /// (requires curl)
+        #[arg(long, value_name = "URL")]
+        tsa_url: Option<String>,
     },
-}
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
-enum RotateDegrees {
-    #[value(name = "90")]
-    D90,
-    #[value(name = "180")]
-    D180,
-    #[value(name = "270")]
-    D270,
-}
+    /// Verify the digital signatures of a document and their timestamps
+    /// (requires openssl).
+    ///
+    /// Exits with an error if any signature or timestamp does not verify.
+    Signatures {
+        /// Input PDF path
+        input: PathBuf,
 
-impl RotateDegrees {
-    fn as_u16(self) -> u16 {
-        match self {
-            Self::D90 => 90,
-            Self::D180 => 180,
-            Self::D270 => 270,
-        }
-    }
-}
+        /// PEM file with trusted root certificates; without it only the
+        /// integrity of signatures and timestamps is checked
+        #[arg(long, value_name = "PATH")]
+        ca_file: Option<PathBuf>,
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
-enum CompressPresetCli {
-    Screen,
-    Ebook,
-    Printer,
-    Prepress,
-    Default,
-}
+        /// Print the signatures as machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
 
-impl From<CompressPresetCli> for pdfcore::CompressPreset {
-    fn from(value: CompressPresetCli) -> Self {
-        match value {
-            CompressPresetCli::Screen => Self::Screen,
-            CompressPresetCli::Ebook => Self::Ebook,
-            CompressPresetCli::Printer => Self::Printer,
-            CompressPresetCli::Prepress => Self::Prepress,
-            CompressPresetCli::Default => Self::Default,
-        }
-    }
-}
+    /// Place a signature image on a page, with the date below it (pure Rust).
+    ///
+    /// This is a visible mark only; the document is not signed
+    /// cryptographically.
+    SignVisual {
+        /// Input PDF path
+        input: PathBuf,
 
-fn main() {
-    let cli = Cli::parse();
-    let code = match run(cli) {
-        Ok(()) => 0,
-        Err(e) => {
-            eprintln!("error: {e:#}");
-            1
-        }
-    };
-    process::exit(code);
-}
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
 
-fn run(cli: Cli) -> anyhow::Result<()> {
-    match cli.command {
-        Commands::Info { input, json } => cmd_info(&input, json),
-        Commands::Merge {
-            output,
-            force,
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// The signature image (JPEG or PNG)
+        #[arg(long)]
+        image: PathBuf,
+
+        /// The page to sign (1-based)
+        #[arg(long, default_value_t = 1)]
+        page: u32,
+
+        /// Bottom left corner of the image, as x,y from the bottom left of
+        /// the page as shown, e.g. 400,120 or 14cm,4cm
+        #[arg(long, value_parser = parse_point)]
+        at: (f32, f32),
+
+        /// Width of the image on the page, e.g. 150 or 5cm (its height
+        /// keeps the aspect ratio)
+        #[arg(long, value_parser = parse_length, default_value = "2in")]
+        width: f32,
+
+        /// Write today's date (UTC) below the image in this format, e.g.
+        /// '%Y-%m-%d' or '%e %B %Y'
+        #[arg(long, value_name = "FORMAT")]
+        date_format: Option<String>,
+
+        /// Font size of the date in points
+        #[arg(long, default_value_t = 10.0)]
+        font_size: f32,
+    },
+
+    /// Stamp headers and footers on every page (pure Rust).
+    ///
+    /// Each text is a template: {n} and {total} are the page number and page
+    /// count, {title}, {author}, {subject}, and {keywords} come from the
+    /// document's metadata, {file} is the input's file name, and {date} is
+    /// --date. Write {{ and }} for literal braces.
+    HeaderFooter {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Text at the top left of each page, e.g. '{title}'
+        #[arg(long, value_name = "TEMPLATE")]
+        header_left: Option<String>,
+
+        /// Text at the top center of each page
+        #[arg(long, value_name = "TEMPLATE")]
+        header_center: Option<String>,
+
+        /// Text at the top right of each page
+        #[arg(long, value_name = "TEMPLATE")]
+        header_right: Option<String>,
+
+        /// Text at the bottom left of each page
+        #[arg(long, value_name = "TEMPLATE")]
+        footer_left: Option<String>,
+
+        /// Text at the bottom center of each page, e.g. 'Page {n} of {total}'
+        #[arg(long, value_name = "TEMPLATE")]
+        footer_center: Option<String>,
+
+        /// Text at the bottom right of each page
+        #[arg(long, value_name = "TEMPLATE")]
+        footer_right: Option<String>,
+
+        /// The text of {date} (default: today's date, YYYY-MM-DD, in UTC)
+        #[arg(long)]
+        date: Option<String>,
+
+        /// Font size in points
+        #[arg(long, default_value_t = 9.0)]
+        font_size: f32,
+
+        /// Distance from the edges of the page, e.g. 0.25in or 6mm
+        #[arg(long, value_parser = parse_length, default_value = "0.25in")]
+        margin: f32,
+    },
+
+    /// Choose and reorder pages in an interactive terminal list with text
+    /// previews, then write them to a new PDF (qpdf, mutool, or native).
+    #[command(
+        after_help = "KEYS:\n  up/down, j/k          move\n  page up/down, g/G     move by a screen, to the first/last page\n  space                 mark or unmark the page\n  shift+up/down, K/J    move the page up or down the list\n  a                     mark every page, or none\n  enter                 write the marked pages in the order shown\n  q, esc                quit without writing"
+    )]
+    Pick {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Split a PDF into one PDF per page (requires qpdf), into files named
+    /// from their text (--name-from-text), at separator sheets
+    /// (--separator), or into parts under a size limit (--max-bytes).
+    #[command(visible_alias = "split")]
+    SplitPages {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Directory to write pages into (default: the configured output directory)
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+
+        /// Output pattern, must contain %d (page number, 1-based); with
+        /// --name-from-text, a regular expression matched against each
+        /// page's text
+        #[arg(long)]
+        pattern: Option<String>,
+
+        /// Start a new file at each page whose text matches --pattern, named
+        /// from the first capture group (or the whole match); pages that do
+        /// not match stay with the file before them
+        #[arg(long, requires = "pattern")]
+        name_from_text: bool,
+
+        /// Split a scanned batch at separator sheets, which are left out: blank
+        /// pages, or pages with a QR code whose text names the file after it
+        /// (pages are rendered with ghostscript, mutool, or pdfium)
+        #[arg(long, value_enum, conflicts_with_all = ["pattern", "name_from_text"])]
+        separator: Option<SeparatorCli>,
+
+        /// Split into parts of consecutive pages no larger than this (e.g.
+        /// `9MB`, for email attachment limits), named <name>.part1.pdf, ...
+        #[arg(
+            long,
+            value_name = "SIZE",
+            value_parser = parse_byte_size,
+            conflicts_with_all = ["pattern", "name_from_text", "separator"]
+        )]
+        max_bytes: Option<u64>,
+
+        /// Overwrite existing files (best-effort; may still fail if tool refuses)
+        #[arg(long)]
+        force: bool,
+
+        /// Also write a JSON manifest of the output files with their source,
+        /// pages, size, and SHA-256
+        #[arg(long, value_name = "PATH")]
+        manifest: Option<PathBuf>,
+    },
+
+    /// Render pages to PNG images named page-<n>.png (ghostscript, mutool, or pdfium).
+    Render {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Directory to write images into (default: the configured output directory)
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+
+        /// Resolution in dots per inch
+        #[arg(long, default_value_t = 150)]
+        dpi: u32,
+
+        /// Write into a non-empty directory
+        #[arg(long)]
+        force: bool,
+
+        /// Reuse images rendered before from an unchanged file, kept in this directory
+        #[arg(long)]
+        cache_dir: Option<PathBuf>,
+
+        /// Also write a JSON manifest of the output files with their source,
+        /// pages, size, and SHA-256
+        #[arg(long, value_name = "PATH")]
+        manifest: Option<PathBuf>,
+    },
+
+    /// Render the first page of each PDF to <name>.png, e.g. for cover images
+    /// (ghostscript, mutool, or pdfium).
+    Thumbnail {
+        /// PDF paths or glob patterns
+        #[arg(required = true)]
+        inputs: Vec<String>,
+
+        /// Directory to write images into (default: the configured output directory)
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+
+        /// Resolution in dots per inch (36 makes a US Letter page 306x396 pixels)
+        #[arg(long, default_value_t = 36)]
+        dpi: u32,
+
+        /// Reuse thumbnails rendered before from unchanged files, kept in this directory
+        #[arg(long)]
+        cache_dir: Option<PathBuf>,
+
+        /// Overwrite existing images
+        #[arg(long)]
+        force: bool,
+
+        /// Also write a JSON manifest of the output files with their source,
+        /// pages, size, and SHA-256
+        #[arg(long, value_name = "PATH")]
+        manifest: Option<PathBuf>,
+    },
+
+    /// Export one page as an SVG file, e.g. to edit a figure (pdftocairo or
+    /// mutool).
+    ToSvg {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Page to export (1-based)
+        #[arg(long)]
+        page: u32,
+
+        /// Output SVG path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Convert a PDF to PostScript, or one page to EPS (requires ghostscript).
+    ToPs {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PostScript (or EPS) path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Page range: an inclusive range like '1-3', a single page like '4',
+        /// or an open range like '5-'
+        #[arg(long)]
+        pages: Option<String>,
+
+        /// Write Encapsulated PostScript (a single page)
+        #[arg(long)]
+        eps: bool,
+    },
+
+    /// Convert a PostScript or EPS file to PDF, like ps2pdf (requires
+    /// ghostscript).
+    FromPs {
+        /// Input PostScript (or EPS) path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Compression preset (default: the configured preset, else `default`)
+        #[arg(long, value_enum)]
+        preset: Option<CompressPresetCli>,
+    },
+
+    /// Convert a (multi-page) TIFF file to PDF, one page per image.
+    FromTiff {
+        /// Input TIFF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// List the attachments of an email message (.eml or .msg), or write
+    /// its PDF attachments.
+    FromEml {
+        /// Input message path (.eml or .msg)
+        input: PathBuf,
+
+        /// Write the PDF attachments rather than list the attachments
+        #[arg(long)]
+        extract_pdfs: bool,
+
+        /// Directory to write PDFs into (default: the configured output directory)
+        #[arg(long, requires = "extract_pdfs", conflicts_with = "merge")]
+        out_dir: Option<PathBuf>,
+
+        /// Merge the PDF attachments, in message order, into this PDF instead
+        #[arg(long, value_name = "OUTPUT", requires = "extract_pdfs")]
+        merge: Option<PathBuf>,
+
+        /// Overwrite existing files
+        #[arg(long)]
+        force: bool,
+
+        /// Print the list of attachments as JSON
+        #[arg(long, conflicts_with = "extract_pdfs")]
+        json: bool,
+    },
+
+    /// Convert a PDF to a multi-page TIFF file (requires ghostscript).
+    ToTiff {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output TIFF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Resolution in dots per inch
+        #[arg(long, default_value_t = 300)]
+        dpi: u32,
+
+        /// Page compression
+        #[arg(long, value_enum, default_value_t = TiffCompressionCli::Lzw)]
+        compression: TiffCompressionCli,
+    },
+
+    /// Send a PDF to a printer (lp or lpr on Unix, `SumatraPDF` on Windows).
+    Print {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Printer to print on (default: the system's default printer)
+        #[arg(long)]
+        printer: Option<String>,
+
+        /// Number of copies
+        #[arg(long, default_value_t = 1)]
+        copies: u32,
+
+        /// Page range: an inclusive range like '1-3', a single page like '4',
+        /// or an open range like '5-'
+        #[arg(long)]
+        pages: Option<String>,
+
+        /// Print on both sides of the paper
+        #[arg(long)]
+        duplex: bool,
+    },
+
+    /// Extract text from a PDF (pdftotext, mutool, pdfium, or native).
+    ExtractText {
+        /// Input PDF path, or with --format, PDF paths or glob patterns
+        #[arg(required = true)]
+        inputs: Vec<String>,
+
+        /// Output text file path
+        #[arg(short, long, conflicts_with = "stdout")]
+        output: Option<PathBuf>,
+
+        /// Write extracted text to stdout
+        #[arg(long)]
+        stdout: bool,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Write records (path, page, text) instead of plain text, to stdout
+        /// unless -o is given
+        #[arg(long, value_enum)]
+        format: Option<RecordFormat>,
+
+        /// One record per page or per file
+        #[arg(long, value_enum, default_value = "page", requires = "format")]
+        per: TextRecords,
+
+        /// Character encoding of the output: utf-8 (the default), utf-16le,
+        /// utf-16be, latin1, or ascii
+        #[arg(long, visible_alias = "enc")]
+        encoding: Option<pdfcore::TextEncoding>,
+
+        /// Start the output with a byte order mark (UTF-8 and UTF-16)
+        #[arg(long)]
+        bom: bool,
+
+        /// Convert line endings (default: as extracted)
+        #[arg(long, value_enum)]
+        eol: Option<EolCli>,
+    },
+
+    /// Extract ruled tables into one CSV or JSON file per table (pure Rust).
+    ExtractTables {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Directory to write tables into (default: the configured output directory)
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+
+        /// File format of each table
+        #[arg(long, value_enum, default_value = "csv")]
+        format: TableFormat,
+
+        /// Write into a non-empty directory
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Set a plain text or Markdown file on new PDF pages (pure Rust).
+    ///
+    /// Plain text is set line by line in Courier, with a new page at each form
+    /// feed; Markdown headings, emphasis, code, lists, and quotes in Helvetica.
+    FromText {
+        /// Input text file (.md and .markdown files are read as Markdown)
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Size of body text in points
+        #[arg(long, default_value_t = 11.0)]
+        font_size: f32,
+
+        /// Paper size (a3, a4, a5, letter, legal, tabloid)
+        #[arg(long, default_value = "a4", value_parser = parse_paper_size)]
+        paper: pdfcore::PaperSize,
+
+        /// Margin on every side (e.g. 20mm, 1in, 72pt)
+        #[arg(long, value_parser = parse_length, default_value = "1in")]
+        margin: f32,
+
+        /// How the input is marked up (default: from its extension)
+        #[arg(long, value_enum)]
+        format: Option<TextFormatCli>,
+    },
+
+    /// Convert an HTML file to PDF (wkhtmltopdf, or headless Chromium/Chrome).
+    ///
+    /// Images, style sheets, and scripts the page refers to are loaded as a
+    /// browser would load them.
+    FromHtml {
+        /// Input HTML file
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Paper size (a3, a4, a5, letter, legal, tabloid)
+        #[arg(long, default_value = "a4", value_parser = parse_paper_size)]
+        paper: pdfcore::PaperSize,
+
+        /// Margin on every side (e.g. 20mm, 1in, 72pt)
+        #[arg(long, value_parser = parse_length, default_value = "0.5in")]
+        margin: f32,
+    },
+
+    /// Convert an office document to PDF (requires soffice).
+    ///
+    /// Reads any format soffice opens, such as .docx, .xlsx, .pptx, .odt, and
+    /// .rtf files.
+    Convert {
+        /// Input document
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Rotate pages in a PDF (qpdf, or native).
+    Rotate {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Rotation degrees (90, 180, 270), or `auto` to turn each page so
+        /// its text is upright
+        #[arg(long, required_unless_present = "spec")]
+        degrees: Option<RotateDegrees>,
+
+        /// Page range: an inclusive range like '1-3', a single page like '4',
+        /// or an open range like '5-'
+        #[arg(long, conflicts_with = "spec")]
+        pages: Option<String>,
+
+        /// Different rotations for different pages, as comma-separated
+        /// <pages>:<degrees> pairs (e.g. '1-3:90,4:180,5-:270')
+        #[arg(long, conflicts_with = "degrees")]
+        spec: Option<String>,
+    },
+
+    /// Compress/optimize a PDF (requires ghostscript).
+    Compress {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Compression preset (default: the configured preset, else `default`)
+        #[arg(long, value_enum)]
+        preset: Option<CompressPresetCli>,
+
+        /// Print the size report as machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Shrink a PDF by rewriting its objects, without changing how pages
+    /// look (pure Rust; --gc with qpdf or native).
+    Optimize {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Store fonts and images with identical contents once, e.g. in a
+        /// file merged from many documents made by one generator
+        #[arg(long, required_unless_present = "gc")]
+        dedupe: bool,
+
+        /// Drop objects that nothing in the document refers to any more,
+        /// e.g. after pages were removed by a tool that kept their fonts
+        /// and images (--engine native works without qpdf)
+        #[arg(long)]
+        gc: bool,
+
+        /// Print the report as machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Downsample and re-encode the images of a PDF as JPEG, leaving the
+    /// rest of the file as it is (pure Rust).
+    RecompressImages {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Downsample images drawn at a higher resolution than this (DPI)
+        #[arg(long)]
+        max_dpi: Option<f32>,
+
+        /// JPEG quality (1-100)
+        #[arg(long, default_value_t = 75)]
+        jpeg_quality: u8,
+
+        /// Leave black and white (1-bit) images as they are
+        #[arg(long)]
+        skip_monochrome: bool,
+
+        /// Print the report as machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Convert JPEG 2000 and JBIG2 images, which some viewers cannot show,
+    /// to JPEG or Flate (requires mutool or ghostscript).
+    TranscodeImages {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Codecs to convert from (comma-separated)
+        #[arg(long, value_enum, value_delimiter = ',', default_value = "jpx,jbig2")]
+        from: Vec<ImageCodecCli>,
+
+        /// Codec to convert to
+        #[arg(long, value_enum, default_value = "jpeg")]
+        to: TranscodeTargetCli,
+
+        /// JPEG quality (1-100)
+        #[arg(long, default_value_t = 85)]
+        jpeg_quality: u8,
+
+        /// Print the report as machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Run several operations in sequence, without intermediate files to manage.
+    #[command(
+        after_help = "STEPS:\n  decrypt [--password <password>]\n  rotate --degrees <0|90|180|270> [--pages <start-end>]\n  compress [--preset <screen|ebook|printer|prepress|default>]\n  convert-color --to <gray|cmyk|rgb>\n  flatten\n  linearize\n  print-ready [--paper <size>] [--bleed <points>] [--cmyk true] [--crop-marks true]\n              [--registration-marks true] [--keep-transparency true]\n\nEXAMPLE:\n  pdfcli run 'decrypt | rotate --degrees 90 | compress --preset ebook' in.pdf -o out.pdf"
+    )]
+    Run {
+        /// Steps separated by '|', each a command with its options
+        steps: String,
+
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Run steps on every PDF that arrives in a directory, until Ctrl-C.
+    #[command(
+        after_help = "Steps are given as for `run`. Processed inputs are recorded in a ledger \
+                      (default: <out-dir>/.pdfcli-watch-ledger) and skipped after a restart \
+                      unless they change.\n\nEXAMPLE:\n  pdfcli watch ./inbox --do 'compress --preset ebook' --out-dir ./outbox"
+    )]
+    Watch {
+        /// Directory to watch
+        inbox: PathBuf,
+
+        /// Steps to run on each PDF, separated by '|' (see `pdfcli run --help`)
+        #[arg(long = "do", value_name = "STEPS")]
+        steps: String,
+
+        /// Directory for the results (default: the configured output directory)
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+
+        /// How long a file must be unchanged before it is processed
+        #[arg(long, value_name = "DURATION", default_value = "2s", value_parser = parse_duration)]
+        debounce: Duration,
+
+        /// Ledger of processed files
+        #[arg(long, value_name = "PATH")]
+        ledger: Option<PathBuf>,
+    },
+
+    /// Serve the operations over HTTP, until Ctrl-C.
+    #[command(
+        after_help = "ROUTES:\n  POST /info                    document information as JSON\n  POST /extract-text            the text\n  POST /run?steps=<steps>       run steps as for `run`; returns the PDF\n  POST /<step>?<option>=<value> one step (rotate, compress, convert-color, flatten, linearize)\n  GET  /health                  ok\n\nUpload the PDF as the request body or as the `file` field of a multipart form. Each request \
+works in its own temp directory. Failures are JSON errors as with --json-errors. There is no \
+authentication: listen on a public address only behind a proxy that provides it.\n\nEXAMPLE:\n  pdfcli --sandbox --timeout 60s serve --listen 127.0.0.1:8080\n  curl -F file=@in.pdf 'http://127.0.0.1:8080/rotate?degrees=90' -o out.pdf"
+    )]
+    Serve {
+        /// Address to listen on
+        #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+        listen: String,
+
+        /// Largest accepted upload
+        #[arg(long, value_name = "SIZE", default_value = "100MB", value_parser = parse_byte_size)]
+        max_size: u64,
+
+        /// Requests processed at once (default: the configured jobs, else the
+        /// number of CPUs)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+    },
+
+    /// Convert page content to grayscale, CMYK, or RGB (requires ghostscript).
+    ConvertColor {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Target color space
+        #[arg(long, value_enum)]
+        to: ColorSpaceCli,
+    },
+
+    /// Prepare a PDF for electronic court filing (requires qpdf and ghostscript).
+    ///
+    /// Flattens forms/annotations, removes JavaScript, embeds fonts, linearizes,
+    /// and compresses until the output fits --max-size.
+    CourtReady {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Maximum output size (e.g. 25MB, 500KB)
+        #[arg(long, value_parser = parse_byte_size)]
+        max_size: Option<u64>,
+
+        /// Highest-quality preset to try before stepping down to fit --max-size
+        #[arg(long, value_enum, default_value_t = CompressPresetCli::Printer)]
+        preset: CompressPresetCli,
+
+        /// Print the compliance report as machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Normalize a PDF for a print shop and preflight the result (requires ghostscript).
+    ///
+    /// Scales pages to the paper size, embeds fonts, flattens transparency,
+    /// optionally converts to CMYK, and adds bleed and crop marks.
+    PrintReady {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Finished (trim) paper size (a3, a4, a5, letter, legal, tabloid)
+        #[arg(long, default_value = "a4", value_parser = parse_paper_size)]
+        paper: pdfcore::PaperSize,
+
+        /// Bleed added on every side (e.g. 3mm, 0.125in, 9pt)
+        #[arg(long, value_parser = parse_length, default_value = "0")]
+        bleed: f32,
+
+        /// Convert all content to CMYK
+        #[arg(long)]
+        cmyk: bool,
+
+        /// Draw crop marks outside the bleed area
+        #[arg(long)]
+        crop_marks: bool,
+
+        /// Draw registration marks outside the bleed area
+        #[arg(long)]
+        registration_marks: bool,
+
+        /// Keep live transparency instead of flattening it
+        #[arg(long)]
+        keep_transparency: bool,
+
+        /// Print the preflight report as machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Add bleed and printer's marks around each page (pure Rust).
+    ///
+    /// The page's trim box (or, if it has none, its crop box) is the finished
+    /// size. The bleed box is set --bleed larger on every side, and the page is
+    /// enlarged to hold the bleed and the marks. Content is not scaled.
+    Bleed {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Bleed added on every side (e.g. 3mm, 0.125in, 9pt)
+        #[arg(long, value_parser = parse_length)]
+        bleed: f32,
+
+        /// Draw crop marks outside the bleed area
+        #[arg(long)]
+        crop_marks: bool,
+
+        /// Draw registration marks outside the bleed area
+        #[arg(long)]
+        registration_marks: bool,
+    },
+
+    /// Convert a PDF to PDF/X for print delivery (requires ghostscript).
+    ///
+    /// Embeds fonts, converts colors to CMYK (flattening transparency for
+    /// PDF/X-1a), adds the output intent with the printer's ICC profile, and
+    /// runs a preflight.
+    ToPdfx {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// PDF/X standard
+        #[arg(long, value_enum, default_value = "x1a")]
+        standard: PdfxStandardCli,
+
+        /// CMYK ICC profile of the printing condition, from the print vendor
+        #[arg(long, value_name = "PROFILE")]
+        icc: PathBuf,
+
+        /// Name of the printing condition, e.g. FOGRA39 (default: the
+        /// profile's description)
+        #[arg(long)]
+        condition: Option<String>,
+
+        /// Print the preflight report as machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Prepare a PDF for accessibility remediation.
+    ///
+    /// OCRs scanned pages (requires ocrmypdf), sets language and title, tags
+    /// headings/paragraphs, and reports issues that still need manual work.
+    A11yPrep {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Document language (BCP 47, e.g. en-US)
+        #[arg(long)]
+        lang: Option<String>,
+
+        /// Document title
+        #[arg(long)]
+        title: Option<String>,
+
+        /// Do not OCR scanned pages
+        #[arg(long)]
+        no_ocr: bool,
+
+        /// OCR language(s) in Tesseract form (e.g. eng, deu+eng)
+        #[arg(long)]
+        ocr_lang: Option<String>,
+
+        /// Print the report as machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Set the document language and reading direction (pure Rust).
+    ///
+    /// Screen readers choose their voice from the language, and PDF/UA requires it.
+    SetLang {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Document language (BCP 47, e.g. de-DE)
+        #[arg(long)]
+        lang: String,
+
+        /// Reading direction, e.g. r2l for Arabic or Hebrew
+        #[arg(long, value_enum)]
+        direction: Option<DirectionCli>,
+    },
+
+    /// Guess the title of a document from the largest text on page 1, and
+    /// optionally write it into the metadata (pure Rust).
+    ///
+    /// The title is printed on stdout. With --apply it is set as the
+    /// document info Title and in the XMP metadata, if the document has
+    /// any; a title already there is only replaced with --replace.
+    InferTitle {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Write the title into the document's metadata
+        #[arg(long, requires = "output")]
+        apply: bool,
+
+        /// Output PDF path for --apply
+        #[arg(short, long, requires = "apply")]
+        output: Option<PathBuf>,
+
+        /// Replace the document's title if it has one
+        #[arg(long, requires = "apply")]
+        replace: bool,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Output machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Set how viewers open a PDF: side panel, page layout, start page and zoom (pure Rust).
+    ///
+    /// Only the settings given are changed. Viewers may ignore some of them,
+    /// browsers in particular.
+    ViewerPrefs {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Panel shown next to the pages
+        #[arg(long, value_enum)]
+        page_mode: Option<PageModeCli>,
+
+        /// How pages are laid out (two-up shows spreads; -cover shows page 1 alone)
+        #[arg(long, value_enum)]
+        page_layout: Option<PageLayoutCli>,
+
+        /// Page to open at (1-based)
+        #[arg(long, value_name = "PAGE", value_parser = clap::value_parser!(u32).range(1..))]
+        open_at: Option<u32>,
+
+        /// Zoom to open at (at page 1 unless --open-at is given)
+        #[arg(long, value_enum)]
+        fit: Option<FitCli>,
+
+        /// Hide the viewer's toolbars
+        #[arg(long)]
+        hide_toolbar: bool,
+
+        /// Hide the viewer's menu bar
+        #[arg(long)]
+        hide_menubar: bool,
+
+        /// Resize the viewer window to the first page
+        #[arg(long)]
+        fit_window: bool,
+
+        /// Center the viewer window on the screen
+        #[arg(long)]
+        center_window: bool,
+    },
+
+    /// Set the page and zoom a PDF opens at, or remove its open action (pure Rust).
+    ///
+    /// Open actions can also run JavaScript; --remove prints what was removed.
+    OpenAction {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Page to open at (1-based)
+        #[arg(
+            long,
+            value_parser = clap::value_parser!(u32).range(1..),
+            required_unless_present_any = ["dest", "remove"],
+            conflicts_with_all = ["dest", "remove"]
+        )]
+        page: Option<u32>,
+
+        /// Zoom: fit, width, height, or a percentage such as 125%
+        /// (default: the viewer's)
+        #[arg(long, value_parser = parse_zoom, requires = "page")]
+        zoom: Option<pdfcore::OpenFit>,
+
+        /// Named destination to open at (see dests list)
+        #[arg(long, conflicts_with = "remove")]
+        dest: Option<String>,
+
+        /// Remove the open action
+        #[arg(long)]
+        remove: bool,
+    },
+
+    /// Check a PDF for common accessibility problems and score it from 0 to 100 (pure Rust).
+    ///
+    /// Checks for a structure tree, the marked flag, language, title, alternative
+    /// text on figures, and tab order on pages with annotations. Passing does not
+    /// make a document accessible, but failing shows what a11y-prep or manual
+    /// remediation has to fix.
+    A11yCheck {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Print the report as machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Remove JavaScript, launch actions, embedded files, and external references (pure Rust).
+    ///
+    /// With --dry-run, only reports what would be removed.
+    Sanitize {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path (required unless --dry-run is given)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Print the report as machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Remove text and image content under rectangles or matching a search (pure Rust).
+    ///
+    /// Glyphs are deleted from the content streams and image pixels are
+    /// overwritten before black boxes are drawn. Pages with content that
+    /// cannot be edited in place are rasterized (requires ghostscript).
+    Redact {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Page the --rect regions are on (1-based)
+        #[arg(long, requires = "rect")]
+        page: Option<u32>,
+
+        /// Region to redact as x1,y1,x2,y2 in points from the bottom left (repeatable)
+        #[arg(long, value_parser = parse_rect, requires = "page", required_unless_present = "text")]
+        rect: Vec<pdfcore::Rect>,
+
+        /// Text to redact on every page (repeatable)
+        #[arg(long)]
+        text: Vec<String>,
+
+        /// Treat --text values as regular expressions
+        #[arg(long, requires = "text")]
+        regex: bool,
+
+        /// Print the report as machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Compare document information and apply reviewed changes (pure Rust).
+    Meta {
+        #[command(subcommand)]
+        action: MetaCommand,
+    },
+
+    /// Remove identifying metadata before publishing (pure Rust).
+    ///
+    /// Removes the document information dictionary, XMP packets, and the
+    /// document ID; --annotation-authors also removes the author of comments.
+    StripMetadata {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Document information entry to keep, e.g. Title (repeatable)
+        #[arg(long, value_name = "KEY")]
+        keep: Vec<String>,
+
+        /// Also remove the author of comments and other annotations
+        #[arg(long)]
+        annotation_authors: bool,
+
+        /// Print the report as machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Set a new document ID (pure Rust), which some signing tools require.
+    RegenId {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Derive the ID from a hash of the content, so the same content
+        /// always gets the same ID
+        #[arg(long)]
+        from_content_hash: bool,
+    },
+
+    /// Check PDFs against a policy file and list non-conforming ones (pure Rust).
+    ///
+    /// The TOML policy combines [preflight], [security], and [metadata] rules.
+    /// Patterns are expanded by pdfcli, so quote them to cover subdirectories
+    /// (e.g. 'incoming/**/*.pdf').
+    Enforce {
+        /// Policy file (TOML)
+        #[arg(long)]
+        policy: PathBuf,
+
+        /// PDF paths or glob patterns
+        #[arg(required = true)]
+        patterns: Vec<String>,
+
+        /// Move non-conforming files into this directory
+        #[arg(long)]
+        quarantine: Option<PathBuf>,
+
+        /// Files to check at once (default: the configured jobs, else the
+        /// number of CPUs)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+
+        /// Print the results as machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Report which external tools are installed and which commands they enable.
+    Doctor {
+        /// Fail unless these tools are found (comma-separated, e.g. qpdf,gs)
+        #[arg(long, value_delimiter = ',')]
+        require: Vec<String>,
+
+        /// Print the report as machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show or change the configuration file.
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+
+    /// Print a shell completion script.
+    Completions {
+        /// Shell to generate completions for
+        shell: clap_complete::Shell,
+    },
+
+    /// Print the man page (roff), or write one page per command.
+    Manpage {
+        /// Write pdfcli.1 and pdfcli-<command>.1 into this directory
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// Print the effective configuration (file, then environment, then flags).
+    Show,
+
+    /// Set a key in the configuration file (an empty value removes it).
+    #[command(
+        after_help = "KEYS:\n  defaults.compress_preset  screen, ebook, printer, prepress, or default\n  defaults.output_dir       directory for split-pages and render\n  defaults.jobs             files enforce checks at once\n  defaults.engines          engines to prefer (comma-separated)\n  tools.<tool>              path to qpdf, pdftotext, ghostscript, ocrmypdf, mutool, wkhtmltopdf, chrome, soffice, openssl, curl, pdftocairo, lp, or sumatrapdf"
+    )]
+    Set {
+        /// Setting, e.g. `defaults.compress_preset`
+        key: String,
+
+        /// New value, e.g. ebook
+        value: String,
+    },
+}
+
+/// The tool `print` sends jobs with.
+const PRINT_TOOL: &str = if cfg!(windows) { "sumatrapdf" } else { "lp" };
+
+/// External tools each subcommand needs, and tools it can do without (with
+/// reduced functionality). Commands served by an engine (see
+/// `pdfcore::Operation`) and commands not listed are not included.
+const COMMAND_TOOLS: &[(&str, &[&str], &[&str])] = &[
+    ("qdf", &[], &["qpdf"]),
+    ("permissions", &[], &["qpdf"]),
+    ("embed-fonts", &["ghostscript"], &[]),
+    ("convert", &["soffice"], &[]),
+    ("sign", &["openssl"], &["curl"]),
+    ("signatures", &["openssl"], &[]),
+    ("split-pages", &["qpdf"], &[]),
+    ("compress", &["ghostscript"], &[]),
+    ("convert-color", &["ghostscript"], &[]),
+    ("court-ready", &["qpdf", "ghostscript"], &[]),
+    ("print-ready", &["ghostscript"], &[]),
+    ("ink-coverage", &["ghostscript"], &[]),
+    ("to-pdfx", &["ghostscript"], &[]),
+    ("to-ps", &["ghostscript"], &[]),
+    ("from-ps", &["ghostscript"], &[]),
+    ("to-tiff", &["ghostscript"], &[]),
+    ("print", &[PRINT_TOOL], &[]),
+    ("a11y-prep", &[], &["ocrmypdf"]),
+    ("redact", &[], &["ghostscript"]),
+];
+
+#[derive(Debug, Subcommand)]
+enum LinksCommand {
+    /// Rewrite URI links, e.g. to move them to a new domain.
+    Rewrite {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// OLD=NEW host (or URI prefix) mapping; may be repeated
+        #[arg(long = "map", required = true, value_parser = parse_mapping)]
+        mappings: Vec<(String, String)>,
+    },
+
+    /// Remove link annotations.
+    Strip {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Only remove links leading outside the document (URIs, files, launch actions)
+        #[arg(long)]
+        external_only: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum RevisionsCommand {
+    /// Write an earlier revision, byte for byte as it was saved.
+    Extract {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Revision to extract: 0 is the original document
+        #[arg(long)]
+        rev: u32,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum BoxesCommand {
+    /// Show the boxes of every page, marking defaulted ones and boxes that
+    /// extend outside the box that should contain them.
+    Show {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum PermissionsCommand {
+    /// Encrypt with AES-256 and the given permissions, replacing any encryption.
+    ///
+    /// Every permission not turned off with a --no-* flag is granted. Viewers
+    /// honor the permissions; the owner password lifts them.
+    Set {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Password that lifts the restrictions
+        #[arg(long)]
+        owner_password: String,
+
+        /// Password needed to open the output (default: none)
+        #[arg(long, default_value = "")]
+        user_password: String,
+
+        /// Password of the input, if it is encrypted
+        #[arg(long)]
+        password: Option<String>,
+
+        /// Do not allow printing
+        #[arg(long)]
+        no_print: bool,
+
+        /// Allow printing only at low resolution
+        #[arg(long)]
+        no_high_quality_print: bool,
+
+        /// Do not allow copying text and images
+        #[arg(long)]
+        no_copy: bool,
+
+        /// Do not allow changing the content
+        #[arg(long)]
+        no_modify: bool,
+
+        /// Do not allow adding or changing annotations
+        #[arg(long)]
+        no_annotate: bool,
+
+        /// Do not allow filling in form fields
+        #[arg(long)]
+        no_fill_forms: bool,
+
+        /// Do not allow inserting, deleting, or rotating pages
+        #[arg(long)]
+        no_assemble: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum DestsCommand {
+    /// List named destinations with their target pages.
+    List {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Add a named destination showing a whole page.
+    Add {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+
+        /// Destination name, as used in `file.pdf#<name>` links
+        #[arg(long)]
+        name: String,
+
+        /// Target page (1-based)
+        #[arg(long)]
+        page: u32,
+
+        /// Change the destination if the name already exists
+        #[arg(long)]
+        replace: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum XfaCommand {
+    /// Write the form's XML (all XFA packets, in order).
+    Dump {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// Output XML path (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum MetaCommand {
+    /// Show how the document information of B differs from that of A; with
+    /// --json, print it as a patch for `meta apply`.
+    Diff {
+        /// PDF to compare from
+        a: PathBuf,
+
+        /// PDF to compare to
+        b: PathBuf,
+
+        /// Print the differences as a JSON patch
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Apply a JSON patch, e.g. from `meta diff --json`, to the document
+    /// information.
+    Apply {
+        /// Input PDF path
+        input: PathBuf,
+
+        /// JSON patch with "set" (entries and their new values) and "remove"
+        /// (entries to remove)
+        #[arg(long, value_name = "PATH")]
+        patch: PathBuf,
+
+        /// Output PDF path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Overwrite output if it exists
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum InfoFormat {
+    /// Aligned columns with human-readable sizes
+    Table,
+    /// Comma-separated values with a header row; sizes in bytes
+    Csv,
+    /// One JSON object per line; sizes in bytes
+    Jsonl,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum RecordFormat {
+    /// Comma-separated values with a header row
+    Csv,
+    /// One JSON object per line
+    Jsonl,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum TextRecords {
+    Page,
+    File,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum TableFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum RotateDegrees {
+    #[value(name = "90")]
+    D90,
+    #[value(name = "180")]
+    D180,
+    #[value(name = "270")]
+    D270,
+    /// Detect each page's orientation from its text (native).
+    Auto,
+}
+
+impl RotateDegrees {
+    /// The fixed rotation, or `None` for `auto`.
+    fn as_u16(self) -> Option<u16> {
+        match self {
+            Self::D90 => Some(90),
+            Self::D180 => Some(180),
+            Self::D270 => Some(270),
+            Self::Auto => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum CompressPresetCli {
+    Screen,
+    Ebook,
+    Printer,
+    Prepress,
+    Default,
+}
+
+impl From<CompressPresetCli> for pdfcore::CompressPreset {
+    fn from(value: CompressPresetCli) -> Self {
+        match value {
+            CompressPresetCli::Screen => Self::Screen,
+            CompressPresetCli::Ebook => Self::Ebook,
+            CompressPresetCli::Printer => Self::Printer,
+            CompressPresetCli::Prepress => Self::Prepress,
+            CompressPresetCli::Default => Self::Default,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SeparatorCli {
+    /// Blank pages
+    Blank,
+    /// Pages with a QR code
+    Qr,
+}
+
+impl From<SeparatorCli> for pdfcore::Separator {
+    fn from(value: SeparatorCli) -> Self {
+        match value {
+            SeparatorCli::Blank => Self::Blank,
+            SeparatorCli::Qr => Self::Qr,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum TextFormatCli {
+    /// Plain text, laid out as it is
+    Text,
+    /// Markdown
+    Markdown,
+}
+
+impl From<TextFormatCli> for pdfcore::TextFormat {
+    fn from(value: TextFormatCli) -> Self {
+        match value {
+            TextFormatCli::Text => Self::Plain,
+            TextFormatCli::Markdown => Self::Markdown,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ImageCodecCli {
+    /// JPEG 2000
+    Jpx,
+    /// JBIG2
+    Jbig2,
+}
+
+impl From<ImageCodecCli> for pdfcore::ImageCodec {
+    fn from(value: ImageCodecCli) -> Self {
+        match value {
+            ImageCodecCli::Jpx => Self::Jpx,
+            ImageCodecCli::Jbig2 => Self::Jbig2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum TranscodeTargetCli {
+    /// JPEG: small, but lossy
+    Jpeg,
+    /// Flate: lossless
+    Flate,
+}
+
+impl From<TranscodeTargetCli> for pdfcore::TranscodeTarget {
+    fn from(value: TranscodeTargetCli) -> Self {
+        match value {
+            TranscodeTargetCli::Jpeg => Self::Jpeg,
+            TranscodeTargetCli::Flate => Self::Flate,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum EolCli {
+    /// Unix line endings (\n)
+    Lf,
+    /// Windows line endings (\r\n)
+    Crlf,
+}
+
+impl From<EolCli> for pdfcore::LineEnding {
+    fn from(value: EolCli) -> Self {
+        match value {
+            EolCli::Lf => Self::Lf,
+            EolCli::Crlf => Self::Crlf,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum TiffCompressionCli {
+    /// CCITT Group 4: black and white, for fax systems
+    G4,
+    /// LZW, in color
+    Lzw,
+    /// `PackBits` run-length, in color
+    Packbits,
+    /// Uncompressed color
+    None,
+}
+
+impl From<TiffCompressionCli> for pdfcore::TiffCompression {
+    fn from(value: TiffCompressionCli) -> Self {
+        match value {
+            TiffCompressionCli::G4 => Self::G4,
+            TiffCompressionCli::Lzw => Self::Lzw,
+            TiffCompressionCli::Packbits => Self::Packbits,
+            TiffCompressionCli::None => Self::None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum PdfxStandardCli {
+    X1a,
+    X4,
+}
+
+impl From<PdfxStandardCli> for pdfcore::PdfxStandard {
+    fn from(value: PdfxStandardCli) -> Self {
+        match value {
+            PdfxStandardCli::X1a => Self::X1a,
+            PdfxStandardCli::X4 => Self::X4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum CertificationCli {
+    /// No changes at all
+    NoChanges,
+    /// Filling in forms and signing
+    FormFilling,
+    /// Filling in forms, signing, and annotating
+    Annotations,
+}
+
+impl From<CertificationCli> for pdfcore::Certification {
+    fn from(value: CertificationCli) -> Self {
+        match value {
+            CertificationCli::NoChanges => Self::NoChanges,
+            CertificationCli::FormFilling => Self::FormFilling,
+            CertificationCli::Annotations => Self::Annotations,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum StampPositionCli {
+    TopLeft,
+    TopCenter,
+    TopRight,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+}
+
+impl From<StampPositionCli> for pdfcore::StampPosition {
+    fn from(value: StampPositionCli) -> Self {
+        match value {
+            StampPositionCli::TopLeft => Self::TopLeft,
+            StampPositionCli::TopCenter => Self::TopCenter,
+            StampPositionCli::TopRight => Self::TopRight,
+            StampPositionCli::BottomLeft => Self::BottomLeft,
+            StampPositionCli::BottomCenter => Self::BottomCenter,
+            StampPositionCli::BottomRight => Self::BottomRight,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum PageModeCli {
+    None,
+    Outlines,
+    Thumbs,
+    FullScreen,
+    Layers,
+    Attachments,
+}
+
+impl From<PageModeCli> for pdfcore::PageMode {
+    fn from(value: PageModeCli) -> Self {
+        match value {
+            PageModeCli::None => Self::None,
+            PageModeCli::Outlines => Self::Outlines,
+            PageModeCli::Thumbs => Self::Thumbnails,
+            PageModeCli::FullScreen => Self::FullScreen,
+            PageModeCli::Layers => Self::Layers,
+            PageModeCli::Attachments => Self::Attachments,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum PageLayoutCli {
+    Single,
+    Continuous,
+    TwoUp,
+    TwoUpCover,
+    TwoColumn,
+    TwoColumnCover,
+}
+
+impl From<PageLayoutCli> for pdfcore::PageLayout {
+    fn from(value: PageLayoutCli) -> Self {
+        match value {
+            PageLayoutCli::Single => Self::SinglePage,
+            PageLayoutCli::Continuous => Self::OneColumn,
+            PageLayoutCli::TwoUp => Self::TwoPageLeft,
+            PageLayoutCli::TwoUpCover => Self::TwoPageRight,
+            PageLayoutCli::TwoColumn => Self::TwoColumnLeft,
+            PageLayoutCli::TwoColumnCover => Self::TwoColumnRight,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum FitCli {
+    Page,
+    Width,
+    Height,
+    Actual,
+}
+
+impl From<FitCli> for pdfcore::OpenFit {
+    fn from(value: FitCli) -> Self {
+        match value {
+            FitCli::Page => Self::Page,
+            FitCli::Width => Self::Width,
+            FitCli::Height => Self::Height,
+            FitCli::Actual => Self::Percent(100),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum DirectionCli {
+    L2r,
+    R2l,
+}
+
+impl From<DirectionCli> for pdfcore::TextDirection {
+    fn from(value: DirectionCli) -> Self {
+        match value {
+            DirectionCli::L2r => Self::LeftToRight,
+            DirectionCli::R2l => Self::RightToLeft,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ColorSpaceCli {
+    Gray,
+    Cmyk,
+    Rgb,
+}
+
+impl From<ColorSpaceCli> for pdfcore::ColorSpace {
+    fn from(value: ColorSpaceCli) -> Self {
+        match value {
+            ColorSpaceCli::Gray => Self::Gray,
+            ColorSpaceCli::Cmyk => Self::Cmyk,
+            ColorSpaceCli::Rgb => Self::Rgb,
+        }
+    }
+}
+
+fn main() {
+    let cli = Cli::try_parse().unwrap_or_else(|e| exit_usage(&e));
+    init_logging(cli.verbose);
+    let (json_errors, strict) = (cli.json_errors, cli.strict);
+    let (result, warnings) = pdfcore::collect_warnings(|| run(cli));
+    for warning in &warnings {
+        report_warning(warning, json_errors);
+    }
+    let result = result.and_then(|()| {
+        if strict && !warnings.is_empty() {
+            return Err(pdfcore::PdfError::Warnings { warnings }.into());
+        }
+        Ok(())
+    });
+    let code = match result {
+        Ok(()) => 0,
+        Err(e) => report_error(&e, json_errors),
+    };
+    process::exit(code);
+}
+
+/// Print a warning from `pdfcore` to stderr, as JSON with `--json-errors`.
+fn report_warning(warning: &pdfcore::Warning, json: bool) {
+    if json {
+        eprintln!(
+            "{{\"warning\":{{\"code\":{},\"message\":{}}}}}",
+            json_string(warning.kind.code()),
+            json_string(&warning.message)
+        );
+    } else {
+        eprintln!("warning: {warning}");
+    }
+}
+
+/// Exit for a command-line parsing error, help, or `--version`.
+fn exit_usage(e: &clap::Error) -> ! {
+    let json = std::env::args_os().any(|arg| arg == "--json-errors");
+    if !json || !e.use_stderr() {
+        e.exit();
+    }
+    let message = e.to_string();
+    let message = message.trim().trim_start_matches("error: ");
+    eprintln!("{}", render_error_json("usage", 2, message, None));
+    process::exit(2);
+}
+
+/// Print `e` to stderr and return the process exit code for it: the
+/// [`pdfcore::PdfError::exit_code`] of the underlying library error, else 1.
+fn report_error(e: &anyhow::Error, json: bool) -> i32 {
+    let source = e
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<pdfcore::PdfError>());
+    let exit_code = source.map_or(1, pdfcore::PdfError::exit_code);
+    if json {
+        let code = source.map_or("error", pdfcore::PdfError::code);
+        eprintln!(
+            "{}",
+            render_error_json(code, exit_code, &format!("{e:#}"), source)
+        );
+    } else {
+        eprintln!("error: {e:#}");
+    }
+    exit_code
+}
+
+/// The error for a run over several inputs in which `failed` of `total`
+/// failed: the first failure itself when the run stopped there, else a
+/// summary with its own exit code ([`pdfcore::PdfError::InputsFailed`]).
+fn batch_error(
+    first: anyhow::Error,
+    failed: usize,
+    total: usize,
+    keep_going: bool,
+) -> anyhow::Error {
+    if keep_going {
+        pdfcore::PdfError::InputsFailed { failed, total }.into()
+    } else {
+        first
+    }
+}
+
+/// Send `pdfcore`'s diagnostics to stderr; warnings only unless `-v` is given.
+fn init_logging(verbose: u8) {
+    let level = match verbose {
+        0 => tracing::Level::WARN,
+        1 => tracing::Level::DEBUG,
+        _ => tracing::Level::TRACE,
+    };
+    tracing_subscriber::fmt()
+        .with_writer(io::stderr)
+        .with_max_level(level)
+        .with_target(false)
+        .without_time()
+        .init();
+}
+
+#[allow(clippy::too_many_lines)] // one arm per subcommand
+fn run(mut cli: Cli) -> anyhow::Result<()> {
+    let dry_run = cli.dry_run;
+    // Commands that must work even when the configuration is invalid.
+    match &cli.command {
+        Commands::Config {
+            action: ConfigCommand::Set { key, value },
+        } => return cmd_config_set(cli.config.as_deref(), key, value),
+        Commands::Completions { shell } => return cmd_completions(*shell),
+        Commands::Manpage { out_dir } => return cmd_manpage(out_dir.as_deref()),
+        _ => {}
+    }
+
+    let mut config = pdfcore::Config::load(cli.config.as_deref()).context("loading config")?;
+    if !cli.engine.is_empty() {
+        config.defaults.engines = cli
+            .engine
+            .iter()
+            .map(|name| name.parse::<pdfcore::Engine>())
+            .collect::<Result<Vec<_>, _>>()
+            .context("parsing --engine")?;
+    }
+    config.apply().context("applying config")?;
+    let mut run_options = if cli.sandbox {
+        pdfcore::ToolRunOptions::sandboxed()
+    } else {
+        pdfcore::ToolRunOptions::default()
+    };
+    run_options.timeout = cli.timeout;
+    pdfcore::set_tool_run_options(run_options);
+    pdfcore::set_low_memory(cli.low_memory);
+    pdfcore::set_deterministic(cli.deterministic);
+    let defaults = &config.defaults;
+    // Kept until the command has run.
+    let _download = remote::fetch_input(&mut cli.command, dry_run)?;
+    let open = match (cli.open, open::output(&cli.command)) {
+        (false, _) => None,
+        (true, None) => bail!("--open needs a command that writes a file"),
+        (true, Some(_)) if dry_run => None,
+        (true, output) => output,
+    };
+    // `None` leaves it to the command.
+    let keep_going = match (cli.keep_going, cli.fail_fast) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    };
+    let result = match cli.command {
+        Commands::Info {
+            inputs,
+            json,
+            pages_only,
+            password,
+            format,
+        } => {
+            let files = expand_patterns(&inputs)?;
+            match (files.as_slice(), format) {
+                ([], _) => bail!("no files match {}", inputs.join(", ")),
+                ([input], None) if pages_only => cmd_page_count(input, json),
+                ([input], None) => cmd_info(input, json, password.as_deref()),
+                (_, None) if json || pages_only => {
+                    bail!("--json and --pages-only take one file; use --format jsonl for several")
+                }
+                (files, format) => cmd_info_summary(
+                    files,
+                    format.unwrap_or(InfoFormat::Table),
+                    password.as_deref(),
+                    keep_going.unwrap_or(true),
+                    cli.tui,
+                ),
+            }
+        }
+        Commands::Fonts { input, json } => cmd_fonts(&input, json),
+        Commands::SizeReport { input, json } => cmd_size_report(&input, json),
+        Commands::InkCoverage { input, json } => cmd_ink_coverage(&input, json),
+        Commands::Stats {
+            input,
+            pages,
+            wpm,
+            json,
+        } => cmd_stats(&input, pages, wpm, json),
+        Commands::PageSizes {
+            input,
+            json,
+            fix: _,
+            to,
+            output,
+            force,
+        } => match (to, output) {
+            (Some(paper), Some(output)) => {
+                cmd_fit_page_sizes(&input, &output, paper, force, json, dry_run)
+            }
+            _ => cmd_page_sizes(&input, json),
+        },
+        Commands::Objects { input, json } => cmd_objects(&input, json),
+        Commands::ShowObject { input, object, raw } => cmd_show_object(&input, object, raw),
+        Commands::Links {
+            action: Some(action),
+            ..
+        } => cmd_links_edit(action, dry_run),
+        Commands::Links {
+            action: None,
+            input,
+            json,
+        } => {
+            let input = input.context("missing input PDF")?;
+            cmd_links(&input, json)
+        }
+        Commands::Barcodes { input, json } => cmd_barcodes(&input, json),
+        Commands::Analyze {
+            input,
+            min_dpi,
+            json,
+        } => cmd_analyze(&input, min_dpi, json),
+        Commands::Fingerprint { input, json } => cmd_fingerprint(&input, json),
+        Commands::Revisions {
+            action:
+                Some(RevisionsCommand::Extract {
+                    input,
+                    rev,
+                    output,
+                    force,
+                }),
+            ..
+        } => cmd_revisions_extract(&input, rev, &output, force, dry_run),
+        Commands::Revisions {
+            action: None,
+            input,
+            json,
+        } => {
+            let input = input.context("missing input PDF")?;
+            cmd_revisions(&input, json)
+        }
+        Commands::Permissions {
+            action:
+                Some(PermissionsCommand::Set {
+                    input,
+                    output,
+                    force,
+                    owner_password,
+                    user_password,
+                    password,
+                    no_print,
+                    no_high_quality_print,
+                    no_copy,
+                    no_modify,
+                    no_annotate,
+                    no_fill_forms,
+                    no_assemble,
+                }),
+            ..
+        } => {
+            let permissions = [
+                ("print", no_print),
+                ("print-high-quality", no_print || no_high_quality_print),
+                ("copy", no_copy),
+                ("modify", no_modify),
+                ("annotate", no_annotate),
+                ("fill-forms", no_fill_forms),
+                ("assemble", no_assemble),
+            ]
+            .into_iter()
+            .fold(pdfcore::Permissions::ALL, |permissions, (name, denied)| {
+                permissions.with(name, !denied)
+            });
+            let options = pdfcore::EncryptOptions {
+                permissions,
+                owner_password,
+                user_password,
+                password,
+            };
+            cmd_permissions_set(&input, &output, force, &options, dry_run)
+        }
+        Commands::Permissions {
+            action: None,
+            input,
+            json,
+        } => {
+            let input = input.context("missing input PDF")?;
+            cmd_permissions(&input, json)
+        }
+        Commands::Boxes {
+            action: Some(BoxesCommand::Show { input, json }),
+            ..
+        } => cmd_boxes_show(&input, json),
+        Commands::Boxes {
+            action: None,
+            input,
+            output,
+            force,
+            set,
+            unset,
+            pages,
+        } => {
+            let input = input.context("missing input PDF")?;
+            let output = output.context("missing output PDF")?;
+            let changes = pdfcore::BoxChanges {
+                set,
+                unset,
+                pages: pages.unwrap_or(pdfcore::PageSelection::All),
+            };
+            cmd_boxes(&input, &output, force, &changes, dry_run)
+        }
+        Commands::Dests {
+            action: DestsCommand::List { input, json },
+        } => cmd_dests_list(&input, json),
+        Commands::Dests {
+            action:
+                DestsCommand::Add {
+                    input,
+                    output,
+                    force,
+                    name,
+                    page,
+                    replace,
+                },
+        } => cmd_dests_add(&input, &output, force, &name, page, replace, dry_run),
+        Commands::Content { input, page } => cmd_content(&input, page),
+        Commands::Qdf {
+            input,
+            output,
+            force,
+        } => cmd_qdf(&input, &output, force, dry_run),
+        Commands::EmbedFonts {
+            input,
+            output,
+            force,
+        } => cmd_embed_fonts(&input, &output, force, dry_run),
+        Commands::Merge {
+            output,
+            force,
+            normalize,
+            inputs,
+        } => cmd_merge(
+            &inputs,
+            &output,
+            force,
+            normalize,
+            keep_going.unwrap_or(false),
+            dry_run,
+        ),
+        Commands::Bates {
+            inputs,
+            out_dir,
+            prefix,
+            start,
+            digits,
+            position,
+            font_size,
+            margin,
+            manifest,
+            force,
+            json,
+        } => {
+            let out_dir = out_dir_or_default(out_dir, defaults)?;
+            let manifest = manifest.unwrap_or_else(|| out_dir.join("bates.csv"));
+            let options = pdfcore::BatesOptions {
+                prefix,
+                start,
+                digits,
+                position: position.into(),
+                font_size,
+                margin,
+            };
+            cmd_bates(&inputs, &out_dir, &options, &manifest, force, json, dry_run)
+        }
+        Commands::Sign {
+            input,
+            output,
+            force,
+            pkcs12,
+            password,
+            visible: _,
+            page,
+            rect,
+            reason,
+            location,
+            certify,
+            tsa_url,
+        } => {
+            let options = pdfcore::SignOptions {
+                pkcs12,
+                password,
+                appearance: rect.map(|rect| pdfcore::SignatureAppearance { page, rect }),
+                reason,
+                location,
+                certify: certify.map(Into::into),
+                tsa_url,
+            };
+            cmd_sign(&input, &output, &options, force, dry_run)
+        }
+        Commands::Signatures {
+            input,
+            ca_file,
+            json,
+        } => cmd_signatures(&input, &pdfcore::SignaturesOptions { ca_file }, json),
+        Commands::SignVisual {
+            input,
+            output,
+            force,
+            image,
+            page,
+            at,
+            width,
+            date_format,
+            font_size,
+        } => {
+            let options = pdfcore::SignVisualOptions {
+                image,
+                page,
+                at,
+                width,
+                date_format,
+                font_size,
+            };
+            cmd_sign_visual(&input, &output, &options, force, dry_run)
+        }
+        Commands::HeaderFooter {
+            input,
+            output,
+            force,
+            header_left,
+            header_center,
+            header_right,
+            footer_left,
+            footer_center,
+            footer_right,
+            date,
+            font_size,
+            margin,
+        } => {
+            let options = pdfcore::HeaderFooterOptions {
+                header_left,
+                header_center,
+                header_right,
+                footer_left,
+                footer_center,
+                footer_right,
+                font_size,
+                margin,
+                date,
+            };
+            cmd_header_footer(&input, &output, &options, force, dry_run)
+        }
+        Commands::Pick {
+            input,
+            output,
+            force,
+        } => cmd_pick(&input, &output, force, dry_run),
+        Commands::SplitPages {
+            input,
+            out_dir,
+            pattern: Some(pattern),
+            name_from_text: true,
+            force,
+            manifest,
+            ..
+        } => {
+            let out_dir = out_dir_or_default(out_dir, defaults)?;
+            cmd_split_by_text(
+                &input,
+                &out_dir,
+                &pattern,
+                force,
+                manifest.as_deref(),
+                dry_run,
+            )
+        }
+        Commands::SplitPages {
+            input,
+            out_dir,
+            separator: Some(separator),
+            force,
+            manifest,
+            ..
+        } => {
+            let out_dir = out_dir_or_default(out_dir, defaults)?;
+            cmd_split_by_separator(
+                &input,
+                &out_dir,
+                separator.into(),
+                force,
+                manifest.as_deref(),
+                dry_run,
+            )
+        }
+        Commands::SplitPages {
+            input,
+            out_dir,
+            max_bytes: Some(max_bytes),
+            force,
+            manifest,
+            ..
+        } => {
+            let out_dir = out_dir_or_default(out_dir, defaults)?;
+            cmd_split_by_size(
+                &input,
+                &out_dir,
+                max_bytes,
+                force,
+                manifest.as_deref(),
+                dry_run,
+            )
+        }
+        Commands::SplitPages {
+            input,
+            out_dir,
+            pattern,
+            force,
+            manifest,
+            ..
+        } => {
+            let out_dir = out_dir_or_default(out_dir, defaults)?;
+            cmd_split_pages(
+                &input,
+                &out_dir,
+                pattern.as_deref(),
+                force,
+                manifest.as_deref(),
+                dry_run,
+            )
+        }
+        Commands::Xfa {
+            action:
+                XfaCommand::Dump {
+                    input,
+                    output,
+                    force,
+                },
+        } => cmd_xfa_dump(&input, output.as_deref(), force, dry_run),
+        Commands::ToSvg {
+            input,
+            page,
+            output,
+            force,
+        } => cmd_to_svg(&input, page, &output, force, dry_run),
+        Commands::ToPs {
+            input,
+            output,
+            force,
+            pages,
+            eps,
+        } => cmd_to_ps(&input, &output, force, pages.as_deref(), eps, dry_run),
+        Commands::Render {
+            input,
+            out_dir,
+            dpi,
+            force,
+            cache_dir,
+            manifest,
+        } => {
+            let out_dir = out_dir_or_default(out_dir, defaults)?;
+            cmd_render(
+                &input,
+                &out_dir,
+                dpi,
+                force,
+                cache_dir.as_deref(),
+                manifest.as_deref(),
+                dry_run,
+            )
+        }
+        Commands::Thumbnail {
+            inputs,
+            out_dir,
+            dpi,
+            cache_dir,
+            force,
+            manifest,
+        } => {
+            let files = expand_patterns(&inputs)?;
+            if files.is_empty() {
+                bail!("no files match {}", inputs.join(", "));
+            }
+            let out_dir = out_dir_or_default(out_dir, defaults)?;
+            cmd_thumbnail(
+                &files,
+                &out_dir,
+                dpi,
+                cache_dir.as_deref(),
+                force,
+                manifest.as_deref(),
+                keep_going.unwrap_or(true),
+                cli.tui,
+                dry_run,
+            )
+        }
+        Commands::ExtractText {
             inputs,
-        } => cmd_merge(&inputs, &output, force),
-        Commands::SplitPages {
+            output,
+            stdout,
+            force,
+            format,
+            per,
+            encoding,
+            bom,
+            eol,
+        } => {
+            let text_output = pdfcore::TextOutput {
+                encoding: encoding.unwrap_or_default(),
+                bom,
+                eol: eol.map(Into::into),
+            };
+            let files = expand_patterns(&inputs)?;
+            match (files.as_slice(), format) {
+                ([], _) => bail!("no files match {}", inputs.join(", ")),
+                (files, Some(format)) => cmd_extract_text_records(
+                    files,
+                    format,
+                    per,
+                    output.as_deref(),
+                    force,
+                    text_output,
+                    keep_going.unwrap_or(true),
+                    cli.tui,
+                    dry_run,
+                ),
+                ([input], None) => cmd_extract_text(
+                    input,
+                    output.as_deref(),
+                    stdout,
+                    force,
+                    text_output,
+                    dry_run,
+                ),
+                (_, None) => bail!("several inputs need --format jsonl or --format csv"),
+            }
+        }
+        Commands::ExtractTables {
             input,
             out_dir,
-            pattern,
+            format,
             force,
-        } => cmd_split_pages(&input, &out_dir, pattern.as_deref(), force),
-        Commands::ExtractText {
+        } => {
+            let out_dir = out_dir_or_default(out_dir, defaults)?;
+            cmd_extract_tables(&input, &out_dir, format, force, dry_run)
+        }
+        Commands::FromText {
+            input,
+            output,
+            force,
+            font_size,
+            paper,
+            margin,
+            format,
+        } => {
+            let options = pdfcore::TextToPdfOptions {
+                paper,
+                font_size,
+                margin,
+                format: format.map_or_else(|| pdfcore::TextFormat::for_path(&input), Into::into),
+            };
+            cmd_from_text(&input, &output, force, &options, dry_run)
+        }
+        Commands::FromHtml {
+            input,
+            output,
+            force,
+            paper,
+            margin,
+        } => {
+            let options = pdfcore::HtmlToPdfOptions { paper, margin };
+            cmd_from_html(&input, &output, force, &options, dry_run)
+        }
+        Commands::Convert {
+            input,
+            output,
+            force,
+        } => cmd_convert(&input, &output, force, dry_run),
+        Commands::Rotate {
+            input,
+            output,
+            force,
+            degrees,
+            pages,
+            spec,
+        } => match (degrees, spec) {
+            (_, Some(spec)) => cmd_rotate_spec(&input, &output, force, &spec, dry_run),
+            (Some(degrees), None) => {
+                cmd_rotate(&input, &output, force, degrees, pages.as_deref(), dry_run)
+            }
+            (None, None) => anyhow::bail!("rotate requires --degrees or --spec"),
+        },
+        Commands::Optimize {
+            input,
+            output,
+            force,
+            dedupe,
+            gc,
+            json,
+        } => {
+            let options = pdfcore::OptimizeOptions { dedupe, gc };
+            if pdfcore::is_pdf_collection(&input) {
+                let op = |i: &Path, o: &Path| pdfcore::optimize(i, o, &options).map(drop);
+                let keep_going = keep_going.unwrap_or(false);
+                cmd_each_pdf(
+                    &input, &output, "optimize", force, json, keep_going, dry_run, op,
+                )
+            } else {
+                cmd_optimize(&input, &output, &options, force, json, dry_run)
+            }
+        }
+        Commands::RecompressImages {
+            input,
+            output,
+            force,
+            max_dpi,
+            jpeg_quality,
+            skip_monochrome,
+            json,
+        } => {
+            let options = pdfcore::RecompressOptions {
+                max_dpi,
+                jpeg_quality,
+                skip_monochrome,
+            };
+            if pdfcore::is_pdf_collection(&input) {
+                let op = |i: &Path, o: &Path| pdfcore::recompress_images(i, o, &options).map(drop);
+                let keep_going = keep_going.unwrap_or(false);
+                cmd_each_pdf(
+                    &input,
+                    &output,
+                    "recompress images",
+                    force,
+                    json,
+                    keep_going,
+                    dry_run,
+                    op,
+                )
+            } else {
+                cmd_recompress_images(&input, &output, &options, force, json, dry_run)
+            }
+        }
+        Commands::TranscodeImages {
+            input,
+            output,
+            force,
+            from,
+            to,
+            jpeg_quality,
+            json,
+        } => {
+            let options = pdfcore::TranscodeOptions {
+                from: from.into_iter().map(Into::into).collect(),
+                to: to.into(),
+                jpeg_quality,
+            };
+            if pdfcore::is_pdf_collection(&input) {
+                let op = |i: &Path, o: &Path| pdfcore::transcode_images(i, o, &options).map(drop);
+                let keep_going = keep_going.unwrap_or(false);
+                cmd_each_pdf(
+                    &input,
+                    &output,
+                    "transcode images",
+                    force,
+                    json,
+                    keep_going,
+                    dry_run,
+                    op,
+                )
+            } else {
+                cmd_transcode_images(&input, &output, &options, force, json, dry_run)
+            }
+        }
+        Commands::FromPs {
+            input,
+            output,
+            force,
+            preset,
+        } => {
+            let preset = preset
+                .map(Into::into)
+                .or(defaults.compress_preset)
+                .unwrap_or(pdfcore::CompressPreset::Default);
+            cmd_from_ps(&input, &output, force, preset, dry_run)
+        }
+        Commands::FromTiff {
+            input,
+            output,
+            force,
+        } => cmd_from_tiff(&input, &output, force, dry_run),
+        Commands::FromEml {
+            input,
+            extract_pdfs,
+            out_dir,
+            merge,
+            force,
+            json,
+        } => match (extract_pdfs, merge) {
+            (false, _) => cmd_email_attachments(&input, json),
+            (true, Some(output)) => cmd_merge_email_pdfs(&input, &output, force, dry_run),
+            (true, None) => {
+                let out_dir = out_dir_or_default(out_dir, defaults)?;
+                cmd_extract_email_pdfs(&input, &out_dir, force, dry_run)
+            }
+        },
+        Commands::ToTiff {
+            input,
+            output,
+            force,
+            dpi,
+            compression,
+        } => {
+            let options = pdfcore::ToTiffOptions {
+                dpi,
+                compression: compression.into(),
+            };
+            cmd_to_tiff(&input, &output, force, options, dry_run)
+        }
+        Commands::Print {
+            input,
+            printer,
+            copies,
+            pages,
+            duplex,
+        } => cmd_print(&input, printer, copies, pages.as_deref(), duplex, dry_run),
+        Commands::Compress {
+            input,
+            output,
+            force,
+            preset,
+            json,
+        } => {
+            let preset = preset
+                .map(Into::into)
+                .or(defaults.compress_preset)
+                .unwrap_or(pdfcore::CompressPreset::Default);
+            if pdfcore::is_pdf_collection(&input) {
+                let op = |i: &Path, o: &Path| pdfcore::compress(i, o, preset).map(drop);
+                let keep_going = keep_going.unwrap_or(false);
+                cmd_each_pdf(
+                    &input, &output, "compress", force, json, keep_going, dry_run, op,
+                )
+            } else {
+                cmd_compress(&input, &output, force, preset, json, dry_run)
+            }
+        }
+        Commands::Run {
+            steps,
+            input,
+            output,
+            force,
+        } => cmd_run(&steps, &input, &output, force, dry_run),
+        Commands::Watch {
+            inbox,
+            steps,
+            out_dir,
+            debounce,
+            ledger,
+        } => {
+            let out_dir = out_dir_or_default(out_dir, defaults)?;
+            let pipeline: pdfcore::Pipeline = steps.parse().context("parsing --do")?;
+            let options = watch::WatchOptions {
+                inbox: &inbox,
+                out_dir: &out_dir,
+                pipeline: &pipeline,
+                debounce,
+                ledger: ledger.as_deref(),
+                tui: cli.tui,
+            };
+            watch::watch(&options, dry_run)
+        }
+        Commands::Serve {
+            listen,
+            max_size,
+            jobs,
+        } => {
+            let concurrency = match jobs.or(defaults.jobs) {
+                Some(0) => bail!("--jobs must be at least 1"),
+                Some(jobs) => jobs,
+                None => thread::available_parallelism().map_or(1, usize::from),
+            };
+            serve::serve(&serve::ServeOptions {
+                listen: &listen,
+                max_upload: max_size,
+                concurrency,
+            })
+        }
+        Commands::ConvertColor {
+            input,
+            output,
+            force,
+            to,
+        } => cmd_convert_color(&input, &output, force, to, dry_run),
+        Commands::CourtReady {
+            input,
+            output,
+            force,
+            max_size,
+            preset,
+            json,
+        } => {
+            let options = pdfcore::CourtReadyOptions {
+                max_bytes: max_size,
+                preset: preset.into(),
+            };
+            cmd_court_ready(&input, &output, force, &options, json, dry_run)
+        }
+        Commands::PrintReady {
+            input,
+            output,
+            force,
+            paper,
+            bleed,
+            cmyk,
+            crop_marks,
+            registration_marks,
+            keep_transparency,
+            json,
+        } => {
+            let options = pdfcore::PrintReadyOptions {
+                paper,
+                bleed,
+                cmyk,
+                flatten_transparency: !keep_transparency,
+                crop_marks,
+                registration_marks,
+            };
+            cmd_print_ready(&input, &output, force, &options, json, dry_run)
+        }
+        Commands::Bleed {
+            input,
+            output,
+            force,
+            bleed,
+            crop_marks,
+            registration_marks,
+        } => {
+            let options = pdfcore::BleedOptions {
+                bleed,
+                crop_marks,
+                registration_marks,
+            };
+            cmd_bleed(&input, &output, force, &options, dry_run)
+        }
+        Commands::ToPdfx {
+            input,
+            output,
+            force,
+            standard,
+            icc,
+            condition,
+            json,
+        } => {
+            let options = pdfcore::PdfxOptions {
+                standard: standard.into(),
+                icc_profile: icc,
+                condition,
+            };
+            cmd_to_pdfx(&input, &output, force, &options, json, dry_run)
+        }
+        Commands::A11yPrep {
+            input,
+            output,
+            force,
+            lang,
+            title,
+            no_ocr,
+            ocr_lang,
+            json,
+        } => {
+            let options = pdfcore::A11yPrepOptions {
+                lang,
+                title,
+                ocr: !no_ocr,
+                ocr_language: ocr_lang,
+            };
+            cmd_a11y_prep(&input, &output, force, &options, json, dry_run)
+        }
+        Commands::InferTitle {
+            input,
+            apply: _,
+            output,
+            replace,
+            force,
+            json,
+        } => cmd_infer_title(&input, output.as_deref(), replace, force, json, dry_run),
+        Commands::SetLang {
+            input,
+            output,
+            force,
+            lang,
+            direction,
+        } => cmd_set_lang(
+            &input,
+            &output,
+            force,
+            &lang,
+            direction.map(Into::into),
+            dry_run,
+        ),
+        Commands::ViewerPrefs {
+            input,
+            output,
+            force,
+            page_mode,
+            page_layout,
+            open_at,
+            fit,
+            hide_toolbar,
+            hide_menubar,
+            fit_window,
+            center_window,
+        } => {
+            let options = pdfcore::ViewerOptions {
+                page_mode: page_mode.map(Into::into),
+                page_layout: page_layout.map(Into::into),
+                open_at,
+                fit: fit.map(Into::into),
+                hide_toolbar,
+                hide_menubar,
+                fit_window,
+                center_window,
+            };
+            cmd_viewer_prefs(&input, &output, force, &options, dry_run)
+        }
+        Commands::OpenAction {
+            input,
+            output,
+            force,
+            page,
+            zoom,
+            dest,
+            remove,
+        } => {
+            let action = match (page, dest) {
+                (Some(page), _) => pdfcore::OpenAction::Page { page, zoom },
+                (None, Some(name)) => pdfcore::OpenAction::Destination(name),
+                (None, None) if remove => pdfcore::OpenAction::Remove,
+                (None, None) => bail!("give --page, --dest, or --remove"),
+            };
+            cmd_open_action(&input, &output, force, &action, dry_run)
+        }
+        Commands::A11yCheck { input, json } => cmd_a11y_check(&input, json),
+        Commands::Sanitize {
+            input,
+            output,
+            force,
+            json,
+        } => cmd_sanitize(&input, output.as_deref(), force, json, dry_run),
+        Commands::Redact {
+            input,
+            output,
+            force,
+            page,
+            rect,
+            text,
+            regex,
+            json,
+        } => {
+            let options = pdfcore::RedactOptions {
+                regions: rect
+                    .into_iter()
+                    .map(|rect| pdfcore::RedactRegion {
+                        page: page.unwrap_or(1),
+                        rect,
+                    })
+                    .collect(),
+                patterns: text,
+                regex,
+            };
+            cmd_redact(&input, &output, force, &options, json, dry_run)
+        }
+        Commands::Meta {
+            action: MetaCommand::Diff { a, b, json },
+        } => cmd_meta_diff(&a, &b, json),
+        Commands::Meta {
+            action:
+                MetaCommand::Apply {
+                    input,
+                    patch,
+                    output,
+                    force,
+                },
+        } => cmd_meta_apply(&input, &patch, &output, force, dry_run),
+        Commands::StripMetadata {
+            input,
+            output,
+            force,
+            keep,
+            annotation_authors,
+            json,
+        } => {
+            let options = pdfcore::StripOptions {
+                keep,
+                annotation_authors,
+            };
+            cmd_strip_metadata(&input, &output, force, &options, json, dry_run)
+        }
+        Commands::RegenId {
+            input,
+            output,
+            force,
+            from_content_hash,
+        } => cmd_regen_id(&input, &output, force, from_content_hash, dry_run),
+        Commands::Enforce {
+            policy,
+            patterns,
+            quarantine,
+            jobs,
+            json,
+        } => {
+            let jobs = match jobs.or(defaults.jobs) {
+                Some(0) => bail!("--jobs must be at least 1"),
+                Some(jobs) => jobs,
+                None => thread::available_parallelism().map_or(1, usize::from),
+            };
+            cmd_enforce(
+                &policy,
+                &patterns,
+                quarantine.as_deref(),
+                jobs,
+                json,
+                cli.tui,
+                dry_run,
+            )
+        }
+        Commands::Doctor { require, json } => cmd_doctor(&require, json),
+        Commands::Config {
+            action: ConfigCommand::Show,
+        } => cmd_config_show(cli.config.as_deref(), &config),
+        Commands::Config {
+            action: ConfigCommand::Set { .. },
+        }
+        | Commands::Completions { .. }
+        | Commands::Manpage { .. } => unreachable!("handled before the config is loaded"),
+    };
+    result?;
+    if let Some(output) = open {
+        open::launch(&output)?;
+    }
+    Ok(())
+}
+
+fn cmd_completions(shell: clap_complete::Shell) -> anyhow::Result<()> {
+    let mut cmd = Cli::command();
+    let name = cmd.get_name().to_string();
+    // Generate into a buffer: clap_complete panics if stdout is closed early.
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut cmd, name, &mut script);
+    io::stdout()
+        .write_all(&script)
+        .context("writing completions")
+}
+
+fn cmd_manpage(out_dir: Option<&Path>) -> anyhow::Result<()> {
+    let cmd = Cli::command();
+    match out_dir {
+        Some(dir) => {
+            ensure_can_write_dir(dir, true)?;
+            clap_mangen::generate_to(cmd, dir)
+                .with_context(|| format!("writing man pages to {}", dir.display()))?;
+            eprintln!("wrote: {}", dir.display());
+        }
+        None => clap_mangen::Man::new(cmd)
+            .render(&mut io::stdout())
+            .context("writing man page")?,
+    }
+    Ok(())
+}
+
+/// `--out-dir`, else `defaults.output_dir` from the config.
+fn out_dir_or_default(
+    out_dir: Option<PathBuf>,
+    defaults: &pdfcore::Defaults,
+) -> anyhow::Result<PathBuf> {
+    out_dir
+        .or_else(|| defaults.output_dir.clone())
+        .context("--out-dir is required (or set defaults.output_dir in the config)")
+}
+
+fn config_path(explicit: Option<&Path>) -> anyhow::Result<PathBuf> {
+    explicit
+        .map(Path::to_path_buf)
+        .or_else(pdfcore::Config::default_path)
+        .context("cannot locate the config file; pass --config or set PDFCLI_CONFIG")
+}
+
+fn cmd_config_show(explicit: Option<&Path>, config: &pdfcore::Config) -> anyhow::Result<()> {
+    let path = config_path(explicit)?;
+    let state = if path.exists() { "" } else { " (not found)" };
+    println!("# config file: {}{state}", path.display());
+    print!("{}", config.to_toml_string());
+    Ok(())
+}
+
+fn cmd_config_set(explicit: Option<&Path>, key: &str, value: &str) -> anyhow::Result<()> {
+    let path = config_path(explicit)?;
+    let mut config = pdfcore::Config::from_file(&path)
+        .with_context(|| format!("reading config: {}", path.display()))?;
+    config.set(key, value)?;
+    config
+        .save(&path)
+        .with_context(|| format!("writing config: {}", path.display()))?;
+    eprintln!("wrote: {}", path.display());
+    Ok(())
+}
+
+fn cmd_info(input: &Path, json: bool, password: Option<&str>) -> anyhow::Result<()> {
+    let info = match password {
+        Some(password) => pdfcore::info_with_password(input, password),
+        None => pdfcore::info(input),
+    }
+    .with_context(|| format!("reading pdf info: {}", input.display()))?;
+
+    if json {
+        let languages = match document_languages(input, &info) {
+            Ok(languages) => languages,
+            Err(e) => {
+                eprintln!("warning: cannot detect the language: {e}");
+                None
+            }
+        };
+        let out = render_info_json(&info, languages.as_deref());
+        print!("{out}");
+    } else {
+        println!("pages: {}", info.pages);
+        match &info.catalog_version {
+            Some(catalog) if *catalog != info.version => {
+                println!("version: {} (catalog {catalog})", info.version);
+            }
+            _ => println!("version: {}", info.version),
+        }
+        let yes_no = |flag: bool| if flag { "yes" } else { "no" };
+        println!("linearized: {}", yes_no(info.linearized));
+        println!("xref streams: {}", yes_no(info.xref_streams));
+        println!("object streams: {}", yes_no(info.object_streams));
+        println!("tagged: {}", yes_no(info.tagged));
+        println!("incremental updates: {}", info.incremental_updates);
+        if let Some([permanent, changing]) = &info.document_id {
+            println!("document ID: {permanent} {changing}");
+        }
+        if !info.metadata.is_empty() {
+            println!("metadata:");
+            for (k, v) in info.metadata {
+                println!("  {k}: {v}");
+            }
+        }
+        if !info.fonts.is_empty() {
+            let unembedded = info.fonts.iter().filter(|f| !f.embedded).count();
+            println!("fonts: {} ({unembedded} not embedded)", info.fonts.len());
+        }
+        if let Some(xfa) = info.xfa {
+            println!("xfa: {xfa}");
+        }
+        if let Some(encryption) = &info.encryption {
+            print_encryption(encryption);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_encryption(encryption: &pdfcore::EncryptionInfo) {
+    println!(
+        "encryption: {} {}-bit ({} handler, V{} R{})",
+        encryption.cipher,
+        encryption.key_bits,
+        encryption.filter,
+        encryption.version,
+        encryption.revision
+    );
+    let password = match encryption.user_password_required {
+        Some(true) => "required",
+        Some(false) => "not required",
+        None => "unknown",
+    };
+    println!("  user password: {password}");
+    let granted = encryption.permissions.granted();
+    println!(
+        "  permissions: {}",
+        if granted.is_empty() {
+            "none".to_string()
+        } else {
+            granted.join(", ")
+        }
+    );
+    if !encryption.decrypted {
+        println!("  not decrypted: metadata is unavailable (pass --password)");
+    }
+}
+
+/// One file of an `info` summary.
+struct InfoRow {
+    path: PathBuf,
+    size: u64,
+    info: anyhow::Result<pdfcore::PdfInfo>,
+}
+
+impl From<pdfcore::InputResult<pdfcore::PdfInfo>> for InfoRow {
+    fn from(read: pdfcore::InputResult<pdfcore::PdfInfo>) -> Self {
+        Self {
+            size: fs::metadata(&read.input).map_or(0, |m| m.len()),
+            path: read.input,
+            info: read.result.map_err(anyhow::Error::from),
+        }
+    }
+}
+
+impl InfoRow {
+    fn title(&self) -> Option<&str> {
+        self.info
+            .as_ref()
+            .ok()
+            .and_then(|info| info.metadata.get("Title"))
+            .map(String::as_str)
+    }
+}
+
+fn cmd_info_summary(
+    files: &[PathBuf],
+    format: InfoFormat,
+    password: Option<&str>,
+    keep_going: bool,
+    tui: bool,
+) -> anyhow::Result<()> {
+    let (dashboard, progress) = dashboard::start_if(tui, "info", 1)?;
+    let (out, result) = info_summary(files, format, password, keep_going, &progress);
+    drop(dashboard);
+    print!("{out}");
+    result
+}
+
+/// The summary of `files` in `format`, and the batch error if some could
+/// not be read.
+fn info_summary(
+    files: &[PathBuf],
+    format: InfoFormat,
+    password: Option<&str>,
+    keep_going: bool,
+    progress: &pdfcore::Progress,
+) -> (String, anyhow::Result<()>) {
+    let rows: Vec<InfoRow> =
+        pdfcore::for_each_input_with_progress(files, keep_going, progress, |path| match password {
+            Some(password) => pdfcore::info_with_password(path, password),
+            None => pdfcore::info(path),
+        })
+        .into_iter()
+        .map(InfoRow::from)
+        .collect();
+    let out = match format {
+        InfoFormat::Table => render_info_table(&rows),
+        InfoFormat::Csv => render_info_csv(&rows),
+        InfoFormat::Jsonl => render_info_jsonl(&rows),
+    };
+
+    let unreadable = rows.iter().filter(|row| row.info.is_err()).count();
+    let first = rows.into_iter().find_map(|row| {
+        let path = row.path;
+        row.info
+            .err()
+            .map(|e| e.context(format!("reading {}", path.display())))
+    });
+    let result = match first {
+        Some(first) => Err(batch_error(first, unreadable, files.len(), keep_going)),
+        None => Ok(()),
+    };
+    (out, result)
+}
+
+fn render_info_table(rows: &[InfoRow]) -> String {
+    let mut lines = vec![[
+        "PATH".to_string(),
+        "PAGES".to_string(),
+        "SIZE".to_string(),
+        "VERSION".to_string(),
+        "ENCRYPTED".to_string(),
+        "TITLE".to_string(),
+    ]];
+    for row in rows {
+        let path = row.path.display().to_string();
+        let size = format_bytes(row.size);
+        lines.push(match &row.info {
+            Ok(info) => [
+                path,
+                info.pages.to_string(),
+                size,
+                info.version.clone(),
+                if info.encryption.is_some() {
+                    "yes"
+                } else {
+                    "no"
+                }
+                .to_string(),
+                row.title().unwrap_or_default().to_string(),
+            ],
+            Err(e) => [
+                path,
+                "-".to_string(),
+                size,
+                "-".to_string(),
+                "-".to_string(),
+                format!("error: {e:#}"),
+            ],
+        });
+    }
+
+    let mut widths = [0; 6];
+    for line in &lines {
+        for (width, cell) in widths.iter_mut().zip(line) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut out = String::new();
+    for line in &lines {
+        let cells: Vec<String> = line
+            .iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(i, (cell, width))| match i {
+                // Numbers right-aligned; the title is last, so not padded.
+                1 | 2 => format!("{cell:>width$}"),
+                5 => cell.clone(),
+                _ => format!("{cell:<width$}"),
+            })
+            .collect();
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+fn render_info_csv(rows: &[InfoRow]) -> String {
+    let mut out = String::from("path,pages,size,version,encrypted,title,error\n");
+    for row in rows {
+        let path = row.path.display().to_string();
+        let fields = match &row.info {
+            Ok(info) => [
+                path,
+                info.pages.to_string(),
+                row.size.to_string(),
+                info.version.clone(),
+                info.encryption.is_some().to_string(),
+                row.title().unwrap_or_default().to_string(),
+                String::new(),
+            ],
+            Err(e) => [
+                path,
+                String::new(),
+                row.size.to_string(),
+                String::new(),
+                String::new(),
+                String::new(),
+                format!("{e:#}"),
+            ],
+        };
+        let fields: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// Quote a CSV field if it needs it (RFC 4180).
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) || field.trim() != field {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn render_bates_csv(ranges: &[pdfcore::BatesRange]) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::from("first,last,pages,source,output,sha256\n");
+    for range in ranges {
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{}",
+            csv_field(&range.first),
+            csv_field(&range.last),
+            range.pages,
+            csv_field(&range.input.to_string_lossy()),
+            csv_field(&range.output.to_string_lossy()),
+            range.sha256
+        );
+    }
+    out
+}
+
+fn render_bates_json(ranges: &[pdfcore::BatesRange]) -> String {
+    let items: Vec<String> = ranges
+        .iter()
+        .map(|range| {
+            format!(
+                "  {{\"first\": {}, \"last\": {}, \"pages\": {}, \"source\": {}, \"output\": {}, \"sha256\": {}}}",
+                json_string(&range.first),
+                json_string(&range.last),
+                range.pages,
+                json_string(&range.input.to_string_lossy()),
+                json_string(&range.output.to_string_lossy()),
+                json_string(&range.sha256)
+            )
+        })
+        .collect();
+    format!("[\n{}\n]\n", items.join(",\n"))
+}
+
+fn render_info_jsonl(rows: &[InfoRow]) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::new();
+    for row in rows {
+        let path = json_string(&row.path.display().to_string());
+        let _ = match &row.info {
+            Ok(info) => writeln!(
+                &mut out,
+                "{{\"path\":{path},\"pages\":{},\"size\":{},\"version\":{},\"encrypted\":{},\"title\":{},\"error\":null}}",
+                info.pages,
+                row.size,
+                json_string(&info.version),
+                info.encryption.is_some(),
+                row.title().map_or_else(|| "null".to_string(), json_string),
+            ),
+            Err(e) => writeln!(
+                &mut out,
+                "{{\"path\":{path},\"pages\":null,\"size\":{},\"version\":null,\"encrypted\":null,\"title\":null,\"error\":{}}}",
+                row.size,
+                json_string(&format!("{e:#}")),
+            ),
+        };
+    }
+    out
+}
+
+fn cmd_page_count(input: &Path, json: bool) -> anyhow::Result<()> {
+    let pages = pdfcore::page_count(input)
+        .with_context(|| format!("reading page count: {}", input.display()))?;
+    if json {
+        println!("{{\"pages\": {pages}}}");
+    } else {
+        println!("{pages}");
+    }
+    Ok(())
+}
+
+fn cmd_fonts(input: &Path, json: bool) -> anyhow::Result<()> {
+    let fonts =
+        pdfcore::fonts(input).with_context(|| format!("reading fonts: {}", input.display()))?;
+
+    if json {
+        let mut out = render_fonts_json(&fonts, 0);
+        out.push('\n');
+        print!("{out}");
+        return Ok(());
+    }
+
+    if fonts.is_empty() {
+        println!("no fonts found");
+        return Ok(());
+    }
+    println!(
+        "{:<40} {:<12} {:<9} {:<7} pages",
+        "name", "type", "embedded", "subset"
+    );
+    for f in &fonts {
+        println!(
+            "{:<40} {:<12} {:<9} {:<7} {}",
+            f.name,
+            f.font_type,
+            if f.embedded { "yes" } else { "no" },
+            if f.subset { "yes" } else { "no" },
+            format_page_list(&f.pages)
+        );
+    }
+    Ok(())
+}
+
+fn cmd_size_report(input: &Path, json: bool) -> anyhow::Result<()> {
+    let report = pdfcore::size_report(input)
+        .with_context(|| format!("analyzing size: {}", input.display()))?;
+
+    if json {
+        print!("{}", render_size_report_json(&report));
+        return Ok(());
+    }
+
+    println!("file size: {}", format_bytes(report.file_bytes));
+    println!(
+        "{:<16} {:>10} {:>6} {:>8}",
+        "category", "size", "share", "objects"
+    );
+    for bucket in &report.buckets {
+        println!(
+            "{:<16} {:>10} {:>5.1}% {:>8}",
+            bucket.category.name(),
+            format_bytes(bucket.bytes),
+            share_percent(bucket.bytes, report.file_bytes),
+            bucket.objects
+        );
+    }
+    if !report.largest.is_empty() {
+        println!("largest objects:");
+        for obj in &report.largest {
+            println!(
+                "  {:>6} {:<3} {:<16} {:>10}",
+                obj.id.0,
+                obj.id.1,
+                obj.category.name(),
+                format_bytes(obj.bytes)
+            );
+        }
+    }
+    Ok(())
+}
+
+fn cmd_page_sizes(input: &Path, json: bool) -> anyhow::Result<()> {
+    let report = pdfcore::page_sizes(input)
+        .with_context(|| format!("reading page sizes: {}", input.display()))?;
+    if json {
+        print!("{}", render_page_sizes_json(input, &report, None));
+        return Ok(());
+    }
+    print_page_sizes(&report);
+    Ok(())
+}
+
+fn print_page_sizes(report: &pdfcore::PageSizeReport) {
+    for group in &report.groups {
+        println!(
+            "{:>5} page(s)  {:<28} {:.1} x {:.1} mm  pages {}",
+            group.pages.len(),
+            group.label(),
+            group.width / pdfcore::POINTS_PER_MM,
+            group.height / pdfcore::POINTS_PER_MM,
+            page_list(&group.pages)
+        );
+    }
+    for (_, outlier) in report.outliers() {
+        println!("outlier: {outlier}");
+    }
+}
+
+/// Page numbers as ranges, e.g. `1-3, 5, 7-9`.
+fn page_list(pages: &[u32]) -> String {
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for &page in pages {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == page => *end = page,
+            _ => ranges.push((page, page)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|&(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{start}-{end}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn render_page_sizes_json(
+    input: &Path,
+    report: &pdfcore::PageSizeReport,
+    fixed: Option<&[u32]>,
+) -> String {
+    use std::fmt::Write as _;
+
+    let groups: Vec<String> = report
+        .groups
+        .iter()
+        .map(|group| {
+            let pages: Vec<String> = group.pages.iter().map(ToString::to_string).collect();
+            format!(
+                "    {{\"label\": {}, \"paper\": {}, \"width\": {}, \"height\": {}, \"landscape\": {}, \"pages\": [{}]}}",
+                json_string(&group.label()),
+                group
+                    .paper
+                    .map_or_else(|| "null".to_string(), |paper| json_string(paper.name)),
+                group.width,
+                group.height,
+                group.is_landscape(),
+                pages.join(", ")
+            )
+        })
+        .collect();
+    let outliers: Vec<String> = report
+        .outliers()
+        .iter()
+        .map(|(page, message)| {
+            format!(
+                "    {{\"page\": {page}, \"message\": {}}}",
+                json_string(message)
+            )
+        })
+        .collect();
+    let mut out = String::from("{\n");
+    let _ = writeln!(
+        out,
+        "  \"input\": {},",
+        json_string(&input.to_string_lossy())
+    );
+    let _ = writeln!(out, "  \"pages\": {},", report.pages);
+    let _ = writeln!(out, "  \"uniform\": {},", report.is_uniform());
+    let _ = writeln!(out, "  \"groups\": [\n{}\n  ],", groups.join(",\n"));
+    if let Some(fixed) = fixed {
+        let fixed: Vec<String> = fixed.iter().map(ToString::to_string).collect();
+        let _ = writeln!(out, "  \"fixed\": [{}],", fixed.join(", "));
+    }
+    if outliers.is_empty() {
+        out.push_str("  \"outliers\": []\n");
+    } else {
+        let _ = writeln!(out, "  \"outliers\": [\n{}\n  ]", outliers.join(",\n"));
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn cmd_fit_page_sizes(
+    input: &Path,
+    output: &Path,
+    paper: pdfcore::PaperSize,
+    force: bool,
+    json: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::fit_pages_to_paper_plan(input, output, paper)
+            .context("planning page size fix")?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    let report = pdfcore::page_sizes(input)
+        .with_context(|| format!("reading page sizes: {}", input.display()))?;
+    let fixed = pdfcore::fit_pages_to_paper(input, output, paper).with_context(|| {
+        format!(
+            "scaling pages to {paper}: {} -> {}",
+            input.display(),
+            output.display()
+        )
+    })?;
+    if json {
+        print!("{}", render_page_sizes_json(input, &report, Some(&fixed)));
+    } else {
+        print_page_sizes(&report);
+        if fixed.is_empty() {
+            println!("every page is already {paper}");
+        } else {
+            println!("scaled to {paper}: pages {}", page_list(&fixed));
+        }
+    }
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+fn cmd_stats(
+    input: &Path,
+    pages: Option<pdfcore::PageSelection>,
+    wpm: u32,
+    json: bool,
+) -> anyhow::Result<()> {
+    let options = pdfcore::TextStatsOptions {
+        pages: pages.unwrap_or(pdfcore::PageSelection::All),
+        words_per_minute: wpm,
+    };
+    let stats = pdfcore::text_stats(input, &options)
+        .with_context(|| format!("counting text: {}", input.display()))?;
+    let language_json = |language: &Option<(pdfcore::Language, f64)>| {
+        language.as_ref().map_or_else(
+            || "null".to_string(),
+            |(language, confidence)| render_language_json(language, *confidence),
+        )
+    };
+    let language_name = |language: &Option<(pdfcore::Language, f64)>| {
+        language
+            .as_ref()
+            .map_or("-", |(language, _)| language.label())
+            .to_string()
+    };
+
+    if json {
+        let items: Vec<String> = stats
+            .pages
+            .iter()
+            .map(|p| {
+                format!(
+                    "    {{\"page\": {}, \"words\": {}, \"characters\": {}, \"characters_no_spaces\": {}, \"language\": {}}}",
+                    p.page,
+                    p.words,
+                    p.characters,
+                    p.characters_no_spaces,
+                    language_json(&p.language)
+                )
+            })
+            .collect();
+        println!("{{");
+        println!("  \"input\": {},", json_string(&input.to_string_lossy()));
+        println!("  \"words\": {},", stats.words);
+        println!("  \"characters\": {},", stats.characters);
+        println!(
+            "  \"characters_no_spaces\": {},",
+            stats.characters_no_spaces
+        );
+        println!("  \"words_per_minute\": {wpm},");
+        println!("  \"reading_minutes\": {:.1},", stats.reading_minutes);
+        println!("  \"language\": {},", language_json(&stats.language));
+        if items.is_empty() {
+            println!("  \"pages\": []");
+        } else {
+            println!("  \"pages\": [\n{}\n  ]", items.join(",\n"));
+        }
+        println!("}}");
+        return Ok(());
+    }
+    println!(
+        "{:>5}  {:>8} {:>10} {:>10}  language",
+        "page", "words", "chars", "no spaces"
+    );
+    for p in &stats.pages {
+        println!(
+            "{:>5}  {:>8} {:>10} {:>10}  {}",
+            p.page,
+            p.words,
+            p.characters,
+            p.characters_no_spaces,
+            language_name(&p.language)
+        );
+    }
+    println!(
+        "{:>5}  {:>8} {:>10} {:>10}  {}",
+        "total",
+        stats.words,
+        stats.characters,
+        stats.characters_no_spaces,
+        language_name(&stats.language)
+    );
+    println!(
+        "reading time: {} at {wpm} words per minute",
+        format_minutes(stats.reading_minutes)
+    );
+    Ok(())
+}
+
+/// A reading time, e.g. `under a minute`, `12 min`, or `1 h 5 min`.
+fn format_minutes(minutes: f64) -> String {
+    if minutes < 1.0 {
+        return "under a minute".to_string();
+    }
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let minutes = minutes.round() as u64;
+    if minutes < 60 {
+        format!("{minutes} min")
+    } else {
+        format!("{} h {} min", minutes / 60, minutes % 60)
+    }
+}
+
+fn cmd_ink_coverage(input: &Path, json: bool) -> anyhow::Result<()> {
+    let pages = pdfcore::ink_coverage(input)
+        .with_context(|| format!("measuring ink coverage: {}", input.display()))?;
+    let count = f64::from(u32::try_from(pages.len()).unwrap_or(u32::MAX).max(1));
+    let average =
+        |ink: fn(&pdfcore::InkCoverage) -> f64| pages.iter().map(ink).sum::<f64>() / count;
+    let averages = [
+        average(|p| p.cyan),
+        average(|p| p.magenta),
+        average(|p| p.yellow),
+        average(|p| p.black),
+    ];
+
+    if json {
+        let items: Vec<String> = pages
+            .iter()
+            .map(|p| {
+                format!(
+                    "    {{\"page\": {}, \"c\": {:.3}, \"m\": {:.3}, \"y\": {:.3}, \"k\": {:.3}, \"total\": {:.3}}}",
+                    p.page,
+                    p.cyan,
+                    p.magenta,
+                    p.yellow,
+                    p.black,
+                    p.total()
+                )
+            })
+            .collect();
+        let [c, m, y, k] = averages;
+        println!("{{");
+        println!("  \"input\": {},", json_string(&input.to_string_lossy()));
+        println!(
+            "  \"average\": {{\"c\": {c:.3}, \"m\": {m:.3}, \"y\": {y:.3}, \"k\": {k:.3}, \"total\": {:.3}}},",
+            c + m + y + k
+        );
+        println!("  \"pages\": [\n{}\n  ]", items.join(",\n"));
+        println!("}}");
+        return Ok(());
+    }
+    println!(
+        "{:>5}  {:>7} {:>7} {:>7} {:>7}  {:>7}",
+        "page", "C %", "M %", "Y %", "K %", "total %"
+    );
+    for p in &pages {
+        println!(
+            "{:>5}  {:>7.2} {:>7.2} {:>7.2} {:>7.2}  {:>7.2}",
+            p.page,
+            p.cyan,
+            p.magenta,
+            p.yellow,
+            p.black,
+            p.total()
+        );
+    }
+    let [c, m, y, k] = averages;
+    println!(
+        "{:>5}  {c:>7.2} {m:>7.2} {y:>7.2} {k:>7.2}  {:>7.2}",
+        "avg",
+        c + m + y + k
+    );
+    Ok(())
+}
+
+fn cmd_objects(input: &Path, json: bool) -> anyhow::Result<()> {
+    let objects =
+        pdfcore::objects(input).with_context(|| format!("reading objects: {}", input.display()))?;
+
+    if json {
+        print!("{}", render_objects_json(&objects));
+        return Ok(());
+    }
+    println!("{:>8} {:<4} {:<28} stream", "object", "gen", "type");
+    for obj in &objects {
+        let stream = obj
+            .stream_length
+            .map(|len| format!("{len} bytes"))
+            .unwrap_or_default();
+        println!("{:>8} {:<4} {:<28} {stream}", obj.id.0, obj.id.1, obj.kind);
+    }
+    Ok(())
+}
+
+fn cmd_show_object(input: &Path, number: u32, raw: bool) -> anyhow::Result<()> {
+    let dump = pdfcore::show_object(input, number, raw)
+        .with_context(|| format!("reading object {number}: {}", input.display()))?;
+
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{} {} obj", dump.id.0, dump.id.1)?;
+    writeln!(stdout, "{}", dump.text)?;
+    if let Some(data) = &dump.stream {
+        let state = if dump.decoded { "decoded" } else { "raw" };
+        writeln!(stdout, "stream ({} bytes, {state})", data.len())?;
+        stdout.write_all(data)?;
+        if !data.ends_with(b"\n") {
+            writeln!(stdout)?;
+        }
+        writeln!(stdout, "endstream")?;
+    }
+    writeln!(stdout, "endobj")?;
+    Ok(())
+}
+
+fn cmd_links(input: &Path, json: bool) -> anyhow::Result<()> {
+    let links =
+        pdfcore::links(input).with_context(|| format!("reading links: {}", input.display()))?;
+
+    if json {
+        print!("{}", render_links_json(&links));
+        return Ok(());
+    }
+    if links.is_empty() {
+        println!("no links found");
+        return Ok(());
+    }
+    println!("{:>5}  {:<28} target", "page", "rect");
+    for link in &links {
+        println!(
+            "{:>5}  {:<28} {}",
+            link.page,
+            link.rect.to_string(),
+            link.target
+        );
+    }
+    Ok(())
+}
+
+fn cmd_barcodes(input: &Path, json: bool) -> anyhow::Result<()> {
+    let codes = pdfcore::barcodes(input)
+        .with_context(|| format!("reading barcodes: {}", input.display()))?;
+
+    if json {
+        print!("{}", render_barcodes_json(&codes));
+        return Ok(());
+    }
+    if codes.is_empty() {
+        println!("no barcodes found");
+        return Ok(());
+    }
+    println!("{:>5}  {:<8} {:<28} text", "page", "type", "rect");
+    for code in &codes {
+        println!(
+            "{:>5}  {:<8} {:<28} {}",
+            code.page,
+            code.kind.name(),
+            code.rect.to_string(),
+            code.text.escape_debug()
+        );
+    }
+    Ok(())
+}
+
+fn cmd_revisions(input: &Path, json: bool) -> anyhow::Result<()> {
+    let revisions = pdfcore::revisions(input)
+        .with_context(|| format!("reading revisions: {}", input.display()))?;
+
+    if json {
+        let items: Vec<String> = revisions
+            .iter()
+            .map(|r| {
+                format!(
+                    "    {{\"revision\": {}, \"offset\": {}, \"size\": {}}}",
+                    r.number, r.offset, r.size
+                )
+            })
+            .collect();
+        println!(
+            "{{\n  \"incremental_updates\": {},\n  \"revisions\": [\n{}\n  ]\n}}",
+            revisions.len().saturating_sub(1),
+            items.join(",\n")
+        );
+        return Ok(());
+    }
+    println!("incremental updates: {}", revisions.len().saturating_sub(1));
+    println!("{:>8}  {:>12}  {:>12}", "revision", "offset", "size");
+    for revision in &revisions {
+        println!(
+            "{:>8}  {:>12}  {:>12}",
+            revision.number, revision.offset, revision.size
+        );
+    }
+    Ok(())
+}
+
+fn cmd_analyze(input: &Path, min_dpi: f32, json: bool) -> anyhow::Result<()> {
+    let images =
+        pdfcore::images(input).with_context(|| format!("analyzing images: {}", input.display()))?;
+    let mut low_pages: Vec<u32> = images
+        .iter()
+        .filter(|image| image.dpi() < min_dpi)
+        .map(|image| image.page)
+        .collect();
+    low_pages.dedup();
+    if json {
+        let items: Vec<String> = images
+            .iter()
+            .map(|image| {
+                format!(
+                    "    {{\"page\": {}, \"name\": {}, \"width\": {}, \"height\": {}, \"placed_width\": {:.2}, \"placed_height\": {:.2}, \"dpi_x\": {:.1}, \"dpi_y\": {:.1}, \"below_min_dpi\": {}}}",
+                    image.page,
+                    json_string(&image.name),
+                    image.width,
+                    image.height,
+                    image.placed_width,
+                    image.placed_height,
+                    image.dpi_x,
+                    image.dpi_y,
+                    image.dpi() < min_dpi
+                )
+            })
+            .collect();
+        let pages: Vec<String> = low_pages.iter().map(ToString::to_string).collect();
+        println!(
+            "{{\n  \"min_dpi\": {min_dpi},\n  \"low_dpi_pages\": [{}],\n  \"images\": [\n{}\n  ]\n}}",
+            pages.join(", "),
+            items.join(",\n")
+        );
+        return Ok(());
+    }
+    if images.is_empty() {
+        println!("no images");
+        return Ok(());
+    }
+    println!(
+        "{:>5}  {:<12}  {:>11}  {:>15}  {:>6}",
+        "page", "image", "pixels", "placed (pt)", "dpi"
+    );
+    for image in &images {
+        println!(
+            "{:>5}  {:<12}  {:>11}  {:>15}  {:>6.0}{}",
+            image.page,
+            image.name,
+            format!("{}x{}", image.width, image.height),
+            format!("{:.0}x{:.0}", image.placed_width, image.placed_height),
+            image.dpi(),
+            if image.dpi() < min_dpi { "  LOW" } else { "" }
+        );
+    }
+    if low_pages.is_empty() {
+        println!("all images are at least {min_dpi} DPI");
+    } else {
+        let pages: Vec<String> = low_pages.iter().map(ToString::to_string).collect();
+        println!(
+            "pages with images below {min_dpi} DPI: {}",
+            pages.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn cmd_fingerprint(input: &Path, json: bool) -> anyhow::Result<()> {
+    let pages = pdfcore::fingerprint(input)
+        .with_context(|| format!("fingerprinting: {}", input.display()))?;
+    if json {
+        let items: Vec<String> = pages
+            .iter()
+            .map(|p| format!("    {{\"page\": {}, \"hash\": \"{}\"}}", p.page, p.hash))
+            .collect();
+        println!("{{\n  \"pages\": [\n{}\n  ]\n}}", items.join(",\n"));
+        return Ok(());
+    }
+    for page in &pages {
+        println!("{:>5}  {}", page.page, page.hash);
+    }
+    Ok(())
+}
+
+fn cmd_revisions_extract(
+    input: &Path,
+    rev: u32,
+    output: &Path,
+    force: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::extract_revision_plan(input, output, rev)
+            .with_context(|| format!("planning extraction from {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    let revision = pdfcore::extract_revision(input, output, rev).with_context(|| {
+        format!(
+            "extracting revision {rev} of {} -> {}",
+            input.display(),
+            output.display()
+        )
+    })?;
+    eprintln!(
+        "wrote: {} (revision {}, {} bytes)",
+        output.display(),
+        revision.number,
+        revision.end()
+    );
+    Ok(())
+}
+
+fn cmd_permissions(input: &Path, json: bool) -> anyhow::Result<()> {
+    let encryption = pdfcore::encryption_info(input)
+        .with_context(|| format!("reading permissions: {}", input.display()))?;
+    let permissions = encryption
+        .as_ref()
+        .map_or(pdfcore::Permissions::ALL, |e| e.permissions);
+
+    if json {
+        let flags: Vec<String> = pdfcore::Permissions::FLAGS
+            .iter()
+            .map(|(name, _)| format!("{}: {}", json_string(name), permissions.allows(name)))
+            .collect();
+        println!(
+            "{{\n  \"input\": {},\n  \"encrypted\": {},\n  \"permissions\": {{{}}}\n}}",
+            json_string(&input.to_string_lossy()),
+            encryption.is_some(),
+            flags.join(", ")
+        );
+        return Ok(());
+    }
+    match &encryption {
+        Some(encryption) => println!(
+            "encryption: {} {}-bit",
+            encryption.cipher, encryption.key_bits
+        ),
+        None => println!("encryption: none (everything is permitted)"),
+    }
+    for (name, _) in pdfcore::Permissions::FLAGS {
+        let allowed = if permissions.allows(name) {
+            "yes"
+        } else {
+            "no"
+        };
+        println!("  {name:<20} {allowed}");
+    }
+    Ok(())
+}
+
+fn cmd_permissions_set(
+    input: &Path,
+    output: &Path,
+    force: bool,
+    options: &pdfcore::EncryptOptions,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::set_permissions_plan(input, output, options)
+            .with_context(|| format!("planning encryption of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    pdfcore::set_permissions(input, output, options)
+        .with_context(|| format!("encrypting {} -> {}", input.display(), output.display()))?;
+    let granted = options.permissions.granted();
+    println!(
+        "permissions: {}",
+        if granted.is_empty() {
+            "none".to_string()
+        } else {
+            granted.join(", ")
+        }
+    );
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+fn cmd_boxes_show(input: &Path, json: bool) -> anyhow::Result<()> {
+    let pages = pdfcore::page_boxes(input)
+        .with_context(|| format!("reading page boxes: {}", input.display()))?;
+
+    if json {
+        print!("{}", render_boxes_json(&pages));
+        return Ok(());
+    }
+    for page in &pages {
+        if page.rotate == 0 {
+            println!("page {}", page.page);
+        } else {
+            println!("page {} (rotated {})", page.page, page.rotate);
+        }
+        for info in &page.boxes {
+            let rect = info.rect;
+            println!(
+                "  {:<6} {:<28} {:.1} x {:.1} mm{}",
+                info.which.name(),
+                rect.to_string(),
+                rect.width() / pdfcore::POINTS_PER_MM,
+                rect.height() / pdfcore::POINTS_PER_MM,
+                if info.explicit { "" } else { "  (default)" }
+            );
+        }
+        for problem in &page.problems {
+            println!("  problem: {problem}");
+        }
+    }
+    Ok(())
+}
+
+fn render_boxes_json(pages: &[pdfcore::PageBoxes]) -> String {
+    let items: Vec<String> = pages
+        .iter()
+        .map(|page| {
+            let boxes: Vec<String> = page
+                .boxes
+                .iter()
+                .map(|info| {
+                    let r = info.rect;
+                    format!(
+                        "{}: {{\"rect\": [{}, {}, {}, {}], \"explicit\": {}}}",
+                        json_string(info.which.name()),
+                        r.llx,
+                        r.lly,
+                        r.urx,
+                        r.ury,
+                        info.explicit
+                    )
+                })
+                .collect();
+            format!(
+                "  {{\"page\": {}, \"rotate\": {}, \"boxes\": {{{}}}, \"problems\": {}}}",
+                page.page,
+                page.rotate,
+                boxes.join(", "),
+                json_string_array(&page.problems)
+            )
+        })
+        .collect();
+    if items.is_empty() {
+        "[]\n".to_string()
+    } else {
+        format!("[\n{}\n]\n", items.join(",\n"))
+    }
+}
+
+fn cmd_boxes(
+    input: &Path,
+    output: &Path,
+    force: bool,
+    changes: &pdfcore::BoxChanges,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::set_page_boxes_plan(input, output, changes)
+            .context("planning page box changes")?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    let pages = pdfcore::set_page_boxes(input, output, changes).with_context(|| {
+        format!(
+            "setting page boxes: {} -> {}",
+            input.display(),
+            output.display()
+        )
+    })?;
+    eprintln!("changed {pages} page(s)");
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+fn cmd_dests_list(input: &Path, json: bool) -> anyhow::Result<()> {
+    let dests = pdfcore::destinations(input)
+        .with_context(|| format!("reading named destinations: {}", input.display()))?;
+
+    if json {
+        let items: Vec<String> = dests
+            .iter()
+            .map(|d| {
+                format!(
+                    "  {{\"name\": {}, \"page\": {}, \"view\": {}}}",
+                    json_string(&d.name),
+                    d.page.map_or_else(|| "null".to_string(), |p| p.to_string()),
+                    d.view
+                        .as_deref()
+                        .map_or_else(|| "null".to_string(), json_string)
+                )
+            })
+            .collect();
+        if items.is_empty() {
+            println!("[]");
+        } else {
+            println!("[\n{}\n]", items.join(",\n"));
+        }
+        return Ok(());
+    }
+    if dests.is_empty() {
+        println!("no named destinations found");
+        return Ok(());
+    }
+    println!("{:>5}  {:<8} name", "page", "view");
+    for dest in &dests {
+        println!(
+            "{:>5}  {:<8} {}",
+            dest.page.map_or_else(|| "?".to_string(), |p| p.to_string()),
+            dest.view.as_deref().unwrap_or("-"),
+            dest.name
+        );
+    }
+    Ok(())
+}
+
+fn cmd_dests_add(
+    input: &Path,
+    output: &Path,
+    force: bool,
+    name: &str,
+    page: u32,
+    replace: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::add_destination_plan(input, output, name, page, replace)
+            .with_context(|| format!("planning named destination in {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    pdfcore::add_destination(input, output, name, page, replace).with_context(|| {
+        format!(
+            "adding named destination {} -> {}",
+            input.display(),
+            output.display()
+        )
+    })?;
+    println!("{name} -> page {page}");
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+fn cmd_links_edit(action: LinksCommand, dry_run: bool) -> anyhow::Result<()> {
+    match action {
+        LinksCommand::Rewrite {
             input,
             output,
-            stdout,
             force,
-        } => cmd_extract_text(&input, output.as_deref(), stdout, force),
-        Commands::Rotate {
+            mappings,
+        } => {
+            if dry_run {
+                check_can_write_file(&output, force)?;
+                let plan = pdfcore::rewrite_links_plan(&input, &output, &mappings)
+                    .with_context(|| format!("planning link rewrite of {}", input.display()))?;
+                print!("{plan}");
+                return Ok(());
+            }
+            ensure_can_write_file(&output, force)?;
+            let changed =
+                pdfcore::rewrite_links(&input, &output, &mappings).with_context(|| {
+                    format!(
+                        "rewriting links {} -> {}",
+                        input.display(),
+                        output.display()
+                    )
+                })?;
+            println!("rewrote {changed} link(s)");
+            eprintln!("wrote: {}", output.display());
+        }
+        LinksCommand::Strip {
             input,
             output,
             force,
-            degrees,
-            pages,
-        } => cmd_rotate(&input, &output, force, degrees, pages.as_deref()),
-        Commands::Compress {
+            external_only,
+        } => {
+            if dry_run {
+                check_can_write_file(&output, force)?;
+                let plan = pdfcore::strip_links_plan(&input, &output, external_only)
+                    .with_context(|| format!("planning link removal from {}", input.display()))?;
+                print!("{plan}");
+                return Ok(());
+            }
+            ensure_can_write_file(&output, force)?;
+            let removed =
+                pdfcore::strip_links(&input, &output, external_only).with_context(|| {
+                    format!(
+                        "stripping links {} -> {}",
+                        input.display(),
+                        output.display()
+                    )
+                })?;
+            println!("removed {removed} link(s)");
+            eprintln!("wrote: {}", output.display());
+        }
+    }
+    Ok(())
+}
+
+fn cmd_content(input: &Path, page: u32) -> anyhow::Result<()> {
+    let dump = pdfcore::content_dump(input, page)
+        .with_context(|| format!("reading page {page} content: {}", input.display()))?;
+    print!("{dump}");
+    Ok(())
+}
+
+fn cmd_qdf(input: &Path, output: &Path, force: bool, dry_run: bool) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::qdf_plan(input, output)
+            .with_context(|| format!("planning debug copy of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    let mode = pdfcore::qdf(input, output).with_context(|| {
+        format!(
+            "writing debug copy of {} -> {}",
+            input.display(),
+            output.display()
+        )
+    })?;
+    if mode == pdfcore::QdfMode::Decompressed {
+        eprintln!("note: qpdf not found; wrote decompressed copy (not full QDF)");
+    }
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+fn share_percent(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let share = part as f64 / total as f64 * 100.0;
+    share
+}
+
+fn cmd_embed_fonts(input: &Path, output: &Path, force: bool, dry_run: bool) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::embed_fonts_plan(input, output)
+            .with_context(|| format!("planning font embedding for {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    let fonts = pdfcore::embed_fonts(input, output).with_context(|| {
+        format!(
+            "embedding fonts {} -> {}",
+            input.display(),
+            output.display()
+        )
+    })?;
+    eprintln!(
+        "wrote: {} ({} font(s), all embedded)",
+        output.display(),
+        fonts.len()
+    );
+    Ok(())
+}
+
+#[allow(clippy::fn_params_excessive_bools)] // one per flag
+fn cmd_merge(
+    inputs: &[PathBuf],
+    output: &Path,
+    force: bool,
+    normalize: bool,
+    keep_going: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let merge = pdfcore::Merge::new(inputs)
+        .output(output)
+        .normalize(normalize);
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = merge.plan().with_context(|| {
+            format!(
+                "planning merge of {} file(s) into {}",
+                inputs.len(),
+                output.display()
+            )
+        })?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    let context = || format!("merging {} file(s) into {}", inputs.len(), output.display());
+    if !keep_going {
+        merge.run().with_context(context)?;
+        eprintln!("wrote: {}", output.display());
+        return Ok(());
+    }
+    let results = merge.run_each().with_context(context)?;
+    for skipped in results.iter().filter(|r| r.result.is_err()) {
+        if let Err(e) = &skipped.result {
+            eprintln!("skipped: {}: {e}", skipped.input.display());
+        }
+    }
+    if results.iter().any(|r| r.result.is_ok()) {
+        eprintln!("wrote: {}", output.display());
+    }
+    pdfcore::check_inputs(&results, inputs.len())?;
+    Ok(())
+}
+
+fn cmd_bates(
+    inputs: &[PathBuf],
+    out_dir: &Path,
+    options: &pdfcore::BatesOptions,
+    manifest: &Path,
+    force: bool,
+    json: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        let plan = pdfcore::bates_number_plan(inputs, out_dir, options)
+            .context("planning Bates numbering")?;
+        print!("{plan}");
+        println!("write: {}", manifest.display());
+        return Ok(());
+    }
+    ensure_can_write_dir(out_dir, force)?;
+    check_can_write_file(manifest, force)?;
+    let ranges = pdfcore::bates_number(inputs, out_dir, options).with_context(|| {
+        format!(
+            "stamping Bates numbers on {} file(s) into {}",
+            inputs.len(),
+            out_dir.display()
+        )
+    })?;
+    fs::write(manifest, render_bates_csv(&ranges))
+        .with_context(|| format!("writing manifest: {}", manifest.display()))?;
+
+    if json {
+        print!("{}", render_bates_json(&ranges));
+    } else {
+        for range in &ranges {
+            println!(
+                "{} - {}  {}",
+                range.first,
+                range.last,
+                range.input.display()
+            );
+        }
+    }
+    eprintln!("wrote {} file(s) to: {}", ranges.len(), out_dir.display());
+    eprintln!("wrote manifest: {}", manifest.display());
+    Ok(())
+}
+
+fn cmd_header_footer(
+    input: &Path,
+    output: &Path,
+    options: &pdfcore::HeaderFooterOptions,
+    force: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::header_footer_plan(input, output, options)
+            .context("planning headers and footers")?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    pdfcore::header_footer(input, output, options).with_context(|| {
+        format!(
+            "stamping headers and footers: {} -> {}",
+            input.display(),
+            output.display()
+        )
+    })?;
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+fn cmd_sign(
+    input: &Path,
+    output: &Path,
+    options: &pdfcore::SignOptions,
+    force: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::sign_plan(input, output, options).context("planning the signature")?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    let report = pdfcore::sign(input, output, options)
+        .with_context(|| format!("signing: {} -> {}", input.display(), output.display()))?;
+    eprintln!(
+        "signed by {} (field {}, {} signature)",
+        report.signer,
+        report.field,
+        format_bytes(report.signature_len as u64)
+    );
+    if let Some(time) = &report.timestamp {
+        eprintln!("timestamped: {time}");
+    }
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+fn cmd_signatures(
+    input: &Path,
+    options: &pdfcore::SignaturesOptions,
+    json: bool,
+) -> anyhow::Result<()> {
+    let signatures = pdfcore::signatures(input, options)
+        .with_context(|| format!("verifying signatures: {}", input.display()))?;
+    if json {
+        let items: Vec<String> = signatures.iter().map(signature_json).collect();
+        println!("[{}]", items.join(","));
+    } else if signatures.is_empty() {
+        println!("no signatures");
+    }
+    let mut failed = 0;
+    for signature in &signatures {
+        let timestamp_ok = signature.timestamp.as_ref().is_none_or(|t| t.valid);
+        if !signature.valid || !timestamp_ok {
+            failed += 1;
+        }
+        if !json {
+            print_signature(signature);
+        }
+    }
+    if failed > 0 {
+        bail!(
+            "{failed} of {} signature(s) did not verify",
+            signatures.len()
+        );
+    }
+    Ok(())
+}
+
+fn print_signature(signature: &pdfcore::SignatureInfo) {
+    let status = match &signature.problem {
+        None => "valid".to_string(),
+        Some(problem) => format!("INVALID ({problem})"),
+    };
+    println!("{}: {status}", signature.field);
+    if let Some(signer) = &signature.signer {
+        println!("  signer: {signer}");
+    }
+    if let Some(signed_at) = &signature.signed_at {
+        println!("  signed at: {signed_at}");
+    }
+    println!("  format: {}", signature.sub_filter);
+    println!(
+        "  covers whole file: {}",
+        if signature.covers_whole_file {
+            "yes"
+        } else {
+            "no (the file was changed after signing)"
+        }
+    );
+    match &signature.timestamp {
+        None => println!("  timestamp: none"),
+        Some(stamp) => match &stamp.problem {
+            None => println!("  timestamp: {} (valid)", stamp.time),
+            Some(problem) => println!("  timestamp: {} INVALID ({problem})", stamp.time),
+        },
+    }
+}
+
+fn signature_json(signature: &pdfcore::SignatureInfo) -> String {
+    let optional = |value: Option<&String>| value.map_or("null".to_string(), |v| json_string(v));
+    let ranges: Vec<String> = signature
+        .byte_range
+        .iter()
+        .map(|(offset, len)| format!("[{offset},{len}]"))
+        .collect();
+    let timestamp = signature
+        .timestamp
+        .as_ref()
+        .map_or("null".to_string(), |t| {
+            format!(
+                "{{\"time\":{},\"valid\":{},\"problem\":{}}}",
+                json_string(&t.time),
+                t.valid,
+                optional(t.problem.as_ref())
+            )
+        });
+    format!(
+        "{{\"field\":{},\"signer\":{},\"signed_at\":{},\"sub_filter\":{},\"byte_range\":[{}],\"covers_whole_file\":{},\"valid\":{},\"problem\":{},\"timestamp\":{}}}",
+        json_string(&signature.field),
+        optional(signature.signer.as_ref()),
+        optional(signature.signed_at.as_ref()),
+        json_string(&signature.sub_filter),
+        ranges.join(","),
+        signature.covers_whole_file,
+        signature.valid,
+        optional(signature.problem.as_ref()),
+        timestamp
+    )
+}
+
+fn cmd_sign_visual(
+    input: &Path,
+    output: &Path,
+    options: &pdfcore::SignVisualOptions,
+    force: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan =
+            pdfcore::sign_visual_plan(input, output, options).context("planning the signature")?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    pdfcore::sign_visual(input, output, options).with_context(|| {
+        format!(
+            "placing the signature on page {}: {} -> {}",
+            options.page,
+            input.display(),
+            output.display()
+        )
+    })?;
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+fn cmd_pick(input: &Path, output: &Path, force: bool, dry_run: bool) -> anyhow::Result<()> {
+    pdfcore::validate_input_file(input)
+        .with_context(|| format!("validating input: {}", input.display()))?;
+    check_can_write_file(output, force)?;
+    let previews = pdfcore::page_previews(input, 200)
+        .with_context(|| format!("reading the pages of {}", input.display()))?;
+    if previews.is_empty() {
+        bail!("{} has no pages", input.display());
+    }
+    let title = input
+        .file_name()
+        .unwrap_or(input.as_os_str())
+        .to_string_lossy();
+    let Some(pages) = pick::pick(&title, previews)? else {
+        eprintln!("nothing written");
+        return Ok(());
+    };
+    if dry_run {
+        let plan = pdfcore::select_pages_plan(input, output, &pages)
+            .with_context(|| format!("planning page selection from {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    pdfcore::select_pages(input, output, &pages)
+        .with_context(|| format!("writing the selected pages to {}", output.display()))?;
+    eprintln!("wrote {} page(s): {}", pages.len(), output.display());
+    Ok(())
+}
+
+fn cmd_split_pages(
+    input: &Path,
+    out_dir: &Path,
+    pattern: Option<&str>,
+    force: bool,
+    manifest: Option<&Path>,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    pdfcore::validate_input_file(input)
+        .with_context(|| format!("validating input: {}", input.display()))?;
+
+    if dry_run {
+        let plan = pdfcore::split_pages_plan(input, out_dir, pattern)
+            .with_context(|| format!("planning split of {}", input.display()))?;
+        print!("{plan}");
+        if let Some(manifest) = manifest {
+            println!("write: {}", manifest.display());
+        }
+        return Ok(());
+    }
+
+    ensure_can_write_dir(out_dir, force)?;
+    pdfcore::split_pages(input, out_dir, pattern).with_context(|| {
+        format!(
+            "splitting {} into pages under {}",
+            input.display(),
+            out_dir.display()
+        )
+    })?;
+    eprintln!("wrote pages to: {}", out_dir.display());
+    if let Some(path) = manifest {
+        let entries = pdfcore::split_pages_manifest(input, out_dir, pattern)
+            .context("checksumming the pages")?;
+        write_manifest(&entries, path)?;
+    }
+    Ok(())
+}
+
+fn cmd_split_by_text(
+    input: &Path,
+    out_dir: &Path,
+    pattern: &str,
+    force: bool,
+    manifest: Option<&Path>,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let parts = pdfcore::split_parts_by_text(input, out_dir, pattern)
+        .with_context(|| format!("reading the text of {}", input.display()))?;
+    write_split_parts(
+        input,
+        out_dir,
+        &parts,
+        Some("no match"),
+        force,
+        manifest,
+        dry_run,
+    )
+}
+
+fn cmd_split_by_separator(
+    input: &Path,
+    out_dir: &Path,
+    separator: pdfcore::Separator,
+    force: bool,
+    manifest: Option<&Path>,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let parts = pdfcore::split_parts_by_separator(input, out_dir, separator)
+        .with_context(|| format!("looking for separator pages in {}", input.display()))?;
+    if parts.is_empty() {
+        bail!("{} has only separator pages", input.display());
+    }
+    let unnamed = (separator == pdfcore::Separator::Qr).then_some("no code");
+    write_split_parts(input, out_dir, &parts, unnamed, force, manifest, dry_run)
+}
+
+fn cmd_split_by_size(
+    input: &Path,
+    out_dir: &Path,
+    max_bytes: u64,
+    force: bool,
+    manifest: Option<&Path>,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    // How many parts there will be is only known once they are written, so
+    // refuse any part left over from an earlier split, or with --force
+    // remove those the new split does not replace.
+    let existing = existing_size_parts(input, out_dir)?;
+    if !force {
+        if let Some(existing) = existing.first() {
+            bail!(
+                "output exists: {} (use --force to overwrite)",
+                existing.display()
+            );
+        }
+    }
+    if dry_run {
+        let plan = pdfcore::split_by_size_plan(input, out_dir, max_bytes)?;
+        print!("{plan}");
+        if let Some(manifest) = manifest {
+            println!("write: {}", manifest.display());
+        }
+        return Ok(());
+    }
+
+    let parts = pdfcore::split_by_size(input, out_dir, max_bytes)
+        .with_context(|| format!("splitting {}", input.display()))?;
+    for stale in existing
+        .iter()
+        .filter(|path| !parts.iter().any(|part| &part.path == *path))
+    {
+        fs::remove_file(stale)
+            .with_context(|| format!("removing old part: {}", stale.display()))?;
+    }
+    let mut entries = pdfcore::Manifest::new();
+    for part in &parts {
+        let size = fs::metadata(&part.path)
+            .with_context(|| format!("reading {}", part.path.display()))?
+            .len();
+        println!(
+            "{}: {} ({})",
+            part.path.display(),
+            page_ranges(&part.pages),
+            format_bytes(size)
+        );
+        if manifest.is_some() {
+            entries.add(&part.path, input, part.pages.clone())?;
+        }
+    }
+    eprintln!("wrote {} file(s) to: {}", parts.len(), out_dir.display());
+    if let Some(path) = manifest {
+        write_manifest(&entries, path)?;
+    }
+    Ok(())
+}
+
+/// Files in `out_dir` named like the parts [`pdfcore::split_by_size`] makes
+/// of `input`.
+fn existing_size_parts(input: &Path, out_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let Some(stem) = input.file_stem().map(|stem| stem.to_string_lossy()) else {
+        return Ok(Vec::new());
+    };
+    let prefix = format!("{stem}.part");
+    let Ok(entries) = fs::read_dir(out_dir) else {
+        return Ok(Vec::new());
+    };
+    let mut existing = Vec::new();
+    for entry in entries {
+        let entry = entry.with_context(|| format!("reading {}", out_dir.display()))?;
+        let name = entry.file_name();
+        let is_part = name
+            .to_str()
+            .and_then(|name| name.strip_prefix(&prefix))
+            .and_then(|rest| rest.strip_suffix(".pdf"))
+            .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+        if is_part {
+            existing.push(entry.path());
+        }
+    }
+    existing.sort();
+    Ok(existing)
+}
+
+/// Write the `parts` of `input` (or print the plan), each listed with the
+/// text that named it or `unnamed`.
+fn write_split_parts(
+    input: &Path,
+    out_dir: &Path,
+    parts: &[pdfcore::SplitPart],
+    unnamed: Option<&str>,
+    force: bool,
+    manifest: Option<&Path>,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    for part in parts {
+        check_can_write_file(&part.path, force)?;
+    }
+    if dry_run {
+        for part in parts {
+            let plan = pdfcore::select_pages_plan(input, &part.path, &part.pages)
+                .with_context(|| format!("planning {}", part.path.display()))?;
+            print!("{plan}");
+        }
+        if let Some(manifest) = manifest {
+            println!("write: {}", manifest.display());
+        }
+        return Ok(());
+    }
+
+    fs::create_dir_all(out_dir)
+        .with_context(|| format!("creating out dir: {}", out_dir.display()))?;
+    let mut entries = pdfcore::Manifest::new();
+    for part in parts {
+        pdfcore::select_pages(input, &part.path, &part.pages)
+            .with_context(|| format!("writing {}", part.path.display()))?;
+        match part.matched.as_deref().or(unnamed) {
+            Some(name) => println!(
+                "{}: {} ({name})",
+                part.path.display(),
+                page_ranges(&part.pages)
+            ),
+            None => println!("{}: {}", part.path.display(), page_ranges(&part.pages)),
+        }
+        if manifest.is_some() {
+            entries.add(&part.path, input, part.pages.clone())?;
+        }
+    }
+    eprintln!("wrote {} file(s) to: {}", parts.len(), out_dir.display());
+    if let Some(path) = manifest {
+        write_manifest(&entries, path)?;
+    }
+    Ok(())
+}
+
+/// `pages 1-3` or `page 4`; the parts of a split are runs of pages.
+fn page_ranges(pages: &[u32]) -> String {
+    match pages {
+        [page] => format!("page {page}"),
+        [first, .., last] => format!("pages {first}-{last}"),
+        [] => "no pages".to_string(),
+    }
+}
+
+fn write_manifest(manifest: &pdfcore::Manifest, path: &Path) -> anyhow::Result<()> {
+    manifest
+        .write(path)
+        .with_context(|| format!("writing manifest: {}", path.display()))?;
+    eprintln!(
+        "wrote manifest of {} file(s): {}",
+        manifest.entries.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn cmd_render(
+    input: &Path,
+    out_dir: &Path,
+    dpi: u32,
+    force: bool,
+    cache_dir: Option<&Path>,
+    manifest: Option<&Path>,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        let plan = pdfcore::render_plan(input, out_dir, dpi)
+            .with_context(|| format!("planning render of {}", input.display()))?;
+        print!("{plan}");
+        if let Some(manifest) = manifest {
+            println!("write: {}", manifest.display());
+        }
+        return Ok(());
+    }
+    pdfcore::validate_input_file(input)
+        .with_context(|| format!("validating input: {}", input.display()))?;
+    ensure_can_write_dir(out_dir, force)?;
+    let context = || {
+        format!(
+            "rendering {} into {} at {dpi} dpi",
+            input.display(),
+            out_dir.display()
+        )
+    };
+    if let Some(cache_dir) = cache_dir {
+        let cache = open_cache(cache_dir)?;
+        let key = render_cache_key(input, &format!("render dpi={dpi}"))?;
+        let entry = cache
+            .get_or_insert_with(&key, |dir| pdfcore::render(input, dir, dpi))
+            .with_context(context)?;
+        for file in fs::read_dir(&entry.dir)? {
+            let file = file?;
+            fs::copy(file.path(), out_dir.join(file.file_name()))
+                .with_context(|| format!("copying {} from the cache", file.path().display()))?;
+        }
+        let source = if entry.hit { " (from cache)" } else { "" };
+        eprintln!("wrote images to: {}{source}", out_dir.display());
+    } else {
+        pdfcore::render(input, out_dir, dpi).with_context(context)?;
+        eprintln!("wrote images to: {}", out_dir.display());
+    }
+    if let Some(path) = manifest {
+        let entries =
+            pdfcore::render_manifest(input, out_dir).context("checksumming the images")?;
+        write_manifest(&entries, path)?;
+    }
+    Ok(())
+}
+
+fn cmd_to_ps(
+    input: &Path,
+    output: &Path,
+    force: bool,
+    pages: Option<&str>,
+    eps: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let pages = pages
+        .map(parse_page_selection)
+        .transpose()
+        .context("parsing --pages")?
+        .unwrap_or(pdfcore::PageSelection::All);
+    let options = pdfcore::ToPsOptions { pages, eps };
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::to_ps_plan(input, output, &options)
+            .with_context(|| format!("planning PostScript export of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    pdfcore::to_ps(input, output, &options)
+        .with_context(|| format!("converting {} -> {}", input.display(), output.display()))?;
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+fn cmd_from_ps(
+    input: &Path,
+    output: &Path,
+    force: bool,
+    preset: pdfcore::CompressPreset,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::from_ps_plan(input, output, preset)
+            .with_context(|| format!("planning conversion of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    pdfcore::from_ps(input, output, preset)
+        .with_context(|| format!("converting {} -> {}", input.display(), output.display()))?;
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+/// Run `op` on each PDF in the ZIP archive or directory `input`, writing an
+/// archive or directory of the same shape to `output`.
+#[allow(clippy::fn_params_excessive_bools, clippy::too_many_arguments)] // one per flag
+fn cmd_each_pdf(
+    input: &Path,
+    output: &Path,
+    operation: &str,
+    force: bool,
+    json: bool,
+    keep_going: bool,
+    dry_run: bool,
+    op: impl FnMut(&Path, &Path) -> pdfcore::Result<()>,
+) -> anyhow::Result<()> {
+    if json {
+        bail!("--json takes a single PDF, not an archive or directory");
+    }
+    if dry_run {
+        if !input.is_dir() {
+            check_can_write_file(output, force)?;
+        }
+        let plan = pdfcore::map_pdfs_plan(input, output, operation)
+            .with_context(|| format!("planning {operation} of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    if input.is_dir() {
+        ensure_can_write_dir(output, force)?;
+    } else {
+        ensure_can_write_file(output, force)?;
+    }
+    let results = pdfcore::map_pdfs(input, output, keep_going, op)
+        .with_context(|| format!("processing {}", input.display()))?;
+    let total = results.len();
+    let mut failures = results
+        .into_iter()
+        .filter_map(|r| r.result.err().map(|e| (r.input, e)))
+        .collect::<Vec<_>>();
+    // Without --keep-going the run stopped at its only failure.
+    if !keep_going {
+        if let Some((input, e)) = failures.pop() {
+            return Err(anyhow::Error::from(e).context(format!("{operation} {}", input.display())));
+        }
+    }
+    for (input, e) in &failures {
+        eprintln!("kept unchanged: {}: {e}", input.display());
+    }
+    eprintln!("wrote: {}", output.display());
+    match failures.len() {
+        0 => Ok(()),
+        failed => Err(pdfcore::PdfError::InputsFailed { failed, total }.into()),
+    }
+}
+
+fn cmd_from_tiff(input: &Path, output: &Path, force: bool, dry_run: bool) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::from_tiff_plan(input, output)
+            .with_context(|| format!("planning conversion of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    let pages = pdfcore::from_tiff(input, output)
+        .with_context(|| format!("converting {} -> {}", input.display(), output.display()))?;
+    println!("pages: {pages}");
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+fn cmd_email_attachments(input: &Path, json: bool) -> anyhow::Result<()> {
+    let attachments = pdfcore::email_attachments(input)
+        .with_context(|| format!("reading attachments: {}", input.display()))?;
+    if json {
+        let items: Vec<String> = attachments
+            .iter()
+            .map(|a| {
+                format!(
+                    "  {{\"name\": {}, \"content_type\": {}, \"size\": {}, \"pdf\": {}}}",
+                    json_string(&a.name),
+                    json_string(&a.content_type),
+                    a.size,
+                    a.pdf
+                )
+            })
+            .collect();
+        if items.is_empty() {
+            println!("[]");
+        } else {
+            println!("[\n{}\n]", items.join(",\n"));
+        }
+        return Ok(());
+    }
+    if attachments.is_empty() {
+        println!("no attachments found");
+        return Ok(());
+    }
+    println!("{:<40} {:<30} {:>10}  pdf", "name", "type", "size");
+    for a in &attachments {
+        println!(
+            "{:<40} {:<30} {:>10}  {}",
+            a.name,
+            a.content_type,
+            a.size,
+            if a.pdf { "yes" } else { "no" }
+        );
+    }
+    Ok(())
+}
+
+fn cmd_extract_email_pdfs(
+    input: &Path,
+    out_dir: &Path,
+    force: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let plan = pdfcore::extract_email_pdfs_plan(input, out_dir)
+        .with_context(|| format!("planning extraction from {}", input.display()))?;
+    // The directory may hold other files; only the ones written must be new.
+    for step in &plan.steps {
+        if let pdfcore::PlanStep::Write(path) = step {
+            check_can_write_file(path, force)?;
+        }
+    }
+    if dry_run {
+        print!("{plan}");
+        return Ok(());
+    }
+    fs::create_dir_all(out_dir)
+        .with_context(|| format!("creating out dir: {}", out_dir.display()))?;
+    let written = pdfcore::extract_email_pdfs(input, out_dir)
+        .with_context(|| format!("extracting PDFs from {}", input.display()))?;
+    if written.is_empty() {
+        println!("no PDF attachments found");
+    }
+    for path in &written {
+        eprintln!("wrote: {}", path.display());
+    }
+    Ok(())
+}
+
+fn cmd_merge_email_pdfs(
+    input: &Path,
+    output: &Path,
+    force: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::merge_email_pdfs_plan(input, output)
+            .with_context(|| format!("planning merge of the PDFs in {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    let merged = pdfcore::merge_email_pdfs(input, output).with_context(|| {
+        format!(
+            "merging the PDFs in {} -> {}",
+            input.display(),
+            output.display()
+        )
+    })?;
+    println!("merged: {merged}");
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+fn cmd_to_tiff(
+    input: &Path,
+    output: &Path,
+    force: bool,
+    options: pdfcore::ToTiffOptions,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::to_tiff_plan(input, output, &options)
+            .with_context(|| format!("planning conversion of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    pdfcore::to_tiff(input, output, &options)
+        .with_context(|| format!("converting {} -> {}", input.display(), output.display()))?;
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+fn cmd_print(
+    input: &Path,
+    printer: Option<String>,
+    copies: u32,
+    pages: Option<&str>,
+    duplex: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let pages = pages
+        .map(parse_page_selection)
+        .transpose()
+        .context("parsing --pages")?
+        .unwrap_or(pdfcore::PageSelection::All);
+    let options = pdfcore::PrintOptions {
+        printer,
+        copies,
+        pages,
+        duplex,
+    };
+    if dry_run {
+        let plan = pdfcore::print_plan(input, &options)
+            .with_context(|| format!("planning printing of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    let job =
+        pdfcore::print(input, &options).with_context(|| format!("printing {}", input.display()))?;
+    match job {
+        Some(job) => eprintln!("printed: {} (job {job})", input.display()),
+        None => eprintln!("printed: {}", input.display()),
+    }
+    Ok(())
+}
+
+fn cmd_to_svg(
+    input: &Path,
+    page: u32,
+    output: &Path,
+    force: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::to_svg_plan(input, output, page)
+            .with_context(|| format!("planning SVG export of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    pdfcore::to_svg(input, output, page).with_context(|| {
+        format!(
+            "exporting page {page} of {} -> {}",
+            input.display(),
+            output.display()
+        )
+    })?;
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments, clippy::fn_params_excessive_bools)] // one per flag
+fn cmd_thumbnail(
+    files: &[PathBuf],
+    out_dir: &Path,
+    dpi: u32,
+    cache_dir: Option<&Path>,
+    force: bool,
+    manifest: Option<&Path>,
+    keep_going: bool,
+    tui: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let output_for = |input: &Path| {
+        let stem = input.file_stem().unwrap_or(input.as_os_str());
+        out_dir.join(Path::new(stem).with_extension("png"))
+    };
+    if dry_run {
+        for input in files {
+            let plan = pdfcore::thumbnail_plan(input, output_for(input), dpi)
+                .with_context(|| format!("planning thumbnail of {}", input.display()))?;
+            print!("{plan}");
+        }
+        if let Some(manifest) = manifest {
+            println!("write: {}", manifest.display());
+        }
+        return Ok(());
+    }
+    fs::create_dir_all(out_dir)
+        .with_context(|| format!("creating out dir: {}", out_dir.display()))?;
+    let cache = cache_dir.map(open_cache).transpose()?;
+
+    let (dashboard, progress) = dashboard::start_if(tui, "thumbnail", 1)?;
+    for input in files {
+        progress.queued(input);
+    }
+    let mut entries = pdfcore::Manifest::new();
+    let mut failed = 0;
+    let mut first_error = None;
+    for input in files {
+        let output = output_for(input);
+        progress.started(input, 0);
+        let start = Instant::now();
+        let result = match &cache {
+            Some(cache) => cached_thumbnail(cache, input, &output, dpi, force),
+            None => check_can_write_file(&output, force).and_then(|()| {
+                pdfcore::thumbnail(input, &output, dpi)?;
+                Ok("rendered")
+            }),
+        };
+        let result = result.and_then(|status| {
+            if manifest.is_some() {
+                entries.add(&output, input, vec![1])?;
+            }
+            Ok(status)
+        });
+        progress.done(input, 0, start.elapsed(), &result);
+        match result {
+            Ok(_) if dashboard.is_some() => {}
+            Ok(status) => println!("{}: {status}", output.display()),
+            // Every other file would fail the same way.
+            Err(e)
+                if matches!(
+                    e.downcast_ref::<pdfcore::PdfError>(),
+                    Some(pdfcore::PdfError::MissingTool { .. })
+                ) =>
+            {
+                return Err(e);
+            }
+            Err(e) if !keep_going => {
+                first_error = Some(e.context(format!("thumbnailing {}", input.display())));
+                break;
+            }
+            Err(e) => {
+                failed += 1;
+                if dashboard.is_none() {
+                    eprintln!("error: {}: {e:#}", input.display());
+                }
+            }
+        }
+    }
+    drop(dashboard);
+    if let Some(path) = manifest {
+        write_manifest(&entries, path)?;
+    }
+    if let Some(first) = first_error {
+        return Err(first);
+    }
+    if failed > 0 {
+        return Err(pdfcore::PdfError::InputsFailed {
+            failed,
+            total: files.len(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Write the thumbnail of `input` to `output` from the cache, rendering it
+/// into the cache first if needed. An existing `output` that already matches
+/// is left alone.
+fn cached_thumbnail(
+    cache: &pdfcore::Cache,
+    input: &Path,
+    output: &Path,
+    dpi: u32,
+    force: bool,
+) -> anyhow::Result<&'static str> {
+    let key = render_cache_key(input, &format!("thumbnail dpi={dpi}"))?;
+    let entry = cache.get_or_insert_with(&key, |dir| {
+        pdfcore::thumbnail(input, dir.join("thumbnail.png"), dpi)
+    })?;
+    let cached = entry.dir.join("thumbnail.png");
+    if output.exists() && fs::read(output).ok() == Some(fs::read(&cached)?) {
+        return Ok("unchanged");
+    }
+    check_can_write_file(output, force)?;
+    fs::copy(&cached, output)?;
+    Ok(if entry.hit { "from cache" } else { "rendered" })
+}
+
+fn open_cache(dir: &Path) -> anyhow::Result<pdfcore::Cache> {
+    pdfcore::Cache::open(dir).with_context(|| format!("opening cache: {}", dir.display()))
+}
+
+/// Cache key for rendered images: the engine is part of it, since engines
+/// draw differently.
+fn render_cache_key(input: &Path, params: &str) -> anyhow::Result<String> {
+    let engine = pdfcore::select_engine(pdfcore::Operation::Render)?;
+    pdfcore::Cache::key(input, &format!("{params} engine={engine}"))
+        .with_context(|| format!("hashing {}", input.display()))
+}
+
+fn cmd_extract_text(
+    input: &Path,
+    output: Option<&Path>,
+    stdout: bool,
+    force: bool,
+    text_output: pdfcore::TextOutput,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    warn_if_xfa(input);
+    let extract = pdfcore::ExtractText::new(input).text_output(text_output);
+    if dry_run {
+        let extract = if stdout {
+            extract
+        } else {
+            let out = output.context("either -o/--output or --stdout is required")?;
+            check_can_write_file(out, force)?;
+            extract.output(out)
+        };
+        let plan = extract
+            .plan()
+            .with_context(|| format!("planning text extraction from {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    if stdout {
+        let text = pdfcore::ExtractText::new(input)
+            .run()
+            .with_context(|| format!("extracting text from {}", input.display()))?;
+        let mut w = io::stdout().lock();
+        w.write_all(&text_output.encode(&text)?)?;
+        return Ok(());
+    }
+
+    let out = output.context("either -o/--output or --stdout is required")?;
+    ensure_can_write_file(out, force)?;
+    extract.output(out).run().with_context(|| {
+        format!(
+            "extracting text from {} into {}",
+            input.display(),
+            out.display()
+        )
+    })?;
+    eprintln!("wrote: {}", out.display());
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments, clippy::fn_params_excessive_bools)] // one per flag
+fn cmd_extract_text_records(
+    files: &[PathBuf],
+    format: RecordFormat,
+    per: TextRecords,
+    output: Option<&Path>,
+    force: bool,
+    text_output: pdfcore::TextOutput,
+    keep_going: bool,
+    tui: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if let Some(out) = output {
+        check_can_write_file(out, force)?;
+    }
+    if dry_run {
+        for input in files {
+            let plan = pdfcore::extract_text_plan(input, None::<&Path>)
+                .with_context(|| format!("planning text extraction from {}", input.display()))?;
+            print!("{plan}");
+        }
+        if let Some(out) = output {
+            println!("write: {}", out.display());
+        }
+        return Ok(());
+    }
+
+    let (dashboard, progress) = dashboard::start_if(tui, "extract-text", 1)?;
+    let results = pdfcore::for_each_input_with_progress(files, keep_going, &progress, |input| {
+        pdfcore::extract_text_pages(input, &pdfcore::TextPagesOptions::default())
+    });
+    drop(dashboard);
+    let mut records = Vec::new();
+    let mut unreadable = 0;
+    let mut first_error = None;
+    for read in results {
+        let path = read.input.display().to_string();
+        match read.result {
+            Ok(pages) => match per {
+                TextRecords::Page => {
+                    for page in pages {
+                        records.push(TextRecord {
+                            path: path.clone(),
+                            page: Some(page.page),
+                            pages: None,
+                            text: Ok(page.text),
+                        });
+                    }
+                }
+                TextRecords::File => records.push(TextRecord {
+                    path,
+                    page: None,
+                    pages: Some(pages.len()),
+                    text: Ok(pages.iter().fold(String::new(), |mut text, page| {
+                        text.push_str(&page.text);
+                        text.push('\x0c');
+                        text
+                    })),
+                }),
+            },
+            Err(e) => {
+                unreadable += 1;
+                records.push(TextRecord {
+                    path: path.clone(),
+                    page: None,
+                    pages: None,
+                    text: Err(e.to_string()),
+                });
+                first_error.get_or_insert_with(|| {
+                    anyhow::Error::from(e).context(format!("extracting text from {path}"))
+                });
+            }
+        }
+    }
+
+    let out = match format {
+        RecordFormat::Csv => render_text_csv(&records, per),
+        RecordFormat::Jsonl => render_text_jsonl(&records, per),
+    };
+    let out = text_output.encode(&out)?;
+    match output {
+        Some(path) => {
+            ensure_can_write_file(path, force)?;
+            fs::write(path, out).with_context(|| format!("writing {}", path.display()))?;
+            eprintln!("wrote: {}", path.display());
+        }
+        None => io::stdout().lock().write_all(&out)?,
+    }
+    match first_error {
+        Some(first) => Err(batch_error(first, unreadable, files.len(), keep_going)),
+        None => Ok(()),
+    }
+}
+
+/// One record of `extract-text --format`: a page, or a whole file.
+struct TextRecord {
+    path: String,
+    /// Page number, for per-page records.
+    page: Option<u32>,
+    /// Page count, for per-file records.
+    pages: Option<usize>,
+    /// The text, or why the file could not be read.
+    text: Result<String, String>,
+}
+
+fn render_text_csv(records: &[TextRecord], per: TextRecords) -> String {
+    let mut out = String::from(match per {
+        TextRecords::Page => "path,page,text,error\n",
+        TextRecords::File => "path,pages,text,error\n",
+    });
+    for record in records {
+        let number = match per {
+            TextRecords::Page => record.page.map(|n| n.to_string()),
+            TextRecords::File => record.pages.map(|n| n.to_string()),
+        };
+        let (text, error) = match &record.text {
+            Ok(text) => (text.as_str(), ""),
+            Err(e) => ("", e.as_str()),
+        };
+        let fields = [
+            csv_field(&record.path),
+            number.unwrap_or_default(),
+            csv_field(text),
+            csv_field(error),
+        ];
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn render_text_jsonl(records: &[TextRecord], per: TextRecords) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::new();
+    for record in records {
+        let (key, number) = match per {
+            TextRecords::Page => ("page", record.page.map(|n| n.to_string())),
+            TextRecords::File => ("pages", record.pages.map(|n| n.to_string())),
+        };
+        let (text, error) = match &record.text {
+            Ok(text) => (json_string(text), "null".to_string()),
+            Err(e) => ("null".to_string(), json_string(e)),
+        };
+        let _ = writeln!(
+            &mut out,
+            "{{\"path\":{},\"{key}\":{},\"text\":{text},\"error\":{error}}}",
+            json_string(&record.path),
+            number.as_deref().unwrap_or("null"),
+        );
+    }
+    out
+}
+
+fn cmd_from_text(
+    input: &Path,
+    output: &Path,
+    force: bool,
+    options: &pdfcore::TextToPdfOptions,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::text_to_pdf_plan(input, output, options)
+            .with_context(|| format!("planning PDF of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    let pages = pdfcore::text_to_pdf(input, output, options)
+        .with_context(|| format!("setting {} as PDF -> {}", input.display(), output.display()))?;
+    println!("pages: {pages}");
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+fn cmd_from_html(
+    input: &Path,
+    output: &Path,
+    force: bool,
+    options: &pdfcore::HtmlToPdfOptions,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::html_to_pdf_plan(input, output, options)
+            .with_context(|| format!("planning PDF of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    pdfcore::html_to_pdf(input, output, options)
+        .with_context(|| format!("converting {} -> {}", input.display(), output.display()))?;
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+fn cmd_convert(input: &Path, output: &Path, force: bool, dry_run: bool) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::office_to_pdf_plan(input, output)
+            .with_context(|| format!("planning PDF of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    pdfcore::office_to_pdf(input, output)
+        .with_context(|| format!("converting {} -> {}", input.display(), output.display()))?;
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+fn cmd_extract_tables(
+    input: &Path,
+    out_dir: &Path,
+    format: TableFormat,
+    force: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        let plan = pdfcore::extract_tables_plan(input, out_dir)
+            .with_context(|| format!("planning table extraction from {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    let tables = pdfcore::extract_tables(input)
+        .with_context(|| format!("extracting tables from {}", input.display()))?;
+    if tables.is_empty() {
+        eprintln!("no ruled tables found in {}", input.display());
+        return Ok(());
+    }
+    ensure_can_write_dir(out_dir, force)?;
+
+    let mut on_page = (0, 0);
+    for table in &tables {
+        on_page = if on_page.0 == table.page {
+            (table.page, on_page.1 + 1)
+        } else {
+            (table.page, 1)
+        };
+        let (content, extension) = match format {
+            TableFormat::Csv => (render_table_csv(table), "csv"),
+            TableFormat::Json => (render_table_json(table), "json"),
+        };
+        let path = out_dir.join(format!(
+            "page-{}-table-{}.{extension}",
+            table.page, on_page.1
+        ));
+        fs::write(&path, content).with_context(|| format!("writing {}", path.display()))?;
+        println!(
+            "{}: page {}, {} row(s) x {} column(s)",
+            path.display(),
+            table.page,
+            table.rows.len(),
+            table.rows.first().map_or(0, Vec::len)
+        );
+    }
+    eprintln!("wrote {} table(s) to: {}", tables.len(), out_dir.display());
+    Ok(())
+}
+
+fn render_table_csv(table: &pdfcore::Table) -> String {
+    let mut out = String::new();
+    for row in &table.rows {
+        let cells: Vec<String> = row.iter().map(|cell| csv_field(cell)).collect();
+        out.push_str(&cells.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn render_table_json(table: &pdfcore::Table) -> String {
+    let rows: Vec<String> = table
+        .rows
+        .iter()
+        .map(|row| {
+            let cells: Vec<String> = row.iter().map(|cell| json_string(cell)).collect();
+            format!("    [{}]", cells.join(", "))
+        })
+        .collect();
+    let b = table.bbox;
+    format!(
+        "{{\n  \"page\": {},\n  \"bbox\": [{}, {}, {}, {}],\n  \"rows\": [\n{}\n  ]\n}}\n",
+        table.page,
+        b.llx,
+        b.lly,
+        b.urx,
+        b.ury,
+        rows.join(",\n")
+    )
+}
+
+/// Dynamic XFA pages are placeholders, so page-based tools miss the form.
+fn warn_if_xfa(input: &Path) {
+    if let Ok(Some(xfa)) = pdfcore::xfa_form(input) {
+        eprintln!(
+            "warning: {} contains a {xfa} XFA form; its fields are not part of the page content \
+             (use `pdfcli xfa dump` to extract the form XML)",
+            input.display()
+        );
+    }
+}
+
+fn cmd_xfa_dump(
+    input: &Path,
+    output: Option<&Path>,
+    force: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        if let Some(output) = output {
+            check_can_write_file(output, force)?;
+        }
+        let plan = pdfcore::xfa_dump_plan(input, output)
+            .with_context(|| format!("planning XFA dump of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    if let Some(output) = output {
+        ensure_can_write_file(output, force)?;
+    }
+    let xml =
+        pdfcore::xfa_xml(input).with_context(|| format!("reading XFA from {}", input.display()))?;
+    match output {
+        Some(output) => {
+            fs::write(output, &xml).with_context(|| format!("writing {}", output.display()))?;
+            eprintln!("wrote: {}", output.display());
+        }
+        None => io::stdout().lock().write_all(&xml)?,
+    }
+    Ok(())
+}
+
+fn cmd_rotate(
+    input: &Path,
+    output: &Path,
+    force: bool,
+    degrees: RotateDegrees,
+    pages: Option<&str>,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let sel = pages
+        .map(parse_page_selection)
+        .transpose()
+        .context("parsing --pages")?;
+    let Some(degrees) = degrees.as_u16() else {
+        return cmd_auto_rotate(
             input,
             output,
             force,
-            preset,
-        } => cmd_compress(&input, &output, force, preset),
+            &sel.unwrap_or(pdfcore::PageSelection::All),
+            dry_run,
+        );
+    };
+    let rotate = pdfcore::Rotate::new(input)
+        .degrees(degrees)
+        .pages(sel.unwrap_or(pdfcore::PageSelection::All))
+        .output(output);
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = rotate
+            .plan()
+            .with_context(|| format!("planning rotation of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+
+    rotate
+        .run()
+        .with_context(|| format!("rotating {} -> {}", input.display(), output.display()))?;
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+fn cmd_rotate_spec(
+    input: &Path,
+    output: &Path,
+    force: bool,
+    spec: &str,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let spec = parse_rotation_spec(spec).context("parsing --spec")?;
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::rotate_spec_plan(input, output, &spec)
+            .with_context(|| format!("planning rotation of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+
+    pdfcore::rotate_spec(input, output, &spec)
+        .with_context(|| format!("rotating {} -> {}", input.display(), output.display()))?;
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+fn cmd_auto_rotate(
+    input: &Path,
+    output: &Path,
+    force: bool,
+    pages: &pdfcore::PageSelection,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::auto_rotate_plan(input, output, pages)
+            .with_context(|| format!("planning rotation of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+
+    let found = pdfcore::auto_rotate(input, output, pages)
+        .with_context(|| format!("rotating {} -> {}", input.display(), output.display()))?;
+    for page in &found {
+        match page.upright {
+            Some(upright) if page.needs_rotation() => eprintln!(
+                "page {}: rotated from {} to {} degrees",
+                page.page, page.rotation, upright
+            ),
+            Some(_) => {}
+            None => eprintln!("page {}: no text to detect orientation from", page.page),
+        }
+    }
+    let rotated = found.iter().filter(|p| p.needs_rotation()).count();
+    eprintln!("wrote: {} ({rotated} page(s) rotated)", output.display());
+    Ok(())
+}
+
+fn cmd_compress(
+    input: &Path,
+    output: &Path,
+    force: bool,
+    preset: pdfcore::CompressPreset,
+    json: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let compress = pdfcore::Compress::new(input).preset(preset).output(output);
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = compress
+            .plan()
+            .with_context(|| format!("planning compression of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    let report = compress.run().with_context(|| {
+        format!(
+            "compressing {} -> {} (preset: {:?})",
+            input.display(),
+            output.display(),
+            preset
+        )
+    })?;
+
+    if json {
+        print!("{}", render_compress_report_json(output, &report));
+    } else {
+        eprintln!(
+            "wrote: {} ({})",
+            output.display(),
+            describe_compress_report(&report)
+        );
+    }
+    Ok(())
+}
+
+fn cmd_optimize(
+    input: &Path,
+    output: &Path,
+    options: &pdfcore::OptimizeOptions,
+    force: bool,
+    json: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::optimize_plan(input, output, options)
+            .with_context(|| format!("planning optimization of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    let report = pdfcore::optimize(input, output, options)
+        .with_context(|| format!("optimizing {} -> {}", input.display(), output.display()))?;
+    if json {
+        print!("{}", render_optimize_report_json(output, &report));
+    } else {
+        if options.dedupe {
+            println!(
+                "duplicates removed: {} object(s)",
+                report.duplicates_removed
+            );
+        }
+        if options.gc {
+            println!(
+                "unreferenced objects removed: {} ({})",
+                report.unreferenced_removed,
+                format_bytes(report.unreferenced_bytes)
+            );
+        }
+        eprintln!(
+            "wrote: {} ({})",
+            output.display(),
+            describe_compress_report(&report.sizes)
+        );
+    }
+    Ok(())
+}
+
+fn cmd_recompress_images(
+    input: &Path,
+    output: &Path,
+    options: &pdfcore::RecompressOptions,
+    force: bool,
+    json: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::recompress_images_plan(input, output, options)
+            .with_context(|| format!("planning image recompression of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    let report = pdfcore::recompress_images(input, output, options).with_context(|| {
+        format!(
+            "recompressing images of {} -> {}",
+            input.display(),
+            output.display()
+        )
+    })?;
+    if json {
+        print!("{}", render_recompress_report_json(output, &report));
+    } else {
+        println!(
+            "images recompressed: {} ({} downsampled), unchanged: {}",
+            report.recompressed, report.downsampled, report.unchanged
+        );
+        eprintln!(
+            "wrote: {} ({})",
+            output.display(),
+            describe_compress_report(&report.sizes)
+        );
+    }
+    Ok(())
+}
+
+fn cmd_transcode_images(
+    input: &Path,
+    output: &Path,
+    options: &pdfcore::TranscodeOptions,
+    force: bool,
+    json: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::transcode_images_plan(input, output, options)
+            .with_context(|| format!("planning image transcoding of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    let report = pdfcore::transcode_images(input, output, options).with_context(|| {
+        format!(
+            "transcoding images of {} -> {}",
+            input.display(),
+            output.display()
+        )
+    })?;
+    if json {
+        let items: Vec<String> = report
+            .images
+            .iter()
+            .map(|image| {
+                format!(
+                    "    {{\"object\": {}, \"codec\": \"{}\", \"width\": {}, \"height\": {}, \"transcoded\": {}, \"skipped\": {}}}",
+                    image.object,
+                    image.codec,
+                    image.width,
+                    image.height,
+                    image.skipped.is_none(),
+                    image.skipped.as_deref().map_or("null".to_string(), json_string)
+                )
+            })
+            .collect();
+        println!(
+            "{{\n  \"output\": {},\n  \"input_bytes\": {},\n  \"output_bytes\": {},\n  \"images\": [\n{}\n  ]\n}}",
+            json_string(&output.to_string_lossy()),
+            report.sizes.input_bytes,
+            report.sizes.output_bytes,
+            items.join(",\n")
+        );
+        return Ok(());
+    }
+    if report.images.is_empty() {
+        println!("no images to transcode");
+    }
+    for image in &report.images {
+        let pixels = format!("{}x{}", image.width, image.height);
+        match &image.skipped {
+            None => println!(
+                "object {:>6}  {:<5}  {pixels:>11}  transcoded",
+                image.object, image.codec
+            ),
+            Some(reason) => println!(
+                "object {:>6}  {:<5}  {pixels:>11}  skipped: {reason}",
+                image.object, image.codec
+            ),
+        }
+    }
+    eprintln!(
+        "wrote: {} ({})",
+        output.display(),
+        describe_compress_report(&report.sizes)
+    );
+    Ok(())
+}
+
+fn cmd_run(
+    steps: &str,
+    input: &Path,
+    output: &Path,
+    force: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let pipeline: pdfcore::Pipeline = steps.parse().context("parsing pipeline")?;
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pipeline
+            .plan(input, output)
+            .with_context(|| format!("planning pipeline for {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+
+    pipeline.run(input, output).with_context(|| {
+        format!(
+            "running '{pipeline}' on {} -> {}",
+            input.display(),
+            output.display()
+        )
+    })?;
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+fn cmd_convert_color(
+    input: &Path,
+    output: &Path,
+    force: bool,
+    to: ColorSpaceCli,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::convert_colorspace_plan(input, output, to.into())
+            .with_context(|| format!("planning color conversion of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    pdfcore::convert_colorspace(input, output, to.into()).with_context(|| {
+        format!(
+            "converting {} -> {} (to: {:?})",
+            input.display(),
+            output.display(),
+            to
+        )
+    })?;
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+fn cmd_court_ready(
+    input: &Path,
+    output: &Path,
+    force: bool,
+    options: &pdfcore::CourtReadyOptions,
+    json: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::court_ready_plan(input, output, options)
+            .with_context(|| format!("planning court filing prep of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    let report = pdfcore::court_ready(input, output, options).with_context(|| {
+        format!(
+            "preparing {} for court filing -> {}",
+            input.display(),
+            output.display()
+        )
+    })?;
+
+    if json {
+        print!("{}", render_court_ready_json(output, &report));
+    } else {
+        println!(
+            "size: {} → {} (preset: {})",
+            format_bytes(report.input_bytes),
+            format_bytes(report.output_bytes),
+            report.preset
+        );
+        println!("javascript removed: {}", report.javascript_removed);
+        print_checks(&report.checks);
+    }
+    eprintln!("wrote: {}", output.display());
+
+    if !report.is_compliant() {
+        let failed = report.checks.iter().filter(|c| !c.passed).count();
+        bail!("output is not court-ready: {failed} check(s) failed");
+    }
+    Ok(())
+}
+
+fn cmd_print_ready(
+    input: &Path,
+    output: &Path,
+    force: bool,
+    options: &pdfcore::PrintReadyOptions,
+    json: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::print_ready_plan(input, output, options)
+            .with_context(|| format!("planning print prep of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    let report = pdfcore::print_ready(input, output, options).with_context(|| {
+        format!(
+            "preparing {} for print -> {}",
+            input.display(),
+            output.display()
+        )
+    })?;
+
+    if json {
+        print!(
+            "{}",
+            render_checks_json(output, report.pages, report.is_compliant(), &report.checks)
+        );
+    } else {
+        println!("pages: {}", report.pages);
+        print_checks(&report.checks);
+    }
+    eprintln!("wrote: {}", output.display());
+
+    if !report.is_compliant() {
+        let failed = report.checks.iter().filter(|c| !c.passed).count();
+        bail!("preflight failed: {failed} check(s) failed");
+    }
+    Ok(())
+}
+
+fn cmd_bleed(
+    input: &Path,
+    output: &Path,
+    force: bool,
+    options: &pdfcore::BleedOptions,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::add_bleed_plan(input, output, options).context("planning bleed")?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    pdfcore::add_bleed(input, output, options)
+        .with_context(|| format!("adding bleed: {} -> {}", input.display(), output.display()))?;
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+fn cmd_to_pdfx(
+    input: &Path,
+    output: &Path,
+    force: bool,
+    options: &pdfcore::PdfxOptions,
+    json: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::to_pdfx_plan(input, output, options).with_context(|| {
+            format!(
+                "planning {} conversion of {}",
+                options.standard,
+                input.display()
+            )
+        })?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    let report = pdfcore::to_pdfx(input, output, options).with_context(|| {
+        format!(
+            "converting {} to {} -> {}",
+            input.display(),
+            options.standard,
+            output.display()
+        )
+    })?;
+
+    if json {
+        print!(
+            "{}",
+            render_checks_json(output, report.pages, report.is_compliant(), &report.checks)
+        );
+    } else {
+        println!("pages: {}", report.pages);
+        print_checks(&report.checks);
     }
+    eprintln!("wrote: {}", output.display());
+
+    if !report.is_compliant() {
+        let failed = report.checks.iter().filter(|c| !c.passed).count();
+        bail!("preflight failed: {failed} check(s) failed");
+    }
+    Ok(())
 }
 
-fn cmd_info(input: &Path, json: bool) -> anyhow::Result<()> {
-    let info =
-        pdfcore::info(input).with_context(|| format!("reading pdf info: {}", input.display()))?;
+fn cmd_a11y_prep(
+    input: &Path,
+    output: &Path,
+    force: bool,
+    options: &pdfcore::A11yPrepOptions,
+    json: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::a11y_prep_plan(input, output, options)
+            .with_context(|| format!("planning accessibility prep of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    let report = pdfcore::a11y_prep(input, output, options).with_context(|| {
+        format!(
+            "preparing {} for accessibility remediation -> {}",
+            input.display(),
+            output.display()
+        )
+    })?;
 
     if json {
-        let out = render_info_json(&info);
-        print!("{out}");
+        print!("{}", render_a11y_prep_json(output, &report));
     } else {
-        println!("pages: {}", info.pages);
-        if !info.metadata.is_empty() {
-            println!("metadata:");
-            for (k, v) in info.metadata {
-                println!("  {k}: {v}");
+        println!("actions:");
+        for action in &report.actions {
+            println!("  - {action}");
+        }
+        if report.issues.is_empty() {
+            println!("issues: none");
+        } else {
+            println!("issues:");
+            for issue in &report.issues {
+                println!("  - {issue}");
             }
         }
     }
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
 
+#[allow(clippy::fn_params_excessive_bools)] // one per flag
+fn cmd_infer_title(
+    input: &Path,
+    output: Option<&Path>,
+    replace: bool,
+    force: bool,
+    json: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if let Some(output) = output {
+        if dry_run {
+            check_can_write_file(output, force)?;
+        } else {
+            ensure_can_write_file(output, force)?;
+        }
+    }
+    let inferred = pdfcore::infer_title(input)
+        .with_context(|| format!("inferring title: {}", input.display()))?
+        .with_context(|| format!("no title found: page 1 of {} has no text", input.display()))?;
+    if let Some(output) = output {
+        if let (Some(current), false) = (&inferred.current, replace) {
+            anyhow::bail!(
+                "{} already has the title {current:?}; pass --replace to overwrite it",
+                input.display()
+            );
+        }
+        if dry_run {
+            let plan = pdfcore::set_document_title_plan(input, output, &inferred.title)
+                .with_context(|| format!("planning title of {}", input.display()))?;
+            print!("{plan}");
+            return Ok(());
+        }
+        pdfcore::set_document_title(input, output, &inferred.title).with_context(|| {
+            format!("setting title {} -> {}", input.display(), output.display())
+        })?;
+    }
+    if json {
+        println!(
+            "{{\"input\": {}, \"title\": {}, \"font_size\": {:.1}, \"current_title\": {}}}",
+            json_string(&input.to_string_lossy()),
+            json_string(&inferred.title),
+            inferred.font_size,
+            inferred
+                .current
+                .as_deref()
+                .map_or_else(|| "null".to_string(), json_string)
+        );
+    } else {
+        println!("{}", inferred.title);
+        if let Some(current) = &inferred.current {
+            eprintln!("current title: {current}");
+        }
+    }
+    if let Some(output) = output {
+        eprintln!("wrote: {}", output.display());
+    }
     Ok(())
 }
 
-fn cmd_merge(inputs: &[PathBuf], output: &Path, force: bool) -> anyhow::Result<()> {
+fn cmd_set_lang(
+    input: &Path,
+    output: &Path,
+    force: bool,
+    lang: &str,
+    direction: Option<pdfcore::TextDirection>,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::set_document_language_plan(input, output, lang, direction)
+            .with_context(|| format!("planning language of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
     ensure_can_write_file(output, force)?;
-    pdfcore::merge(inputs, output)
-        .with_context(|| format!("merging {} file(s) into {}", inputs.len(), output.display()))?;
+    pdfcore::set_document_language(input, output, lang, direction).with_context(|| {
+        format!(
+            "setting language {} -> {}",
+            input.display(),
+            output.display()
+        )
+    })?;
     eprintln!("wrote: {}", output.display());
     Ok(())
 }
 
-fn cmd_split_pages(
+fn cmd_viewer_prefs(
     input: &Path,
-    out_dir: &Path,
-    pattern: Option<&str>,
+    output: &Path,
     force: bool,
+    options: &pdfcore::ViewerOptions,
+    dry_run: bool,
 ) -> anyhow::Result<()> {
-    pdfcore::validate_input_file(input)
-        .with_context(|| format!("validating input: {}", input.display()))?;
-
-    fs::create_dir_all(out_dir)
-        .with_context(|| format!("creating out dir: {}", out_dir.display()))?;
-
-    if !force {
-        // best-effort: if directory non-empty, require --force
-        if let Ok(mut it) = fs::read_dir(out_dir) {
-            if it.next().is_some() {
-                bail!(
-                    "out-dir is not empty: {} (use --force to proceed)",
-                    out_dir.display()
-                );
-            }
-        }
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::set_viewer_options_plan(input, output, options)
+            .with_context(|| format!("planning viewer settings of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
     }
-
-    pdfcore::split_pages(input, out_dir, pattern).with_context(|| {
+    ensure_can_write_file(output, force)?;
+    pdfcore::set_viewer_options(input, output, options).with_context(|| {
         format!(
-            "splitting {} into pages under {}",
+            "setting viewer preferences {} -> {}",
             input.display(),
-            out_dir.display()
+            output.display()
         )
     })?;
-    eprintln!("wrote pages to: {}", out_dir.display());
+    eprintln!("wrote: {}", output.display());
     Ok(())
 }
 
-fn cmd_extract_text(
+fn cmd_open_action(
     input: &Path,
-    output: Option<&Path>,
-    stdout: bool,
+    output: &Path,
     force: bool,
+    action: &pdfcore::OpenAction,
+    dry_run: bool,
 ) -> anyhow::Result<()> {
-    if stdout {
-        let text = pdfcore::extract_text(input, Option::<&Path>::None)
-            .with_context(|| format!("extracting text from {}", input.display()))?;
-        let mut w = io::stdout().lock();
-        w.write_all(text.as_bytes())?;
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::set_open_action_plan(input, output, action)
+            .with_context(|| format!("planning open action of {}", input.display()))?;
+        print!("{plan}");
         return Ok(());
     }
-
-    let out = output.context("either -o/--output or --stdout is required")?;
-    ensure_can_write_file(out, force)?;
-    pdfcore::extract_text(input, Some(out)).with_context(|| {
+    ensure_can_write_file(output, force)?;
+    let previous = pdfcore::set_open_action(input, output, action).with_context(|| {
         format!(
-            "extracting text from {} into {}",
+            "setting open action {} -> {}",
             input.display(),
-            out.display()
+            output.display()
         )
     })?;
-    eprintln!("wrote: {}", out.display());
+    match (previous, action) {
+        (Some(previous), pdfcore::OpenAction::Remove) => println!("removed: {previous}"),
+        (None, pdfcore::OpenAction::Remove) => println!("removed: nothing (no open action)"),
+        (Some(previous), _) => println!("replaced: {previous}"),
+        (None, _) => {}
+    }
+    eprintln!("wrote: {}", output.display());
     Ok(())
 }
 
-fn cmd_rotate(
+fn cmd_a11y_check(input: &Path, json: bool) -> anyhow::Result<()> {
+    let report = pdfcore::a11y_check(input)
+        .with_context(|| format!("checking accessibility of {}", input.display()))?;
+
+    if json {
+        print!("{}", render_a11y_check_json(input, &report));
+    } else {
+        println!("pages: {}", report.pages);
+        println!("score: {}/100", report.score);
+        print_checks(&report.checks);
+    }
+    Ok(())
+}
+
+/// Sanitize `input` into `output`, or only audit it if `output` is `None`.
+fn cmd_sanitize(
+    input: &Path,
+    output: Option<&Path>,
+    force: bool,
+    json: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if let (Some(output), true) = (output, dry_run) {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::sanitize_plan(input, output)
+            .with_context(|| format!("auditing {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    if output.is_none() && !dry_run {
+        bail!("-o/--output is required (or use --dry-run to only audit)");
+    }
+    let report = match output {
+        Some(output) => {
+            ensure_can_write_file(output, force)?;
+            pdfcore::sanitize(input, output).with_context(|| {
+                format!("sanitizing {} -> {}", input.display(), output.display())
+            })?
+        }
+        None => pdfcore::audit_active_content(input)
+            .with_context(|| format!("auditing {}", input.display()))?,
+    };
+
+    if json {
+        print!("{}", render_sanitize_json(output, &report));
+    } else if report.is_clean() {
+        println!("no active content found");
+    } else {
+        println!("{}:", if output.is_some() { "removed" } else { "found" });
+        for finding in &report.findings {
+            println!("  {}: {}", finding.kind, finding.detail);
+        }
+    }
+    if let Some(output) = output {
+        eprintln!("wrote: {}", output.display());
+    }
+    Ok(())
+}
+
+fn cmd_redact(
     input: &Path,
     output: &Path,
     force: bool,
-    degrees: RotateDegrees,
-    pages: Option<&str>,
+    options: &pdfcore::RedactOptions,
+    json: bool,
+    dry_run: bool,
 ) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::redact_plan(input, output, options)
+            .with_context(|| format!("planning redaction of {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
     ensure_can_write_file(output, force)?;
-    let sel = pages
-        .map(parse_page_selection)
-        .transpose()
-        .context("parsing --pages")?;
+    let report = pdfcore::redact(input, output, options)
+        .with_context(|| format!("redacting {} -> {}", input.display(), output.display()))?;
 
-    pdfcore::rotate(input, output, degrees.as_u16(), sel)
-        .with_context(|| format!("rotating {} -> {}", input.display(), output.display()))?;
+    if json {
+        print!("{}", render_redact_json(output, &report));
+    } else {
+        if !options.patterns.is_empty() {
+            println!("matches: {}", report.matches);
+        }
+        println!("glyphs removed: {}", report.glyphs_removed);
+        println!("images redacted: {}", report.images_redacted);
+        println!("annotations removed: {}", report.annotations_removed);
+        if !report.rasterized_pages.is_empty() {
+            println!(
+                "rasterized pages: {}",
+                join_numbers(&report.rasterized_pages)
+            );
+        }
+        if !report.unsearched_pages.is_empty() {
+            println!(
+                "warning: could not search pages: {}",
+                join_numbers(&report.unsearched_pages)
+            );
+        }
+    }
     eprintln!("wrote: {}", output.display());
     Ok(())
 }
 
-fn cmd_compress(
+fn cmd_meta_diff(a: &Path, b: &Path, json: bool) -> anyhow::Result<()> {
+    let patch = pdfcore::metadata_diff(a, b)
+        .with_context(|| format!("comparing {} with {}", a.display(), b.display()))?;
+    if json {
+        print!("{}", patch.to_json());
+        return Ok(());
+    }
+    if patch.is_empty() {
+        println!("no differences");
+        return Ok(());
+    }
+    let before = pdfcore::info(a)
+        .with_context(|| format!("reading {}", a.display()))?
+        .metadata;
+    for key in &patch.remove {
+        let old = before.get(key).map(String::as_str).unwrap_or_default();
+        println!("- {key}: {}", json_string(old));
+    }
+    for (key, value) in &patch.set {
+        match before.get(key) {
+            Some(old) => println!("~ {key}: {} -> {}", json_string(old), json_string(value)),
+            None => println!("+ {key}: {}", json_string(value)),
+        }
+    }
+    Ok(())
+}
+
+fn cmd_meta_apply(
+    input: &Path,
+    patch: &Path,
+    output: &Path,
+    force: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let json = fs::read_to_string(patch).with_context(|| format!("reading {}", patch.display()))?;
+    let patch =
+        pdfcore::MetadataPatch::from_json(&json).with_context(|| patch.display().to_string())?;
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::apply_metadata_patch_plan(input, output, &patch)
+            .with_context(|| format!("planning metadata changes to {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    pdfcore::apply_metadata_patch(input, output, &patch)
+        .with_context(|| format!("changing the metadata of {}", input.display()))?;
+    eprintln!(
+        "wrote: {} ({} set, {} removed)",
+        output.display(),
+        patch.set.len(),
+        patch.remove.len()
+    );
+    Ok(())
+}
+
+fn cmd_strip_metadata(
     input: &Path,
     output: &Path,
     force: bool,
-    preset: CompressPresetCli,
+    options: &pdfcore::StripOptions,
+    json: bool,
+    dry_run: bool,
 ) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::strip_metadata_plan(input, output, options)
+            .with_context(|| format!("planning metadata removal for {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
     ensure_can_write_file(output, force)?;
-    pdfcore::compress(input, output, preset.into()).with_context(|| {
+    let report = pdfcore::strip_metadata(input, output, options).with_context(|| {
         format!(
-            "compressing {} -> {} (preset: {:?})",
+            "stripping metadata from {} -> {}",
             input.display(),
-            output.display(),
-            preset
+            output.display()
         )
     })?;
+
+    if json {
+        print!("{}", render_strip_json(output, &report));
+    } else if report.is_empty() {
+        println!("no metadata found");
+    } else {
+        if !report.info_keys.is_empty() {
+            println!("document info removed: {}", report.info_keys.join(", "));
+        }
+        println!("XMP packets removed: {}", report.xmp_streams);
+        println!(
+            "document ID removed: {}",
+            if report.document_id { "yes" } else { "no" }
+        );
+        if options.annotation_authors {
+            println!("annotation authors removed: {}", report.annotation_authors);
+        }
+    }
+    eprintln!("wrote: {}", output.display());
+    Ok(())
+}
+
+fn cmd_regen_id(
+    input: &Path,
+    output: &Path,
+    force: bool,
+    from_content_hash: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    if dry_run {
+        check_can_write_file(output, force)?;
+        let plan = pdfcore::regenerate_id_plan(input, output, from_content_hash)
+            .with_context(|| format!("planning new ID for {}", input.display()))?;
+        print!("{plan}");
+        return Ok(());
+    }
+    ensure_can_write_file(output, force)?;
+    let [permanent, changing] = pdfcore::regenerate_id(input, output, from_content_hash)
+        .with_context(|| {
+            format!(
+                "setting the ID of {} -> {}",
+                input.display(),
+                output.display()
+            )
+        })?;
+    println!("document ID: {permanent} {changing}");
     eprintln!("wrote: {}", output.display());
     Ok(())
 }
 
-fn ensure_can_write_file(path: &Path, force: bool) -> anyhow::Result<()> {
+fn join_numbers(numbers: &[u32]) -> String {
+    numbers
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A file that failed policy enforcement.
+struct Nonconforming {
+    path: PathBuf,
+    /// Why the file could not be checked at all, if it could not be read.
+    error: Option<String>,
+    violations: Vec<pdfcore::ComplianceCheck>,
+    quarantined: Option<PathBuf>,
+}
+
+fn cmd_enforce(
+    policy_path: &Path,
+    patterns: &[String],
+    quarantine: Option<&Path>,
+    jobs: usize,
+    json: bool,
+    tui: bool,
+    dry_run: bool,
+) -> anyhow::Result<()> {
+    let policy = pdfcore::Policy::from_file(policy_path)
+        .with_context(|| format!("reading policy: {}", policy_path.display()))?;
+    let files = expand_patterns(patterns)?;
+    if let Some(dir) = quarantine.filter(|_| !dry_run) {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("creating quarantine dir: {}", dir.display()))?;
+    }
+
+    let workers = jobs.min(files.len());
+    let (dashboard, progress) = dashboard::start_if(tui, "enforce", workers)?;
+    let results = check_policies(&files, &policy, jobs, &progress);
+    drop(dashboard);
+
+    let mut failures = Vec::new();
+    for (path, result) in files.iter().zip(results) {
+        let (error, violations) = match result {
+            Ok(report) if report.is_compliant() => continue,
+            Ok(report) => (None, report.violations().cloned().collect()),
+            Err(e) => (Some(e.to_string()), Vec::new()),
+        };
+        let quarantined = match quarantine {
+            Some(dir) if dry_run => Some(quarantine_destination(path, dir)?),
+            Some(dir) => Some(
+                quarantine_file(path, dir)
+                    .with_context(|| format!("quarantining {}", path.display()))?,
+            ),
+            None => None,
+        };
+        failures.push(Nonconforming {
+            path: path.clone(),
+            error,
+            violations,
+            quarantined,
+        });
+    }
+
+    if json {
+        print!("{}", render_enforce_json(files.len(), &failures));
+    } else {
+        for failure in &failures {
+            println!("{}", failure.path.display());
+            if let Some(error) = &failure.error {
+                println!("  unreadable: {error}");
+            }
+            for check in &failure.violations {
+                println!("  FAIL {}: {}", check.name, check.detail);
+            }
+            if let Some(dest) = &failure.quarantined {
+                let verb = if dry_run {
+                    "would be quarantined"
+                } else {
+                    "quarantined"
+                };
+                println!("  {verb} -> {}", dest.display());
+            }
+        }
+        println!(
+            "{} file(s) checked, {} non-conforming",
+            files.len(),
+            failures.len()
+        );
+    }
+
+    if !failures.is_empty() {
+        bail!(
+            "{} of {} file(s) do not conform to {}",
+            failures.len(),
+            files.len(),
+            policy_path.display()
+        );
+    }
+    Ok(())
+}
+
+/// [`pdfcore::check_policy`] for every file, `jobs` files at a time; results
+/// are in the order of `files`. Each file is reported to `progress`.
+fn check_policies(
+    files: &[PathBuf],
+    policy: &pdfcore::Policy,
+    jobs: usize,
+    progress: &pdfcore::Progress,
+) -> Vec<pdfcore::Result<pdfcore::PolicyReport>> {
+    for path in files {
+        progress.queued(path);
+    }
+    let chunk = files.len().div_ceil(jobs.max(1)).max(1);
+    thread::scope(|scope| {
+        let workers: Vec<_> = files
+            .chunks(chunk)
+            .enumerate()
+            .map(|(worker, chunk)| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|path| {
+                            progress.started(path, worker);
+                            let start = Instant::now();
+                            let result = pdfcore::check_policy(path, policy);
+                            progress.done(path, worker, start.elapsed(), &result);
+                            result
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|e| std::panic::resume_unwind(e))
+            })
+            .collect()
+    })
+}
+
+fn cmd_doctor(require: &[String], json: bool) -> anyhow::Result<()> {
+    let required = require
+        .iter()
+        .map(|name| canonical_tool_name(name))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let tools = pdfcore::tool_statuses();
+    let found = |name: &str| tools.iter().any(|t| t.name == name && t.path.is_some());
+
+    if json {
+        print!("{}", render_doctor_json(&tools, &found));
+    } else {
+        println!("tools:");
+        for tool in &tools {
+            let via = if tool.env_override {
+                format!(" (via {})", tool.env_var)
+            } else {
+                String::new()
+            };
+            match &tool.path {
+                Some(path) => println!(
+                    "  {:<12} {:<10} {}{via}",
+                    tool.name,
+                    tool.version
+                        .as_ref()
+                        .map_or_else(|| "unknown".to_string(), ToString::to_string),
+                    path.display()
+                ),
+                None => println!("  {:<12} missing{via}", tool.name),
+            }
+        }
+        println!("commands:");
+        for (command, needs, optional) in COMMAND_TOOLS {
+            let missing: Vec<&str> = needs.iter().copied().filter(|t| !found(t)).collect();
+            let limited: Vec<&str> = optional.iter().copied().filter(|t| !found(t)).collect();
+            if !missing.is_empty() {
+                println!("  {command:<14} unavailable (needs {})", missing.join(", "));
+            } else if !limited.is_empty() {
+                println!("  {command:<14} limited (without {})", limited.join(", "));
+            } else {
+                println!("  {command:<14} ok");
+            }
+        }
+        for op in pdfcore::Operation::ALL {
+            if let Ok(engine) = pdfcore::select_engine(op) {
+                println!("  {:<14} ok (engine: {engine})", op.name());
+            } else {
+                let engines: Vec<&str> = op.engines().iter().map(|e| e.name()).collect();
+                println!(
+                    "  {:<14} unavailable (needs one of {})",
+                    op.name(),
+                    engines.join(", ")
+                );
+            }
+        }
+        println!("  (all other commands are pure Rust)");
+        for tool in tools.iter().filter(|t| t.path.is_none()) {
+            if let Some(hint) = &tool.hint {
+                println!("\n{}: {hint}", tool.name);
+            }
+        }
+    }
+
+    let unmet: Vec<&str> = required.into_iter().filter(|t| !found(t)).collect();
+    if !unmet.is_empty() {
+        bail!("required tool(s) not found: {}", unmet.join(", "));
+    }
+    Ok(())
+}
+
+/// Map a tool name or common executable name (`gs`) to pdfcore's tool name.
+fn canonical_tool_name(name: &str) -> anyhow::Result<&'static str> {
+    match name.trim().to_ascii_lowercase().as_str() {
+        "qpdf" => Ok("qpdf"),
+        "pdftotext" | "poppler" => Ok("pdftotext"),
+        "gs" | "ghostscript" => Ok("ghostscript"),
+        "ocrmypdf" => Ok("ocrmypdf"),
+        "mutool" | "mupdf" => Ok("mutool"),
+        "wkhtmltopdf" => Ok("wkhtmltopdf"),
+        "chrome" | "chromium" => Ok("chrome"),
+        "soffice" | "libreoffice" => Ok("soffice"),
+        "openssl" => Ok("openssl"),
+        "curl" => Ok("curl"),
+        "pdftocairo" => Ok("pdftocairo"),
+        "lp" | "lpr" | "cups" => Ok("lp"),
+        "sumatrapdf" | "sumatra" => Ok("sumatrapdf"),
+        other => bail!(
+            "unknown tool: {other} (expected qpdf, pdftotext, gs, ocrmypdf, mutool, wkhtmltopdf, chrome, soffice, openssl, curl, pdftocairo, lp, or sumatrapdf)"
+        ),
+    }
+}
+
+/// Expand glob patterns (plain paths pass through), sorted and de-duplicated.
+fn expand_patterns(patterns: &[String]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for pattern in patterns {
+        if !pattern.contains(['*', '?', '[']) {
+            files.push(PathBuf::from(pattern));
+            continue;
+        }
+        let matches =
+            glob::glob(pattern).with_context(|| format!("invalid glob pattern: {pattern}"))?;
+        for entry in matches {
+            let path = entry.with_context(|| format!("expanding {pattern}"))?;
+            if path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Move `path` into `dir`, adding a numeric suffix rather than overwriting.
+fn quarantine_file(path: &Path, dir: &Path) -> anyhow::Result<PathBuf> {
+    let dest = quarantine_destination(path, dir)?;
+    // rename fails across filesystems; fall back to copy + remove.
+    if fs::rename(path, &dest).is_err() {
+        fs::copy(path, &dest)?;
+        fs::remove_file(path)?;
+    }
+    Ok(dest)
+}
+
+/// Where [`quarantine_file`] would move `path`.
+fn quarantine_destination(path: &Path, dir: &Path) -> anyhow::Result<PathBuf> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("not a file: {}", path.display()))?;
+    let mut dest = dir.join(name);
+    let stem = path.file_stem().unwrap_or(name).to_string_lossy();
+    let ext = path.extension().map(|e| e.to_string_lossy());
+    let mut n = 1;
+    while dest.exists() {
+        let candidate = match &ext {
+            Some(ext) => format!("{stem}-{n}.{ext}"),
+            None => format!("{stem}-{n}"),
+        };
+        dest = dir.join(candidate);
+        n += 1;
+    }
+    Ok(dest)
+}
+
+fn print_checks(checks: &[pdfcore::ComplianceCheck]) {
+    println!("checks:");
+    for check in checks {
+        let mark = if check.passed { "PASS" } else { "FAIL" };
+        println!("  {mark} {}: {}", check.name, check.detail);
+    }
+}
+
+fn ensure_can_write_file(path: &Path, force: bool) -> anyhow::Result<()> {
+    check_can_write_file(path, force)?;
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("creating output dir: {}", parent.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Create `dir` and, unless `force` is set, require it to be empty.
+fn ensure_can_write_dir(dir: &Path, force: bool) -> anyhow::Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("creating out dir: {}", dir.display()))?;
+
+    if !force {
+        // best-effort: if directory non-empty, require --force
+        if let Ok(mut it) = fs::read_dir(dir) {
+            if it.next().is_some() {
+                bail!(
+                    "out-dir is not empty: {} (use --force to proceed)",
+                    dir.display()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The checks of [`ensure_can_write_file`], without creating directories.
+fn check_can_write_file(path: &Path, force: bool) -> anyhow::Result<()> {
     if path.exists() && !force {
         bail!(
             "output already exists: {} (use --force to overwrite)",
             path.display()
         );
     }
-    if let Some(parent) = path.parent() {
-        if !parent.as_os_str().is_empty() {
-            fs::create_dir_all(parent)
-                .with_context(|| format!("creating output dir: {}", parent.display()))?;
-        }
-    }
     Ok(())
 }
 
 fn parse_page_selection(s: &str) -> anyhow::Result<pdfcore::PageSelection> {
-    // pdfcore currently supports All or a single inclusive range.
+    Ok(s.parse()?)
+}
+
+/// Parse a rotation spec such as `1-3:90,4:180,5-:270`.
+fn parse_rotation_spec(s: &str) -> anyhow::Result<Vec<(pdfcore::PageSelection, u16)>> {
+    s.split(',')
+        .map(|entry| {
+            let (pages, degrees) = entry
+                .split_once(':')
+                .with_context(|| format!("expected <pages>:<degrees>, got {:?}", entry.trim()))?;
+            let degrees = degrees
+                .trim()
+                .parse::<u16>()
+                .with_context(|| format!("invalid degrees {:?}", degrees.trim()))?;
+            Ok((parse_page_selection(pages)?, degrees))
+        })
+        .collect()
+}
+
+/// Parse a byte size such as `25MB`, `500 KB`, `1.5G`, or `1048576`.
+///
+/// Units are decimal (`KB` = 1000) unless the binary `KiB`/`MiB`/`GiB` forms
+/// are used.
+fn parse_byte_size(s: &str) -> anyhow::Result<u64> {
     let s = s.trim();
-    let (start, end) = s
-        .split_once('-')
-        .ok_or_else(|| anyhow::anyhow!("expected format <start>-<end> (e.g. 1-3)"))?;
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number
+        .parse()
+        .with_context(|| format!("invalid size: {s:?}"))?;
+
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1000,
+        "m" | "mb" => 1000 * 1000,
+        "g" | "gb" => 1000 * 1000 * 1000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        other => bail!("unknown size unit {other:?} (expected B, KB, MB, GB, KiB, MiB, GiB)"),
+    };
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    let bytes = (number * multiplier as f64).round() as u64;
+    Ok(bytes)
+}
+
+/// Parse a duration such as `120s`, `5m`, `1.5h`, `500ms`, or `90` (seconds).
+fn parse_duration(s: &str) -> anyhow::Result<Duration> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number
+        .parse()
+        .with_context(|| format!("invalid duration: {s:?}"))?;
+
+    let seconds = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "s" | "sec" => number,
+        "ms" => number / 1000.0,
+        "m" | "min" => number * 60.0,
+        "h" => number * 3600.0,
+        other => bail!("unknown duration unit {other:?} (expected ms, s, m, h)"),
+    };
+    let duration =
+        Duration::try_from_secs_f64(seconds).with_context(|| format!("invalid duration: {s:?}"))?;
+    if duration.is_zero() {
+        bail!("duration must be greater than zero");
+    }
+    Ok(duration)
+}
+
+/// Parse an `OLD=NEW` mapping.
+fn parse_mapping(s: &str) -> anyhow::Result<(String, String)> {
+    let (from, to) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("expected OLD=NEW (e.g. old.com=new.com)"))?;
+    if from.trim().is_empty() {
+        bail!("mapping source must not be empty");
+    }
+    Ok((from.trim().to_string(), to.trim().to_string()))
+}
+
+/// Parse a page box name: `media`, `crop`, `bleed`, `trim`, or `art`.
+fn parse_page_box(s: &str) -> anyhow::Result<pdfcore::PageBox> {
+    let name = s.trim().to_ascii_lowercase();
+    pdfcore::PageBox::ALL
+        .into_iter()
+        .find(|b| b.name() == name)
+        .with_context(|| format!("unknown box {s:?} (expected media, crop, bleed, trim, or art)"))
+}
 
-    let start: u32 = start.trim().parse().context("parsing start page")?;
-    let end: u32 = end.trim().parse().context("parsing end page")?;
+/// Parse a box assignment such as `trim=20,20,575,822`.
+fn parse_box_assignment(s: &str) -> anyhow::Result<(pdfcore::PageBox, pdfcore::Rect)> {
+    let (which, rect) = s
+        .split_once('=')
+        .with_context(|| format!("expected <box>=x1,y1,x2,y2, got {s:?}"))?;
+    Ok((parse_page_box(which)?, parse_rect(rect)?))
+}
 
-    if start == 0 || end == 0 {
-        bail!("pages are 1-based; got {start}-{end}");
+/// Parse a rectangle given as `x1,y1,x2,y2` in points.
+fn parse_rect(s: &str) -> anyhow::Result<pdfcore::Rect> {
+    let values = s
+        .split(',')
+        .map(|v| v.trim().parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("invalid rectangle: {s:?}"))?;
+    let [llx, lly, urx, ury] = values[..] else {
+        bail!("expected x1,y1,x2,y2 (e.g. 100,500,300,530), got {s:?}");
+    };
+    let rect = pdfcore::Rect { llx, lly, urx, ury }.normalized();
+    if rect.urx - rect.llx <= 0.0 || rect.ury - rect.lly <= 0.0 {
+        bail!("rectangle {s:?} has no area");
     }
-    if start > end {
-        bail!("page range start must be <= end; got {start}-{end}");
+    Ok(rect)
+}
+
+/// Parse a point `x,y`, each a length as for [`parse_length`].
+fn parse_point(s: &str) -> anyhow::Result<(f32, f32)> {
+    let Some((x, y)) = s.split_once(',') else {
+        bail!("expected x,y (e.g. 400,120), got {s:?}");
+    };
+    Ok((parse_length(x)?, parse_length(y)?))
+}
+
+/// Parse an open zoom: `fit`, `width`, `height`, or a percentage (`125%`
+/// or `125`).
+fn parse_zoom(s: &str) -> anyhow::Result<pdfcore::OpenFit> {
+    let s = s.trim();
+    Ok(match s.to_ascii_lowercase().as_str() {
+        "fit" | "page" => pdfcore::OpenFit::Page,
+        "width" => pdfcore::OpenFit::Width,
+        "height" => pdfcore::OpenFit::Height,
+        other => {
+            let percent: u32 = other
+                .strip_suffix('%')
+                .unwrap_or(other)
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&p| (1..=6400).contains(&p))
+                .with_context(|| {
+                    format!("invalid zoom {s:?} (expected fit, width, height, or 1% to 6400%)")
+                })?;
+            pdfcore::OpenFit::Percent(percent)
+        }
+    })
+}
+
+fn parse_paper_size(s: &str) -> anyhow::Result<pdfcore::PaperSize> {
+    Ok(pdfcore::PaperSize::from_name(s)?)
+}
+
+/// Parse a length such as `3mm`, `0.125in`, `1cm`, or `9pt` into points.
+/// A bare number is taken as points.
+fn parse_length(s: &str) -> anyhow::Result<f32> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f32 = number
+        .parse()
+        .with_context(|| format!("invalid length: {s:?}"))?;
+
+    let points = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "pt" => number,
+        "mm" => number * pdfcore::POINTS_PER_MM,
+        "cm" => number * pdfcore::POINTS_PER_MM * 10.0,
+        "in" => number * 72.0,
+        other => bail!("unknown length unit {other:?} (expected pt, mm, cm, in)"),
+    };
+    Ok(points)
+}
+
+/// The languages of `input` for `info --json`; `None` for an encrypted
+/// document, whose text cannot be read without decrypting it.
+fn document_languages(
+    input: &Path,
+    info: &pdfcore::PdfInfo,
+) -> pdfcore::Result<Option<Vec<(pdfcore::Language, f64)>>> {
+    if info.encryption.is_some() {
+        return Ok(None);
     }
+    pdfcore::detect_language(input).map(Some)
+}
 
-    Ok(pdfcore::PageSelection::Range { start, end })
+fn render_language_json(language: &pdfcore::Language, confidence: f64) -> String {
+    format!(
+        "{{\"code\": {}, \"name\": {}, \"confidence\": {confidence:.2}}}",
+        json_string(&language.code),
+        language
+            .name
+            .map_or_else(|| "null".to_string(), json_string)
+    )
 }
 
-fn render_info_json(info: &pdfcore::PdfInfo) -> String {
+fn render_info_json(
+    info: &pdfcore::PdfInfo,
+    languages: Option<&[(pdfcore::Language, f64)]>,
+) -> String {
     use std::fmt::Write as _;
 
     let mut out = String::new();
     out.push_str("{\n");
     let _ = writeln!(&mut out, "  \"pages\": {},", info.pages);
+    let _ = writeln!(&mut out, "  \"version\": {},", json_string(&info.version));
+    let _ = writeln!(
+        &mut out,
+        "  \"catalog_version\": {},",
+        info.catalog_version
+            .as_deref()
+            .map_or_else(|| "null".to_string(), json_string)
+    );
+    let _ = writeln!(&mut out, "  \"linearized\": {},", info.linearized);
+    let _ = writeln!(&mut out, "  \"xref_streams\": {},", info.xref_streams);
+    let _ = writeln!(&mut out, "  \"object_streams\": {},", info.object_streams);
+    let _ = writeln!(&mut out, "  \"tagged\": {},", info.tagged);
+    let _ = writeln!(
+        &mut out,
+        "  \"incremental_updates\": {},",
+        info.incremental_updates
+    );
+    let _ = writeln!(
+        &mut out,
+        "  \"document_id\": {},",
+        info.document_id
+            .as_ref()
+            .map_or_else(|| "null".to_string(), |id| json_string_array(id))
+    );
 
     out.push_str("  \"metadata\": {");
     if info.metadata.is_empty() {
-        out.push_str("}\n");
+        out.push_str("},\n");
     } else {
         out.push('\n');
         let mut first = true;
@@ -397,31 +7799,802 @@ fn render_info_json(info: &pdfcore::PdfInfo) -> String {
             first = false;
             let _ = write!(&mut out, "    {}: {}", json_string(k), json_string(v));
         }
-        out.push_str("\n  }\n");
+        out.push_str("\n  },\n");
     }
 
+    let _ = writeln!(
+        &mut out,
+        "  \"fonts\": {},",
+        render_fonts_json(&info.fonts, 1)
+    );
+    let xfa = info
+        .xfa
+        .map_or("null".to_string(), |x| json_string(x.name()));
+    let _ = writeln!(&mut out, "  \"xfa\": {xfa},");
+    let languages = languages.map_or_else(
+        || "null".to_string(),
+        |languages| {
+            let items: Vec<String> = languages
+                .iter()
+                .map(|(language, confidence)| render_language_json(language, *confidence))
+                .collect();
+            format!("[{}]", items.join(", "))
+        },
+    );
+    let _ = writeln!(&mut out, "  \"languages\": {languages},");
+    let _ = writeln!(
+        &mut out,
+        "  \"encryption\": {}",
+        info.encryption
+            .as_ref()
+            .map_or_else(|| "null".to_string(), render_encryption_json)
+    );
+
     out.push_str("}\n");
     out
 }
 
-fn json_string(s: &str) -> String {
+fn render_encryption_json(encryption: &pdfcore::EncryptionInfo) -> String {
+    let granted: Vec<String> = encryption
+        .permissions
+        .granted()
+        .into_iter()
+        .map(json_string)
+        .collect();
+    let user_password_required = encryption
+        .user_password_required
+        .map_or_else(|| "null".to_string(), |b| b.to_string());
+    format!(
+        "{{\"filter\": {}, \"version\": {}, \"revision\": {}, \"cipher\": {}, \"key_bits\": {}, \"permissions\": [{}], \"p\": {}, \"user_password_required\": {user_password_required}, \"decrypted\": {}}}",
+        json_string(&encryption.filter),
+        encryption.version,
+        encryption.revision,
+        json_string(&encryption.cipher),
+        encryption.key_bits,
+        granted.join(", "),
+        encryption.permissions.raw,
+        encryption.decrypted
+    )
+}
+
+/// Render fonts as a JSON array, indented for nesting at `depth` levels.
+fn render_fonts_json(fonts: &[pdfcore::FontInfo], depth: usize) -> String {
     use std::fmt::Write as _;
 
-    let mut out = String::with_capacity(s.len() + 2);
-    out.push('"');
-    for c in s.chars() {
-        match c {
-            '"' => out.push_str("\\\""),
-            '\\' => out.push_str("\\\\"),
-            '\n' => out.push_str("\\n"),
-            '\r' => out.push_str("\\r"),
-            '\t' => out.push_str("\\t"),
-            c if c.is_control() => {
-                let _ = write!(&mut out, "\\u{:04x}", c as u32);
-            }
-            _ => out.push(c),
+    if fonts.is_empty() {
+        return "[]".to_string();
+    }
+    let pad = "  ".repeat(depth);
+    let mut out = String::from("[");
+    for (i, f) in fonts.iter().enumerate() {
+        out.push_str(if i == 0 { "\n" } else { ",\n" });
+        let pages = f
+            .pages
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let _ = write!(
+            &mut out,
+            "{pad}  {{\"name\": {}, \"type\": {}, \"embedded\": {}, \"subset\": {}, \"pages\": [{pages}]}}",
+            json_string(&f.name),
+            json_string(&f.font_type),
+            f.embedded,
+            f.subset
+        );
+    }
+    let _ = write!(&mut out, "\n{pad}]");
+    out
+}
+
+/// Compact a sorted page list into ranges, e.g. `1-3, 7`.
+fn format_page_list(pages: &[u32]) -> String {
+    let mut parts = Vec::new();
+    let mut iter = pages.iter().copied().peekable();
+    while let Some(start) = iter.next() {
+        let mut end = start;
+        while iter.peek() == Some(&(end + 1)) {
+            end += 1;
+            iter.next();
+        }
+        if start == end {
+            parts.push(start.to_string());
+        } else {
+            parts.push(format!("{start}-{end}"));
         }
     }
-    out.push('"');
+    parts.join(", ")
+}
+
+fn render_court_ready_json(output: &Path, report: &pdfcore::CourtReadyReport) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::new();
+    out.push_str("{\n");
+    let _ = writeln!(
+        &mut out,
+        "  \"output\": {},",
+        json_string(&output.to_string_lossy())
+    );
+    let _ = writeln!(&mut out, "  \"input_bytes\": {},", report.input_bytes);
+    let _ = writeln!(&mut out, "  \"output_bytes\": {},", report.output_bytes);
+    let _ = writeln!(
+        &mut out,
+        "  \"preset\": {},",
+        json_string(&report.preset.to_string())
+    );
+    let _ = writeln!(
+        &mut out,
+        "  \"javascript_removed\": {},",
+        report.javascript_removed
+    );
+    let _ = writeln!(&mut out, "  \"compliant\": {},", report.is_compliant());
+    let _ = writeln!(
+        &mut out,
+        "  \"checks\": {}",
+        render_check_list_json(&report.checks)
+    );
+    out.push_str("}\n");
+    out
+}
+
+fn render_a11y_prep_json(output: &Path, report: &pdfcore::A11yPrepReport) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::new();
+    out.push_str("{\n");
+    let _ = writeln!(
+        &mut out,
+        "  \"output\": {},",
+        json_string(&output.to_string_lossy())
+    );
+    let _ = writeln!(&mut out, "  \"ocr_applied\": {},", report.ocr_applied);
+    let _ = writeln!(&mut out, "  \"headings\": {},", report.headings);
+    let _ = writeln!(&mut out, "  \"paragraphs\": {},", report.paragraphs);
+    let _ = writeln!(
+        &mut out,
+        "  \"actions\": {},",
+        json_string_array(&report.actions)
+    );
+    let _ = writeln!(
+        &mut out,
+        "  \"issues\": {}",
+        json_string_array(&report.issues)
+    );
+    out.push_str("}\n");
+    out
+}
+
+fn render_a11y_check_json(input: &Path, report: &pdfcore::A11yReport) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::new();
+    out.push_str("{\n");
+    let _ = writeln!(
+        &mut out,
+        "  \"input\": {},",
+        json_string(&input.to_string_lossy())
+    );
+    let _ = writeln!(&mut out, "  \"pages\": {},", report.pages);
+    let _ = writeln!(&mut out, "  \"score\": {},", report.score);
+    let _ = writeln!(&mut out, "  \"compliant\": {},", report.is_compliant());
+    let _ = writeln!(
+        &mut out,
+        "  \"checks\": {}",
+        render_check_list_json(&report.checks)
+    );
+    out.push_str("}\n");
+    out
+}
+
+fn json_string_array(items: &[String]) -> String {
+    let items = items
+        .iter()
+        .map(|s| json_string(s))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("[{items}]")
+}
+
+fn render_checks_json(
+    output: &Path,
+    pages: u32,
+    compliant: bool,
+    checks: &[pdfcore::ComplianceCheck],
+) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::new();
+    out.push_str("{\n");
+    let _ = writeln!(
+        &mut out,
+        "  \"output\": {},",
+        json_string(&output.to_string_lossy())
+    );
+    let _ = writeln!(&mut out, "  \"pages\": {pages},");
+    let _ = writeln!(&mut out, "  \"compliant\": {compliant},");
+    let _ = writeln!(&mut out, "  \"checks\": {}", render_check_list_json(checks));
+    out.push_str("}\n");
+    out
+}
+
+fn render_check_list_json(checks: &[pdfcore::ComplianceCheck]) -> String {
+    use std::fmt::Write as _;
+
+    if checks.is_empty() {
+        return "[]".to_string();
+    }
+    let mut out = String::from("[");
+    for (i, check) in checks.iter().enumerate() {
+        out.push_str(if i == 0 { "\n" } else { ",\n" });
+        let _ = write!(
+            &mut out,
+            "    {{\"name\": {}, \"passed\": {}, \"detail\": {}}}",
+            json_string(check.name),
+            check.passed,
+            json_string(&check.detail)
+        );
+    }
+    out.push_str("\n  ]");
+    out
+}
+
+fn render_links_json(links: &[pdfcore::LinkInfo]) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::from("[");
+    for (i, link) in links.iter().enumerate() {
+        out.push_str(if i == 0 { "\n" } else { ",\n" });
+        let r = &link.rect;
+        let (kind, target, dest_page) = match &link.target {
+            pdfcore::LinkTarget::Uri(uri) => ("uri", json_string(uri), "null".to_string()),
+            pdfcore::LinkTarget::Internal { name, page } => (
+                "internal",
+                name.as_deref().map_or("null".to_string(), json_string),
+                page.map_or("null".to_string(), |p| p.to_string()),
+            ),
+            pdfcore::LinkTarget::Remote { file } => {
+                ("remote", json_string(file), "null".to_string())
+            }
+            pdfcore::LinkTarget::Action(action) => {
+                ("action", json_string(action), "null".to_string())
+            }
+            pdfcore::LinkTarget::None => ("none", "null".to_string(), "null".to_string()),
+        };
+        let _ = write!(
+            &mut out,
+            "  {{\"page\": {}, \"rect\": [{}, {}, {}, {}], \"type\": \"{kind}\", \"target\": {target}, \"dest_page\": {dest_page}}}",
+            link.page, r.llx, r.lly, r.urx, r.ury
+        );
+    }
+    out.push_str(if links.is_empty() { "]\n" } else { "\n]\n" });
+    out
+}
+
+fn render_barcodes_json(codes: &[pdfcore::Barcode]) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::from("[");
+    for (i, code) in codes.iter().enumerate() {
+        out.push_str(if i == 0 { "\n" } else { ",\n" });
+        let r = &code.rect;
+        let _ = write!(
+            &mut out,
+            "  {{\"page\": {}, \"type\": \"{}\", \"payload\": {}, \"rect\": [{}, {}, {}, {}]}}",
+            code.page,
+            code.kind.name(),
+            json_string(&code.text),
+            r.llx,
+            r.lly,
+            r.urx,
+            r.ury
+        );
+    }
+    out.push_str(if codes.is_empty() { "]\n" } else { "\n]\n" });
     out
 }
+
+fn render_objects_json(objects: &[pdfcore::ObjectSummary]) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::from("[");
+    for (i, obj) in objects.iter().enumerate() {
+        out.push_str(if i == 0 { "\n" } else { ",\n" });
+        let stream = obj
+            .stream_length
+            .map_or_else(|| "null".to_string(), |len| len.to_string());
+        let _ = write!(
+            &mut out,
+            "  {{\"object\": {}, \"generation\": {}, \"type\": {}, \"stream_length\": {stream}}}",
+            obj.id.0,
+            obj.id.1,
+            json_string(&obj.kind)
+        );
+    }
+    out.push_str(if objects.is_empty() { "]\n" } else { "\n]\n" });
+    out
+}
+
+fn render_size_report_json(report: &pdfcore::SizeReport) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::new();
+    out.push_str("{\n");
+    let _ = writeln!(&mut out, "  \"file_bytes\": {},", report.file_bytes);
+    out.push_str("  \"categories\": [");
+    for (i, bucket) in report.buckets.iter().enumerate() {
+        out.push_str(if i == 0 { "\n" } else { ",\n" });
+        let _ = write!(
+            &mut out,
+            "    {{\"category\": {}, \"bytes\": {}, \"objects\": {}}}",
+            json_string(bucket.category.name()),
+            bucket.bytes,
+            bucket.objects
+        );
+    }
+    out.push_str(if report.buckets.is_empty() {
+        "],\n"
+    } else {
+        "\n  ],\n"
+    });
+    out.push_str("  \"largest\": [");
+    for (i, obj) in report.largest.iter().enumerate() {
+        out.push_str(if i == 0 { "\n" } else { ",\n" });
+        let _ = write!(
+            &mut out,
+            "    {{\"object\": {}, \"generation\": {}, \"category\": {}, \"bytes\": {}}}",
+            obj.id.0,
+            obj.id.1,
+            json_string(obj.category.name()),
+            obj.bytes
+        );
+    }
+    out.push_str(if report.largest.is_empty() {
+        "]\n"
+    } else {
+        "\n  ]\n"
+    });
+    out.push_str("}\n");
+    out
+}
+
+fn render_sanitize_json(output: Option<&Path>, report: &pdfcore::SanitizeReport) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::new();
+    out.push_str("{\n");
+    let output = output.map_or("null".to_string(), |p| json_string(&p.to_string_lossy()));
+    let _ = writeln!(&mut out, "  \"output\": {output},");
+    let _ = writeln!(&mut out, "  \"clean\": {},", report.is_clean());
+    out.push_str("  \"findings\": [");
+    for (i, finding) in report.findings.iter().enumerate() {
+        out.push_str(if i == 0 { "\n" } else { ",\n" });
+        let _ = write!(
+            &mut out,
+            "    {{\"kind\": {}, \"detail\": {}}}",
+            json_string(finding.kind.name()),
+            json_string(&finding.detail)
+        );
+    }
+    out.push_str(if report.findings.is_empty() {
+        "]\n"
+    } else {
+        "\n  ]\n"
+    });
+    out.push_str("}\n");
+    out
+}
+
+fn render_redact_json(output: &Path, report: &pdfcore::RedactReport) -> String {
+    use std::fmt::Write as _;
+
+    let numbers = |pages: &[u32]| {
+        let items: Vec<String> = pages.iter().map(ToString::to_string).collect();
+        format!("[{}]", items.join(", "))
+    };
+    let mut out = String::new();
+    out.push_str("{\n");
+    let _ = writeln!(
+        &mut out,
+        "  \"output\": {},",
+        json_string(&output.to_string_lossy())
+    );
+    let _ = writeln!(&mut out, "  \"matches\": {},", report.matches);
+    let _ = writeln!(&mut out, "  \"glyphs_removed\": {},", report.glyphs_removed);
+    let _ = writeln!(
+        &mut out,
+        "  \"images_redacted\": {},",
+        report.images_redacted
+    );
+    let _ = writeln!(
+        &mut out,
+        "  \"annotations_removed\": {},",
+        report.annotations_removed
+    );
+    let _ = writeln!(
+        &mut out,
+        "  \"rasterized_pages\": {},",
+        numbers(&report.rasterized_pages)
+    );
+    let _ = writeln!(
+        &mut out,
+        "  \"unsearched_pages\": {}",
+        numbers(&report.unsearched_pages)
+    );
+    out.push_str("}\n");
+    out
+}
+
+fn render_strip_json(output: &Path, report: &pdfcore::StripReport) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::new();
+    out.push_str("{\n");
+    let _ = writeln!(
+        &mut out,
+        "  \"output\": {},",
+        json_string(&output.to_string_lossy())
+    );
+    let _ = writeln!(
+        &mut out,
+        "  \"info_keys\": {},",
+        json_string_array(&report.info_keys)
+    );
+    let _ = writeln!(&mut out, "  \"xmp_streams\": {},", report.xmp_streams);
+    let _ = writeln!(&mut out, "  \"document_id\": {},", report.document_id);
+    let _ = writeln!(
+        &mut out,
+        "  \"annotation_authors\": {}",
+        report.annotation_authors
+    );
+    out.push_str("}\n");
+    out
+}
+
+fn render_enforce_json(checked: usize, failures: &[Nonconforming]) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::new();
+    out.push_str("{\n");
+    let _ = writeln!(&mut out, "  \"checked\": {checked},");
+    out.push_str("  \"non_conforming\": [");
+    for (i, failure) in failures.iter().enumerate() {
+        out.push_str(if i == 0 { "\n" } else { ",\n" });
+        let violations = failure
+            .violations
+            .iter()
+            .map(|c| {
+                format!(
+                    "{{\"name\": {}, \"detail\": {}}}",
+                    json_string(c.name),
+                    json_string(&c.detail)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let optional =
+            |value: Option<String>| value.map_or("null".to_string(), |v| json_string(&v));
+        let _ = write!(
+            &mut out,
+            "    {{\"path\": {}, \"error\": {}, \"violations\": [{violations}], \"quarantined\": {}}}",
+            json_string(&failure.path.to_string_lossy()),
+            optional(failure.error.clone()),
+            optional(
+                failure
+                    .quarantined
+                    .as_ref()
+                    .map(|p| p.to_string_lossy().to_string())
+            ),
+        );
+    }
+    out.push_str(if failures.is_empty() {
+        "]\n"
+    } else {
+        "\n  ]\n"
+    });
+    out.push_str("}\n");
+    out
+}
+
+fn render_doctor_json(tools: &[pdfcore::ToolStatus], found: &dyn Fn(&str) -> bool) -> String {
+    use std::fmt::Write as _;
+
+    let optional = |value: Option<String>| value.map_or("null".to_string(), |v| json_string(&v));
+    let mut out = String::new();
+    out.push_str("{\n  \"tools\": [");
+    for (i, tool) in tools.iter().enumerate() {
+        out.push_str(if i == 0 { "\n" } else { ",\n" });
+        let _ = write!(
+            &mut out,
+            "    {{\"name\": {}, \"found\": {}, \"path\": {}, \"version\": {}, \"env_var\": {}, \"env_override\": {}}}",
+            json_string(tool.name),
+            tool.path.is_some(),
+            optional(tool.path.as_ref().map(|p| p.to_string_lossy().to_string())),
+            optional(tool.version.as_ref().map(ToString::to_string)),
+            json_string(tool.env_var),
+            tool.env_override,
+        );
+    }
+    out.push_str("\n  ],\n  \"commands\": [");
+    for (i, (command, needs, optional_tools)) in COMMAND_TOOLS.iter().enumerate() {
+        out.push_str(if i == 0 { "\n" } else { ",\n" });
+        let missing: Vec<String> = needs
+            .iter()
+            .chain(optional_tools.iter())
+            .filter(|t| !found(t))
+            .map(ToString::to_string)
+            .collect();
+        let _ = write!(
+            &mut out,
+            "    {{\"command\": {}, \"available\": {}, \"missing_tools\": {}}}",
+            json_string(command),
+            needs.iter().all(|t| found(t)),
+            json_string_array(&missing),
+        );
+    }
+    for op in pdfcore::Operation::ALL {
+        let engine = pdfcore::select_engine(op).ok();
+        let _ = write!(
+            &mut out,
+            ",\n    {{\"command\": {}, \"available\": {}, \"engine\": {}}}",
+            json_string(op.name()),
+            engine.is_some(),
+            optional(engine.map(|e| e.to_string())),
+        );
+    }
+    out.push_str("\n  ]\n}\n");
+    out
+}
+
+fn render_compress_report_json(output: &Path, report: &pdfcore::CompressReport) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::new();
+    out.push_str("{\n");
+    let _ = writeln!(
+        &mut out,
+        "  \"output\": {},",
+        json_string(&output.to_string_lossy())
+    );
+    let _ = writeln!(&mut out, "  \"input_bytes\": {},", report.input_bytes);
+    let _ = writeln!(&mut out, "  \"output_bytes\": {},", report.output_bytes);
+    let _ = writeln!(&mut out, "  \"ratio\": {:.4}", report.ratio);
+    out.push_str("}\n");
+    out
+}
+
+fn render_optimize_report_json(output: &Path, report: &pdfcore::OptimizeReport) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::new();
+    out.push_str("{\n");
+    let _ = writeln!(
+        &mut out,
+        "  \"output\": {},",
+        json_string(&output.to_string_lossy())
+    );
+    let _ = writeln!(
+        &mut out,
+        "  \"duplicates_removed\": {},",
+        report.duplicates_removed
+    );
+    let _ = writeln!(
+        &mut out,
+        "  \"unreferenced_removed\": {},",
+        report.unreferenced_removed
+    );
+    let _ = writeln!(
+        &mut out,
+        "  \"unreferenced_bytes\": {},",
+        report.unreferenced_bytes
+    );
+    let _ = writeln!(&mut out, "  \"input_bytes\": {},", report.sizes.input_bytes);
+    let _ = writeln!(
+        &mut out,
+        "  \"output_bytes\": {},",
+        report.sizes.output_bytes
+    );
+    let _ = writeln!(&mut out, "  \"ratio\": {:.4}", report.sizes.ratio);
+    out.push_str("}\n");
+    out
+}
+
+fn render_recompress_report_json(output: &Path, report: &pdfcore::RecompressReport) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::new();
+    out.push_str("{\n");
+    let _ = writeln!(
+        &mut out,
+        "  \"output\": {},",
+        json_string(&output.to_string_lossy())
+    );
+    let _ = writeln!(&mut out, "  \"recompressed\": {},", report.recompressed);
+    let _ = writeln!(&mut out, "  \"downsampled\": {},", report.downsampled);
+    let _ = writeln!(&mut out, "  \"unchanged\": {},", report.unchanged);
+    let _ = writeln!(&mut out, "  \"input_bytes\": {},", report.sizes.input_bytes);
+    let _ = writeln!(
+        &mut out,
+        "  \"output_bytes\": {},",
+        report.sizes.output_bytes
+    );
+    let _ = writeln!(&mut out, "  \"ratio\": {:.4}", report.sizes.ratio);
+    out.push_str("}\n");
+    out
+}
+
+/// e.g. `12.4 MB → 1.8 MB, 85% smaller`
+fn describe_compress_report(report: &pdfcore::CompressReport) -> String {
+    let savings = report.savings_percent();
+    let change = if savings >= 0.0 {
+        format!("{savings:.0}% smaller")
+    } else {
+        format!("{:.0}% larger", -savings)
+    };
+    format!(
+        "{} → {}, {change}",
+        format_bytes(report.input_bytes),
+        format_bytes(report.output_bytes)
+    )
+}
+
+/// Human-readable size using decimal units (1 MB = 1,000,000 bytes).
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+    if bytes < 1000 {
+        return format!("{bytes} B");
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let mut value = bytes as f64 / 1000.0;
+    let mut unit = UNITS[0];
+    for next in &UNITS[1..] {
+        if value < 1000.0 {
+            break;
+        }
+        value /= 1000.0;
+        unit = next;
+    }
+    format!("{value:.1} {unit}")
+}
+
+fn render_error_json(
+    code: &str,
+    exit_code: i32,
+    message: &str,
+    source: Option<&pdfcore::PdfError>,
+) -> String {
+    let tool = source
+        .and_then(pdfcore::PdfError::tool)
+        .map_or_else(|| "null".to_string(), json_string);
+    let status = match source {
+        Some(pdfcore::PdfError::ToolFailed { status, .. }) => status.to_string(),
+        _ => "null".to_string(),
+    };
+    let hint = source
+        .and_then(pdfcore::PdfError::hint)
+        .map_or_else(|| "null".to_string(), |h| json_string(&h));
+    format!(
+        "{{\"error\":{{\"code\":{},\"exit_code\":{exit_code},\"message\":{},\"tool\":{tool},\"status\":{status},\"hint\":{hint}}}}}",
+        json_string(code),
+        json_string(message),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_records() -> Vec<TextRecord> {
+        vec![
+            TextRecord {
+                path: "a, b.pdf".to_string(),
+                page: Some(1),
+                pages: None,
+                text: Ok("He said \"hi\",\nthen left.".to_string()),
+            },
+            TextRecord {
+                path: "plain.pdf".to_string(),
+                page: Some(2),
+                pages: None,
+                text: Ok("plain".to_string()),
+            },
+            TextRecord {
+                path: "broken.pdf".to_string(),
+                page: None,
+                pages: None,
+                text: Err("not a PDF:\r\nbad header".to_string()),
+            },
+        ]
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a, b"), "\"a, b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("cr\r"), "\"cr\r\"");
+        assert_eq!(csv_field(" padded"), "\" padded\"");
+        assert_eq!(csv_field(""), "");
+
+        assert_eq!(
+            render_text_csv(&text_records(), TextRecords::Page),
+            "path,page,text,error\n\
+             \"a, b.pdf\",1,\"He said \"\"hi\"\",\nthen left.\",\n\
+             plain.pdf,2,plain,\n\
+             broken.pdf,,,\"not a PDF:\r\nbad header\"\n"
+        );
+    }
+
+    #[test]
+    fn unreadable_files_are_reported_and_skipped() -> anyhow::Result<()> {
+        let scratch = pdfcore::tempspace::TempSpace::new("info-summary-test")?;
+        let text = scratch.write("page.txt", "A page of text.\n")?;
+        let mut files = Vec::new();
+        for name in ["first.pdf", "broken.pdf", "second.pdf"] {
+            let path = scratch.file(name);
+            if name == "broken.pdf" {
+                fs::write(&path, "not a PDF")?;
+            } else {
+                pdfcore::text_to_pdf(&text, &path, &pdfcore::TextToPdfOptions::default())?;
+            }
+            files.push(path);
+        }
+
+        let progress = pdfcore::Progress::default();
+        let (out, result) = info_summary(&files, InfoFormat::Csv, None, true, &progress);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 4, "{out}");
+        for (line, path) in lines.iter().skip(1).zip(&files) {
+            assert!(line.starts_with(&path.display().to_string()), "{line}");
+            let failed = !line.ends_with(',');
+            assert_eq!(failed, path.ends_with("broken.pdf"), "{line}");
+        }
+        assert!(matches!(
+            result.map_err(anyhow::Error::downcast::<pdfcore::PdfError>),
+            Err(Ok(pdfcore::PdfError::InputsFailed {
+                failed: 1,
+                total: 3
+            }))
+        ));
+
+        // Without --keep-going, the first failure ends the run.
+        let (out, result) = info_summary(&files, InfoFormat::Jsonl, None, false, &progress);
+        assert_eq!(out.lines().count(), 2, "{out}");
+        let error = result.err().map(|e| format!("{e:#}")).unwrap_or_default();
+        assert!(error.contains("broken.pdf"), "{error}");
+        Ok(())
+    }
+
+    #[test]
+    fn jsonl_has_one_object_per_line() -> Result<(), serde_json::Error> {
+        let records = text_records();
+        let out = render_text_jsonl(&records, TextRecords::Page);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), records.len());
+        assert!(out.ends_with('\n'));
+        for (line, record) in lines.iter().zip(&records) {
+            let value: serde_json::Value = serde_json::from_str(line)?;
+            assert!(value.is_object(), "{line}");
+            assert_eq!(value["path"], record.path.as_str());
+        }
+        let first: serde_json::Value = serde_json::from_str(lines[0])?;
+        assert_eq!(first["text"], "He said \"hi\",\nthen left.");
+        assert_eq!(first["page"], 1);
+        let last: serde_json::Value = serde_json::from_str(lines[2])?;
+        assert!(last["text"].is_null() && last["page"].is_null());
+
+        let rows = [InfoRow {
+            path: PathBuf::from("missing\n.pdf"),
+            size: 0,
+            info: Err(anyhow::anyhow!("no such file")),
+        }];
+        let out = render_info_jsonl(&rows);
+        assert_eq!(out.lines().count(), 1);
+        let value: serde_json::Value = serde_json::from_str(out.trim_end())?;
+        assert_eq!(value["error"], "no such file");
+        assert!(value["pages"].is_null());
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+//! `--open`: show the file a command wrote in the platform's default
+//! viewer, e.g. to check a watermark or crop before trying other values.
+
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::Context;
+
+use crate::{
+    Commands, DestsCommand, LinksCommand, MetaCommand, PermissionsCommand, RevisionsCommand,
+    XfaCommand,
+};
+
+/// The file `command` writes, for commands that write one.
+pub(crate) fn output(command: &Commands) -> Option<PathBuf> {
+    match command {
+        Commands::Qdf { output, .. }
+        | Commands::EmbedFonts { output, .. }
+        | Commands::Merge { output, .. }
+        | Commands::Sign { output, .. }
+        | Commands::SignVisual { output, .. }
+        | Commands::HeaderFooter { output, .. }
+        | Commands::Pick { output, .. }
+        | Commands::ToSvg { output, .. }
+        | Commands::ToPs { output, .. }
+        | Commands::FromPs { output, .. }
+        | Commands::FromTiff { output, .. }
+        | Commands::ToTiff { output, .. }
+        | Commands::FromText { output, .. }
+        | Commands::FromHtml { output, .. }
+        | Commands::Convert { output, .. }
+        | Commands::Rotate { output, .. }
+        | Commands::Compress { output, .. }
+        | Commands::Optimize { output, .. }
+        | Commands::RecompressImages { output, .. }
+        | Commands::TranscodeImages { output, .. }
+        | Commands::Run { output, .. }
+        | Commands::ConvertColor { output, .. }
+        | Commands::CourtReady { output, .. }
+        | Commands::PrintReady { output, .. }
+        | Commands::Bleed { output, .. }
+        | Commands::ToPdfx { output, .. }
+        | Commands::A11yPrep { output, .. }
+        | Commands::SetLang { output, .. }
+        | Commands::ViewerPrefs { output, .. }
+        | Commands::OpenAction { output, .. }
+        | Commands::Redact { output, .. }
+        | Commands::StripMetadata { output, .. }
+        | Commands::RegenId { output, .. }
+        | Commands::Links {
+            action: Some(LinksCommand::Rewrite { output, .. } | LinksCommand::Strip { output, .. }),
+            ..
+        }
+        | Commands::Revisions {
+            action: Some(RevisionsCommand::Extract { output, .. }),
+            ..
+        }
+        | Commands::Permissions {
+            action: Some(PermissionsCommand::Set { output, .. }),
+            ..
+        }
+        | Commands::Dests {
+            action: DestsCommand::Add { output, .. },
+            ..
+        }
+        | Commands::Meta {
+            action: MetaCommand::Apply { output, .. },
+        }
+        | Commands::PageSizes {
+            output: Some(output),
+            ..
+        }
+        | Commands::Boxes {
+            output: Some(output),
+            ..
+        }
+        | Commands::ExtractText {
+            output: Some(output),
+            ..
+        }
+        | Commands::Sanitize {
+            output: Some(output),
+            ..
+        }
+        | Commands::InferTitle {
+            output: Some(output),
+            ..
+        }
+        | Commands::FromEml {
+            merge: Some(output),
+            ..
+        }
+        | Commands::Xfa {
+            action:
+                XfaCommand::Dump {
+                    output: Some(output),
+                    ..
+                },
+        } => Some(output.clone()),
+        _ => None,
+    }
+}
+
+/// Open `path` in the default application for its type (`open` on macOS,
+/// `start` on Windows, `xdg-open` elsewhere), without waiting for it.
+pub(crate) fn launch(path: &Path) -> anyhow::Result<()> {
+    let mut cmd = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        // The empty argument is the window title `start` would otherwise
+        // take a quoted path for.
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", "start", ""]);
+        cmd
+    } else {
+        Command::new("xdg-open")
+    };
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    cmd.arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("opening {} with {program}", path.display()))?;
+    Ok(())
+}
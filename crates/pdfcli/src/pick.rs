@@ -0,0 +1,307 @@
+//! `pdfcli pick`: choose and reorder pages in the terminal.
+//!
+//! Each page is listed with a short preview of its text. Marked pages are
+//! written in the order shown. The screen is drawn with `ratatui` on its
+//! `crossterm` backend, so this works in Unix terminals and the Windows
+//! console alike.
+
+use std::io::{self, IsTerminal};
+
+use anyhow::bail;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{List, ListItem, ListState},
+    DefaultTerminal, Frame,
+};
+
+/// Show the picker for pages with the given `previews` and return the
+/// marked pages (1-based) in the chosen order, or `None` if the user quit.
+pub(crate) fn pick(title: &str, previews: Vec<String>) -> anyhow::Result<Option<Vec<u32>>> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        bail!("pick is interactive; run it in a terminal");
+    }
+    let mut picker = Picker::new(previews);
+    let mut screen = Screen::enter()?;
+    loop {
+        screen.terminal.draw(|frame| picker.render(frame, title))?;
+        match event::read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => {
+                match picker.handle(Key::from(key)) {
+                    Some(Done::Write(pages)) => return Ok(Some(pages)),
+                    Some(Done::Quit) => return Ok(None),
+                    None => {}
+                }
+            }
+            // Resizes and the rest just redraw.
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    MoveUp,
+    MoveDown,
+    Toggle,
+    ToggleAll,
+    Enter,
+    Quit,
+    Other,
+}
+
+impl From<KeyEvent> for Key {
+    fn from(key: KeyEvent) -> Self {
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Up if shift => Self::MoveUp,
+            KeyCode::Down if shift => Self::MoveDown,
+            KeyCode::Char('c' | 'd') if ctrl => Self::Quit,
+            KeyCode::Up | KeyCode::Char('k') => Self::Up,
+            KeyCode::Down | KeyCode::Char('j') => Self::Down,
+            KeyCode::PageUp => Self::PageUp,
+            KeyCode::PageDown => Self::PageDown,
+            KeyCode::Home | KeyCode::Char('g') => Self::Home,
+            KeyCode::End | KeyCode::Char('G') => Self::End,
+            KeyCode::Char('K') => Self::MoveUp,
+            KeyCode::Char('J') => Self::MoveDown,
+            KeyCode::Char(' ') => Self::Toggle,
+            KeyCode::Char('a') => Self::ToggleAll,
+            KeyCode::Enter => Self::Enter,
+            KeyCode::Esc | KeyCode::Char('q') => Self::Quit,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// How the picker was left.
+#[derive(Debug, PartialEq, Eq)]
+enum Done {
+    /// Write these pages, in this order.
+    Write(Vec<u32>),
+    Quit,
+}
+
+struct Row {
+    page: u32,
+    preview: String,
+    marked: bool,
+}
+
+struct Picker {
+    rows: Vec<Row>,
+    cursor: usize,
+    /// Rows that fit on screen, as of the last draw.
+    height: usize,
+    /// The list's selection and scroll offset, kept between draws.
+    list: ListState,
+    message: Option<&'static str>,
+}
+
+impl Picker {
+    fn new(previews: Vec<String>) -> Self {
+        Self {
+            rows: (1..)
+                .zip(previews)
+                .map(|(page, preview)| Row {
+                    page,
+                    preview,
+                    marked: false,
+                })
+                .collect(),
+            cursor: 0,
+            height: 1,
+            list: ListState::default(),
+            message: None,
+        }
+    }
+
+    /// Apply `key`; `Some` when the picker is done.
+    fn handle(&mut self, key: Key) -> Option<Done> {
+        let last = self.rows.len().saturating_sub(1);
+        self.message = None;
+        match key {
+            Key::Up => self.cursor = self.cursor.saturating_sub(1),
+            Key::Down => self.cursor = (self.cursor + 1).min(last),
+            Key::PageUp => self.cursor = self.cursor.saturating_sub(self.height),
+            Key::PageDown => self.cursor = (self.cursor + self.height).min(last),
+            Key::Home => self.cursor = 0,
+            Key::End => self.cursor = last,
+            Key::MoveUp if self.cursor > 0 => {
+                self.rows.swap(self.cursor, self.cursor - 1);
+                self.cursor -= 1;
+            }
+            Key::MoveDown if self.cursor < last => {
+                self.rows.swap(self.cursor, self.cursor + 1);
+                self.cursor += 1;
+            }
+            Key::Toggle => {
+                if let Some(row) = self.rows.get_mut(self.cursor) {
+                    row.marked = !row.marked;
+                }
+                self.cursor = (self.cursor + 1).min(last);
+            }
+            Key::ToggleAll => {
+                let mark = !self.rows.iter().all(|row| row.marked);
+                for row in &mut self.rows {
+                    row.marked = mark;
+                }
+            }
+            Key::Enter => {
+                let pages: Vec<u32> = self
+                    .rows
+                    .iter()
+                    .filter(|row| row.marked)
+                    .map(|row| row.page)
+                    .collect();
+                if !pages.is_empty() {
+                    return Some(Done::Write(pages));
+                }
+                self.message = Some("mark at least one page with space first");
+            }
+            Key::Quit => return Some(Done::Quit),
+            Key::MoveUp | Key::MoveDown | Key::Other => {}
+        }
+        None
+    }
+
+    /// Draw the title line, the pages with the cursor's in reverse video,
+    /// and the help or message line.
+    fn render(&mut self, frame: &mut Frame, title: &str) {
+        let [header, body, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        self.height = usize::from(body.height).max(1);
+
+        let marked = self.rows.iter().filter(|row| row.marked).count();
+        frame.render_widget(
+            Line::from(format!(
+                "{title}: {marked} of {} page(s) marked",
+                self.rows.len()
+            )),
+            header,
+        );
+        let items: Vec<ListItem> = self
+            .rows
+            .iter()
+            .map(|row| {
+                ListItem::new(format!(
+                    "[{}] {:>5}  {}",
+                    if row.marked { 'x' } else { ' ' },
+                    row.page,
+                    row.preview
+                ))
+            })
+            .collect();
+        let list = List::new(items)
+            .highlight_symbol("> ")
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        self.list.select(Some(self.cursor));
+        frame.render_stateful_widget(list, body, &mut self.list);
+        frame.render_widget(
+            Line::from(
+                self.message.unwrap_or(
+                    "space mark  shift+up/down move page  a all/none  enter write  q quit",
+                ),
+            ),
+            footer,
+        );
+    }
+}
+
+/// The terminal in raw mode on the alternate screen, until dropped.
+struct Screen {
+    terminal: DefaultTerminal,
+}
+
+impl Screen {
+    fn enter() -> io::Result<Self> {
+        Ok(Self {
+            terminal: ratatui::try_init()?,
+        })
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        ratatui::restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_pages(pages: usize) -> Picker {
+        Picker::new((1..=pages).map(|n| format!("page {n}")).collect())
+    }
+
+    fn press(picker: &mut Picker, keys: &[Key]) -> Option<Done> {
+        keys.iter().find_map(|&key| picker.handle(key))
+    }
+
+    #[test]
+    fn marked_pages_are_written_in_the_order_shown() {
+        let mut picker = with_pages(4);
+        // Mark 1, move 3 to the top and mark it, then mark 4.
+        let keys = [
+            Key::Toggle,
+            Key::Down,
+            Key::MoveUp,
+            Key::MoveUp,
+            Key::Toggle,
+            Key::End,
+            Key::Toggle,
+            Key::Enter,
+        ];
+        assert_eq!(press(&mut picker, &keys), Some(Done::Write(vec![3, 1, 4])));
+
+        let mut picker = with_pages(3);
+        let keys = [Key::Home, Key::MoveDown, Key::MoveDown, Key::MoveDown];
+        assert_eq!(press(&mut picker, &keys), None);
+        assert_eq!(picker.cursor, 2);
+        let keys = [Key::ToggleAll, Key::Enter];
+        assert_eq!(press(&mut picker, &keys), Some(Done::Write(vec![2, 3, 1])));
+    }
+
+    #[test]
+    fn enter_needs_a_marked_page() {
+        let mut picker = with_pages(2);
+        assert_eq!(picker.handle(Key::Enter), None);
+        assert!(picker.message.is_some());
+        assert_eq!(
+            press(&mut picker, &[Key::ToggleAll, Key::ToggleAll, Key::Enter]),
+            None
+        );
+        assert_eq!(picker.handle(Key::Up), None);
+        assert!(picker.message.is_none());
+        assert_eq!(picker.handle(Key::Quit), Some(Done::Quit));
+    }
+
+    #[test]
+    fn paging_stays_in_the_list() {
+        let mut picker = with_pages(10);
+        picker.height = 4;
+        assert_eq!(press(&mut picker, &[Key::PageDown, Key::PageDown]), None);
+        assert_eq!(picker.cursor, 8);
+        picker.handle(Key::PageDown);
+        assert_eq!(picker.cursor, 9);
+        picker.handle(Key::PageUp);
+        assert_eq!(picker.cursor, 5);
+        press(&mut picker, &[Key::PageUp, Key::PageUp]);
+        assert_eq!(picker.cursor, 0);
+        assert_eq!(Picker::new(Vec::new()).handle(Key::PageDown), None);
+    }
+}
@@ -0,0 +1,109 @@
+//! `https://` and `s3://` inputs, with the `remote` feature: the input of
+//! a command that takes one PDF is downloaded to the scratch directory
+//! first, and the command runs on the download. Outputs stay local.
+
+use std::path::PathBuf;
+
+use crate::Commands;
+
+/// The input path of commands that take a single input.
+fn input_mut(command: &mut Commands) -> Option<&mut PathBuf> {
+    match command {
+        Commands::Fonts { input, .. }
+        | Commands::SizeReport { input, .. }
+        | Commands::PageSizes { input, .. }
+        | Commands::InkCoverage { input, .. }
+        | Commands::Stats { input, .. }
+        | Commands::Objects { input, .. }
+        | Commands::ShowObject { input, .. }
+        | Commands::Barcodes { input, .. }
+        | Commands::Analyze { input, .. }
+        | Commands::Fingerprint { input, .. }
+        | Commands::Content { input, .. }
+        | Commands::Qdf { input, .. }
+        | Commands::EmbedFonts { input, .. }
+        | Commands::Sign { input, .. }
+        | Commands::Signatures { input, .. }
+        | Commands::SignVisual { input, .. }
+        | Commands::HeaderFooter { input, .. }
+        | Commands::Pick { input, .. }
+        | Commands::SplitPages { input, .. }
+        | Commands::Render { input, .. }
+        | Commands::ToSvg { input, .. }
+        | Commands::ToPs { input, .. }
+        | Commands::FromPs { input, .. }
+        | Commands::FromTiff { input, .. }
+        | Commands::ToTiff { input, .. }
+        | Commands::Print { input, .. }
+        | Commands::ExtractTables { input, .. }
+        | Commands::FromText { input, .. }
+        | Commands::FromHtml { input, .. }
+        | Commands::Convert { input, .. }
+        | Commands::Rotate { input, .. }
+        | Commands::Compress { input, .. }
+        | Commands::Optimize { input, .. }
+        | Commands::RecompressImages { input, .. }
+        | Commands::TranscodeImages { input, .. }
+        | Commands::Run { input, .. }
+        | Commands::ConvertColor { input, .. }
+        | Commands::CourtReady { input, .. }
+        | Commands::PrintReady { input, .. }
+        | Commands::Bleed { input, .. }
+        | Commands::ToPdfx { input, .. }
+        | Commands::A11yPrep { input, .. }
+        | Commands::SetLang { input, .. }
+        | Commands::InferTitle { input, .. }
+        | Commands::ViewerPrefs { input, .. }
+        | Commands::OpenAction { input, .. }
+        | Commands::A11yCheck { input, .. }
+        | Commands::Sanitize { input, .. }
+        | Commands::Redact { input, .. }
+        | Commands::StripMetadata { input, .. }
+        | Commands::RegenId { input, .. } => Some(input),
+        _ => None,
+    }
+}
+
+/// If the input of `command` is a URL, download it and point the command at
+/// the download, which is removed when the returned value is dropped. With
+/// `dry_run` the download command is printed too; the input is still
+/// downloaded, so that the command's own plan can be checked against it.
+#[cfg(feature = "remote")]
+pub(crate) fn fetch_input(
+    command: &mut Commands,
+    dry_run: bool,
+) -> anyhow::Result<Option<pdfcore::RemoteInput>> {
+    use anyhow::Context;
+
+    let Some(input) = input_mut(command) else {
+        return Ok(None);
+    };
+    let Some(url) = input
+        .to_str()
+        .filter(|s| pdfcore::is_remote(s))
+        .map(str::to_string)
+    else {
+        return Ok(None);
+    };
+    if dry_run {
+        let plan =
+            pdfcore::fetch_plan(&url).with_context(|| format!("planning download of {url}"))?;
+        print!("{plan}");
+    }
+    let remote = pdfcore::fetch(&url).with_context(|| format!("downloading {url}"))?;
+    *input = remote.path().to_path_buf();
+    Ok(Some(remote))
+}
+
+/// Without the `remote` feature, refuse URLs rather than report them as
+/// missing files.
+#[cfg(not(feature = "remote"))]
+pub(crate) fn fetch_input(command: &mut Commands, _dry_run: bool) -> anyhow::Result<Option<()>> {
+    if let Some(input) = input_mut(command) {
+        let input = input.to_string_lossy();
+        if input.starts_with("https://") || input.starts_with("s3://") {
+            anyhow::bail!("{input}: URL inputs need pdfcli built with the `remote` feature");
+        }
+    }
+    Ok(None)
+}
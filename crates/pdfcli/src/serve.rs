@@ -0,0 +1,457 @@
+//! `pdfcli serve`: the core operations over HTTP.
+//!
+//! Each request uploads one PDF, either as the raw body or as a
+//! `multipart/form-data` field (named `file`, else the first file), and gets
+//! the result back:
+//!
+//! - `POST /<step>?<option>=<value>...` runs one pipeline step (`decrypt`,
+//!   `rotate`, `compress`, `convert-color`, `flatten`, `linearize`,
+//!   `print-ready`) and returns the PDF.
+//! - `POST /run?steps=<steps>` runs a pipeline, as `pdfcli run`.
+//! - `POST /info` returns the document information as JSON.
+//! - `POST /extract-text` returns the text.
+//! - `GET /health` returns `ok`.
+//!
+//! Every request works in its own temporary directory, removed when the
+//! response is sent. Failures are JSON errors as printed by `--json-errors`.
+
+use std::{
+    io::{Cursor, Read},
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::Context;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+pub(crate) struct ServeOptions<'a> {
+    pub listen: &'a str,
+    /// Largest accepted upload, in bytes.
+    pub max_upload: u64,
+    /// Requests processed at once; more wait in the queue.
+    pub concurrency: usize,
+}
+
+pub(crate) fn serve(options: &ServeOptions<'_>) -> anyhow::Result<()> {
+    let server = Server::http(options.listen)
+        .map_err(|e| anyhow::anyhow!("{e}"))
+        .with_context(|| format!("listening on {}", options.listen))?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let handler_stop = Arc::clone(&stop);
+    ctrlc::set_handler(move || handler_stop.store(true, Ordering::SeqCst))
+        .context("installing the Ctrl-C handler")?;
+    eprintln!(
+        "listening: http://{} ({} at a time, uploads up to {} bytes; Ctrl-C to stop)",
+        server.server_addr(),
+        options.concurrency,
+        options.max_upload
+    );
+
+    thread::scope(|scope| {
+        for _ in 0..options.concurrency {
+            scope.spawn(|| {
+                while !stop.load(Ordering::SeqCst) {
+                    match server.recv_timeout(Duration::from_millis(200)) {
+                        Ok(Some(request)) => handle(request, options.max_upload),
+                        Ok(None) => {}
+                        Err(e) => tracing::warn!(error = %e, "failed to accept a request"),
+                    }
+                }
+            });
+        }
+    });
+    eprintln!("stopped");
+    Ok(())
+}
+
+type HttpResponse = Response<Cursor<Vec<u8>>>;
+
+fn handle(mut request: Request, max_upload: u64) {
+    let method = request.method().clone();
+    let url = request.url().to_string();
+    // A panic in one request must not take the worker down with it.
+    let response = match panic::catch_unwind(AssertUnwindSafe(|| respond(&mut request, max_upload)))
+    {
+        Ok(Ok(response)) => response,
+        Ok(Err(e)) => error_response(&e),
+        Err(_) => {
+            error_response(&HttpError::new(500, "internal_error", "internal error (panic)").into())
+        }
+    };
+    tracing::debug!(%method, %url, status = response.status_code().0, "handled request");
+    if let Err(e) = request.respond(response) {
+        tracing::warn!(error = %e, "failed to send a response");
+    }
+}
+
+fn respond(request: &mut Request, max_upload: u64) -> anyhow::Result<HttpResponse> {
+    let (path, query) = request
+        .url()
+        .split_once('?')
+        .map_or((request.url(), ""), |(p, q)| (p, q));
+    let route = path.trim_matches('/').to_string();
+    let query = parse_query(query);
+
+    if route == "health" {
+        return Ok(Response::from_string("ok\n"));
+    }
+    if *request.method() != Method::Post {
+        return Err(HttpError::new(405, "method_not_allowed", "use POST").into());
+    }
+
+    let scratch = pdfcore::tempspace::TempSpace::new("serve")?;
+    let input = scratch.write("input.pdf", read_upload(request, max_upload)?)?;
+    let output = scratch.file("output.pdf");
+    match route.as_str() {
+        "info" => {
+            let info = pdfcore::info(&input)?;
+            let languages = crate::document_languages(&input, &info)?;
+            Ok(with_type(
+                Response::from_string(crate::render_info_json(&info, languages.as_deref())),
+                "application/json",
+            ))
+        }
+        "extract-text" => {
+            let text = pdfcore::ExtractText::new(&input).run()?;
+            Ok(with_type(
+                Response::from_string(text),
+                "text/plain; charset=utf-8",
+            ))
+        }
+        "run" => {
+            let steps = query
+                .iter()
+                .find(|(key, _)| key == "steps")
+                .map(|(_, value)| value.as_str())
+                .ok_or_else(|| HttpError::new(400, "invalid_argument", "missing ?steps="))?;
+            let pipeline: pdfcore::Pipeline = steps.parse()?;
+            pdf_response(&pipeline, &input, &output)
+        }
+        step if pdfcore::PipelineStep::NAMES.contains(&step) => {
+            let pipeline = pdfcore::Pipeline::new(vec![query_step(step, &query)?])?;
+            pdf_response(&pipeline, &input, &output)
+        }
+        _ => Err(HttpError::new(404, "not_found", format!("no such operation: /{route}")).into()),
+    }
+}
+
+/// The pipeline step `name` with the query pairs as its options.
+fn query_step(name: &str, query: &[(String, String)]) -> pdfcore::Result<pdfcore::PipelineStep> {
+    let options: Vec<(&str, &str)> = query
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+    pdfcore::PipelineStep::from_options(name, &options)
+}
+
+fn pdf_response(
+    pipeline: &pdfcore::Pipeline,
+    input: &Path,
+    output: &Path,
+) -> anyhow::Result<HttpResponse> {
+    pipeline.run(input, output)?;
+    let bytes = std::fs::read(output)?;
+    let response = with_type(Response::from_data(bytes), "application/pdf");
+    Ok(
+        match Header::from_bytes("Content-Disposition", "attachment; filename=\"output.pdf\"") {
+            Ok(header) => response.with_header(header),
+            Err(()) => response,
+        },
+    )
+}
+
+/// The uploaded PDF: the `file` part (or the first file) of a multipart
+/// form, else the whole body.
+fn read_upload(request: &mut Request, max_upload: u64) -> anyhow::Result<Vec<u8>> {
+    let too_large = || {
+        HttpError::new(
+            413,
+            "payload_too_large",
+            format!("uploads are limited to {max_upload} bytes"),
+        )
+    };
+    if request
+        .body_length()
+        .is_some_and(|len| len as u64 > max_upload)
+    {
+        return Err(too_large().into());
+    }
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .take(max_upload + 1)
+        .read_to_end(&mut body)?;
+    if body.len() as u64 > max_upload {
+        return Err(too_large().into());
+    }
+
+    let content_type = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Content-Type"))
+        .map(|h| h.value.as_str().to_string())
+        .unwrap_or_default();
+    let Some(boundary) = multipart_boundary(&content_type) else {
+        return Ok(body);
+    };
+    multipart_file(&body, &boundary).ok_or_else(|| {
+        HttpError::new(400, "invalid_argument", "no file in the multipart form").into()
+    })
+}
+
+fn multipart_boundary(content_type: &str) -> Option<String> {
+    let (kind, params) = content_type.split_once(';')?;
+    if !kind.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    params.split(';').find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("boundary")
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+/// The content of the part named `file`, else of the first part with a
+/// file name.
+fn multipart_file(body: &[u8], boundary: &str) -> Option<Vec<u8>> {
+    let delimiter = format!("--{boundary}");
+    let mut parts = Vec::new();
+    let mut rest = &body[find(body, delimiter.as_bytes())? + delimiter.len()..];
+    while !rest.starts_with(b"--") {
+        let start = find(rest, b"\r\n\r\n")?;
+        let headers = String::from_utf8_lossy(&rest[..start]).to_ascii_lowercase();
+        let content = &rest[start + 4..];
+        let end = find(content, format!("\r\n{delimiter}").as_bytes())?;
+        parts.push((headers, &content[..end]));
+        rest = &content[end + 2 + delimiter.len()..];
+    }
+    parts
+        .iter()
+        .find(|(headers, _)| headers.contains("name=\"file\""))
+        .or_else(|| {
+            parts
+                .iter()
+                .find(|(headers, _)| headers.contains("filename="))
+        })
+        .map(|(_, content)| content.to_vec())
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// `key=value` pairs, percent-decoded.
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let mut out = Vec::with_capacity(s.len());
+    let mut bytes = s.bytes();
+    while let Some(b) = bytes.next() {
+        match b {
+            b'+' => out.push(b' '),
+            b'%' => {
+                let hex: Vec<u8> = bytes.by_ref().take(2).collect();
+                if let Some(decoded) = std::str::from_utf8(&hex)
+                    .ok()
+                    .filter(|h| h.len() == 2 && h.bytes().all(|b| b.is_ascii_hexdigit()))
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                {
+                    out.push(decoded);
+                } else {
+                    out.push(b'%');
+                    out.extend_from_slice(&hex);
+                }
+            }
+            _ => out.push(b),
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn with_type<R: Read>(response: Response<R>, content_type: &str) -> Response<R> {
+    match Header::from_bytes("Content-Type", content_type) {
+        Ok(header) => response.with_header(header),
+        Err(()) => response,
+    }
+}
+
+/// A failure that is the client's doing rather than an operation's.
+#[derive(Debug)]
+struct HttpError {
+    status: u16,
+    code: &'static str,
+    message: String,
+}
+
+impl HttpError {
+    fn new(status: u16, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for HttpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+fn error_response(e: &anyhow::Error) -> HttpResponse {
+    let (status, body) = if let Some(http) = e.downcast_ref::<HttpError>() {
+        (
+            http.status,
+            crate::render_error_json(http.code, 1, &http.message, None),
+        )
+    } else {
+        let source = e
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<pdfcore::PdfError>());
+        let status = match source {
+            Some(
+                pdfcore::PdfError::InvalidArgument(_)
+                | pdfcore::PdfError::PdfParse { .. }
+                | pdfcore::PdfError::InputNotFound(_)
+                | pdfcore::PdfError::Encrypted { .. },
+            ) => 400,
+            Some(pdfcore::PdfError::MissingTool { .. } | pdfcore::PdfError::ToolTooOld { .. }) => {
+                503
+            }
+            Some(pdfcore::PdfError::Timeout { .. }) => 504,
+            _ => 500,
+        };
+        let code = source.map_or("error", pdfcore::PdfError::code);
+        let exit_code = source.map_or(1, pdfcore::PdfError::exit_code);
+        (
+            status,
+            crate::render_error_json(code, exit_code, &format!("{e:#}"), source),
+        )
+    };
+    with_type(
+        Response::from_string(body).with_status_code(status),
+        "application/json",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write as _;
+
+    use super::*;
+
+    #[test]
+    fn queries_are_percent_decoded() {
+        assert_eq!(
+            parse_query("degrees=90&pages=1-3&&password=a+b%26c%3D&flag"),
+            [
+                ("degrees".to_string(), "90".to_string()),
+                ("pages".to_string(), "1-3".to_string()),
+                ("password".to_string(), "a b&c=".to_string()),
+                ("flag".to_string(), String::new()),
+            ]
+        );
+        assert_eq!(percent_decode("caf%C3%A9%20menu"), "café menu");
+        // Bad escapes are kept as they are.
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+        assert_eq!(percent_decode("%+1"), "%+1");
+    }
+
+    #[test]
+    fn query_values_do_not_add_options() -> pdfcore::Result<()> {
+        let query = parse_query("password=secret%20--bogus%20x");
+        assert_eq!(
+            query_step("decrypt", &query)?,
+            pdfcore::PipelineStep::Decrypt {
+                password: Some("secret --bogus x".to_string())
+            }
+        );
+        let query = parse_query("degrees=90%20--pages%201");
+        assert!(query_step("rotate", &query).is_err());
+        assert!(query_step("flatten", &parse_query("bogus=1")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn boundaries_come_from_the_content_type() {
+        assert_eq!(
+            multipart_boundary("multipart/form-data; boundary=\"abc 123\"").as_deref(),
+            Some("abc 123")
+        );
+        assert_eq!(
+            multipart_boundary("Multipart/Form-Data;charset=utf-8; BOUNDARY=x").as_deref(),
+            Some("x")
+        );
+        assert_eq!(multipart_boundary("application/pdf"), None);
+        assert_eq!(multipart_boundary("multipart/mixed; boundary=x"), None);
+        assert_eq!(multipart_boundary("multipart/form-data"), None);
+    }
+
+    fn form(parts: &[(&str, &str)]) -> Vec<u8> {
+        let mut body = String::from("preamble\r\n");
+        for (disposition, content) in parts {
+            let _ = write!(
+                body,
+                "--b\r\nContent-Disposition: form-data; {disposition}\r\n\r\n{content}\r\n"
+            );
+        }
+        body.push_str("--b--\r\n");
+        body.into_bytes()
+    }
+
+    #[test]
+    fn the_file_part_is_found() {
+        let body = form(&[
+            ("name=\"note\"", "hello"),
+            ("name=\"scan\"; filename=\"a.pdf\"", "%PDF-a"),
+            (
+                "name=\"file\"; filename=\"b.pdf\"",
+                "%PDF-b\r\n--not-the-end",
+            ),
+        ]);
+        assert_eq!(
+            multipart_file(&body, "b").as_deref(),
+            Some(&b"%PDF-b\r\n--not-the-end"[..])
+        );
+        let body = form(&[
+            ("name=\"note\"", "hello"),
+            ("name=\"scan\"; filename=\"a.pdf\"", "%PDF-a"),
+        ]);
+        assert_eq!(multipart_file(&body, "b").as_deref(), Some(&b"%PDF-a"[..]));
+    }
+
+    #[test]
+    fn forms_without_a_file_have_none() {
+        assert_eq!(multipart_file(&form(&[("name=\"note\"", "hi")]), "b"), None);
+        assert_eq!(multipart_file(&form(&[]), "b"), None);
+        assert_eq!(multipart_file(b"no delimiter here", "b"), None);
+        // A part that never ends.
+        assert_eq!(
+            multipart_file(
+                b"--b\r\nContent-Disposition: form-data; name=\"file\"\r\n\r\n%PDF",
+                "b"
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,343 @@
+//! `pdfcli watch`: run a pipeline on every PDF that arrives in a directory.
+//!
+//! Files are processed once they have been quiet for the debounce interval,
+//! so half-copied files are not picked up. Each result is written under a
+//! hidden name and renamed into place when complete. Inputs that were
+//! processed are recorded in a ledger (by name, size, and modification
+//! time), so restarting the watcher does not redo them, while a replaced
+//! file is processed again.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    time::{Duration, Instant, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context};
+use notify::{EventKind, RecursiveMode, Watcher};
+
+/// Default ledger file name, inside the output directory.
+const LEDGER_NAME: &str = ".pdfcli-watch-ledger";
+
+pub(crate) struct WatchOptions<'a> {
+    pub inbox: &'a Path,
+    pub out_dir: &'a Path,
+    pub pipeline: &'a pdfcore::Pipeline,
+    pub debounce: Duration,
+    pub ledger: Option<&'a Path>,
+    /// Show a dashboard instead of a line per file.
+    pub tui: bool,
+}
+
+pub(crate) fn watch(options: &WatchOptions<'_>, dry_run: bool) -> anyhow::Result<()> {
+    let WatchOptions {
+        inbox,
+        out_dir,
+        pipeline,
+        debounce,
+        ..
+    } = *options;
+    if !inbox.is_dir() {
+        bail!("not a directory: {}", inbox.display());
+    }
+    if fs::canonicalize(out_dir).ok() == Some(fs::canonicalize(inbox)?) {
+        bail!("the output directory must differ from the watched directory");
+    }
+    let ledger_path = options
+        .ledger
+        .map_or_else(|| out_dir.join(LEDGER_NAME), Path::to_path_buf);
+    let mut ledger = Ledger::load(&ledger_path)?;
+
+    let waiting = existing_pdfs(inbox)?;
+    if dry_run {
+        for input in waiting.iter().filter(|p| !ledger.contains(p)) {
+            let output = out_dir.join(input.file_name().unwrap_or_default());
+            let plan = pipeline
+                .plan(input, &output)
+                .with_context(|| format!("planning {}", input.display()))?;
+            print!("{plan}");
+        }
+        return Ok(());
+    }
+    fs::create_dir_all(out_dir)
+        .with_context(|| format!("creating output dir: {}", out_dir.display()))?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let handler_stop = Arc::clone(&stop);
+    ctrlc::set_handler(move || handler_stop.store(true, Ordering::SeqCst))
+        .context("installing the Ctrl-C handler")?;
+
+    let title = format!(
+        "watching {} -> {} (Ctrl-C to stop)",
+        inbox.display(),
+        out_dir.display()
+    );
+    let (dashboard, progress) = crate::dashboard::start_if(options.tui, &title, 1)?;
+    let log = dashboard.is_none();
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).context("starting the file watcher")?;
+    watcher
+        .watch(inbox, RecursiveMode::NonRecursive)
+        .with_context(|| format!("watching {}", inbox.display()))?;
+    if log {
+        eprintln!(
+            "watching: {} -> {} (Ctrl-C to stop)",
+            inbox.display(),
+            out_dir.display()
+        );
+    }
+
+    // Files already present are processed first; changes meanwhile queue up.
+    let waiting: Vec<PathBuf> = waiting
+        .into_iter()
+        .filter(|p| !ledger.contains(p))
+        .collect();
+    for input in &waiting {
+        progress.queued(input);
+    }
+    for input in waiting {
+        if stop.load(Ordering::SeqCst) {
+            break;
+        }
+        process(&input, out_dir, pipeline, &mut ledger, &progress, log)?;
+    }
+    let mut pending: BTreeMap<PathBuf, Instant> = BTreeMap::new();
+    while !stop.load(Ordering::SeqCst) {
+        match rx.recv_timeout(Duration::from_millis(200)) {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    for path in event.paths.into_iter().filter(|p| is_pdf(p)) {
+                        if !pending.contains_key(&path) {
+                            progress.queued(&path);
+                        }
+                        pending.insert(path, Instant::now());
+                    }
+                }
+            }
+            Ok(Err(e)) => tracing::warn!(error = %e, "file watcher error"),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => bail!("the file watcher stopped"),
+        }
+
+        let ready: Vec<PathBuf> = pending
+            .iter()
+            .filter(|(_, changed)| changed.elapsed() >= debounce)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for input in ready {
+            pending.remove(&input);
+            if stop.load(Ordering::SeqCst) {
+                break;
+            }
+            process(&input, out_dir, pipeline, &mut ledger, &progress, log)?;
+        }
+    }
+    drop(dashboard);
+    eprintln!("stopped watching: {}", inbox.display());
+    Ok(())
+}
+
+/// Run the pipeline on `input` unless the ledger says it was done. Failures
+/// are reported (to `progress`, and on stderr if `log`) and the file is
+/// retried when it next changes.
+fn process(
+    input: &Path,
+    out_dir: &Path,
+    pipeline: &pdfcore::Pipeline,
+    ledger: &mut Ledger,
+    progress: &pdfcore::Progress,
+    log: bool,
+) -> anyhow::Result<()> {
+    let Some(name) = input.file_name() else {
+        return Ok(());
+    };
+    if !input.is_file() || ledger.contains(input) {
+        progress.skipped(input);
+        return Ok(());
+    }
+    let output = out_dir.join(name);
+    let partial = out_dir.join(format!(".{}.partial", name.to_string_lossy()));
+    progress.started(input, 0);
+    let start = Instant::now();
+    let result = pipeline
+        .run(input, &partial)
+        .map_err(anyhow::Error::from)
+        .and_then(|()| {
+            fs::rename(&partial, &output)
+                .with_context(|| format!("moving result to {}", output.display()))
+        });
+    if result.is_ok() {
+        ledger.record(input)?;
+    } else {
+        let _ = fs::remove_file(&partial);
+    }
+    progress.done(input, 0, start.elapsed(), &result);
+    match result {
+        Ok(()) if log => eprintln!("wrote: {}", output.display()),
+        Err(e) if log => eprintln!("failed: {}: {e:#}", input.display()),
+        _ => {}
+    }
+    Ok(())
+}
+
+fn existing_pdfs(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut pdfs: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("reading {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| is_pdf(p) && p.is_file())
+        .collect();
+    pdfs.sort();
+    Ok(pdfs)
+}
+
+/// `*.pdf`, ignoring hidden files (such as other programs' partial copies).
+fn is_pdf(path: &Path) -> bool {
+    let hidden = path
+        .file_name()
+        .is_some_and(|n| n.to_string_lossy().starts_with('.'));
+    !hidden
+        && path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"))
+}
+
+/// Processed inputs, one `<mtime>\t<size>\t<name>` line each.
+struct Ledger {
+    path: PathBuf,
+    entries: BTreeSet<String>,
+}
+
+impl Ledger {
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let entries = match fs::read_to_string(path) {
+            Ok(text) => text.lines().map(ToString::to_string).collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeSet::new(),
+            Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries,
+        })
+    }
+
+    fn contains(&self, input: &Path) -> bool {
+        entry(input).is_some_and(|e| self.entries.contains(&e))
+    }
+
+    fn record(&mut self, input: &Path) -> anyhow::Result<()> {
+        let Some(line) = entry(input) else {
+            return Ok(());
+        };
+        if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("opening {}", self.path.display()))?;
+        writeln!(file, "{line}").with_context(|| format!("writing {}", self.path.display()))?;
+        self.entries.insert(line);
+        Ok(())
+    }
+}
+
+fn entry(input: &Path) -> Option<String> {
+    let meta = fs::metadata(input).ok()?;
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos());
+    let name = input.file_name()?.to_string_lossy();
+    Some(format!("{modified}\t{}\t{name}", meta.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use pdfcore::{tempspace::TempSpace, ProgressEvent};
+
+    use super::*;
+
+    #[test]
+    fn only_complete_visible_pdfs_are_picked_up() {
+        for name in ["scan.pdf", "SCAN.PDF", "inbox/report.Pdf"] {
+            assert!(is_pdf(Path::new(name)), "{name}");
+        }
+        for name in [
+            ".scan.pdf",
+            ".scan.pdf.partial",
+            "scan.pdf.part",
+            "scan.pdf~",
+            "scan.pdf.crdownload",
+            "notes.txt",
+            "pdf",
+            "inbox.pdf/notes",
+        ] {
+            assert!(!is_pdf(Path::new(name)), "{name}");
+        }
+    }
+
+    #[test]
+    fn the_ledger_survives_a_restart() -> anyhow::Result<()> {
+        let space = TempSpace::new("watch-test")?;
+        let input = space.write("in.pdf", b"one")?;
+        let other = space.write("other.pdf", b"two")?;
+        let path = space.path().join("state").join(LEDGER_NAME);
+
+        let mut ledger = Ledger::load(&path)?;
+        assert!(!ledger.contains(&input));
+        ledger.record(&input)?;
+        assert!(ledger.contains(&input));
+
+        let reloaded = Ledger::load(&path)?;
+        assert!(reloaded.contains(&input));
+        assert!(!reloaded.contains(&other));
+        // A replaced file is new work.
+        fs::write(&input, b"replaced")?;
+        assert!(!Ledger::load(&path)?.contains(&input));
+        Ok(())
+    }
+
+    #[test]
+    fn processed_files_are_skipped() -> anyhow::Result<()> {
+        let space = TempSpace::new("watch-test")?;
+        let text = space.write("in.txt", b"Quarterly figures")?;
+        let input = space.file("in.pdf");
+        pdfcore::text_to_pdf(&text, &input, &pdfcore::TextToPdfOptions::default())?;
+        let out_dir = space.path().join("out");
+        fs::create_dir(&out_dir)?;
+        let output = out_dir.join("in.pdf");
+        let pipeline: pdfcore::Pipeline = "decrypt".parse()?;
+        let (tx, rx) = mpsc::channel();
+        let progress = pdfcore::Progress::new(move |event| {
+            let _ = tx.send(event);
+        });
+
+        let mut ledger = Ledger::load(&out_dir.join(LEDGER_NAME))?;
+        process(&input, &out_dir, &pipeline, &mut ledger, &progress, false)?;
+        assert!(output.is_file());
+        assert!(matches!(
+            rx.try_iter().last(),
+            Some(ProgressEvent::Finished { .. })
+        ));
+
+        // After a restart, the same file is left alone.
+        fs::remove_file(&output)?;
+        let mut ledger = Ledger::load(&out_dir.join(LEDGER_NAME))?;
+        process(&input, &out_dir, &pipeline, &mut ledger, &progress, false)?;
+        assert!(!output.exists());
+        assert!(matches!(
+            rx.try_iter().collect::<Vec<_>>().as_slice(),
+            [ProgressEvent::Skipped { .. }]
+        ));
+        Ok(())
+    }
+}
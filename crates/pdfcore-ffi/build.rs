@@ -0,0 +1,17 @@
+//! Regenerates `include/pdfcore_ffi.h` from the crate's `extern "C"` items.
+
+fn main() {
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".to_string());
+    let config =
+        cbindgen::Config::from_file(format!("{crate_dir}/cbindgen.toml")).unwrap_or_default();
+    match cbindgen::generate_with_config(&crate_dir, config) {
+        Ok(bindings) => {
+            bindings.write_to_file(format!("{crate_dir}/include/pdfcore_ffi.h"));
+        }
+        // A stale header is better than a failed build (e.g. while the
+        // source does not parse yet); the compiler reports the real error.
+        Err(e) => println!("cargo:warning=not regenerating pdfcore_ffi.h: {e}"),
+    }
+}
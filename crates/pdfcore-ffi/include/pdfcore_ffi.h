@@ -0,0 +1,117 @@
+#ifndef PDFCORE_FFI_H
+#define PDFCORE_FFI_H
+
+/* Generated with cbindgen:0.29.4 */
+
+/* Generated by cbindgen from crates/pdfcore-ffi; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Version of this ABI. It changes only when existing declarations change
+ * incompatibly; additions keep it.
+ */
+#define PDFCLI_ABI_VERSION 1
+
+/**
+ * Bytes owned by the caller, such as an input PDF.
+ */
+typedef struct PdfcliBytes {
+  /**
+   * First byte; may be null when `len` is 0.
+   */
+  const uint8_t *data;
+  size_t len;
+} PdfcliBytes;
+
+/**
+ * Bytes owned by the library, such as an output PDF. Release with
+ * [`pdfcli_buffer_free`].
+ */
+typedef struct PdfcliBuffer {
+  uint8_t *data;
+  size_t len;
+} PdfcliBuffer;
+
+/**
+ * Why a call failed.
+ */
+typedef struct PdfcliError {
+  /**
+   * The value the call returned.
+   */
+  int32_t code;
+  /**
+   * NUL-terminated UTF-8 description, owned by the library. Release with
+   * [`pdfcli_error_free`].
+   */
+  char *message;
+} PdfcliError;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * [`PDFCLI_ABI_VERSION`] of the loaded library.
+ */
+uint32_t pdfcli_abi_version(void);
+
+/**
+ * Document information as JSON (`pages`, `metadata`, `fonts`, `xfa`,
+ * `encryption`), in the shape of `pdfcli info --json`.
+ *
+ * # Safety
+ *
+ * `pdf` must describe readable memory, `out_json` must point to a writable
+ * [`PdfcliBuffer`], and `error` must be null or point to a writable
+ * [`PdfcliError`].
+ */
+int32_t pdfcli_info(struct PdfcliBytes pdf,
+                    struct PdfcliBuffer *out_json,
+                    struct PdfcliError *error);
+
+/**
+ * Merge `count` PDFs, in order, into one.
+ *
+ * # Safety
+ *
+ * `inputs` must point to `count` [`PdfcliBytes`], each describing readable
+ * memory; `out_pdf` must point to a writable [`PdfcliBuffer`], and `error`
+ * must be null or point to a writable [`PdfcliError`].
+ */
+int32_t pdfcli_merge(const struct PdfcliBytes *inputs,
+                     size_t count,
+                     struct PdfcliBuffer *out_pdf,
+                     struct PdfcliError *error);
+
+/**
+ * Release a buffer returned by the library and reset it to empty. Null
+ * pointers and empty buffers are ignored.
+ *
+ * # Safety
+ *
+ * `buffer` must be null or point to a buffer filled in by this library and
+ * not yet released.
+ */
+void pdfcli_buffer_free(struct PdfcliBuffer *buffer);
+
+/**
+ * Release an error's message and reset it. Null pointers are ignored.
+ *
+ * # Safety
+ *
+ * `error` must be null or point to an error filled in by this library and
+ * not yet released.
+ */
+void pdfcli_error_free(struct PdfcliError *error);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* PDFCORE_FFI_H */
@@ -0,0 +1,398 @@
+//! C ABI for pdfcore's pure-Rust operations, for calling them from other
+//! languages (Python, Node, Go, ...) without running the CLI.
+//!
+//! The header is `include/pdfcore_ffi.h`, regenerated by the build script.
+//!
+//! Conventions:
+//!
+//! - Functions return `0` on success, else the failure's exit code as listed
+//!   in the README (`1` for failures without one, including panics).
+//! - PDFs are passed in as [`PdfcliBytes`] (borrowed; the caller keeps
+//!   ownership) and returned as [`PdfcliBuffer`]s, which the library owns
+//!   until they are released with [`pdfcli_buffer_free`].
+//! - On failure, a non-null `error` is filled in; its message is released
+//!   with [`pdfcli_error_free`].
+//! - Operations run in process with the native engine; no external tools
+//!   are started.
+
+use std::{
+    ffi::{c_char, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use pdfcore::{json_string, tempspace::TempSpace, Engine, Merge, PdfError};
+
+/// Version of this ABI. It changes only when existing declarations change
+/// incompatibly; additions keep it.
+pub const PDFCLI_ABI_VERSION: u32 = 1;
+
+/// Bytes owned by the caller, such as an input PDF.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PdfcliBytes {
+    /// First byte; may be null when `len` is 0.
+    pub data: *const u8,
+    pub len: usize,
+}
+
+/// Bytes owned by the library, such as an output PDF. Release with
+/// [`pdfcli_buffer_free`].
+#[repr(C)]
+#[derive(Debug)]
+pub struct PdfcliBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+/// Why a call failed.
+#[repr(C)]
+#[derive(Debug)]
+pub struct PdfcliError {
+    /// The value the call returned.
+    pub code: i32,
+    /// NUL-terminated UTF-8 description, owned by the library. Release with
+    /// [`pdfcli_error_free`].
+    pub message: *mut c_char,
+}
+
+/// [`PDFCLI_ABI_VERSION`] of the loaded library.
+#[no_mangle]
+pub extern "C" fn pdfcli_abi_version() -> u32 {
+    PDFCLI_ABI_VERSION
+}
+
+/// Document information as JSON (`pages`, `metadata`, `fonts`, `xfa`,
+/// `encryption`), in the shape of `pdfcli info --json`.
+///
+/// # Safety
+///
+/// `pdf` must describe readable memory, `out_json` must point to a writable
+/// [`PdfcliBuffer`], and `error` must be null or point to a writable
+/// [`PdfcliError`].
+#[no_mangle]
+pub unsafe extern "C" fn pdfcli_info(
+    pdf: PdfcliBytes,
+    out_json: *mut PdfcliBuffer,
+    error: *mut PdfcliError,
+) -> i32 {
+    // SAFETY: the caller guarantees `pdf` is readable.
+    let pdf = unsafe { bytes(pdf) };
+    call(out_json, error, || {
+        let scratch = TempSpace::new("ffi-info")?;
+        let info = pdfcore::info(scratch.write("input.pdf", pdf?)?)?;
+        Ok(info_json(&info).into_bytes())
+    })
+}
+
+/// Merge `count` PDFs, in order, into one.
+///
+/// # Safety
+///
+/// `inputs` must point to `count` [`PdfcliBytes`], each describing readable
+/// memory; `out_pdf` must point to a writable [`PdfcliBuffer`], and `error`
+/// must be null or point to a writable [`PdfcliError`].
+#[no_mangle]
+pub unsafe extern "C" fn pdfcli_merge(
+    inputs: *const PdfcliBytes,
+    count: usize,
+    out_pdf: *mut PdfcliBuffer,
+    error: *mut PdfcliError,
+) -> i32 {
+    let inputs: &[PdfcliBytes] = if count == 0 {
+        &[]
+    } else if inputs.is_null() {
+        return fail(error, &invalid("inputs is null"));
+    } else {
+        // SAFETY: the caller guarantees `inputs` points to `count` items.
+        unsafe { std::slice::from_raw_parts(inputs, count) }
+    };
+    // SAFETY: the caller guarantees every input is readable.
+    let inputs: Vec<_> = inputs.iter().map(|&b| unsafe { bytes(b) }).collect();
+    call(out_pdf, error, || {
+        let scratch = TempSpace::new("ffi-merge")?;
+        let mut paths = Vec::with_capacity(inputs.len());
+        for (index, input) in inputs.into_iter().enumerate() {
+            paths.push(scratch.write(&format!("input-{}.pdf", index + 1), input?)?);
+        }
+        let output = scratch.file("output.pdf");
+        Merge::new(&paths)
+            .output(&output)
+            .engine(Engine::Native)
+            .run()?;
+        Ok(std::fs::read(output)?)
+    })
+}
+
+/// Release a buffer returned by the library and reset it to empty. Null
+/// pointers and empty buffers are ignored.
+///
+/// # Safety
+///
+/// `buffer` must be null or point to a buffer filled in by this library and
+/// not yet released.
+#[no_mangle]
+pub unsafe extern "C" fn pdfcli_buffer_free(buffer: *mut PdfcliBuffer) {
+    // SAFETY: the caller guarantees `buffer` is null or valid.
+    let Some(buffer) = (unsafe { buffer.as_mut() }) else {
+        return;
+    };
+    if !buffer.data.is_null() {
+        let slice = ptr::slice_from_raw_parts_mut(buffer.data, buffer.len);
+        // SAFETY: `data` and `len` came from `Box::into_raw` in `call`.
+        drop(unsafe { Box::from_raw(slice) });
+    }
+    buffer.data = ptr::null_mut();
+    buffer.len = 0;
+}
+
+/// Release an error's message and reset it. Null pointers are ignored.
+///
+/// # Safety
+///
+/// `error` must be null or point to an error filled in by this library and
+/// not yet released.
+#[no_mangle]
+pub unsafe extern "C" fn pdfcli_error_free(error: *mut PdfcliError) {
+    // SAFETY: the caller guarantees `error` is null or valid.
+    let Some(error) = (unsafe { error.as_mut() }) else {
+        return;
+    };
+    if !error.message.is_null() {
+        // SAFETY: `message` came from `CString::into_raw` in `fail`.
+        drop(unsafe { CString::from_raw(error.message) });
+    }
+    error.code = 0;
+    error.message = ptr::null_mut();
+}
+
+/// The bytes `b` describes, copied, or an error for a null pointer.
+///
+/// # Safety
+///
+/// `b` must describe readable memory.
+unsafe fn bytes(b: PdfcliBytes) -> pdfcore::Result<Vec<u8>> {
+    if b.len == 0 {
+        return Ok(Vec::new());
+    }
+    if b.data.is_null() {
+        return Err(invalid("input data is null"));
+    }
+    // SAFETY: the caller guarantees `data` points to `len` readable bytes.
+    Ok(unsafe { std::slice::from_raw_parts(b.data, b.len) }.to_vec())
+}
+
+/// Run `op`, storing its result in `out` or its failure in `error`.
+fn call(
+    out: *mut PdfcliBuffer,
+    error: *mut PdfcliError,
+    op: impl FnOnce() -> pdfcore::Result<Vec<u8>>,
+) -> i32 {
+    if out.is_null() {
+        return fail(error, &invalid("output buffer is null"));
+    }
+    match panic::catch_unwind(AssertUnwindSafe(op)) {
+        Ok(Ok(data)) => {
+            let len = data.len();
+            let data = Box::into_raw(data.into_boxed_slice()).cast::<u8>();
+            // SAFETY: checked non-null above; the caller guarantees it is
+            // writable.
+            unsafe { out.write(PdfcliBuffer { data, len }) };
+            0
+        }
+        Ok(Err(e)) => fail(error, &e),
+        Err(_) => fail_with(error, 1, "internal error (panic)"),
+    }
+}
+
+fn fail(error: *mut PdfcliError, e: &PdfError) -> i32 {
+    fail_with(error, e.exit_code(), &e.to_string())
+}
+
+fn fail_with(error: *mut PdfcliError, code: i32, message: &str) -> i32 {
+    // SAFETY: the caller guarantees `error` is null or writable.
+    if let Some(error) = unsafe { error.as_mut() } {
+        let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+        *error = PdfcliError {
+            code,
+            message: message.into_raw(),
+        };
+    }
+    code
+}
+
+fn invalid(message: &str) -> PdfError {
+    PdfError::InvalidArgument(message.to_string())
+}
+
+fn info_json(info: &pdfcore::PdfInfo) -> String {
+    let metadata: Vec<String> = info
+        .metadata
+        .iter()
+        .map(|(k, v)| format!("{}:{}", json_string(k), json_string(v)))
+        .collect();
+    let fonts: Vec<String> = info
+        .fonts
+        .iter()
+        .map(|f| {
+            let pages: Vec<String> = f.pages.iter().map(ToString::to_string).collect();
+            format!(
+                "{{\"name\":{},\"type\":{},\"embedded\":{},\"subset\":{},\"pages\":[{}]}}",
+                json_string(&f.name),
+                json_string(&f.font_type),
+                f.embedded,
+                f.subset,
+                pages.join(",")
+            )
+        })
+        .collect();
+    let xfa = info
+        .xfa
+        .map_or_else(|| "null".to_string(), |x| json_string(x.name()));
+    let encryption = info.encryption.as_ref().map_or_else(
+        || "null".to_string(),
+        |e| {
+            let granted: Vec<String> = e.permissions.granted().into_iter().map(json_string).collect();
+            format!(
+                "{{\"cipher\":{},\"key_bits\":{},\"permissions\":[{}],\"user_password_required\":{},\"decrypted\":{}}}",
+                json_string(&e.cipher),
+                e.key_bits,
+                granted.join(","),
+                e.user_password_required.map_or_else(|| "null".to_string(), |b| b.to_string()),
+                e.decrypted
+            )
+        },
+    );
+    let catalog_version = info
+        .catalog_version
+        .as_deref()
+        .map_or_else(|| "null".to_string(), json_string);
+    let document_id = info.document_id.as_ref().map_or_else(
+        || "null".to_string(),
+        |[permanent, changing]| format!("[{},{}]", json_string(permanent), json_string(changing)),
+    );
+    format!(
+        "{{\"pages\":{},\"version\":{},\"catalog_version\":{catalog_version},\"linearized\":{},\"xref_streams\":{},\"object_streams\":{},\"tagged\":{},\"incremental_updates\":{},\"document_id\":{document_id},\"metadata\":{{{}}},\"fonts\":[{}],\"xfa\":{xfa},\"encryption\":{encryption}}}",
+        info.pages,
+        json_string(&info.version),
+        info.linearized,
+        info.xref_streams,
+        info.object_streams,
+        info.tagged,
+        info.incremental_updates,
+        metadata.join(","),
+        fonts.join(",")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{Dictionary, Document, Object};
+
+    fn one_page_pdf() -> Vec<u8> {
+        let mut doc = Document::with_version("1.5");
+        let pages = doc.new_object_id();
+        let page = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Page".to_vec())),
+            ("Parent", Object::Reference(pages)),
+        ]));
+        doc.objects.insert(
+            pages,
+            Object::Dictionary(Dictionary::from_iter([
+                ("Type", Object::Name(b"Pages".to_vec())),
+                ("Kids", Object::Array(vec![Object::Reference(page)])),
+                ("Count", Object::Integer(1)),
+            ])),
+        );
+        let catalog = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Catalog".to_vec())),
+            ("Pages", Object::Reference(pages)),
+        ]));
+        doc.trailer.set("Root", catalog);
+        let mut out = Vec::new();
+        let _ = doc.save_to(&mut out);
+        out
+    }
+
+    fn as_bytes(data: &[u8]) -> PdfcliBytes {
+        PdfcliBytes {
+            data: data.as_ptr(),
+            len: data.len(),
+        }
+    }
+
+    fn take(buffer: &mut PdfcliBuffer) -> Vec<u8> {
+        // SAFETY: filled in by a successful call.
+        let data = unsafe { std::slice::from_raw_parts(buffer.data, buffer.len) }.to_vec();
+        // SAFETY: as above, released once.
+        unsafe { pdfcli_buffer_free(buffer) };
+        assert!(buffer.data.is_null());
+        data
+    }
+
+    #[test]
+    fn merge_then_info_through_the_c_abi() {
+        let pdf = one_page_pdf();
+        let inputs = [as_bytes(&pdf), as_bytes(&pdf), as_bytes(&pdf)];
+        let mut merged = PdfcliBuffer {
+            data: ptr::null_mut(),
+            len: 0,
+        };
+        // SAFETY: valid inputs and output; no error wanted.
+        let status = unsafe {
+            pdfcli_merge(
+                inputs.as_ptr(),
+                inputs.len(),
+                &raw mut merged,
+                ptr::null_mut(),
+            )
+        };
+        assert_eq!(status, 0);
+        let merged = take(&mut merged);
+        // The engine is chosen per call, not for the host process.
+        assert!(pdfcore::preferred_engines().is_empty());
+
+        let mut json = PdfcliBuffer {
+            data: ptr::null_mut(),
+            len: 0,
+        };
+        // SAFETY: as above.
+        let status = unsafe { pdfcli_info(as_bytes(&merged), &raw mut json, ptr::null_mut()) };
+        assert_eq!(status, 0);
+        let json = String::from_utf8_lossy(&take(&mut json)).into_owned();
+        assert!(json.starts_with("{\"pages\":3,"), "{json}");
+    }
+
+    #[test]
+    fn failures_fill_in_an_owned_error() {
+        let mut out = PdfcliBuffer {
+            data: ptr::null_mut(),
+            len: 0,
+        };
+        let mut error = PdfcliError {
+            code: 0,
+            message: ptr::null_mut(),
+        };
+        // SAFETY: valid (if not a PDF) input, output, and error.
+        let status = unsafe { pdfcli_info(as_bytes(b"not a pdf"), &raw mut out, &raw mut error) };
+        assert_eq!(status, 5, "pdf_parse");
+        assert_eq!(error.code, status);
+        assert!(out.data.is_null());
+        // SAFETY: set by the failed call.
+        let message = unsafe { std::ffi::CStr::from_ptr(error.message) };
+        assert!(!message.to_bytes().is_empty());
+        // SAFETY: released once.
+        unsafe { pdfcli_error_free(&raw mut error) };
+        assert!(error.message.is_null());
+
+        // SAFETY: null pointers are documented as accepted.
+        unsafe {
+            pdfcli_buffer_free(ptr::null_mut());
+            pdfcli_error_free(ptr::null_mut());
+        }
+        // SAFETY: a null output is rejected before anything is written.
+        let status = unsafe { pdfcli_merge(ptr::null(), 0, ptr::null_mut(), ptr::null_mut()) };
+        assert_eq!(status, 3);
+    }
+}
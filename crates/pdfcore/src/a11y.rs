@@ -0,0 +1,755 @@
+//! Accessibility primitives: document language, reading direction, title,
+//! and basic tagging, and a structural accessibility check.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use lopdf::content::Operation;
+
+use crate::{
+    load_document, load_for_reading, metadata, save_document, stamp, validate_input_file,
+    ComplianceCheck, PdfError, Plan, Result,
+};
+
+/// Summary of [`tag_text_blocks`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct TaggingSummary {
+    /// Text blocks tagged as paragraphs.
+    pub paragraphs: usize,
+    /// Text blocks tagged as headings.
+    pub headings: usize,
+    /// Pages whose content could not be decoded (left untagged).
+    pub skipped_pages: usize,
+}
+
+/// Blocks at least this much larger than the median text size become headings.
+const HEADING_SIZE_RATIO: f32 = 1.3;
+
+/// `true` if the catalog already has a structure tree.
+pub(crate) fn is_tagged(doc: &lopdf::Document) -> bool {
+    doc.catalog().is_ok_and(|c| c.has(b"StructTreeRoot"))
+}
+
+/// Reading direction of a document, stored as `/Direction` in its viewer
+/// preferences. Viewers use it to lay out pages side by side and to order
+/// thumbnails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextDirection {
+    /// Left to right (the default).
+    LeftToRight,
+    /// Right to left, as for Arabic or Hebrew.
+    RightToLeft,
+}
+
+impl TextDirection {
+    /// The `/Direction` name.
+    #[must_use]
+    pub fn pdf_name(self) -> &'static str {
+        match self {
+            Self::LeftToRight => "L2R",
+            Self::RightToLeft => "R2L",
+        }
+    }
+}
+
+/// Set the document language (a BCP 47 tag such as `de-DE`), and
+/// optionally the reading direction (pure Rust). Screen readers pick their
+/// voice from the language; PDF/UA requires it.
+pub fn set_document_language(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    lang: &str,
+    direction: Option<TextDirection>,
+) -> Result<()> {
+    let mut doc = load_document(input.as_ref())?;
+    set_language(&mut doc, lang)?;
+    if let Some(direction) = direction {
+        set_viewer_preference(
+            &mut doc,
+            "Direction",
+            lopdf::Object::Name(direction.pdf_name().as_bytes().to_vec()),
+        )?;
+    }
+    save_document(&mut doc, output.as_ref())
+}
+
+/// What [`set_document_language`] would do.
+pub fn set_document_language_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    lang: &str,
+    direction: Option<TextDirection>,
+) -> Result<Plan> {
+    validate_input_file(input.as_ref())?;
+    let lang = check_language_tag(lang)?;
+    let mut plan = Plan::new().native(format!("set /Lang to {lang}"));
+    if let Some(direction) = direction {
+        plan = plan.native(format!(
+            "set /ViewerPreferences /Direction to /{}",
+            direction.pdf_name()
+        ));
+    }
+    Ok(plan.write(output.as_ref()))
+}
+
+/// Set the catalog `/Lang` entry (a BCP 47 tag such as `en-US`).
+pub(crate) fn set_language(doc: &mut lopdf::Document, lang: &str) -> Result<()> {
+    let lang = check_language_tag(lang)?;
+    catalog_mut(doc)?.set("Lang", metadata::text_string(lang));
+    Ok(())
+}
+
+/// `lang` without surrounding spaces, if it has the shape of a BCP 47 tag:
+/// subtags of 1 to 8 letters or digits separated by hyphens, the first of
+/// them letters only.
+fn check_language_tag(lang: &str) -> Result<&str> {
+    let lang = lang.trim();
+    if lang.is_empty() {
+        return Err(PdfError::InvalidArgument(
+            "language tag must not be empty".to_string(),
+        ));
+    }
+    let well_formed = lang.split('-').enumerate().all(|(i, subtag)| {
+        (1..=8).contains(&subtag.len())
+            && subtag
+                .bytes()
+                .all(|b| b.is_ascii_alphabetic() || (i > 0 && b.is_ascii_digit()))
+    });
+    if !well_formed {
+        return Err(PdfError::InvalidArgument(format!(
+            "{lang:?} is not a language tag (expected e.g. en, de-DE, or zh-Hant-TW)"
+        )));
+    }
+    Ok(lang)
+}
+
+/// Set the document title and ask viewers to display it instead of the file name.
+pub(crate) fn set_title(doc: &mut lopdf::Document, title: &str) -> Result<()> {
+    metadata::set_info_entry(doc, "Title", title)?;
+    set_viewer_preference(doc, "DisplayDocTitle", lopdf::Object::Boolean(true))
+}
+
+/// Set `key` in the catalog `/ViewerPreferences`, creating the dictionary
+/// if needed.
+pub(crate) fn set_viewer_preference(
+    doc: &mut lopdf::Document,
+    key: &str,
+    value: lopdf::Object,
+) -> Result<()> {
+    let prefs = catalog_mut(doc)?.get(b"ViewerPreferences").ok().cloned();
+    if let Some(lopdf::Object::Reference(id)) = prefs {
+        if let Ok(lopdf::Object::Dictionary(prefs)) = doc.get_object_mut(id) {
+            prefs.set(key, value);
+            return Ok(());
+        }
+    }
+    let mut prefs = match prefs {
+        Some(lopdf::Object::Dictionary(prefs)) => prefs,
+        _ => lopdf::Dictionary::new(),
+    };
+    prefs.set(key, value);
+    catalog_mut(doc)?.set("ViewerPreferences", prefs);
+    Ok(())
+}
+
+pub(crate) fn catalog_mut(doc: &mut lopdf::Document) -> Result<&mut lopdf::Dictionary> {
+    doc.catalog_mut()
+        .map_err(|_| PdfError::InvalidArgument("document has no catalog".to_string()))
+}
+
+#[derive(Debug)]
+struct TextBlock {
+    /// Index of the `BT` operator.
+    begin: usize,
+    /// Index of the matching `ET` operator.
+    end: usize,
+    /// Largest effective font size shown in the block.
+    size: f32,
+}
+
+/// Wrap every text object (`BT`…`ET`) in a marked-content sequence and build
+/// a flat structure tree of `H1`/`H2`/`P` elements under a `Document` root.
+///
+/// Heading levels are inferred from font size relative to the page median.
+/// Does nothing (and returns an empty summary) if the document is already
+/// tagged.
+pub(crate) fn tag_text_blocks(doc: &mut lopdf::Document) -> Result<TaggingSummary> {
+    let mut summary = TaggingSummary::default();
+    if is_tagged(doc) {
+        return Ok(summary);
+    }
+
+    let root_id = doc.new_object_id();
+    let document_elem_id = doc.new_object_id();
+    let mut document_kids = Vec::new();
+    let mut parent_tree_nums = Vec::new();
+    let mut next_key: i64 = 0;
+
+    for page_id in doc.get_pages().into_values() {
+        let Some(page_elems) = tag_page(doc, page_id, document_elem_id, &mut summary)? else {
+            continue;
+        };
+        if let Ok(page) = doc.get_dictionary_mut(page_id) {
+            page.set("StructParents", next_key);
+        }
+
+        document_kids.extend(page_elems.iter().cloned());
+        parent_tree_nums.push(lopdf::Object::Integer(next_key));
+        parent_tree_nums.push(lopdf::Object::Array(page_elems));
+        next_key += 1;
+    }
+
+    doc.objects.insert(
+        document_elem_id,
+        lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"StructElem".to_vec())),
+            ("S", lopdf::Object::Name(b"Document".to_vec())),
+            ("P", root_id.into()),
+            ("K", lopdf::Object::Array(document_kids)),
+        ])
+        .into(),
+    );
+    let parent_tree = doc.add_object(lopdf::Dictionary::from_iter([(
+        "Nums",
+        lopdf::Object::Array(parent_tree_nums),
+    )]));
+    doc.objects.insert(
+        root_id,
+        lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"StructTreeRoot".to_vec())),
+            ("K", document_elem_id.into()),
+            ("ParentTree", parent_tree.into()),
+            ("ParentTreeNextKey", next_key.into()),
+        ])
+        .into(),
+    );
+
+    let catalog = catalog_mut(doc)?;
+    catalog.set("StructTreeRoot", root_id);
+    catalog.set(
+        "MarkInfo",
+        lopdf::Dictionary::from_iter([("Marked", lopdf::Object::Boolean(true))]),
+    );
+    Ok(summary)
+}
+
+/// Tag the text blocks of one page, returning its structure elements in
+/// MCID order, or `None` if the page has no text (or could not be decoded).
+fn tag_page(
+    doc: &mut lopdf::Document,
+    page_id: lopdf::ObjectId,
+    parent: lopdf::ObjectId,
+    summary: &mut TaggingSummary,
+) -> Result<Option<Vec<lopdf::Object>>> {
+    let Ok(mut content) = doc.get_and_decode_page_content(page_id) else {
+        summary.skipped_pages += 1;
+        return Ok(None);
+    };
+    let blocks = find_text_blocks(&content.operations);
+    if blocks.is_empty() {
+        return Ok(None);
+    }
+    let levels = heading_levels(&blocks);
+
+    let mut ops = Vec::with_capacity(content.operations.len() + blocks.len() * 2);
+    let mut page_elems = Vec::new();
+    let mut block_iter = blocks.iter().zip(&levels).peekable();
+    for (i, op) in std::mem::take(&mut content.operations)
+        .into_iter()
+        .enumerate()
+    {
+        let current = block_iter.peek().copied();
+        if let Some((block, level)) = current {
+            if block.begin == i {
+                let mcid = i64::try_from(page_elems.len()).unwrap_or(i64::MAX);
+                let tag = match level {
+                    Some(1) => "H1",
+                    Some(_) => "H2",
+                    None => "P",
+                };
+                ops.push(Operation::new(
+                    "BDC",
+                    vec![
+                        lopdf::Object::Name(tag.as_bytes().to_vec()),
+                        lopdf::Dictionary::from_iter([("MCID", lopdf::Object::Integer(mcid))])
+                            .into(),
+                    ],
+                ));
+                let elem = doc.add_object(lopdf::Dictionary::from_iter([
+                    ("Type", lopdf::Object::Name(b"StructElem".to_vec())),
+                    ("S", lopdf::Object::Name(tag.as_bytes().to_vec())),
+                    ("P", parent.into()),
+                    ("Pg", page_id.into()),
+                    ("K", mcid.into()),
+                ]));
+                page_elems.push(lopdf::Object::Reference(elem));
+                if level.is_some() {
+                    summary.headings += 1;
+                } else {
+                    summary.paragraphs += 1;
+                }
+            }
+        }
+        ops.push(op);
+        if let Some((block, _)) = current {
+            if block.end == i {
+                ops.push(Operation::new("EMC", vec![]));
+                block_iter.next();
+            }
+        }
+    }
+
+    content.operations = ops;
+    let bytes = content
+        .encode()
+        .map_err(|e| PdfError::InvalidArgument(format!("encoding content: {e}")))?;
+    stamp::replace_page_content(doc, page_id, bytes)?;
+    Ok(Some(page_elems))
+}
+
+/// Locate top-level text objects that actually show text, with their
+/// effective font size (`Tf` size scaled by the text matrix).
+fn find_text_blocks(ops: &[Operation]) -> Vec<TextBlock> {
+    let mut blocks = Vec::new();
+    let mut font_size = 0.0_f32;
+    let mut open: Option<(usize, f32, bool)> = None;
+    let mut tm_scale = 1.0_f32;
+
+    for (i, op) in ops.iter().enumerate() {
+        let number = |n: usize| op.operands.get(n).and_then(|o| o.as_float().ok());
+        match op.operator.as_str() {
+            "BT" if open.is_none() => {
+                tm_scale = 1.0;
+                open = Some((i, 0.0, false));
+            }
+            "ET" => {
+                if let Some((begin, size, shows_text)) = open.take() {
+                    if shows_text {
+                        blocks.push(TextBlock {
+                            begin,
+                            end: i,
+                            size,
+                        });
+                    }
+                }
+            }
+            "Tf" => font_size = number(1).unwrap_or(font_size).abs(),
+            "Tm" => {
+                let (b, d) = (number(1).unwrap_or(0.0), number(3).unwrap_or(1.0));
+                tm_scale = b.hypot(d);
+            }
+            "Tj" | "TJ" | "'" | "\"" => {
+                if let Some((_, size, shows_text)) = open.as_mut() {
+                    *size = size.max(font_size * tm_scale);
+                    *shows_text = true;
+                }
+            }
+            _ => {}
+        }
+    }
+    blocks
+}
+
+/// Heading level per block (`Some(1)` for the largest heading size,
+/// `Some(2)` for other heading sizes, `None` for body text).
+fn heading_levels(blocks: &[TextBlock]) -> Vec<Option<u8>> {
+    if blocks.len() < 2 {
+        return vec![None; blocks.len()];
+    }
+    let mut sizes: Vec<f32> = blocks.iter().map(|b| b.size).collect();
+    sizes.sort_by(f32::total_cmp);
+    let median = sizes[sizes.len() / 2];
+    let largest = sizes[sizes.len() - 1];
+
+    blocks
+        .iter()
+        .map(|b| {
+            if median > 0.0 && b.size >= median * HEADING_SIZE_RATIO {
+                Some(if (b.size - largest).abs() < 0.01 {
+                    1
+                } else {
+                    2
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Image `XObject`s referenced from page resources, keyed by object id.
+pub(crate) fn page_images(doc: &lopdf::Document) -> BTreeMap<lopdf::ObjectId, u32> {
+    let mut images = BTreeMap::new();
+    for (page_no, page_id) in doc.get_pages() {
+        let Ok((inline, ids)) = doc.get_page_resources(page_id) else {
+            continue;
+        };
+        let resources = inline
+            .into_iter()
+            .chain(ids.into_iter().filter_map(|id| doc.get_dictionary(id).ok()));
+        for res in resources {
+            let Some(xobjects) = crate::fonts::resolve_dict(doc, res.get(b"XObject").ok()) else {
+                continue;
+            };
+            for (_, value) in xobjects {
+                let Ok(id) = value.as_reference() else {
+                    continue;
+                };
+                let is_image = doc
+                    .get_object(id)
+                    .and_then(lopdf::Object::as_stream)
+                    .is_ok_and(|s| {
+                        s.dict
+                            .get(b"Subtype")
+                            .and_then(lopdf::Object::as_name)
+                            .is_ok_and(|n| n == b"Image")
+                    });
+                if is_image {
+                    images.entry(id).or_insert(page_no);
+                }
+            }
+        }
+    }
+    images
+}
+
+/// Result of [`a11y_check`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct A11yReport {
+    /// Number of pages.
+    pub pages: u32,
+    /// 0 to 100: the weights of the checks that passed.
+    pub score: u32,
+    /// The checks, in order.
+    pub checks: Vec<ComplianceCheck>,
+}
+
+impl A11yReport {
+    /// `true` if every check passed.
+    #[must_use]
+    pub fn is_compliant(&self) -> bool {
+        self.checks.iter().all(|c| c.passed)
+    }
+}
+
+/// Checks of [`a11y_check`] with their weights in the score, which add up
+/// to 100.
+const A11Y_CHECKS: [(&str, u32); 7] = [
+    ("tagged", 25),
+    ("marked", 5),
+    ("language", 15),
+    ("title", 10),
+    ("display-title", 5),
+    ("figure-alt-text", 25),
+    ("tab-order", 15),
+];
+
+/// Structural accessibility checks, as a first triage for PDF/UA (pure
+/// Rust): a structure tree, the document marked as tagged, a language, a
+/// title shown by viewers, alternative text on figures, and tab order
+/// following the structure on pages with annotations. Passing them does not
+/// make a document accessible; reading order, headings, tables, and
+/// contrast still need a human (or a full PDF/UA validator).
+pub fn a11y_check(input: impl AsRef<Path>) -> Result<A11yReport> {
+    let doc = load_for_reading(input.as_ref())?;
+    let catalog = doc.catalog().ok();
+    let entry = |key: &[u8]| catalog.and_then(|c| c.get(key).ok());
+    let detail = |passed: bool, yes: &str, no: String| if passed { yes.to_string() } else { no };
+
+    let tagged = is_tagged(&doc);
+    let marked = crate::fonts::resolve_dict(&doc, entry(b"MarkInfo"))
+        .and_then(|info| info.get(b"Marked").and_then(lopdf::Object::as_bool).ok())
+        .unwrap_or(false);
+    let language = entry(b"Lang")
+        .and_then(|lang| lang.as_str().ok())
+        .map(metadata::decode_text_string)
+        .filter(|lang| !lang.trim().is_empty());
+    let title = metadata::info_entry(&doc, "Title").filter(|title| !title.trim().is_empty());
+    let display_title = crate::fonts::resolve_dict(&doc, entry(b"ViewerPreferences"))
+        .and_then(|prefs| {
+            prefs
+                .get(b"DisplayDocTitle")
+                .and_then(lopdf::Object::as_bool)
+                .ok()
+        })
+        .unwrap_or(false);
+    let untabbed = pages_without_tab_order(&doc);
+
+    let (alt_passed, alt_detail) = figure_alt_text(&doc, tagged);
+
+    let results = [
+        (
+            tagged,
+            detail(
+                tagged,
+                "structure tree present",
+                "no /StructTreeRoot".to_string(),
+            ),
+        ),
+        (
+            marked,
+            detail(
+                marked,
+                "/MarkInfo /Marked is true",
+                "/MarkInfo /Marked is not true".to_string(),
+            ),
+        ),
+        (
+            language.is_some(),
+            language.unwrap_or_else(|| "no document language (/Lang)".to_string()),
+        ),
+        (
+            title.is_some(),
+            title.unwrap_or_else(|| "no document title".to_string()),
+        ),
+        (
+            display_title,
+            detail(
+                display_title,
+                "viewers show the title",
+                "viewers show the file name (/DisplayDocTitle is not true)".to_string(),
+            ),
+        ),
+        (alt_passed, alt_detail),
+        (
+            untabbed.is_empty(),
+            detail(
+                untabbed.is_empty(),
+                "pages with annotations use the structure order",
+                format!("pages with annotations without /Tabs /S: {untabbed:?}"),
+            ),
+        ),
+    ];
+    let mut score = 0;
+    let mut checks = Vec::new();
+    for ((name, weight), (passed, detail)) in A11Y_CHECKS.into_iter().zip(results) {
+        if passed {
+            score += weight;
+        }
+        checks.push(ComplianceCheck {
+            name,
+            passed,
+            detail,
+        });
+    }
+    Ok(A11yReport {
+        pages: u32::try_from(doc.get_pages().len()).unwrap_or(u32::MAX),
+        score,
+        checks,
+    })
+}
+
+/// Pages that have annotations but no `/Tabs /S`, so that tabbing through
+/// their links and fields does not follow the structure order.
+fn pages_without_tab_order(doc: &lopdf::Document) -> Vec<u32> {
+    doc.get_pages()
+        .into_iter()
+        .filter(|&(_, page_id)| {
+            doc.get_dictionary(page_id).is_ok_and(|page| {
+                let annotated = match page.get(b"Annots") {
+                    Ok(lopdf::Object::Array(annots)) => !annots.is_empty(),
+                    Ok(lopdf::Object::Reference(id)) => doc
+                        .get_object(*id)
+                        .and_then(lopdf::Object::as_array)
+                        .is_ok_and(|annots| !annots.is_empty()),
+                    _ => false,
+                };
+                let structured = page
+                    .get(b"Tabs")
+                    .and_then(lopdf::Object::as_name)
+                    .is_ok_and(|tabs| tabs == b"S");
+                annotated && !structured
+            })
+        })
+        .map(|(page_no, _)| page_no)
+        .collect()
+}
+
+/// Result and detail of the `figure-alt-text` check.
+fn figure_alt_text(doc: &lopdf::Document, tagged: bool) -> (bool, String) {
+    let figures = if tagged { Some(figures(doc)) } else { None };
+    match figures {
+        Some((0, _)) => (true, "no figures in the structure tree".to_string()),
+        Some((total, 0)) => (true, format!("all {total} figure(s) have alternative text")),
+        Some((total, missing)) => (
+            false,
+            format!("{missing} of {total} figure(s) without alternative text"),
+        ),
+        None => match page_images(doc).len() {
+            0 => (true, "no images".to_string()),
+            images => (
+                false,
+                format!("{images} image(s), and no structure tree to give them alternative text"),
+            ),
+        },
+    }
+}
+
+/// Number of `Figure` structure elements (directly, or through the role
+/// map), and of those without `/Alt` or `/ActualText`.
+fn figures(doc: &lopdf::Document) -> (usize, usize) {
+    let root = crate::fonts::resolve_dict(
+        doc,
+        doc.catalog()
+            .ok()
+            .and_then(|c| c.get(b"StructTreeRoot").ok()),
+    );
+    let role_map = root.and_then(|root| crate::fonts::resolve_dict(doc, root.get(b"RoleMap").ok()));
+    let is_figure = |role: &[u8]| {
+        let mut role = role.to_vec();
+        // Roles may map to other custom roles; a few steps are plenty.
+        for _ in 0..8 {
+            if role == b"Figure" {
+                return true;
+            }
+            match role_map.and_then(|map| map.get(&role).and_then(lopdf::Object::as_name).ok()) {
+                Some(mapped) if mapped != role.as_slice() => role = mapped.to_vec(),
+                _ => return false,
+            }
+        }
+        false
+    };
+
+    let (mut total, mut missing) = (0, 0);
+    let mut seen = BTreeSet::new();
+    let mut pending: Vec<&lopdf::Object> =
+        root.and_then(|r| r.get(b"K").ok()).into_iter().collect();
+    while let Some(kid) = pending.pop() {
+        let element = match kid {
+            lopdf::Object::Reference(id) => {
+                if !seen.insert(*id) {
+                    continue;
+                }
+                doc.get_object(*id).ok()
+            }
+            other => Some(other),
+        };
+        match element {
+            Some(lopdf::Object::Array(kids)) => pending.extend(kids),
+            Some(lopdf::Object::Dictionary(element)) => {
+                let role = element.get(b"S").and_then(lopdf::Object::as_name);
+                if role.is_ok_and(is_figure) {
+                    total += 1;
+                    let described = [b"Alt".as_slice(), b"ActualText"].iter().any(|key| {
+                        element
+                            .get(key)
+                            .and_then(lopdf::Object::as_str)
+                            .is_ok_and(|text| !metadata::decode_text_string(text).trim().is_empty())
+                    });
+                    if !described {
+                        missing += 1;
+                    }
+                }
+                pending.extend(element.get(b"K").ok());
+            }
+            _ => {}
+        }
+    }
+    (total, missing)
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::content::Content;
+
+    use super::*;
+
+    fn ops(src: &str) -> Vec<Operation> {
+        Content::decode(src.as_bytes())
+            .map(|c| c.operations)
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn larger_text_blocks_become_headings() {
+        let ops = ops(concat!(
+            "BT /F1 24 Tf (Title) Tj ET\n",
+            "BT /F1 10 Tf (Body one) Tj ET\n",
+            "BT /F1 10 Tf (Body two) Tj ET\n",
+            "BT ET\n",
+        ));
+        let blocks = find_text_blocks(&ops);
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(heading_levels(&blocks), vec![Some(1), None, None]);
+    }
+
+    #[test]
+    fn language_tags_are_checked() {
+        for tag in ["en", "de-DE", " zh-Hant-TW ", "es-419", "x-klingon"] {
+            assert_eq!(check_language_tag(tag).ok(), Some(tag.trim()));
+        }
+        for tag in ["", "de_DE", "419", "en--US", "englishes-US"] {
+            assert!(check_language_tag(tag).is_err(), "{tag}");
+        }
+    }
+
+    #[test]
+    fn viewer_preferences_are_updated_in_place() -> Result<()> {
+        use lopdf::{dictionary, Object};
+
+        let mut doc = lopdf::Document::with_version("1.7");
+        let prefs = doc.add_object(dictionary! { "HideToolbar" => true });
+        let catalog = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "ViewerPreferences" => prefs,
+        });
+        doc.trailer.set("Root", catalog);
+        set_viewer_preference(&mut doc, "Direction", Object::Name(b"R2L".to_vec()))?;
+        set_title(&mut doc, "Annual Report")?;
+        assert_eq!(
+            doc.catalog()
+                .ok()
+                .and_then(|c| c.get(b"ViewerPreferences").ok())
+                .and_then(|p| p.as_reference().ok()),
+            Some(prefs)
+        );
+        let prefs = doc.get_dictionary(prefs).ok();
+        let entry = |key: &[u8]| prefs.and_then(|prefs| prefs.get(key).ok());
+        assert_eq!(
+            entry(b"Direction").and_then(|d| d.as_name().ok()),
+            Some(b"R2L".as_slice())
+        );
+        assert_eq!(
+            entry(b"DisplayDocTitle").and_then(|d| d.as_bool().ok()),
+            Some(true)
+        );
+        assert_eq!(
+            entry(b"HideToolbar").and_then(|h| h.as_bool().ok()),
+            Some(true)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn figures_without_alt_text_are_counted() {
+        use lopdf::{dictionary, Object};
+
+        let mut doc = lopdf::Document::with_version("1.7");
+        let described = doc.add_object(dictionary! {
+            "S" => "Figure",
+            "Alt" => Object::string_literal("A bar chart"),
+        });
+        // A custom role mapped to Figure, and an element reached twice.
+        let custom = doc.add_object(dictionary! { "S" => "Chart" });
+        let section = doc.add_object(dictionary! {
+            "S" => "Sect",
+            "K" => vec![described.into(), custom.into(), custom.into()],
+        });
+        let root = doc.add_object(dictionary! {
+            "Type" => "StructTreeRoot",
+            "RoleMap" => dictionary! { "Chart" => "Figure" },
+            "K" => vec![section.into(), Object::Dictionary(dictionary! {
+                "S" => "Figure",
+                "ActualText" => Object::string_literal(" "),
+            })],
+        });
+        let catalog = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "StructTreeRoot" => root,
+        });
+        doc.trailer.set("Root", catalog);
+        assert_eq!(figures(&doc), (3, 2));
+    }
+}
@@ -0,0 +1,524 @@
+//! Async operations for tokio runtimes (the `tokio` feature).
+//!
+//! These mirror the blocking functions of the same names, for embedding
+//! pdfcore in async services without tying up worker threads:
+//!
+//! - External tools run as [`tokio::process`] children, each in a process
+//!   group of its own on Unix. Dropping the returned future (for example
+//!   when a request is cancelled) kills the group.
+//! - Each call takes an optional timeout; when it expires the group is
+//!   killed, whatever the tool wrote is removed, and [`PdfError::Timeout`]
+//!   is returned. The other limits set with [`crate::set_tool_run_options`]
+//!   apply as for the blocking functions.
+//! - Pure Rust work (the native engine, `PDFium`) runs on tokio's blocking
+//!   pool. It cannot be interrupted, so it writes to a temporary path that
+//!   only becomes the output if it finishes in time; on timeout it finishes
+//!   in the background and what it wrote is removed.
+
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    pin::pin,
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    command_to_string, engine, extract_text_command, gs_pdfwrite_command, kill_process_group,
+    linearize_command, merge_command, merge_inputs, mutool_merge_command, mutool_render_command,
+    mutool_text_command, native, own_process_group, render_command, rotate_command, sandbox,
+    select_engine, split_pages_command, tool_outcome, tool_run_options, validate_input_file,
+    validate_render, validate_rotation, ColorSpace, CompressPreset, CompressReport, Engine,
+    Operation, PageSelection, PartialOutput, PdfError, Result, Tool,
+};
+
+/// Run `cmd` to completion, killing it and its process group if `timeout`
+/// expires or the future is dropped. Returns its standard output.
+async fn run_tool(tool: Tool, cmd: Command, timeout: Option<Duration>) -> Result<String> {
+    let command_str = command_to_string(&cmd);
+    tracing::debug!(tool = tool.name(), command = %command_str, "running");
+    let started = Instant::now();
+    let (mut cmd, _tmp) = sandbox::prepare(cmd, &tool_run_options())?;
+    own_process_group(&mut cmd);
+    let mut cmd = tokio::process::Command::from(cmd);
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let child = cmd.spawn()?;
+    let leader = child.id();
+    let mut output = pin!(child.wait_with_output());
+    // Declared after `output` so that it is dropped first, while the tool
+    // has not been reaped and its process group still exists.
+    let mut group = ProcessGroup(leader);
+    let out = with_timeout(tool.name(), timeout, output.as_mut())
+        .await?
+        .map_err(PdfError::from)?;
+    group.0 = None;
+    let stdout = String::from_utf8_lossy(&out.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&out.stderr).to_string();
+    tracing::debug!(
+        tool = tool.name(),
+        status = out.status.code().unwrap_or(-1),
+        elapsed_ms = started.elapsed().as_millis(),
+        "finished"
+    );
+    tracing::trace!(%stdout, %stderr, "output");
+    tool_outcome(tool, command_str, out.status, stdout, stderr)
+}
+
+/// Kills the process group of a running tool when dropped.
+struct ProcessGroup(Option<u32>);
+
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        if let Some(leader) = self.0 {
+            kill_process_group(leader);
+        }
+    }
+}
+
+/// [`run_tool`] for a tool writing `output` (a file, or a directory of
+/// files): if the tool times out, whatever it wrote there is removed.
+async fn run_tool_writing(
+    tool: Tool,
+    cmd: Command,
+    output: &Path,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let partial = PartialOutput::watch(output);
+    let result = run_tool(tool, cmd, timeout).await.map(drop);
+    partial.remove_on_timeout(&result);
+    result
+}
+
+/// Run blocking pure Rust work on the blocking pool.
+async fn blocking<T: Send + 'static>(
+    engine: Engine,
+    timeout: Option<Duration>,
+    work: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T> {
+    let task = tokio::task::spawn_blocking(work);
+    match with_timeout(engine.name(), timeout, task).await? {
+        Ok(result) => result,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => Err(PdfError::Io(std::io::Error::other(e))),
+    }
+}
+
+async fn with_timeout<F: Future>(
+    tool: &'static str,
+    timeout: Option<Duration>,
+    future: F,
+) -> Result<F::Output> {
+    match timeout {
+        Some(after) => tokio::time::timeout(after, future)
+            .await
+            .map_err(|_| PdfError::Timeout { tool, after }),
+        None => Ok(future.await),
+    }
+}
+
+/// [`blocking`] work writing the file `output`, which it is handed a
+/// temporary path next to instead. The temporary file becomes `output` only
+/// if the work succeeds in time; otherwise it is removed once the work
+/// stops.
+async fn blocking_to_file(
+    engine: Engine,
+    timeout: Option<Duration>,
+    output: &Path,
+    work: impl FnOnce(&Path) -> Result<()> + Send + 'static,
+) -> Result<()> {
+    let staged = tempfile::Builder::new()
+        .prefix(".pdfcli-")
+        .suffix(".part")
+        .tempfile_in(parent_dir(output))?
+        .into_temp_path();
+    let staged = blocking(engine, timeout, move || {
+        work(&staged)?;
+        Ok(staged)
+    })
+    .await?;
+    staged.persist(output).map_err(|e| PdfError::Io(e.error))
+}
+
+/// [`blocking`] work writing files into the directory `out_dir`, which it
+/// is handed a temporary directory inside instead. The files are moved into
+/// `out_dir` only if the work succeeds in time; otherwise they are removed
+/// once the work stops.
+#[cfg(feature = "pdfium")]
+async fn blocking_to_dir(
+    engine: Engine,
+    timeout: Option<Duration>,
+    out_dir: &Path,
+    work: impl FnOnce(&Path) -> Result<()> + Send + 'static,
+) -> Result<()> {
+    std::fs::create_dir_all(out_dir)?;
+    let staged = tempfile::Builder::new()
+        .prefix(".pdfcli-")
+        .tempdir_in(out_dir)?;
+    let staged = blocking(engine, timeout, move || {
+        work(staged.path())?;
+        Ok(staged)
+    })
+    .await?;
+    for entry in std::fs::read_dir(staged.path())? {
+        let entry = entry?;
+        std::fs::rename(entry.path(), out_dir.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+fn owned(paths: &[&Path]) -> Vec<PathBuf> {
+    paths.iter().map(|p| p.to_path_buf()).collect()
+}
+
+/// Async [`crate::merge`].
+pub async fn merge(
+    inputs: &[impl AsRef<Path>],
+    output: impl AsRef<Path>,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let inputs: Vec<PathBuf> = inputs.iter().map(|p| p.as_ref().to_path_buf()).collect();
+    let inputs = merge_inputs(&inputs)?;
+    let output = output.as_ref();
+    match select_engine(Operation::Merge)? {
+        Engine::Qpdf => {
+            let cmd = merge_command(&inputs, output)?;
+            run_tool_writing(Tool::Qpdf, cmd, output, timeout).await
+        }
+        Engine::Mutool => {
+            let cmd = mutool_merge_command(&inputs, output)?;
+            run_tool_writing(Tool::Mutool, cmd, output, timeout).await
+        }
+        Engine::Native => {
+            let inputs = owned(&inputs);
+            blocking_to_file(Engine::Native, timeout, output, move |output| {
+                let inputs: Vec<&Path> = inputs.iter().map(PathBuf::as_path).collect();
+                native::merge(&inputs, output)
+            })
+            .await
+        }
+        other => Err(engine::unsupported(Operation::Merge, other)),
+    }
+}
+
+/// Async [`crate::split_pages`].
+pub async fn split_pages(
+    input: impl AsRef<Path>,
+    out_dir: impl AsRef<Path>,
+    pattern: Option<&str>,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let out_dir = out_dir.as_ref();
+    let cmd = split_pages_command(input.as_ref(), out_dir, pattern)?;
+    run_tool_writing(Tool::Qpdf, cmd, out_dir, timeout).await
+}
+
+/// Async [`crate::rotate`].
+pub async fn rotate(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    degrees: u16,
+    pages: Option<PageSelection>,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_rotation(input, degrees)?;
+    let pages = pages.unwrap_or(PageSelection::All);
+    match select_engine(Operation::Rotate)? {
+        Engine::Qpdf => {
+            let cmd = rotate_command(input, output, degrees, &pages)?;
+            run_tool_writing(Tool::Qpdf, cmd, output, timeout).await
+        }
+        Engine::Native => {
+            let input = input.to_path_buf();
+            blocking_to_file(Engine::Native, timeout, output, move |output| {
+                native::rotate(&input, output, degrees, &pages)
+            })
+            .await
+        }
+        other => Err(engine::unsupported(Operation::Rotate, other)),
+    }
+}
+
+/// Async [`crate::extract_text`]: the text, or an empty string if it was
+/// written to `output`.
+pub async fn extract_text(
+    input: impl AsRef<Path>,
+    output: Option<impl AsRef<Path>>,
+    timeout: Option<Duration>,
+) -> Result<String> {
+    let input = input.as_ref().to_path_buf();
+    let output = output.map(|p| p.as_ref().to_path_buf());
+    validate_input_file(&input)?;
+    let selected = select_engine(Operation::ExtractText)?;
+    let text = match selected {
+        Engine::Pdftotext => {
+            let cmd = extract_text_command(&input, output.as_deref())?;
+            text_tool(Tool::Pdftotext, cmd, output.as_deref(), timeout).await?
+        }
+        Engine::Mutool => {
+            let cmd = mutool_text_command(&input, output.as_deref())?;
+            text_tool(Tool::Mutool, cmd, output.as_deref(), timeout).await?
+        }
+        #[cfg(feature = "pdfium")]
+        Engine::Pdfium => {
+            blocking(selected, timeout, move || {
+                crate::pdfium::extract_text(&input)
+            })
+            .await?
+        }
+        Engine::Native => blocking(selected, timeout, move || native::extract_text(&input)).await?,
+        other => return Err(engine::unsupported(Operation::ExtractText, other)),
+    };
+    match output {
+        // The tools wrote the file themselves.
+        Some(_) if matches!(selected, Engine::Pdftotext | Engine::Mutool) => Ok(String::new()),
+        Some(out) => {
+            tokio::fs::write(out, text).await?;
+            Ok(String::new())
+        }
+        None => Ok(text),
+    }
+}
+
+/// Run a text extraction tool, which writes `output` if there is one and
+/// prints the text otherwise.
+async fn text_tool(
+    tool: Tool,
+    cmd: Command,
+    output: Option<&Path>,
+    timeout: Option<Duration>,
+) -> Result<String> {
+    match output {
+        Some(output) => run_tool_writing(tool, cmd, output, timeout)
+            .await
+            .map(|()| String::new()),
+        None => run_tool(tool, cmd, timeout).await,
+    }
+}
+
+/// Async [`crate::render`].
+pub async fn render(
+    input: impl AsRef<Path>,
+    out_dir: impl AsRef<Path>,
+    dpi: u32,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let (input, out_dir) = (input.as_ref(), out_dir.as_ref());
+    validate_render(input, dpi)?;
+    match select_engine(Operation::Render)? {
+        Engine::Ghostscript => {
+            let cmd = render_command(input, out_dir, dpi)?;
+            run_tool_writing(Tool::Ghostscript, cmd, out_dir, timeout).await
+        }
+        Engine::Mutool => {
+            let cmd = mutool_render_command(input, out_dir, dpi)?;
+            run_tool_writing(Tool::Mutool, cmd, out_dir, timeout).await
+        }
+        #[cfg(feature = "pdfium")]
+        Engine::Pdfium => {
+            let input = input.to_path_buf();
+            blocking_to_dir(Engine::Pdfium, timeout, out_dir, move |out_dir| {
+                crate::pdfium::render(&input, out_dir, dpi)
+            })
+            .await
+        }
+        other => Err(engine::unsupported(Operation::Render, other)),
+    }
+}
+
+/// Async [`crate::compress`].
+pub async fn compress(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    preset: CompressPreset,
+    timeout: Option<Duration>,
+) -> Result<CompressReport> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_input_file(input)?;
+    let cmd = gs_pdfwrite_command(input, output, Some(preset), &[])?;
+    run_tool_writing(Tool::Ghostscript, cmd, output, timeout).await?;
+
+    let input_bytes = tokio::fs::metadata(input).await?.len();
+    let output_bytes = tokio::fs::metadata(output).await?.len();
+    Ok(CompressReport::from_sizes(input_bytes, output_bytes))
+}
+
+/// Async [`crate::convert_colorspace`].
+pub async fn convert_colorspace(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    to: ColorSpace,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_input_file(input)?;
+    let cmd = gs_pdfwrite_command(input, output, None, &to.as_gs_args())?;
+    run_tool_writing(Tool::Ghostscript, cmd, output, timeout).await
+}
+
+/// Async [`crate::linearize`].
+pub async fn linearize(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_input_file(input)?;
+    let cmd = linearize_command(input, output)?;
+    run_tool_writing(Tool::Qpdf, cmd, output, timeout).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn runtime() -> Result<tokio::runtime::Runtime> {
+        Ok(tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn timeout_kills_the_tool() -> Result<()> {
+        let mut cmd = Command::new("sleep");
+        cmd.arg("5");
+        let started = Instant::now();
+        let result = runtime()?.block_on(run_tool(
+            Tool::Ghostscript,
+            cmd,
+            Some(Duration::from_millis(100)),
+        ));
+        assert!(
+            matches!(
+                result,
+                Err(PdfError::Timeout {
+                    tool: "ghostscript",
+                    ..
+                })
+            ),
+            "{result:?}"
+        );
+        assert!(started.elapsed() < Duration::from_secs(4));
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn timeout_kills_the_process_group_and_removes_the_output() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let (output, pid_file) = (dir.path().join("out.pdf"), dir.path().join("pid"));
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg("echo partial > \"$1\"; sleep 5 & echo $! > \"$2\"; wait")
+            .arg("sh")
+            .arg(&output)
+            .arg(&pid_file);
+        let result = runtime()?.block_on(run_tool_writing(
+            Tool::Qpdf,
+            cmd,
+            &output,
+            Some(Duration::from_millis(300)),
+        ));
+        assert!(
+            matches!(result, Err(PdfError::Timeout { .. })),
+            "{result:?}"
+        );
+        assert!(!output.exists());
+
+        // The `sleep` the tool started is killed with it.
+        let pid = fs::read_to_string(&pid_file)?;
+        let running = || {
+            fs::read_to_string(format!("/proc/{}/stat", pid.trim()))
+                .is_ok_and(|stat| !stat.contains(") Z "))
+        };
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while running() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(!running(), "sleep {} is still running", pid.trim());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn qpdf_warnings_are_not_failures() -> Result<()> {
+        let rt = runtime()?;
+        let exit_3 = || {
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c")
+                .arg("echo fixed; echo 'WARNING: damaged xref' >&2; exit 3");
+            cmd
+        };
+        assert_eq!(
+            rt.block_on(run_tool(Tool::Qpdf, exit_3(), None))?,
+            "fixed\n"
+        );
+        assert!(matches!(
+            rt.block_on(run_tool(Tool::Ghostscript, exit_3(), None)),
+            Err(PdfError::ToolFailed { status: 3, .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn native_work_runs_on_the_blocking_pool() -> Result<()> {
+        let rt = runtime()?;
+        assert_eq!(rt.block_on(blocking(Engine::Native, None, || Ok(7)))?, 7);
+        let slow = blocking(Engine::Native, Some(Duration::from_millis(10)), || {
+            std::thread::sleep(Duration::from_millis(200));
+            Ok(())
+        });
+        assert!(matches!(
+            rt.block_on(slow),
+            Err(PdfError::Timeout { tool: "native", .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn native_output_appears_only_if_the_work_finishes_in_time() -> Result<()> {
+        let rt = runtime()?;
+        let dir = tempfile::tempdir()?;
+        let output = dir.path().join("out.pdf");
+        let write_after = |delay: u64| {
+            move |path: &Path| {
+                std::thread::sleep(Duration::from_millis(delay));
+                fs::write(path, "done")?;
+                Ok(())
+            }
+        };
+
+        let late = blocking_to_file(
+            Engine::Native,
+            Some(Duration::from_millis(10)),
+            &output,
+            write_after(200),
+        );
+        assert!(matches!(rt.block_on(late), Err(PdfError::Timeout { .. })));
+        std::thread::sleep(Duration::from_millis(400));
+        assert_eq!(fs::read_dir(dir.path())?.count(), 0);
+
+        rt.block_on(blocking_to_file(
+            Engine::Native,
+            Some(Duration::from_secs(5)),
+            &output,
+            write_after(0),
+        ))?;
+        assert_eq!(fs::read_to_string(&output)?, "done");
+        assert_eq!(fs::read_dir(dir.path())?.count(), 1);
+        Ok(())
+    }
+}
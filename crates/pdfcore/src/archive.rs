@@ -0,0 +1,402 @@
+//! Running a PDF-to-PDF operation over every PDF in a ZIP archive or a
+//! directory, writing an archive or directory of the same shape; other
+//! entries are carried over untouched.
+//!
+//! Archives are read and written with the `zip` crate, one entry at a
+//! time: entries other than PDFs are copied without being decompressed,
+//! and each PDF is inflated to a temporary file, up to a size limit.
+
+use std::{
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use zip::{result::ZipError, write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{tempspace::TempSpace, InputResult, PdfError, Plan, Result};
+
+/// Whether `path` is something [`map_pdfs`] takes: a directory, or a file
+/// named `*.zip`.
+pub fn is_pdf_collection(path: impl AsRef<Path>) -> bool {
+    let path = path.as_ref();
+    path.is_dir() || is_zip(path)
+}
+
+fn is_zip(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+fn is_pdf_name(name: &str) -> bool {
+    !name.ends_with('/')
+        && Path::new(name)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"))
+}
+
+/// Run `f(input, output)` on every PDF (by its `.pdf` name) in the ZIP
+/// archive or directory `input`, and write a new archive or directory
+/// `output` holding the results under the same names, with every other
+/// entry copied unchanged. Returns a result per PDF, named
+/// `<input>/<entry>`.
+///
+/// With `keep_going`, a PDF that fails is copied unchanged and the rest are
+/// still processed. Without it, processing stops at the first failure; an
+/// archive is then not written at all, while a directory keeps the files
+/// written so far.
+pub fn map_pdfs(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    keep_going: bool,
+    f: impl FnMut(&Path, &Path) -> Result<()>,
+) -> Result<Vec<InputResult<()>>> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    if input.is_dir() {
+        map_directory(input, output, keep_going, f)
+    } else {
+        map_archive(input, output, keep_going, f)
+    }
+}
+
+/// What [`map_pdfs`] would do, with `operation` naming what is done to each
+/// PDF.
+pub fn map_pdfs_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    operation: &str,
+) -> Result<Plan> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    let names: Vec<String> = if input.is_dir() {
+        check_not_nested(input, output)?;
+        directory_files(input)?
+            .iter()
+            .map(|(_, name)| name.clone())
+            .collect()
+    } else {
+        open_archive(input)?
+            .file_names()
+            .map(ToString::to_string)
+            .collect()
+    };
+    let mut plan = Plan::new();
+    let mut others = 0;
+    for name in names {
+        if is_pdf_name(&name) {
+            plan = plan.native(format!("{operation}: {}", input.join(name).display()));
+        } else {
+            others += 1;
+        }
+    }
+    Ok(plan
+        .native(format!("copy {others} other entries unchanged"))
+        .write(output))
+}
+
+fn invalid(input: &Path, message: &str) -> PdfError {
+    PdfError::InvalidArgument(format!("{}: {message}", input.display()))
+}
+
+fn zip_error(path: &Path) -> impl Fn(ZipError) -> PdfError + '_ {
+    move |e| invalid(path, &e.to_string())
+}
+
+/// Refuse an output directory inside the input directory, which would be
+/// read back as input. The output need not exist yet: its nearest existing
+/// ancestor is resolved, so links and `..` cannot hide the nesting.
+fn check_not_nested(input: &Path, output: &Path) -> Result<()> {
+    let input = input.canonicalize()?;
+    let output = resolve(output)?;
+    if output.starts_with(&input) {
+        return Err(PdfError::InvalidArgument(format!(
+            "the output directory {} is inside the input directory",
+            output.display()
+        )));
+    }
+    Ok(())
+}
+
+/// `path` made absolute, with its longest existing ancestor canonicalized
+/// and the rest appended.
+fn resolve(path: &Path) -> Result<PathBuf> {
+    let absolute = std::path::absolute(path)?;
+    let mut missing = Vec::new();
+    let mut existing = absolute.as_path();
+    loop {
+        if let Ok(resolved) = existing.canonicalize() {
+            return Ok(missing
+                .iter()
+                .rev()
+                .fold(resolved, |path, name| path.join(name)));
+        }
+        match (existing.file_name(), existing.parent()) {
+            (Some(name), Some(parent)) => {
+                missing.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return Ok(absolute),
+        }
+    }
+}
+
+/// Every file below `dir`, with its path relative to `dir` (using `/`), in
+/// name order.
+fn directory_files(dir: &Path) -> Result<Vec<(PathBuf, String)>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                let name = path
+                    .strip_prefix(dir)
+                    .unwrap_or(&path)
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                files.push((path, name));
+            }
+        }
+    }
+    files.sort_by(|a, b| a.1.cmp(&b.1));
+    Ok(files)
+}
+
+fn map_directory(
+    input: &Path,
+    output: &Path,
+    keep_going: bool,
+    mut f: impl FnMut(&Path, &Path) -> Result<()>,
+) -> Result<Vec<InputResult<()>>> {
+    check_not_nested(input, output)?;
+    let mut results = Vec::new();
+    for (path, name) in directory_files(input)? {
+        let target = output.join(&name);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if !is_pdf_name(&name) {
+            fs::copy(&path, &target)?;
+            continue;
+        }
+        let result = f(&path, &target);
+        let failed = result.is_err();
+        results.push(InputResult {
+            input: path.clone(),
+            result,
+        });
+        if failed {
+            if !keep_going {
+                break;
+            }
+            fs::copy(&path, &target)?;
+        }
+    }
+    Ok(results)
+}
+
+fn open_archive(input: &Path) -> Result<ZipArchive<File>> {
+    if !input.exists() {
+        return Err(PdfError::InputNotFound(input.to_path_buf()));
+    }
+    if !is_zip(input) {
+        return Err(invalid(input, "expected a directory or a .zip archive"));
+    }
+    ZipArchive::new(File::open(input)?).map_err(|e| invalid(input, &e.to_string()))
+}
+
+fn map_archive(
+    input: &Path,
+    output: &Path,
+    keep_going: bool,
+    mut f: impl FnMut(&Path, &Path) -> Result<()>,
+) -> Result<Vec<InputResult<()>>> {
+    let mut archive = open_archive(input)?;
+    let temp = TempSpace::new("archive")?;
+    // Written next to the output and renamed into place when complete, so
+    // a run that stops early leaves no archive behind.
+    let dir = match output.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let partial = tempfile::NamedTempFile::new_in(dir)?;
+    let mut writer = ZipWriter::new(partial.reopen()?);
+    writer.set_raw_comment(archive.comment().into());
+    let mut results = Vec::new();
+    for i in 0..archive.len() {
+        let name = archive
+            .by_index_raw(i)
+            .map_err(zip_error(input))?
+            .name()
+            .to_string();
+        if !is_pdf_name(&name) {
+            let entry = archive.by_index_raw(i).map_err(zip_error(input))?;
+            writer.raw_copy_file(entry).map_err(zip_error(output))?;
+            continue;
+        }
+        let (pdf_in, pdf_out) = (
+            temp.file(&format!("in-{i}.pdf")),
+            temp.file(&format!("out-{i}.pdf")),
+        );
+        let entry_path = input.join(&name);
+        let result = extract(&mut archive, i, &pdf_in, MAX_ENTRY_BYTES)
+            .map_err(|e| invalid(&entry_path, &e))
+            .and_then(|()| f(&pdf_in, &pdf_out));
+        let _ = fs::remove_file(&pdf_in);
+        let failed = result.is_err();
+        if result.is_ok() {
+            let entry = archive.by_index_raw(i).map_err(zip_error(input))?;
+            let mut options = SimpleFileOptions::default()
+                .compression_method(CompressionMethod::Deflated)
+                .large_file(fs::metadata(&pdf_out)?.len() >= u64::from(u32::MAX));
+            if let Some(modified) = entry.last_modified() {
+                options = options.last_modified_time(modified);
+            }
+            if let Some(mode) = entry.unix_mode() {
+                options = options.unix_permissions(mode);
+            }
+            drop(entry);
+            writer
+                .start_file(&name, options)
+                .map_err(zip_error(output))?;
+            io::copy(&mut File::open(&pdf_out)?, &mut writer)?;
+        }
+        let _ = fs::remove_file(&pdf_out);
+        results.push(InputResult {
+            input: entry_path,
+            result,
+        });
+        if failed {
+            if !keep_going {
+                return Ok(results);
+            }
+            let entry = archive.by_index_raw(i).map_err(zip_error(input))?;
+            writer.raw_copy_file(entry).map_err(zip_error(output))?;
+        }
+    }
+    writer.finish().map_err(zip_error(output))?;
+    partial.persist(output).map_err(|e| PdfError::Io(e.error))?;
+    Ok(results)
+}
+
+/// Largest uncompressed size of a PDF taken out of an archive, so that a
+/// small archive cannot fill the disk (a "zip bomb").
+const MAX_ENTRY_BYTES: u64 = 2 << 30;
+
+/// Inflate entry `index` of `archive` into `path`, refusing entries larger
+/// than `limit` bytes, whatever size the archive claims.
+fn extract(
+    archive: &mut ZipArchive<File>,
+    index: usize,
+    path: &Path,
+    limit: u64,
+) -> std::result::Result<(), String> {
+    let entry = archive.by_index(index).map_err(|e| e.to_string())?;
+    if entry.size() > limit {
+        return Err(format!("the entry is larger than {limit} bytes"));
+    }
+    let mut file = File::create(path).map_err(|e| e.to_string())?;
+    copy_limited(entry, &mut file, limit)
+}
+
+/// Copy at most `limit` bytes from `reader`; more is an error.
+fn copy_limited(
+    reader: impl Read,
+    writer: &mut impl Write,
+    limit: u64,
+) -> std::result::Result<(), String> {
+    let copied = io::copy(&mut reader.take(limit + 1), writer)
+        .map_err(|e| format!("the entry cannot be read: {e}"))?;
+    if copied > limit {
+        return Err(format!("the entry is larger than {limit} bytes"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archives_are_mapped_entry_by_entry() -> Result<()> {
+        let temp = TempSpace::new("archive-test")?;
+        let input = temp.file("in.zip");
+        let mut writer = ZipWriter::new(File::create(&input)?);
+        let zip = zip_error(&input);
+        writer.set_comment("comment");
+        writer
+            .start_file("docs/a.pdf", SimpleFileOptions::default())
+            .map_err(&zip)?;
+        writer.write_all(b"%PDF-1.4 a")?;
+        writer
+            .start_file("docs/b.pdf", SimpleFileOptions::default())
+            .map_err(&zip)?;
+        writer.write_all(b"%PDF-1.4 b")?;
+        writer
+            .start_file("notes.txt", SimpleFileOptions::default())
+            .map_err(&zip)?;
+        writer.write_all(b"hi")?;
+        writer.finish().map_err(&zip)?;
+
+        let output = temp.file("out.zip");
+        let results = map_pdfs(&input, &output, true, |from, to| {
+            let contents = fs::read(from)?;
+            if contents.ends_with(b"b") {
+                return Err(PdfError::InvalidArgument("broken".to_string()));
+            }
+            fs::write(to, [contents.as_slice(), b" done"].concat())?;
+            Ok(())
+        })?;
+        assert_eq!(results.len(), 2);
+        assert!(results[0].result.is_ok() && results[1].result.is_err());
+
+        let mut archive = ZipArchive::new(File::open(&output)?).map_err(&zip)?;
+        assert_eq!(archive.comment(), b"comment");
+        let mut read = |name: &str| -> Result<Vec<u8>> {
+            let mut contents = Vec::new();
+            archive
+                .by_name(name)
+                .map_err(&zip)?
+                .read_to_end(&mut contents)?;
+            Ok(contents)
+        };
+        assert_eq!(read("docs/a.pdf")?, b"%PDF-1.4 a done");
+        assert_eq!(read("docs/b.pdf")?, b"%PDF-1.4 b");
+        assert_eq!(read("notes.txt")?, b"hi");
+
+        // Without keep_going, nothing is written.
+        let stopped = temp.file("stopped.zip");
+        map_pdfs(&input, &stopped, false, |_, _| {
+            Err(PdfError::InvalidArgument("broken".to_string()))
+        })?;
+        assert!(!stopped.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn entries_are_capped_and_outputs_not_nested() -> Result<()> {
+        let mut out = Vec::new();
+        assert!(copy_limited(&[0u8; 10][..], &mut out, 10).is_ok());
+        assert!(copy_limited(&[0u8; 11][..], &mut out, 10).is_err());
+
+        let temp = TempSpace::new("archive-nested")?;
+        let input = temp.file("in");
+        fs::create_dir_all(input.join("sub"))?;
+        assert!(check_not_nested(&input, &input.join("new/out")).is_err());
+        assert!(check_not_nested(&input, &input.join("sub/../out")).is_err());
+        assert!(check_not_nested(&input, &input).is_err());
+        assert!(check_not_nested(&input, &temp.file("out")).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn pdfs_are_recognized_by_name() {
+        assert!(is_pdf_name("scans/A.PDF"));
+        assert!(!is_pdf_name("scans/a.pdf.txt"));
+        assert!(!is_pdf_name("scans.pdf/"));
+        assert!(!is_pdf_name(".pdf"));
+    }
+}
@@ -0,0 +1,781 @@
+//! Finding and reading the barcodes printed on pages (QR codes, Code 128,
+//! EAN-13, and EAN-8), for routing documents by the codes on them.
+//!
+//! Pages are rendered to grayscale images. QR codes are read as in
+//! [`crate::qr`]; linear barcodes are read along rows and columns of
+//! pixels a few pixels apart, in both directions, so they may be printed
+//! at any quarter turn. A linear barcode counts once it has been read the
+//! same way on two lines.
+
+use std::{fmt, path::Path};
+
+use crate::{
+    qr::read_qr_codes,
+    raster::{for_each_gray_page, GrayImage},
+    Rect, Result,
+};
+
+/// Resolution pages are rendered at: the narrowest bars of common labels
+/// (0.25 mm) come out two pixels wide.
+const BARCODE_DPI: u32 = 200;
+
+/// Pixels between the lines that linear barcodes are read along.
+const LINE_STEP: usize = 4;
+
+/// Patterns of Code 128 symbols 0 to 106 (the stop pattern has a seventh
+/// bar): widths in modules of alternating bars and spaces, from a bar.
+const CODE128: [[u8; 6]; 107] = [
+    [2, 1, 2, 2, 2, 2],
+    [2, 2, 2, 1, 2, 2],
+    [2, 2, 2, 2, 2, 1],
+    [1, 2, 1, 2, 2, 3],
+    [1, 2, 1, 3, 2, 2],
+    [1, 3, 1, 2, 2, 2],
+    [1, 2, 2, 2, 1, 3],
+    [1, 2, 2, 3, 1, 2],
+    [1, 3, 2, 2, 1, 2],
+    [2, 2, 1, 2, 1, 3],
+    [2, 2, 1, 3, 1, 2],
+    [2, 3, 1, 2, 1, 2],
+    [1, 1, 2, 2, 3, 2],
+    [1, 2, 2, 1, 3, 2],
+    [1, 2, 2, 2, 3, 1],
+    [1, 1, 3, 2, 2, 2],
+    [1, 2, 3, 1, 2, 2],
+    [1, 2, 3, 2, 2, 1],
+    [2, 2, 3, 2, 1, 1],
+    [2, 2, 1, 1, 3, 2],
+    [2, 2, 1, 2, 3, 1],
+    [2, 1, 3, 2, 1, 2],
+    [2, 2, 3, 1, 1, 2],
+    [3, 1, 2, 1, 3, 1],
+    [3, 1, 1, 2, 2, 2],
+    [3, 2, 1, 1, 2, 2],
+    [3, 2, 1, 2, 2, 1],
+    [3, 1, 2, 2, 1, 2],
+    [3, 2, 2, 1, 1, 2],
+    [3, 2, 2, 2, 1, 1],
+    [2, 1, 2, 1, 2, 3],
+    [2, 1, 2, 3, 2, 1],
+    [2, 3, 2, 1, 2, 1],
+    [1, 1, 1, 3, 2, 3],
+    [1, 3, 1, 1, 2, 3],
+    [1, 3, 1, 3, 2, 1],
+    [1, 1, 2, 3, 1, 3],
+    [1, 3, 2, 1, 1, 3],
+    [1, 3, 2, 3, 1, 1],
+    [2, 1, 1, 3, 1, 3],
+    [2, 3, 1, 1, 1, 3],
+    [2, 3, 1, 3, 1, 1],
+    [1, 1, 2, 1, 3, 3],
+    [1, 1, 2, 3, 3, 1],
+    [1, 3, 2, 1, 3, 1],
+    [1, 1, 3, 1, 2, 3],
+    [1, 1, 3, 3, 2, 1],
+    [1, 3, 3, 1, 2, 1],
+    [3, 1, 3, 1, 2, 1],
+    [2, 1, 1, 3, 3, 1],
+    [2, 3, 1, 1, 3, 1],
+    [2, 1, 3, 1, 1, 3],
+    [2, 1, 3, 3, 1, 1],
+    [2, 1, 3, 1, 3, 1],
+    [3, 1, 1, 1, 2, 3],
+    [3, 1, 1, 3, 2, 1],
+    [3, 3, 1, 1, 2, 1],
+    [3, 1, 2, 1, 1, 3],
+    [3, 1, 2, 3, 1, 1],
+    [3, 3, 2, 1, 1, 1],
+    [3, 1, 4, 1, 1, 1],
+    [2, 2, 1, 4, 1, 1],
+    [4, 3, 1, 1, 1, 1],
+    [1, 1, 1, 2, 2, 4],
+    [1, 1, 1, 4, 2, 2],
+    [1, 2, 1, 1, 2, 4],
+    [1, 2, 1, 4, 2, 1],
+    [1, 4, 1, 1, 2, 2],
+    [1, 4, 1, 2, 2, 1],
+    [1, 1, 2, 2, 1, 4],
+    [1, 1, 2, 4, 1, 2],
+    [1, 2, 2, 1, 1, 4],
+    [1, 2, 2, 4, 1, 1],
+    [1, 4, 2, 1, 1, 2],
+    [1, 4, 2, 2, 1, 1],
+    [2, 4, 1, 2, 1, 1],
+    [2, 2, 1, 1, 1, 4],
+    [4, 1, 3, 1, 1, 1],
+    [2, 4, 1, 1, 1, 2],
+    [1, 3, 4, 1, 1, 1],
+    [1, 1, 1, 2, 4, 2],
+    [1, 2, 1, 1, 4, 2],
+    [1, 2, 1, 2, 4, 1],
+    [1, 1, 4, 2, 1, 2],
+    [1, 2, 4, 1, 1, 2],
+    [1, 2, 4, 2, 1, 1],
+    [4, 1, 1, 2, 1, 2],
+    [4, 2, 1, 1, 1, 2],
+    [4, 2, 1, 2, 1, 1],
+    [2, 1, 2, 1, 4, 1],
+    [2, 1, 4, 1, 2, 1],
+    [4, 1, 2, 1, 2, 1],
+    [1, 1, 1, 1, 4, 3],
+    [1, 1, 1, 3, 4, 1],
+    [1, 3, 1, 1, 4, 1],
+    [1, 1, 4, 1, 1, 3],
+    [1, 1, 4, 3, 1, 1],
+    [4, 1, 1, 1, 1, 3],
+    [4, 1, 1, 3, 1, 1],
+    [1, 1, 3, 1, 4, 1],
+    [1, 1, 4, 1, 3, 1],
+    [3, 1, 1, 1, 4, 1],
+    [4, 1, 1, 1, 3, 1],
+    [2, 1, 1, 4, 1, 2],
+    [2, 1, 1, 2, 1, 4],
+    [2, 1, 1, 2, 3, 2],
+    [2, 3, 3, 1, 1, 1],
+];
+
+/// Code 128 start symbols (for code sets A, B, and C) and the stop symbol.
+const CODE128_START_A: usize = 103;
+const CODE128_START_C: usize = 105;
+const CODE128_STOP: usize = 106;
+
+/// Widths of the EAN digits 0 to 9 in modules, as left-hand odd-parity
+/// (from a space) and right-hand (from a bar) digits; even-parity digits
+/// are these reversed.
+const EAN_DIGITS: [[u8; 4]; 10] = [
+    [3, 2, 1, 1],
+    [2, 2, 2, 1],
+    [2, 1, 2, 2],
+    [1, 4, 1, 1],
+    [1, 1, 3, 2],
+    [1, 2, 3, 1],
+    [1, 1, 1, 4],
+    [1, 3, 1, 2],
+    [1, 2, 1, 3],
+    [3, 1, 1, 2],
+];
+
+/// The parities of the six left-hand digits of an EAN-13 code (a bit set
+/// for even parity, first digit highest), which give its first digit.
+const EAN13_PARITIES: [u8; 10] = [
+    0b00_0000, 0b00_1011, 0b00_1101, 0b00_1110, 0b01_0011, 0b01_1001, 0b01_1100, 0b01_0101,
+    0b01_0110, 0b01_1010,
+];
+
+/// Kind of barcode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BarcodeKind {
+    /// A QR code.
+    Qr,
+    /// A Code 128 barcode (including GS1-128).
+    Code128,
+    /// An EAN-13 barcode (a UPC-A code reads as one with a leading 0).
+    Ean13,
+    /// An EAN-8 barcode.
+    Ean8,
+}
+
+impl BarcodeKind {
+    /// Lowercase name, as in `pdfcli barcodes` output: `qr`, `code128`,
+    /// `ean13`, or `ean8`.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Qr => "qr",
+            Self::Code128 => "code128",
+            Self::Ean13 => "ean13",
+            Self::Ean8 => "ean8",
+        }
+    }
+}
+
+impl fmt::Display for BarcodeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A barcode found by [`barcodes`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Barcode {
+    /// 1-based page number.
+    pub page: u32,
+    /// Kind of barcode.
+    pub kind: BarcodeKind,
+    /// What it says. Byte data that is not UTF-8 is read as Latin-1, and
+    /// GS1 field separators are `\x1d`.
+    pub text: String,
+    /// Where it is, in points from the lower-left corner of the page as
+    /// shown.
+    pub rect: Rect,
+}
+
+/// Find and read the barcodes on every page of `input`, page by page (pages
+/// are rendered with the engine chosen as for [`crate::render`]).
+pub fn barcodes(input: impl AsRef<Path>) -> Result<Vec<Barcode>> {
+    let mut found = Vec::new();
+    for_each_gray_page(input.as_ref(), BARCODE_DPI, |page, image| {
+        let codes = read_barcodes(&image);
+        tracing::debug!(page, codes = codes.len(), "looked for barcodes");
+        // To a hundredth of a point, finer than the pixels are.
+        let points = |pixels: f64| {
+            let points = (pixels * 7200.0 / f64::from(BARCODE_DPI)).round() / 100.0;
+            // Page sizes are far below f32::MAX.
+            #[allow(clippy::cast_possible_truncation)]
+            let points = points as f32;
+            points
+        };
+        let height = to_f64(image.height);
+        found.extend(
+            codes
+                .into_iter()
+                .map(|(kind, text, [left, top, right, bottom])| Barcode {
+                    page,
+                    kind,
+                    text,
+                    rect: Rect {
+                        llx: points(left),
+                        lly: points(height - bottom),
+                        urx: points(right),
+                        ury: points(height - top),
+                    },
+                }),
+        );
+        Ok(())
+    })?;
+    Ok(found)
+}
+
+#[allow(clippy::cast_precision_loss)] // pixel counts are far below 2^52
+fn to_f64(n: usize) -> f64 {
+    n as f64
+}
+
+/// The barcodes in `image`, with their left, top, right, and bottom edges
+/// in pixels: QR codes first, then linear barcodes from the top.
+fn read_barcodes(image: &GrayImage) -> Vec<(BarcodeKind, String, [f64; 4])> {
+    let mut found: Vec<(BarcodeKind, String, [f64; 4])> = read_qr_codes(image)
+        .into_iter()
+        .map(|code| (BarcodeKind::Qr, code.text, code.bounds))
+        .collect();
+    let threshold = image.dark_threshold();
+    let (width, height) = (image.width, image.height);
+    let mut seen: Vec<LinearHit> = Vec::new();
+    for y in (0..height).step_by(LINE_STEP) {
+        let line: Vec<bool> = image.pixels[y * width..(y + 1) * width]
+            .iter()
+            .map(|&p| p <= threshold)
+            .collect();
+        for (kind, text, start, end) in read_line(&line) {
+            let bounds = [to_f64(start), to_f64(y), to_f64(end), to_f64(y + 1)];
+            add_hit(&mut seen, kind, text, bounds);
+        }
+    }
+    for x in (0..width).step_by(LINE_STEP) {
+        let line: Vec<bool> = (0..height)
+            .map(|y| image.pixels[y * width + x] <= threshold)
+            .collect();
+        for (kind, text, start, end) in read_line(&line) {
+            let bounds = [to_f64(x), to_f64(start), to_f64(x + 1), to_f64(end)];
+            add_hit(&mut seen, kind, text, bounds);
+        }
+    }
+    found.extend(
+        seen.into_iter()
+            .filter(|hit| hit.lines >= 2)
+            .map(|hit| (hit.kind, hit.text, hit.bounds)),
+    );
+    found
+}
+
+/// A linear barcode read along one or more lines.
+struct LinearHit {
+    kind: BarcodeKind,
+    text: String,
+    bounds: [f64; 4],
+    lines: u32,
+}
+
+/// Count a reading towards the same code read on a line nearby, if any.
+fn add_hit(seen: &mut Vec<LinearHit>, kind: BarcodeKind, text: String, bounds: [f64; 4]) {
+    let gap = to_f64(LINE_STEP * 2);
+    let near = |a: &[f64; 4]| {
+        bounds[0] <= a[2] + gap
+            && a[0] <= bounds[2] + gap
+            && bounds[1] <= a[3] + gap
+            && a[1] <= bounds[3] + gap
+    };
+    match seen
+        .iter_mut()
+        .find(|hit| hit.kind == kind && hit.text == text && near(&hit.bounds))
+    {
+        Some(hit) => {
+            hit.bounds = [
+                hit.bounds[0].min(bounds[0]),
+                hit.bounds[1].min(bounds[1]),
+                hit.bounds[2].max(bounds[2]),
+                hit.bounds[3].max(bounds[3]),
+            ];
+            hit.lines += 1;
+        }
+        None => seen.push(LinearHit {
+            kind,
+            text,
+            bounds,
+            lines: 1,
+        }),
+    }
+}
+
+/// Linear barcodes along a line of pixels (dark or light), read both ways,
+/// with the first and one past the last pixel of each.
+fn read_line(line: &[bool]) -> Vec<(BarcodeKind, String, usize, usize)> {
+    // (start, length) of alternating runs, the first of them dark.
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for (i, &pixel) in line.iter().enumerate() {
+        match runs.last_mut() {
+            None if !pixel => {}
+            Some((start, len)) if line[*start] == pixel => *len += 1,
+            _ => runs.push((i, 1)),
+        }
+    }
+    if runs.is_empty() {
+        return Vec::new();
+    }
+    let mut found = read_runs(&runs);
+    // The same line the other way round, for codes upside down.
+    let end = line.len();
+    let mut reversed: Vec<(usize, usize)> = runs
+        .iter()
+        .rev()
+        .map(|&(start, len)| (end - start - len, len))
+        .collect();
+    // Reversed, a light run at the end of the line would come first.
+    if runs.len().is_multiple_of(2) {
+        reversed.remove(0);
+    }
+    found.extend(
+        read_runs(&reversed)
+            .into_iter()
+            .map(|(kind, text, start, stop)| (kind, text, end - stop, end - start)),
+    );
+    found
+}
+
+/// Linear barcodes in `runs` (alternating dark and light, from dark).
+fn read_runs(runs: &[(usize, usize)]) -> Vec<(BarcodeKind, String, usize, usize)> {
+    let widths: Vec<usize> = runs.iter().map(|&(_, len)| len).collect();
+    let mut found = Vec::new();
+    let mut i = 0;
+    while i < widths.len() {
+        let quiet = if i == 0 { None } else { Some(widths[i - 1]) };
+        let read = code128(&widths[i..], quiet)
+            .map(|(text, used)| (BarcodeKind::Code128, text, used))
+            .or_else(|| ean(&widths[i..], quiet, 6).map(|(t, n)| (BarcodeKind::Ean13, t, n)))
+            .or_else(|| ean(&widths[i..], quiet, 4).map(|(t, n)| (BarcodeKind::Ean8, t, n)));
+        match read {
+            Some((kind, text, used)) => {
+                let (start, last) = (runs[i].0, runs[i + used - 1]);
+                found.push((kind, text, start, last.0 + last.1));
+                i += used;
+            }
+            // Codes start with a bar.
+            None => i += 2,
+        }
+    }
+    found
+}
+
+/// How far `widths` are from `pattern` (in modules), or `None` if any
+/// width is more than `0.7` modules off. Returns the average difference in
+/// modules.
+fn mismatch(widths: &[usize], pattern: &[u8]) -> Option<f64> {
+    let total: usize = widths.iter().sum();
+    let modules: u32 = pattern.iter().map(|&m| u32::from(m)).sum();
+    let unit = to_f64(total) / f64::from(modules);
+    let mut sum = 0.0;
+    for (&width, &module) in widths.iter().zip(pattern) {
+        let off = (to_f64(width) / unit - f64::from(module)).abs();
+        if off > 0.7 {
+            return None;
+        }
+        sum += off;
+    }
+    Some(sum / f64::from(modules))
+}
+
+/// Whether `widths` fit `pattern` well.
+fn fits(widths: &[usize], pattern: &[u8]) -> bool {
+    mismatch(widths, pattern).is_some_and(|m| m < 0.25)
+}
+
+/// The symbol among `symbols` of `patterns` that `widths` fit best.
+fn best_fit<const N: usize>(
+    widths: &[usize],
+    patterns: &[[u8; N]],
+    symbols: std::ops::Range<usize>,
+) -> Option<usize> {
+    symbols
+        .filter_map(|s| Some((mismatch(widths, &patterns[s])?, s)))
+        .filter(|&(m, _)| m < 0.25)
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, s)| s)
+}
+
+/// A Code 128 barcode at the start of `widths` (after a light run `quiet`
+/// wide, if not at the edge): its text and the runs it takes.
+fn code128(widths: &[usize], quiet: Option<usize>) -> Option<(String, usize)> {
+    let start = best_fit(
+        widths.get(..6)?,
+        &CODE128,
+        CODE128_START_A..CODE128_START_C + 1,
+    )?;
+    let unit = to_f64(widths[..6].iter().sum()) / 11.0;
+    if quiet.is_some_and(|q| to_f64(q) < unit * 5.0) {
+        return None;
+    }
+    let mut values = vec![start];
+    let mut pos = 6;
+    loop {
+        let symbol = best_fit(widths.get(pos..pos + 6)?, &CODE128, 0..CODE128_STOP + 1)?;
+        if symbol == CODE128_STOP {
+            // The stop pattern ends with a two-module bar.
+            let bar = to_f64(*widths.get(pos + 6)?);
+            if (bar / unit - 2.0).abs() > 0.7 {
+                return None;
+            }
+            pos += 7;
+            break;
+        }
+        if symbol >= CODE128_START_A {
+            return None;
+        }
+        values.push(symbol);
+        pos += 6;
+    }
+    let check = values.pop()?;
+    if values.len() < 2 {
+        return None;
+    }
+    let sum: usize = values[0] + (1..).zip(&values[1..]).map(|(i, &v)| i * v).sum::<usize>();
+    if sum % 103 != check {
+        return None;
+    }
+    Some((code128_text(&values)?, pos))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CodeSet {
+    A,
+    B,
+    C,
+}
+
+/// The text of Code 128 symbol values, from the start symbol.
+fn code128_text(values: &[usize]) -> Option<String> {
+    let mut set = match values[0] {
+        CODE128_START_A => CodeSet::A,
+        CODE128_START_C => CodeSet::C,
+        _ => CodeSet::B,
+    };
+    let mut text = String::new();
+    let mut shifted = false;
+    for (i, &value) in values[1..].iter().enumerate() {
+        // A shift switches between sets A and B for one symbol.
+        let current = match (std::mem::take(&mut shifted), set) {
+            (true, CodeSet::A) => CodeSet::B,
+            (true, CodeSet::B) => CodeSet::A,
+            (_, set) => set,
+        };
+        let ascii = |code: usize| char::from(u8::try_from(code).unwrap_or(b'?'));
+        match (current, value) {
+            // FNC1 first marks GS1-128; later ones separate fields.
+            (_, 102) => {
+                if i > 0 {
+                    text.push('\x1d');
+                }
+            }
+            (CodeSet::C, 0..=99) => {
+                text.push(ascii(48 + value / 10));
+                text.push(ascii(48 + value % 10));
+            }
+            (CodeSet::C | CodeSet::A, 100) => set = CodeSet::B,
+            (CodeSet::C | CodeSet::B, 101) => set = CodeSet::A,
+            (CodeSet::A | CodeSet::B, 99) => set = CodeSet::C,
+            (CodeSet::A | CodeSet::B, 98) => shifted = true,
+            // FNC2, FNC3, and FNC4.
+            (CodeSet::A | CodeSet::B, 96 | 97) | (CodeSet::A, 101) | (CodeSet::B, 100) => {}
+            (CodeSet::A, 0..=63) | (CodeSet::B, 0..=95) => text.push(ascii(value + 32)),
+            (CodeSet::A, 64..=95) => text.push(ascii(value - 64)),
+            _ => return None,
+        }
+    }
+    Some(text)
+}
+
+/// An EAN-13 (`half` 6) or EAN-8 (`half` 4) barcode at the start of
+/// `widths` (after a light run `quiet` wide, if not at the edge): its
+/// digits and the runs it takes.
+fn ean(widths: &[usize], quiet: Option<usize>, half: usize) -> Option<(String, usize)> {
+    // Guard, digits, middle guard, digits, guard.
+    let count = 3 + 4 * half + 5 + 4 * half + 3;
+    let widths = widths.get(..count)?;
+    let modules = 3 + 7 * half + 5 + 7 * half + 3;
+    let unit = to_f64(widths.iter().sum()) / to_f64(modules);
+    if !fits(&widths[..3], &[1, 1, 1]) || quiet.is_some_and(|q| to_f64(q) < unit * 3.0) {
+        return None;
+    }
+    let middle = 3 + 4 * half;
+    if !fits(&widths[middle..middle + 5], &[1, 1, 1, 1, 1])
+        || !fits(&widths[count - 3..], &[1, 1, 1])
+    {
+        return None;
+    }
+    let reversed: Vec<[u8; 4]> = EAN_DIGITS
+        .iter()
+        .map(|&[a, b, c, d]| [d, c, b, a])
+        .collect();
+    let mut digits = Vec::with_capacity(2 * half + 1);
+    let mut parities = 0u8;
+    for k in 0..half {
+        let digit = &widths[3 + 4 * k..7 + 4 * k];
+        parities <<= 1;
+        if let Some(d) = best_fit(digit, &EAN_DIGITS, 0..10) {
+            digits.push(d);
+        } else {
+            digits.push(best_fit(digit, &reversed, 0..10)?);
+            parities |= 1;
+        }
+    }
+    for k in 0..half {
+        let start = middle + 5 + 4 * k;
+        digits.push(best_fit(&widths[start..start + 4], &EAN_DIGITS, 0..10)?);
+    }
+    if half == 6 {
+        let first = EAN13_PARITIES.iter().position(|&p| p == parities)?;
+        digits.insert(0, first);
+    } else if parities != 0 {
+        return None;
+    }
+    // Weights 3 and 1 alternate back from the check digit.
+    let check = digits.pop()?;
+    let sum: usize = digits
+        .iter()
+        .rev()
+        .zip([3, 1].into_iter().cycle())
+        .map(|(d, w)| d * w)
+        .sum();
+    if (10 - sum % 10) % 10 != check {
+        return None;
+    }
+    digits.push(check);
+    Some((digits.iter().map(ToString::to_string).collect(), count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qrcode::{Color, QrCode};
+
+    /// A white image `width` x `height`.
+    fn page(width: usize, height: usize) -> GrayImage {
+        GrayImage {
+            width,
+            height,
+            pixels: vec![245; width * height],
+        }
+    }
+
+    /// Draw bars of `widths` (in modules, from a bar) `scale` pixels per
+    /// module at (`x`, `y`), `tall` pixels high; across, or down if
+    /// `vertical`.
+    fn draw_bars(
+        image: &mut GrayImage,
+        widths: &[u8],
+        (x, y): (usize, usize),
+        scale: usize,
+        tall: usize,
+        vertical: bool,
+    ) {
+        let mut at = 0;
+        for (i, &w) in widths.iter().enumerate() {
+            let w = usize::from(w) * scale;
+            if i % 2 == 0 {
+                for along in at..at + w {
+                    for across in 0..tall {
+                        let (px, py) = if vertical {
+                            (x + across, y + along)
+                        } else {
+                            (x + along, y + across)
+                        };
+                        image.pixels[py * image.width + px] = 15;
+                    }
+                }
+            }
+            at += w;
+        }
+    }
+
+    fn code128_widths(text: &str) -> Vec<u8> {
+        let mut values = vec![104];
+        values.extend(text.bytes().map(|b| usize::from(b) - 32));
+        let check = (values[0] + (1..).zip(&values[1..]).map(|(i, &v)| i * v).sum::<usize>()) % 103;
+        values.push(check);
+        values.push(CODE128_STOP);
+        let mut widths: Vec<u8> = values.iter().flat_map(|&v| CODE128[v]).collect();
+        widths.push(2);
+        widths
+    }
+
+    fn ean13_widths(digits: &str) -> Vec<u8> {
+        let d: Vec<usize> = digits.bytes().map(|b| usize::from(b - b'0')).collect();
+        let mut widths = vec![1, 1, 1];
+        for (k, &digit) in d[1..7].iter().enumerate() {
+            let mut w = EAN_DIGITS[digit];
+            if EAN13_PARITIES[d[0]] >> (5 - k) & 1 == 1 {
+                w.reverse();
+            }
+            widths.extend(w);
+        }
+        widths.extend([1, 1, 1, 1, 1]);
+        for &digit in &d[7..] {
+            widths.extend(EAN_DIGITS[digit]);
+        }
+        widths.extend([1, 1, 1]);
+        widths
+    }
+
+    fn ean8_widths(digits: &str) -> Vec<u8> {
+        let mut widths = vec![1, 1, 1];
+        for (k, b) in digits.bytes().enumerate() {
+            if k == 4 {
+                widths.extend([1, 1, 1, 1, 1]);
+            }
+            widths.extend(EAN_DIGITS[usize::from(b - b'0')]);
+        }
+        widths.extend([1, 1, 1]);
+        widths
+    }
+
+    #[test]
+    fn code128_patterns_are_well_formed() {
+        for (i, pattern) in CODE128.iter().enumerate() {
+            let modules: u8 = pattern.iter().sum();
+            let bars = pattern[0] + pattern[2] + pattern[4];
+            assert_eq!(modules, 11, "symbol {i}");
+            assert_eq!(bars % 2, 0, "symbol {i}");
+        }
+    }
+
+    #[test]
+    fn code128_code_sets_are_followed() {
+        // GS1-128: FNC1, two digit pairs, a field separator, then set B.
+        assert_eq!(
+            code128_text(&[105, 102, 1, 23, 102, 100, 33]).as_deref(),
+            Some("0123\x1dA")
+        );
+        // Set A with a shift to B for one symbol.
+        assert_eq!(
+            code128_text(&[103, 33, 98, 65, 65]).as_deref(),
+            Some("Aa\x01")
+        );
+        assert_eq!(code128_text(&[104, 96 + 9]), None);
+    }
+
+    #[test]
+    fn linear_codes_are_read_with_their_positions() {
+        let mut image = page(900, 700);
+        draw_bars(
+            &mut image,
+            &code128_widths("INV-2024/07"),
+            (60, 40),
+            2,
+            80,
+            false,
+        );
+        // EAN-13 upside down: its bars drawn in reverse.
+        let mut upside_down = ean13_widths("4006381333931");
+        upside_down.reverse();
+        draw_bars(&mut image, &upside_down, (100, 300), 3, 90, false);
+        draw_bars(
+            &mut image,
+            &code128_widths("Route 66"),
+            (800, 200),
+            2,
+            60,
+            true,
+        );
+
+        let found = read_barcodes(&image);
+        let summary: Vec<(BarcodeKind, &str)> =
+            found.iter().map(|(k, t, _)| (*k, t.as_str())).collect();
+        assert_eq!(summary.len(), 3, "{summary:?}");
+        assert!(summary.contains(&(BarcodeKind::Code128, "INV-2024/07")));
+        assert!(summary.contains(&(BarcodeKind::Ean13, "4006381333931")));
+        assert!(summary.contains(&(BarcodeKind::Code128, "Route 66")));
+
+        let (_, _, [left, top, right, bottom]) = &found[0];
+        assert!((*left - 60.0).abs() < 2.0 && (*top - 40.0).abs() <= 4.0);
+        assert!(*right > 300.0 && (*bottom - 120.0).abs() <= 4.0);
+    }
+
+    #[test]
+    fn qr_codes_are_read_with_linear_codes() -> Result<()> {
+        let mut image = page(700, 500);
+        for (text, (x, y)) in [("BIN 4", (40, 40)), ("BIN 9", (400, 60))] {
+            let code =
+                QrCode::new(text).map_err(|e| crate::PdfError::InvalidArgument(e.to_string()))?;
+            let n = code.width();
+            for (i, color) in code.to_colors().into_iter().enumerate() {
+                if color == Color::Dark {
+                    let (mx, my) = (i % n, i / n);
+                    for dy in 0..6 {
+                        for dx in 0..6 {
+                            image.pixels[(y + my * 6 + dy) * 700 + x + mx * 6 + dx] = 0;
+                        }
+                    }
+                }
+            }
+        }
+        draw_bars(
+            &mut image,
+            &ean8_widths("01234565"),
+            (100, 350),
+            3,
+            60,
+            false,
+        );
+
+        let found = read_barcodes(&image);
+        let summary: Vec<(BarcodeKind, &str)> =
+            found.iter().map(|(k, t, _)| (*k, t.as_str())).collect();
+        assert_eq!(
+            summary,
+            [
+                (BarcodeKind::Qr, "BIN 4"),
+                (BarcodeKind::Qr, "BIN 9"),
+                (BarcodeKind::Ean8, "01234565")
+            ]
+        );
+        // A 21-module code 6 pixels per module.
+        let [left, top, right, bottom] = found[0].2;
+        assert!((left - 40.0).abs() < 1.0 && (top - 40.0).abs() < 1.0);
+        assert!((right - 166.0).abs() < 1.0 && (bottom - 166.0).abs() < 1.0);
+        Ok(())
+    }
+
+    #[test]
+    fn text_and_noise_are_not_barcodes() {
+        let mut image = page(400, 300);
+        for y in (20..280).step_by(15) {
+            for x in 10..390 {
+                if (x * 7 + y) % 11 < 4 {
+                    for dy in 0..8 {
+                        image.pixels[(y + dy) * 400 + x] = 30;
+                    }
+                }
+            }
+        }
+        assert!(read_barcodes(&image).is_empty());
+    }
+}
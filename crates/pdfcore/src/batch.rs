@@ -0,0 +1,103 @@
+//! Running one operation over several inputs with a result for each, so a
+//! bad file can be reported without losing the work done on the others.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use crate::{PdfError, Progress, Result};
+
+/// The outcome of an operation on one of several inputs.
+#[derive(Debug)]
+pub struct InputResult<T> {
+    /// The input.
+    pub input: PathBuf,
+    /// What the operation returned for it.
+    pub result: Result<T>,
+}
+
+/// Run `f` on each input in order and collect a result per input. Without
+/// `keep_going`, stops after the first failure; the inputs after it are left
+/// out of the results.
+pub fn for_each_input<T>(
+    inputs: &[impl AsRef<Path>],
+    keep_going: bool,
+    f: impl FnMut(&Path) -> Result<T>,
+) -> Vec<InputResult<T>> {
+    for_each_input_with_progress(inputs, keep_going, &Progress::default(), f)
+}
+
+/// [`for_each_input`], reporting each input to `progress` as it is queued,
+/// started (by worker 0), and done.
+pub fn for_each_input_with_progress<T>(
+    inputs: &[impl AsRef<Path>],
+    keep_going: bool,
+    progress: &Progress,
+    mut f: impl FnMut(&Path) -> Result<T>,
+) -> Vec<InputResult<T>> {
+    for input in inputs {
+        progress.queued(input.as_ref());
+    }
+    let mut results = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let input = input.as_ref();
+        progress.started(input, 0);
+        let start = Instant::now();
+        let result = f(input);
+        progress.done(input, 0, start.elapsed(), &result);
+        let failed = result.is_err();
+        results.push(InputResult {
+            input: input.to_path_buf(),
+            result,
+        });
+        if failed && !keep_going {
+            break;
+        }
+    }
+    results
+}
+
+/// `Ok` if no input failed, else [`PdfError::InputsFailed`] counting the
+/// failures out of `total` inputs.
+pub fn check_inputs<T>(results: &[InputResult<T>], total: usize) -> Result<()> {
+    match results.iter().filter(|r| r.result.is_err()).count() {
+        0 => Ok(()),
+        failed => Err(PdfError::InputsFailed { failed, total }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn even(path: &Path) -> Result<usize> {
+        let n = path.to_string_lossy().len();
+        if n.is_multiple_of(2) {
+            Ok(n)
+        } else {
+            Err(PdfError::InvalidArgument(format!("odd: {n}")))
+        }
+    }
+
+    #[test]
+    fn failures_stop_the_batch_unless_keeping_going() {
+        let inputs = ["ab", "abc", "abcd"];
+        let kept = for_each_input(&inputs, true, even);
+        assert_eq!(kept.len(), 3);
+        assert!(matches!(
+            check_inputs(&kept, 3),
+            Err(PdfError::InputsFailed {
+                failed: 1,
+                total: 3
+            })
+        ));
+
+        let stopped = for_each_input(&inputs, false, even);
+        assert_eq!(stopped.len(), 2);
+        assert_eq!(stopped[1].input, Path::new("abc"));
+
+        let ok = for_each_input(&["ab", "abcd"], false, even);
+        assert!(check_inputs(&ok, 2).is_ok());
+    }
+}
@@ -0,0 +1,232 @@
+//! Bates numbering: stamping every page of a set of documents with a
+//! unique, sequential identifier (a prefix and a zero-padded number), as
+//! legal discovery productions require. Numbering continues from one
+//! document to the next.
+
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    load_document,
+    manifest::file_sha256,
+    page_count, save_document,
+    stamp::{self, StampPosition, TextStamp},
+    validate_input_file, PdfError, Plan, Result,
+};
+
+/// Options for [`bates_number`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatesOptions {
+    /// Text before the number, e.g. `ABC`.
+    pub prefix: String,
+    /// Number of the first page of the first document.
+    pub start: u64,
+    /// Digits of the number, zero-padded.
+    pub digits: usize,
+    /// Where the number goes on each page.
+    pub position: StampPosition,
+    /// Font size in points.
+    pub font_size: f32,
+    /// Distance from the edges of the page, in points.
+    pub margin: f32,
+}
+
+impl Default for BatesOptions {
+    fn default() -> Self {
+        Self {
+            prefix: String::new(),
+            start: 1,
+            digits: 6,
+            position: StampPosition::BottomRight,
+            font_size: 10.0,
+            margin: 18.0,
+        }
+    }
+}
+
+impl BatesOptions {
+    /// The Bates number of the page numbered `number`.
+    #[must_use]
+    pub fn label(&self, number: u64) -> String {
+        format!("{}{number:0width$}", self.prefix, width = self.digits)
+    }
+}
+
+/// The Bates numbers given to one document, for the production's mapping
+/// manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatesRange {
+    /// The original document.
+    pub input: PathBuf,
+    /// The stamped document.
+    pub output: PathBuf,
+    /// Number of pages.
+    pub pages: u32,
+    /// Bates number of the first page.
+    pub first: String,
+    /// Bates number of the last page.
+    pub last: String,
+    /// SHA-256 of the stamped document, lower-case hex.
+    pub sha256: String,
+}
+
+/// Stamp Bates numbers on every page of `inputs`, in order, numbering on
+/// from one document to the next (pure Rust). Each stamped document is
+/// written to `out_dir` under its original file name.
+///
+/// The page counts of all inputs are read before anything is written, so
+/// that a damaged file in the middle of a production is found before the
+/// documents ahead of it are numbered.
+pub fn bates_number(
+    inputs: &[impl AsRef<Path>],
+    out_dir: impl AsRef<Path>,
+    options: &BatesOptions,
+) -> Result<Vec<BatesRange>> {
+    let jobs = bates_jobs(inputs, out_dir.as_ref(), options)?;
+    stamp::stamp_text_bytes(&options.prefix, "prefix")?;
+    fs::create_dir_all(out_dir.as_ref())?;
+    let mut ranges = Vec::with_capacity(jobs.len());
+    for job in jobs {
+        let mut doc = load_document(&job.input)?;
+        let font = stamp::add_stamp_font(&mut doc);
+        for (number, (_, page_id)) in (job.first..).zip(doc.get_pages()) {
+            let stamp = TextStamp {
+                text: stamp::stamp_text_bytes(&options.label(number), "Bates number")?,
+                size: options.font_size,
+                margin: options.margin,
+                position: options.position,
+            };
+            stamp::stamp_text(&mut doc, page_id, font, &stamp)?;
+        }
+        save_document(&mut doc, &job.output)?;
+        ranges.push(BatesRange {
+            sha256: file_sha256(&job.output)?,
+            first: options.label(job.first),
+            last: options.label(job.first + u64::from(job.pages.max(1)) - 1),
+            pages: job.pages,
+            input: job.input,
+            output: job.output,
+        });
+    }
+    Ok(ranges)
+}
+
+/// What [`bates_number`] would do.
+pub fn bates_number_plan(
+    inputs: &[impl AsRef<Path>],
+    out_dir: impl AsRef<Path>,
+    options: &BatesOptions,
+) -> Result<Plan> {
+    let jobs = bates_jobs(inputs, out_dir.as_ref(), options)?;
+    stamp::stamp_text_bytes(&options.prefix, "prefix")?;
+    let mut plan = Plan::new();
+    for job in &jobs {
+        plan = plan
+            .native(format!(
+                "stamp {} to {} on the {} page(s) of {}",
+                options.label(job.first),
+                options.label(job.first + u64::from(job.pages.max(1)) - 1),
+                job.pages,
+                job.input.display()
+            ))
+            .write(&job.output);
+    }
+    Ok(plan)
+}
+
+/// One document to number.
+struct BatesJob {
+    input: PathBuf,
+    output: PathBuf,
+    pages: u32,
+    /// Number of its first page.
+    first: u64,
+}
+
+/// The documents to number with their first numbers, after checking the
+/// options and that the outputs neither collide nor overwrite an input.
+fn bates_jobs(
+    inputs: &[impl AsRef<Path>],
+    out_dir: &Path,
+    options: &BatesOptions,
+) -> Result<Vec<BatesJob>> {
+    if inputs.is_empty() {
+        return Err(PdfError::InvalidArgument("no input PDFs given".to_string()));
+    }
+    if !(1..=18).contains(&options.digits) {
+        return Err(PdfError::InvalidArgument(format!(
+            "Bates numbers need 1 to 18 digits, not {}",
+            options.digits
+        )));
+    }
+    if options.font_size <= 0.0 || options.margin < 0.0 {
+        return Err(PdfError::InvalidArgument(
+            "font size must be positive and margin not negative".to_string(),
+        ));
+    }
+    let mut names = BTreeSet::new();
+    let mut jobs = Vec::with_capacity(inputs.len());
+    let mut next = options.start;
+    for input in inputs {
+        let input = input.as_ref();
+        validate_input_file(input)?;
+        let name = input.file_name().ok_or_else(|| {
+            PdfError::InvalidArgument(format!("{} has no file name", input.display()))
+        })?;
+        if !names.insert(name.to_os_string()) {
+            return Err(PdfError::InvalidArgument(format!(
+                "two inputs are named {}; their stamped copies would overwrite each other",
+                name.to_string_lossy()
+            )));
+        }
+        let output = out_dir.join(name);
+        if same_file(input, &output) {
+            return Err(PdfError::InvalidArgument(format!(
+                "{} would overwrite its input; choose another output directory",
+                output.display()
+            )));
+        }
+        let pages = page_count(input)?;
+        jobs.push(BatesJob {
+            input: input.to_path_buf(),
+            output,
+            pages,
+            first: next,
+        });
+        next += u64::from(pages);
+    }
+    let last = next.saturating_sub(1).max(options.start);
+    if last.to_string().len() > options.digits {
+        return Err(PdfError::InvalidArgument(format!(
+            "the last number, {last}, has more than {} digits",
+            options.digits
+        )));
+    }
+    Ok(jobs)
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_are_zero_padded() {
+        let options = BatesOptions {
+            prefix: "ABC".to_string(),
+            start: 1000,
+            ..BatesOptions::default()
+        };
+        assert_eq!(options.label(1000), "ABC001000");
+        assert_eq!(options.label(1_234_567), "ABC1234567");
+    }
+}
@@ -0,0 +1,277 @@
+//! Auditing and setting page boxes (`MediaBox`, `CropBox`, `BleedBox`,
+//! `TrimBox`, `ArtBox`).
+
+use std::path::Path;
+
+use crate::{
+    geometry::{self, PageBox, Rect},
+    load_document, load_for_reading, save_document, validate_input_file, PageSelection, PdfError,
+    Plan, Result,
+};
+
+/// Overhang in points tolerated before a box counts as outside another.
+const TOLERANCE: f32 = 0.01;
+
+/// One box of a page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoxInfo {
+    pub which: PageBox,
+    /// The effective box, after the PDF defaulting rules.
+    pub rect: Rect,
+    /// Whether the box is set on the page (or inherited from the page
+    /// tree) rather than defaulted.
+    pub explicit: bool,
+}
+
+/// The boxes of one page, for [`page_boxes`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageBoxes {
+    /// 1-based page number.
+    pub page: u32,
+    /// The page's `/Rotate`, in degrees.
+    pub rotate: i64,
+    /// All five boxes, media box first.
+    pub boxes: Vec<BoxInfo>,
+    /// Boxes that extend outside the box that should contain them.
+    pub problems: Vec<String>,
+}
+
+impl PageBoxes {
+    /// The effective `which` box.
+    #[must_use]
+    pub fn get(&self, which: PageBox) -> Option<Rect> {
+        self.boxes.iter().find(|b| b.which == which).map(|b| b.rect)
+    }
+}
+
+/// The boxes of every page of `input` (pure Rust), with the boxes that lie
+/// outside the media box, or a trim or art box that lies outside the bleed
+/// box.
+pub fn page_boxes(input: impl AsRef<Path>) -> Result<Vec<PageBoxes>> {
+    let doc = load_for_reading(input.as_ref())?;
+    Ok(doc
+        .get_pages()
+        .into_iter()
+        .map(|(page, page_id)| {
+            let boxes: Vec<BoxInfo> = PageBox::ALL
+                .into_iter()
+                .map(|which| BoxInfo {
+                    which,
+                    rect: geometry::effective_page_box(&doc, page_id, which),
+                    explicit: geometry::explicit_page_box(&doc, page_id, which).is_some(),
+                })
+                .collect();
+            let rotate = doc
+                .get_dictionary(page_id)
+                .ok()
+                .and_then(|dict| crate::native::inherited(&doc, dict, b"Rotate"))
+                .and_then(|r| r.as_i64().ok())
+                .unwrap_or(0);
+            let problems = box_problems(&boxes);
+            PageBoxes {
+                page,
+                rotate,
+                boxes,
+                problems,
+            }
+        })
+        .collect())
+}
+
+fn box_problems(boxes: &[BoxInfo]) -> Vec<String> {
+    let rect = |which| {
+        boxes
+            .iter()
+            .find(|b| b.which == which)
+            .map_or(Rect::from_size(0.0, 0.0), |b| b.rect)
+    };
+    let mut problems = Vec::new();
+    for (inner, outer) in [
+        (PageBox::Crop, PageBox::Media),
+        (PageBox::Bleed, PageBox::Media),
+        (PageBox::Trim, PageBox::Media),
+        (PageBox::Art, PageBox::Media),
+        (PageBox::Trim, PageBox::Bleed),
+        (PageBox::Art, PageBox::Bleed),
+    ] {
+        let explicit = boxes.iter().any(|b| b.which == inner && b.explicit);
+        if explicit && !rect(outer).contains(&rect(inner), TOLERANCE) {
+            problems.push(format!("{inner} box extends outside the {outer} box"));
+        }
+    }
+    problems
+}
+
+/// Changes for [`set_page_boxes`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoxChanges {
+    /// Boxes to set.
+    pub set: Vec<(PageBox, Rect)>,
+    /// Boxes to remove, so that they default to the crop box (or, for the
+    /// crop box, to the media box) again.
+    pub unset: Vec<PageBox>,
+    /// The pages to change.
+    pub pages: PageSelection,
+}
+
+impl Default for BoxChanges {
+    fn default() -> Self {
+        Self {
+            set: Vec::new(),
+            unset: Vec::new(),
+            pages: PageSelection::All,
+        }
+    }
+}
+
+/// Set and remove page boxes on the selected pages of `input` (pure Rust).
+/// Returns the number of pages changed.
+///
+/// A box set outside the page's media box is refused, since viewers and
+/// printers clip every box to it.
+pub fn set_page_boxes(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    changes: &BoxChanges,
+) -> Result<u32> {
+    validate_changes(changes)?;
+    let mut doc = load_document(input.as_ref())?;
+    let mut pages_changed = 0;
+    for (page, page_id) in doc.get_pages() {
+        if !changes.pages.contains(page) {
+            continue;
+        }
+        for &which in &changes.unset {
+            if let Ok(dict) = doc.get_dictionary_mut(page_id) {
+                dict.remove(which.key().as_bytes());
+            }
+        }
+        for &(which, rect) in &changes.set {
+            geometry::set_page_box(&mut doc, page_id, which, rect)?;
+        }
+        let media = geometry::effective_page_box(&doc, page_id, PageBox::Media);
+        for &(which, rect) in &changes.set {
+            if !media.contains(&rect, TOLERANCE) {
+                return Err(PdfError::InvalidArgument(format!(
+                    "page {page}: the {which} box {rect} is outside the media box {media}"
+                )));
+            }
+        }
+        pages_changed += 1;
+    }
+    if pages_changed == 0 {
+        return Err(PdfError::InvalidArgument(format!(
+            "no page selected; the document has {} page(s)",
+            doc.get_pages().len()
+        )));
+    }
+    save_document(&mut doc, output.as_ref())?;
+    Ok(pages_changed)
+}
+
+/// What [`set_page_boxes`] would do.
+pub fn set_page_boxes_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    changes: &BoxChanges,
+) -> Result<Plan> {
+    validate_input_file(input.as_ref())?;
+    validate_changes(changes)?;
+    let pages = match changes.pages {
+        PageSelection::All => "every page".to_string(),
+        PageSelection::Range { start, end } if start == end => format!("page {start}"),
+        PageSelection::Range { start, end } => format!("pages {start}-{end}"),
+        PageSelection::From { start } => format!("pages {start} to the end"),
+    };
+    let mut plan = Plan::new();
+    for which in &changes.unset {
+        plan = plan.native(format!("remove {} from {pages}", which.key()));
+    }
+    for (which, rect) in &changes.set {
+        plan = plan.native(format!("set {} to {rect} on {pages}", which.key()));
+    }
+    Ok(plan.write(output.as_ref()))
+}
+
+fn validate_changes(changes: &BoxChanges) -> Result<()> {
+    if changes.set.is_empty() && changes.unset.is_empty() {
+        return Err(PdfError::InvalidArgument(
+            "no box to set or remove".to_string(),
+        ));
+    }
+    if changes.unset.contains(&PageBox::Media) {
+        return Err(PdfError::InvalidArgument(
+            "the media box cannot be removed; every page needs one".to_string(),
+        ));
+    }
+    for (i, (which, rect)) in changes.set.iter().enumerate() {
+        if changes.unset.contains(which) || changes.set[..i].iter().any(|(w, _)| w == which) {
+            return Err(PdfError::InvalidArgument(format!(
+                "the {which} box is changed twice"
+            )));
+        }
+        if rect.width() <= 0.0 || rect.height() <= 0.0 {
+            return Err(PdfError::InvalidArgument(format!(
+                "the {which} box {rect} has no area"
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_explicit_boxes_outside_their_container_are_problems() {
+        let info = |which, rect, explicit| BoxInfo {
+            which,
+            rect,
+            explicit,
+        };
+        let media = Rect::from_size(600.0, 800.0);
+        let trim = Rect {
+            llx: 20.0,
+            lly: 20.0,
+            urx: 620.0,
+            ury: 780.0,
+        };
+        let boxes = vec![
+            info(PageBox::Media, media, true),
+            info(PageBox::Crop, media, false),
+            info(PageBox::Bleed, media, false),
+            info(PageBox::Trim, trim, true),
+            info(PageBox::Art, media, false),
+        ];
+        assert_eq!(
+            box_problems(&boxes),
+            [
+                "trim box extends outside the media box",
+                "trim box extends outside the bleed box"
+            ]
+        );
+    }
+
+    #[test]
+    fn conflicting_changes_are_refused() {
+        let trim = (PageBox::Trim, Rect::from_size(10.0, 10.0));
+        let twice = BoxChanges {
+            set: vec![trim],
+            unset: vec![PageBox::Trim],
+            ..BoxChanges::default()
+        };
+        assert!(validate_changes(&twice).is_err());
+        let media = BoxChanges {
+            unset: vec![PageBox::Media],
+            ..BoxChanges::default()
+        };
+        assert!(validate_changes(&media).is_err());
+        assert!(validate_changes(&BoxChanges::default()).is_err());
+        let ok = BoxChanges {
+            set: vec![trim],
+            ..BoxChanges::default()
+        };
+        assert!(validate_changes(&ok).is_ok());
+    }
+}
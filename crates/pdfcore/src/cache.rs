@@ -0,0 +1,146 @@
+//! A cache of generated files (thumbnails, rendered pages) keyed by the
+//! content of the input PDF, so work on unchanged files is not redone.
+//!
+//! Each entry is a directory named after its key, filled completely in a
+//! temporary directory next to it and then renamed into place, so readers
+//! never see a partial entry and concurrent writers do not clash.
+
+use std::{
+    fmt::Write as _,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use md5::{Digest, Md5};
+
+use crate::Result;
+
+/// A cache directory; see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cache {
+    root: PathBuf,
+}
+
+/// A cache entry returned by [`Cache::get_or_insert_with`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry {
+    /// Directory holding the entry's files.
+    pub dir: PathBuf,
+    /// Whether the entry was already cached.
+    pub hit: bool,
+}
+
+impl Cache {
+    /// Use `root` as the cache directory, creating it if needed.
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root)?;
+        Ok(Self { root })
+    }
+
+    /// The cache directory.
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Key for the output of an operation on `input`: a hash of the file's
+    /// content and of `params`, which should name the operation and every
+    /// setting that changes its output (e.g. `"thumbnail dpi=36 engine=gs"`).
+    pub fn key(input: &Path, params: &str) -> Result<String> {
+        let mut hasher = Md5::new();
+        let mut file = File::open(input)?;
+        let mut buffer = vec![0; 64 << 10];
+        loop {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        hasher.update([0]);
+        hasher.update(params.as_bytes());
+        Ok(hasher
+            .finalize()
+            .iter()
+            .fold(String::with_capacity(32), |mut hex, byte| {
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            }))
+    }
+
+    /// The entry for `key`, if cached.
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<PathBuf> {
+        let dir = self.root.join(key);
+        dir.is_dir().then_some(dir)
+    }
+
+    /// The entry for `key`, created by `fill` (which writes the entry's files
+    /// into the directory it is given) if it is not cached yet. Nothing is
+    /// cached if `fill` fails.
+    pub fn get_or_insert_with(
+        &self,
+        key: &str,
+        fill: impl FnOnce(&Path) -> Result<()>,
+    ) -> Result<CacheEntry> {
+        if let Some(dir) = self.get(key) {
+            tracing::debug!(key, "cache hit");
+            return Ok(CacheEntry { dir, hit: true });
+        }
+        let staging = tempfile::Builder::new()
+            .prefix(&format!(".{key}-"))
+            .tempdir_in(&self.root)?;
+        fill(staging.path())?;
+        let dir = self.root.join(key);
+        // Another process may have filled the same entry meanwhile; its copy
+        // is as good as ours.
+        if fs::rename(staging.path(), &dir).is_err() && !dir.is_dir() {
+            return Err(std::io::Error::other(format!(
+                "could not move the cache entry into {}",
+                dir.display()
+            ))
+            .into());
+        }
+        tracing::debug!(key, "cached");
+        Ok(CacheEntry { dir, hit: false })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_are_filled_once_per_content_and_params() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let cache = Cache::open(dir.path().join("cache"))?;
+        let input = dir.path().join("in.pdf");
+        fs::write(&input, "%PDF-1.4 first")?;
+
+        let key = Cache::key(&input, "thumbnail dpi=36")?;
+        assert_ne!(key, Cache::key(&input, "thumbnail dpi=72")?);
+        let mut fills = 0;
+        let mut fill = |dir: &Path| {
+            fills += 1;
+            fs::write(dir.join("out.png"), "png")?;
+            Ok(())
+        };
+        let first = cache.get_or_insert_with(&key, &mut fill)?;
+        let second = cache.get_or_insert_with(&key, &mut fill)?;
+        assert!(!first.hit && second.hit);
+        assert_eq!(first.dir, second.dir);
+        assert_eq!(fs::read_to_string(second.dir.join("out.png"))?, "png");
+        assert_eq!(fills, 1);
+
+        fs::write(&input, "%PDF-1.4 second")?;
+        assert_ne!(Cache::key(&input, "thumbnail dpi=36")?, key);
+
+        let failed = cache.get_or_insert_with("broken", |_| {
+            Err(crate::PdfError::InvalidArgument("no".to_string()))
+        });
+        assert!(failed.is_err() && cache.get("broken").is_none());
+        Ok(())
+    }
+}
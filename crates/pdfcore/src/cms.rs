@@ -0,0 +1,295 @@
+//! The CMS signatures in signed PDFs, read and written with the `cms` and
+//! `der` crates: finding a signer's signature value, adding an unsigned
+//! attribute to it, and reading the RFC 3161 timestamp token kept in one.
+
+use cms::{
+    cert::x509::{attr::Attribute, spki::AlgorithmIdentifierOwned},
+    content_info::ContentInfo,
+    signed_data::{SignedData, SignerInfo, SignerInfos},
+};
+use der::{
+    asn1::{AnyRef, Int, ObjectIdentifier, OctetString, SetOfVec},
+    Any, Decode, Encode, Header, Reader, SliceReader, Tag, Tagged,
+};
+
+use crate::{PdfError, Result};
+
+/// `id-aa-signatureTimeStampToken` (1.2.840.113549.1.9.16.2.14).
+pub(crate) const SIGNATURE_TIMESTAMP_TOKEN: ObjectIdentifier =
+    ObjectIdentifier::new_unwrap("1.2.840.113549.1.9.16.2.14");
+
+/// `id-sha256` (2.16.840.1.101.3.4.2.1).
+pub(crate) const SHA256: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.2.1");
+
+fn invalid(what: impl std::fmt::Display) -> PdfError {
+    PdfError::InvalidArgument(format!("invalid CMS signature: {what}"))
+}
+
+/// The length of the DER element at the start of `data`: a signature in
+/// `/Contents` is followed by the zeros padding its placeholder.
+pub(crate) fn element_len(data: &[u8]) -> Result<usize> {
+    let mut reader = SliceReader::new(data).map_err(invalid)?;
+    let header = Header::decode(&mut reader).map_err(invalid)?;
+    let len = (header.encoded_len().map_err(invalid)? + header.length).map_err(invalid)?;
+    let len = usize::try_from(u32::from(len)).map_err(invalid)?;
+    if len > data.len() {
+        return Err(invalid("truncated"));
+    }
+    Ok(len)
+}
+
+/// The `SignedData` in a `ContentInfo`.
+fn signed_data(content_info: &[u8]) -> Result<SignedData> {
+    ContentInfo::from_der(content_info)
+        .and_then(|info| info.content.decode_as::<SignedData>())
+        .map_err(invalid)
+}
+
+/// The first signer of `data`.
+fn first_signer(data: &SignedData) -> Result<&SignerInfo> {
+    data.signer_infos
+        .0
+        .get(0)
+        .ok_or_else(|| invalid("no signer infos"))
+}
+
+/// The signature value of the first signer of `content_info`: what an RFC
+/// 3161 signature timestamp covers.
+pub(crate) fn signature_value(content_info: &[u8]) -> Result<Vec<u8>> {
+    let data = signed_data(content_info)?;
+    Ok(first_signer(&data)?.signature.as_bytes().to_vec())
+}
+
+/// `content_info` with the unsigned attribute `oid` = `value` (one DER
+/// element) added to its first signer.
+pub(crate) fn add_unsigned_attribute(
+    content_info: &[u8],
+    oid: ObjectIdentifier,
+    value: &[u8],
+) -> Result<Vec<u8>> {
+    let info = ContentInfo::from_der(content_info).map_err(invalid)?;
+    let mut data = info.content.decode_as::<SignedData>().map_err(invalid)?;
+    let mut signers = data.signer_infos.0.into_vec();
+    let signer = signers
+        .first_mut()
+        .ok_or_else(|| invalid("no signer infos"))?;
+    let attribute = Attribute {
+        oid,
+        values: SetOfVec::try_from(vec![Any::from_der(value).map_err(invalid)?])
+            .map_err(invalid)?,
+    };
+    signer
+        .unsigned_attrs
+        .get_or_insert_with(SetOfVec::new)
+        .insert(attribute)
+        .map_err(invalid)?;
+    data.signer_infos = SignerInfos(SetOfVec::try_from(signers).map_err(invalid)?);
+    ContentInfo {
+        content_type: info.content_type,
+        content: Any::encode_from(&data).map_err(invalid)?,
+    }
+    .to_der()
+    .map_err(invalid)
+}
+
+/// The RFC 3161 timestamp token among the unsigned attributes of the first
+/// signer of `content_info`, if it has one.
+pub(crate) fn timestamp_token(content_info: &[u8]) -> Result<Option<Vec<u8>>> {
+    let data = signed_data(content_info)?;
+    let token = first_signer(&data)?
+        .unsigned_attrs
+        .iter()
+        .flat_map(SetOfVec::iter)
+        .find(|attribute| attribute.oid == SIGNATURE_TIMESTAMP_TOKEN)
+        .and_then(|attribute| attribute.values.get(0));
+    token
+        .map(|token| token.to_der().map_err(invalid))
+        .transpose()
+}
+
+/// What a timestamp token says: when, and the digest of what.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TstInfo {
+    /// The time, as a DER `GeneralizedTime`, e.g. `20261016141800Z`.
+    pub gen_time: String,
+    /// The digest algorithm.
+    pub hash_algorithm: ObjectIdentifier,
+    /// The digest of the timestamped data.
+    pub hashed_message: Vec<u8>,
+}
+
+/// The `TSTInfo` inside a timestamp token.
+pub(crate) fn tst_info(token: &[u8]) -> Result<TstInfo> {
+    let data = signed_data(token)?;
+    let content = data
+        .encap_content_info
+        .econtent
+        .ok_or_else(|| invalid("the timestamp token has no content"))?
+        .decode_as::<OctetString>()
+        .map_err(invalid)?;
+    let mut reader = SliceReader::new(content.as_bytes()).map_err(invalid)?;
+    let info = reader
+        .sequence(|fields| {
+            Int::decode(fields)?; // version
+            ObjectIdentifier::decode(fields)?; // policy
+            let (algorithm, hashed) = fields.sequence(|imprint| {
+                Ok((
+                    AlgorithmIdentifierOwned::decode(imprint)?,
+                    OctetString::decode(imprint)?,
+                ))
+            })?;
+            Int::decode(fields)?; // serial number
+                                  // Kept as text: timestamps may have fractional seconds, which
+                                  // `der`'s `GeneralizedTime` does not accept.
+            let time = AnyRef::decode(fields)?;
+            time.tag().assert_eq(Tag::GeneralizedTime)?;
+            // accuracy, ordering, nonce, tsa, extensions
+            while !fields.is_finished() {
+                AnyRef::decode(fields)?;
+            }
+            Ok(TstInfo {
+                gen_time: String::from_utf8_lossy(time.value()).into_owned(),
+                hash_algorithm: algorithm.oid,
+                hashed_message: hashed.into_bytes(),
+            })
+        })
+        .map_err(invalid)?;
+    reader.finish(info).map_err(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use cms::{
+        cert::x509::ext::pkix::SubjectKeyIdentifier,
+        content_info::CmsVersion,
+        signed_data::{
+            CertificateSet, DigestAlgorithmIdentifiers, EncapsulatedContentInfo, SignerIdentifier,
+        },
+    };
+    use der::asn1::GeneralizedTime;
+
+    use super::*;
+
+    /// `id-signedData` and `id-data` (RFC 5652), and `id-ct-TSTInfo`
+    /// (RFC 3161).
+    const SIGNED_DATA: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.7.2");
+    const DATA: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.7.1");
+    const TST_INFO: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.9.16.1.4");
+
+    fn sha256() -> AlgorithmIdentifierOwned {
+        AlgorithmIdentifierOwned {
+            oid: SHA256,
+            parameters: None,
+        }
+    }
+
+    /// A `ContentInfo` holding `SignedData` of `content` (`econtent_type`)
+    /// with one signer whose signature value is `signature`.
+    fn content_info(
+        econtent_type: ObjectIdentifier,
+        content: Option<&[u8]>,
+        signature: &[u8],
+    ) -> Result<Vec<u8>> {
+        let signer = SignerInfo {
+            version: CmsVersion::V3,
+            sid: SignerIdentifier::SubjectKeyIdentifier(SubjectKeyIdentifier(
+                OctetString::new(vec![7; 20]).map_err(invalid)?,
+            )),
+            digest_alg: sha256(),
+            signed_attrs: None,
+            signature_algorithm: sha256(),
+            signature: OctetString::new(signature).map_err(invalid)?,
+            unsigned_attrs: None,
+        };
+        let econtent = content
+            .map(|content| Any::encode_from(&OctetString::new(content)?))
+            .transpose()
+            .map_err(invalid)?;
+        let data = SignedData {
+            version: CmsVersion::V3,
+            digest_algorithms: DigestAlgorithmIdentifiers::try_from(vec![sha256()])
+                .map_err(invalid)?,
+            encap_content_info: EncapsulatedContentInfo {
+                econtent_type,
+                econtent,
+            },
+            certificates: None::<CertificateSet>,
+            crls: None,
+            signer_infos: SignerInfos(SetOfVec::try_from(vec![signer]).map_err(invalid)?),
+        };
+        ContentInfo {
+            content_type: SIGNED_DATA,
+            content: Any::encode_from(&data).map_err(invalid)?,
+        }
+        .to_der()
+        .map_err(invalid)
+    }
+
+    #[test]
+    fn lengths_come_from_the_header() -> Result<()> {
+        for len in [0, 1, 127, 128, 255, 256, 70_000] {
+            let der = OctetString::new(vec![7; len])
+                .and_then(|s| s.to_der())
+                .map_err(invalid)?;
+            assert_eq!(element_len(&[der.as_slice(), &[0; 9]].concat())?, der.len());
+        }
+        // Indefinite lengths are BER, not DER.
+        assert!(element_len(&[0x30, 0x80, 0, 0]).is_err());
+        assert!(element_len(&[0x30, 0x05, 1]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn timestamp_tokens_are_unsigned_attributes() -> Result<()> {
+        let data = content_info(DATA, None, b"signature value")?;
+        assert_eq!(signature_value(&data)?, b"signature value");
+        assert_eq!(timestamp_token(&data)?, None);
+
+        let token = OctetString::new(vec![1; 300])
+            .and_then(|s| s.to_der())
+            .map_err(invalid)?;
+        let stamped = add_unsigned_attribute(&data, SIGNATURE_TIMESTAMP_TOKEN, &token)?;
+        assert_eq!(signature_value(&stamped)?, b"signature value");
+        assert_eq!(timestamp_token(&stamped)?, Some(token));
+        assert_eq!(element_len(&stamped)?, stamped.len());
+        assert!(signature_value(b"\x30\x03\x02\x01\x01").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn tst_info_gives_the_time_and_imprint() -> Result<()> {
+        let time = der::DateTime::new(2026, 10, 16, 9, 30, 0)
+            .map(GeneralizedTime::from_date_time)
+            .map_err(invalid)?;
+        let imprint = [
+            sha256().to_der().map_err(invalid)?,
+            OctetString::new([0xab; 32])
+                .and_then(|s| s.to_der())
+                .map_err(invalid)?,
+        ]
+        .concat();
+        let fields = [
+            Int::new(&[1]).and_then(|i| i.to_der()),
+            ObjectIdentifier::new_unwrap("1.2.3.4").to_der(),
+            AnyRef::new(Tag::Sequence, &imprint).and_then(|a| a.to_der()),
+            Int::new(&[42]).and_then(|i| i.to_der()),
+            time.to_der(),
+            true.to_der(),
+        ]
+        .into_iter()
+        .collect::<der::Result<Vec<_>>>()
+        .map_err(invalid)?
+        .concat();
+        let info = AnyRef::new(Tag::Sequence, &fields)
+            .and_then(|a| a.to_der())
+            .map_err(invalid)?;
+        let token = content_info(TST_INFO, Some(&info), b"tsa signature")?;
+
+        let info = tst_info(&token)?;
+        assert_eq!(info.gen_time, "20261016093000Z");
+        assert_eq!(info.hash_algorithm, SHA256);
+        assert_eq!(info.hashed_message, [0xab; 32]);
+        assert!(tst_info(&content_info(TST_INFO, None, b"tsa signature")?).is_err());
+        Ok(())
+    }
+}
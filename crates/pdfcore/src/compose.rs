@@ -0,0 +1,1251 @@
+//! Composing new documents from plain text and Markdown (pure Rust).
+//!
+//! Pages are set in the standard Helvetica and Courier fonts, which every
+//! PDF reader has, so no font is embedded and only characters of the
+//! Windows-1252 (`WinAnsiEncoding`) set can be shown. Lines are broken
+//! greedily at spaces, pages at the bottom margin, and headings are kept
+//! with the line after them.
+
+use std::{fs, path::Path};
+
+use lopdf::{
+    content::{Content, Operation},
+    Dictionary, Document, Object, StringFormat,
+};
+use regex::Regex;
+
+use crate::{
+    save_document, validate_input_file,
+    warnings::{self, WarningKind},
+    PaperSize, PdfError, Plan, Rect, Result,
+};
+
+/// Widths of the printable ASCII characters in Helvetica, in thousandths of
+/// the font size (from Adobe's font metrics; the oblique face is the same).
+const HELVETICA: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278,
+    278, // space to /
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, // digits
+    278, 278, 584, 584, 584, 556, 1015, // : to @
+    667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667,
+    611, 722, 667, 944, 667, 667, 611, // A to Z
+    278, 278, 278, 469, 556, 333, // [ to `
+    556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, 556, 556, 333, 500,
+    278, 556, 500, 722, 500, 500, 500, // a to z
+    334, 260, 334, 584, // { to ~
+];
+
+/// The same for Helvetica-Bold.
+const HELVETICA_BOLD: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278,
+    278, // space to /
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, // digits
+    333, 333, 584, 584, 584, 611, 975, // : to @
+    722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778, 667, 778, 722, 667,
+    611, 722, 667, 944, 667, 667, 611, // A to Z
+    333, 278, 333, 584, 556, 333, // [ to `
+    556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611, 611, 611, 389, 556,
+    333, 611, 556, 778, 556, 556, 500, // a to z
+    389, 280, 389, 584, // { to ~
+];
+
+/// Characters of `WinAnsiEncoding` outside ASCII and Latin-1, with their
+/// codes.
+const WIN_ANSI_EXTRA: [(char, u8); 27] = [
+    ('€', 0x80),
+    ('‚', 0x82),
+    ('ƒ', 0x83),
+    ('„', 0x84),
+    ('…', 0x85),
+    ('†', 0x86),
+    ('‡', 0x87),
+    ('ˆ', 0x88),
+    ('‰', 0x89),
+    ('Š', 0x8a),
+    ('‹', 0x8b),
+    ('Œ', 0x8c),
+    ('Ž', 0x8e),
+    ('‘', 0x91),
+    ('’', 0x92),
+    ('“', 0x93),
+    ('”', 0x94),
+    ('•', 0x95),
+    ('–', 0x96),
+    ('—', 0x97),
+    ('˜', 0x98),
+    ('™', 0x99),
+    ('š', 0x9a),
+    ('›', 0x9b),
+    ('œ', 0x9c),
+    ('ž', 0x9e),
+    ('Ÿ', 0x9f),
+];
+
+/// Line height as a multiple of the font size.
+const LEADING: f32 = 1.25;
+
+/// How the input of [`text_to_pdf`] is marked up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextFormat {
+    /// Plain text, set line by line in Courier as it is laid out; form
+    /// feeds start new pages.
+    Plain,
+    /// Markdown: headings, paragraphs, bold, italic, code, lists, block
+    /// quotes, and rules, set in Helvetica. Links show their text.
+    Markdown,
+}
+
+impl TextFormat {
+    /// Markdown for `.md` and `.markdown` files, plain text otherwise.
+    #[must_use]
+    pub fn for_path(path: &Path) -> Self {
+        let markdown = path.extension().is_some_and(|ext| {
+            ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown")
+        });
+        if markdown {
+            Self::Markdown
+        } else {
+            Self::Plain
+        }
+    }
+}
+
+/// Options for [`text_to_pdf`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextToPdfOptions {
+    /// Page size (portrait).
+    pub paper: PaperSize,
+    /// Size of body text in points; headings are set larger.
+    pub font_size: f32,
+    /// Margin on every side, in points.
+    pub margin: f32,
+    /// How the input is marked up.
+    pub format: TextFormat,
+}
+
+impl Default for TextToPdfOptions {
+    fn default() -> Self {
+        Self {
+            paper: PaperSize::A4,
+            font_size: 11.0,
+            margin: 72.0,
+            format: TextFormat::Plain,
+        }
+    }
+}
+
+/// Set the text file `input` on new pages and write them to `output`;
+/// returns the number of pages. The text is read as UTF-8 (as Latin-1 if
+/// it is not valid UTF-8); characters the standard fonts cannot show are
+/// replaced by `?`, with a warning.
+pub fn text_to_pdf(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &TextToPdfOptions,
+) -> Result<u32> {
+    let input = input.as_ref();
+    validate_input_file(input)?;
+    validate_options(options)?;
+    let text = read_text(&fs::read(input)?);
+    let mut lost = 0;
+    let blocks = match options.format {
+        TextFormat::Plain => plain_blocks(&text, options.font_size, &mut lost),
+        TextFormat::Markdown => markdown_blocks(&text, options.font_size, &mut lost),
+    };
+    if lost > 0 {
+        warnings::warn(
+            WarningKind::LossyText,
+            format!(
+                "{}: {lost} character(s) the standard fonts cannot show were replaced by '?'",
+                input.display()
+            ),
+        );
+    }
+    let pages = paginate(&blocks, options);
+    let count = u32::try_from(pages.len()).unwrap_or(u32::MAX);
+    let mut doc = build_document(pages, options)?;
+    save_document(&mut doc, output.as_ref())?;
+    Ok(count)
+}
+
+/// What [`text_to_pdf`] would do.
+pub fn text_to_pdf_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &TextToPdfOptions,
+) -> Result<Plan> {
+    let input = input.as_ref();
+    validate_input_file(input)?;
+    validate_options(options)?;
+    let what = match options.format {
+        TextFormat::Plain => "plain text",
+        TextFormat::Markdown => "Markdown",
+    };
+    Ok(Plan::new()
+        .native(format!(
+            "set {} as {what} in {} pt type on {} pages",
+            input.display(),
+            options.font_size,
+            options.paper
+        ))
+        .write(output.as_ref()))
+}
+
+fn validate_options(options: &TextToPdfOptions) -> Result<()> {
+    if !(4.0..=72.0).contains(&options.font_size) {
+        return Err(PdfError::InvalidArgument(format!(
+            "font size must be between 4 and 72 points, not {}",
+            options.font_size
+        )));
+    }
+    let room = options.paper.width.min(options.paper.height) - 2.0 * options.margin;
+    if options.margin < 0.0 || room < options.font_size * 10.0 {
+        return Err(PdfError::InvalidArgument(format!(
+            "a margin of {} points leaves no room for text on {} paper",
+            options.margin, options.paper
+        )));
+    }
+    Ok(())
+}
+
+/// `bytes` as UTF-8 without a byte order mark, or as Latin-1.
+fn read_text(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|&b| char::from(b)).collect(),
+    }
+}
+
+/// One of the fonts pages are set in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Face {
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+    Mono,
+}
+
+impl Face {
+    const ALL: [Self; 5] = [
+        Self::Regular,
+        Self::Bold,
+        Self::Italic,
+        Self::BoldItalic,
+        Self::Mono,
+    ];
+
+    fn styled(bold: bool, italic: bool) -> Self {
+        match (bold, italic) {
+            (false, false) => Self::Regular,
+            (true, false) => Self::Bold,
+            (false, true) => Self::Italic,
+            (true, true) => Self::BoldItalic,
+        }
+    }
+
+    /// Name in the page resources.
+    fn resource(self) -> &'static str {
+        match self {
+            Self::Regular => "F1",
+            Self::Bold => "F2",
+            Self::Italic => "F3",
+            Self::BoldItalic => "F4",
+            Self::Mono => "F5",
+        }
+    }
+
+    fn base_font(self) -> &'static str {
+        match self {
+            Self::Regular => "Helvetica",
+            Self::Bold => "Helvetica-Bold",
+            Self::Italic => "Helvetica-Oblique",
+            Self::BoldItalic => "Helvetica-BoldOblique",
+            Self::Mono => "Courier",
+        }
+    }
+
+    /// Width of the character with `WinAnsiEncoding` code `code`, in
+    /// thousandths of the font size.
+    fn width(self, code: u8) -> u16 {
+        let bold = matches!(self, Self::Bold | Self::BoldItalic);
+        match (self, code) {
+            (Self::Mono, _) => 600,
+            (_, 32..=126) => {
+                let table = if bold { &HELVETICA_BOLD } else { &HELVETICA };
+                table[usize::from(code - 32)]
+            }
+            // Ellipsis, per mille, Œ, em dash, trade mark.
+            (_, 0x85 | 0x89 | 0x8c | 0x97 | 0x99) => 1000,
+            (_, 0x82 | 0x91 | 0x92) => {
+                if bold {
+                    278
+                } else {
+                    222
+                }
+            }
+            (_, 0x84 | 0x93 | 0x94) => {
+                if bold {
+                    500
+                } else {
+                    333
+                }
+            }
+            (_, 0x95) => 350,
+            (_, 0xa0) => 278,
+            (_, 0xb0) => 400,
+            // Capital letters with accents.
+            (_, 0xc0..=0xde) => 722,
+            _ => 556,
+        }
+    }
+
+    /// Width of `text` at `size` points.
+    fn measure(self, text: &[u8], size: f32) -> f32 {
+        let thousandths: u32 = text.iter().map(|&b| u32::from(self.width(b))).sum();
+        // Line widths are far below 2^24 thousandths.
+        #[allow(clippy::cast_precision_loss)]
+        let thousandths = thousandths as f32;
+        thousandths * size / 1000.0
+    }
+}
+
+/// The `WinAnsiEncoding` code of `c`.
+fn win_ansi(c: char) -> Option<u8> {
+    match c {
+        ' '..='~' | '\u{a0}'..='\u{ff}' => u8::try_from(c).ok(),
+        _ => WIN_ANSI_EXTRA
+            .iter()
+            .find(|&&(extra, _)| extra == c)
+            .map(|&(_, code)| code),
+    }
+}
+
+/// `text` in `WinAnsiEncoding`, counting the characters replaced by `?`
+/// in `lost`. Tabs and other control characters become spaces.
+pub(crate) fn encode(text: &str, lost: &mut usize) -> Vec<u8> {
+    text.chars()
+        .map(|c| {
+            if c.is_control() {
+                return b' ';
+            }
+            win_ansi(c).unwrap_or_else(|| {
+                *lost += 1;
+                b'?'
+            })
+        })
+        .collect()
+}
+
+/// Text in one face.
+#[derive(Debug, Clone, PartialEq)]
+struct Span {
+    face: Face,
+    text: Vec<u8>,
+}
+
+/// A paragraph, heading, list item, or line of preformatted text.
+#[derive(Debug, Clone, PartialEq)]
+struct TextBlock {
+    spans: Vec<Span>,
+    /// Font size in points.
+    size: f32,
+    /// Left indent from the margin, in points.
+    indent: f32,
+    /// Bullet or number set before the first line, in the indent.
+    marker: Option<Vec<u8>>,
+    /// Space above the block unless it starts a page, in points.
+    space_before: f32,
+    /// Keep on a page with the first line of the next block.
+    keep_with_next: bool,
+    /// Keep the spaces as they are and break lines only where they are too
+    /// long, rather than filling lines word by word.
+    preformatted: bool,
+}
+
+impl TextBlock {
+    fn new(spans: Vec<Span>, size: f32) -> Self {
+        Self {
+            spans,
+            size,
+            indent: 0.0,
+            marker: None,
+            space_before: 0.0,
+            keep_with_next: false,
+            preformatted: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Block {
+    Text(TextBlock),
+    /// A horizontal rule across the text.
+    Rule,
+    PageBreak,
+}
+
+/// Blocks of plain `text`: a line of Courier per line, a new page per form
+/// feed, and tabs every eight columns.
+fn plain_blocks(text: &str, size: f32, lost: &mut usize) -> Vec<Block> {
+    let mut pages: Vec<&str> = text.split('\x0c').collect();
+    // A form feed after the last page (as `extract-text` writes them) does
+    // not start another.
+    if pages.len() > 1 && pages.last().is_some_and(|page| page.trim().is_empty()) {
+        pages.pop();
+    }
+    let mut blocks = Vec::new();
+    for (i, page) in pages.into_iter().enumerate() {
+        if i > 0 {
+            blocks.push(Block::PageBreak);
+        }
+        for line in page.lines() {
+            let mut block = TextBlock::new(
+                vec![Span {
+                    face: Face::Mono,
+                    text: encode(&expand_tabs(line, 8), lost),
+                }],
+                size,
+            );
+            block.preformatted = true;
+            blocks.push(Block::Text(block));
+        }
+    }
+    blocks
+}
+
+/// `line` with tabs replaced by spaces up to the next multiple of `stop`
+/// columns.
+fn expand_tabs(line: &str, stop: usize) -> String {
+    let mut out = String::with_capacity(line.len());
+    for c in line.chars() {
+        if c == '\t' {
+            let column = out.chars().count();
+            out.extend(std::iter::repeat_n(' ', stop - column % stop));
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// A Markdown block whose lines are still being read.
+enum Open {
+    Paragraph(String),
+    Item {
+        level: usize,
+        marker: String,
+        text: String,
+    },
+    Quote(String),
+}
+
+impl Open {
+    fn into_block(self, size: f32, inline: &Inline, lost: &mut usize) -> Block {
+        let mut block = TextBlock::new(Vec::new(), size);
+        block.space_before = 0.6 * size;
+        match self {
+            Self::Paragraph(text) => block.spans = inline.spans(&text, lost),
+            Self::Item {
+                level,
+                marker,
+                text,
+            } => {
+                // Depths are bounded by the line length.
+                #[allow(clippy::cast_precision_loss)]
+                let depth = (level + 1) as f32;
+                block.indent = depth * 1.6 * size;
+                block.marker = Some(encode(&marker, lost));
+                block.space_before = 0.3 * size;
+                block.spans = inline.spans(&text, lost);
+            }
+            Self::Quote(text) => {
+                block.indent = 1.6 * size;
+                block.spans = inline.spans(text.trim(), lost);
+                restyle(&mut block.spans, Face::Italic);
+            }
+        }
+        Block::Text(block)
+    }
+}
+
+/// Blocks of Markdown `text`, set at `size` points for body text.
+fn markdown_blocks(text: &str, size: f32, lost: &mut usize) -> Vec<Block> {
+    let inline = Inline::new();
+    let mut blocks = Vec::new();
+    let mut open: Option<Open> = None;
+    let mut fence: Option<&str> = None;
+    let flush = |open: &mut Option<Open>, blocks: &mut Vec<Block>, lost: &mut usize| {
+        if let Some(open) = open.take() {
+            blocks.push(open.into_block(size, &inline, lost));
+        }
+    };
+
+    for line in text.lines() {
+        let line = expand_tabs(line, 4);
+        let trimmed = line.trim_start();
+        if let Some(marks) = fence {
+            if trimmed.starts_with(marks) {
+                fence = None;
+            } else {
+                let first = !matches!(blocks.last(), Some(Block::Text(b)) if b.preformatted);
+                blocks.push(code_line(&line, size, first, lost));
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            flush(&mut open, &mut blocks, lost);
+            fence = Some(if trimmed.starts_with("```") {
+                "```"
+            } else {
+                "~~~"
+            });
+            continue;
+        }
+        if trimmed.is_empty() {
+            flush(&mut open, &mut blocks, lost);
+            continue;
+        }
+        if let Some((level, title)) = heading(trimmed) {
+            flush(&mut open, &mut blocks, lost);
+            blocks.push(heading_block(level, title, size, &inline, lost));
+            continue;
+        }
+        if let (Some(Open::Paragraph(title)), Some(level)) = (&open, setext_level(trimmed)) {
+            blocks.push(heading_block(level, title, size, &inline, lost));
+            open = None;
+            continue;
+        }
+        if is_rule(trimmed) {
+            flush(&mut open, &mut blocks, lost);
+            blocks.push(Block::Rule);
+            continue;
+        }
+        if let Some((marker, rest)) = list_item(trimmed) {
+            flush(&mut open, &mut blocks, lost);
+            open = Some(Open::Item {
+                level: ((line.len() - trimmed.len()) / 2).min(5),
+                marker,
+                text: rest.to_string(),
+            });
+            continue;
+        }
+        if let Some(rest) = trimmed.strip_prefix('>') {
+            if !matches!(open, Some(Open::Quote(_))) {
+                flush(&mut open, &mut blocks, lost);
+                open = Some(Open::Quote(String::new()));
+            }
+            if let Some(Open::Quote(text)) = &mut open {
+                text.push(' ');
+                text.push_str(rest.trim());
+            }
+            continue;
+        }
+        match &mut open {
+            Some(Open::Paragraph(text) | Open::Item { text, .. } | Open::Quote(text)) => {
+                text.push(' ');
+                text.push_str(trimmed.trim_end());
+            }
+            None => open = Some(Open::Paragraph(trimmed.trim_end().to_string())),
+        }
+    }
+    flush(&mut open, &mut blocks, lost);
+    blocks
+}
+
+/// A line of a fenced code block; the `first` is set apart from the text
+/// before it.
+fn code_line(line: &str, size: f32, first: bool, lost: &mut usize) -> Block {
+    let mut block = TextBlock::new(
+        vec![Span {
+            face: Face::Mono,
+            text: encode(line, lost),
+        }],
+        0.9 * size,
+    );
+    block.indent = 1.6 * size;
+    block.preformatted = true;
+    block.space_before = if first { 0.6 * size } else { 0.0 };
+    Block::Text(block)
+}
+
+/// Level of the heading that `line` underlines (`===` or `---`), if it is
+/// one.
+fn setext_level(line: &str) -> Option<usize> {
+    let line = line.trim_end();
+    if line.is_empty() {
+        None
+    } else if line.bytes().all(|b| b == b'=') {
+        Some(1)
+    } else if line.bytes().all(|b| b == b'-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+/// Level and text of an ATX heading (`## Title`).
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.bytes().take_while(|&b| b == b'#').count();
+    let rest = &line[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    // A closing sequence of `#`s is not part of the title.
+    let title = rest.trim();
+    let title = match title.trim_end_matches('#') {
+        stripped if stripped.is_empty() || stripped.ends_with(' ') => stripped.trim_end(),
+        _ => title,
+    };
+    Some((level, title))
+}
+
+fn heading_block(level: usize, title: &str, size: f32, inline: &Inline, lost: &mut usize) -> Block {
+    let scale = match level {
+        1 => 1.8,
+        2 => 1.45,
+        3 => 1.2,
+        _ => 1.0,
+    };
+    let mut spans = inline.spans(title, lost);
+    restyle(&mut spans, Face::Bold);
+    let mut block = TextBlock::new(spans, scale * size);
+    block.space_before = size;
+    block.keep_with_next = true;
+    Block::Text(block)
+}
+
+/// Set `spans` in bold or italic (`face`) as well as any style they have;
+/// code stays as it is.
+fn restyle(spans: &mut [Span], face: Face) {
+    for span in spans {
+        span.face = match (span.face, face) {
+            (Face::Regular, face) => face,
+            (Face::Bold, Face::Italic) | (Face::Italic, Face::Bold) => Face::BoldItalic,
+            (other, _) => other,
+        };
+    }
+}
+
+/// Whether `line` is a thematic break: three or more `-`, `*`, or `_`,
+/// with spaces between them allowed.
+fn is_rule(line: &str) -> bool {
+    let marks: Vec<u8> = line.bytes().filter(|&b| b != b' ').collect();
+    marks.len() >= 3
+        && [b'-', b'*', b'_']
+            .iter()
+            .any(|&mark| marks.iter().all(|&b| b == mark))
+}
+
+/// Marker and text of a list item (`- item`, `* item`, `1. item`).
+fn list_item(line: &str) -> Option<(String, &str)> {
+    if let Some(rest) = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))
+        .or_else(|| line.strip_prefix("+ "))
+    {
+        return Some(("•".to_string(), rest.trim()));
+    }
+    let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+    let rest = &line[digits..];
+    let rest = rest
+        .strip_prefix(". ")
+        .or_else(|| rest.strip_prefix(") "))
+        .filter(|_| (1..=9).contains(&digits))?;
+    Some((format!("{}.", &line[..digits]), rest.trim()))
+}
+
+/// Inline Markdown: emphasis, code spans, links, and escapes.
+struct Inline {
+    links: Regex,
+}
+
+impl Inline {
+    fn new() -> Self {
+        // Images and links show their text; autolinks their address.
+        #[allow(clippy::expect_used)] // a constant pattern
+        let links =
+            Regex::new(r#"!?\[([^\]]*)\]\([^)\s]*(?:\s+"[^"]*")?\)|<((?:https?|mailto):[^>\s]+)>"#)
+                .expect("valid link pattern");
+        Self { links }
+    }
+
+    fn spans(&self, text: &str, lost: &mut usize) -> Vec<Span> {
+        let text = self.links.replace_all(text, "$1$2");
+        let chars: Vec<char> = text.chars().collect();
+        let mut spans: Vec<Span> = Vec::new();
+        let mut current = String::new();
+        let (mut bold, mut italic): (Option<char>, Option<char>) = (None, None);
+        let mut push = |spans: &mut Vec<Span>, current: &mut String, face: Face| {
+            if current.is_empty() {
+                return;
+            }
+            let text = encode(&std::mem::take(current), lost);
+            match spans.last_mut() {
+                Some(previous) if previous.face == face => previous.text.extend(text),
+                _ => spans.push(Span { face, text }),
+            }
+        };
+        // A closing run of `marks` after `from`, not after a space.
+        let closes = |from: usize, marks: &[char]| {
+            (from + 1..chars.len())
+                .any(|j| chars[j..].starts_with(marks) && !chars[j - 1].is_whitespace())
+        };
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let face = Face::styled(bold.is_some(), italic.is_some());
+            let next = chars.get(i + 1).copied();
+            if c == '\\' && next.is_some_and(|n| n.is_ascii_punctuation()) {
+                current.extend(next);
+                i += 2;
+                continue;
+            }
+            if c == '`' {
+                if let Some(end) = (i + 1..chars.len()).find(|&j| chars[j] == '`') {
+                    push(&mut spans, &mut current, face);
+                    current.extend(&chars[i + 1..end]);
+                    push(&mut spans, &mut current, Face::Mono);
+                    i = end + 1;
+                    continue;
+                }
+            }
+            if c == '*' || c == '_' {
+                let before = i.checked_sub(1).map(|j| chars[j]);
+                // Underscores inside words are not emphasis.
+                let in_word = c == '_'
+                    && before.is_some_and(char::is_alphanumeric)
+                    && chars
+                        .get(i + 1 + usize::from(next == Some(c)))
+                        .is_some_and(|n| n.is_alphanumeric());
+                if !in_word {
+                    let double = next == Some(c);
+                    let (state, width) = if double {
+                        (&mut bold, 2)
+                    } else {
+                        (&mut italic, 1)
+                    };
+                    let marks = vec![c; width];
+                    // Emphasis opens before a word and closes after one.
+                    let opens = chars.get(i + width).is_some_and(|n| !n.is_whitespace());
+                    let ends = before.is_some_and(|b| !b.is_whitespace());
+                    if (*state == Some(c) && ends)
+                        || (state.is_none() && opens && closes(i + width, &marks))
+                    {
+                        push(&mut spans, &mut current, face);
+                        *state = if state.is_some() { None } else { Some(c) };
+                        i += width;
+                        continue;
+                    }
+                }
+            }
+            current.push(c);
+            i += 1;
+        }
+        let face = Face::styled(bold.is_some(), italic.is_some());
+        push(&mut spans, &mut current, face);
+        spans
+    }
+}
+
+/// A line of text, ready to be set on a page.
+type Line = Vec<Span>;
+
+/// `block` broken into lines no wider than `width` points.
+fn break_lines(block: &TextBlock, width: f32) -> Vec<Line> {
+    if block.preformatted {
+        return block
+            .spans
+            .iter()
+            .flat_map(|span| break_preformatted(span, block.size, width))
+            .collect();
+    }
+    // Words, each made of spans, with the face of the space after them.
+    let mut words: Vec<Vec<Span>> = vec![Vec::new()];
+    for span in &block.spans {
+        for &byte in &span.text {
+            let word = words.last().map_or(0, Vec::len);
+            if byte == b' ' {
+                if word > 0 {
+                    words.push(Vec::new());
+                }
+                continue;
+            }
+            match words.last_mut().and_then(|word| word.last_mut()) {
+                Some(last) if last.face == span.face => last.text.push(byte),
+                _ => {
+                    if let Some(word) = words.last_mut() {
+                        word.push(Span {
+                            face: span.face,
+                            text: vec![byte],
+                        });
+                    }
+                }
+            }
+        }
+    }
+    let measure = |spans: &[Span]| -> f32 {
+        spans
+            .iter()
+            .map(|s| s.face.measure(&s.text, block.size))
+            .sum()
+    };
+    let mut lines: Vec<Line> = Vec::new();
+    let (mut line, mut used): (Line, f32) = (Vec::new(), 0.0);
+    for word in words.into_iter().filter(|word| !word.is_empty()) {
+        let space = line
+            .last()
+            .map_or(0.0, |last| last.face.measure(b" ", block.size));
+        let size = measure(&word);
+        if !line.is_empty() && used + space + size > width {
+            lines.push(std::mem::take(&mut line));
+            used = 0.0;
+        }
+        if let Some(last) = line.last_mut() {
+            last.text.push(b' ');
+            used += space;
+        }
+        if size <= width {
+            for piece in word {
+                match line.last_mut() {
+                    Some(last) if last.face == piece.face => last.text.extend(piece.text),
+                    _ => line.push(piece),
+                }
+            }
+            used += size;
+            continue;
+        }
+        // A word wider than the line is broken where it has to be.
+        for piece in word {
+            for &byte in &piece.text {
+                let advance = piece.face.measure(&[byte], block.size);
+                if used + advance > width && !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                    used = 0.0;
+                }
+                match line.last_mut() {
+                    Some(last) if last.face == piece.face => last.text.push(byte),
+                    _ => line.push(Span {
+                        face: piece.face,
+                        text: vec![byte],
+                    }),
+                }
+                used += advance;
+            }
+        }
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// A line of monospaced text broken into lines no wider than `width`
+/// points, at the last space that fits where there is one.
+fn break_preformatted(span: &Span, size: f32, width: f32) -> Vec<Line> {
+    let fits = (width / span.face.measure(b" ", size)).floor();
+    // At least one character per line; lines are far shorter than usize::MAX.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let fits = (fits as usize).max(1);
+    let mut lines = Vec::new();
+    let mut rest: &[u8] = &span.text;
+    while rest.len() > fits {
+        let indent = rest.iter().take_while(|&&b| b == b' ').count();
+        let (line, next) = match rest[..=fits].iter().rposition(|&b| b == b' ') {
+            Some(at) if at > indent => (&rest[..at], &rest[at + 1..]),
+            _ => (&rest[..fits], &rest[fits..]),
+        };
+        lines.push(vec![Span {
+            face: span.face,
+            text: line.to_vec(),
+        }]);
+        rest = next;
+    }
+    lines.push(vec![Span {
+        face: span.face,
+        text: rest.to_vec(),
+    }]);
+    lines
+}
+
+/// Content stream operations of each page of `blocks`.
+fn paginate(blocks: &[Block], options: &TextToPdfOptions) -> Vec<Vec<Operation>> {
+    let paper = options.paper;
+    let (top, bottom) = (paper.height - options.margin, options.margin);
+    let width = paper.width - 2.0 * options.margin;
+    let mut pages: Vec<Vec<Operation>> = vec![Vec::new()];
+    let mut y = top;
+    let new_page = |pages: &mut Vec<Vec<Operation>>, y: &mut f32| {
+        pages.push(Vec::new());
+        *y = top;
+    };
+    for (i, block) in blocks.iter().enumerate() {
+        let block = match block {
+            Block::PageBreak => {
+                new_page(&mut pages, &mut y);
+                continue;
+            }
+            Block::Rule => {
+                let gap = options.font_size;
+                if y - 2.0 * gap < bottom {
+                    new_page(&mut pages, &mut y);
+                }
+                let rule_y = if y < top { y - gap } else { y - gap / 2.0 };
+                if let Some(page) = pages.last_mut() {
+                    page.extend(rule(options.margin, options.margin + width, rule_y));
+                }
+                y = rule_y - gap / 2.0;
+                continue;
+            }
+            Block::Text(block) => block,
+        };
+        let x = options.margin + block.indent;
+        let lines = break_lines(block, width - block.indent);
+        let leading = block.size * LEADING;
+        let space = if y < top {
+            block.space_before.max(0.0)
+        } else {
+            0.0
+        };
+        let mut needed =
+            space + leading * f32::from(u16::try_from(lines.len()).unwrap_or(u16::MAX));
+        if block.keep_with_next {
+            if let Some(Block::Text(next)) = blocks.get(i + 1) {
+                needed += next.space_before.max(0.0) + next.size * LEADING;
+            }
+        }
+        if y - needed < bottom && y < top {
+            new_page(&mut pages, &mut y);
+        } else {
+            y -= space;
+        }
+        for (n, line) in lines.iter().enumerate() {
+            if y - leading < bottom && y < top {
+                new_page(&mut pages, &mut y);
+            }
+            let baseline = y - block.size;
+            let Some(page) = pages.last_mut() else {
+                continue;
+            };
+            if let (0, Some(marker)) = (n, &block.marker) {
+                let marker_x = x - Face::Regular.measure(marker, block.size) - 0.4 * block.size;
+                page.extend(show(
+                    &[Span {
+                        face: Face::Regular,
+                        text: marker.clone(),
+                    }],
+                    block.size,
+                    marker_x,
+                    baseline,
+                ));
+            }
+            page.extend(show(line, block.size, x, baseline));
+            y -= leading;
+        }
+    }
+    pages
+}
+
+/// Operations setting `line` with its baseline starting at (`x`, `y`).
+fn show(line: &[Span], size: f32, x: f32, y: f32) -> Vec<Operation> {
+    if line.iter().all(|span| span.text.is_empty()) {
+        return Vec::new();
+    }
+    let mut ops = vec![
+        Operation::new("BT", vec![]),
+        Operation::new("Td", vec![x.into(), y.into()]),
+    ];
+    for span in line {
+        ops.push(Operation::new(
+            "Tf",
+            vec![
+                Object::Name(span.face.resource().as_bytes().to_vec()),
+                size.into(),
+            ],
+        ));
+        ops.push(Operation::new(
+            "Tj",
+            vec![Object::String(span.text.clone(), StringFormat::Literal)],
+        ));
+    }
+    ops.push(Operation::new("ET", vec![]));
+    ops
+}
+
+/// Operations drawing a thin gray rule from `x1` to `x2` at height `y`.
+fn rule(x1: f32, x2: f32, y: f32) -> Vec<Operation> {
+    vec![
+        Operation::new("q", vec![]),
+        Operation::new("G", vec![0.6_f32.into()]),
+        Operation::new("w", vec![0.5_f32.into()]),
+        Operation::new("m", vec![x1.into(), y.into()]),
+        Operation::new("l", vec![x2.into(), y.into()]),
+        Operation::new("S", vec![]),
+        Operation::new("Q", vec![]),
+    ]
+}
+
+/// The font dictionary of `face`, with `WinAnsiEncoding` and its widths.
+fn font_dictionary(face: Face) -> Dictionary {
+    let widths: Vec<Object> = (32..=255u8)
+        .map(|code| Object::Integer(i64::from(face.width(code))))
+        .collect();
+    Dictionary::from_iter([
+        ("Type", Object::Name(b"Font".to_vec())),
+        ("Subtype", Object::Name(b"Type1".to_vec())),
+        (
+            "BaseFont",
+            Object::Name(face.base_font().as_bytes().to_vec()),
+        ),
+        ("Encoding", Object::Name(b"WinAnsiEncoding".to_vec())),
+        ("FirstChar", Object::Integer(32)),
+        ("LastChar", Object::Integer(255)),
+        ("Widths", Object::Array(widths)),
+    ])
+}
+
+/// The Helvetica font dictionary, for text stamped on existing pages.
+pub(crate) fn helvetica() -> Dictionary {
+    font_dictionary(Face::Regular)
+}
+
+/// Width of `text` (in `WinAnsiEncoding`) in Helvetica at `size` points.
+pub(crate) fn helvetica_width(text: &[u8], size: f32) -> f32 {
+    Face::Regular.measure(text, size)
+}
+
+/// A document with a page per entry of `pages`.
+fn build_document(pages: Vec<Vec<Operation>>, options: &TextToPdfOptions) -> Result<Document> {
+    let mut doc = Document::with_version("1.4");
+    let tree = doc.new_object_id();
+    let mut fonts = Dictionary::new();
+    for face in Face::ALL {
+        let font = doc.add_object(font_dictionary(face));
+        fonts.set(face.resource(), font);
+    }
+    let resources = doc.add_object(Dictionary::from_iter([("Font", Object::Dictionary(fonts))]));
+    let mut kids = Vec::with_capacity(pages.len());
+    for operations in pages {
+        let content = Content { operations }
+            .encode()
+            .map_err(|e| PdfError::InvalidArgument(format!("encoding content: {e}")))?;
+        let content = doc.add_object(lopdf::Stream::new(Dictionary::new(), content));
+        kids.push(Object::Reference(doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Page".to_vec())),
+            ("Parent", Object::Reference(tree)),
+            ("Resources", Object::Reference(resources)),
+            ("Contents", Object::Reference(content)),
+        ]))));
+    }
+    let count = i64::try_from(kids.len()).unwrap_or(i64::MAX);
+    doc.objects.insert(
+        tree,
+        Object::Dictionary(Dictionary::from_iter([
+            ("Type", Object::Name(b"Pages".to_vec())),
+            ("Kids", Object::Array(kids)),
+            ("Count", Object::Integer(count)),
+            (
+                "MediaBox",
+                Rect::from_size(options.paper.width, options.paper.height).to_object(),
+            ),
+        ])),
+    );
+    let catalog = doc.add_object(Dictionary::from_iter([
+        ("Type", Object::Name(b"Catalog".to_vec())),
+        ("Pages", Object::Reference(tree)),
+    ]));
+    doc.trailer.set("Root", catalog);
+    doc.compress();
+    Ok(doc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text;
+
+    fn texts(blocks: &[Block]) -> Vec<String> {
+        blocks
+            .iter()
+            .map(|block| match block {
+                Block::Text(block) => block
+                    .spans
+                    .iter()
+                    .map(|span| {
+                        let text: String = span.text.iter().map(|&b| char::from(b)).collect();
+                        match span.face {
+                            Face::Bold => format!("<b>{text}</b>"),
+                            Face::Italic => format!("<i>{text}</i>"),
+                            Face::BoldItalic => format!("<bi>{text}</bi>"),
+                            Face::Mono => format!("<tt>{text}</tt>"),
+                            Face::Regular => text,
+                        }
+                    })
+                    .collect(),
+                Block::Rule => "---".to_string(),
+                Block::PageBreak => "\\f".to_string(),
+            })
+            .collect()
+    }
+
+    /// The text of each page of `pages`, as the native extractor reads it.
+    fn page_texts(pages: Vec<Vec<Operation>>, options: &TextToPdfOptions) -> Result<Vec<String>> {
+        let doc = build_document(pages, options)?;
+        let mut out = Vec::new();
+        for page_id in doc.get_pages().into_values() {
+            let content = doc
+                .get_and_decode_page_content(page_id)
+                .map_err(|e| PdfError::InvalidArgument(e.to_string()))?;
+            let (page, _) = text::layout(&doc, page_id, &content.operations).text_with_origins();
+            out.push(page);
+        }
+        Ok(out)
+    }
+
+    #[test]
+    fn markdown_blocks_and_emphasis_are_read() {
+        let source = "# Notes *today*\n\nSome **bold** and _italic_ text\nwith `code` and a [link](https://example.com).\n\n\
+                      - first\n  - nested snake_case item\n2. numbered\n\n> quoted\n\n***\n\n```\nlet x = 1;\n```\n\
+                      Setext\n---\nLiteral \\*stars\\* and 2 * 3 * 4 and ünïcödé ☃\n";
+        let mut lost = 0;
+        let blocks = markdown_blocks(source, 10.0, &mut lost);
+        assert_eq!(
+            texts(&blocks),
+            [
+                "<b>Notes </b><bi>today</bi>",
+                "Some <b>bold</b> and <i>italic</i> text with <tt>code</tt> and a link.",
+                "first",
+                "nested snake_case item",
+                "numbered",
+                "<i>quoted</i>",
+                "---",
+                "<tt>let x = 1;</tt>",
+                "<b>Setext</b>",
+                "Literal *stars* and 2 * 3 * 4 and \u{fc}n\u{ef}c\u{f6}d\u{e9} ?",
+            ]
+        );
+        assert_eq!(lost, 1);
+        let markers: Vec<Option<&[u8]>> = blocks
+            .iter()
+            .filter_map(|block| match block {
+                Block::Text(b) => Some(b.marker.as_deref()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            markers[2..5],
+            [Some(&b"\x95"[..]), Some(b"\x95"), Some(b"2.")]
+        );
+    }
+
+    #[test]
+    fn plain_text_is_wrapped_and_paged() -> Result<()> {
+        let options = TextToPdfOptions {
+            paper: PaperSize::A5,
+            ..TextToPdfOptions::default()
+        };
+        let long = "word ".repeat(30);
+        let mut source = format!("Title\n\tindented\n{long}\n\x0cpage two\n");
+        source.extend((0..60).map(|n| format!("line {n}\n")));
+        source.push('\x0c');
+        let mut lost = 0;
+        let blocks = plain_blocks(&source, options.font_size, &mut lost);
+        assert_eq!(blocks.iter().filter(|b| **b == Block::PageBreak).count(), 1);
+
+        let pages = page_texts(paginate(&blocks, &options), &options)?;
+        // 61 lines after the form feed do not fit on one A5 page.
+        assert_eq!(pages.len(), 3);
+        let first: Vec<&str> = pages[0].lines().collect();
+        assert_eq!(first[0].trim(), "Title");
+        assert!(matches!(&blocks[1], Block::Text(b) if b.spans[0].text == b"        indented"));
+        // Courier at 11 pt: 41 characters fit in 276 points.
+        assert!(first[2].len() <= 41 && first.len() == 6, "{first:?}");
+        assert!(pages[1].starts_with("page two\nline 0\n"));
+        assert!(pages[2].trim_end().ends_with("line 59"));
+        Ok(())
+    }
+
+    #[test]
+    fn markdown_lines_fill_the_width() -> Result<()> {
+        let options = TextToPdfOptions {
+            format: TextFormat::Markdown,
+            ..TextToPdfOptions::default()
+        };
+        let words = "The quick brown fox jumps over the lazy dog. ".repeat(12);
+        let source = format!("## Heading\n\n{words}\n\n- item with {}\n", "x".repeat(120));
+        let mut lost = 0;
+        let blocks = markdown_blocks(&source, options.font_size, &mut lost);
+        let width = options.paper.width - 2.0 * options.margin;
+        let Some(Block::Text(paragraph)) = blocks.get(1) else {
+            return Err(PdfError::InvalidArgument("no paragraph".to_string()));
+        };
+        let lines = break_lines(paragraph, width);
+        for line in &lines {
+            let used: f32 = line
+                .iter()
+                .map(|s| s.face.measure(&s.text, paragraph.size))
+                .sum();
+            assert!(used <= width + 0.01, "{used} > {width}");
+        }
+        assert!(lines.len() > 4);
+        let joined: Vec<String> = lines
+            .iter()
+            .map(|line| {
+                line.iter()
+                    .map(|s| String::from_utf8_lossy(&s.text))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(joined.join(" "), words.trim_end());
+
+        let pages = page_texts(paginate(&blocks, &options), &options)?;
+        assert_eq!(pages.len(), 1);
+        assert!(pages[0].starts_with("Heading\n"), "{}", pages[0]);
+        assert!(pages[0].contains("\n• item with\nxxx"), "{:?}", pages[0]);
+        Ok(())
+    }
+
+    #[test]
+    fn headings_are_kept_with_the_next_line() -> Result<()> {
+        let options = TextToPdfOptions {
+            format: TextFormat::Markdown,
+            ..TextToPdfOptions::default()
+        };
+        let mut source = String::new();
+        // The heading would fit below 32 paragraphs, but the line after it
+        // would not.
+        source.extend((0..32).map(|n| format!("line {n}\n\n")));
+        source.push_str("# Last\n\nbody\n");
+        let mut lost = 0;
+        let blocks = markdown_blocks(&source, options.font_size, &mut lost);
+        let pages = page_texts(paginate(&blocks, &options), &options)?;
+        assert_eq!(pages.len(), 2);
+        assert!(pages[0].trim_end().ends_with("line 31"), "{}", pages[0]);
+        assert!(pages[1].starts_with("Last\n"), "{}", pages[1]);
+        Ok(())
+    }
+
+    #[test]
+    fn formats_and_options_are_checked() {
+        assert_eq!(
+            TextFormat::for_path(Path::new("a/README.MD")),
+            TextFormat::Markdown
+        );
+        assert_eq!(
+            TextFormat::for_path(Path::new("notes.txt")),
+            TextFormat::Plain
+        );
+        assert_eq!(read_text(b"\xef\xbb\xbfA\xe9"), "A\u{e9}");
+        assert!(validate_options(&TextToPdfOptions::default()).is_ok());
+        let tiny = TextToPdfOptions {
+            font_size: 2.0,
+            ..TextToPdfOptions::default()
+        };
+        let wide = TextToPdfOptions {
+            margin: 280.0,
+            ..TextToPdfOptions::default()
+        };
+        assert!(validate_options(&tiny).is_err() && validate_options(&wide).is_err());
+    }
+}
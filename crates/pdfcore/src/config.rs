@@ -0,0 +1,343 @@
+//! Layered configuration: a TOML file, overridden by `PDFCLI_*` environment
+//! variables, overridden in turn by command-line flags (applied by the caller).
+//!
+//! ```toml
+//! [defaults]
+//! compress_preset = "ebook"
+//! output_dir = "out"
+//! jobs = 4
+//! engines = ["qpdf", "native"]
+//!
+//! [tools]
+//! qpdf = "/opt/qpdf/bin/qpdf"
+//! ghostscript = "/usr/local/bin/gs"
+//! ```
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{set_preferred_engines, set_tool_path, CompressPreset, Engine, PdfError, Result};
+
+/// Settings keys accepted by [`Config::set`], besides `tools.<tool>`.
+pub const CONFIG_KEYS: [&str; 4] = [
+    "defaults.compress_preset",
+    "defaults.output_dir",
+    "defaults.jobs",
+    "defaults.engines",
+];
+
+/// `pdfcli` configuration.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Defaults for command options.
+    pub defaults: Defaults,
+    /// External tool locations.
+    pub tools: ToolPaths,
+}
+
+/// `[defaults]` section of a [`Config`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Defaults {
+    /// Preset for `compress` when none is given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compress_preset: Option<CompressPreset>,
+    /// Directory for commands that write several files (`split-pages`,
+    /// `render`) when none is given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_dir: Option<PathBuf>,
+    /// How many files batch commands process at once.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<usize>,
+    /// Engines to prefer, in order (see [`crate::set_preferred_engines`]).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub engines: Vec<Engine>,
+}
+
+/// `[tools]` section of a [`Config`]: executables to use instead of searching
+/// `PATH`. The `PDFCLI_<TOOL>` environment variables take precedence.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolPaths {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub qpdf: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pdftotext: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none", alias = "gs")]
+    pub ghostscript: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ocrmypdf: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mutool: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wkhtmltopdf: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none", alias = "chromium")]
+    pub chrome: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none", alias = "libreoffice")]
+    pub soffice: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub openssl: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub curl: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pdftocairo: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none", alias = "lpr")]
+    pub lp: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sumatrapdf: Option<PathBuf>,
+}
+
+impl ToolPaths {
+    /// Tool names with their configured paths.
+    #[must_use]
+    pub fn entries(&self) -> [(&'static str, Option<&Path>); 13] {
+        [
+            ("qpdf", self.qpdf.as_deref()),
+            ("pdftotext", self.pdftotext.as_deref()),
+            ("ghostscript", self.ghostscript.as_deref()),
+            ("ocrmypdf", self.ocrmypdf.as_deref()),
+            ("mutool", self.mutool.as_deref()),
+            ("wkhtmltopdf", self.wkhtmltopdf.as_deref()),
+            ("chrome", self.chrome.as_deref()),
+            ("soffice", self.soffice.as_deref()),
+            ("openssl", self.openssl.as_deref()),
+            ("curl", self.curl.as_deref()),
+            ("pdftocairo", self.pdftocairo.as_deref()),
+            ("lp", self.lp.as_deref()),
+            ("sumatrapdf", self.sumatrapdf.as_deref()),
+        ]
+    }
+
+    fn slot(&mut self, name: &str) -> Option<&mut Option<PathBuf>> {
+        match name {
+            "qpdf" => Some(&mut self.qpdf),
+            "pdftotext" => Some(&mut self.pdftotext),
+            "ghostscript" | "gs" => Some(&mut self.ghostscript),
+            "ocrmypdf" => Some(&mut self.ocrmypdf),
+            "mutool" => Some(&mut self.mutool),
+            "wkhtmltopdf" => Some(&mut self.wkhtmltopdf),
+            "chrome" | "chromium" => Some(&mut self.chrome),
+            "soffice" | "libreoffice" => Some(&mut self.soffice),
+            "openssl" => Some(&mut self.openssl),
+            "curl" => Some(&mut self.curl),
+            "pdftocairo" => Some(&mut self.pdftocairo),
+            "lp" | "lpr" => Some(&mut self.lp),
+            "sumatrapdf" => Some(&mut self.sumatrapdf),
+            _ => None,
+        }
+    }
+}
+
+/// Environment variables overriding tool paths, by tool name.
+const TOOL_ENV: [(&str, &str); 13] = [
+    ("qpdf", "PDFCLI_QPDF"),
+    ("pdftotext", "PDFCLI_PDFTOTEXT"),
+    ("ghostscript", "PDFCLI_GS"),
+    ("ocrmypdf", "PDFCLI_OCRMYPDF"),
+    ("mutool", "PDFCLI_MUTOOL"),
+    ("wkhtmltopdf", "PDFCLI_WKHTMLTOPDF"),
+    ("chrome", "PDFCLI_CHROME"),
+    ("soffice", "PDFCLI_SOFFICE"),
+    ("openssl", "PDFCLI_OPENSSL"),
+    ("curl", "PDFCLI_CURL"),
+    ("pdftocairo", "PDFCLI_PDFTOCAIRO"),
+    ("lp", "PDFCLI_LP"),
+    ("sumatrapdf", "PDFCLI_SUMATRAPDF"),
+];
+
+impl Config {
+    /// Parse a configuration from TOML text.
+    pub fn from_toml_str(text: &str) -> Result<Self> {
+        let config: Self = toml::from_str(text)
+            .map_err(|e| PdfError::InvalidArgument(format!("invalid config: {e}")))?;
+        if config.defaults.jobs == Some(0) {
+            return Err(PdfError::InvalidArgument(
+                "invalid config: defaults.jobs must be at least 1".to_string(),
+            ));
+        }
+        Ok(config)
+    }
+
+    /// Read a configuration file; a missing file is an empty configuration.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(text) => Self::from_toml_str(&text).map_err(|e| match e {
+                PdfError::InvalidArgument(msg) => {
+                    PdfError::InvalidArgument(format!("{}: {msg}", path.display()))
+                }
+                other => other,
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// The configuration file: `PDFCLI_CONFIG` if set, else `pdfcli/config.toml`
+    /// in the user's configuration directory (`$XDG_CONFIG_HOME`, `~/.config`,
+    /// or `%APPDATA%` on Windows).
+    #[must_use]
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os("PDFCLI_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+        let dir = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|d| !d.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| {
+                if cfg!(windows) {
+                    std::env::var_os("APPDATA").map(PathBuf::from)
+                } else {
+                    std::env::var_os("HOME").map(|home| Path::new(&home).join(".config"))
+                }
+            })?;
+        Some(dir.join("pdfcli").join("config.toml"))
+    }
+
+    /// The configuration file at `path` (or [`Config::default_path`]) with
+    /// environment overrides applied.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let mut config = match path.map(Path::to_path_buf).or_else(Self::default_path) {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    /// Override settings from `PDFCLI_COMPRESS_PRESET`, `PDFCLI_OUTPUT_DIR`,
+    /// `PDFCLI_JOBS`, `PDFCLI_ENGINE`, and the tool variables (`PDFCLI_QPDF`,
+    /// `PDFCLI_GS`, ...).
+    pub fn apply_env(&mut self) -> Result<()> {
+        let vars = [
+            ("PDFCLI_COMPRESS_PRESET", "defaults.compress_preset"),
+            ("PDFCLI_OUTPUT_DIR", "defaults.output_dir"),
+            ("PDFCLI_JOBS", "defaults.jobs"),
+            ("PDFCLI_ENGINE", "defaults.engines"),
+        ];
+        for (var, key) in vars {
+            if let Some(value) = std::env::var_os(var) {
+                self.set(key, &value.to_string_lossy())
+                    .map_err(|e| PdfError::InvalidArgument(format!("{var}: {e}")))?;
+            }
+        }
+        for (tool, var) in TOOL_ENV {
+            if let Some(value) = std::env::var_os(var) {
+                if let Some(slot) = self.tools.slot(tool) {
+                    *slot = Some(PathBuf::from(value));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Set one setting by key (see [`CONFIG_KEYS`]; tools are `tools.<tool>`).
+    /// An empty value clears the setting.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let value = value.trim();
+        let clear = value.is_empty();
+        let defaults = &mut self.defaults;
+        match key {
+            "defaults.compress_preset" => {
+                defaults.compress_preset = if clear { None } else { Some(value.parse()?) };
+            }
+            "defaults.output_dir" => {
+                defaults.output_dir = (!clear).then(|| PathBuf::from(value));
+            }
+            "defaults.jobs" => {
+                defaults.jobs = if clear {
+                    None
+                } else {
+                    match value.parse::<usize>() {
+                        Ok(jobs) if jobs > 0 => Some(jobs),
+                        _ => {
+                            return Err(PdfError::InvalidArgument(format!(
+                                "jobs must be a positive integer, got {value}"
+                            )))
+                        }
+                    }
+                };
+            }
+            "defaults.engines" => {
+                defaults.engines = value
+                    .split(',')
+                    .filter(|name| !name.trim().is_empty())
+                    .map(str::parse)
+                    .collect::<Result<_>>()?;
+            }
+            _ => {
+                let slot = key
+                    .strip_prefix("tools.")
+                    .and_then(|tool| self.tools.slot(tool))
+                    .ok_or_else(|| {
+                        PdfError::InvalidArgument(format!(
+                            "unknown config key: {key} (expected one of: {}, tools.<tool>)",
+                            CONFIG_KEYS.join(", ")
+                        ))
+                    })?;
+                *slot = (!clear).then(|| PathBuf::from(value));
+            }
+        }
+        Ok(())
+    }
+
+    /// The configuration as TOML.
+    #[must_use]
+    pub fn to_toml_string(&self) -> String {
+        toml::to_string(self).unwrap_or_default()
+    }
+
+    /// Write the configuration to `path`, creating its directory.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.to_toml_string())?;
+        Ok(())
+    }
+
+    /// Make the engine preferences and tool paths take effect for this
+    /// process.
+    pub fn apply(&self) -> Result<()> {
+        set_preferred_engines(&self.defaults.engines);
+        for (tool, path) in self.tools.entries() {
+            set_tool_path(tool, path.map(Path::to_path_buf))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_round_trips_through_toml() -> Result<()> {
+        let mut config = Config::from_toml_str("[tools]\ngs = \"/opt/gs\"\n")?;
+        config.set("defaults.compress_preset", "ebook")?;
+        config.set("defaults.jobs", "4")?;
+        config.set("defaults.engines", "qpdf, native")?;
+        config.set("tools.qpdf", "/opt/qpdf")?;
+        assert!(config.set("defaults.jobs", "0").is_err());
+        assert!(config.set("defaults.colour", "red").is_err());
+        assert!(config.set("tools.acrobat", "/opt/acrobat").is_err());
+
+        let text = config.to_toml_string();
+        assert!(text.contains("engines = [\"qpdf\", \"native\"]"), "{text}");
+        assert_eq!(Config::from_toml_str(&text)?, config);
+        assert_eq!(
+            config.tools.ghostscript.as_deref(),
+            Some(Path::new("/opt/gs"))
+        );
+
+        config.set("tools.qpdf", "")?;
+        assert_eq!(config.tools.qpdf, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,239 @@
+//! Deduplicating resources: fonts and images that are stored more than once
+//! with identical contents, as happens when the output of one generator is
+//! merged many times over.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+
+use sha2::{Digest, Sha256};
+
+use crate::size;
+
+/// Store identical fonts and images in `doc` once, pointing every reference
+/// at the copy kept. Returns the number of objects removed.
+///
+/// Streams are compared by their decoded data where they can be decoded, so
+/// copies compressed differently still match. A font whose program was
+/// deduplicated can match another in the next round, so rounds run until
+/// nothing changes.
+pub(crate) fn dedupe_objects(doc: &mut Document) -> usize {
+    let mut removed = 0;
+    loop {
+        let mut kept: HashMap<String, ObjectId> = HashMap::new();
+        let mut duplicates = BTreeMap::new();
+        for id in candidates(doc) {
+            let Some(obj) = doc.objects.get(&id) else {
+                continue;
+            };
+            let key = object_key(obj);
+            match kept.get(&key) {
+                Some(&original) => {
+                    duplicates.insert(id, original);
+                }
+                None => {
+                    kept.insert(key, id);
+                }
+            }
+        }
+        if duplicates.is_empty() {
+            return removed;
+        }
+        for obj in doc.objects.values_mut() {
+            rewrite_references(obj, &duplicates);
+        }
+        for (_, value) in &mut doc.trailer {
+            rewrite_references(value, &duplicates);
+        }
+        for id in duplicates.keys() {
+            doc.objects.remove(id);
+        }
+        removed += duplicates.len();
+        tracing::debug!(objects = duplicates.len(), "deduplicated objects");
+    }
+}
+
+/// The objects that may be deduplicated, in object number order: images,
+/// font and font descriptor dictionaries, and the objects fonts own (font
+/// programs, `ToUnicode` maps, widths).
+fn candidates(doc: &Document) -> BTreeSet<ObjectId> {
+    let mut ids = size::font_owned_objects(doc);
+    for (&id, obj) in &doc.objects {
+        let dict = match obj {
+            Object::Dictionary(dict) => dict,
+            Object::Stream(stream) => &stream.dict,
+            _ => continue,
+        };
+        let is_image = dict
+            .get(b"Subtype")
+            .and_then(Object::as_name)
+            .is_ok_and(|subtype| subtype == b"Image");
+        if is_image || dict.type_is(b"Font") || dict.type_is(b"FontDescriptor") {
+            ids.insert(id);
+        }
+    }
+    ids
+}
+
+/// A digest of `obj` that is equal for objects with the same contents.
+fn object_key(obj: &Object) -> String {
+    let mut hasher = Sha256::new();
+    match obj {
+        Object::Stream(stream) => {
+            let mut dict = stream.dict.clone();
+            dict.remove(b"Length");
+            let decoded = if dict.has(b"Filter") {
+                stream.decompressed_content().ok()
+            } else {
+                Some(stream.content.clone())
+            };
+            if let Some(data) = decoded {
+                dict.remove(b"Filter");
+                dict.remove(b"DecodeParms");
+                hash_dict(&mut hasher, &dict);
+                hash_bytes(&mut hasher, b"s", &data);
+            } else {
+                hash_dict(&mut hasher, &dict);
+                hash_bytes(&mut hasher, b"raw", &stream.content);
+            }
+        }
+        other => hash_object(&mut hasher, other),
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Feed an unambiguous encoding of `obj` to `hasher`, with dictionary keys
+/// in sorted order.
+fn hash_object(hasher: &mut Sha256, obj: &Object) {
+    match obj {
+        Object::Null => hasher.update(b"n"),
+        Object::Boolean(b) => hasher.update(if *b { b"t" } else { b"f" }),
+        Object::Integer(i) => {
+            hasher.update(b"i");
+            hasher.update(i.to_be_bytes());
+        }
+        Object::Real(r) => {
+            hasher.update(b"r");
+            hasher.update(r.to_be_bytes());
+        }
+        Object::Name(name) => hash_bytes(hasher, b"/", name),
+        Object::String(bytes, _) => hash_bytes(hasher, b"(", bytes),
+        Object::Array(items) => {
+            hasher.update(b"[");
+            hasher.update((items.len() as u64).to_be_bytes());
+            for item in items {
+                hash_object(hasher, item);
+            }
+        }
+        Object::Dictionary(dict) => hash_dict(hasher, dict),
+        Object::Stream(stream) => {
+            hash_dict(hasher, &stream.dict);
+            hash_bytes(hasher, b"s", &stream.content);
+        }
+        Object::Reference((number, generation)) => {
+            hasher.update(b"R");
+            hasher.update(number.to_be_bytes());
+            hasher.update(generation.to_be_bytes());
+        }
+    }
+}
+
+fn hash_dict(hasher: &mut Sha256, dict: &Dictionary) {
+    let mut entries: Vec<(&Vec<u8>, &Object)> = dict.iter().collect();
+    entries.sort_by_key(|(key, _)| *key);
+    hasher.update(b"<");
+    hasher.update((entries.len() as u64).to_be_bytes());
+    for (key, value) in entries {
+        hash_bytes(hasher, b"/", key);
+        hash_object(hasher, value);
+    }
+}
+
+/// `tag`, the length of `bytes`, then `bytes`, so that neighbouring values
+/// cannot run into each other.
+pub(crate) fn hash_bytes(hasher: &mut Sha256, tag: &[u8], bytes: &[u8]) {
+    hasher.update(tag);
+    hasher.update((bytes.len() as u64).to_be_bytes());
+    hasher.update(bytes);
+}
+
+/// Point the references in `obj` to objects in `map` at their replacements.
+fn rewrite_references(obj: &mut Object, map: &BTreeMap<ObjectId, ObjectId>) {
+    match obj {
+        Object::Reference(id) => {
+            if let Some(&new_id) = map.get(id) {
+                *id = new_id;
+            }
+        }
+        Object::Array(items) => {
+            for item in items {
+                rewrite_references(item, map);
+            }
+        }
+        Object::Dictionary(dict) => {
+            for (_, value) in dict.iter_mut() {
+                rewrite_references(value, map);
+            }
+        }
+        Object::Stream(stream) => {
+            for (_, value) in &mut stream.dict {
+                rewrite_references(value, map);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::Stream;
+
+    use super::*;
+
+    #[test]
+    fn identical_fonts_are_stored_once() {
+        let mut doc = Document::with_version("1.4");
+        let font = |doc: &mut Document, compress: bool| {
+            let program = Stream::new(Dictionary::new(), b"font program".repeat(20));
+            let mut program = program.with_compression(compress);
+            if compress {
+                let _ = program.compress();
+            }
+            let program = doc.add_object(program);
+            let descriptor = doc.add_object(Dictionary::from_iter([
+                ("Type", Object::Name(b"FontDescriptor".to_vec())),
+                ("FontName", Object::Name(b"Demo".to_vec())),
+                ("FontFile2", Object::Reference(program)),
+            ]));
+            doc.add_object(Dictionary::from_iter([
+                ("Type", Object::Name(b"Font".to_vec())),
+                ("BaseFont", Object::Name(b"Demo".to_vec())),
+                ("FontDescriptor", Object::Reference(descriptor)),
+            ]))
+        };
+        let first = font(&mut doc, false);
+        let second = font(&mut doc, true);
+        let page = doc.add_object(Dictionary::from_iter([(
+            "Resources",
+            Object::Dictionary(Dictionary::from_iter([(
+                "Font",
+                Object::Dictionary(Dictionary::from_iter([
+                    ("F1", Object::Reference(first)),
+                    ("F2", Object::Reference(second)),
+                ])),
+            )])),
+        )]));
+
+        assert_eq!(dedupe_objects(&mut doc), 3);
+        assert_eq!(doc.objects.len(), 4);
+        let fonts = doc
+            .get_dictionary(page)
+            .and_then(|page| page.get_deref(b"Resources", &doc))
+            .and_then(Object::as_dict)
+            .and_then(|resources| resources.get(b"Font"))
+            .and_then(Object::as_dict)
+            .map(|fonts| (fonts.get(b"F1").cloned(), fonts.get(b"F2").cloned()));
+        assert!(matches!(fonts, Ok((Ok(a), Ok(b))) if a == b && a == Object::Reference(first)));
+        assert_eq!(dedupe_objects(&mut doc), 0);
+    }
+}
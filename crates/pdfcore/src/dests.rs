@@ -0,0 +1,268 @@
+//! Named destinations: the targets of deep links such as `file.pdf#chapter2`.
+
+use std::{collections::BTreeMap, path::Path};
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+
+use crate::{
+    a11y::catalog_mut,
+    fonts::resolve_dict,
+    links::{dest_page, named_destinations, walk_name_tree},
+    load_document, load_for_reading, metadata,
+    plan::Plan,
+    save_document, PdfError, Result,
+};
+
+/// A named destination, as returned by [`destinations`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Destination {
+    /// The name, as used after `#` in a link.
+    pub name: String,
+    /// 1-based target page, or `None` if it could not be resolved.
+    pub page: Option<u32>,
+    /// How the page is shown (`XYZ`, `Fit`, `FitH`, ...), if given.
+    pub view: Option<String>,
+}
+
+/// List the named destinations, from both the catalog `/Dests` dictionary
+/// and the `/Names /Dests` name tree, sorted by name (pure Rust).
+pub fn destinations(path: impl AsRef<Path>) -> Result<Vec<Destination>> {
+    let doc = load_for_reading(path.as_ref())?;
+    let page_numbers: BTreeMap<ObjectId, u32> = doc
+        .get_pages()
+        .into_iter()
+        .map(|(no, id)| (id, no))
+        .collect();
+    Ok(named_destinations(&doc)
+        .into_iter()
+        .map(|(name, dest)| Destination {
+            name: metadata::decode_text_string(&name),
+            page: dest_page(&doc, dest, &page_numbers),
+            view: dest_view(&doc, dest),
+        })
+        .collect())
+}
+
+/// Add a named destination showing the whole of `page` (1-based), stored in
+/// the `/Names /Dests` name tree. An existing destination with that name is
+/// an error unless `replace` is set.
+pub fn add_destination(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    name: &str,
+    page: u32,
+    replace: bool,
+) -> Result<()> {
+    let mut doc = load_document(input.as_ref())?;
+    let page_id = check_destination(&doc, name, page, replace)?;
+    let mut entries: BTreeMap<Vec<u8>, Object> = name_tree(&doc)
+        .into_iter()
+        .map(|(key, value)| (key, value.clone()))
+        .collect();
+    entries.insert(
+        name.as_bytes().to_vec(),
+        Object::Array(vec![
+            Object::Reference(page_id),
+            Object::Name(b"Fit".to_vec()),
+        ]),
+    );
+    // The name tree is rewritten as one sorted leaf, which is valid at any
+    // size; a name in the old catalog `/Dests` dictionary would shadow it.
+    let names: Vec<Object> = entries
+        .into_iter()
+        .flat_map(|(key, value)| [Object::string_literal(key), value])
+        .collect();
+    let tree = doc.add_object(Dictionary::from_iter([("Names", Object::Array(names))]));
+    remove_old_style(&mut doc, name.as_bytes());
+    set_catalog_dests_tree(&mut doc, tree)?;
+    save_document(&mut doc, output.as_ref())
+}
+
+/// What [`add_destination`] would do.
+pub fn add_destination_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    name: &str,
+    page: u32,
+    replace: bool,
+) -> Result<Plan> {
+    let doc = load_document(input.as_ref())?;
+    check_destination(&doc, name, page, replace)?;
+    Ok(Plan::new()
+        .native(format!("add named destination {name:?} -> page {page}"))
+        .write(output.as_ref()))
+}
+
+/// The target page's object, after checking that `name` can be added.
+fn check_destination(doc: &Document, name: &str, page: u32, replace: bool) -> Result<ObjectId> {
+    if name.is_empty() {
+        return Err(PdfError::InvalidArgument(
+            "destination name must not be empty".to_string(),
+        ));
+    }
+    if !replace && named_destinations(doc).contains_key(name.as_bytes()) {
+        return Err(PdfError::InvalidArgument(format!(
+            "destination {name:?} already exists (use --replace to change it)"
+        )));
+    }
+    let pages = doc.get_pages();
+    pages.get(&page).copied().ok_or_else(|| {
+        PdfError::InvalidArgument(format!(
+            "page {page} is out of range (document has {} pages)",
+            pages.len()
+        ))
+    })
+}
+
+/// Entries of the `/Names /Dests` name tree.
+fn name_tree(doc: &Document) -> BTreeMap<Vec<u8>, &Object> {
+    let mut out = BTreeMap::new();
+    if let Some(tree) = doc
+        .catalog()
+        .ok()
+        .and_then(|catalog| resolve_dict(doc, catalog.get(b"Names").ok()))
+        .and_then(|names| resolve_dict(doc, names.get(b"Dests").ok()))
+    {
+        walk_name_tree(doc, tree, &mut out, 0);
+    }
+    out
+}
+
+/// Remove `name` from the catalog `/Dests` dictionary (PDF 1.1 style).
+fn remove_old_style(doc: &mut Document, name: &[u8]) {
+    let Some(dests) = doc
+        .catalog()
+        .ok()
+        .and_then(|catalog| catalog.get(b"Dests").ok())
+        .cloned()
+    else {
+        return;
+    };
+    let dict = match dests {
+        Object::Reference(id) => doc.get_dictionary_mut(id).ok(),
+        _ => doc
+            .catalog_mut()
+            .ok()
+            .and_then(|catalog| catalog.get_mut(b"Dests").ok())
+            .and_then(|d| d.as_dict_mut().ok()),
+    };
+    if let Some(dict) = dict {
+        dict.remove(name);
+    }
+}
+
+/// Point the catalog's `/Names /Dests` at `tree`, creating `/Names` if
+/// needed.
+fn set_catalog_dests_tree(doc: &mut Document, tree: ObjectId) -> Result<()> {
+    let catalog = catalog_mut(doc)?;
+    match catalog.get(b"Names").ok().cloned() {
+        Some(Object::Reference(id)) => {
+            if let Ok(names) = doc.get_dictionary_mut(id) {
+                names.set("Dests", tree);
+                return Ok(());
+            }
+            catalog_mut(doc)?.set(
+                "Names",
+                Dictionary::from_iter([("Dests", Object::Reference(tree))]),
+            );
+        }
+        Some(Object::Dictionary(mut names)) => {
+            names.set("Dests", tree);
+            catalog.set("Names", names);
+        }
+        _ => catalog.set(
+            "Names",
+            Dictionary::from_iter([("Dests", Object::Reference(tree))]),
+        ),
+    }
+    Ok(())
+}
+
+/// The view type of an explicit destination (`[page /Fit]`, or a
+/// dictionary with `/D`).
+fn dest_view(doc: &Document, dest: &Object) -> Option<String> {
+    let (_, dest) = doc.dereference(dest).ok()?;
+    match dest {
+        Object::Array(items) => items
+            .get(1)
+            .and_then(|view| view.as_name().ok())
+            .map(|view| String::from_utf8_lossy(view).into_owned()),
+        Object::Dictionary(d) => dest_view(doc, d.get(b"D").ok()?),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_pages() -> Document {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let kids: Vec<Object> = (0..2)
+            .map(|_| {
+                doc.add_object(Dictionary::from_iter([
+                    ("Type", Object::Name(b"Page".to_vec())),
+                    ("Parent", Object::Reference(pages_id)),
+                ]))
+                .into()
+            })
+            .collect();
+        let first = kids[0].clone();
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(Dictionary::from_iter([
+                ("Type", Object::Name(b"Pages".to_vec())),
+                ("Kids", Object::Array(kids)),
+                ("Count", Object::Integer(2)),
+            ])),
+        );
+        let catalog = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Catalog".to_vec())),
+            ("Pages", Object::Reference(pages_id)),
+            (
+                "Dests",
+                Object::Dictionary(Dictionary::from_iter([(
+                    "intro",
+                    Object::Array(vec![first, Object::Name(b"XYZ".to_vec())]),
+                )])),
+            ),
+        ]));
+        doc.trailer.set("Root", catalog);
+        doc
+    }
+
+    #[test]
+    fn added_destinations_are_listed_with_existing_ones() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("in.pdf");
+        two_pages().save(&input)?;
+        let output = dir.path().join("out.pdf");
+        add_destination(&input, &output, "chapter2", 2, false)?;
+
+        let listed = destinations(&output)?;
+        let summary: Vec<(&str, Option<u32>, Option<&str>)> = listed
+            .iter()
+            .map(|d| (d.name.as_str(), d.page, d.view.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("chapter2", Some(2), Some("Fit")),
+                ("intro", Some(1), Some("XYZ"))
+            ]
+        );
+
+        assert!(matches!(
+            add_destination(&output, &output, "intro", 2, false),
+            Err(PdfError::InvalidArgument(_))
+        ));
+        add_destination(&output, &output, "intro", 2, true)?;
+        let intro = destinations(&output)?
+            .into_iter()
+            .find(|d| d.name == "intro")
+            .map(|d| d.page);
+        assert_eq!(intro, Some(Some(2)));
+        Ok(())
+    }
+}
@@ -0,0 +1,220 @@
+//! The file identifier (`/ID` in the trailer), and reproducible output (see
+//! [`crate::set_deterministic`]).
+//!
+//! The ID is a pair of byte strings: the first is meant to stay the same
+//! for the life of the document, the second to change with each revision.
+//! Encryption keys and some signing tools depend on it.
+
+use std::{
+    fmt::Write as _,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use lopdf::{Document, Object, StringFormat};
+use md5::{Digest, Md5};
+
+use crate::{load_document, plan::Plan, save_document, PdfError, Result};
+
+/// Set a new document ID on `input` and return it as lower-case hex (pure
+/// Rust). With `from_content_hash`, both halves are a hash of the document's
+/// content, so the same content always gets the same ID; otherwise they are
+/// a fresh unique value.
+///
+/// Encrypted documents are refused, as their key depends on the ID.
+pub fn regenerate_id(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    from_content_hash: bool,
+) -> Result<[String; 2]> {
+    let input = input.as_ref();
+    let mut doc = load_document(input)?;
+    check_unencrypted(&doc, input)?;
+    let mut id = content_id(&mut doc)?;
+    if !from_content_hash {
+        id = unique_id(&id);
+    }
+    set_id(&mut doc, &id, &id);
+    save_document(&mut doc, output.as_ref())?;
+    Ok(document_id(&doc).unwrap_or_default())
+}
+
+/// What [`regenerate_id`] would do.
+pub fn regenerate_id_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    from_content_hash: bool,
+) -> Result<Plan> {
+    let input = input.as_ref();
+    let doc = load_document(input)?;
+    check_unencrypted(&doc, input)?;
+    let how = if from_content_hash {
+        "a hash of the content"
+    } else {
+        "a new unique value"
+    };
+    Ok(Plan::new()
+        .native(format!("set the document ID to {how}"))
+        .write(output.as_ref()))
+}
+
+/// The trailer's `/ID` pair as lower-case hex, if present.
+pub(crate) fn document_id(doc: &Document) -> Option<[String; 2]> {
+    let id = doc.trailer.get(b"ID").and_then(Object::as_array).ok()?;
+    let hex = |object: &Object| {
+        object.as_str().ok().map(|bytes| {
+            bytes.iter().fold(String::new(), |mut hex, byte| {
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            })
+        })
+    };
+    match id.as_slice() {
+        [permanent, changing] => Some([hex(permanent)?, hex(changing)?]),
+        _ => None,
+    }
+}
+
+fn check_unencrypted(doc: &Document, path: &Path) -> Result<()> {
+    if doc.trailer.has(b"Encrypt") {
+        return Err(PdfError::Encrypted {
+            path: path.to_path_buf(),
+            reason: "the encryption key depends on the document ID; decrypt the file first"
+                .to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// An ID unique to this content, process, and moment.
+fn unique_id(content: &[u8]) -> Vec<u8> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let mut hasher = Md5::new();
+    hasher.update(content);
+    hasher.update(now.as_nanos().to_le_bytes());
+    hasher.update(std::process::id().to_le_bytes());
+    hasher.finalize().to_vec()
+}
+
+/// Information dictionary entries that record when a file was written.
+const TIMESTAMPS: [&[u8]; 2] = [b"CreationDate", b"ModDate"];
+
+/// Prepare `doc` so that saving it gives the same bytes for the same
+/// content: drop the timestamps from the information dictionary and set the
+/// ID from a hash of the rest of the file. Objects are always written in
+/// object number order, so nothing else varies between runs.
+pub(crate) fn make_deterministic(doc: &mut Document) -> Result<()> {
+    let info = match doc.trailer.get(b"Info") {
+        Ok(Object::Reference(id)) => {
+            let id = *id;
+            doc.get_dictionary_mut(id).ok()
+        }
+        Ok(Object::Dictionary(_)) => doc
+            .trailer
+            .get_mut(b"Info")
+            .ok()
+            .and_then(|info| info.as_dict_mut().ok()),
+        _ => None,
+    };
+    if let Some(info) = info {
+        for key in TIMESTAMPS {
+            info.remove(key);
+        }
+    }
+    let id = content_id(doc)?;
+    set_id(doc, &id, &id);
+    Ok(())
+}
+
+/// A hash of the document as it would be saved without an ID.
+pub(crate) fn content_id(doc: &mut Document) -> Result<Vec<u8>> {
+    let previous = doc.trailer.remove(b"ID");
+    let mut bytes = Vec::new();
+    let saved = doc.save_to(&mut bytes);
+    if let Some(previous) = previous {
+        doc.trailer.set("ID", previous);
+    }
+    saved?;
+    Ok(Md5::digest(&bytes).to_vec())
+}
+
+/// Set the trailer's `/ID` pair.
+pub(crate) fn set_id(doc: &mut Document, permanent: &[u8], changing: &[u8]) {
+    let string = |bytes: &[u8]| Object::String(bytes.to_vec(), StringFormat::Hexadecimal);
+    doc.trailer.set(
+        "ID",
+        Object::Array(vec![string(permanent), string(changing)]),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::Dictionary;
+
+    fn document(moment: &str) -> Document {
+        let mut doc = Document::with_version("1.5");
+        let pages = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Pages".to_vec())),
+            ("Kids", Object::Array(Vec::new())),
+            ("Count", Object::Integer(0)),
+        ]));
+        let catalog = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Catalog".to_vec())),
+            ("Pages", Object::Reference(pages)),
+        ]));
+        let info = doc.add_object(Dictionary::from_iter([
+            ("Title", Object::string_literal("Report")),
+            ("ModDate", Object::string_literal(moment)),
+        ]));
+        doc.trailer.set("Root", catalog);
+        doc.trailer.set("Info", info);
+        doc
+    }
+
+    #[test]
+    fn ids_are_regenerated_fresh_or_from_content() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("in.pdf");
+        document("D:20240101000000Z").save(&input)?;
+        let output = dir.path().join("out.pdf");
+
+        let hashed = regenerate_id(&input, &output, true)?;
+        assert_eq!(hashed[0].len(), 32);
+        assert_eq!(hashed[0], hashed[1]);
+        assert_eq!(regenerate_id(&input, &output, true)?, hashed);
+        assert_eq!(document_id(&load_document(&output)?), Some(hashed.clone()));
+
+        let fresh = regenerate_id(&input, &output, false)?;
+        assert_ne!(fresh, hashed);
+        assert_ne!(regenerate_id(&input, &output, false)?, fresh);
+        Ok(())
+    }
+
+    #[test]
+    fn deterministic_output_ignores_timestamps() -> Result<()> {
+        let save = |mut doc: Document| -> Result<Vec<u8>> {
+            make_deterministic(&mut doc)?;
+            let mut bytes = Vec::new();
+            doc.save_to(&mut bytes)?;
+            Ok(bytes)
+        };
+        let first = save(document("D:20240101000000Z"))?;
+        assert_eq!(first, save(document("D:20250601120000Z"))?);
+        assert!(!first.windows(7).any(|w| w == b"ModDate"));
+
+        let mut other = document("D:20240101000000Z");
+        other.trailer.set("Extra", true);
+        assert_ne!(first, save(other)?);
+
+        let doc = Document::load_mem(&first).map_err(|source| crate::PdfError::PdfParse {
+            path: "memory".into(),
+            source,
+        })?;
+        let id = doc.trailer.get(b"ID").and_then(Object::as_array).ok();
+        assert_eq!(id.map(Vec::len), Some(2));
+        Ok(())
+    }
+}
@@ -0,0 +1,356 @@
+//! PDF attachments of email messages: MIME messages (`.eml`) as mail
+//! clients save them, and Outlook messages (`.msg`, see [`crate::msg`]).
+
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use mail_parser::{Message, MessageParser, MimeHeaders};
+
+use crate::{
+    msg,
+    split::{safe_file_stem, unique_name},
+    tempspace::TempSpace,
+    validate_input_file, Merge, PdfError, Plan, Result,
+};
+
+/// An attachment of an email message, as [`email_attachments`] lists it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailAttachment {
+    /// The file name the message gives it, or `attachment-<n>`.
+    pub name: String,
+    /// Its MIME type as the message declares it (lowercase).
+    pub content_type: String,
+    /// Size in bytes, decoded.
+    pub size: usize,
+    /// Whether it is a PDF, by its contents rather than its name or type.
+    pub pdf: bool,
+}
+
+/// An attachment with its contents.
+pub(crate) struct Attachment {
+    pub(crate) name: Option<String>,
+    pub(crate) content_type: String,
+    pub(crate) data: Vec<u8>,
+}
+
+impl Attachment {
+    /// PDF files start with `%PDF-`, possibly after some junk that readers
+    /// skip.
+    fn is_pdf(&self) -> bool {
+        let head = &self.data[..self.data.len().min(1024)];
+        head.windows(5).any(|w| w == b"%PDF-")
+    }
+}
+
+/// The attachments of the message `input` (`.eml` or `.msg`), in the
+/// order the message holds them, including those of forwarded messages
+/// inside it (pure Rust).
+pub fn email_attachments(input: impl AsRef<Path>) -> Result<Vec<EmailAttachment>> {
+    Ok(attachments(input.as_ref())?
+        .iter()
+        .enumerate()
+        .map(|(i, attachment)| EmailAttachment {
+            name: attachment
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("attachment-{}", i + 1)),
+            content_type: attachment.content_type.clone(),
+            size: attachment.data.len(),
+            pdf: attachment.is_pdf(),
+        })
+        .collect())
+}
+
+/// Write the PDF attachments of the message `input` to `out_dir`, named as
+/// in the message (made safe for the file system, with a `-2`, `-3`, ...
+/// suffix if repeated); returns the files written, which is none if the
+/// message has no PDF attachments (pure Rust).
+pub fn extract_email_pdfs(
+    input: impl AsRef<Path>,
+    out_dir: impl AsRef<Path>,
+) -> Result<Vec<PathBuf>> {
+    let out_dir = out_dir.as_ref();
+    let pdfs = pdf_attachments(input.as_ref())?;
+    let mut written = Vec::with_capacity(pdfs.len());
+    for (name, attachment) in pdfs {
+        let path = out_dir.join(name);
+        fs::write(&path, &attachment.data)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// What [`extract_email_pdfs`] would do.
+pub fn extract_email_pdfs_plan(input: impl AsRef<Path>, out_dir: impl AsRef<Path>) -> Result<Plan> {
+    let input = input.as_ref();
+    let pdfs = pdf_attachments(input)?;
+    let mut plan = Plan::new().native(format!(
+        "read {} PDF attachment(s) of {}",
+        pdfs.len(),
+        input.display()
+    ));
+    for (name, _) in pdfs {
+        plan = plan.write(&out_dir.as_ref().join(name));
+    }
+    Ok(plan)
+}
+
+/// Merge the PDF attachments of the message `input`, in message order, into
+/// `output` (merged as [`Merge`] does); returns how many were merged. A
+/// message without PDF attachments is an error.
+pub fn merge_email_pdfs(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<usize> {
+    let input = input.as_ref();
+    let space = TempSpace::new("email")?;
+    let files = extract_email_pdfs(input, space.path())?;
+    if files.is_empty() {
+        return Err(no_pdfs(input));
+    }
+    Merge::new(&files).output(output.as_ref()).run()?;
+    Ok(files.len())
+}
+
+/// What [`merge_email_pdfs`] would do.
+pub fn merge_email_pdfs_plan(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<Plan> {
+    let input = input.as_ref();
+    let pdfs = pdf_attachments(input)?;
+    if pdfs.is_empty() {
+        return Err(no_pdfs(input));
+    }
+    let names: Vec<String> = pdfs.into_iter().map(|(name, _)| name).collect();
+    Ok(Plan::new()
+        .native(format!(
+            "merge the PDF attachments of {}: {}",
+            input.display(),
+            names.join(", ")
+        ))
+        .write(output.as_ref()))
+}
+
+fn no_pdfs(input: &Path) -> PdfError {
+    PdfError::InvalidArgument(format!("{} has no PDF attachments", input.display()))
+}
+
+/// The PDF attachments of `input` with the unique file names they are
+/// written under.
+fn pdf_attachments(input: &Path) -> Result<Vec<(String, Attachment)>> {
+    let mut used = BTreeSet::new();
+    Ok(attachments(input)?
+        .into_iter()
+        .enumerate()
+        .filter(|(_, attachment)| attachment.is_pdf())
+        .map(|(i, attachment)| {
+            let stem = attachment
+                .name
+                .as_deref()
+                .map(|name| {
+                    let stem = name.rsplit(['/', '\\']).next().unwrap_or(name);
+                    let stem = Path::new(stem)
+                        .extension()
+                        .filter(|ext| ext.eq_ignore_ascii_case("pdf"))
+                        .map_or(stem, |_| &stem[..stem.len() - 4]);
+                    safe_file_stem(stem)
+                })
+                .filter(|stem| !stem.is_empty())
+                .unwrap_or_else(|| format!("attachment-{}", i + 1));
+            (unique_name(&mut used, &stem), attachment)
+        })
+        .collect())
+}
+
+fn attachments(input: &Path) -> Result<Vec<Attachment>> {
+    validate_input_file(input)?;
+    let data = fs::read(input)?;
+    if msg::is_msg(&data) {
+        return msg::attachments(&data)
+            .map_err(|e| PdfError::InvalidArgument(format!("{}: {e}", input.display())));
+    }
+    let message = MessageParser::default().parse(&data).ok_or_else(|| {
+        PdfError::InvalidArgument(format!("{} is not an email message", input.display()))
+    })?;
+    let mut found = Vec::new();
+    mime_attachments(&message, 0, &mut found);
+    Ok(found)
+}
+
+/// How deep forwarded messages are followed.
+const MAX_NESTING: usize = 32;
+
+/// Collect the attachments of `message` into `found`, following forwarded
+/// messages into theirs. Message text, including the alternatives of a
+/// `multipart/alternative` body, is not an attachment.
+fn mime_attachments(message: &Message, depth: usize, found: &mut Vec<Attachment>) {
+    if depth > MAX_NESTING {
+        return;
+    }
+    for part in message.attachments() {
+        if let Some(forwarded) = part.message() {
+            mime_attachments(forwarded, depth + 1, found);
+            continue;
+        }
+        let content_type = part.content_type().map_or_else(
+            || "application/octet-stream".to_string(),
+            |ct| match ct.subtype() {
+                Some(subtype) => format!("{}/{subtype}", ct.ctype()),
+                None => ct.ctype().to_string(),
+            },
+        );
+        found.push(Attachment {
+            name: part
+                .attachment_name()
+                .filter(|name| !name.trim().is_empty())
+                .map(str::to_string),
+            content_type: content_type.to_ascii_lowercase(),
+            data: part.contents().to_vec(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PDF: &[u8] = b"%PDF-1.4\n%%EOF\n";
+
+    fn message() -> Vec<u8> {
+        let pdf = base64(PDF);
+        format!(
+            "From: a@example.com\r\n\
+             Subject: scans\r\n\
+             Content-Type: multipart/mixed;\r\n boundary=\"outer\"\r\n\
+             \r\n\
+             preamble\r\n\
+             --outer\r\n\
+             Content-Type: text/plain\r\n\
+             \r\n\
+             See attached.\r\n\
+             --outer\r\n\
+             Content-Type: application/pdf; name=\"=?utf-8?B?w6l0w6kucGRm?=\"\r\n\
+             Content-Transfer-Encoding: base64\r\n\
+             \r\n\
+             {pdf}\r\n\
+             --outer\r\n\
+             Content-Type: message/rfc822\r\n\
+             \r\n\
+             Subject: forwarded\r\n\
+             Content-Type: multipart/mixed; boundary=inner\r\n\
+             \r\n\
+             --inner\r\n\
+             Content-Type: application/octet-stream\r\n\
+             Content-Disposition: attachment; filename*0*=utf-8''Invoice%20;\r\n filename*1=\"2024.pdf\"\r\n\
+             Content-Transfer-Encoding: quoted-printable\r\n\
+             \r\n\
+             %PDF-1.7=0A%%EOF=\r\n\
+             \r\n\
+             --inner\r\n\
+             Content-Type: image/png; name=logo.png\r\n\
+             \r\n\
+             png\r\n\
+             --inner--\r\n\
+             --outer--\r\n"
+        )
+        .into_bytes()
+    }
+
+    fn base64(data: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut out = String::new();
+        for chunk in data.chunks(3) {
+            let n = chunk
+                .iter()
+                .enumerate()
+                .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    out.push(char::from(ALPHABET[(n >> (18 - 6 * i) & 63) as usize]));
+                } else {
+                    out.push('=');
+                }
+            }
+        }
+        out
+    }
+
+    fn parsed(data: &[u8]) -> Vec<Attachment> {
+        let mut found = Vec::new();
+        if let Some(message) = MessageParser::default().parse(data) {
+            mime_attachments(&message, 0, &mut found);
+        }
+        found
+    }
+
+    fn summary(found: &[Attachment]) -> Vec<(Option<&str>, &str, bool)> {
+        found
+            .iter()
+            .map(|a| (a.name.as_deref(), a.content_type.as_str(), a.is_pdf()))
+            .collect()
+    }
+
+    #[test]
+    fn attachments_are_found_in_nested_parts() {
+        let found = parsed(&message());
+        assert_eq!(
+            summary(&found),
+            [
+                (Some("été.pdf"), "application/pdf", true),
+                (Some("Invoice 2024.pdf"), "application/octet-stream", true),
+                (Some("logo.png"), "image/png", false),
+            ]
+        );
+        assert_eq!(found[0].data, PDF);
+        assert_eq!(found[1].data, b"%PDF-1.7\n%%EOF");
+    }
+
+    #[test]
+    fn alternative_bodies_are_not_attachments() {
+        let pdf = base64(PDF);
+        let message = format!(
+            "Subject: report\r\n\
+             Content-Type: multipart/mixed; boundary=mixed\r\n\
+             \r\n\
+             --mixed\r\n\
+             Content-Type: multipart/alternative; boundary=alt\r\n\
+             \r\n\
+             --alt\r\n\
+             Content-Type: text/plain; charset=utf-8\r\n\
+             \r\n\
+             Report attached.\r\n\
+             --alt\r\n\
+             Content-Type: text/html; charset=utf-8\r\n\
+             \r\n\
+             <p>Report attached.</p>\r\n\
+             --alt--\r\n\
+             --mixed\r\n\
+             Content-Type: application/pdf\r\n\
+             Content-Disposition: attachment; filename=report.pdf\r\n\
+             Content-Transfer-Encoding: base64\r\n\
+             \r\n\
+             {pdf}\r\n\
+             --mixed--\r\n"
+        );
+        let found = parsed(message.as_bytes());
+        assert_eq!(
+            summary(&found),
+            [(Some("report.pdf"), "application/pdf", true)]
+        );
+    }
+
+    #[test]
+    fn encoded_words_in_names_are_decoded() {
+        let message = "Content-Type: multipart/mixed; boundary=b\r\n\
+                       \r\n\
+                       --b\r\n\
+                       Content-Type: application/pdf;\r\n \
+                       name=\"=?iso-8859-1?Q?caf=E9_menu.pdf?=\"\r\n\
+                       \r\n\
+                       %PDF-1.4\r\n\
+                       --b--\r\n";
+        let found = parsed(message.as_bytes());
+        assert_eq!(
+            summary(&found),
+            [(Some("café menu.pdf"), "application/pdf", true)]
+        );
+    }
+}
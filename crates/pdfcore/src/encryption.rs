@@ -0,0 +1,537 @@
+//! Encrypted documents: what the `/Encrypt` dictionary says, decryption
+//! for reading, and encryption with new permissions (with `qpdf`).
+//!
+//! Decryption is done by [`lopdf`], which supports the standard security
+//! handler with RC4 (`/V` 1 or 2, revisions 2 and 3). AES-encrypted files
+//! are still described, but not decrypted.
+
+use std::{path::Path, process::Command};
+
+use lopdf::{encryption::DecryptionError, Document, Object};
+
+use crate::{
+    find_tool, load_document, load_for_reading, plan, run_tool_writing, save_document,
+    tempspace::TempSpace, validate_input_file, PdfError, Plan, Result, Tool,
+};
+
+/// Encryption of a document (its `/Encrypt` dictionary).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptionInfo {
+    /// Security handler (`/Filter`), normally `Standard`.
+    pub filter: String,
+    /// Algorithm version (`/V`).
+    pub version: i64,
+    /// Security handler revision (`/R`).
+    pub revision: i64,
+    /// Cipher, e.g. `RC4` or `AES-256`.
+    pub cipher: String,
+    /// Key length in bits.
+    pub key_bits: u32,
+    /// What the document allows without the owner password.
+    pub permissions: Permissions,
+    /// Whether a password is needed to open the document; `None` when that
+    /// cannot be checked (unsupported encryption).
+    pub user_password_required: Option<bool>,
+    /// Whether the document could be decrypted, so the rest of the
+    /// information is complete.
+    pub decrypted: bool,
+}
+
+/// Permission flags of an encrypted document (`/P`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Permissions {
+    /// The raw `/P` value.
+    pub raw: i32,
+}
+
+impl Permissions {
+    /// Every permission granted, as for a document that is not encrypted.
+    pub const ALL: Self = Self { raw: -4 };
+
+    /// Permission names and their `/P` bits (1-based, as in the PDF
+    /// specification).
+    pub const FLAGS: [(&'static str, u32); 8] = [
+        ("print", 3),
+        ("modify", 4),
+        ("copy", 5),
+        ("annotate", 6),
+        ("fill-forms", 9),
+        ("accessibility", 10),
+        ("assemble", 11),
+        ("print-high-quality", 12),
+    ];
+
+    /// Whether the permission called `name` (one of [`Permissions::FLAGS`])
+    /// is granted.
+    #[must_use]
+    pub fn allows(self, name: &str) -> bool {
+        Self::FLAGS
+            .iter()
+            .find(|(flag, _)| *flag == name)
+            .is_some_and(|(_, bit)| self.raw & (1 << (bit - 1)) != 0)
+    }
+
+    /// These permissions with the one called `name` (one of
+    /// [`Permissions::FLAGS`]) granted or not; unknown names change nothing.
+    #[must_use]
+    pub fn with(self, name: &str, allowed: bool) -> Self {
+        let Some((_, bit)) = Self::FLAGS.iter().find(|(flag, _)| *flag == name) else {
+            return self;
+        };
+        let mask = 1 << (bit - 1);
+        Self {
+            raw: if allowed {
+                self.raw | mask
+            } else {
+                self.raw & !mask
+            },
+        }
+    }
+
+    /// Names of the granted permissions, in [`Permissions::FLAGS`] order.
+    #[must_use]
+    pub fn granted(self) -> Vec<&'static str> {
+        Self::FLAGS
+            .iter()
+            .map(|(name, _)| *name)
+            .filter(|name| self.allows(name))
+            .collect()
+    }
+}
+
+/// Options for [`set_permissions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptOptions {
+    /// Permissions of the output.
+    pub permissions: Permissions,
+    /// Password that lifts the restrictions; must not be empty.
+    pub owner_password: String,
+    /// Password needed to open the output; empty for none.
+    pub user_password: String,
+    /// Password of `input`, if it is encrypted and needs one.
+    pub password: Option<String>,
+}
+
+/// The document's encryption, if it is encrypted (pure Rust). Documents
+/// that are not encrypted grant every permission.
+pub fn encryption_info(path: impl AsRef<Path>) -> Result<Option<EncryptionInfo>> {
+    Ok(inspect(&load_for_reading(path.as_ref())?))
+}
+
+/// Encrypt `input` with AES-256 and new permissions (with `qpdf`), replacing
+/// any encryption it had. Permissions are honored by viewers, not enforced
+/// by the encryption; anyone who can open the file can remove them.
+///
+/// The passwords are handed to `qpdf` in an arguments file in a private
+/// scratch directory, not on its command line, where other users of the
+/// machine could see them.
+pub fn set_permissions(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &EncryptOptions,
+) -> Result<()> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_input_file(input)?;
+    let args = encrypt_args(options)?;
+    let scratch = TempSpace::new("permissions")?;
+    let args_file = scratch.write("qpdf-args", args.join("\n") + "\n")?;
+    run_tool_writing(
+        Tool::Qpdf,
+        encrypt_command(input, output, &args_file)?,
+        output,
+    )
+}
+
+/// What [`set_permissions`] would run.
+pub fn set_permissions_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &EncryptOptions,
+) -> Result<Plan> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_input_file(input)?;
+    let args = encrypt_args(options)?;
+    let args_file = plan::temp_path("qpdf-args");
+    let shown: Vec<&str> = args
+        .iter()
+        .map(String::as_str)
+        .filter(|arg| arg.starts_with("--") && !arg.starts_with("--password="))
+        .collect();
+    Ok(Plan::new()
+        .native(format!(
+            "write {} with the passwords and {}",
+            args_file.display(),
+            shown.join(" ")
+        ))
+        .run(Tool::Qpdf, &encrypt_command(input, output, &args_file)?)
+        .write(output))
+}
+
+fn encrypt_command(input: &Path, output: &Path, args_file: &Path) -> Result<Command> {
+    let mut at_file = std::ffi::OsString::from("@");
+    at_file.push(args_file);
+    let mut cmd = Command::new(find_tool(Tool::Qpdf)?);
+    cmd.arg(at_file)
+        .arg(input.as_os_str())
+        .arg(output.as_os_str());
+    Ok(cmd)
+}
+
+/// The `qpdf` arguments, one per line of the arguments file, that encrypt
+/// with `options`.
+fn encrypt_args(options: &EncryptOptions) -> Result<Vec<String>> {
+    if options.owner_password.is_empty() {
+        return Err(PdfError::InvalidArgument(
+            "an owner password is needed to restrict permissions".to_string(),
+        ));
+    }
+    let passwords = [
+        Some(&options.owner_password),
+        Some(&options.user_password),
+        options.password.as_ref(),
+    ];
+    if passwords
+        .into_iter()
+        .flatten()
+        .any(|p| p.contains(['\n', '\r']))
+    {
+        return Err(PdfError::InvalidArgument(
+            "passwords must not contain line breaks".to_string(),
+        ));
+    }
+    let permissions = options.permissions;
+    // AES-256 (revision 6) ignores the accessibility bit: PDF 2.0 does not
+    // allow keeping content from assistive technology.
+    if !permissions.allows("accessibility") {
+        return Err(PdfError::InvalidArgument(
+            "content extraction for accessibility cannot be restricted".to_string(),
+        ));
+    }
+    let yes_no = |name: &str| if permissions.allows(name) { "y" } else { "n" };
+    let print = match (
+        permissions.allows("print"),
+        permissions.allows("print-high-quality"),
+    ) {
+        (true, true) => "full",
+        (true, false) => "low",
+        (false, _) => "none",
+    };
+    let mut args = Vec::new();
+    if let Some(password) = &options.password {
+        args.push(format!("--password={password}"));
+    }
+    args.extend([
+        "--encrypt".to_string(),
+        options.user_password.clone(),
+        options.owner_password.clone(),
+        "256".to_string(),
+        format!("--print={print}"),
+        format!("--modify-other={}", yes_no("modify")),
+        format!("--extract={}", yes_no("copy")),
+        format!("--annotate={}", yes_no("annotate")),
+        format!("--form={}", yes_no("fill-forms")),
+        format!("--assemble={}", yes_no("assemble")),
+        "--".to_string(),
+    ]);
+    Ok(args)
+}
+
+/// Describe the document's encryption, if it is encrypted.
+pub(crate) fn inspect(doc: &Document) -> Option<EncryptionInfo> {
+    let dict = doc.get_encrypted().ok()?;
+    let int = |key: &[u8]| dict.get(key).and_then(Object::as_i64).ok();
+    let name = |object: Option<&Object>| {
+        object
+            .and_then(|o| o.as_name().ok())
+            .map(|n| String::from_utf8_lossy(n).into_owned())
+    };
+
+    let version = int(b"V").unwrap_or(0);
+    let crypt_filter = dict
+        .get(b"CF")
+        .and_then(Object::as_dict)
+        .and_then(|cf| cf.get(b"StdCF"))
+        .and_then(Object::as_dict)
+        .ok();
+    let cipher = match (
+        version,
+        name(crypt_filter.and_then(|cf| cf.get(b"CFM").ok())).as_deref(),
+    ) {
+        (5, _) | (_, Some("AESV3")) => "AES-256",
+        (_, Some("AESV2")) => "AES-128",
+        (_, Some("None")) => "none",
+        _ => "RC4",
+    };
+    let key_bits = int(b"Length")
+        .and_then(|bits| u32::try_from(bits).ok())
+        .unwrap_or(match cipher {
+            "AES-256" => 256,
+            "AES-128" => 128,
+            _ if version >= 4 => 128,
+            _ => 40,
+        });
+    let user_password_required = match lopdf::encryption::get_encryption_key(doc, "", true) {
+        Ok(_) => Some(false),
+        Err(DecryptionError::IncorrectPassword) => Some(true),
+        Err(_) => None,
+    };
+    Some(EncryptionInfo {
+        filter: name(dict.get(b"Filter").ok()).unwrap_or_else(|| "Standard".to_string()),
+        version,
+        revision: int(b"R").unwrap_or(0),
+        cipher: cipher.to_string(),
+        key_bits,
+        // `/P` is a 32-bit two's complement value; keep its bits.
+        #[allow(clippy::cast_possible_truncation)]
+        permissions: Permissions {
+            raw: int(b"P").unwrap_or(-1) as i32,
+        },
+        user_password_required,
+        decrypted: false,
+    })
+}
+
+/// Decrypt `doc` in place if it is encrypted, with `password` or, when
+/// none is given, the empty user password.
+///
+/// Returns the encryption, with [`EncryptionInfo::decrypted`] false when no
+/// password was given and the document could not be decrypted without one.
+/// A wrong `password`, or one given for encryption that cannot be
+/// decrypted, is a [`PdfError::Encrypted`] error.
+pub(crate) fn decrypt(
+    doc: &mut Document,
+    path: &Path,
+    password: Option<&str>,
+) -> Result<Option<EncryptionInfo>> {
+    let Some(mut info) = inspect(doc) else {
+        return Ok(None);
+    };
+    match doc.decrypt(password.unwrap_or_default()) {
+        Ok(()) => info.decrypted = true,
+        Err(e) if password.is_some() => {
+            let reason = match e {
+                lopdf::Error::Decryption(DecryptionError::IncorrectPassword) => {
+                    "incorrect password".to_string()
+                }
+                other => format!("cannot decrypt: {other}"),
+            };
+            return Err(PdfError::Encrypted {
+                path: path.to_path_buf(),
+                reason,
+            });
+        }
+        Err(e) => tracing::debug!(error = %e, "not decrypting without a password"),
+    }
+    Ok(Some(info))
+}
+
+/// Write `input` to `output` decrypted, with `password` or the empty user
+/// password, and without its `/Encrypt` dictionary. A document that is not
+/// encrypted is rewritten as it is.
+pub(crate) fn decrypt_file(input: &Path, output: &Path, password: Option<&str>) -> Result<()> {
+    let mut doc = load_document(input)?;
+    if let Some(info) = decrypt(&mut doc, input, password)? {
+        if !info.decrypted {
+            return Err(PdfError::Encrypted {
+                path: input.to_path_buf(),
+                reason: "a password is needed to decrypt it".to_string(),
+            });
+        }
+        if let Ok(id) = doc.trailer.get(b"Encrypt").and_then(Object::as_reference) {
+            doc.objects.remove(&id);
+        }
+        doc.trailer.remove(b"Encrypt");
+    }
+    save_document(&mut doc, output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{Dictionary, StringFormat};
+
+    #[test]
+    fn permissions_become_qpdf_arguments() -> Result<()> {
+        let options = EncryptOptions {
+            permissions: Permissions::ALL
+                .with("print-high-quality", false)
+                .with("copy", false),
+            owner_password: "owner".to_string(),
+            user_password: String::new(),
+            password: Some("old".to_string()),
+        };
+        assert!(options.permissions.allows("print"));
+        assert!(!options.permissions.allows("copy"));
+        assert_eq!(
+            encrypt_args(&options)?,
+            [
+                "--password=old",
+                "--encrypt",
+                "",
+                "owner",
+                "256",
+                "--print=low",
+                "--modify-other=y",
+                "--extract=n",
+                "--annotate=y",
+                "--form=y",
+                "--assemble=y",
+                "--",
+            ]
+        );
+        let no_owner = EncryptOptions {
+            owner_password: String::new(),
+            ..options.clone()
+        };
+        assert!(encrypt_args(&no_owner).is_err());
+        let accessibility = EncryptOptions {
+            permissions: Permissions::ALL.with("accessibility", false),
+            ..options
+        };
+        assert!(encrypt_args(&accessibility).is_err());
+        Ok(())
+    }
+
+    const PAD: [u8; 32] = [
+        0x28, 0xBF, 0x4E, 0x5E, 0x4E, 0x75, 0x8A, 0x41, 0x64, 0x00, 0x4E, 0x56, 0xFF, 0xFA, 0x01,
+        0x08, 0x2E, 0x2E, 0x00, 0xB6, 0xD0, 0x68, 0x3E, 0x80, 0x2F, 0x0C, 0xA9, 0xFE, 0x64, 0x53,
+        0x69, 0x7A,
+    ];
+
+    fn rc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut s: Vec<u8> = (0..=255).collect();
+        let mut j = 0u8;
+        for i in 0..256 {
+            j = j.wrapping_add(s[i]).wrapping_add(key[i % key.len()]);
+            s.swap(i, usize::from(j));
+        }
+        let (mut i, mut j) = (0u8, 0u8);
+        data.iter()
+            .map(|byte| {
+                i = i.wrapping_add(1);
+                j = j.wrapping_add(s[usize::from(i)]);
+                s.swap(usize::from(i), usize::from(j));
+                byte ^ s[usize::from(s[usize::from(i)].wrapping_add(s[usize::from(j)]))]
+            })
+            .collect()
+    }
+
+    /// A one-page document encrypted with RC4 40-bit (revision 2) for
+    /// `user_password`, allowing printing only, titled "Secret report".
+    fn encrypted(user_password: &str) -> Document {
+        let mut doc = Document::with_version("1.4");
+        let pages_id = doc.new_object_id();
+        let page = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Page".to_vec())),
+            ("Parent", Object::Reference(pages_id)),
+        ]));
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(Dictionary::from_iter([
+                ("Type", Object::Name(b"Pages".to_vec())),
+                ("Kids", Object::Array(vec![Object::Reference(page)])),
+                ("Count", Object::Integer(1)),
+            ])),
+        );
+        let catalog = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Catalog".to_vec())),
+            ("Pages", Object::Reference(pages_id)),
+        ]));
+        let encrypt = doc.add_object(Dictionary::from_iter([
+            ("Filter", Object::Name(b"Standard".to_vec())),
+            ("V", Object::Integer(1)),
+            ("R", Object::Integer(2)),
+            ("O", Object::String(vec![7; 32], StringFormat::Hexadecimal)),
+            ("U", Object::String(vec![0; 32], StringFormat::Hexadecimal)),
+            ("P", Object::Integer(-4 & !0xff8)),
+        ]));
+        doc.trailer.set("Root", catalog);
+        doc.trailer.set("Encrypt", encrypt);
+        doc.trailer.set(
+            "ID",
+            Object::Array(vec![
+                Object::String(b"0123456789abcdef".to_vec(), StringFormat::Hexadecimal),
+                Object::String(b"0123456789abcdef".to_vec(), StringFormat::Hexadecimal),
+            ]),
+        );
+
+        let Ok(key) = lopdf::encryption::get_encryption_key(&doc, user_password, false) else {
+            return doc;
+        };
+        if let Ok(dict) = doc.get_dictionary_mut(encrypt) {
+            dict.set(
+                "U",
+                Object::String(rc4(&key, &PAD), StringFormat::Hexadecimal),
+            );
+        }
+        // RC4 is symmetric: "decrypting" the plain title encrypts it.
+        let info_id = doc.new_object_id();
+        let title = Object::String(b"Secret report".to_vec(), StringFormat::Literal);
+        let title = lopdf::encryption::decrypt_object(&key, info_id, &title).unwrap_or_default();
+        doc.objects.insert(
+            info_id,
+            Object::Dictionary(Dictionary::from_iter([(
+                "Title",
+                Object::String(title, StringFormat::Literal),
+            )])),
+        );
+        doc.trailer.set("Info", info_id);
+        doc
+    }
+
+    #[test]
+    fn encryption_is_described_and_decrypted_with_the_password() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let open = dir.path().join("open.pdf");
+        encrypted("").save(&open)?;
+        let locked = dir.path().join("locked.pdf");
+        encrypted("secret").save(&locked)?;
+
+        // No user password: readable by anyone, restricted by permissions.
+        let info = crate::info(&open)?;
+        let encryption = info.encryption.ok_or(PdfError::InvalidArgument(
+            "no encryption reported".to_string(),
+        ))?;
+        assert_eq!(
+            (
+                encryption.cipher.as_str(),
+                encryption.key_bits,
+                encryption.revision
+            ),
+            ("RC4", 40, 2)
+        );
+        assert_eq!(encryption.user_password_required, Some(false));
+        assert!(encryption.decrypted);
+        assert_eq!(encryption.permissions.granted(), ["print"]);
+        assert_eq!(info.metadata["Title"], "Secret report");
+
+        // A user password: details only, until it is given.
+        let info = crate::info(&locked)?;
+        let encryption = info.encryption.as_ref();
+        assert_eq!(
+            encryption.and_then(|e| e.user_password_required),
+            Some(true)
+        );
+        assert!(!encryption.is_some_and(|e| e.decrypted));
+        assert!(info.metadata.is_empty());
+        assert_eq!(info.pages, 1);
+
+        let info = crate::info_with_password(&locked, "secret")?;
+        assert_eq!(info.metadata["Title"], "Secret report");
+        assert!(matches!(
+            crate::info_with_password(&locked, "guess"),
+            Err(PdfError::Encrypted { .. })
+        ));
+
+        let decrypted = dir.path().join("decrypted.pdf");
+        assert!(matches!(
+            decrypt_file(&locked, &decrypted, None),
+            Err(PdfError::Encrypted { .. })
+        ));
+        decrypt_file(&locked, &decrypted, Some("secret"))?;
+        let info = crate::info(&decrypted)?;
+        assert!(info.encryption.is_none());
+        assert_eq!(info.metadata["Title"], "Secret report");
+        Ok(())
+    }
+}
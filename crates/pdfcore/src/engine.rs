@@ -0,0 +1,278 @@
+//! Engine selection: which backend serves an operation.
+//!
+//! Several operations can be carried out by more than one engine, either an
+//! external tool or pdfcore's own pure Rust implementation. Each operation
+//! has a default priority list; engines passed to [`set_preferred_engines`]
+//! are tried first. The first engine that supports the operation and is
+//! installed is used.
+
+use std::{fmt, str::FromStr, sync::RwLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{find_tool, PdfError, Result, Tool};
+
+/// A backend that can carry out operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "&'static str")]
+pub enum Engine {
+    /// pdfcore itself (pure Rust, always available).
+    Native,
+    /// qpdf.
+    Qpdf,
+    /// Ghostscript.
+    Ghostscript,
+    /// pdftotext (Poppler).
+    Pdftotext,
+    /// `mutool` (`MuPDF`).
+    Mutool,
+    /// `PDFium`, in process (requires the `pdfium` feature).
+    Pdfium,
+    /// `wkhtmltopdf`.
+    Wkhtmltopdf,
+    /// Headless Chromium or Chrome.
+    Chrome,
+    /// pdftocairo (Poppler).
+    Pdftocairo,
+}
+
+impl Engine {
+    /// Every engine, in no particular priority.
+    pub const ALL: [Self; 9] = [
+        Self::Native,
+        Self::Qpdf,
+        Self::Ghostscript,
+        Self::Pdftotext,
+        Self::Mutool,
+        Self::Pdfium,
+        Self::Wkhtmltopdf,
+        Self::Chrome,
+        Self::Pdftocairo,
+    ];
+
+    /// Lower-case identifier, e.g. `qpdf`.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Native => "native",
+            Self::Qpdf => "qpdf",
+            Self::Ghostscript => "ghostscript",
+            Self::Pdftotext => "pdftotext",
+            Self::Mutool => "mutool",
+            Self::Pdfium => "pdfium",
+            Self::Wkhtmltopdf => "wkhtmltopdf",
+            Self::Chrome => "chrome",
+            Self::Pdftocairo => "pdftocairo",
+        }
+    }
+
+    /// Fails with [`PdfError::MissingTool`] if this engine cannot be used.
+    pub(crate) fn check_available(self) -> Result<()> {
+        let tool = match self {
+            Self::Native => return Ok(()),
+            Self::Pdfium => return check_pdfium(),
+            Self::Qpdf => Tool::Qpdf,
+            Self::Ghostscript => Tool::Ghostscript,
+            Self::Pdftotext => Tool::Pdftotext,
+            Self::Mutool => Tool::Mutool,
+            Self::Wkhtmltopdf => Tool::Wkhtmltopdf,
+            Self::Chrome => Tool::Chrome,
+            Self::Pdftocairo => Tool::Pdftocairo,
+        };
+        find_tool(tool).map(drop)
+    }
+}
+
+#[cfg(feature = "pdfium")]
+fn check_pdfium() -> Result<()> {
+    crate::pdfium::check_available()
+}
+
+#[cfg(not(feature = "pdfium"))]
+#[allow(clippy::unnecessary_wraps)]
+fn check_pdfium() -> Result<()> {
+    Err(PdfError::MissingTool {
+        tool: "pdfium",
+        hint: "This build does not include PDFium; rebuild with `--features pdfium`.".to_string(),
+    })
+}
+
+impl fmt::Display for Engine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Engine {
+    type Err = PdfError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "native" | "rust" => Ok(Self::Native),
+            "qpdf" => Ok(Self::Qpdf),
+            "gs" | "ghostscript" => Ok(Self::Ghostscript),
+            "pdftotext" | "poppler" => Ok(Self::Pdftotext),
+            "mutool" | "mupdf" => Ok(Self::Mutool),
+            "pdfium" => Ok(Self::Pdfium),
+            "wkhtmltopdf" => Ok(Self::Wkhtmltopdf),
+            "chrome" | "chromium" => Ok(Self::Chrome),
+            "pdftocairo" | "cairo" => Ok(Self::Pdftocairo),
+            other => {
+                let names: Vec<&str> = Self::ALL.iter().map(|e| e.name()).collect();
+                Err(PdfError::InvalidArgument(format!(
+                    "unknown engine: {other} (expected one of: {})",
+                    names.join(", ")
+                )))
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for Engine {
+    type Error = PdfError;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<Engine> for &'static str {
+    fn from(engine: Engine) -> Self {
+        engine.name()
+    }
+}
+
+/// Operations that more than one engine can serve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Merge,
+    Rotate,
+    ExtractText,
+    Render,
+    HtmlToPdf,
+    /// Dropping unreferenced objects (`optimize --gc`).
+    Optimize,
+    /// Exporting a page as SVG.
+    ToSvg,
+}
+
+impl Operation {
+    /// Every operation.
+    pub const ALL: [Self; 7] = [
+        Self::Merge,
+        Self::Rotate,
+        Self::ExtractText,
+        Self::Render,
+        Self::HtmlToPdf,
+        Self::Optimize,
+        Self::ToSvg,
+    ];
+
+    /// Lower-case identifier, e.g. `extract-text`.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Merge => "merge",
+            Self::Rotate => "rotate",
+            Self::ExtractText => "extract-text",
+            Self::Render => "render",
+            Self::HtmlToPdf => "from-html",
+            Self::Optimize => "optimize",
+            Self::ToSvg => "to-svg",
+        }
+    }
+
+    /// Engines that can serve this operation, best first.
+    #[must_use]
+    pub fn engines(self) -> &'static [Engine] {
+        match self {
+            Self::Merge => &[Engine::Qpdf, Engine::Mutool, Engine::Native],
+            Self::Rotate | Self::Optimize => &[Engine::Qpdf, Engine::Native],
+            Self::ExtractText => &[
+                Engine::Pdftotext,
+                Engine::Mutool,
+                Engine::Pdfium,
+                Engine::Native,
+            ],
+            Self::Render => &[Engine::Ghostscript, Engine::Mutool, Engine::Pdfium],
+            Self::HtmlToPdf => &[Engine::Wkhtmltopdf, Engine::Chrome],
+            Self::ToSvg => &[Engine::Pdftocairo, Engine::Mutool],
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+static PREFERRED: RwLock<Vec<Engine>> = RwLock::new(Vec::new());
+
+/// Engines to try first for every operation, in order, before the
+/// operation's defaults. Applies to the whole process.
+pub fn set_preferred_engines(engines: &[Engine]) {
+    if let Ok(mut preferred) = PREFERRED.write() {
+        *preferred = engines.to_vec();
+    }
+}
+
+/// Engines set by [`set_preferred_engines`].
+#[must_use]
+pub fn preferred_engines() -> Vec<Engine> {
+    PREFERRED.read().map(|p| p.clone()).unwrap_or_default()
+}
+
+/// The engine that will serve `op`: the first preferred engine that
+/// supports it and is installed, else the first installed default.
+///
+/// Fails with the first candidate's [`PdfError::MissingTool`] if no engine
+/// is available.
+pub fn select_engine(op: Operation) -> Result<Engine> {
+    let preferred = preferred_engines();
+    let candidates = preferred
+        .iter()
+        .filter(|e| op.engines().contains(e))
+        .chain(op.engines());
+
+    let mut first_error = None;
+    for &engine in candidates {
+        match engine.check_available() {
+            Ok(()) => {
+                tracing::debug!(
+                    operation = op.name(),
+                    engine = engine.name(),
+                    "selected engine"
+                );
+                return Ok(engine);
+            }
+            Err(e @ PdfError::MissingTool { .. }) => {
+                first_error.get_or_insert(e);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Err(first_error
+        .unwrap_or_else(|| PdfError::InvalidArgument(format!("no engine available for {op}"))))
+}
+
+/// Error for an engine chosen for an operation it does not implement.
+pub(crate) fn unsupported(op: Operation, engine: Engine) -> PdfError {
+    PdfError::InvalidArgument(format!("engine {engine} does not support {op}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn engines_parse_by_name_and_alias() -> Result<()> {
+        assert_eq!("gs".parse::<Engine>()?, Engine::Ghostscript);
+        assert_eq!("Native".parse::<Engine>()?, Engine::Native);
+        assert!("acrobat".parse::<Engine>().is_err());
+        for engine in Engine::ALL {
+            assert_eq!(engine.name().parse::<Engine>()?, engine);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+//! File-structure features reported by [`crate::info`]: PDF version,
+//! linearization, cross-reference and object streams, tagging, and
+//! incremental updates.
+
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+use lopdf::{xref::XrefEntry, xref::XrefType, Document, Object};
+
+use crate::Result;
+
+/// See the matching fields of [`crate::PdfInfo`].
+#[allow(clippy::struct_excessive_bools)] // independent file features
+pub(crate) struct Features {
+    pub version: String,
+    pub catalog_version: Option<String>,
+    pub linearized: bool,
+    pub xref_streams: bool,
+    pub object_streams: bool,
+    pub tagged: bool,
+    pub incremental_updates: u32,
+}
+
+pub(crate) fn detect(doc: &Document, path: &Path) -> Result<Features> {
+    let catalog = doc.catalog().ok();
+    let catalog_version = catalog
+        .and_then(|c| c.get(b"Version").ok())
+        .and_then(|v| v.as_name().ok())
+        .map(|v| String::from_utf8_lossy(v).into_owned());
+    let tagged = catalog
+        .and_then(|c| c.get(b"MarkInfo").ok())
+        .and_then(|m| doc.dereference(m).ok())
+        .and_then(|(_, m)| m.as_dict().ok())
+        .and_then(|m| m.get(b"Marked").ok())
+        .and_then(|m| m.as_bool().ok())
+        .unwrap_or(false);
+    let linearized = is_linearized(doc);
+
+    // Each revision ends with `%%EOF`; a linearized file has an extra one
+    // after its first-page section.
+    let revisions = count_eof_markers(path)?;
+    let incremental_updates = revisions.saturating_sub(1 + u32::from(linearized && revisions > 1));
+
+    Ok(Features {
+        version: doc.version.clone(),
+        catalog_version,
+        linearized,
+        xref_streams: matches!(
+            doc.reference_table.cross_reference_type,
+            XrefType::CrossReferenceStream
+        ),
+        object_streams: doc
+            .reference_table
+            .entries
+            .values()
+            .any(|entry| matches!(entry, XrefEntry::Compressed { .. })),
+        tagged,
+        incremental_updates,
+    })
+}
+
+/// Whether the first object in the file is a linearization dictionary.
+pub(crate) fn is_linearized(doc: &Document) -> bool {
+    let first = doc
+        .reference_table
+        .entries
+        .iter()
+        .filter_map(|(&id, entry)| match *entry {
+            XrefEntry::Normal { offset, generation } => Some((offset, (id, generation))),
+            _ => None,
+        })
+        .min();
+    first
+        .and_then(|(_, id)| doc.get_object(id).ok())
+        .and_then(|object| match object {
+            Object::Dictionary(dict) => Some(dict.has(b"Linearized")),
+            _ => None,
+        })
+        .unwrap_or(false)
+}
+
+/// Occurrences of `%%EOF` in the file.
+fn count_eof_markers(path: &Path) -> Result<u32> {
+    let ends = eof_marker_ends(path)?;
+    Ok(u32::try_from(ends.len()).unwrap_or(u32::MAX))
+}
+
+/// Byte offsets just past each `%%EOF` marker in the file, read in chunks.
+pub(crate) fn eof_marker_ends(path: &Path) -> Result<Vec<u64>> {
+    const MARKER: &[u8] = b"%%EOF";
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut buffer = vec![0; 64 << 10];
+    // The last bytes of the previous chunk, so markers split across chunks
+    // are found.
+    let mut carried = 0;
+    // File offset of the start of `buffer`.
+    let mut base = 0_u64;
+    let mut ends = Vec::new();
+    loop {
+        let read = reader.read(&mut buffer[carried..])?;
+        if read == 0 {
+            return Ok(ends);
+        }
+        let filled = carried + read;
+        let window = &buffer[..filled];
+        let mut start = 0;
+        while let Some(at) = window[start..]
+            .windows(MARKER.len())
+            .position(|w| w == MARKER)
+        {
+            start += at + MARKER.len();
+            ends.push(base + start as u64);
+        }
+        carried = (MARKER.len() - 1).min(filled - start);
+        base += (filled - carried) as u64;
+        buffer.copy_within(filled - carried..filled, 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{Dictionary, IncrementalDocument};
+
+    #[test]
+    fn versions_tagging_and_updates_are_detected() -> Result<()> {
+        let mut doc = Document::with_version("1.4");
+        let pages_id = doc.new_object_id();
+        let page = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Page".to_vec())),
+            ("Parent", Object::Reference(pages_id)),
+        ]));
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(Dictionary::from_iter([
+                ("Type", Object::Name(b"Pages".to_vec())),
+                ("Kids", Object::Array(vec![Object::Reference(page)])),
+                ("Count", Object::Integer(1)),
+            ])),
+        );
+        let catalog = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Catalog".to_vec())),
+            ("Pages", Object::Reference(pages_id)),
+            ("Version", Object::Name(b"1.7".to_vec())),
+            (
+                "MarkInfo",
+                Object::Dictionary(Dictionary::from_iter([("Marked", Object::Boolean(true))])),
+            ),
+        ]));
+        doc.trailer.set("Root", catalog);
+        doc.reference_table.cross_reference_type = XrefType::CrossReferenceTable;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("tagged.pdf");
+        doc.save(&path)?;
+
+        let info = crate::info(&path)?;
+        assert_eq!(info.version, "1.4");
+        assert_eq!(info.catalog_version.as_deref(), Some("1.7"));
+        assert!(info.tagged);
+        assert!(!info.linearized);
+        assert!(!info.xref_streams);
+        assert!(!info.object_streams);
+        assert_eq!(info.incremental_updates, 0);
+
+        // Append one revision that changes the page count.
+        let bytes = std::fs::read(&path)?;
+        let loaded = Document::load_mem(&bytes).map_err(|source| crate::PdfError::PdfParse {
+            path: path.clone(),
+            source,
+        })?;
+        let mut update = IncrementalDocument::create_from(bytes, loaded);
+        update.new_document.objects.insert(
+            pages_id,
+            Object::Dictionary(Dictionary::from_iter([
+                ("Type", Object::Name(b"Pages".to_vec())),
+                ("Kids", Object::Array(vec![Object::Reference(page)])),
+                ("Count", Object::Integer(1)),
+            ])),
+        );
+        update.save(&path)?;
+        assert_eq!(crate::info(&path)?.incremental_updates, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn eof_markers_are_counted_across_chunks() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("markers");
+        let mut data = vec![b' '; (64 << 10) - 2];
+        data.extend_from_slice(b"%%EOF\n%%EOF%%EOF");
+        std::fs::write(&path, data)?;
+        assert_eq!(count_eof_markers(&path)?, 3);
+        let first = (64 << 10) + 3;
+        assert_eq!(eof_marker_ends(&path)?, [first, first + 6, first + 11]);
+        Ok(())
+    }
+}
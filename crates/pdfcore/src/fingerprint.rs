@@ -0,0 +1,257 @@
+//! Per-page fingerprints: a digest of what each page shows that stays the
+//! same when a file is rewritten, so that the pages changed between two
+//! revisions can be found without comparing renderings.
+
+use std::{collections::HashMap, path::Path};
+
+use lopdf::{content::Content, Dictionary, Document, Object, ObjectId};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    dedupe::hash_bytes, geometry::effective_page_box, load_document, native::inherited,
+    stamp::effective_resources, PageBox, Result,
+};
+
+/// The fingerprint of one page, as returned by [`fingerprint`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageFingerprint {
+    /// 1-based page number.
+    pub page: u32,
+    /// SHA-256 of the page's canonical form, as lower-case hex.
+    pub hash: String,
+}
+
+/// Fingerprint each page of `input` (pure Rust): a SHA-256 of its content
+/// streams, its resources, its media and crop boxes, and its rotation.
+///
+/// The content is hashed as parsed operators, so whitespace and the way
+/// streams are split or compressed do not count; resources are hashed by
+/// what they contain, with dictionary keys sorted and references followed,
+/// so object numbers do not count either. Equal hashes mean the page is
+/// drawn from the same content and resources.
+pub fn fingerprint(input: impl AsRef<Path>) -> Result<Vec<PageFingerprint>> {
+    let doc = load_document(input.as_ref())?;
+    let mut streams = HashMap::new();
+    Ok(doc
+        .get_pages()
+        .into_iter()
+        .map(|(page, page_id)| PageFingerprint {
+            page,
+            hash: page_hash(&doc, page_id, &mut streams),
+        })
+        .collect())
+}
+
+/// The hash of one page. `streams` caches the digests of stream data, which
+/// pages often share (fonts, images).
+fn page_hash(
+    doc: &Document,
+    page_id: ObjectId,
+    streams: &mut HashMap<ObjectId, [u8; 32]>,
+) -> String {
+    let mut hasher = Sha256::new();
+    let content = doc.get_page_content(page_id).unwrap_or_default();
+    let normalized = Content::decode(&content)
+        .and_then(|ops| ops.encode())
+        .unwrap_or(content);
+    hash_bytes(&mut hasher, b"content", &normalized);
+
+    let mut canon = Canonical {
+        doc,
+        streams,
+        open: Vec::new(),
+    };
+    canon.object(
+        &mut hasher,
+        &Object::Dictionary(effective_resources(doc, page_id)),
+    );
+
+    for which in [PageBox::Media, PageBox::Crop] {
+        let rect = effective_page_box(doc, page_id, which).normalized();
+        for value in [rect.llx, rect.lly, rect.urx, rect.ury] {
+            hasher.update(value.to_be_bytes());
+        }
+    }
+    let rotate = doc
+        .get_dictionary(page_id)
+        .ok()
+        .and_then(|page| inherited(doc, page, b"Rotate"))
+        .and_then(|rotate| rotate.as_i64().ok())
+        .unwrap_or(0)
+        .rem_euclid(360);
+    hasher.update(rotate.to_be_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Hashes objects by what they contain rather than how they are stored.
+struct Canonical<'a> {
+    doc: &'a Document,
+    streams: &'a mut HashMap<ObjectId, [u8; 32]>,
+    /// The references being followed, to cut cycles.
+    open: Vec<ObjectId>,
+}
+
+impl Canonical<'_> {
+    fn object(&mut self, hasher: &mut Sha256, obj: &Object) {
+        match obj {
+            Object::Reference(id) => self.reference(hasher, *id),
+            Object::Array(items) => {
+                hasher.update(b"[");
+                hasher.update((items.len() as u64).to_be_bytes());
+                for item in items {
+                    self.object(hasher, item);
+                }
+            }
+            Object::Dictionary(dict) => self.dictionary(hasher, dict),
+            Object::Stream(stream) => {
+                let mut dict = stream.dict.clone();
+                dict.remove(b"Length");
+                let decoded = if dict.has(b"Filter") {
+                    stream.decompressed_content().ok()
+                } else {
+                    Some(stream.content.clone())
+                };
+                let data = match decoded {
+                    Some(data) => {
+                        dict.remove(b"Filter");
+                        dict.remove(b"DecodeParms");
+                        data
+                    }
+                    None => stream.content.clone(),
+                };
+                self.dictionary(hasher, &dict);
+                hash_bytes(hasher, b"s", &data);
+            }
+            Object::Null => hasher.update(b"n"),
+            Object::Boolean(b) => hasher.update(if *b { b"t" } else { b"f" }),
+            Object::Integer(i) => {
+                hasher.update(b"i");
+                hasher.update(i.to_be_bytes());
+            }
+            Object::Real(r) => {
+                hasher.update(b"r");
+                hasher.update(r.to_be_bytes());
+            }
+            Object::Name(name) => hash_bytes(hasher, b"/", name),
+            Object::String(bytes, _) => hash_bytes(hasher, b"(", bytes),
+        }
+    }
+
+    /// Sorted keys; `/Parent` is left out, as it leads back up a tree.
+    fn dictionary(&mut self, hasher: &mut Sha256, dict: &Dictionary) {
+        let mut entries: Vec<(&Vec<u8>, &Object)> = dict
+            .iter()
+            .filter(|(key, _)| key.as_slice() != b"Parent")
+            .collect();
+        entries.sort_by_key(|(key, _)| *key);
+        hasher.update(b"<");
+        hasher.update((entries.len() as u64).to_be_bytes());
+        for (key, value) in entries {
+            hash_bytes(hasher, b"/", key);
+            self.object(hasher, value);
+        }
+    }
+
+    fn reference(&mut self, hasher: &mut Sha256, id: ObjectId) {
+        if let Some(depth) = self.open.iter().position(|&open| open == id) {
+            // A cycle: hash how far back it leads, not the object number.
+            hasher.update(b"cycle");
+            hasher.update((depth as u64).to_be_bytes());
+            return;
+        }
+        let Ok(target) = self.doc.get_object(id) else {
+            hasher.update(b"missing");
+            return;
+        };
+        if let Object::Stream(_) = target {
+            let digest = if let Some(digest) = self.streams.get(&id) {
+                *digest
+            } else {
+                let mut inner = Sha256::new();
+                self.open.push(id);
+                self.object(&mut inner, target);
+                self.open.pop();
+                let digest = inner.finalize().into();
+                self.streams.insert(id, digest);
+                digest
+            };
+            hasher.update(digest);
+            return;
+        }
+        self.open.push(id);
+        self.object(hasher, target);
+        self.open.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::Stream;
+
+    use super::*;
+
+    fn one_page(content: &[u8], compress: bool, padding: usize) -> Document {
+        let mut doc = Document::with_version("1.5");
+        for _ in 0..padding {
+            doc.add_object(Object::Null);
+        }
+        let font = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type1".to_vec())),
+            ("BaseFont", Object::Name(b"Helvetica".to_vec())),
+        ]));
+        let mut stream = Stream::new(Dictionary::new(), content.to_vec());
+        if compress {
+            let _ = stream.compress();
+        }
+        let contents = doc.add_object(stream);
+        let pages = doc.new_object_id();
+        let page = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Page".to_vec())),
+            ("Parent", Object::Reference(pages)),
+            (
+                "MediaBox",
+                Object::Array(vec![0.into(), 0.into(), 612.into(), 792.into()]),
+            ),
+            ("Contents", Object::Reference(contents)),
+            (
+                "Resources",
+                Object::Dictionary(Dictionary::from_iter([(
+                    "Font",
+                    Object::Dictionary(Dictionary::from_iter([("F1", Object::Reference(font))])),
+                )])),
+            ),
+        ]));
+        doc.objects.insert(
+            pages,
+            Object::Dictionary(Dictionary::from_iter([
+                ("Type", Object::Name(b"Pages".to_vec())),
+                ("Kids", Object::Array(vec![Object::Reference(page)])),
+                ("Count", Object::Integer(1)),
+            ])),
+        );
+        let catalog = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Catalog".to_vec())),
+            ("Pages", Object::Reference(pages)),
+        ]));
+        doc.trailer.set("Root", Object::Reference(catalog));
+        doc
+    }
+
+    fn hash(doc: &Document) -> String {
+        let (_, &page_id) = doc.get_pages().iter().next().unwrap_or((&0, &(0, 0)));
+        page_hash(doc, page_id, &mut HashMap::new())
+    }
+
+    #[test]
+    fn storage_does_not_change_the_fingerprint() {
+        let content = b"BT /F1 12 Tf 72 720 Td (Hello) Tj ET ".repeat(4);
+        let spaced = b"BT\n/F1   12 Tf\n72 720 Td\n(Hello) Tj\nET\n".repeat(4);
+        let plain = hash(&one_page(&content, false, 0));
+        assert_eq!(plain, hash(&one_page(&spaced, true, 7)));
+        assert_ne!(
+            plain,
+            hash(&one_page(b"BT /F1 12 Tf 72 720 Td (Hellp) Tj ET", false, 0))
+        );
+    }
+}
@@ -0,0 +1,274 @@
+//! Font inventory gathered purely with [`lopdf`] by walking page resources.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use crate::{
+    gs_pdfwrite, gs_pdfwrite_command, load_document, load_for_reading,
+    plan::{self, Plan},
+    tempspace::TempSpace,
+    validate_input_file, PdfError, Result, Tool,
+};
+
+/// Ghostscript `pdfwrite` arguments that embed (and subset) every font.
+pub(crate) const EMBED_ALL_FONTS_ARGS: [&str; 2] = ["-dEmbedAllFonts=true", "-dSubsetFonts=true"];
+
+/// A font referenced from page resources.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FontInfo {
+    /// `/BaseFont` name, including any subset prefix (e.g. `ABCDEF+Arial`).
+    pub name: String,
+    /// Font `/Subtype` (e.g. `Type1`, `TrueType`, `Type0`, `Type3`).
+    pub font_type: String,
+    /// Whether the font program is embedded in the file.
+    pub embedded: bool,
+    /// Whether the font is a subset (name carries a `XXXXXX+` tag).
+    pub subset: bool,
+    /// 1-based pages on which the font is used.
+    pub pages: Vec<u32>,
+}
+
+/// List fonts used by a PDF (pure Rust; no external tools).
+pub fn fonts(path: impl AsRef<Path>) -> Result<Vec<FontInfo>> {
+    let doc = load_for_reading(path.as_ref())?;
+    Ok(collect_fonts(&doc))
+}
+
+/// Re-distill a PDF through Ghostscript with all fonts embedded and subset,
+/// then audit the result.
+///
+/// Returns the fonts of the output. Fails with [`PdfError::FontsNotEmbedded`]
+/// (and writes nothing) if any font is still not embedded, e.g. because no
+/// substitute font program was available.
+pub fn embed_fonts(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<Vec<FontInfo>> {
+    let input = input.as_ref();
+    validate_input_file(input)?;
+
+    let scratch = TempSpace::new("embed-fonts")?;
+    let distilled = scratch.file("embedded.pdf");
+    gs_pdfwrite(input, &distilled, None, &EMBED_ALL_FONTS_ARGS)?;
+
+    let fonts = collect_fonts(&load_document(&distilled)?);
+    let missing: Vec<String> = fonts
+        .iter()
+        .filter(|f| !f.embedded)
+        .map(|f| f.name.clone())
+        .collect();
+    if !missing.is_empty() {
+        return Err(PdfError::FontsNotEmbedded { fonts: missing });
+    }
+
+    std::fs::copy(&distilled, output.as_ref())?;
+    Ok(fonts)
+}
+
+/// What [`embed_fonts`] would run.
+pub fn embed_fonts_plan(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<Plan> {
+    let input = input.as_ref();
+    validate_input_file(input)?;
+    let distilled = plan::temp_path("embedded.pdf");
+    let cmd = gs_pdfwrite_command(input, &distilled, None, &EMBED_ALL_FONTS_ARGS)?;
+    Ok(Plan::new()
+        .run(Tool::Ghostscript, &cmd)
+        .native("check that every font is embedded")
+        .write(output.as_ref()))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum FontKey {
+    Object(lopdf::ObjectId),
+    Inline(u32, Vec<u8>),
+}
+
+/// Collect fonts from every page's resources, including resources of nested
+/// form `XObject`s. Fonts are de-duplicated by object and sorted by name.
+pub(crate) fn collect_fonts(doc: &lopdf::Document) -> Vec<FontInfo> {
+    let mut found: BTreeMap<FontKey, FontInfo> = BTreeMap::new();
+
+    for (page_no, page_id) in doc.get_pages() {
+        let mut resources = Vec::new();
+        if let Ok((inline, ids)) = doc.get_page_resources(page_id) {
+            resources.extend(inline);
+            resources.extend(ids.into_iter().filter_map(|id| doc.get_dictionary(id).ok()));
+        }
+
+        let mut visited = BTreeSet::new();
+        while let Some(res) = resources.pop() {
+            if let Some(font_dict) = resolve_dict(doc, res.get(b"Font").ok()) {
+                for (res_name, value) in font_dict {
+                    let key = match value {
+                        lopdf::Object::Reference(id) => FontKey::Object(*id),
+                        _ => FontKey::Inline(page_no, res_name.clone()),
+                    };
+                    if let Some(info) = found.get_mut(&key) {
+                        if !info.pages.contains(&page_no) {
+                            info.pages.push(page_no);
+                        }
+                        continue;
+                    }
+                    if let Some(font) = resolve_dict(doc, Some(value)) {
+                        let mut info = describe_font(doc, font);
+                        info.pages.push(page_no);
+                        found.insert(key, info);
+                    }
+                }
+            }
+
+            // Descend into form XObjects, which carry their own resources.
+            if let Some(xobjects) = resolve_dict(doc, res.get(b"XObject").ok()) {
+                for (_, value) in xobjects {
+                    let Ok(id) = value.as_reference() else {
+                        continue;
+                    };
+                    if !visited.insert(id) {
+                        continue;
+                    }
+                    // A stream, or just its dictionary in low-memory mode.
+                    let Some(xobject) = resolve_dict(doc, Some(value)) else {
+                        continue;
+                    };
+                    if let Some(nested) = resolve_dict(doc, xobject.get(b"Resources").ok()) {
+                        resources.push(nested);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut fonts: Vec<FontInfo> = found.into_values().collect();
+    fonts.sort_by(|a, b| a.name.cmp(&b.name).then(a.pages.cmp(&b.pages)));
+    fonts
+}
+
+fn describe_font(doc: &lopdf::Document, font: &lopdf::Dictionary) -> FontInfo {
+    let name = font
+        .get(b"BaseFont")
+        .and_then(lopdf::Object::as_name)
+        .map_or_else(
+            |_| "(unnamed)".to_string(),
+            |n| String::from_utf8_lossy(n).to_string(),
+        );
+    let font_type = font
+        .get(b"Subtype")
+        .and_then(lopdf::Object::as_name)
+        .map_or_else(
+            |_| "Unknown".to_string(),
+            |n| String::from_utf8_lossy(n).to_string(),
+        );
+
+    // Type3 glyphs are content streams inside the file; Type0 fonts keep
+    // their descriptor on the descendant CIDFont.
+    let embedded = match font_type.as_str() {
+        "Type3" => true,
+        "Type0" => font
+            .get(b"DescendantFonts")
+            .ok()
+            .and_then(|d| doc.dereference(d).ok())
+            .and_then(|(_, d)| d.as_array().ok())
+            .and_then(|arr| arr.first())
+            .and_then(|d| resolve_dict(doc, Some(d)))
+            .is_some_and(|d| has_font_file(doc, d)),
+        _ => has_font_file(doc, font),
+    };
+
+    FontInfo {
+        subset: is_subset_name(&name),
+        name,
+        font_type,
+        embedded,
+        pages: Vec::new(),
+    }
+}
+
+fn has_font_file(doc: &lopdf::Document, font: &lopdf::Dictionary) -> bool {
+    resolve_dict(doc, font.get(b"FontDescriptor").ok()).is_some_and(|desc| {
+        [b"FontFile".as_slice(), b"FontFile2", b"FontFile3"]
+            .iter()
+            .any(|k| desc.has(k))
+    })
+}
+
+/// Subset fonts are named with six uppercase letters and a `+`, e.g. `EOODIA+Arial`.
+fn is_subset_name(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    bytes.len() > 7 && bytes[6] == b'+' && bytes[..6].iter().all(u8::is_ascii_uppercase)
+}
+
+/// Follow a (possibly indirect) object to a dictionary.
+pub(crate) fn resolve_dict<'a>(
+    doc: &'a lopdf::Document,
+    obj: Option<&'a lopdf::Object>,
+) -> Option<&'a lopdf::Dictionary> {
+    let (_, obj) = doc.dereference(obj?).ok()?;
+    match obj {
+        lopdf::Object::Dictionary(d) => Some(d),
+        lopdf::Object::Stream(s) => Some(&s.dict),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collect_fonts_reports_embedding_and_subset() {
+        let mut doc = lopdf::Document::with_version("1.4");
+        let pages_id = doc.new_object_id();
+
+        let file_id = doc.add_object(lopdf::Stream::new(lopdf::Dictionary::new(), vec![0; 4]));
+        let desc_id = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"FontDescriptor".to_vec())),
+            ("FontFile2", lopdf::Object::Reference(file_id)),
+        ]));
+        let embedded_id = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"Font".to_vec())),
+            ("Subtype", lopdf::Object::Name(b"TrueType".to_vec())),
+            ("BaseFont", lopdf::Object::Name(b"ABCDEF+Arial".to_vec())),
+            ("FontDescriptor", lopdf::Object::Reference(desc_id)),
+        ]));
+        let plain_id = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"Font".to_vec())),
+            ("Subtype", lopdf::Object::Name(b"Type1".to_vec())),
+            ("BaseFont", lopdf::Object::Name(b"Helvetica".to_vec())),
+        ]));
+
+        let resources = lopdf::Dictionary::from_iter([(
+            "Font",
+            lopdf::Object::Dictionary(lopdf::Dictionary::from_iter([
+                ("F1", lopdf::Object::Reference(embedded_id)),
+                ("F2", lopdf::Object::Reference(plain_id)),
+            ])),
+        )]);
+        let single_page_id = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"Page".to_vec())),
+            ("Parent", lopdf::Object::Reference(pages_id)),
+            ("Resources", lopdf::Object::Dictionary(resources)),
+        ]));
+        doc.objects.insert(
+            pages_id,
+            lopdf::Object::Dictionary(lopdf::Dictionary::from_iter([
+                ("Type", lopdf::Object::Name(b"Pages".to_vec())),
+                ("Kids", lopdf::Object::Array(vec![single_page_id.into()])),
+                ("Count", lopdf::Object::Integer(1)),
+            ])),
+        );
+        let catalog_id = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"Catalog".to_vec())),
+            ("Pages", lopdf::Object::Reference(pages_id)),
+        ]));
+        doc.trailer.set("Root", catalog_id);
+
+        let fonts = collect_fonts(&doc);
+        assert_eq!(fonts.len(), 2);
+        assert_eq!(fonts[0].name, "ABCDEF+Arial");
+        assert!(fonts[0].embedded);
+        assert!(fonts[0].subset);
+        assert_eq!(fonts[1].name, "Helvetica");
+        assert_eq!(fonts[1].font_type, "Type1");
+        assert!(!fonts[1].embedded);
+        assert_eq!(fonts[1].pages, vec![1]);
+    }
+}
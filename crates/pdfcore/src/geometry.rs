@@ -0,0 +1,471 @@
+//! Page geometry primitives: rectangles, paper sizes, and page boxes.
+//!
+//! All measurements are in PDF points (1/72 inch).
+
+use std::fmt;
+
+use crate::{PdfError, Result};
+
+/// Points per millimetre.
+pub const POINTS_PER_MM: f32 = 72.0 / 25.4;
+
+/// A rectangle in default user space, `[llx lly urx ury]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    /// Lower-left x.
+    pub llx: f32,
+    /// Lower-left y.
+    pub lly: f32,
+    /// Upper-right x.
+    pub urx: f32,
+    /// Upper-right y.
+    pub ury: f32,
+}
+
+impl Rect {
+    /// Rectangle from the origin with the given size.
+    #[must_use]
+    pub fn from_size(width: f32, height: f32) -> Self {
+        Self {
+            llx: 0.0,
+            lly: 0.0,
+            urx: width,
+            ury: height,
+        }
+    }
+
+    /// Width in points.
+    #[must_use]
+    pub fn width(&self) -> f32 {
+        (self.urx - self.llx).abs()
+    }
+
+    /// Height in points.
+    #[must_use]
+    pub fn height(&self) -> f32 {
+        (self.ury - self.lly).abs()
+    }
+
+    /// Grow (or shrink, if negative) the rectangle by `amount` on every side.
+    #[must_use]
+    pub fn expand(&self, amount: f32) -> Self {
+        Self {
+            llx: self.llx - amount,
+            lly: self.lly - amount,
+            urx: self.urx + amount,
+            ury: self.ury + amount,
+        }
+    }
+
+    /// Normalize so that `llx <= urx` and `lly <= ury`.
+    #[must_use]
+    pub fn normalized(&self) -> Self {
+        Self {
+            llx: self.llx.min(self.urx),
+            lly: self.lly.min(self.ury),
+            urx: self.llx.max(self.urx),
+            ury: self.lly.max(self.ury),
+        }
+    }
+
+    pub(crate) fn from_object(doc: &lopdf::Document, obj: &lopdf::Object) -> Option<Self> {
+        let (_, obj) = doc.dereference(obj).ok()?;
+        let arr = obj.as_array().ok()?;
+        if arr.len() != 4 {
+            return None;
+        }
+        let mut v = [0.0_f32; 4];
+        for (slot, item) in v.iter_mut().zip(arr) {
+            let (_, item) = doc.dereference(item).ok()?;
+            *slot = item.as_float().ok()?;
+        }
+        Some(
+            Self {
+                llx: v[0],
+                lly: v[1],
+                urx: v[2],
+                ury: v[3],
+            }
+            .normalized(),
+        )
+    }
+
+    pub(crate) fn to_object(self) -> lopdf::Object {
+        lopdf::Object::Array(vec![
+            self.llx.into(),
+            self.lly.into(),
+            self.urx.into(),
+            self.ury.into(),
+        ])
+    }
+}
+
+impl Rect {
+    /// `true` if the rectangles overlap (touching edges do not count).
+    #[must_use]
+    pub fn intersects(&self, other: &Self) -> bool {
+        let (a, b) = (self.normalized(), other.normalized());
+        a.llx < b.urx && b.llx < a.urx && a.lly < b.ury && b.lly < a.ury
+    }
+
+    /// `true` if `other` lies inside the rectangle, allowing `tolerance`
+    /// points of overhang on each side.
+    #[must_use]
+    pub fn contains(&self, other: &Self, tolerance: f32) -> bool {
+        let (a, b) = (self.normalized(), other.normalized());
+        b.llx >= a.llx - tolerance
+            && b.lly >= a.lly - tolerance
+            && b.urx <= a.urx + tolerance
+            && b.ury <= a.ury + tolerance
+    }
+
+    /// Smallest rectangle containing both.
+    #[must_use]
+    pub fn union(&self, other: &Self) -> Self {
+        let (a, b) = (self.normalized(), other.normalized());
+        Self {
+            llx: a.llx.min(b.llx),
+            lly: a.lly.min(b.lly),
+            urx: a.urx.max(b.urx),
+            ury: a.ury.max(b.ury),
+        }
+    }
+}
+
+impl fmt::Display for Rect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{},{},{}", self.llx, self.lly, self.urx, self.ury)
+    }
+}
+
+/// A PDF transformation matrix `[a b c d e f]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Matrix(pub [f32; 6]);
+
+// The single-letter names are the PDF specification's own.
+#[allow(clippy::many_single_char_names)]
+impl Matrix {
+    pub(crate) const IDENTITY: Self = Self([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+    pub(crate) fn translate(tx: f32, ty: f32) -> Self {
+        Self([1.0, 0.0, 0.0, 1.0, tx, ty])
+    }
+
+    /// Read a six-number array, e.g. a form `/Matrix` or `cm` operands.
+    pub(crate) fn from_operands(values: &[lopdf::Object]) -> Option<Self> {
+        if values.len() != 6 {
+            return None;
+        }
+        let mut m = [0.0_f32; 6];
+        for (slot, value) in m.iter_mut().zip(values) {
+            *slot = value.as_float().ok()?;
+        }
+        Some(Self(m))
+    }
+
+    /// `self` applied first, then `other` (PDF's `self × other`).
+    pub(crate) fn then(&self, other: &Self) -> Self {
+        let [a, b, c, d, e, f] = self.0;
+        let [a2, b2, c2, d2, e2, f2] = other.0;
+        Self([
+            a * a2 + b * c2,
+            a * b2 + b * d2,
+            c * a2 + d * c2,
+            c * b2 + d * d2,
+            e * a2 + f * c2 + e2,
+            e * b2 + f * d2 + f2,
+        ])
+    }
+
+    pub(crate) fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        let [a, b, c, d, e, f] = self.0;
+        (a * x + c * y + e, b * x + d * y + f)
+    }
+
+    /// Bounding box of a transformed rectangle.
+    pub(crate) fn apply_rect(&self, rect: &Rect) -> Rect {
+        let corners = [
+            self.apply(rect.llx, rect.lly),
+            self.apply(rect.urx, rect.lly),
+            self.apply(rect.llx, rect.ury),
+            self.apply(rect.urx, rect.ury),
+        ];
+        corners.iter().fold(
+            Rect {
+                llx: f32::INFINITY,
+                lly: f32::INFINITY,
+                urx: f32::NEG_INFINITY,
+                ury: f32::NEG_INFINITY,
+            },
+            |acc, &(x, y)| Rect {
+                llx: acc.llx.min(x),
+                lly: acc.lly.min(y),
+                urx: acc.urx.max(x),
+                ury: acc.ury.max(y),
+            },
+        )
+    }
+
+    /// Inverse matrix, or `None` if the matrix is singular.
+    pub(crate) fn invert(&self) -> Option<Self> {
+        let [a, b, c, d, e, f] = self.0;
+        let det = a * d - b * c;
+        if det.abs() < f32::EPSILON {
+            return None;
+        }
+        let (ia, ib, ic, id) = (d / det, -b / det, -c / det, a / det);
+        Some(Self([
+            ia,
+            ib,
+            ic,
+            id,
+            -(e * ia + f * ic),
+            -(e * ib + f * id),
+        ]))
+    }
+}
+
+/// A named paper size (portrait orientation).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaperSize {
+    /// Lower-case name, e.g. `a4`.
+    pub name: &'static str,
+    /// Width in points.
+    pub width: f32,
+    /// Height in points.
+    pub height: f32,
+}
+
+impl PaperSize {
+    /// ISO A3.
+    pub const A3: Self = Self::new("a3", 842.0, 1191.0);
+    /// ISO A4.
+    pub const A4: Self = Self::new("a4", 595.0, 842.0);
+    /// ISO A5.
+    pub const A5: Self = Self::new("a5", 420.0, 595.0);
+    /// US Letter.
+    pub const LETTER: Self = Self::new("letter", 612.0, 792.0);
+    /// US Legal.
+    pub const LEGAL: Self = Self::new("legal", 612.0, 1008.0);
+    /// US Tabloid (ledger, portrait).
+    pub const TABLOID: Self = Self::new("tabloid", 792.0, 1224.0);
+
+    /// All known sizes.
+    pub const ALL: [Self; 6] = [
+        Self::A3,
+        Self::A4,
+        Self::A5,
+        Self::LETTER,
+        Self::LEGAL,
+        Self::TABLOID,
+    ];
+
+    const fn new(name: &'static str, width: f32, height: f32) -> Self {
+        Self {
+            name,
+            width,
+            height,
+        }
+    }
+
+    /// Look up a paper size by (case-insensitive) name.
+    pub fn from_name(name: &str) -> Result<Self> {
+        let wanted = name.trim().to_ascii_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|p| p.name == wanted)
+            .ok_or_else(|| {
+                let known = Self::ALL.map(|p| p.name).join(", ");
+                PdfError::InvalidArgument(format!("unknown paper size {name:?} (known: {known})"))
+            })
+    }
+
+    /// The matching size for `rect` in either orientation, within `tolerance` points.
+    #[must_use]
+    pub fn matching(rect: &Rect, tolerance: f32) -> Option<Self> {
+        let (w, h) = (rect.width(), rect.height());
+        Self::ALL.into_iter().find(|p| {
+            let portrait = (p.width - w).abs() <= tolerance && (p.height - h).abs() <= tolerance;
+            let landscape = (p.width - h).abs() <= tolerance && (p.height - w).abs() <= tolerance;
+            portrait || landscape
+        })
+    }
+}
+
+impl fmt::Display for PaperSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+/// One of the five PDF page boundary boxes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PageBox {
+    /// `/MediaBox`: the physical medium.
+    Media,
+    /// `/CropBox`: the visible region (defaults to the media box).
+    Crop,
+    /// `/BleedBox`: the region to clip to in production (defaults to the crop box).
+    Bleed,
+    /// `/TrimBox`: the intended finished page (defaults to the crop box).
+    Trim,
+    /// `/ArtBox`: the meaningful content (defaults to the crop box).
+    Art,
+}
+
+impl PageBox {
+    /// All boxes, media first.
+    pub const ALL: [Self; 5] = [Self::Media, Self::Crop, Self::Bleed, Self::Trim, Self::Art];
+
+    /// The dictionary key, e.g. `TrimBox`.
+    #[must_use]
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::Media => "MediaBox",
+            Self::Crop => "CropBox",
+            Self::Bleed => "BleedBox",
+            Self::Trim => "TrimBox",
+            Self::Art => "ArtBox",
+        }
+    }
+
+    /// Lower-case short name, e.g. `trim`.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Media => "media",
+            Self::Crop => "crop",
+            Self::Bleed => "bleed",
+            Self::Trim => "trim",
+            Self::Art => "art",
+        }
+    }
+}
+
+impl fmt::Display for PageBox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Read a box set explicitly on the page or inherited from the page tree
+/// (only `MediaBox` and `CropBox` are inheritable).
+pub(crate) fn explicit_page_box(
+    doc: &lopdf::Document,
+    page_id: lopdf::ObjectId,
+    which: PageBox,
+) -> Option<Rect> {
+    let key = which.key().as_bytes();
+    let inheritable = matches!(which, PageBox::Media | PageBox::Crop);
+
+    let mut node = doc.get_dictionary(page_id).ok();
+    let mut depth = 0;
+    while let Some(dict) = node {
+        if let Some(rect) = dict.get(key).ok().and_then(|o| Rect::from_object(doc, o)) {
+            return Some(rect);
+        }
+        if !inheritable || depth > 32 {
+            return None;
+        }
+        depth += 1;
+        node = dict
+            .get(b"Parent")
+            .and_then(lopdf::Object::as_reference)
+            .and_then(|id| doc.get_dictionary(id))
+            .ok();
+    }
+    None
+}
+
+/// Effective box for a page, applying the PDF defaulting rules
+/// (missing boxes fall back to the crop box, which falls back to the media box;
+/// a missing media box is treated as US Letter).
+pub(crate) fn effective_page_box(
+    doc: &lopdf::Document,
+    page_id: lopdf::ObjectId,
+    which: PageBox,
+) -> Rect {
+    let media = || {
+        explicit_page_box(doc, page_id, PageBox::Media)
+            .unwrap_or_else(|| Rect::from_size(PaperSize::LETTER.width, PaperSize::LETTER.height))
+    };
+    let crop = || explicit_page_box(doc, page_id, PageBox::Crop).unwrap_or_else(media);
+    match which {
+        PageBox::Media => media(),
+        PageBox::Crop => crop(),
+        other => explicit_page_box(doc, page_id, other).unwrap_or_else(crop),
+    }
+}
+
+/// Set a box directly on the page dictionary.
+pub(crate) fn set_page_box(
+    doc: &mut lopdf::Document,
+    page_id: lopdf::ObjectId,
+    which: PageBox,
+    rect: Rect,
+) -> Result<()> {
+    let page = doc
+        .get_dictionary_mut(page_id)
+        .map_err(|_| PdfError::InvalidArgument(format!("page object {page_id:?} not found")))?;
+    page.set(which.key(), rect.normalized().to_object());
+    Ok(())
+}
+
+/// Add bleed around the finished size of a page: its `TrimBox`, or its
+/// crop box if it has none, which becomes the `TrimBox`. The `BleedBox` is
+/// `bleed` points larger on every side, and the media and crop boxes
+/// `slug` points larger (room for printer's marks; at least `bleed`).
+///
+/// Returns the trim box. Applying this twice does not grow the page twice.
+pub(crate) fn set_bleed_boxes(
+    doc: &mut lopdf::Document,
+    page_id: lopdf::ObjectId,
+    bleed: f32,
+    slug: f32,
+) -> Result<Rect> {
+    let trim = effective_page_box(doc, page_id, PageBox::Trim).normalized();
+    let media = trim.expand(slug.max(bleed));
+    set_page_box(doc, page_id, PageBox::Media, media)?;
+    set_page_box(doc, page_id, PageBox::Crop, media)?;
+    set_page_box(doc, page_id, PageBox::Bleed, trim.expand(bleed))?;
+    set_page_box(doc, page_id, PageBox::Trim, trim)?;
+    Ok(trim)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paper_size_lookup_and_matching() -> Result<()> {
+        assert_eq!(PaperSize::from_name("A4")?, PaperSize::A4);
+        assert!(PaperSize::from_name("b7").is_err());
+
+        let landscape_letter = Rect::from_size(792.0, 612.0);
+        assert_eq!(
+            PaperSize::matching(&landscape_letter, 1.0),
+            Some(PaperSize::LETTER)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn matrix_composition_and_inverse() {
+        let m = Matrix::translate(10.0, 20.0).then(&Matrix([2.0, 0.0, 0.0, 2.0, 0.0, 0.0]));
+        assert_eq!(m.apply(1.0, 1.0), (22.0, 42.0));
+        let back = m.invert().map(|inv| inv.apply(22.0, 42.0));
+        assert_eq!(back, Some((1.0, 1.0)));
+
+        let r = Rect::from_size(10.0, 10.0);
+        assert!(r.intersects(&Rect::from_size(5.0, 5.0)));
+        assert!(!r.intersects(&Matrix::translate(10.0, 0.0).apply_rect(&r)));
+    }
+
+    #[test]
+    fn rect_expand_grows_every_side() {
+        let r = Rect::from_size(100.0, 50.0).expand(5.0);
+        assert!((r.width() - 110.0).abs() < f32::EPSILON);
+        assert!((r.height() - 60.0).abs() < f32::EPSILON);
+        assert!((r.llx + 5.0).abs() < f32::EPSILON);
+    }
+}
@@ -0,0 +1,342 @@
+//! Headers and footers: lines of text stamped at the edges of every page,
+//! built from templates with variables such as `{n}/{total}` and `{title}`.
+
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    load_document,
+    metadata::info_entry,
+    save_document,
+    stamp::{self, StampPosition, TextStamp},
+    validate_input_file, PdfError, Plan, Result,
+};
+
+/// The variables a header or footer template may use.
+pub const HEADER_FOOTER_VARIABLES: &[&str] = &[
+    "n", "total", "title", "author", "subject", "keywords", "file", "date",
+];
+
+/// Options for [`header_footer`]. Each slot is a template: text with
+/// variables in braces (see [`HEADER_FOOTER_VARIABLES`]), and `{{` and `}}`
+/// for literal braces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeaderFooterOptions {
+    pub header_left: Option<String>,
+    pub header_center: Option<String>,
+    pub header_right: Option<String>,
+    pub footer_left: Option<String>,
+    pub footer_center: Option<String>,
+    pub footer_right: Option<String>,
+    /// Font size in points.
+    pub font_size: f32,
+    /// Distance from the edges of the page, in points.
+    pub margin: f32,
+    /// The value of `{date}`; today's date (UTC, `YYYY-MM-DD`) if not given.
+    pub date: Option<String>,
+}
+
+impl Default for HeaderFooterOptions {
+    fn default() -> Self {
+        Self {
+            header_left: None,
+            header_center: None,
+            header_right: None,
+            footer_left: None,
+            footer_center: None,
+            footer_right: None,
+            font_size: 9.0,
+            margin: 18.0,
+            date: None,
+        }
+    }
+}
+
+impl HeaderFooterOptions {
+    /// The slots that have a template, with their positions and names.
+    fn slots(&self) -> impl Iterator<Item = (StampPosition, &'static str, &str)> {
+        [
+            (StampPosition::TopLeft, "header-left", &self.header_left),
+            (
+                StampPosition::TopCenter,
+                "header-center",
+                &self.header_center,
+            ),
+            (StampPosition::TopRight, "header-right", &self.header_right),
+            (StampPosition::BottomLeft, "footer-left", &self.footer_left),
+            (
+                StampPosition::BottomCenter,
+                "footer-center",
+                &self.footer_center,
+            ),
+            (
+                StampPosition::BottomRight,
+                "footer-right",
+                &self.footer_right,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(position, name, template)| Some((position, name, template.as_deref()?)))
+    }
+}
+
+/// Stamp headers and footers on every page of `input` (pure Rust).
+///
+/// `{title}`, `{author}`, `{subject}`, and `{keywords}` come from the
+/// document information dictionary and are empty when it lacks them.
+pub fn header_footer(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &HeaderFooterOptions,
+) -> Result<()> {
+    let input = input.as_ref();
+    let slots = parse_slots(options)?;
+    let mut doc = load_document(input)?;
+    let pages = doc.get_pages();
+    let mut values = Values {
+        page: 0,
+        total: pages.len(),
+        title: info_entry(&doc, "Title").unwrap_or_default(),
+        author: info_entry(&doc, "Author").unwrap_or_default(),
+        subject: info_entry(&doc, "Subject").unwrap_or_default(),
+        keywords: info_entry(&doc, "Keywords").unwrap_or_default(),
+        file: input
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        date: options.date.clone().unwrap_or_else(today),
+    };
+    let font = stamp::add_stamp_font(&mut doc);
+    for (page, page_id) in (1..=pages.len()).zip(pages.into_values()) {
+        values.page = page;
+        for (position, name, pieces) in &slots {
+            let text = render(pieces, &values);
+            if text.trim().is_empty() {
+                continue;
+            }
+            let stamp = TextStamp {
+                text: stamp::stamp_text_bytes(&text, name)?,
+                size: options.font_size,
+                margin: options.margin,
+                position: *position,
+            };
+            stamp::stamp_text(&mut doc, page_id, font, &stamp)?;
+        }
+    }
+    save_document(&mut doc, output.as_ref())
+}
+
+/// What [`header_footer`] would do.
+pub fn header_footer_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &HeaderFooterOptions,
+) -> Result<Plan> {
+    let input = input.as_ref();
+    validate_input_file(input)?;
+    parse_slots(options)?;
+    let mut plan = Plan::new();
+    for (_, name, template) in options.slots() {
+        plan = plan.native(format!("stamp {name} {template:?} on every page"));
+    }
+    Ok(plan.write(output.as_ref()))
+}
+
+/// One part of a parsed template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    Variable(Variable),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Variable {
+    Page,
+    Total,
+    Title,
+    Author,
+    Subject,
+    Keywords,
+    File,
+    Date,
+}
+
+/// The values of the variables on one page.
+struct Values {
+    page: usize,
+    total: usize,
+    title: String,
+    author: String,
+    subject: String,
+    keywords: String,
+    file: String,
+    date: String,
+}
+
+type Slot = (StampPosition, &'static str, Vec<Piece>);
+
+/// The templates of `options`, parsed, after checking the options.
+fn parse_slots(options: &HeaderFooterOptions) -> Result<Vec<Slot>> {
+    if options.font_size <= 0.0 || options.margin < 0.0 {
+        return Err(PdfError::InvalidArgument(
+            "font size must be positive and margin not negative".to_string(),
+        ));
+    }
+    let slots = options
+        .slots()
+        .map(|(position, name, template)| Ok((position, name, parse_template(template, name)?)))
+        .collect::<Result<Vec<_>>>()?;
+    if slots.is_empty() {
+        return Err(PdfError::InvalidArgument(
+            "no header or footer given".to_string(),
+        ));
+    }
+    Ok(slots)
+}
+
+fn parse_template(template: &str, name: &str) -> Result<Vec<Piece>> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut variable = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => variable.push(c),
+                        None => {
+                            return Err(PdfError::InvalidArgument(format!(
+                            "{name}: unclosed {{ in {template:?} (write {{{{ for a literal brace)"
+                        )))
+                        }
+                    }
+                }
+                let variable = match variable.as_str() {
+                    "n" => Variable::Page,
+                    "total" => Variable::Total,
+                    "title" => Variable::Title,
+                    "author" => Variable::Author,
+                    "subject" => Variable::Subject,
+                    "keywords" => Variable::Keywords,
+                    "file" => Variable::File,
+                    "date" => Variable::Date,
+                    _ => {
+                        return Err(PdfError::InvalidArgument(format!(
+                            "{name}: unknown variable {{{variable}}} (expected one of: {})",
+                            HEADER_FOOTER_VARIABLES.join(", ")
+                        )))
+                    }
+                };
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(Piece::Variable(variable));
+            }
+            '}' => {
+                return Err(PdfError::InvalidArgument(format!(
+                    "{name}: unmatched }} in {template:?} (write }}}} for a literal brace)"
+                )))
+            }
+            _ => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Ok(pieces)
+}
+
+fn render(pieces: &[Piece], values: &Values) -> String {
+    let mut out = String::new();
+    for piece in pieces {
+        match piece {
+            Piece::Text(text) => out.push_str(text),
+            Piece::Variable(Variable::Page) => out.push_str(&values.page.to_string()),
+            Piece::Variable(Variable::Total) => out.push_str(&values.total.to_string()),
+            Piece::Variable(Variable::Title) => out.push_str(&values.title),
+            Piece::Variable(Variable::Author) => out.push_str(&values.author),
+            Piece::Variable(Variable::Subject) => out.push_str(&values.subject),
+            Piece::Variable(Variable::Keywords) => out.push_str(&values.keywords),
+            Piece::Variable(Variable::File) => out.push_str(&values.file),
+            Piece::Variable(Variable::Date) => out.push_str(&values.date),
+        }
+    }
+    out
+}
+
+/// Today's date in UTC, as `YYYY-MM-DD`.
+fn today() -> String {
+    let (year, month, day) = today_civil();
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Today's date in UTC, as (year, month, day).
+pub(crate) fn today_civil() -> (u64, u64, u64) {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    civil_date(seconds / 86_400)
+}
+
+/// The calendar date `days` days after 1970-01-01 (Howard Hinnant's
+/// `civil_from_days`, for dates after the epoch).
+pub(crate) fn civil_date(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn templates_substitute_variables() -> Result<()> {
+        let values = Values {
+            page: 3,
+            total: 12,
+            title: "Annual Report".to_string(),
+            author: String::new(),
+            subject: String::new(),
+            keywords: String::new(),
+            file: "report.pdf".to_string(),
+            date: "2024-05-01".to_string(),
+        };
+        let pieces = parse_template("{date} \u{2022} {n}/{total} {{draft}}", "footer-right")?;
+        assert_eq!(render(&pieces, &values), "2024-05-01 \u{2022} 3/12 {draft}");
+        let pieces = parse_template("{title} ({file}){author}", "header-left")?;
+        assert_eq!(render(&pieces, &values), "Annual Report (report.pdf)");
+
+        assert!(parse_template("{page}", "footer-right").is_err());
+        assert!(parse_template("a } b", "footer-right").is_err());
+        assert!(parse_template("{n", "footer-right").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn dates_are_counted_from_the_epoch() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(11_016), (2000, 2, 29));
+        assert_eq!(civil_date(19_844), (2024, 5, 1));
+    }
+}
@@ -0,0 +1,267 @@
+//! Converting HTML to PDF with an external browser engine: `wkhtmltopdf`,
+//! or headless Chromium or Chrome.
+//!
+//! `wkhtmltopdf` takes the page size and margins as arguments. Chrome has
+//! no such arguments, so it is given a copy of the page with an `@page`
+//! rule that sets them, and a `<base>` so that relative links to images
+//! and style sheets still resolve next to the original.
+
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    command_to_string, engine, find_tool, plan, run_tool_writing, select_engine,
+    tempspace::TempSpace, validate_input_file, Engine, Operation, PaperSize, PdfError, Plan,
+    Result, Tool, POINTS_PER_MM,
+};
+
+/// Options for [`html_to_pdf`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HtmlToPdfOptions {
+    /// Page size (portrait).
+    pub paper: PaperSize,
+    /// Margin on every side, in points.
+    pub margin: f32,
+}
+
+impl Default for HtmlToPdfOptions {
+    fn default() -> Self {
+        Self {
+            paper: PaperSize::A4,
+            margin: 36.0,
+        }
+    }
+}
+
+/// Convert the HTML file `input` to the PDF `output` (with `wkhtmltopdf`
+/// or Chrome; see [`select_engine`]). Images, style sheets, and scripts the
+/// page refers to are loaded as a browser would load them.
+pub fn html_to_pdf(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &HtmlToPdfOptions,
+) -> Result<()> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_input_file(input)?;
+    validate_options(options)?;
+    match select_engine(Operation::HtmlToPdf)? {
+        Engine::Wkhtmltopdf => run_tool_writing(
+            Tool::Wkhtmltopdf,
+            wkhtmltopdf_command(input, output, options)?,
+            output,
+        ),
+        Engine::Chrome => {
+            let scratch = TempSpace::new("from-html")?;
+            let page = scratch.write(
+                "page.html",
+                with_page_style(&fs::read(input)?, input, options)?,
+            )?;
+            let url = file_url(&std::path::absolute(page)?);
+            let cmd = chrome_command(&url, output, &scratch.file("profile"))?;
+            let command = command_to_string(&cmd);
+            run_tool_writing(Tool::Chrome, cmd, output)?;
+            // Chrome exits with success even when it could not print.
+            if output.exists() {
+                Ok(())
+            } else {
+                Err(PdfError::ToolFailed {
+                    tool: Tool::Chrome.name(),
+                    command,
+                    status: 0,
+                    stdout: String::new(),
+                    stderr: format!("no PDF was written to {}", output.display()),
+                })
+            }
+        }
+        other => Err(engine::unsupported(Operation::HtmlToPdf, other)),
+    }
+}
+
+/// What [`html_to_pdf`] would run.
+pub fn html_to_pdf_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &HtmlToPdfOptions,
+) -> Result<Plan> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_input_file(input)?;
+    validate_options(options)?;
+    let plan = match select_engine(Operation::HtmlToPdf)? {
+        Engine::Wkhtmltopdf => Plan::new().run(
+            Tool::Wkhtmltopdf,
+            &wkhtmltopdf_command(input, output, options)?,
+        ),
+        Engine::Chrome => {
+            let page = plan::temp_path("page.html");
+            Plan::new()
+                .native(format!(
+                    "copy {} to {} with {}",
+                    input.display(),
+                    page.display(),
+                    page_style(options)
+                ))
+                .run(
+                    Tool::Chrome,
+                    &chrome_command(
+                        &format!("file://{}", page.display()),
+                        output,
+                        &plan::temp_path("profile"),
+                    )?,
+                )
+        }
+        other => return Err(engine::unsupported(Operation::HtmlToPdf, other)),
+    };
+    Ok(plan.write(output))
+}
+
+fn validate_options(options: &HtmlToPdfOptions) -> Result<()> {
+    let room = options.paper.width.min(options.paper.height) - 2.0 * options.margin;
+    if options.margin < 0.0 || room < 72.0 {
+        return Err(PdfError::InvalidArgument(format!(
+            "a margin of {} points leaves no room for the page on {} paper",
+            options.margin, options.paper
+        )));
+    }
+    Ok(())
+}
+
+fn wkhtmltopdf_command(input: &Path, output: &Path, options: &HtmlToPdfOptions) -> Result<Command> {
+    let mm = |points: f32| format!("{:.2}mm", points / POINTS_PER_MM);
+    let margin = mm(options.margin);
+    let mut cmd = Command::new(find_tool(Tool::Wkhtmltopdf)?);
+    cmd.arg("--quiet")
+        .arg("--enable-local-file-access")
+        .arg("--page-width")
+        .arg(mm(options.paper.width))
+        .arg("--page-height")
+        .arg(mm(options.paper.height));
+    for side in ["-T", "-B", "-L", "-R"] {
+        cmd.arg(side).arg(&margin);
+    }
+    cmd.arg(input.as_os_str()).arg(output.as_os_str());
+    Ok(cmd)
+}
+
+fn chrome_command(url: &str, output: &Path, profile: &Path) -> Result<Command> {
+    let mut cmd = Command::new(find_tool(Tool::Chrome)?);
+    let mut print_to = std::ffi::OsString::from("--print-to-pdf=");
+    print_to.push(std::path::absolute(output)?);
+    let mut user_data = std::ffi::OsString::from("--user-data-dir=");
+    user_data.push(profile);
+    cmd.arg("--headless")
+        .arg("--disable-gpu")
+        .arg("--no-first-run")
+        .arg(user_data)
+        // The second is the name in Chrome before 119.
+        .arg("--no-pdf-header-footer")
+        .arg("--print-to-pdf-no-header")
+        .arg(print_to)
+        .arg(url);
+    Ok(cmd)
+}
+
+/// The `@page` rule that sets the paper and margins in Chrome.
+fn page_style(options: &HtmlToPdfOptions) -> String {
+    format!(
+        "@page {{ size: {}pt {}pt !important; margin: {}pt !important; }}",
+        options.paper.width, options.paper.height, options.margin
+    )
+}
+
+/// The HTML document `html` (in whatever encoding it is) with
+/// [`page_style`], and a `<base>` pointing next to `input` unless it has
+/// one, added at the start of its head.
+fn with_page_style(html: &[u8], input: &Path, options: &HtmlToPdfOptions) -> Result<Vec<u8>> {
+    let mut added = String::new();
+    if find_tag(html, b"<base").is_none() {
+        let dir = std::path::absolute(input)?
+            .parent()
+            .map_or_else(PathBuf::new, Path::to_path_buf);
+        let _ = write!(added, "<base href=\"{}/\">", file_url(&dir));
+    }
+    let _ = write!(added, "<style>{}</style>", page_style(options));
+    // After `<head ...>`; without one, before the first element after the
+    // doctype, where the parser opens the head itself.
+    let at = [b"<head".as_slice(), b"<!doctype"]
+        .into_iter()
+        .find_map(|tag| {
+            let start = find_tag(html, tag)?;
+            let end = html[start..].iter().position(|&b| b == b'>')?;
+            Some(start + end + 1)
+        })
+        .unwrap_or(0);
+    let mut out = Vec::with_capacity(html.len() + added.len());
+    out.extend_from_slice(&html[..at]);
+    out.extend_from_slice(added.as_bytes());
+    out.extend_from_slice(&html[at..]);
+    Ok(out)
+}
+
+/// Position of the first `tag` (lower case, without its `>`) in `html`,
+/// ignoring case; `<head` does not match `<header`.
+fn find_tag(html: &[u8], tag: &[u8]) -> Option<usize> {
+    html.windows(tag.len() + 1).position(|w| {
+        w[..tag.len()].eq_ignore_ascii_case(tag)
+            && (w[tag.len()] == b'>' || w[tag.len()].is_ascii_whitespace())
+    })
+}
+
+/// A `file:` URL for the absolute path `path`.
+pub(crate) fn file_url(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut url = String::from("file://");
+    if !path.starts_with('/') {
+        url.push('/');
+    }
+    for b in path.bytes() {
+        if b.is_ascii_alphanumeric() || b"/-._~:".contains(&b) {
+            url.push(char::from(b));
+        } else {
+            let _ = write!(url, "%{b:02X}");
+        }
+    }
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_style_is_added_to_the_head() -> Result<()> {
+        let options = HtmlToPdfOptions {
+            paper: PaperSize::LETTER,
+            margin: 18.0,
+        };
+        let input = Path::new("/srv/my reports/q3.html");
+        let html = b"<!DOCTYPE html>\n<HTML><Head lang=en><title>Q3</title></head><header>";
+        let out = String::from_utf8_lossy(&with_page_style(html, input, &options)?).to_string();
+        assert_eq!(
+            out,
+            "<!DOCTYPE html>\n<HTML><Head lang=en><base href=\"file:///srv/my%20reports/\">\
+             <style>@page { size: 612pt 792pt !important; margin: 18pt !important; }</style>\
+             <title>Q3</title></head><header>"
+        );
+
+        let html = b"<!doctype html><base href=\"https://example.com/\"><p>hi";
+        let out = String::from_utf8_lossy(&with_page_style(html, input, &options)?).to_string();
+        assert!(out.starts_with("<!doctype html><style>@page"));
+        assert!(out.ends_with("</style><base href=\"https://example.com/\"><p>hi"));
+        Ok(())
+    }
+
+    #[test]
+    fn margins_must_leave_room() {
+        let options = |margin| HtmlToPdfOptions {
+            margin,
+            ..HtmlToPdfOptions::default()
+        };
+        assert!(validate_options(&options(36.0)).is_ok());
+        assert!(validate_options(&options(-1.0)).is_err());
+        assert!(validate_options(&options(270.0)).is_err());
+    }
+}
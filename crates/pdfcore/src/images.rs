@@ -0,0 +1,233 @@
+//! Where images are drawn and at what resolution: the pixel size of each
+//! image `XObject` against the size it is placed at on the page.
+
+use std::path::Path;
+
+use lopdf::{content::Content, Dictionary, Document, Object, ObjectId};
+
+use crate::{geometry::Matrix, load_document, stamp::effective_resources, Result};
+
+/// How deeply nested form `XObject`s are followed.
+const MAX_FORM_DEPTH: usize = 12;
+
+/// One drawing of an image, as returned by [`images`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageInfo {
+    /// 1-based page number.
+    pub page: u32,
+    /// Resource name of the image (inside a form, `Form/Image`).
+    pub name: String,
+    /// Width of the image, in pixels.
+    pub width: u32,
+    /// Height of the image, in pixels.
+    pub height: u32,
+    /// Width the image is drawn at, in points.
+    pub placed_width: f32,
+    /// Height the image is drawn at, in points.
+    pub placed_height: f32,
+    /// Horizontal resolution as drawn, in pixels per inch.
+    pub dpi_x: f32,
+    /// Vertical resolution as drawn, in pixels per inch.
+    pub dpi_y: f32,
+}
+
+impl ImageInfo {
+    /// The effective resolution: the lower of [`Self::dpi_x`] and
+    /// [`Self::dpi_y`].
+    #[must_use]
+    pub fn dpi(&self) -> f32 {
+        self.dpi_x.min(self.dpi_y)
+    }
+}
+
+/// Every drawing of an image `XObject` in `input`, in page order, with its
+/// effective resolution (pure Rust). An image drawn twice is listed twice;
+/// images in form `XObject`s are included. Inline images are not.
+pub fn images(input: impl AsRef<Path>) -> Result<Vec<ImageInfo>> {
+    let doc = load_document(input.as_ref())?;
+    Ok(placements(&doc)
+        .into_iter()
+        .map(|(_, image)| image)
+        .collect())
+}
+
+/// Every drawing of an image `XObject` in `doc`, with the image's object.
+pub(crate) fn placements(doc: &Document) -> Vec<(ObjectId, ImageInfo)> {
+    let mut out = Vec::new();
+    for (page, page_id) in doc.get_pages() {
+        let content = doc.get_page_content(page_id).unwrap_or_default();
+        let mut walk = Walk {
+            doc,
+            page,
+            forms: Vec::new(),
+            out: &mut out,
+        };
+        walk.content(
+            &content,
+            &effective_resources(doc, page_id),
+            Matrix::IDENTITY,
+            "",
+        );
+    }
+    out
+}
+
+/// Walks the content of one page, into the forms it draws.
+struct Walk<'a> {
+    doc: &'a Document,
+    page: u32,
+    /// The forms being walked, to cut cycles.
+    forms: Vec<ObjectId>,
+    out: &'a mut Vec<(ObjectId, ImageInfo)>,
+}
+
+impl Walk<'_> {
+    fn content(&mut self, content: &[u8], resources: &Dictionary, base: Matrix, prefix: &str) {
+        let Ok(content) = Content::decode(content) else {
+            return;
+        };
+        let xobjects = resources
+            .get(b"XObject")
+            .ok()
+            .and_then(|o| self.doc.dereference(o).ok())
+            .and_then(|(_, o)| o.as_dict().ok());
+        let mut ctm = base;
+        let mut stack = Vec::new();
+        for op in &content.operations {
+            match op.operator.as_str() {
+                "q" => stack.push(ctm),
+                "Q" => ctm = stack.pop().unwrap_or(base),
+                "cm" => {
+                    if let Some(m) = Matrix::from_operands(&op.operands) {
+                        ctm = m.then(&ctm);
+                    }
+                }
+                "Do" => {
+                    let (Some(Object::Name(name)), Some(xobjects)) =
+                        (op.operands.first(), xobjects)
+                    else {
+                        continue;
+                    };
+                    if let Ok(&Object::Reference(id)) = xobjects.get(name) {
+                        let name = format!("{prefix}{}", String::from_utf8_lossy(name));
+                        self.xobject(id, &name, ctm, resources);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn xobject(&mut self, id: ObjectId, name: &str, ctm: Matrix, resources: &Dictionary) {
+        let Ok(stream) = self.doc.get_object(id).and_then(Object::as_stream) else {
+            return;
+        };
+        let dict = &stream.dict;
+        let subtype = dict
+            .get(b"Subtype")
+            .and_then(Object::as_name)
+            .unwrap_or_default();
+        if subtype == b"Image" {
+            let int = |key: &[u8]| {
+                dict.get(key)
+                    .ok()
+                    .and_then(|o| self.doc.dereference(o).ok())
+                    .and_then(|(_, o)| o.as_i64().ok())
+                    .and_then(|n| u32::try_from(n).ok())
+            };
+            if let (Some(width), Some(height)) = (int(b"Width"), int(b"Height")) {
+                self.out
+                    .push((id, placed(self.page, name, width, height, &ctm)));
+            }
+        } else if subtype == b"Form"
+            && self.forms.len() < MAX_FORM_DEPTH
+            && !self.forms.contains(&id)
+        {
+            let content = if dict.has(b"Filter") {
+                stream.decompressed_content().unwrap_or_default()
+            } else {
+                stream.content.clone()
+            };
+            let matrix = dict
+                .get(b"Matrix")
+                .and_then(Object::as_array)
+                .ok()
+                .and_then(|m| Matrix::from_operands(m))
+                .unwrap_or(Matrix::IDENTITY);
+            // A form without resources uses those of whatever draws it.
+            let own = dict
+                .get(b"Resources")
+                .ok()
+                .and_then(|o| self.doc.dereference(o).ok())
+                .and_then(|(_, o)| o.as_dict().ok())
+                .unwrap_or(resources)
+                .clone();
+            self.forms.push(id);
+            self.content(&content, &own, matrix.then(&ctm), &format!("{name}/"));
+            self.forms.pop();
+        }
+    }
+}
+
+/// An image of `width` × `height` pixels drawn with `ctm`, which maps the
+/// unit square onto the page.
+#[allow(clippy::cast_precision_loss)] // pixel counts are far below 2^24
+fn placed(page: u32, name: &str, width: u32, height: u32, ctm: &Matrix) -> ImageInfo {
+    let [a, b, c, d, _, _] = ctm.0;
+    let placed_width = a.hypot(b);
+    let placed_height = c.hypot(d);
+    let dpi = |pixels: u32, points: f32| {
+        if points > 0.0 {
+            pixels as f32 * 72.0 / points
+        } else {
+            0.0
+        }
+    };
+    ImageInfo {
+        page,
+        name: name.to_string(),
+        width,
+        height,
+        placed_width,
+        placed_height,
+        dpi_x: dpi(width, placed_width),
+        dpi_y: dpi(height, placed_height),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolution_follows_the_placement() {
+        // 2550 × 3300 pixels over a whole letter page: 300 DPI.
+        let full = placed(
+            1,
+            "Im0",
+            2550,
+            3300,
+            &Matrix([612.0, 0.0, 0.0, 792.0, 0.0, 0.0]),
+        );
+        assert!((full.dpi() - 300.0).abs() < 0.01, "{full:?}");
+        // Rotated a quarter turn and drawn at half the size: 600 DPI.
+        let turned = placed(
+            1,
+            "Im0",
+            2550,
+            3300,
+            &Matrix([0.0, 306.0, -396.0, 0.0, 306.0, 0.0]),
+        );
+        assert!((turned.dpi_x - 600.0).abs() < 0.01, "{turned:?}");
+        assert!((turned.dpi_y - 600.0).abs() < 0.01, "{turned:?}");
+        // Stretched: the lower resolution counts.
+        let stretched = placed(
+            1,
+            "Im0",
+            300,
+            300,
+            &Matrix([72.0, 0.0, 0.0, 144.0, 0.0, 0.0]),
+        );
+        assert!((stretched.dpi() - 150.0).abs() < 0.01, "{stretched:?}");
+    }
+}
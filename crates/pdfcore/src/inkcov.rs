@@ -0,0 +1,101 @@
+//! Ink coverage: how much cyan, magenta, yellow, and black ink each page
+//! takes, measured with ghostscript's `inkcov` device. Print shops price
+//! jobs by it.
+
+use std::{path::Path, process::Command};
+
+use crate::{find_tool, run_tool_capture, validate_input_file, PdfError, Result, Tool};
+
+/// The ink one page takes, as percentages of the page area covered by each
+/// colorant at full strength.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InkCoverage {
+    /// 1-based page number.
+    pub page: u32,
+    pub cyan: f64,
+    pub magenta: f64,
+    pub yellow: f64,
+    pub black: f64,
+}
+
+impl InkCoverage {
+    /// Total coverage of all four inks (up to 400%).
+    #[must_use]
+    pub fn total(&self) -> f64 {
+        self.cyan + self.magenta + self.yellow + self.black
+    }
+}
+
+/// The ink coverage of every page of `input` (requires ghostscript).
+///
+/// Colors are separated as ghostscript converts them to CMYK, without the
+/// printer's ICC profile, so the figures are estimates for costing rather
+/// than press measurements.
+pub fn ink_coverage(input: impl AsRef<Path>) -> Result<Vec<InkCoverage>> {
+    let input = input.as_ref();
+    validate_input_file(input)?;
+    let stdout = run_tool_capture(Tool::Ghostscript, inkcov_command(input)?)?;
+    parse_inkcov(&stdout)
+}
+
+fn inkcov_command(input: &Path) -> Result<Command> {
+    let mut cmd = Command::new(find_tool(Tool::Ghostscript)?);
+    cmd.arg("-q")
+        .arg("-sDEVICE=inkcov")
+        .arg("-dNOPAUSE")
+        .arg("-dBATCH")
+        .arg("-dSAFER")
+        .arg("-sOutputFile=%stdout")
+        .arg(input.as_os_str());
+    Ok(cmd)
+}
+
+/// Parse the `inkcov` report: one line per page of four fractions followed
+/// by `CMYK OK`, e.g. ` 0.02645  0.01794  0.01794  0.00000 CMYK OK`.
+fn parse_inkcov(stdout: &str) -> Result<Vec<InkCoverage>> {
+    let mut pages = Vec::new();
+    for line in stdout.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [c, m, y, k, "CMYK", ..] = fields[..] else {
+            continue;
+        };
+        let percent = |value: &str| {
+            value.parse::<f64>().map(|v| v * 100.0).map_err(|_| {
+                PdfError::InvalidArgument(format!("unexpected ink coverage line: {line:?}"))
+            })
+        };
+        pages.push(InkCoverage {
+            page: u32::try_from(pages.len() + 1).unwrap_or(u32::MAX),
+            cyan: percent(c)?,
+            magenta: percent(m)?,
+            yellow: percent(y)?,
+            black: percent(k)?,
+        });
+    }
+    if pages.is_empty() {
+        return Err(PdfError::InvalidArgument(
+            "ghostscript reported no ink coverage".to_string(),
+        ));
+    }
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inkcov_lines_become_percentages() -> Result<()> {
+        let stdout = "GPL Ghostscript 10.02.1: some warning\n \
+                      0.02645  0.01794  0.01794  0.00000 CMYK OK\n \
+                      0.00000  0.00000  0.00000  0.12500 CMYK OK\n";
+        let pages = parse_inkcov(stdout)?;
+        assert_eq!(pages.len(), 2);
+        assert!((pages[0].cyan - 2.645).abs() < 1e-9);
+        assert_eq!(pages[1].page, 2);
+        assert!((pages[1].total() - 12.5).abs() < 1e-9);
+        assert!(parse_inkcov("").is_err());
+        assert!(parse_inkcov("x 0.1 0.1 0.1 CMYK OK").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,256 @@
+//! Document language: what the text is written in, detected with
+//! `whatlang`, weighed against the language the catalog declares.
+
+use std::{collections::BTreeMap, path::Path};
+
+use crate::{extract_text_pages, load_for_reading, metadata, Result, TextPagesOptions};
+
+/// Share of the evidence given to the catalog's `/Lang` entry when the text
+/// also shows a language.
+const DECLARED_WEIGHT: f64 = 0.1;
+
+/// Confidence in the catalog's `/Lang` entry when no language shows in the
+/// text, e.g. in a scanned document.
+const DECLARED_ONLY: f64 = 0.5;
+
+/// A natural language.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Language {
+    /// ISO 639-3 code, e.g. `eng`. A `/Lang` entry naming a language
+    /// `whatlang` does not know keeps its own primary subtag, e.g. `ga`.
+    pub code: String,
+    /// English name, e.g. `English`; `None` for languages `whatlang` does
+    /// not know.
+    pub name: Option<&'static str>,
+}
+
+impl Language {
+    /// The language of a BCP 47 tag as `/Lang` holds it, e.g. `en-US`.
+    /// `None` for an empty or unspecified (`x-`, `und`) tag.
+    #[must_use]
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let primary = tag.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+        if primary.is_empty() || primary == "x" || primary == "i" || primary == "und" {
+            return None;
+        }
+        let known = match primary.len() {
+            2 => ISO_639_1
+                .iter()
+                .find(|(two, _)| *two == primary)
+                .and_then(|(_, three)| whatlang::Lang::from_code(*three)),
+            _ => whatlang::Lang::from_code(primary.as_str()),
+        };
+        Some(known.map_or(
+            Self {
+                code: primary,
+                name: None,
+            },
+            Self::from,
+        ))
+    }
+
+    /// The English name, or the code for languages without one.
+    #[must_use]
+    pub fn label(&self) -> &str {
+        self.name.unwrap_or(&self.code)
+    }
+}
+
+impl From<whatlang::Lang> for Language {
+    fn from(lang: whatlang::Lang) -> Self {
+        Self {
+            code: lang.code().to_string(),
+            name: Some(lang.eng_name()),
+        }
+    }
+}
+
+/// ISO 639-1 codes (and the withdrawn ones older files still use) of the
+/// languages `whatlang` detects, with their ISO 639-3 codes.
+const ISO_639_1: &[(&str, &str)] = &[
+    ("af", "afr"),
+    ("ak", "aka"),
+    ("am", "amh"),
+    ("ar", "ara"),
+    ("az", "aze"),
+    ("be", "bel"),
+    ("bg", "bul"),
+    ("bn", "ben"),
+    ("ca", "cat"),
+    ("cs", "ces"),
+    ("da", "dan"),
+    ("de", "deu"),
+    ("el", "ell"),
+    ("en", "eng"),
+    ("eo", "epo"),
+    ("es", "spa"),
+    ("et", "est"),
+    ("fa", "pes"),
+    ("fi", "fin"),
+    ("fr", "fra"),
+    ("gu", "guj"),
+    ("he", "heb"),
+    ("hi", "hin"),
+    ("hr", "hrv"),
+    ("hu", "hun"),
+    ("hy", "hye"),
+    ("id", "ind"),
+    ("in", "ind"),
+    ("it", "ita"),
+    ("iw", "heb"),
+    ("ja", "jpn"),
+    ("ji", "yid"),
+    ("jv", "jav"),
+    ("ka", "kat"),
+    ("km", "khm"),
+    ("kn", "kan"),
+    ("ko", "kor"),
+    ("la", "lat"),
+    ("lt", "lit"),
+    ("lv", "lav"),
+    ("mk", "mkd"),
+    ("ml", "mal"),
+    ("mr", "mar"),
+    ("my", "mya"),
+    ("nb", "nob"),
+    ("ne", "nep"),
+    ("nl", "nld"),
+    ("no", "nob"),
+    ("or", "ori"),
+    ("pa", "pan"),
+    ("pl", "pol"),
+    ("pt", "por"),
+    ("ro", "ron"),
+    ("ru", "rus"),
+    ("si", "sin"),
+    ("sk", "slk"),
+    ("sl", "slv"),
+    ("sn", "sna"),
+    ("sr", "srp"),
+    ("sv", "swe"),
+    ("ta", "tam"),
+    ("te", "tel"),
+    ("th", "tha"),
+    ("tk", "tuk"),
+    ("tl", "tgl"),
+    ("tr", "tur"),
+    ("uk", "ukr"),
+    ("ur", "urd"),
+    ("uz", "uzb"),
+    ("vi", "vie"),
+    ("yi", "yid"),
+    ("zh", "cmn"),
+    ("zu", "zul"),
+];
+
+/// The languages `input` is written in, most likely first, each with a
+/// confidence from 0 to 1 (text from [`extract_text_pages`]; languages with
+/// `whatlang`).
+///
+/// Each page's language counts in proportion to its words, so a document
+/// half in English and half in French gets both at about 0.5; pages too
+/// short to tell lower every confidence. The catalog's `/Lang` entry counts
+/// for a tenth of the evidence, or is reported at 0.5 on its own when the
+/// text shows no language (a scanned document, say).
+pub fn detect_language(input: impl AsRef<Path>) -> Result<Vec<(Language, f64)>> {
+    let input = input.as_ref();
+    let doc = load_for_reading(input)?;
+    let declared = doc
+        .catalog()
+        .ok()
+        .and_then(|c| c.get(b"Lang").ok())
+        .and_then(|o| o.as_str().ok())
+        .map(metadata::decode_text_string)
+        .and_then(|tag| Language::from_tag(&tag));
+    let pages: Vec<(u64, Option<(Language, f64)>)> =
+        extract_text_pages(input, &TextPagesOptions::default())?
+            .iter()
+            .map(|page| (words(&page.text), detect(&page.text)))
+            .collect();
+    Ok(combine(&pages, declared))
+}
+
+/// The language of `text`, if `whatlang` is sure of it.
+pub(crate) fn detect(text: &str) -> Option<(Language, f64)> {
+    let info = whatlang::detect(text).filter(whatlang::Info::is_reliable)?;
+    Some((info.lang().into(), info.confidence()))
+}
+
+/// Runs of non-whitespace holding a letter or digit.
+pub(crate) fn words(text: &str) -> u64 {
+    let count = text
+        .split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .count();
+    count as u64
+}
+
+/// Weigh each page's language by its words (`(words, language)` per page),
+/// then the declared language, into confidences, most likely first.
+fn combine(
+    pages: &[(u64, Option<(Language, f64)>)],
+    declared: Option<Language>,
+) -> Vec<(Language, f64)> {
+    let total: u64 = pages.iter().map(|(words, _)| words).sum();
+    let mut shares: BTreeMap<Language, f64> = BTreeMap::new();
+    for (words, guess) in pages {
+        if let Some((language, confidence)) = guess {
+            #[allow(clippy::cast_precision_loss)]
+            let share = *words as f64 / total as f64 * confidence;
+            *shares.entry(language.clone()).or_default() += share;
+        }
+    }
+    shares.retain(|_, share| *share > 0.0);
+    if let Some(declared) = declared {
+        if shares.is_empty() {
+            shares.insert(declared, DECLARED_ONLY);
+        } else {
+            for share in shares.values_mut() {
+                *share *= 1.0 - DECLARED_WEIGHT;
+            }
+            *shares.entry(declared).or_default() += DECLARED_WEIGHT;
+        }
+    }
+    let mut languages: Vec<(Language, f64)> = shares.into_iter().collect();
+    languages.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    languages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_languages_are_weighed_with_the_declared_one() {
+        let english = Language::from(whatlang::Lang::Eng);
+        let french = Language::from(whatlang::Lang::Fra);
+        assert_eq!(Language::from_tag("en-US").as_ref(), Some(&english));
+        assert_eq!(Language::from_tag("FRA").as_ref(), Some(&french));
+        assert_eq!(
+            Language::from_tag("ga-IE").map(|l| l.code).as_deref(),
+            Some("ga")
+        );
+        assert!(Language::from_tag(" ").is_none());
+
+        let pages = [
+            (300, Some((english.clone(), 1.0))),
+            (100, Some((french.clone(), 1.0))),
+            (0, None),
+        ];
+        let found = combine(&pages, Some(french.clone()));
+        let found: Vec<(&str, f64)> = found
+            .iter()
+            .map(|(language, confidence)| {
+                (language.label(), (confidence * 1000.0).round() / 1000.0)
+            })
+            .collect();
+        assert_eq!(found, [("English", 0.675), ("French", 0.325)]);
+        assert_eq!(combine(&[(0, None)], Some(french.clone())), [(french, 0.5)]);
+        assert!(combine(&[(0, None)], None).is_empty());
+        assert_eq!(
+            detect("The committee met on Tuesday to review the budget for the coming year.")
+                .map(|(language, _)| language),
+            Some(english)
+        );
+    }
+}
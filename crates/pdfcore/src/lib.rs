@@ -1,25 +1,226 @@
 //! Core library for `pdfcli`.
 //!
 //! This crate provides:
-//! - **Pure Rust** PDF inspection (`info`) using [`lopdf`].
-//! - Thin wrappers around external tools (`qpdf`, `pdftotext`, `ghostscript`).
+//! - **Pure Rust** PDF inspection (`info`, `fonts`) using [`lopdf`].
+//! - Thin wrappers around external tools (`qpdf`, `pdftotext`, `ghostscript`,
+//!   `ocrmypdf`, `mutool`).
 //!
 //! External tools are detected at runtime (PATH search) and may be overridden
 //! via env vars:
 //! - `PDFCLI_QPDF`
 //! - `PDFCLI_PDFTOTEXT`
 //! - `PDFCLI_GS`
+//! - `PDFCLI_OCRMYPDF`
+//! - `PDFCLI_MUTOOL`
+//!
+//! With the `pdfium` feature, pages can also be rendered and their text
+//! extracted in process with `PDFium`, loaded from `PDFCLI_PDFIUM` or the
+//! system library path.
+//!
+//! External tools can be given a time limit with [`set_tool_timeout`]; a tool
+//! that runs longer is killed (with its process group on Unix), anything it
+//! had written to the operation's output is removed, and the operation fails
+//! with [`PdfError::Timeout`]. [`set_tool_run_options`] also limits their
+//! memory, CPU time, and file sizes, for running them on untrusted input.
+//!
+//! Diagnostics are emitted as [`tracing`] events: external commands, their
+//! duration, and tool versions at `DEBUG`; tool output at `TRACE`.
 
 use std::{
     collections::BTreeMap,
     ffi::OsStr,
-    fmt,
+    fmt, fs,
+    io::Read,
     path::{Path, PathBuf},
-    process::{Command, Output},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, RwLock,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
 use thiserror::Error;
 
+mod a11y;
+#[cfg(feature = "tokio")]
+pub mod aio;
+mod archive;
+mod barcode;
+mod batch;
+mod bates;
+mod boxes;
+mod cache;
+mod cms;
+mod compose;
+mod config;
+mod dedupe;
+mod dests;
+mod docid;
+mod email;
+mod encryption;
+mod engine;
+mod features;
+mod fingerprint;
+mod fonts;
+mod geometry;
+mod header_footer;
+mod html;
+mod images;
+mod inkcov;
+mod language;
+mod links;
+mod manifest;
+mod metadata;
+mod msg;
+mod native;
+mod objects;
+mod office;
+mod ops;
+mod optimize;
+mod orientation;
+mod pagesize;
+mod pagetext;
+#[cfg(feature = "pdfium")]
+mod pdfium;
+mod pipeline;
+mod plan;
+mod policy;
+mod postscript;
+mod prepress;
+mod print;
+mod profiles;
+mod progress;
+mod qr;
+mod raster;
+mod recompress;
+mod redact;
+#[cfg(feature = "remote")]
+mod remote;
+mod revisions;
+mod sandbox;
+mod sanitize;
+mod select;
+mod sign;
+mod sign_visual;
+mod signatures;
+mod size;
+mod split;
+mod stamp;
+mod stats;
+mod strip;
+mod svg;
+mod tables;
+pub mod tempspace;
+mod text;
+mod textenc;
+mod tiff;
+mod title;
+mod transcode;
+mod viewer;
+mod warnings;
+mod xfa;
+
+pub use a11y::{
+    a11y_check, set_document_language, set_document_language_plan, A11yReport, TextDirection,
+};
+pub use archive::{is_pdf_collection, map_pdfs, map_pdfs_plan};
+pub use barcode::{barcodes, Barcode, BarcodeKind};
+pub use batch::{check_inputs, for_each_input, for_each_input_with_progress, InputResult};
+pub use bates::{bates_number, bates_number_plan, BatesOptions, BatesRange};
+pub use boxes::{page_boxes, set_page_boxes, set_page_boxes_plan, BoxChanges, BoxInfo, PageBoxes};
+pub use cache::{Cache, CacheEntry};
+pub use compose::{text_to_pdf, text_to_pdf_plan, TextFormat, TextToPdfOptions};
+pub use config::{Config, Defaults, ToolPaths, CONFIG_KEYS};
+pub use dests::{add_destination, add_destination_plan, destinations, Destination};
+pub use docid::{regenerate_id, regenerate_id_plan};
+pub use email::{
+    email_attachments, extract_email_pdfs, extract_email_pdfs_plan, merge_email_pdfs,
+    merge_email_pdfs_plan, EmailAttachment,
+};
+pub use encryption::{
+    encryption_info, set_permissions, set_permissions_plan, EncryptOptions, EncryptionInfo,
+    Permissions,
+};
+pub use engine::{preferred_engines, select_engine, set_preferred_engines, Engine, Operation};
+pub use fingerprint::{fingerprint, PageFingerprint};
+pub use fonts::{embed_fonts, embed_fonts_plan, fonts, FontInfo};
+pub use geometry::{PageBox, PaperSize, Rect, POINTS_PER_MM};
+pub use header_footer::{
+    header_footer, header_footer_plan, HeaderFooterOptions, HEADER_FOOTER_VARIABLES,
+};
+pub use html::{html_to_pdf, html_to_pdf_plan, HtmlToPdfOptions};
+pub use images::{images, ImageInfo};
+pub use inkcov::{ink_coverage, InkCoverage};
+pub use language::{detect_language, Language};
+pub use links::{
+    links, rewrite_links, rewrite_links_plan, strip_links, strip_links_plan, LinkInfo, LinkTarget,
+};
+pub use manifest::{render_manifest, split_pages_manifest, Manifest, ManifestEntry};
+pub use metadata::{apply_metadata_patch, apply_metadata_patch_plan, metadata_diff, MetadataPatch};
+pub use objects::{content_dump, objects, show_object, ObjectDump, ObjectSummary};
+pub use office::{office_to_pdf, office_to_pdf_plan};
+pub use ops::{Compress, ExtractText, Merge, Rotate};
+pub use optimize::{dedupe_resources, optimize, optimize_plan, OptimizeOptions, OptimizeReport};
+pub use orientation::{auto_rotate, auto_rotate_plan, detect_orientation, PageOrientation};
+pub use pagesize::{
+    fit_pages_to_paper, fit_pages_to_paper_plan, page_sizes, PageSizeGroup, PageSizeReport,
+};
+pub use pagetext::{extract_text_pages, PageText, TextPagesOptions};
+pub use pipeline::{Pipeline, PipelineStep};
+pub use plan::{Plan, PlanStep};
+pub use policy::{
+    check_policy, MetadataRules, Policy, PolicyReport, PreflightRules, SecurityRules,
+};
+pub use postscript::{from_ps, from_ps_plan, to_ps, to_ps_plan, ToPsOptions};
+pub use prepress::{add_bleed, add_bleed_plan, BleedOptions};
+pub use print::{print, print_plan, PrintOptions};
+pub use profiles::{
+    a11y_prep, a11y_prep_plan, court_ready, court_ready_plan, print_ready, print_ready_plan,
+    to_pdfx, to_pdfx_plan, A11yPrepOptions, A11yPrepReport, ComplianceCheck, CourtReadyOptions,
+    CourtReadyReport, PdfxOptions, PdfxReport, PdfxStandard, PrintReadyOptions, PrintReadyReport,
+};
+pub use progress::{Progress, ProgressEvent};
+pub use recompress::{
+    recompress_images, recompress_images_plan, RecompressOptions, RecompressReport,
+};
+pub use redact::{redact, redact_plan, RedactOptions, RedactRegion, RedactReport};
+#[cfg(feature = "remote")]
+pub use remote::{fetch, fetch_plan, is_remote, RemoteInput};
+pub use revisions::{extract_revision, extract_revision_plan, revisions, Revision};
+pub use sandbox::ToolRunOptions;
+pub use sanitize::{
+    audit_active_content, sanitize, sanitize_plan, ActiveContent, SanitizeFinding, SanitizeReport,
+};
+pub use select::{page_previews, select_pages, select_pages_plan};
+pub use sign::{sign, sign_plan, Certification, SignOptions, SignReport, SignatureAppearance};
+pub use sign_visual::{sign_visual, sign_visual_plan, SignVisualOptions};
+pub use signatures::{signatures, SignatureInfo, SignaturesOptions, TimestampInfo};
+pub use size::{size_report, SizeBucket, SizeCategory, SizeReport, SizedObject};
+pub use split::{
+    split_by_size, split_by_size_plan, split_parts_by_separator, split_parts_by_text, Separator,
+    SplitPart,
+};
+pub use stamp::StampPosition;
+pub use stats::{text_stats, PageStats, TextStats, TextStatsOptions, READING_WORDS_PER_MINUTE};
+pub use strip::{strip_metadata, strip_metadata_plan, StripOptions, StripReport};
+pub use svg::{to_svg, to_svg_plan};
+pub use tables::{extract_tables, extract_tables_plan, Table};
+pub use textenc::{LineEnding, TextEncoding, TextOutput};
+pub use tiff::{from_tiff, from_tiff_plan, to_tiff, to_tiff_plan, TiffCompression, ToTiffOptions};
+pub use title::{infer_title, set_document_title, set_document_title_plan, InferredTitle};
+pub use transcode::{
+    transcode_images, transcode_images_plan, ImageCodec, TranscodeOptions, TranscodeReport,
+    TranscodeTarget, TranscodedImage,
+};
+pub use viewer::{
+    set_open_action, set_open_action_plan, set_viewer_options, set_viewer_options_plan, OpenAction,
+    OpenFit, PageLayout, PageMode, ViewerOptions,
+};
+pub use warnings::{collect_warnings, Warning, WarningKind};
+pub use xfa::{xfa_dump_plan, xfa_form, xfa_xml, XfaForm};
+
 /// Convenient result type for this crate.
 pub type Result<T> = std::result::Result<T, PdfError>;
 
@@ -38,10 +239,23 @@ pub enum PdfError {
         source: lopdf::Error,
     },
 
+    /// Some fonts could not be embedded.
+    #[error("fonts could not be embedded: {}", fonts.join(", "))]
+    FontsNotEmbedded { fonts: Vec<String> },
+
     /// External tool required but missing.
     #[error("required tool not found: {tool}\n\n{hint}")]
     MissingTool { tool: &'static str, hint: String },
 
+    /// External tool is installed but older than a feature requires.
+    #[error("{tool} {found} is too old for {feature}; version {required} or newer is required")]
+    ToolTooOld {
+        tool: &'static str,
+        found: ToolVersion,
+        required: ToolVersion,
+        feature: &'static str,
+    },
+
     /// External tool failed.
     #[error(
         "tool execution failed: {tool}\ncommand: {command}\nstatus: {status}\nstdout: {stdout}\nstderr: {stderr}"
@@ -61,6 +275,123 @@ pub enum PdfError {
     /// Invalid argument.
     #[error("invalid argument: {0}")]
     InvalidArgument(String),
+
+    /// The in-process `PDFium` engine failed.
+    #[error("pdfium failed: {0}")]
+    Pdfium(String),
+
+    /// An operation did not finish in time (and was stopped, for tools).
+    #[error("{tool} timed out after {}s", after.as_secs_f64())]
+    Timeout {
+        /// Tool or engine name.
+        tool: &'static str,
+        after: Duration,
+    },
+
+    /// An encrypted document could not be decrypted.
+    #[error("cannot open encrypted pdf: {path}: {reason}")]
+    Encrypted { path: PathBuf, reason: String },
+
+    /// Some or all of the inputs of a multi-input operation failed (see
+    /// [`check_inputs`]).
+    #[error("{failed} of {total} input(s) failed")]
+    InputsFailed { failed: usize, total: usize },
+
+    /// An operation succeeded with warnings, which the caller treats as
+    /// errors (see [`collect_warnings`]).
+    #[error("{} warning(s) treated as errors: {}", warnings.len(), warnings.first().map(ToString::to_string).unwrap_or_default())]
+    Warnings { warnings: Vec<Warning> },
+}
+
+impl PdfError {
+    /// Stable, machine-readable identifier of the error kind.
+    ///
+    /// | code | exit code |
+    /// |------|-----------|
+    /// | `invalid_argument` | 3 |
+    /// | `input_not_found` | 4 |
+    /// | `pdf_parse` | 5 |
+    /// | `missing_tool` | 6 |
+    /// | `tool_too_old` | 7 |
+    /// | `tool_failed` | 8 |
+    /// | `fonts_not_embedded` | 9 |
+    /// | `io` | 10 |
+    /// | `pdfium` | 11 |
+    /// | `timeout` | 12 |
+    /// | `encrypted` | 13 |
+    /// | `some_inputs_failed` | 14 |
+    /// | `all_inputs_failed` | 15 |
+    /// | `warnings` | 16 |
+    ///
+    /// Codes and exit codes are never reused; new variants get new ones.
+    #[must_use]
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidArgument(_) => "invalid_argument",
+            Self::InputNotFound(_) => "input_not_found",
+            Self::PdfParse { .. } => "pdf_parse",
+            Self::MissingTool { .. } => "missing_tool",
+            Self::ToolTooOld { .. } => "tool_too_old",
+            Self::ToolFailed { .. } => "tool_failed",
+            Self::FontsNotEmbedded { .. } => "fonts_not_embedded",
+            Self::Io(_) => "io",
+            Self::Pdfium(_) => "pdfium",
+            Self::Timeout { .. } => "timeout",
+            Self::Encrypted { .. } => "encrypted",
+            Self::InputsFailed { failed, total } if failed < total => "some_inputs_failed",
+            Self::InputsFailed { .. } => "all_inputs_failed",
+            Self::Warnings { .. } => "warnings",
+        }
+    }
+
+    /// Process exit code for this error (see [`PdfError::code`]). Codes 1
+    /// (other failures) and 2 (command-line usage) are left to the caller.
+    #[must_use]
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::InvalidArgument(_) => 3,
+            Self::InputNotFound(_) => 4,
+            Self::PdfParse { .. } => 5,
+            Self::MissingTool { .. } => 6,
+            Self::ToolTooOld { .. } => 7,
+            Self::ToolFailed { .. } => 8,
+            Self::FontsNotEmbedded { .. } => 9,
+            Self::Io(_) => 10,
+            Self::Pdfium(_) => 11,
+            Self::Timeout { .. } => 12,
+            Self::Encrypted { .. } => 13,
+            Self::InputsFailed { failed, total } if failed < total => 14,
+            Self::InputsFailed { .. } => 15,
+            Self::Warnings { .. } => 16,
+        }
+    }
+
+    /// The external tool involved, if any.
+    #[must_use]
+    pub fn tool(&self) -> Option<&'static str> {
+        match self {
+            Self::MissingTool { tool, .. }
+            | Self::ToolTooOld { tool, .. }
+            | Self::ToolFailed { tool, .. }
+            | Self::Timeout { tool, .. } => Some(tool),
+            _ => None,
+        }
+    }
+
+    /// Advice for fixing the error, if any.
+    #[must_use]
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            Self::MissingTool { hint, .. } => Some(hint.clone()),
+            Self::ToolTooOld { tool, required, .. } => {
+                Some(format!("upgrade {tool} to version {required} or newer"))
+            }
+            Self::Encrypted { .. } => {
+                Some("pass the document's user or owner password".to_string())
+            }
+            _ => None,
+        }
+    }
 }
 
 /// Validates that the input path exists (and is a file).
@@ -73,36 +404,89 @@ pub fn validate_input_file(path: &Path) -> Result<()> {
 
 /// Basic information about a PDF file.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)] // independent file features
 pub struct PdfInfo {
     /// Total number of pages.
     pub pages: u32,
     /// Document metadata. Keys are typical PDF Info dict entries (e.g. `Title`).
     pub metadata: BTreeMap<String, String>,
+    /// Fonts referenced from page resources, sorted by name.
+    pub fonts: Vec<FontInfo>,
+    /// XFA form, if the document has one.
+    pub xfa: Option<XfaForm>,
+    /// Encryption, if the document is encrypted.
+    pub encryption: Option<EncryptionInfo>,
+    /// PDF version from the file header, e.g. `1.7`.
+    pub version: String,
+    /// PDF version from the catalog (`/Version`), which overrides the
+    /// header's when later.
+    pub catalog_version: Option<String>,
+    /// Whether the file is linearized ("fast web view").
+    pub linearized: bool,
+    /// Whether the cross-reference table is stored as a stream (PDF 1.5+).
+    pub xref_streams: bool,
+    /// Whether some objects are stored in object streams (PDF 1.5+).
+    pub object_streams: bool,
+    /// Whether the document is tagged (`/MarkInfo /Marked true`).
+    pub tagged: bool,
+    /// Revisions appended to the original file.
+    pub incremental_updates: u32,
+    /// The document ID (`/ID`) as two lower-case hex strings: the permanent
+    /// and the changing identifier.
+    pub document_id: Option<[String; 2]>,
 }
 
 /// Read PDF info **without external tools**.
 ///
-/// Returns at least page count; metadata may be empty.
+/// Returns at least page count; metadata may be empty. When only the page
+/// count is needed, [`page_count`] is faster.
+///
+/// Encrypted documents that open without a password are decrypted. Those
+/// that need one are described by [`PdfInfo::encryption`] with no metadata;
+/// use [`info_with_password`] for the rest.
 pub fn info(path: impl AsRef<Path>) -> Result<PdfInfo> {
-    let path = path.as_ref();
-    validate_input_file(path)?;
+    read_info(path.as_ref(), None)
+}
 
-    let doc = lopdf::Document::load(path).map_err(|source| PdfError::PdfParse {
-        path: path.to_path_buf(),
-        source,
-    })?;
+/// [`info`] for an encrypted document, decrypted with `password` (the user
+/// or owner password). A wrong password is a [`PdfError::Encrypted`] error.
+pub fn info_with_password(path: impl AsRef<Path>, password: &str) -> Result<PdfInfo> {
+    read_info(path.as_ref(), Some(password))
+}
+
+fn read_info(path: &Path, password: Option<&str>) -> Result<PdfInfo> {
+    let mut doc = load_for_reading(path)?;
+    let encryption = encryption::decrypt(&mut doc, path, password)?;
+    let locked = encryption.as_ref().is_some_and(|e| !e.decrypted);
+    let features = features::detect(&doc, path)?;
 
     let pages = u32::try_from(doc.get_pages().len())
         .map_err(|_| PdfError::InvalidArgument("page count overflow".to_string()))?;
 
     let mut metadata = BTreeMap::new();
-    if let Ok(trailer) = doc.trailer.get(b"Info") {
+    // The strings of a document that is still encrypted are ciphertext.
+    if let (false, Ok(trailer)) = (locked, doc.trailer.get(b"Info")) {
         if let Ok(info_ref) = trailer.as_reference() {
             if let Ok(obj) = doc.get_object(info_ref) {
                 if let Ok(dict) = obj.as_dict() {
                     for (k, v) in dict {
                         let key = String::from_utf8_lossy(k).to_string();
-                        if let Some(val) = pdf_object_to_string(v) {
+                        let val = match v {
+                            lopdf::Object::String(bytes, _) => {
+                                Some(metadata::try_decode_text_string(bytes).unwrap_or_else(|| {
+                                    warnings::warn(
+                                        WarningKind::LossyMetadata,
+                                        format!(
+                                            "{}: /{key} could not be decoded exactly; undecodable bytes were replaced",
+                                            path.display()
+                                        ),
+                                    );
+                                    metadata::decode_text_string(bytes)
+                                }))
+                            }
+                            other => pdf_object_to_string(other),
+                        };
+                        if let Some(val) = val {
                             metadata.insert(key, val);
                         }
                     }
@@ -111,7 +495,46 @@ pub fn info(path: impl AsRef<Path>) -> Result<PdfInfo> {
         }
     }
 
-    Ok(PdfInfo { pages, metadata })
+    Ok(PdfInfo {
+        pages,
+        metadata,
+        fonts: fonts::collect_fonts(&doc),
+        xfa: xfa::detect(&doc),
+        encryption,
+        version: features.version,
+        catalog_version: features.catalog_version,
+        linearized: features.linearized,
+        xref_streams: features.xref_streams,
+        object_streams: features.object_streams,
+        tagged: features.tagged,
+        incremental_updates: features.incremental_updates,
+        document_id: docid::document_id(&doc),
+    })
+}
+
+/// Page count **without external tools**, faster and leaner than [`info`]
+/// on large files.
+///
+/// Only the document structure is loaded (see [`set_low_memory`]). The
+/// count is the root
+/// page tree's `/Count`, or the number of pages found in the tree if that
+/// is missing.
+pub fn page_count(path: impl AsRef<Path>) -> Result<u32> {
+    let doc = load_structure(path.as_ref())?;
+    let count = doc
+        .catalog()
+        .and_then(|catalog| catalog.get(b"Pages"))
+        .and_then(lopdf::Object::as_reference)
+        .and_then(|id| doc.get_dictionary(id))
+        .and_then(|pages| pages.get(b"Count"))
+        .and_then(lopdf::Object::as_i64)
+        .ok()
+        .and_then(|count| u32::try_from(count).ok());
+    match count {
+        Some(count) => Ok(count),
+        None => u32::try_from(doc.get_pages().len())
+            .map_err(|_| PdfError::InvalidArgument("page count overflow".to_string())),
+    }
 }
 
 fn pdf_object_to_string(obj: &lopdf::Object) -> Option<String> {
@@ -125,8 +548,34 @@ fn pdf_object_to_string(obj: &lopdf::Object) -> Option<String> {
     }
 }
 
+/// `s` as a quoted JSON string, as the JSON output of `pdfcli` and the C ABI
+/// writes strings.
+#[must_use]
+pub fn json_string(s: &str) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", u32::from(c));
+            }
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
 /// Compression preset for `compress`.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum CompressPreset {
     /// `/screen`
     Screen,
@@ -152,6 +601,68 @@ impl CompressPreset {
     }
 }
 
+impl std::str::FromStr for CompressPreset {
+    type Err = PdfError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "screen" => Ok(Self::Screen),
+            "ebook" => Ok(Self::Ebook),
+            "printer" => Ok(Self::Printer),
+            "prepress" => Ok(Self::Prepress),
+            "default" => Ok(Self::Default),
+            other => Err(PdfError::InvalidArgument(format!(
+                "unknown compress preset: {other} (expected screen, ebook, printer, prepress, or default)"
+            ))),
+        }
+    }
+}
+
+/// Target color space for [`convert_colorspace`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// `DeviceGray`
+    Gray,
+    /// `DeviceCMYK`
+    Cmyk,
+    /// `DeviceRGB`
+    Rgb,
+}
+
+impl std::str::FromStr for ColorSpace {
+    type Err = PdfError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "gray" | "grey" => Ok(Self::Gray),
+            "cmyk" => Ok(Self::Cmyk),
+            "rgb" => Ok(Self::Rgb),
+            other => Err(PdfError::InvalidArgument(format!(
+                "unknown color space: {other} (expected gray, cmyk, or rgb)"
+            ))),
+        }
+    }
+}
+
+impl ColorSpace {
+    fn as_gs_args(self) -> [&'static str; 2] {
+        match self {
+            Self::Gray => [
+                "-sColorConversionStrategy=Gray",
+                "-sProcessColorModel=DeviceGray",
+            ],
+            Self::Cmyk => [
+                "-sColorConversionStrategy=CMYK",
+                "-sProcessColorModel=DeviceCMYK",
+            ],
+            Self::Rgb => [
+                "-sColorConversionStrategy=RGB",
+                "-sProcessColorModel=DeviceRGB",
+            ],
+        }
+    }
+}
+
 /// Page selection for operations like rotate.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PageSelection {
@@ -159,37 +670,131 @@ pub enum PageSelection {
     All,
     /// Apply to a 1-based inclusive page range.
     Range { start: u32, end: u32 },
+    /// Apply to the pages from 1-based `start` to the end of the document.
+    From { start: u32 },
+}
+
+impl std::str::FromStr for PageSelection {
+    type Err = PdfError;
+
+    /// `all`, a 1-based inclusive range such as `1-3`, a single page such as
+    /// `4`, or an open range such as `5-` (page 5 to the end).
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("all") {
+            return Ok(Self::All);
+        }
+        let invalid = |msg: String| PdfError::InvalidArgument(msg);
+        let page = |n: &str| {
+            let n = n.trim();
+            match n.parse::<u32>() {
+                Ok(0) => Err(invalid(format!("pages are 1-based; got {s:?}"))),
+                Ok(page) => Ok(page),
+                Err(e) => Err(invalid(format!(
+                    "expected <start>-<end>, <page>, or <start>- (e.g. 1-3), got {s:?}: \
+                     invalid page number {n:?}: {e}"
+                ))),
+            }
+        };
+        let Some((start, end)) = s.split_once('-') else {
+            let page = page(s)?;
+            return Ok(Self::Range {
+                start: page,
+                end: page,
+            });
+        };
+        let start = page(start)?;
+        if end.trim().is_empty() {
+            return Ok(Self::From { start });
+        }
+        let end = page(end)?;
+        if start > end {
+            return Err(invalid(format!(
+                "page range start must be <= end; got {start}-{end}"
+            )));
+        }
+        Ok(Self::Range { start, end })
+    }
 }
 
 impl PageSelection {
+    /// Whether the selection includes 1-based `page`.
+    #[must_use]
+    pub fn contains(&self, page: u32) -> bool {
+        match *self {
+            Self::All => true,
+            Self::Range { start, end } => (start..=end).contains(&page),
+            Self::From { start } => page >= start,
+        }
+    }
+
+    /// Whether the two selections have a page in common.
+    fn overlaps(&self, other: &Self) -> bool {
+        let bounds = |pages: &Self| match *pages {
+            Self::All => (1, u32::MAX),
+            Self::Range { start, end } => (start, end),
+            Self::From { start } => (start, u32::MAX),
+        };
+        let ((a, b), (c, d)) = (bounds(self), bounds(other));
+        a <= d && c <= b
+    }
+
     fn to_qpdf_arg(&self) -> Option<String> {
         match self {
             Self::All => None,
             Self::Range { start, end } => Some(format!("{start}-{end}")),
+            Self::From { start } => Some(format!("{start}-z")),
         }
     }
 }
 
-/// Merge multiple PDFs into one using `qpdf`.
+/// Merge multiple PDFs into one (with `qpdf`, `mutool`, or natively; see
+/// [`select_engine`]). Shorthand for [`Merge`].
 pub fn merge(inputs: &[impl AsRef<Path>], output: impl AsRef<Path>) -> Result<()> {
+    merge_builder(inputs, output).run()
+}
+
+/// What [`merge`] would run.
+pub fn merge_plan(inputs: &[impl AsRef<Path>], output: impl AsRef<Path>) -> Result<Plan> {
+    merge_builder(inputs, output).plan()
+}
+
+fn merge_builder(inputs: &[impl AsRef<Path>], output: impl AsRef<Path>) -> Merge {
+    Merge::new(inputs.iter().map(|p| p.as_ref().to_path_buf())).output(output.as_ref())
+}
+
+fn merge_inputs(inputs: &[PathBuf]) -> Result<Vec<&Path>> {
     if inputs.is_empty() {
         return Err(PdfError::InvalidArgument(
             "merge requires at least one input".to_string(),
         ));
     }
-    for p in inputs {
-        validate_input_file(p.as_ref())?;
+    let inputs: Vec<&Path> = inputs.iter().map(PathBuf::as_path).collect();
+    for p in &inputs {
+        validate_input_file(p)?;
     }
+    Ok(inputs)
+}
 
+fn merge_command(inputs: &[&Path], output: &Path) -> Result<Command> {
     let qpdf = find_tool(Tool::Qpdf)?;
     let mut cmd = Command::new(qpdf);
     cmd.arg("--empty")
         .arg("--pages")
-        .args(inputs.iter().map(|p| p.as_ref().as_os_str()))
+        .args(inputs.iter().map(|p| p.as_os_str()))
         .arg("--")
-        .arg(output.as_ref().as_os_str());
+        .arg(output.as_os_str());
+    Ok(cmd)
+}
 
-    run_tool(Tool::Qpdf, cmd)
+fn mutool_merge_command(inputs: &[&Path], output: &Path) -> Result<Command> {
+    let mutool = find_tool(Tool::Mutool)?;
+    let mut cmd = Command::new(mutool);
+    cmd.arg("merge")
+        .arg("-o")
+        .arg(output.as_os_str())
+        .args(inputs.iter().map(|p| p.as_os_str()));
+    Ok(cmd)
 }
 
 /// Split a PDF into pages using `qpdf`.
@@ -203,13 +808,28 @@ pub fn split_pages(
     out_dir: impl AsRef<Path>,
     pattern: Option<&str>,
 ) -> Result<()> {
-    validate_input_file(input.as_ref())?;
+    let cmd = split_pages_command(input.as_ref(), out_dir.as_ref(), pattern)?;
+    run_tool_writing(Tool::Qpdf, cmd, out_dir.as_ref())
+}
+
+/// What [`split_pages`] would run.
+pub fn split_pages_plan(
+    input: impl AsRef<Path>,
+    out_dir: impl AsRef<Path>,
+    pattern: Option<&str>,
+) -> Result<Plan> {
+    let cmd = split_pages_command(input.as_ref(), out_dir.as_ref(), pattern)?;
+    Ok(Plan::new().run(Tool::Qpdf, &cmd).write(out_dir.as_ref()))
+}
+
+fn split_pages_command(input: &Path, out_dir: &Path, pattern: Option<&str>) -> Result<Command> {
+    validate_input_file(input)?;
     let qpdf = find_tool(Tool::Qpdf)?;
 
     let pattern = if let Some(p) = pattern {
         p.to_string()
     } else {
-        let mut p = PathBuf::from(out_dir.as_ref());
+        let mut p = PathBuf::from(out_dir);
         p.push("page-%d.pdf");
         p.to_string_lossy().to_string()
     };
@@ -221,34 +841,59 @@ pub fn split_pages(
     }
 
     let mut cmd = Command::new(qpdf);
-    cmd.arg("--split-pages")
-        .arg(input.as_ref().as_os_str())
-        .arg(pattern);
-
-    run_tool(Tool::Qpdf, cmd)
+    cmd.arg("--split-pages").arg(input.as_os_str()).arg(pattern);
+    Ok(cmd)
 }
 
-/// Extract text using Poppler's `pdftotext`.
+/// Extract text (with Poppler's `pdftotext`, `mutool`, `PDFium`, or natively; see
+/// [`select_engine`]). Shorthand for [`ExtractText`].
 ///
-/// If `output` is `None`, writes to stdout.
+/// If `output` is `None`, the text is returned instead of written.
 pub fn extract_text(input: impl AsRef<Path>, output: Option<impl AsRef<Path>>) -> Result<String> {
-    validate_input_file(input.as_ref())?;
+    extract_text_builder(input, output).run()
+}
+
+/// What [`extract_text`] would run.
+pub fn extract_text_plan(
+    input: impl AsRef<Path>,
+    output: Option<impl AsRef<Path>>,
+) -> Result<Plan> {
+    extract_text_builder(input, output).plan()
+}
+
+fn extract_text_builder(input: impl AsRef<Path>, output: Option<impl AsRef<Path>>) -> ExtractText {
+    let builder = ExtractText::new(input.as_ref());
+    match output {
+        Some(output) => builder.output(output.as_ref()),
+        None => builder,
+    }
+}
+
+fn extract_text_command(input: &Path, output: Option<&Path>) -> Result<Command> {
     let pdftotext = find_tool(Tool::Pdftotext)?;
 
     let mut cmd = Command::new(pdftotext);
-    cmd.arg(input.as_ref().as_os_str());
-    if let Some(out) = output {
-        cmd.arg(out.as_ref().as_os_str());
-        run_tool(Tool::Pdftotext, cmd)?;
-        Ok(String::new())
-    } else {
-        cmd.arg("-");
-        let out = run_tool_capture(Tool::Pdftotext, cmd)?;
-        Ok(out)
+    // UTF-8 whatever the locale or xpdfrc say, since the text is read as
+    // UTF-8.
+    cmd.arg("-enc").arg("UTF-8").arg(input.as_os_str());
+    cmd.arg(output.map_or(OsStr::new("-"), Path::as_os_str));
+    Ok(cmd)
+}
+
+/// `mutool draw -F txt`, writing to stdout if `output` is `None`.
+fn mutool_text_command(input: &Path, output: Option<&Path>) -> Result<Command> {
+    let mutool = find_tool(Tool::Mutool)?;
+    let mut cmd = Command::new(mutool);
+    cmd.arg("draw").arg("-q").arg("-F").arg("txt");
+    if let Some(output) = output {
+        cmd.arg("-o").arg(output.as_os_str());
     }
+    cmd.arg(input.as_os_str());
+    Ok(cmd)
 }
 
-/// Rotate pages using `qpdf`.
+/// Rotate pages (with `qpdf`, or natively; see [`select_engine`]). Shorthand
+/// for [`Rotate`].
 ///
 /// `degrees` must be one of: 0, 90, 180, 270.
 /// `pages` defaults to `All`.
@@ -258,173 +903,1335 @@ pub fn rotate(
     degrees: u16,
     pages: Option<PageSelection>,
 ) -> Result<()> {
-    validate_input_file(input.as_ref())?;
-    let qpdf = find_tool(Tool::Qpdf)?;
+    rotate_builder(input, output, degrees, pages).run()
+}
+
+/// What [`rotate`] would run.
+pub fn rotate_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    degrees: u16,
+    pages: Option<PageSelection>,
+) -> Result<Plan> {
+    rotate_builder(input, output, degrees, pages).plan()
+}
+
+fn rotate_builder(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    degrees: u16,
+    pages: Option<PageSelection>,
+) -> Rotate {
+    Rotate::new(input.as_ref())
+        .output(output.as_ref())
+        .degrees(degrees)
+        .pages(pages.unwrap_or(PageSelection::All))
+}
 
+fn validate_rotation(input: &Path, degrees: u16) -> Result<()> {
+    validate_input_file(input)?;
     if !matches!(degrees, 0 | 90 | 180 | 270) {
         return Err(PdfError::InvalidArgument(
             "degrees must be 0, 90, 180, or 270".to_string(),
         ));
     }
+    Ok(())
+}
 
-    let pages = pages.unwrap_or(PageSelection::All);
-    let mut rotate_arg = format!("+{degrees}");
-    if let Some(sel) = pages.to_qpdf_arg() {
-        rotate_arg.push(':');
-        rotate_arg.push_str(&sel);
-    }
-
-    let mut cmd = Command::new(qpdf);
-    cmd.arg("--rotate")
-        .arg(rotate_arg)
-        .arg(input.as_ref().as_os_str())
-        .arg(output.as_ref().as_os_str());
-
-    run_tool(Tool::Qpdf, cmd)
+fn rotate_command(
+    input: &Path,
+    output: &Path,
+    degrees: u16,
+    pages: &PageSelection,
+) -> Result<Command> {
+    rotate_spec_command(input, output, &[(pages.clone(), degrees)])
 }
 
-/// Compress/optimize a PDF using Ghostscript.
-pub fn compress(
+/// Rotate different pages by different amounts in one pass (with `qpdf`, or
+/// natively; see [`select_engine`]). Each entry adds its clockwise rotation
+/// (0, 90, 180, or 270) to the pages it selects; selections must not
+/// overlap.
+pub fn rotate_spec(
     input: impl AsRef<Path>,
     output: impl AsRef<Path>,
-    preset: CompressPreset,
+    spec: &[(PageSelection, u16)],
 ) -> Result<()> {
-    validate_input_file(input.as_ref())?;
-    let gs = find_tool(Tool::Ghostscript)?;
-
-    let mut cmd = Command::new(gs);
-    cmd.arg("-sDEVICE=pdfwrite")
-        .arg("-dCompatibilityLevel=1.4")
-        .arg(format!("-dPDFSETTINGS={}", preset.as_gs_setting()))
-        .arg("-dNOPAUSE")
-        .arg("-dBATCH")
-        .arg("-dSAFER")
-        .arg(format!("-sOutputFile={}", output.as_ref().display()))
-        .arg(input.as_ref().as_os_str());
-
-    run_tool(Tool::Ghostscript, cmd)
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_rotation_spec(input, spec)?;
+    match select_engine(Operation::Rotate)? {
+        Engine::Qpdf => run_tool_writing(
+            Tool::Qpdf,
+            rotate_spec_command(input, output, spec)?,
+            output,
+        ),
+        Engine::Native => native::rotate_spec(input, output, spec),
+        other => Err(engine::unsupported(Operation::Rotate, other)),
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Tool {
-    Qpdf,
-    Pdftotext,
-    Ghostscript,
+/// What [`rotate_spec`] would run.
+pub fn rotate_spec_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    spec: &[(PageSelection, u16)],
+) -> Result<Plan> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_rotation_spec(input, spec)?;
+    let plan = match select_engine(Operation::Rotate)? {
+        Engine::Qpdf => Plan::new().run(Tool::Qpdf, &rotate_spec_command(input, output, spec)?),
+        Engine::Native => spec.iter().fold(Plan::new(), |plan, (pages, degrees)| {
+            plan.native(format!("rotate pages {pages} by {degrees} degrees"))
+        }),
+        other => return Err(engine::unsupported(Operation::Rotate, other)),
+    };
+    Ok(plan.write(output))
 }
 
-impl Tool {
-    fn name(self) -> &'static str {
-        match self {
-            Self::Qpdf => "qpdf",
-            Self::Pdftotext => "pdftotext",
-            Self::Ghostscript => "ghostscript",
-        }
+fn validate_rotation_spec(input: &Path, spec: &[(PageSelection, u16)]) -> Result<()> {
+    if spec.is_empty() {
+        return Err(PdfError::InvalidArgument(
+            "rotation spec must name at least one page range".to_string(),
+        ));
     }
-
-    fn env_override(self) -> &'static str {
-        match self {
-            Self::Qpdf => "PDFCLI_QPDF",
-            Self::Pdftotext => "PDFCLI_PDFTOTEXT",
-            Self::Ghostscript => "PDFCLI_GS",
+    for (i, (pages, degrees)) in spec.iter().enumerate() {
+        validate_rotation(input, *degrees)?;
+        if let Some((other, _)) = spec[..i].iter().find(|(other, _)| other.overlaps(pages)) {
+            return Err(PdfError::InvalidArgument(format!(
+                "rotation spec selects pages twice: {other} and {pages}"
+            )));
         }
     }
+    Ok(())
+}
 
-    fn default_exe_names(self) -> &'static [&'static str] {
-        match self {
-            Self::Qpdf => &["qpdf"],
-            Self::Pdftotext => &["pdftotext"],
-            Self::Ghostscript => &["gs", "gswin64c", "gswin32c"],
+/// `qpdf` with one `--rotate` argument per entry of `spec`.
+fn rotate_spec_command(
+    input: &Path,
+    output: &Path,
+    spec: &[(PageSelection, u16)],
+) -> Result<Command> {
+    let qpdf = find_tool(Tool::Qpdf)?;
+
+    let mut cmd = Command::new(qpdf);
+    for (pages, degrees) in spec {
+        let mut rotate_arg = format!("+{degrees}");
+        if let Some(sel) = pages.to_qpdf_arg() {
+            rotate_arg.push(':');
+            rotate_arg.push_str(&sel);
         }
+        cmd.arg("--rotate").arg(rotate_arg);
     }
+    cmd.arg(input.as_os_str()).arg(output.as_os_str());
+    Ok(cmd)
+}
 
-    fn install_hint(self) -> String {
-        let tool = self.name();
-        let mac = match self {
-            Self::Ghostscript => "brew install ghostscript",
-            _ => &format!("brew install {tool}"),
-        };
-        let ubuntu = match self {
-            Self::Pdftotext => "sudo apt-get update && sudo apt-get install -y poppler-utils",
-            Self::Ghostscript => "sudo apt-get update && sudo apt-get install -y ghostscript",
-            Self::Qpdf => "sudo apt-get update && sudo apt-get install -y qpdf",
-        };
-        let windows = match self {
-            Self::Ghostscript => "choco install ghostscript OR scoop install ghostscript",
-            Self::Pdftotext => "choco install poppler OR scoop install poppler",
-            Self::Qpdf => "choco install qpdf OR scoop install qpdf",
-        };
-
-        format!(
-            "Set {} to a full path, or install:\n  macOS: {}\n  Ubuntu/Debian: {}\n  Windows: {}",
-            self.env_override(),
-            mac,
-            ubuntu,
-            windows
-        )
+/// Render pages to PNG images named `page-<n>.png` in `out_dir` (with
+/// Ghostscript, `mutool`, or `PDFium`; see [`select_engine`]).
+pub fn render(input: impl AsRef<Path>, out_dir: impl AsRef<Path>, dpi: u32) -> Result<()> {
+    let (input, out_dir) = (input.as_ref(), out_dir.as_ref());
+    validate_render(input, dpi)?;
+    match select_engine(Operation::Render)? {
+        Engine::Ghostscript => run_tool_writing(
+            Tool::Ghostscript,
+            render_command(input, out_dir, dpi)?,
+            out_dir,
+        ),
+        Engine::Mutool => run_tool_writing(
+            Tool::Mutool,
+            mutool_render_command(input, out_dir, dpi)?,
+            out_dir,
+        ),
+        #[cfg(feature = "pdfium")]
+        Engine::Pdfium => pdfium::render(input, out_dir, dpi),
+        other => Err(engine::unsupported(Operation::Render, other)),
     }
 }
 
-fn find_tool(tool: Tool) -> Result<PathBuf> {
-    if let Some(val) = std::env::var_os(tool.env_override()) {
-        let p = PathBuf::from(val);
-        if p.exists() {
-            return Ok(p);
+/// What [`render`] would run.
+pub fn render_plan(input: impl AsRef<Path>, out_dir: impl AsRef<Path>, dpi: u32) -> Result<Plan> {
+    let (input, out_dir) = (input.as_ref(), out_dir.as_ref());
+    validate_render(input, dpi)?;
+    let plan = match select_engine(Operation::Render)? {
+        Engine::Ghostscript => {
+            Plan::new().run(Tool::Ghostscript, &render_command(input, out_dir, dpi)?)
         }
-        return Err(PdfError::MissingTool {
-            tool: tool.name(),
-            hint: format!(
-                "{} was set to {}, but that path does not exist.\n\n{}",
-                tool.env_override(),
-                p.display(),
-                tool.install_hint()
-            ),
-        });
-    }
-
-    for exe in tool.default_exe_names() {
-        if let Ok(p) = which::which(exe) {
-            return Ok(p);
+        Engine::Mutool => {
+            Plan::new().run(Tool::Mutool, &mutool_render_command(input, out_dir, dpi)?)
         }
-    }
-
-    Err(PdfError::MissingTool {
-        tool: tool.name(),
-        hint: tool.install_hint(),
-    })
+        Engine::Pdfium => Plan::new().native(format!("render every page at {dpi} dpi with pdfium")),
+        other => return Err(engine::unsupported(Operation::Render, other)),
+    };
+    Ok(plan.write(out_dir))
 }
 
-fn run_tool(tool: Tool, mut cmd: Command) -> Result<()> {
-    let command_str = command_to_string(&cmd);
-    let out = cmd.output()?;
-    if out.status.success() {
-        return Ok(());
+/// Render the first page to the PNG file `output`, e.g. as a cover image
+/// (with Ghostscript, `mutool`, or `PDFium`, like [`render`]).
+pub fn thumbnail(input: impl AsRef<Path>, output: impl AsRef<Path>, dpi: u32) -> Result<()> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_render(input, dpi)?;
+    match select_engine(Operation::Render)? {
+        Engine::Ghostscript => run_tool_writing(
+            Tool::Ghostscript,
+            thumbnail_command(input, output, dpi)?,
+            output,
+        ),
+        Engine::Mutool => run_tool_writing(
+            Tool::Mutool,
+            mutool_thumbnail_command(input, output, dpi)?,
+            output,
+        ),
+        #[cfg(feature = "pdfium")]
+        Engine::Pdfium => pdfium::render_first_page(input, output, dpi),
+        other => Err(engine::unsupported(Operation::Render, other)),
     }
+}
 
-    Err(PdfError::ToolFailed {
-        tool: tool.name(),
-        command: command_str,
-        status: out.status.code().unwrap_or(-1),
-        stdout: String::from_utf8_lossy(&out.stdout).to_string(),
-        stderr: String::from_utf8_lossy(&out.stderr).to_string(),
-    })
+/// What [`thumbnail`] would run.
+pub fn thumbnail_plan(input: impl AsRef<Path>, output: impl AsRef<Path>, dpi: u32) -> Result<Plan> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_render(input, dpi)?;
+    let plan = match select_engine(Operation::Render)? {
+        Engine::Ghostscript => {
+            Plan::new().run(Tool::Ghostscript, &thumbnail_command(input, output, dpi)?)
+        }
+        Engine::Mutool => {
+            Plan::new().run(Tool::Mutool, &mutool_thumbnail_command(input, output, dpi)?)
+        }
+        Engine::Pdfium => Plan::new().native(format!("render page 1 at {dpi} dpi with pdfium")),
+        other => return Err(engine::unsupported(Operation::Render, other)),
+    };
+    Ok(plan.write(output))
 }
 
-fn run_tool_capture(tool: Tool, mut cmd: Command) -> Result<String> {
-    let command_str = command_to_string(&cmd);
-    let out: Output = cmd.output()?;
-    if out.status.success() {
-        return Ok(String::from_utf8_lossy(&out.stdout).to_string());
+fn validate_render(input: &Path, dpi: u32) -> Result<()> {
+    validate_input_file(input)?;
+    if !(1..=2400).contains(&dpi) {
+        return Err(PdfError::InvalidArgument(format!(
+            "dpi must be between 1 and 2400, got {dpi}"
+        )));
     }
+    Ok(())
+}
 
-    Err(PdfError::ToolFailed {
+fn render_command(input: &Path, out_dir: &Path, dpi: u32) -> Result<Command> {
+    let gs = find_tool(Tool::Ghostscript)?;
+    let mut cmd = Command::new(gs);
+    cmd.arg("-sDEVICE=png16m")
+        .arg(format!("-r{dpi}"))
+        .arg("-dNOPAUSE")
+        .arg("-dBATCH")
+        .arg("-dSAFER")
+        .arg(format!(
+            "-sOutputFile={}",
+            out_dir.join("page-%d.png").display()
+        ))
+        .arg(input.as_os_str());
+    Ok(cmd)
+}
+
+fn mutool_render_command(input: &Path, out_dir: &Path, dpi: u32) -> Result<Command> {
+    let mutool = find_tool(Tool::Mutool)?;
+    let mut cmd = Command::new(mutool);
+    cmd.arg("draw")
+        .arg("-q")
+        .arg("-F")
+        .arg("png")
+        .arg("-r")
+        .arg(dpi.to_string())
+        .arg("-o")
+        .arg(out_dir.join("page-%d.png").as_os_str())
+        .arg(input.as_os_str());
+    Ok(cmd)
+}
+
+fn thumbnail_command(input: &Path, output: &Path, dpi: u32) -> Result<Command> {
+    let gs = find_tool(Tool::Ghostscript)?;
+    let mut cmd = Command::new(gs);
+    cmd.arg("-sDEVICE=png16m")
+        .arg(format!("-r{dpi}"))
+        .arg("-dFirstPage=1")
+        .arg("-dLastPage=1")
+        .arg("-dNOPAUSE")
+        .arg("-dBATCH")
+        .arg("-dSAFER")
+        .arg(format!("-sOutputFile={}", output.display()))
+        .arg(input.as_os_str());
+    Ok(cmd)
+}
+
+fn mutool_thumbnail_command(input: &Path, output: &Path, dpi: u32) -> Result<Command> {
+    let mutool = find_tool(Tool::Mutool)?;
+    let mut cmd = Command::new(mutool);
+    cmd.arg("draw")
+        .arg("-q")
+        .arg("-F")
+        .arg("png")
+        .arg("-r")
+        .arg(dpi.to_string())
+        .arg("-o")
+        .arg(output.as_os_str())
+        .arg(input.as_os_str())
+        .arg("1");
+    Ok(cmd)
+}
+
+/// Size comparison between the input and output of [`compress`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompressReport {
+    /// Input file size in bytes.
+    pub input_bytes: u64,
+    /// Output file size in bytes.
+    pub output_bytes: u64,
+    /// `output_bytes / input_bytes` (`1.0` if the input is empty).
+    pub ratio: f64,
+}
+
+impl CompressReport {
+    /// Build a report from raw byte counts.
+    #[must_use]
+    pub fn from_sizes(input_bytes: u64, output_bytes: u64) -> Self {
+        #[allow(clippy::cast_precision_loss)]
+        let ratio = if input_bytes == 0 {
+            1.0
+        } else {
+            output_bytes as f64 / input_bytes as f64
+        };
+        Self {
+            input_bytes,
+            output_bytes,
+            ratio,
+        }
+    }
+
+    /// Percentage saved relative to the input (negative if the output grew).
+    #[must_use]
+    pub fn savings_percent(&self) -> f64 {
+        (1.0 - self.ratio) * 100.0
+    }
+}
+
+/// Compress/optimize a PDF using Ghostscript. Shorthand for [`Compress`].
+///
+/// Returns a [`CompressReport`] comparing input and output sizes.
+pub fn compress(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    preset: CompressPreset,
+) -> Result<CompressReport> {
+    Compress::new(input.as_ref())
+        .output(output.as_ref())
+        .preset(preset)
+        .run()
+}
+
+/// What [`compress`] would run.
+pub fn compress_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    preset: CompressPreset,
+) -> Result<Plan> {
+    Compress::new(input.as_ref())
+        .output(output.as_ref())
+        .preset(preset)
+        .plan()
+}
+
+/// Convert all page content to a single color space using Ghostscript.
+pub fn convert_colorspace(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    to: ColorSpace,
+) -> Result<()> {
+    validate_input_file(input.as_ref())?;
+    gs_pdfwrite(input.as_ref(), output.as_ref(), None, &to.as_gs_args())
+}
+
+/// What [`convert_colorspace`] would run.
+pub fn convert_colorspace_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    to: ColorSpace,
+) -> Result<Plan> {
+    validate_input_file(input.as_ref())?;
+    let cmd = gs_pdfwrite_command(input.as_ref(), output.as_ref(), None, &to.as_gs_args())?;
+    Ok(Plan::new()
+        .run(Tool::Ghostscript, &cmd)
+        .write(output.as_ref()))
+}
+
+/// Flatten form fields and annotations into page content using `qpdf`.
+///
+/// Missing appearance streams are generated first so that flattened fields
+/// keep their visible values.
+pub fn flatten_annotations(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<()> {
+    validate_input_file(input.as_ref())?;
+    let cmd = flatten_annotations_command(input.as_ref(), output.as_ref())?;
+    run_tool_writing(Tool::Qpdf, cmd, output.as_ref())
+}
+
+/// What [`flatten_annotations`] would run.
+pub fn flatten_annotations_plan(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<Plan> {
+    validate_input_file(input.as_ref())?;
+    let cmd = flatten_annotations_command(input.as_ref(), output.as_ref())?;
+    Ok(Plan::new().run(Tool::Qpdf, &cmd).write(output.as_ref()))
+}
+
+fn flatten_annotations_command(input: &Path, output: &Path) -> Result<Command> {
+    let qpdf = find_tool_min(Tool::Qpdf, &[8, 3], "--flatten-annotations")?;
+
+    let mut cmd = Command::new(qpdf);
+    cmd.arg("--generate-appearances")
+        .arg("--flatten-annotations=all")
+        .arg(input.as_os_str())
+        .arg(output.as_os_str());
+    Ok(cmd)
+}
+
+/// Linearize ("fast web view") a PDF using `qpdf`.
+pub fn linearize(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<()> {
+    validate_input_file(input.as_ref())?;
+    run_tool_writing(
+        Tool::Qpdf,
+        linearize_command(input.as_ref(), output.as_ref())?,
+        output.as_ref(),
+    )
+}
+
+/// What [`linearize`] would run.
+pub fn linearize_plan(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<Plan> {
+    validate_input_file(input.as_ref())?;
+    let cmd = linearize_command(input.as_ref(), output.as_ref())?;
+    Ok(Plan::new().run(Tool::Qpdf, &cmd).write(output.as_ref()))
+}
+
+fn linearize_command(input: &Path, output: &Path) -> Result<Command> {
+    let qpdf = find_tool(Tool::Qpdf)?;
+
+    let mut cmd = Command::new(qpdf);
+    cmd.arg("--linearize")
+        .arg(input.as_os_str())
+        .arg(output.as_os_str());
+    Ok(cmd)
+}
+
+/// How [`qdf`] produced its output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QdfMode {
+    /// Full QDF output from `qpdf --qdf` (normalized content, annotated objects).
+    Qpdf,
+    /// Pure-Rust fallback: streams decompressed, object streams expanded.
+    Decompressed,
+}
+
+/// Write an uncompressed, hand-editable copy of a PDF for debugging.
+///
+/// Uses `qpdf --qdf --object-streams=disable` when qpdf is available and
+/// otherwise falls back to decompressing every stream with [`lopdf`].
+pub fn qdf(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<QdfMode> {
+    let input = input.as_ref();
+    let output = output.as_ref();
+    let Some(cmd) = qdf_command(input, output)? else {
+        let mut doc = load_document(input)?;
+        doc.objects.retain(|_, obj| {
+            !obj.as_stream()
+                .is_ok_and(|s| s.dict.type_is(b"ObjStm") || s.dict.type_is(b"XRef"))
+        });
+        doc.decompress();
+        save_document(&mut doc, output)?;
+        return Ok(QdfMode::Decompressed);
+    };
+
+    run_tool_writing(Tool::Qpdf, cmd, output)?;
+    Ok(QdfMode::Qpdf)
+}
+
+/// What [`qdf`] would do: run qpdf, or decompress natively without it.
+pub fn qdf_plan(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<Plan> {
+    let plan = match qdf_command(input.as_ref(), output.as_ref())? {
+        Some(cmd) => Plan::new().run(Tool::Qpdf, &cmd),
+        None => {
+            Plan::new().native("qpdf not found; decompress all streams and expand object streams")
+        }
+    };
+    Ok(plan.write(output.as_ref()))
+}
+
+/// The qpdf command for [`qdf`], or `None` if qpdf is not installed.
+fn qdf_command(input: &Path, output: &Path) -> Result<Option<Command>> {
+    validate_input_file(input)?;
+    let qpdf = match find_tool(Tool::Qpdf) {
+        Ok(qpdf) => qpdf,
+        Err(PdfError::MissingTool { .. }) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let mut cmd = Command::new(qpdf);
+    cmd.arg("--qdf")
+        .arg("--object-streams=disable")
+        .arg(input.as_os_str())
+        .arg(output.as_os_str());
+    Ok(Some(cmd))
+}
+
+/// Re-distill `input` through Ghostscript's `pdfwrite` device.
+///
+/// `extra` args are inserted before the output/input arguments. Output
+/// targets PDF 1.4 unless `extra` sets `-dCompatibilityLevel` itself.
+fn gs_pdfwrite(
+    input: &Path,
+    output: &Path,
+    preset: Option<CompressPreset>,
+    extra: &[&str],
+) -> Result<()> {
+    let cmd = gs_pdfwrite_command(input, output, preset, extra)?;
+    run_tool_writing(Tool::Ghostscript, cmd, output)
+}
+
+fn gs_pdfwrite_command(
+    input: &Path,
+    output: &Path,
+    preset: Option<CompressPreset>,
+    extra: &[&str],
+) -> Result<Command> {
+    let gs = find_tool(Tool::Ghostscript)?;
+
+    let mut cmd = Command::new(gs);
+    cmd.arg("-sDEVICE=pdfwrite");
+    if !extra.iter().any(|a| a.starts_with("-dCompatibilityLevel=")) {
+        cmd.arg("-dCompatibilityLevel=1.4");
+    }
+    if let Some(preset) = preset {
+        cmd.arg(format!("-dPDFSETTINGS={}", preset.as_gs_setting()));
+    }
+    cmd.arg("-dNOPAUSE")
+        .arg("-dBATCH")
+        .arg("-dSAFER")
+        .args(extra)
+        .arg(format!("-sOutputFile={}", output.display()))
+        .arg(input.as_os_str());
+    Ok(cmd)
+}
+
+/// Load a PDF with [`lopdf`], mapping parse failures to [`PdfError::PdfParse`].
+fn load_document(path: &Path) -> Result<lopdf::Document> {
+    validate_input_file(path)?;
+    let started = Instant::now();
+    let doc = lopdf::Document::load(path).map_err(|source| PdfError::PdfParse {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    tracing::debug!(
+        path = %path.display(),
+        objects = doc.objects.len(),
+        elapsed_ms = started.elapsed().as_millis(),
+        "loaded pdf"
+    );
+    warn_skipped_objects(&doc, path);
+    Ok(doc)
+}
+
+/// Files at least this large are read in low-memory mode (see
+/// [`set_low_memory`]) even when it is off: 256 MiB.
+pub const LOW_MEMORY_THRESHOLD: u64 = 256 << 20;
+
+static LOW_MEMORY: AtomicBool = AtomicBool::new(false);
+
+/// Read every file in low-memory mode, not only those of at least
+/// [`LOW_MEMORY_THRESHOLD`] bytes. Applies to the whole process.
+///
+/// In low-memory mode, operations that only inspect a document ([`info`],
+/// [`fonts`], [`links`], [`page_count`]) keep its structure but not its
+/// stream data: page content, images, and embedded fonts are dropped as the
+/// file is parsed, and streams are kept as their dictionaries. The file is
+/// memory-mapped rather than read into memory, so the operating system pages
+/// in the parts the parser touches and can drop them again; what stays
+/// allocated is the document structure. Operations that write a PDF always
+/// load everything.
+pub fn set_low_memory(enabled: bool) {
+    LOW_MEMORY.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether [`set_low_memory`] is on.
+#[must_use]
+pub fn low_memory() -> bool {
+    LOW_MEMORY.load(Ordering::Relaxed)
+}
+
+static DETERMINISTIC: AtomicBool = AtomicBool::new(false);
+
+/// Make PDFs written by pure Rust operations reproducible: the same input
+/// gives byte-identical output. Applies to the whole process.
+///
+/// The `CreationDate` and `ModDate` entries of the document information
+/// dictionary are dropped and the document ID is derived from the content.
+/// Output written by external tools is not affected.
+pub fn set_deterministic(enabled: bool) {
+    DETERMINISTIC.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether [`set_deterministic`] is on.
+#[must_use]
+pub fn deterministic() -> bool {
+    DETERMINISTIC.load(Ordering::Relaxed)
+}
+
+/// [`load_document`] for operations that do not need stream data:
+/// [`load_structure`] in low-memory mode or for large files.
+pub(crate) fn load_for_reading(path: &Path) -> Result<lopdf::Document> {
+    let large = fs::metadata(path).is_ok_and(|m| m.len() >= LOW_MEMORY_THRESHOLD);
+    if low_memory() || large {
+        load_structure(path)
+    } else {
+        load_document(path)
+    }
+}
+
+/// Load a PDF without its stream data: streams other than object streams
+/// become their dictionaries as the file is parsed. The file is
+/// memory-mapped, not read into a buffer.
+fn load_structure(path: &Path) -> Result<lopdf::Document> {
+    validate_input_file(path)?;
+    let started = Instant::now();
+    let file = fs::File::open(path)?;
+    // SAFETY: the map is only read, and dropped before this returns. Like
+    // any reader, the result is undefined if another process truncates the
+    // file while it is parsed.
+    #[allow(unsafe_code)]
+    let map = unsafe { memmap2::Mmap::map(&file)? };
+    let doc = lopdf::Reader {
+        buffer: &map,
+        document: lopdf::Document::new(),
+    }
+    .read(Some(keep_structure))
+    .map_err(|source| PdfError::PdfParse {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    tracing::debug!(
+        path = %path.display(),
+        objects = doc.objects.len(),
+        elapsed_ms = started.elapsed().as_millis(),
+        "loaded pdf structure"
+    );
+    warn_skipped_objects(&doc, path);
+    Ok(doc)
+}
+
+/// Warn about objects in the cross-reference table that could not be read
+/// and were left out of `doc`.
+fn warn_skipped_objects(doc: &lopdf::Document, path: &Path) {
+    let skipped = doc
+        .reference_table
+        .entries
+        .iter()
+        .filter(|&(&number, entry)| match *entry {
+            lopdf::xref::XrefEntry::Normal { generation, .. } => {
+                !doc.objects.contains_key(&(number, generation))
+            }
+            lopdf::xref::XrefEntry::Compressed { .. } => !doc.objects.contains_key(&(number, 0)),
+            _ => false,
+        })
+        .count();
+    if skipped > 0 {
+        warnings::warn(
+            WarningKind::Repaired,
+            format!(
+                "{}: {skipped} damaged object(s) could not be read and were skipped",
+                path.display()
+            ),
+        );
+    }
+}
+
+/// [`lopdf::Reader::read`] filter for [`load_structure`].
+///
+/// Top-level objects are taken from `object` as modified here; the returned
+/// value is only used for objects inside object streams, which are never
+/// streams themselves.
+#[allow(clippy::unnecessary_wraps)] // the signature lopdf expects
+fn keep_structure(
+    id: lopdf::ObjectId,
+    object: &mut lopdf::Object,
+) -> Option<(lopdf::ObjectId, lopdf::Object)> {
+    if let lopdf::Object::Stream(stream) = object {
+        if stream.dict.type_is(b"ObjStm") {
+            return Some((id, lopdf::Object::Null));
+        }
+        *object = lopdf::Object::Dictionary(std::mem::take(&mut stream.dict));
+        return Some((id, lopdf::Object::Null));
+    }
+    Some((id, object.clone()))
+}
+
+/// Save a [`lopdf`] document to `path` (reproducibly if
+/// [`set_deterministic`] is on).
+fn save_document(doc: &mut lopdf::Document, path: &Path) -> Result<()> {
+    if deterministic() {
+        docid::make_deterministic(doc)?;
+    }
+    doc.save(path)?;
+    tracing::debug!(path = %path.display(), objects = doc.objects.len(), "saved pdf");
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Tool {
+    Qpdf,
+    Pdftotext,
+    Ghostscript,
+    Ocrmypdf,
+    Mutool,
+    Wkhtmltopdf,
+    Chrome,
+    Soffice,
+    Openssl,
+    Curl,
+    Pdftocairo,
+    Lp,
+    Sumatrapdf,
+}
+
+impl Tool {
+    const ALL: [Self; 13] = [
+        Self::Qpdf,
+        Self::Pdftotext,
+        Self::Ghostscript,
+        Self::Ocrmypdf,
+        Self::Mutool,
+        Self::Wkhtmltopdf,
+        Self::Chrome,
+        Self::Soffice,
+        Self::Openssl,
+        Self::Curl,
+        Self::Pdftocairo,
+        Self::Lp,
+        Self::Sumatrapdf,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Qpdf => "qpdf",
+            Self::Pdftotext => "pdftotext",
+            Self::Ghostscript => "ghostscript",
+            Self::Ocrmypdf => "ocrmypdf",
+            Self::Mutool => "mutool",
+            Self::Wkhtmltopdf => "wkhtmltopdf",
+            Self::Chrome => "chrome",
+            Self::Soffice => "soffice",
+            Self::Openssl => "openssl",
+            Self::Curl => "curl",
+            Self::Pdftocairo => "pdftocairo",
+            Self::Lp => "lp",
+            Self::Sumatrapdf => "sumatrapdf",
+        }
+    }
+
+    fn env_override(self) -> &'static str {
+        match self {
+            Self::Qpdf => "PDFCLI_QPDF",
+            Self::Pdftotext => "PDFCLI_PDFTOTEXT",
+            Self::Ghostscript => "PDFCLI_GS",
+            Self::Ocrmypdf => "PDFCLI_OCRMYPDF",
+            Self::Mutool => "PDFCLI_MUTOOL",
+            Self::Wkhtmltopdf => "PDFCLI_WKHTMLTOPDF",
+            Self::Chrome => "PDFCLI_CHROME",
+            Self::Soffice => "PDFCLI_SOFFICE",
+            Self::Openssl => "PDFCLI_OPENSSL",
+            Self::Curl => "PDFCLI_CURL",
+            Self::Pdftocairo => "PDFCLI_PDFTOCAIRO",
+            Self::Lp => "PDFCLI_LP",
+            Self::Sumatrapdf => "PDFCLI_SUMATRAPDF",
+        }
+    }
+
+    /// Arguments that make the tool print its version.
+    fn version_args(self) -> &'static [&'static str] {
+        match self {
+            Self::Pdftotext | Self::Mutool | Self::Pdftocairo => &["-v"],
+            Self::Openssl => &["version"],
+            Self::Qpdf
+            | Self::Ghostscript
+            | Self::Ocrmypdf
+            | Self::Wkhtmltopdf
+            | Self::Chrome
+            | Self::Soffice
+            | Self::Curl
+            | Self::Lp => &["--version"],
+            // A GUI program, which would open a window.
+            Self::Sumatrapdf => &[],
+        }
+    }
+
+    fn default_exe_names(self) -> &'static [&'static str] {
+        match self {
+            Self::Qpdf => &["qpdf"],
+            Self::Pdftotext => &["pdftotext"],
+            Self::Ghostscript => &["gs", "gswin64c", "gswin32c"],
+            Self::Ocrmypdf => &["ocrmypdf"],
+            Self::Mutool => &["mutool"],
+            Self::Wkhtmltopdf => &["wkhtmltopdf"],
+            Self::Chrome => &[
+                "chromium",
+                "chromium-browser",
+                "google-chrome",
+                "google-chrome-stable",
+                "chrome",
+            ],
+            Self::Soffice => &["soffice", "libreoffice"],
+            Self::Openssl => &["openssl"],
+            Self::Curl => &["curl"],
+            Self::Pdftocairo => &["pdftocairo"],
+            Self::Lp => &["lp", "lpr"],
+            Self::Sumatrapdf => &["SumatraPDF", "sumatrapdf"],
+        }
+    }
+
+    fn install_hint(self) -> String {
+        let tool = self.name();
+        let mac = match self {
+            Self::Ghostscript => "brew install ghostscript",
+            Self::Mutool => "brew install mupdf-tools",
+            Self::Wkhtmltopdf => "brew install --cask wkhtmltopdf",
+            Self::Chrome => "brew install --cask chromium",
+            Self::Soffice => "brew install --cask libreoffice",
+            Self::Pdftocairo => "brew install poppler",
+            Self::Lp => "lp is part of macOS (CUPS)",
+            Self::Sumatrapdf => "SumatraPDF is Windows-only",
+            _ => &format!("brew install {tool}"),
+        };
+        let ubuntu = match self {
+            Self::Pdftotext | Self::Pdftocairo => {
+                "sudo apt-get update && sudo apt-get install -y poppler-utils"
+            }
+            Self::Ghostscript => "sudo apt-get update && sudo apt-get install -y ghostscript",
+            Self::Qpdf => "sudo apt-get update && sudo apt-get install -y qpdf",
+            Self::Ocrmypdf => "sudo apt-get update && sudo apt-get install -y ocrmypdf",
+            Self::Mutool => "sudo apt-get update && sudo apt-get install -y mupdf-tools",
+            Self::Wkhtmltopdf => "sudo apt-get update && sudo apt-get install -y wkhtmltopdf",
+            Self::Chrome => "sudo apt-get update && sudo apt-get install -y chromium",
+            Self::Soffice => "sudo apt-get update && sudo apt-get install -y libreoffice",
+            Self::Openssl => "sudo apt-get update && sudo apt-get install -y openssl",
+            Self::Curl => "sudo apt-get update && sudo apt-get install -y curl",
+            Self::Lp => "sudo apt-get update && sudo apt-get install -y cups-client",
+            Self::Sumatrapdf => "SumatraPDF is Windows-only; printing uses lp",
+        };
+        let windows = match self {
+            Self::Ghostscript => "choco install ghostscript OR scoop install ghostscript",
+            Self::Pdftotext | Self::Pdftocairo => "choco install poppler OR scoop install poppler",
+            Self::Qpdf => "choco install qpdf OR scoop install qpdf",
+            Self::Ocrmypdf => "pip install ocrmypdf (also requires Tesseract and Ghostscript)",
+            Self::Mutool => "choco install mupdf OR scoop install mupdf",
+            Self::Wkhtmltopdf => "choco install wkhtmltopdf OR scoop install wkhtmltopdf",
+            Self::Chrome => "choco install chromium OR scoop install extras/chromium",
+            Self::Soffice => "choco install libreoffice-fresh OR scoop install extras/libreoffice",
+            Self::Openssl => "choco install openssl OR scoop install openssl",
+            Self::Curl => "choco install curl OR scoop install curl",
+            Self::Lp => "printing uses SumatraPDF",
+            Self::Sumatrapdf => "choco install sumatrapdf OR scoop install extras/sumatrapdf",
+        };
+
+        format!(
+            "Set {} to a full path, or install:\n  macOS: {}\n  Ubuntu/Debian: {}\n  Windows: {}",
+            self.env_override(),
+            mac,
+            ubuntu,
+            windows
+        )
+    }
+}
+
+/// Status of one external tool, as reported by [`tool_statuses`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolStatus {
+    /// Tool name, e.g. `qpdf`.
+    pub name: &'static str,
+    /// Environment variable that overrides the tool's location.
+    pub env_var: &'static str,
+    /// Whether `env_var` is set.
+    pub env_override: bool,
+    /// Resolved executable, if found.
+    pub path: Option<PathBuf>,
+    /// Version parsed from the tool's version output.
+    pub version: Option<ToolVersion>,
+    /// Why the tool is unavailable and how to install it, if it was not found.
+    pub hint: Option<String>,
+}
+
+/// Locate every external tool and probe its version.
+#[must_use]
+pub fn tool_statuses() -> Vec<ToolStatus> {
+    Tool::ALL
+        .into_iter()
+        .map(|tool| {
+            let env_override = std::env::var_os(tool.env_override()).is_some();
+            let (path, version, hint) = match tool_info(tool) {
+                Ok(info) => (Some(info.path), info.version, None),
+                Err(PdfError::MissingTool { hint, .. }) => (None, None, Some(hint)),
+                Err(e) => (None, None, Some(e.to_string())),
+            };
+            ToolStatus {
+                name: tool.name(),
+                env_var: tool.env_override(),
+                env_override,
+                path,
+                version,
+                hint,
+            }
+        })
+        .collect()
+}
+
+/// A dotted version number such as `10.02.1`, compared numerically.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ToolVersion(Vec<u32>);
+
+impl ToolVersion {
+    /// A version from its numeric components, e.g. `[8, 3, 0]`.
+    #[must_use]
+    pub fn new(parts: &[u32]) -> Self {
+        let mut parts = parts.to_vec();
+        // 8.3 and 8.3.0 are the same version.
+        while parts.len() > 1 && parts.last() == Some(&0) {
+            parts.pop();
+        }
+        Self(parts)
+    }
+
+    /// The version number in a tool's version line, e.g. `11.9.0` from
+    /// `qpdf version 11.9.0`.
+    #[must_use]
+    pub fn parse(line: &str) -> Option<Self> {
+        line.split_whitespace()
+            .map(|w| w.trim_start_matches('v').trim_end_matches([',', ';', ')']))
+            .find(|w| {
+                w.starts_with(|c: char| c.is_ascii_digit())
+                    && w.chars().all(|c| c.is_ascii_digit() || c == '.')
+            })
+            .and_then(|w| {
+                let parts = w
+                    .split('.')
+                    .filter(|p| !p.is_empty())
+                    .map(str::parse)
+                    .collect::<std::result::Result<Vec<u32>, _>>()
+                    .ok()?;
+                Some(Self::new(&parts))
+            })
+    }
+}
+
+impl fmt::Display for ToolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self.0.iter().map(ToString::to_string).collect();
+        f.write_str(&parts.join("."))
+    }
+}
+
+/// A located external tool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolInfo {
+    /// Resolved executable.
+    pub path: PathBuf,
+    /// Version, if the tool's version output could be parsed.
+    pub version: Option<ToolVersion>,
+}
+
+/// Locate an external tool by name (`qpdf`, `pdftotext`, `ghostscript` or
+/// `gs`, `ocrmypdf`, `mutool`, `wkhtmltopdf`, `chrome`, `soffice`,
+/// `openssl`, `curl`, `pdftocairo`, `lp`, `sumatrapdf`) and probe its
+/// version.
+pub fn find_tool_info(name: &str) -> Result<ToolInfo> {
+    tool_info(tool_by_name(name)?)
+}
+
+fn tool_by_name(name: &str) -> Result<Tool> {
+    Tool::ALL
+        .into_iter()
+        .find(|t| t.name() == name || t.default_exe_names().contains(&name))
+        .ok_or_else(|| PdfError::InvalidArgument(format!("unknown tool: {name}")))
+}
+
+static TOOL_PATHS: RwLock<BTreeMap<&'static str, PathBuf>> = RwLock::new(BTreeMap::new());
+
+/// Use `path` for the tool `name` instead of searching `PATH` (`None` restores
+/// the search). The tool's `PDFCLI_<TOOL>` environment variable still takes
+/// precedence. Applies to the whole process.
+pub fn set_tool_path(name: &str, path: Option<PathBuf>) -> Result<()> {
+    let tool = tool_by_name(name)?;
+    if let Ok(mut paths) = TOOL_PATHS.write() {
+        match path {
+            Some(path) => paths.insert(tool.name(), path),
+            None => paths.remove(tool.name()),
+        };
+    }
+    Ok(())
+}
+
+/// Locate a tool and probe its version. Versions are probed once per
+/// executable per process.
+fn tool_info(tool: Tool) -> Result<ToolInfo> {
+    static VERSIONS: Mutex<BTreeMap<PathBuf, Option<ToolVersion>>> = Mutex::new(BTreeMap::new());
+
+    let path = locate_tool(tool)?;
+    let cached = VERSIONS
+        .lock()
+        .ok()
+        .and_then(|versions| versions.get(&path).cloned());
+    let version = if let Some(version) = cached {
+        version
+    } else {
+        let line = tool_version(tool, &path);
+        let version = line.as_deref().and_then(ToolVersion::parse);
+        tracing::debug!(
+            tool = tool.name(),
+            path = %path.display(),
+            version = line.as_deref().unwrap_or("unknown"),
+            "found tool"
+        );
+        if let Ok(mut versions) = VERSIONS.lock() {
+            versions.insert(path.clone(), version.clone());
+        }
+        version
+    };
+    Ok(ToolInfo { path, version })
+}
+
+fn find_tool(tool: Tool) -> Result<PathBuf> {
+    tool_info(tool).map(|info| info.path)
+}
+
+/// [`find_tool`] for a feature that needs at least version `min`. Tools whose
+/// version cannot be determined are given the benefit of the doubt.
+fn find_tool_min(tool: Tool, min: &[u32], feature: &'static str) -> Result<PathBuf> {
+    let info = tool_info(tool)?;
+    let required = ToolVersion::new(min);
+    match info.version {
+        Some(found) if found < required => Err(PdfError::ToolTooOld {
+            tool: tool.name(),
+            found,
+            required,
+            feature,
+        }),
+        Some(_) => Ok(info.path),
+        None => {
+            warnings::warn(
+                WarningKind::UnknownToolVersion,
+                format!(
+                    "could not determine the version of {}; {feature} needs {required} or newer",
+                    tool.name()
+                ),
+            );
+            Ok(info.path)
+        }
+    }
+}
+
+fn locate_tool(tool: Tool) -> Result<PathBuf> {
+    if let Some(val) = std::env::var_os(tool.env_override()) {
+        let p = PathBuf::from(val);
+        if p.exists() {
+            return Ok(p);
+        }
+        return Err(PdfError::MissingTool {
+            tool: tool.name(),
+            hint: format!(
+                "{} was set to {}, but that path does not exist.\n\n{}",
+                tool.env_override(),
+                p.display(),
+                tool.install_hint()
+            ),
+        });
+    }
+
+    let configured = TOOL_PATHS
+        .read()
+        .ok()
+        .and_then(|paths| paths.get(tool.name()).cloned());
+    if let Some(p) = configured {
+        if p.exists() {
+            return Ok(p);
+        }
+        return Err(PdfError::MissingTool {
+            tool: tool.name(),
+            hint: format!(
+                "tools.{} is configured as {}, but that path does not exist.\n\n{}",
+                tool.name(),
+                p.display(),
+                tool.install_hint()
+            ),
+        });
+    }
+
+    for exe in tool.default_exe_names() {
+        if let Ok(p) = which::which(exe) {
+            return Ok(p);
+        }
+    }
+
+    Err(PdfError::MissingTool {
         tool: tool.name(),
-        command: command_str,
-        status: out.status.code().unwrap_or(-1),
-        stdout: String::from_utf8_lossy(&out.stdout).to_string(),
-        stderr: String::from_utf8_lossy(&out.stderr).to_string(),
+        hint: tool.install_hint(),
     })
 }
 
+/// First line of the tool's version output (some tools print it to stderr).
+fn tool_version(tool: Tool, path: &Path) -> Option<String> {
+    if tool.version_args().is_empty() {
+        return None;
+    }
+    let mut cmd = Command::new(path);
+    cmd.args(tool.version_args());
+    let timeout = tool_timeout();
+    let (_, stdout, stderr) =
+        wait_with_timeout(tool, spawn_piped(cmd, timeout).ok()?, timeout).ok()?;
+    let text = if stdout.is_empty() { stderr } else { stdout };
+    text.lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .map(ToString::to_string)
+}
+
+static TOOL_RUN_OPTIONS: RwLock<Option<ToolRunOptions>> = RwLock::new(None);
+
+/// Run external tools under `options` (time, memory, CPU, and file limits,
+/// and a private temporary directory). Applies to the whole process.
+pub fn set_tool_run_options(options: ToolRunOptions) {
+    if let Ok(mut current) = TOOL_RUN_OPTIONS.write() {
+        *current = Some(options);
+    }
+}
+
+/// The options set with [`set_tool_run_options`] (by default, no limits).
+#[must_use]
+pub fn tool_run_options() -> ToolRunOptions {
+    TOOL_RUN_OPTIONS
+        .read()
+        .ok()
+        .and_then(|options| options.clone())
+        .unwrap_or_default()
+}
+
+/// Kill external tools that run longer than `timeout` (`None`, the default,
+/// waits forever), keeping the other [`ToolRunOptions`]. Applies to the
+/// whole process.
+pub fn set_tool_timeout(timeout: Option<Duration>) {
+    set_tool_run_options(ToolRunOptions {
+        timeout,
+        ..tool_run_options()
+    });
+}
+
+/// The limit set with [`set_tool_timeout`].
+#[must_use]
+pub fn tool_timeout() -> Option<Duration> {
+    tool_run_options().timeout
+}
+
+fn run_tool(tool: Tool, cmd: Command) -> Result<()> {
+    run_tool_capture(tool, cmd).map(drop)
+}
+
+/// [`run_tool`] for a tool writing `output` (a file, or a directory of
+/// files): if the tool times out, whatever it wrote there is removed.
+fn run_tool_writing(tool: Tool, cmd: Command, output: &Path) -> Result<()> {
+    remove_output_on_timeout(output, || run_tool(tool, cmd))
+}
+
+fn remove_output_on_timeout(output: &Path, run: impl FnOnce() -> Result<()>) -> Result<()> {
+    let partial = PartialOutput::watch(output);
+    let result = run();
+    partial.remove_on_timeout(&result);
+    result
+}
+
+/// Output a tool is about to write: a file, or the files it adds to a
+/// directory.
+struct PartialOutput {
+    output: PathBuf,
+    /// The directory's entries before the tool ran, if `output` is one.
+    existing: Option<Vec<PathBuf>>,
+}
+
+impl PartialOutput {
+    fn watch(output: &Path) -> Self {
+        Self {
+            output: output.to_path_buf(),
+            existing: output.is_dir().then(|| dir_entries(output)),
+        }
+    }
+
+    /// Remove what the tool wrote if `result` is a timeout.
+    fn remove_on_timeout<T>(self, result: &Result<T>) {
+        if !matches!(result, Err(PdfError::Timeout { .. })) {
+            return;
+        }
+        let partial = match self.existing {
+            Some(before) => dir_entries(&self.output)
+                .into_iter()
+                .filter(|entry| !before.contains(entry))
+                .collect(),
+            None => vec![self.output],
+        };
+        for path in partial {
+            if fs::remove_file(&path).is_ok() {
+                tracing::debug!(path = %path.display(), "removed partial output");
+            }
+        }
+    }
+}
+
+fn dir_entries(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| entries.filter_map(|e| e.ok().map(|e| e.path())).collect())
+        .unwrap_or_default()
+}
+
+fn run_tool_capture(tool: Tool, cmd: Command) -> Result<String> {
+    run_tool_with(tool, cmd, &tool_run_options())
+}
+
+fn run_tool_with(tool: Tool, cmd: Command, options: &ToolRunOptions) -> Result<String> {
+    let command_str = command_to_string(&cmd);
+    let _span = tracing::debug_span!("tool", tool = tool.name()).entered();
+    tracing::debug!(command = %command_str, "running");
+    let started = Instant::now();
+    let (cmd, _tmp) = sandbox::prepare(cmd, options)?;
+    let child = spawn_piped(cmd, options.timeout)?;
+    let (status, stdout, stderr) = wait_with_timeout(tool, child, options.timeout)?;
+    tracing::debug!(
+        status = status.code().unwrap_or(-1),
+        elapsed_ms = started.elapsed().as_millis(),
+        "finished"
+    );
+    tracing::trace!(%stdout, %stderr, "output");
+    tool_outcome(tool, command_str, status, stdout, stderr)
+}
+
+/// The result of a tool that exited with `status`: its standard output, or
+/// [`PdfError::ToolFailed`]. Warnings on its standard error are reported.
+fn tool_outcome(
+    tool: Tool,
+    command: String,
+    status: ExitStatus,
+    stdout: String,
+    stderr: String,
+) -> Result<String> {
+    // qpdf exits with 3 when it succeeded despite problems in the input.
+    let qpdf_warned = matches!(tool, Tool::Qpdf) && status.code() == Some(3);
+    if status.success() || qpdf_warned {
+        let mut warned = false;
+        for line in stderr.lines().map(str::trim) {
+            if line.to_ascii_lowercase().contains("warning") {
+                warnings::warn(WarningKind::Tool, format!("{}: {line}", tool.name()));
+                warned = true;
+            }
+        }
+        if qpdf_warned && !warned {
+            warnings::warn(WarningKind::Tool, "qpdf: finished with warnings");
+        }
+        return Ok(stdout);
+    }
+
+    Err(PdfError::ToolFailed {
+        tool: tool.name(),
+        command,
+        status: status.code().unwrap_or(-1),
+        stdout,
+        stderr,
+    })
+}
+
+/// Start `cmd` with its output captured, ready for [`wait_with_timeout`].
+fn spawn_piped(mut cmd: Command, timeout: Option<Duration>) -> std::io::Result<Child> {
+    if timeout.is_some() {
+        own_process_group(&mut cmd);
+    }
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+}
+
+/// Wait for `child`, collecting its output, and kill it if `timeout` expires.
+fn wait_with_timeout(
+    tool: Tool,
+    mut child: Child,
+    timeout: Option<Duration>,
+) -> Result<(ExitStatus, String, String)> {
+    // Drain both pipes on their own threads so a chatty tool never blocks.
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+
+    let status = match timeout {
+        None => child.wait()?,
+        Some(after) => {
+            let deadline = Instant::now() + after;
+            loop {
+                if let Some(status) = child.try_wait()? {
+                    break status;
+                }
+                if Instant::now() >= deadline {
+                    tracing::warn!(tool = tool.name(), "killed after {}s", after.as_secs_f64());
+                    kill_tree(&mut child);
+                    return Err(PdfError::Timeout {
+                        tool: tool.name(),
+                        after,
+                    });
+                }
+                thread::sleep(Duration::from_millis(20));
+            }
+        }
+    };
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+    Ok((status, stdout, stderr))
+}
+
+fn read_pipe(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        String::from_utf8_lossy(&buf).to_string()
+    })
+}
+
+/// Start `cmd` in a process group of its own (on Unix), so that helpers the
+/// tool spawns are killed with it by [`kill_process_group`].
+fn own_process_group(cmd: &mut Command) {
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(cmd, 0);
+    #[cfg(not(unix))]
+    let _ = cmd;
+}
+
+/// Kill a tool started by [`run_tool_with`] with a timeout, and on Unix
+/// everything else in its process group.
+fn kill_tree(child: &mut Child) {
+    kill_process_group(child.id());
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Kill the process group led by `leader`, a tool started with
+/// [`own_process_group`] that has not been waited for yet (Unix only).
+fn kill_process_group(leader: u32) {
+    #[cfg(unix)]
+    {
+        let Some(group) = i32::try_from(leader)
+            .ok()
+            .and_then(rustix::process::Pid::from_raw)
+        else {
+            return;
+        };
+        if let Err(e) = rustix::process::kill_process_group(group, rustix::process::Signal::KILL) {
+            tracing::debug!(error = %e, "failed to kill process group");
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = leader;
+}
+
 fn command_to_string(cmd: &Command) -> String {
     let prog = cmd.get_program().to_string_lossy();
     let args = cmd
@@ -444,11 +2251,26 @@ fn shell_escape(s: &OsStr) -> String {
     }
 }
 
+impl fmt::Display for CompressPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Screen => "screen",
+            Self::Ebook => "ebook",
+            Self::Printer => "printer",
+            Self::Prepress => "prepress",
+            Self::Default => "default",
+        };
+        f.write_str(name)
+    }
+}
+
 impl fmt::Display for PageSelection {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::All => write!(f, "all"),
+            Self::Range { start, end } if start == end => write!(f, "{start}"),
             Self::Range { start, end } => write!(f, "{start}-{end}"),
+            Self::From { start } => write!(f, "{start}-"),
         }
     }
 }
@@ -457,6 +2279,42 @@ impl fmt::Display for PageSelection {
 mod tests {
     use super::*;
 
+    #[test]
+    fn versions_are_parsed_from_tool_banners() {
+        assert_eq!(
+            ToolVersion::parse("qpdf version 11.9.0"),
+            Some(ToolVersion::new(&[11, 9]))
+        );
+        assert_eq!(
+            ToolVersion::parse("10.02.1")
+                .map(|v| v.to_string())
+                .as_deref(),
+            Some("10.2.1")
+        );
+        assert_eq!(
+            ToolVersion::parse("pdftotext version 24.02.0"),
+            Some(ToolVersion::new(&[24, 2, 0]))
+        );
+        assert_eq!(ToolVersion::parse("no version here"), None);
+        assert!(ToolVersion::new(&[8, 2, 9]) < ToolVersion::new(&[8, 3]));
+        assert!(ToolVersion::new(&[10]) > ToolVersion::new(&[9, 56, 1]));
+    }
+
+    #[test]
+    fn page_selections_parse_single_pages_and_open_ranges() -> Result<()> {
+        let parse = |s: &str| s.parse::<PageSelection>();
+        assert_eq!(parse("4")?, PageSelection::Range { start: 4, end: 4 });
+        assert_eq!(parse(" 5- ")?, PageSelection::From { start: 5 });
+        assert_eq!(parse("5-")?.to_qpdf_arg().as_deref(), Some("5-z"));
+        assert!(parse("0-3").is_err() && parse("3-1").is_err() && parse("-3").is_err());
+
+        let from = PageSelection::From { start: 5 };
+        assert!(from.contains(9) && !from.contains(4));
+        assert!(from.overlaps(&PageSelection::Range { start: 1, end: 5 }));
+        assert!(!from.overlaps(&PageSelection::Range { start: 1, end: 4 }));
+        Ok(())
+    }
+
     #[test]
     fn info_reads_page_count_from_minimal_pdf(
     ) -> std::result::Result<(), Box<dyn std::error::Error>> {
@@ -515,6 +2373,314 @@ mod tests {
         Ok(())
     }
 
+    /// Two pages whose tree is kept in an object stream, found through a
+    /// cross-reference stream.
+    fn object_stream_pdf() -> Vec<u8> {
+        let objects = "<< /Type /Pages /Kids [3 0 R 4 0 R] /Count 2 >>\n\
+                       << /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] >>\n\
+                       << /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] >>\n";
+        let first = objects.find("<< /Type /Page ").unwrap_or_default();
+        let second = objects.rfind("<< /Type /Page ").unwrap_or_default();
+        let index = format!("2 0 3 {first} 4 {second}\n");
+
+        let mut pdf = b"%PDF-1.5\n".to_vec();
+        let catalog = pdf.len();
+        pdf.extend_from_slice(b"1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n");
+        let stream = pdf.len();
+        pdf.extend_from_slice(
+            format!(
+                "5 0 obj\n<< /Type /ObjStm /N 3 /First {} /Length {} >>\nstream\n{index}{objects}\nendstream\nendobj\n",
+                index.len(),
+                index.len() + objects.len()
+            )
+            .as_bytes(),
+        );
+        let xref = pdf.len();
+        // Entries of 1 + 4 + 2 bytes: type, offset or stream, generation or
+        // index.
+        let entry = |kind: u8, field: usize, index: u16| {
+            let field = u32::try_from(field).unwrap_or_default();
+            [&[kind][..], &field.to_be_bytes(), &index.to_be_bytes()].concat()
+        };
+        let entries = [
+            entry(0, 0, 0xFFFF),
+            entry(1, catalog, 0),
+            entry(2, 5, 0),
+            entry(2, 5, 1),
+            entry(2, 5, 2),
+            entry(1, stream, 0),
+            entry(1, xref, 0),
+        ]
+        .concat();
+        pdf.extend_from_slice(
+            format!(
+                "6 0 obj\n<< /Type /XRef /Size 7 /W [1 4 2] /Root 1 0 R /Length {} >>\nstream\n",
+                entries.len()
+            )
+            .as_bytes(),
+        );
+        pdf.extend_from_slice(&entries);
+        pdf.extend_from_slice(
+            format!("\nendstream\nendobj\nstartxref\n{xref}\n%%EOF\n").as_bytes(),
+        );
+        pdf
+    }
+
+    #[test]
+    fn page_count_matches_info() -> Result<()> {
+        use lopdf::{Dictionary, IncrementalDocument, Object};
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("objstm.pdf");
+        std::fs::write(&path, object_stream_pdf())?;
+        let i = info(&path)?;
+        assert!(i.object_streams);
+        assert_eq!((page_count(&path)?, i.pages), (2, 2));
+
+        // An incremental update adding a third page: the count comes from
+        // the latest revision.
+        let bytes = std::fs::read(&path)?;
+        let loaded = lopdf::Document::load_mem(&bytes).map_err(|source| PdfError::PdfParse {
+            path: path.clone(),
+            source,
+        })?;
+        let mut update = IncrementalDocument::create_from(bytes, loaded);
+        let page = update.new_document.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Page".to_vec())),
+            ("Parent", Object::Reference((2, 0))),
+            (
+                "MediaBox",
+                Object::Array(vec![0.into(), 0.into(), 612.into(), 792.into()]),
+            ),
+        ]));
+        update.new_document.objects.insert(
+            (2, 0),
+            Object::Dictionary(Dictionary::from_iter([
+                ("Type", Object::Name(b"Pages".to_vec())),
+                (
+                    "Kids",
+                    Object::Array(vec![
+                        Object::Reference((3, 0)),
+                        Object::Reference((4, 0)),
+                        Object::Reference(page),
+                    ]),
+                ),
+                ("Count", Object::Integer(3)),
+            ])),
+        );
+        let updated = dir.path().join("updated.pdf");
+        update.save(&updated)?;
+        let i = info(&updated)?;
+        assert_eq!(i.incremental_updates, 1);
+        assert_eq!((page_count(&updated)?, i.pages), (3, 3));
+        Ok(())
+    }
+
+    #[test]
+    fn structure_loads_drop_stream_data_but_keep_fonts(
+    ) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        use lopdf::{Dictionary, Object, Stream};
+
+        // A page drawing a form XObject whose own resources hold the font.
+        let mut doc = lopdf::Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type1".to_vec())),
+            ("BaseFont", Object::Name(b"Courier".to_vec())),
+        ]));
+        let form = doc.add_object(Stream::new(
+            Dictionary::from_iter([
+                ("Type", Object::Name(b"XObject".to_vec())),
+                ("Subtype", Object::Name(b"Form".to_vec())),
+                (
+                    "Resources",
+                    Object::Dictionary(Dictionary::from_iter([(
+                        "Font",
+                        Object::Dictionary(Dictionary::from_iter([(
+                            "F1",
+                            Object::Reference(font),
+                        )])),
+                    )])),
+                ),
+            ]),
+            b"BT /F1 12 Tf (hi) Tj ET".to_vec(),
+        ));
+        let content = doc.add_object(Stream::new(Dictionary::new(), vec![b' '; 64 << 10]));
+        let page = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Page".to_vec())),
+            ("Parent", Object::Reference(pages_id)),
+            ("Contents", Object::Reference(content)),
+            (
+                "Resources",
+                Object::Dictionary(Dictionary::from_iter([(
+                    "XObject",
+                    Object::Dictionary(Dictionary::from_iter([("X1", Object::Reference(form))])),
+                )])),
+            ),
+        ]));
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(Dictionary::from_iter([
+                ("Type", Object::Name(b"Pages".to_vec())),
+                ("Kids", Object::Array(vec![Object::Reference(page)])),
+                ("Count", Object::Integer(1)),
+            ])),
+        );
+        let catalog = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Catalog".to_vec())),
+            ("Pages", Object::Reference(pages_id)),
+        ]));
+        doc.trailer.set("Root", catalog);
+        let f = tempfile::NamedTempFile::new()?;
+        doc.save(f.path())?;
+
+        let full = load_document(f.path())?;
+        let structure = load_structure(f.path())?;
+        assert!(full.get_object(content)?.as_stream().is_ok());
+        assert!(structure.get_object(content)?.as_dict().is_ok());
+        assert_eq!(
+            fonts::collect_fonts(&structure),
+            fonts::collect_fonts(&full)
+        );
+        assert_eq!(fonts::collect_fonts(&structure).len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn compress_report_computes_ratio_and_savings() {
+        let r = CompressReport::from_sizes(1000, 150);
+        assert!((r.ratio - 0.15).abs() < f64::EPSILON);
+        assert!((r.savings_percent() - 85.0).abs() < 1e-9);
+
+        let empty = CompressReport::from_sizes(0, 10);
+        assert!((empty.ratio - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn error_codes_and_exit_codes_are_distinct() {
+        let errors = [
+            PdfError::InvalidArgument(String::new()),
+            PdfError::InputNotFound(PathBuf::new()),
+            PdfError::PdfParse {
+                path: PathBuf::new(),
+                source: lopdf::Error::ObjectNotFound,
+            },
+            PdfError::MissingTool {
+                tool: "qpdf",
+                hint: String::new(),
+            },
+            PdfError::ToolTooOld {
+                tool: "qpdf",
+                found: ToolVersion::new(&[8]),
+                required: ToolVersion::new(&[9]),
+                feature: "",
+            },
+            PdfError::ToolFailed {
+                tool: "qpdf",
+                command: String::new(),
+                status: 2,
+                stdout: String::new(),
+                stderr: String::new(),
+            },
+            PdfError::FontsNotEmbedded { fonts: Vec::new() },
+            PdfError::Io(std::io::Error::other("")),
+            PdfError::Pdfium(String::new()),
+            PdfError::Timeout {
+                tool: "gs",
+                after: Duration::from_secs(1),
+            },
+            PdfError::Encrypted {
+                path: PathBuf::new(),
+                reason: String::new(),
+            },
+            PdfError::InputsFailed {
+                failed: 1,
+                total: 2,
+            },
+            PdfError::InputsFailed {
+                failed: 2,
+                total: 2,
+            },
+            PdfError::Warnings {
+                warnings: Vec::new(),
+            },
+        ];
+        let codes: std::collections::BTreeSet<_> = errors.iter().map(PdfError::code).collect();
+        let exits: std::collections::BTreeSet<_> = errors.iter().map(PdfError::exit_code).collect();
+        assert_eq!(codes.len(), errors.len());
+        assert_eq!(exits.len(), errors.len());
+        assert!(exits.iter().all(|&code| code > 2));
+    }
+
+    #[test]
+    fn damaged_objects_are_skipped_with_a_warning() -> Result<()> {
+        use std::fmt::Write as _;
+
+        let objects = [
+            "<</Type/Catalog/Pages 2 0 R>>",
+            "<</Type/Pages/Kids[]/Count 0>>",
+            "<</A [ ( >>",
+        ];
+        let mut pdf = String::from("%PDF-1.4\n");
+        let mut offsets = Vec::new();
+        for (number, object) in (1..).zip(objects) {
+            offsets.push(pdf.len());
+            let _ = write!(pdf, "{number} 0 obj\n{object}\nendobj\n");
+        }
+        let xref = pdf.len();
+        pdf.push_str("xref\n0 4\n0000000000 65535 f \n");
+        for offset in offsets {
+            let _ = writeln!(pdf, "{offset:010} 00000 n ");
+        }
+        let _ = write!(
+            pdf,
+            "trailer\n<</Size 4/Root 1 0 R>>\nstartxref\n{xref}\n%%EOF\n"
+        );
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("damaged.pdf");
+        fs::write(&path, pdf)?;
+
+        let (info, warnings) = collect_warnings(|| info(&path));
+        assert_eq!(info?.pages, 0);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, WarningKind::Repaired);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn timed_out_tools_are_killed_and_their_output_removed() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let kept = dir.path().join("kept.pdf");
+        fs::write(&kept, "")?;
+        let started = Instant::now();
+        let result = remove_output_on_timeout(dir.path(), || {
+            fs::write(dir.path().join("page-1.pdf"), "partial")?;
+            // The background sleep keeps stdout open: it must be killed too.
+            let mut cmd = Command::new("sh");
+            cmd.args(["-c", "sleep 5 & sleep 5"]);
+            let options = ToolRunOptions {
+                timeout: Some(Duration::from_millis(100)),
+                ..ToolRunOptions::default()
+            };
+            run_tool_with(Tool::Ghostscript, cmd, &options).map(drop)
+        });
+        assert!(
+            matches!(
+                result,
+                Err(PdfError::Timeout {
+                    tool: "ghostscript",
+                    ..
+                })
+            ),
+            "{result:?}"
+        );
+        assert!(started.elapsed() < Duration::from_secs(4));
+        assert_eq!(dir_entries(dir.path()), vec![kept]);
+        Ok(())
+    }
+
     fn tool_available(tool: Tool) -> bool {
         find_tool(tool).is_ok()
     }
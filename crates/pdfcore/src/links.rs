@@ -0,0 +1,561 @@
+//! Link annotations and destination resolution.
+
+use std::{collections::BTreeMap, fmt, path::Path};
+
+use crate::{
+    fonts::resolve_dict, geometry::Rect, load_document, load_for_reading, metadata, objects,
+    plan::Plan, save_document, PdfError, Result,
+};
+
+/// Where a link annotation leads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkTarget {
+    /// An external URI (`/URI` action).
+    Uri(String),
+    /// A destination in the same document (`/Dest` or `/GoTo`).
+    Internal {
+        /// Named destination, if the link used one.
+        name: Option<String>,
+        /// 1-based target page, or `None` if it could not be resolved.
+        page: Option<u32>,
+    },
+    /// A destination in another PDF (`/GoToR`).
+    Remote {
+        /// Target file specification.
+        file: String,
+    },
+    /// Any other action type (e.g. `Launch`, `JavaScript`).
+    Action(String),
+    /// The annotation has neither an action nor a destination.
+    None,
+}
+
+impl LinkTarget {
+    /// `true` for targets outside the document: URIs, other files, and
+    /// launch actions.
+    #[must_use]
+    pub fn is_external(&self) -> bool {
+        match self {
+            Self::Uri(_) | Self::Remote { .. } => true,
+            Self::Action(kind) => kind == "Launch",
+            Self::Internal { .. } | Self::None => false,
+        }
+    }
+}
+
+impl fmt::Display for LinkTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Uri(uri) => write!(f, "{uri}"),
+            Self::Internal { name, page } => {
+                match page {
+                    Some(p) => write!(f, "page {p}")?,
+                    None => f.write_str("page ?")?,
+                }
+                if let Some(name) = name {
+                    write!(f, " (#{name})")?;
+                }
+                Ok(())
+            }
+            Self::Remote { file } => write!(f, "file {file}"),
+            Self::Action(kind) => write!(f, "action {kind}"),
+            Self::None => f.write_str("(none)"),
+        }
+    }
+}
+
+/// A link annotation, as returned by [`links`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkInfo {
+    /// 1-based page the annotation is on.
+    pub page: u32,
+    /// Clickable area in default user space.
+    pub rect: Rect,
+    /// Resolved target.
+    pub target: LinkTarget,
+}
+
+/// List every link annotation with its resolved target (pure Rust).
+///
+/// `GoTo` actions and named destinations (from both the catalog `/Dests`
+/// dictionary and the `/Names` tree) are resolved to page numbers.
+pub fn links(path: impl AsRef<Path>) -> Result<Vec<LinkInfo>> {
+    let doc = load_for_reading(path.as_ref())?;
+    Ok(collect_links(&doc))
+}
+
+pub(crate) fn collect_links(doc: &lopdf::Document) -> Vec<LinkInfo> {
+    let pages = doc.get_pages();
+    let page_numbers: BTreeMap<lopdf::ObjectId, u32> =
+        pages.iter().map(|(no, id)| (*id, *no)).collect();
+    let named = named_destinations(doc);
+
+    let mut out = Vec::new();
+    for (&page_no, &page_id) in &pages {
+        for annot in link_annotations(doc, page_id) {
+            let rect = annot
+                .get(b"Rect")
+                .ok()
+                .and_then(|r| Rect::from_object(doc, r))
+                .unwrap_or_else(|| Rect::from_size(0.0, 0.0));
+            out.push(LinkInfo {
+                page: page_no,
+                rect,
+                target: annotation_target(doc, annot, &page_numbers, &named),
+            });
+        }
+    }
+    out
+}
+
+/// Rewrite the host (or prefix) of URI links.
+///
+/// Each `(from, to)` mapping applies to URIs whose host is `from` (e.g.
+/// `old-domain.com`), keeping the scheme, port, and path; otherwise, a URI
+/// that starts with `from` has that prefix replaced. The first matching
+/// mapping wins. Returns the number of URIs changed.
+pub fn rewrite_links(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    map: &[(String, String)],
+) -> Result<usize> {
+    validate_link_map(map)?;
+    let mut doc = load_document(input.as_ref())?;
+    let mut changed = 0;
+    for obj in doc.objects.values_mut() {
+        objects::visit_dicts_mut(obj, &mut |dict| {
+            let is_uri = dict
+                .get(b"S")
+                .and_then(lopdf::Object::as_name)
+                .is_ok_and(|s| s == b"URI");
+            let Some(uri) = is_uri
+                .then(|| dict.get(b"URI").and_then(lopdf::Object::as_str).ok())
+                .flatten()
+            else {
+                return;
+            };
+            let uri = String::from_utf8_lossy(uri).to_string();
+            if let Some(new) = map
+                .iter()
+                .find_map(|(from, to)| rewrite_uri(&uri, from, to))
+            {
+                dict.set("URI", lopdf::Object::string_literal(new));
+                changed += 1;
+            }
+        });
+    }
+    save_document(&mut doc, output.as_ref())?;
+    Ok(changed)
+}
+
+/// What [`rewrite_links`] would do.
+pub fn rewrite_links_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    map: &[(String, String)],
+) -> Result<Plan> {
+    validate_link_map(map)?;
+    load_document(input.as_ref())?;
+    let plan = map.iter().fold(Plan::new(), |plan, (from, to)| {
+        plan.native(format!("rewrite URI links {from} -> {to}"))
+    });
+    Ok(plan.write(output.as_ref()))
+}
+
+fn validate_link_map(map: &[(String, String)]) -> Result<()> {
+    if map.iter().any(|(from, _)| from.is_empty()) {
+        return Err(PdfError::InvalidArgument(
+            "link mapping source must not be empty".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// What [`strip_links`] would do.
+pub fn strip_links_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    external_only: bool,
+) -> Result<Plan> {
+    load_document(input.as_ref())?;
+    let what = if external_only {
+        "remove link annotations leading outside the document"
+    } else {
+        "remove all link annotations"
+    };
+    Ok(Plan::new().native(what).write(output.as_ref()))
+}
+
+/// Remove link annotations, or only those leading outside the document
+/// (URIs, other files, and launch actions) if `external_only` is set.
+/// Returns the number of annotations removed.
+pub fn strip_links(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    external_only: bool,
+) -> Result<usize> {
+    let mut doc = load_document(input.as_ref())?;
+    let pages = doc.get_pages();
+    let page_numbers: BTreeMap<lopdf::ObjectId, u32> =
+        pages.iter().map(|(no, id)| (*id, *no)).collect();
+
+    let named = named_destinations(&doc);
+
+    // Decide what to keep with the document borrowed, then apply the edits.
+    let mut removed = 0;
+    let mut edits = Vec::new();
+    for &page_id in pages.values() {
+        let Some(annots) = doc
+            .get_dictionary(page_id)
+            .ok()
+            .and_then(|page| page.get(b"Annots").ok())
+        else {
+            continue;
+        };
+        let Ok((_, lopdf::Object::Array(items))) = doc.dereference(annots) else {
+            continue;
+        };
+        let keep: Vec<lopdf::Object> = items
+            .iter()
+            .filter(|item| {
+                let Some(annot) = resolve_dict(&doc, Some(item)) else {
+                    return true;
+                };
+                let is_link = annot
+                    .get(b"Subtype")
+                    .and_then(lopdf::Object::as_name)
+                    .is_ok_and(|s| s == b"Link");
+                let strip = is_link
+                    && (!external_only
+                        || annotation_target(&doc, annot, &page_numbers, &named).is_external());
+                !strip
+            })
+            .cloned()
+            .collect();
+        if keep.len() < items.len() {
+            removed += items.len() - keep.len();
+            edits.push((page_id, annots.as_reference().ok(), keep));
+        }
+    }
+
+    for (page_id, annots_id, keep) in edits {
+        match annots_id {
+            Some(id) => {
+                doc.objects.insert(id, lopdf::Object::Array(keep));
+            }
+            None => {
+                if let Ok(page) = doc.get_dictionary_mut(page_id) {
+                    page.set("Annots", keep);
+                }
+            }
+        }
+    }
+    save_document(&mut doc, output.as_ref())?;
+    Ok(removed)
+}
+
+fn rewrite_uri(uri: &str, from: &str, to: &str) -> Option<String> {
+    if let Some((scheme, rest)) = uri.split_once("://") {
+        let host_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+        let (authority, tail) = rest.split_at(host_end);
+        let (userinfo, host_port) = match authority.rsplit_once('@') {
+            Some((user, hp)) => (Some(user), hp),
+            None => (None, authority),
+        };
+        let (host, port) = match host_port.split_once(':') {
+            Some((h, p)) => (h, Some(p)),
+            None => (host_port, None),
+        };
+        if host.eq_ignore_ascii_case(from) {
+            let mut out = format!("{scheme}://");
+            if let Some(user) = userinfo {
+                out.push_str(user);
+                out.push('@');
+            }
+            out.push_str(to);
+            if let Some(port) = port {
+                out.push(':');
+                out.push_str(port);
+            }
+            out.push_str(tail);
+            return Some(out);
+        }
+    }
+    uri.strip_prefix(from).map(|rest| format!("{to}{rest}"))
+}
+
+fn annotation_target(
+    doc: &lopdf::Document,
+    annot: &lopdf::Dictionary,
+    page_numbers: &BTreeMap<lopdf::ObjectId, u32>,
+    named: &BTreeMap<Vec<u8>, &lopdf::Object>,
+) -> LinkTarget {
+    if let Some(action) = resolve_dict(doc, annot.get(b"A").ok()) {
+        action_target(doc, action, page_numbers, named)
+    } else if let Ok(dest) = annot.get(b"Dest") {
+        dest_target(doc, dest, page_numbers, named)
+    } else {
+        LinkTarget::None
+    }
+}
+
+/// `/Link` annotation dictionaries on a page.
+pub(crate) fn link_annotations(
+    doc: &lopdf::Document,
+    page_id: lopdf::ObjectId,
+) -> Vec<&lopdf::Dictionary> {
+    let Ok(page) = doc.get_dictionary(page_id) else {
+        return Vec::new();
+    };
+    let Some(annots) = page
+        .get(b"Annots")
+        .ok()
+        .and_then(|a| doc.dereference(a).ok())
+        .and_then(|(_, a)| a.as_array().ok())
+    else {
+        return Vec::new();
+    };
+    annots
+        .iter()
+        .filter_map(|a| resolve_dict(doc, Some(a)))
+        .filter(|a| {
+            a.get(b"Subtype")
+                .and_then(lopdf::Object::as_name)
+                .is_ok_and(|s| s == b"Link")
+        })
+        .collect()
+}
+
+fn action_target(
+    doc: &lopdf::Document,
+    action: &lopdf::Dictionary,
+    page_numbers: &BTreeMap<lopdf::ObjectId, u32>,
+    named: &BTreeMap<Vec<u8>, &lopdf::Object>,
+) -> LinkTarget {
+    let kind = action
+        .get(b"S")
+        .and_then(lopdf::Object::as_name)
+        .map(|n| String::from_utf8_lossy(n).to_string())
+        .unwrap_or_default();
+    match kind.as_str() {
+        "URI" => LinkTarget::Uri(string_value(doc, action.get(b"URI").ok()).unwrap_or_default()),
+        "GoTo" => action.get(b"D").map_or(
+            LinkTarget::Internal {
+                name: None,
+                page: None,
+            },
+            |d| dest_target(doc, d, page_numbers, named),
+        ),
+        "GoToR" => LinkTarget::Remote {
+            file: file_spec(doc, action.get(b"F").ok()).unwrap_or_default(),
+        },
+        _ => LinkTarget::Action(kind),
+    }
+}
+
+fn dest_target(
+    doc: &lopdf::Document,
+    dest: &lopdf::Object,
+    page_numbers: &BTreeMap<lopdf::ObjectId, u32>,
+    named: &BTreeMap<Vec<u8>, &lopdf::Object>,
+) -> LinkTarget {
+    let Ok((_, dest)) = doc.dereference(dest) else {
+        return LinkTarget::Internal {
+            name: None,
+            page: None,
+        };
+    };
+    let name = match dest {
+        lopdf::Object::Name(n) | lopdf::Object::String(n, _) => Some(n.clone()),
+        _ => None,
+    };
+    let explicit = match &name {
+        Some(n) => named.get(n).copied(),
+        None => Some(dest),
+    };
+    LinkTarget::Internal {
+        name: name.map(|n| metadata::decode_text_string(&n)),
+        page: explicit.and_then(|d| dest_page(doc, d, page_numbers)),
+    }
+}
+
+/// Page number of an explicit destination (`[page /XYZ …]` or `<< /D […] >>`).
+pub(crate) fn dest_page(
+    doc: &lopdf::Document,
+    dest: &lopdf::Object,
+    page_numbers: &BTreeMap<lopdf::ObjectId, u32>,
+) -> Option<u32> {
+    let (_, dest) = doc.dereference(dest).ok()?;
+    match dest {
+        lopdf::Object::Array(items) => {
+            let id = items.first()?.as_reference().ok()?;
+            page_numbers.get(&id).copied()
+        }
+        lopdf::Object::Dictionary(d) => dest_page(doc, d.get(b"D").ok()?, page_numbers),
+        _ => None,
+    }
+}
+
+/// Named destinations from the catalog `/Dests` dictionary and the
+/// `/Names /Dests` name tree, keyed by raw name bytes.
+pub(crate) fn named_destinations(doc: &lopdf::Document) -> BTreeMap<Vec<u8>, &lopdf::Object> {
+    let mut out = BTreeMap::new();
+    let Ok(catalog) = doc.catalog() else {
+        return out;
+    };
+    if let Some(dests) = resolve_dict(doc, catalog.get(b"Dests").ok()) {
+        for (k, v) in dests {
+            out.insert(k.clone(), v);
+        }
+    }
+    if let Some(tree) = resolve_dict(doc, catalog.get(b"Names").ok())
+        .and_then(|names| resolve_dict(doc, names.get(b"Dests").ok()))
+    {
+        walk_name_tree(doc, tree, &mut out, 0);
+    }
+    out
+}
+
+pub(crate) fn walk_name_tree<'a>(
+    doc: &'a lopdf::Document,
+    node: &'a lopdf::Dictionary,
+    out: &mut BTreeMap<Vec<u8>, &'a lopdf::Object>,
+    depth: usize,
+) {
+    if depth > 32 {
+        return;
+    }
+    if let Ok(names) = node.get(b"Names").and_then(lopdf::Object::as_array) {
+        for pair in names.chunks_exact(2) {
+            if let Ok(key) = pair[0].as_str() {
+                out.insert(key.to_vec(), &pair[1]);
+            }
+        }
+    }
+    if let Ok(kids) = node.get(b"Kids").and_then(lopdf::Object::as_array) {
+        for kid in kids {
+            if let Some(kid) = resolve_dict(doc, Some(kid)) {
+                walk_name_tree(doc, kid, out, depth + 1);
+            }
+        }
+    }
+}
+
+fn string_value(doc: &lopdf::Document, obj: Option<&lopdf::Object>) -> Option<String> {
+    let (_, obj) = doc.dereference(obj?).ok()?;
+    obj.as_str().ok().map(metadata::decode_text_string)
+}
+
+/// File name from a file specification (string or `/F`/`/UF` dictionary).
+fn file_spec(doc: &lopdf::Document, obj: Option<&lopdf::Object>) -> Option<String> {
+    string_value(doc, obj).or_else(|| {
+        let spec = resolve_dict(doc, obj)?;
+        string_value(doc, spec.get(b"UF").ok()).or_else(|| string_value(doc, spec.get(b"F").ok()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uri_rewrite_replaces_host_or_prefix() {
+        let rw = |uri: &str, from: &str, to: &str| rewrite_uri(uri, from, to);
+        assert_eq!(
+            rw("https://old.com:8080/docs?q=1", "old.com", "new.org").as_deref(),
+            Some("https://new.org:8080/docs?q=1")
+        );
+        assert_eq!(rw("https://www.old.com/", "old.com", "new.org"), None);
+        assert_eq!(
+            rw(
+                "https://old.com/v1/api",
+                "https://old.com/v1",
+                "https://new.org/v2"
+            )
+            .as_deref(),
+            Some("https://new.org/v2/api")
+        );
+    }
+
+    #[test]
+    fn links_resolve_uris_and_named_destinations() {
+        let mut doc = lopdf::Document::with_version("1.4");
+        let pages_id = doc.new_object_id();
+        let second_id = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"Page".to_vec())),
+            ("Parent", lopdf::Object::Reference(pages_id)),
+        ]));
+        let uri_link = lopdf::Dictionary::from_iter([
+            ("Subtype", lopdf::Object::Name(b"Link".to_vec())),
+            (
+                "Rect",
+                lopdf::Object::Array(vec![10.into(), 10.into(), 50.into(), 20.into()]),
+            ),
+            (
+                "A",
+                lopdf::Object::Dictionary(lopdf::Dictionary::from_iter([
+                    ("S", lopdf::Object::Name(b"URI".to_vec())),
+                    ("URI", lopdf::Object::string_literal("https://example.com")),
+                ])),
+            ),
+        ]);
+        let named_link = lopdf::Dictionary::from_iter([
+            ("Subtype", lopdf::Object::Name(b"Link".to_vec())),
+            ("Dest", lopdf::Object::string_literal("chapter2")),
+        ]);
+        let first_id = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"Page".to_vec())),
+            ("Parent", lopdf::Object::Reference(pages_id)),
+            (
+                "Annots",
+                lopdf::Object::Array(vec![uri_link.into(), named_link.into()]),
+            ),
+        ]));
+        doc.objects.insert(
+            pages_id,
+            lopdf::Object::Dictionary(lopdf::Dictionary::from_iter([
+                ("Type", lopdf::Object::Name(b"Pages".to_vec())),
+                (
+                    "Kids",
+                    lopdf::Object::Array(vec![first_id.into(), second_id.into()]),
+                ),
+                ("Count", lopdf::Object::Integer(2)),
+            ])),
+        );
+        let dest_tree = lopdf::Dictionary::from_iter([(
+            "Names",
+            lopdf::Object::Array(vec![
+                lopdf::Object::string_literal("chapter2"),
+                lopdf::Object::Array(vec![second_id.into(), lopdf::Object::Name(b"Fit".to_vec())]),
+            ]),
+        )]);
+        let catalog_id = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"Catalog".to_vec())),
+            ("Pages", lopdf::Object::Reference(pages_id)),
+            (
+                "Names",
+                lopdf::Object::Dictionary(lopdf::Dictionary::from_iter([(
+                    "Dests",
+                    lopdf::Object::Dictionary(dest_tree),
+                )])),
+            ),
+        ]));
+        doc.trailer.set("Root", catalog_id);
+
+        let links = collect_links(&doc);
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].page, 1);
+        assert_eq!(
+            links[0].target,
+            LinkTarget::Uri("https://example.com".to_string())
+        );
+        assert!((links[0].rect.width() - 40.0).abs() < f32::EPSILON);
+        assert_eq!(
+            links[1].target,
+            LinkTarget::Internal {
+                name: Some("chapter2".to_string()),
+                page: Some(2),
+            }
+        );
+    }
+}
@@ -0,0 +1,173 @@
+//! Integrity manifests for batch outputs: for each written file, where it
+//! came from, its size, and its SHA-256, so a transfer can be checked for
+//! completeness on the other side.
+
+use std::{
+    fmt::Write as _,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::{json_string, page_count, Result};
+
+/// One output file in a [`Manifest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    /// The output file.
+    pub path: PathBuf,
+    /// The PDF it was made from.
+    pub source: PathBuf,
+    /// 1-based pages of `source` it holds.
+    pub pages: Vec<u32>,
+    /// Size in bytes.
+    pub size: u64,
+    /// SHA-256 of the content, lower-case hex.
+    pub sha256: String,
+}
+
+/// Output files with their checksums, in the order they were added.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    /// The files, in the order they were added.
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    /// An empty manifest.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `path`, made from `pages` of `source`, reading it for its size
+    /// and checksum.
+    pub fn add(
+        &mut self,
+        path: impl Into<PathBuf>,
+        source: impl Into<PathBuf>,
+        pages: Vec<u32>,
+    ) -> Result<&ManifestEntry> {
+        let path = path.into();
+        let size = fs::metadata(&path)
+            .map_err(|e| std::io::Error::new(e.kind(), format!("{}: {e}", path.display())))?
+            .len();
+        let sha256 = file_sha256(&path)?;
+        self.entries.push(ManifestEntry {
+            path,
+            source: source.into(),
+            pages,
+            size,
+            sha256,
+        });
+        Ok(&self.entries[self.entries.len() - 1])
+    }
+
+    /// The manifest as a JSON document:
+    /// `{"files": [{"path", "source", "pages", "size", "sha256"}]}`.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\n  \"files\": [");
+        for (i, entry) in self.entries.iter().enumerate() {
+            let pages: Vec<String> = entry.pages.iter().map(ToString::to_string).collect();
+            let _ = write!(
+                out,
+                "{}\n    {{\"path\": {}, \"source\": {}, \"pages\": [{}], \"size\": {}, \"sha256\": \"{}\"}}",
+                if i == 0 { "" } else { "," },
+                json_path(&entry.path),
+                json_path(&entry.source),
+                pages.join(", "),
+                entry.size,
+                entry.sha256
+            );
+        }
+        out.push_str(if self.entries.is_empty() {
+            "]\n}\n"
+        } else {
+            "\n  ]\n}\n"
+        });
+        out
+    }
+
+    /// Write [`Manifest::to_json`] to `path`.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path.as_ref(), self.to_json())?;
+        Ok(())
+    }
+}
+
+/// The manifest of the files written by [`crate::split_pages`] with the same
+/// arguments (one file per page).
+pub fn split_pages_manifest(
+    input: impl AsRef<Path>,
+    out_dir: impl AsRef<Path>,
+    pattern: Option<&str>,
+) -> Result<Manifest> {
+    let (input, out_dir) = (input.as_ref(), out_dir.as_ref());
+    let pattern = pattern.map_or_else(
+        || out_dir.join("page-%d.pdf").to_string_lossy().into_owned(),
+        str::to_string,
+    );
+    let pages = page_count(input)?;
+    // qpdf pads page numbers to the width of the last one.
+    let width = pages.to_string().len();
+    let mut manifest = Manifest::new();
+    for page in 1..=pages {
+        let padded = PathBuf::from(pattern.replace("%d", &format!("{page:0width$}")));
+        let path = if padded.exists() {
+            padded
+        } else {
+            PathBuf::from(pattern.replace("%d", &page.to_string()))
+        };
+        manifest.add(path, input, vec![page])?;
+    }
+    Ok(manifest)
+}
+
+/// The manifest of the images written by [`crate::render`] into `out_dir`
+/// (`page-<n>.png`, one per page).
+pub fn render_manifest(input: impl AsRef<Path>, out_dir: impl AsRef<Path>) -> Result<Manifest> {
+    let (input, out_dir) = (input.as_ref(), out_dir.as_ref());
+    let mut manifest = Manifest::new();
+    for page in 1..=page_count(input)? {
+        manifest.add(out_dir.join(format!("page-{page}.png")), input, vec![page])?;
+    }
+    Ok(manifest)
+}
+
+fn json_path(path: &Path) -> String {
+    json_string(&path.to_string_lossy())
+}
+
+/// SHA-256 of a file's content as lower-case hex, read in chunks.
+pub(crate) fn file_sha256(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_record_size_and_checksum() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let page = dir.path().join("page \"1\".pdf");
+        fs::write(&page, "abc")?;
+        let mut manifest = Manifest::new();
+        let entry = manifest.add(&page, "in.pdf", vec![1])?;
+        assert_eq!(entry.size, 3);
+        assert_eq!(
+            entry.sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        let json = manifest.to_json();
+        assert!(json.contains(r#"page \"1\".pdf", "source": "in.pdf", "pages": [1], "size": 3"#));
+        assert_eq!(Manifest::new().to_json(), "{\n  \"files\": []\n}\n");
+        Ok(())
+    }
+}
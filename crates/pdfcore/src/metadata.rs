@@ -0,0 +1,297 @@
+//! Native editing of the document information dictionary, and patches
+//! that carry a reviewed set of changes to it from one file to many.
+
+use std::{collections::BTreeMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{info, load_document, save_document, validate_input_file, PdfError, Plan, Result};
+
+/// Document information entries whose values are names, not text strings.
+const NAME_ENTRIES: [&str; 1] = ["Trapped"];
+
+/// Changes to the document information dictionary, from [`metadata_diff`]
+/// or written by hand, applied with [`apply_metadata_patch`].
+///
+/// As JSON: `{"set": {"Title": "Annual Report"}, "remove": ["Producer"]}`;
+/// either member may be left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetadataPatch {
+    /// Entries to set, e.g. `Title`, to their new values.
+    pub set: BTreeMap<String, String>,
+    /// Entries to remove.
+    pub remove: Vec<String>,
+}
+
+impl MetadataPatch {
+    /// `true` if the patch changes nothing.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty() && self.remove.is_empty()
+    }
+
+    /// Parse a patch from JSON.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let patch: Self = serde_json::from_str(json)
+            .map_err(|e| PdfError::InvalidArgument(format!("invalid metadata patch: {e}")))?;
+        patch.validate()?;
+        Ok(patch)
+    }
+
+    /// The patch as pretty-printed JSON, ending in a newline.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let mut json = serde_json::to_string_pretty(self).unwrap_or_default();
+        json.push('\n');
+        json
+    }
+
+    fn validate(&self) -> Result<()> {
+        for key in self.set.keys().chain(&self.remove) {
+            if key.is_empty()
+                || key
+                    .bytes()
+                    .any(|b| b.is_ascii_whitespace() || b"/()<>[]{}%".contains(&b))
+            {
+                return Err(PdfError::InvalidArgument(format!(
+                    "invalid document info key: {key:?}"
+                )));
+            }
+        }
+        if let Some(key) = self.remove.iter().find(|key| self.set.contains_key(*key)) {
+            return Err(PdfError::InvalidArgument(format!(
+                "{key} is both set and removed"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The patch that turns the document information of `a` into that of `b`
+/// (pure Rust): entries of `b` that `a` lacks or has with another value are
+/// set, and entries only `a` has are removed.
+pub fn metadata_diff(a: impl AsRef<Path>, b: impl AsRef<Path>) -> Result<MetadataPatch> {
+    let (before, after) = (info(a)?.metadata, info(b)?.metadata);
+    Ok(MetadataPatch {
+        set: after
+            .iter()
+            .filter(|(key, value)| before.get(*key) != Some(*value))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect(),
+        remove: before
+            .keys()
+            .filter(|key| !after.contains_key(*key))
+            .cloned()
+            .collect(),
+    })
+}
+
+/// Apply `patch` to the document information of `input` (pure Rust). Other
+/// entries, and the XMP metadata, are left as they are.
+pub fn apply_metadata_patch(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    patch: &MetadataPatch,
+) -> Result<()> {
+    patch.validate()?;
+    let mut doc = load_document(input.as_ref())?;
+    let info = info_dict_mut(&mut doc)?;
+    for key in &patch.remove {
+        info.remove(key.as_bytes());
+    }
+    for (key, value) in &patch.set {
+        if NAME_ENTRIES.contains(&key.as_str()) {
+            info.set(
+                key.as_bytes(),
+                lopdf::Object::Name(value.as_bytes().to_vec()),
+            );
+        } else {
+            info.set(key.as_bytes(), text_string(value));
+        }
+    }
+    save_document(&mut doc, output.as_ref())
+}
+
+/// What [`apply_metadata_patch`] would do.
+pub fn apply_metadata_patch_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    patch: &MetadataPatch,
+) -> Result<Plan> {
+    validate_input_file(input.as_ref())?;
+    patch.validate()?;
+    let mut plan = Plan::new();
+    for key in &patch.remove {
+        plan = plan.native(format!("remove document info {key}"));
+    }
+    for (key, value) in &patch.set {
+        plan = plan.native(format!("set document info {key} to {value:?}"));
+    }
+    Ok(plan.write(output.as_ref()))
+}
+
+/// Encode `s` as a PDF text string: a literal string if it is plain ASCII,
+/// otherwise UTF-16BE with a byte-order mark.
+pub(crate) fn text_string(s: &str) -> lopdf::Object {
+    if s.is_ascii() {
+        return lopdf::Object::string_literal(s);
+    }
+    let mut bytes = vec![0xFE, 0xFF];
+    for unit in s.encode_utf16() {
+        bytes.extend_from_slice(&unit.to_be_bytes());
+    }
+    lopdf::Object::String(bytes, lopdf::StringFormat::Hexadecimal)
+}
+
+/// [`decode_text_string`], or `None` if some bytes cannot be decoded.
+pub(crate) fn try_decode_text_string(bytes: &[u8]) -> Option<String> {
+    if let Some(utf16) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        if utf16.len() % 2 != 0 {
+            return None;
+        }
+        let units: Vec<u16> = utf16
+            .chunks_exact(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .collect();
+        return String::from_utf16(&units).ok();
+    }
+    let utf8 = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]);
+    match (utf8, std::str::from_utf8(utf8.unwrap_or(bytes))) {
+        (_, Ok(text)) => Some(text.to_string()),
+        (Some(_), Err(_)) => None,
+        (None, Err(_)) => bytes.iter().map(|&b| pdf_doc_char(b)).collect(),
+    }
+}
+
+/// Decode a PDF text string: UTF-16BE or UTF-8 after a byte-order mark,
+/// else `PDFDocEncoding`. Strings without a byte-order mark that are valid
+/// UTF-8, as some producers write them, are read as UTF-8. Bytes that
+/// cannot be decoded become U+FFFD.
+pub(crate) fn decode_text_string(bytes: &[u8]) -> String {
+    if let Some(utf16) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = utf16
+            .chunks_exact(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .collect();
+        return String::from_utf16_lossy(&units);
+    }
+    if let Some(utf8) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return String::from_utf8_lossy(utf8).into_owned();
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes
+            .iter()
+            .map(|&b| pdf_doc_char(b).unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect(),
+    }
+}
+
+/// The character `PDFDocEncoding` (ISO 32000-1, Annex D) gives `byte`:
+/// Latin-1, but for the accents at 0x18–0x1F and the punctuation and
+/// letters at 0x80–0xA0. `None` for the undefined 0x7F, 0x9F, and 0xAD.
+fn pdf_doc_char(byte: u8) -> Option<char> {
+    const ACCENTS: [char; 8] = ['˘', 'ˇ', 'ˆ', '˙', '˝', '˛', '˚', '˜'];
+    const HIGH: [char; 31] = [
+        '•', '†', '‡', '…', '—', '–', 'ƒ', '⁄', '‹', '›', '−', '‰', '„', '“', '”', '‘', '’', '‚',
+        '™', 'ﬁ', 'ﬂ', 'Ł', 'Œ', 'Š', 'Ÿ', 'Ž', 'ı', 'ł', 'œ', 'š', 'ž',
+    ];
+    match byte {
+        0x18..=0x1F => Some(ACCENTS[usize::from(byte - 0x18)]),
+        0x80..=0x9E => Some(HIGH[usize::from(byte - 0x80)]),
+        0xA0 => Some('€'),
+        0x7F | 0x9F | 0xAD => None,
+        _ => Some(char::from(byte)),
+    }
+}
+
+/// Return the trailer's `/Info` dictionary, creating it if missing.
+pub(crate) fn info_dict_mut(doc: &mut lopdf::Document) -> Result<&mut lopdf::Dictionary> {
+    let info_id = match doc
+        .trailer
+        .get(b"Info")
+        .and_then(lopdf::Object::as_reference)
+    {
+        Ok(id) if doc.get_dictionary(id).is_ok() => id,
+        _ => {
+            let id = doc.add_object(lopdf::Dictionary::new());
+            doc.trailer.set("Info", id);
+            id
+        }
+    };
+    doc.get_dictionary_mut(info_id)
+        .map_err(|_| PdfError::InvalidArgument("document info is not a dictionary".to_string()))
+}
+
+/// Set a string entry (e.g. `Title`) in the document information dictionary.
+pub(crate) fn set_info_entry(doc: &mut lopdf::Document, key: &str, value: &str) -> Result<()> {
+    info_dict_mut(doc)?.set(key, text_string(value));
+    Ok(())
+}
+
+/// Read a string entry from the document information dictionary.
+pub(crate) fn info_entry(doc: &lopdf::Document, key: &str) -> Option<String> {
+    let info = crate::fonts::resolve_dict(doc, doc.trailer.get(b"Info").ok())?;
+    match info.get(key.as_bytes()).ok()? {
+        lopdf::Object::String(bytes, _) => Some(decode_text_string(bytes)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_strings_round_trip() {
+        for s in ["Plain title", "Überschrift – 日本"] {
+            let lopdf::Object::String(bytes, _) = text_string(s) else {
+                panic!("expected a string object");
+            };
+            assert_eq!(decode_text_string(&bytes), s);
+            assert_eq!(try_decode_text_string(&bytes).as_deref(), Some(s));
+        }
+        // An unpaired surrogate, and an odd length.
+        assert_eq!(try_decode_text_string(&[0xFE, 0xFF, 0xD8, 0x00]), None);
+        assert_eq!(try_decode_text_string(&[0xFE, 0xFF, 0x00]), None);
+        assert_eq!(
+            try_decode_text_string(b"\xef\xbb\xbfna\xc3\xafve").as_deref(),
+            Some("naïve")
+        );
+    }
+
+    #[test]
+    fn pdf_doc_encoding_is_decoded() {
+        assert_eq!(
+            try_decode_text_string(b"caf\xe9 \x84 \x93ve \x92 \xa0 5").as_deref(),
+            Some("café — ﬁve ™ € 5")
+        );
+        assert_eq!(decode_text_string(b"\x8dQuote\x8e"), "“Quote”");
+        assert_eq!(try_decode_text_string(b"bad \x9f"), None);
+        assert_eq!(decode_text_string(b"bad \x9f"), "bad \u{fffd}");
+    }
+
+    #[test]
+    fn patches_round_trip_through_json() -> Result<()> {
+        let patch = MetadataPatch::from_json(r#"{"set": {"Title": "Report"}}"#)?;
+        assert_eq!(patch.set.get("Title").map(String::as_str), Some("Report"));
+        assert!(patch.remove.is_empty());
+        assert_eq!(MetadataPatch::from_json(&patch.to_json())?, patch);
+
+        assert!(MetadataPatch::from_json(r#"{"set": {"Title": 1}}"#).is_err());
+        assert!(MetadataPatch::from_json(r#"{"sets": {}}"#).is_err());
+        assert!(MetadataPatch::from_json(r#"{"remove": ["Bad Key"]}"#).is_err());
+        let both = r#"{"set": {"Title": "x"}, "remove": ["Title"]}"#;
+        assert!(MetadataPatch::from_json(both).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn set_info_entry_creates_info_dict() -> Result<()> {
+        let mut doc = lopdf::Document::with_version("1.4");
+        set_info_entry(&mut doc, "Title", "Report")?;
+        assert_eq!(info_entry(&doc, "Title").as_deref(), Some("Report"));
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+//! Attachments of Outlook messages (`.msg`): a compound file (the
+//! container format of legacy Office documents) in which each attachment
+//! is a storage of MAPI property streams.
+
+use std::{
+    io::{Cursor, Read},
+    path::{Path, PathBuf},
+};
+
+use cfb::CompoundFile;
+
+use crate::email::Attachment;
+
+/// Compound files start with this signature.
+const MAGIC: [u8; 8] = [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+
+const ATTACHMENT_PREFIX: &str = "__attach_version1.0_#";
+/// `PR_ATTACH_DATA_BIN`: the attached file.
+const ATTACH_DATA: &str = "__substg1.0_37010102";
+/// `PR_ATTACH_DATA_OBJ`: an attached message, as a storage.
+const ATTACH_MESSAGE: &str = "__substg1.0_3701000D";
+/// `PR_ATTACH_LONG_FILENAME` and `PR_ATTACH_FILENAME`, as Unicode or 8-bit
+/// strings, in order of preference.
+const ATTACH_NAMES: [&str; 4] = [
+    "__substg1.0_3707001F",
+    "__substg1.0_3707001E",
+    "__substg1.0_3704001F",
+    "__substg1.0_3704001E",
+];
+/// `PR_ATTACH_MIME_TAG`.
+const ATTACH_MIME: [&str; 2] = ["__substg1.0_370E001F", "__substg1.0_370E001E"];
+
+/// How deep attached messages are followed.
+const MAX_NESTING: usize = 32;
+
+type Message<'a> = CompoundFile<Cursor<&'a [u8]>>;
+
+pub(crate) fn is_msg(data: &[u8]) -> bool {
+    data.starts_with(&MAGIC)
+}
+
+/// The attachments of the Outlook message `data`, including those of the
+/// messages attached to it.
+pub(crate) fn attachments(data: &[u8]) -> Result<Vec<Attachment>, String> {
+    let mut file = CompoundFile::open(Cursor::new(data)).map_err(|e| e.to_string())?;
+    let mut found = Vec::new();
+    message_attachments(&mut file, Path::new("/"), 0, &mut found)?;
+    Ok(found)
+}
+
+/// Collect the attachments of the message stored at `message` into `found`.
+fn message_attachments(
+    file: &mut Message,
+    message: &Path,
+    depth: usize,
+    found: &mut Vec<Attachment>,
+) -> Result<(), String> {
+    if depth > MAX_NESTING {
+        return Ok(());
+    }
+    let storages: Vec<PathBuf> = file
+        .read_storage(message)
+        .map_err(|e| e.to_string())?
+        .filter(|entry| entry.is_storage() && entry.name().starts_with(ATTACHMENT_PREFIX))
+        .map(|entry| entry.path().to_path_buf())
+        .collect();
+    for storage in storages {
+        let data = storage.join(ATTACH_DATA);
+        let attached = storage.join(ATTACH_MESSAGE);
+        if file.is_stream(&data) {
+            found.push(Attachment {
+                name: string(file, &storage, &ATTACH_NAMES)?,
+                content_type: string(file, &storage, &ATTACH_MIME)?
+                    .unwrap_or_else(|| "application/octet-stream".to_string())
+                    .to_ascii_lowercase(),
+                data: stream(file, &data)?,
+            });
+        } else if file.is_storage(&attached) {
+            message_attachments(file, &attached, depth + 1, found)?;
+        }
+    }
+    Ok(())
+}
+
+fn stream(file: &mut Message, path: &Path) -> Result<Vec<u8>, String> {
+    let mut data = Vec::new();
+    file.open_stream(path)
+        .and_then(|mut stream| stream.read_to_end(&mut data))
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    Ok(data)
+}
+
+/// The first of the string properties `names` in `storage`.
+fn string(file: &mut Message, storage: &Path, names: &[&str]) -> Result<Option<String>, String> {
+    for name in names {
+        let path = storage.join(name);
+        if !file.is_stream(&path) {
+            continue;
+        }
+        let bytes = stream(file, &path)?;
+        let text = if name.ends_with("001F") {
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        } else {
+            String::from_utf8_lossy(&bytes).into_owned()
+        };
+        let text = text.trim_end_matches('\0').trim().to_string();
+        if !text.is_empty() {
+            return Ok(Some(text));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    /// An Outlook message with a PDF small enough for the mini stream, and
+    /// an attached message holding a PDF that is not.
+    fn message(small: &[u8], large: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut file = CompoundFile::create(Cursor::new(Vec::new()))?;
+        let first = Path::new("/__attach_version1.0_#00000000");
+        let second = Path::new("/__attach_version1.0_#00000001");
+        let attached = second.join(ATTACH_MESSAGE);
+        let nested = attached.join("__attach_version1.0_#00000000");
+        for storage in [first, second, &attached, &nested] {
+            file.create_storage(storage)?;
+        }
+        let streams: [(PathBuf, &[u8]); 5] = [
+            (first.join(ATTACH_DATA), small),
+            (first.join(ATTACH_NAMES[0]), &utf16("Scan.pdf\0")),
+            (nested.join(ATTACH_DATA), large),
+            (nested.join(ATTACH_NAMES[3]), b"forwarded.pdf"),
+            (nested.join(ATTACH_MIME[1]), b"Application/PDF"),
+        ];
+        for (path, data) in streams {
+            file.create_stream(path)?.write_all(data)?;
+        }
+        file.flush()?;
+        Ok(file.into_inner().into_inner())
+    }
+
+    #[test]
+    fn attachments_are_read_from_attached_messages_too() -> Result<(), String> {
+        let small = b"%PDF-1.4\n".repeat(20);
+        let large = b"%PDF-1.7\n".repeat(1000);
+        let file = message(&small, &large).map_err(|e| e.to_string())?;
+        assert!(is_msg(&file));
+        let found = attachments(&file)?;
+        let summary: Vec<(Option<&str>, &str, &[u8])> = found
+            .iter()
+            .map(|a| {
+                (
+                    a.name.as_deref(),
+                    a.content_type.as_str(),
+                    a.data.as_slice(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (Some("Scan.pdf"), "application/octet-stream", &small[..]),
+                (Some("forwarded.pdf"), "application/pdf", &large[..]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn truncated_messages_are_errors() -> Result<(), String> {
+        let large = b"%PDF-1.7\n".repeat(1000);
+        let file = message(b"%PDF-1.4\n", &large).map_err(|e| e.to_string())?;
+        for len in [8, 512, 1536, file.len() / 2] {
+            assert!(attachments(&file[..len]).is_err(), "{len} bytes");
+        }
+        Ok(())
+    }
+}
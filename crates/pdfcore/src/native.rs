@@ -0,0 +1,357 @@
+//! Pure Rust fallbacks for operations usually served by external tools.
+//!
+//! These are selected through [`crate::select_engine`] when the tool is missing or
+//! the native engine is preferred. They cover the common cases only: merging
+//! keeps pages (with their annotations) but drops document-level structures
+//! such as outlines and forms, as `qpdf --empty --pages` does.
+
+use std::{collections::BTreeMap, path::Path};
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+
+use crate::{load_document, objects, save_document, text, PageSelection, PdfError, Result};
+
+/// Page attributes inherited from the page tree (PDF 32000-1, 7.7.3.4).
+const INHERITABLE: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+/// Concatenate the pages of `inputs` into `output`.
+pub(crate) fn merge(inputs: &[&Path], output: &Path) -> Result<()> {
+    let mut dst = Document::with_version("1.4");
+    let pages_id = dst.new_object_id();
+    let mut kids = Vec::new();
+    for input in inputs {
+        let src = load_document(input)?;
+        if src.version > dst.version {
+            dst.version.clone_from(&src.version);
+        }
+
+        // Reserve the new page numbers first, so that references to pages
+        // (annotation /P entries, link destinations) map onto the copies
+        // instead of importing the source page tree.
+        let mut map = BTreeMap::new();
+        let pages: Vec<(ObjectId, ObjectId)> = src
+            .get_pages()
+            .into_values()
+            .map(|id| (id, dst.new_object_id()))
+            .collect();
+        map.extend(pages.iter().copied());
+
+        for (src_id, dst_id) in pages {
+            let page = standalone_page(&src, src_id)?;
+            let Object::Dictionary(mut page) =
+                objects::import_object(&mut dst, &src, &Object::Dictionary(page), &mut map)
+            else {
+                unreachable!("importing a dictionary yields a dictionary");
+            };
+            page.set("Parent", pages_id);
+            dst.objects.insert(dst_id, Object::Dictionary(page));
+            kids.push(Object::Reference(dst_id));
+        }
+    }
+
+    let count = i64::try_from(kids.len()).unwrap_or(i64::MAX);
+    dst.objects.insert(
+        pages_id,
+        Object::Dictionary(Dictionary::from_iter([
+            ("Type", Object::Name(b"Pages".to_vec())),
+            ("Kids", Object::Array(kids)),
+            ("Count", Object::Integer(count)),
+        ])),
+    );
+    let catalog = dst.add_object(Dictionary::from_iter([
+        ("Type", Object::Name(b"Catalog".to_vec())),
+        ("Pages", Object::Reference(pages_id)),
+    ]));
+    dst.trailer.set("Root", catalog);
+    save_document(&mut dst, output)
+}
+
+/// Keep only `pages` (1-based, validated by the caller), in that order. The
+/// rest of the document (outlines, forms, metadata) is kept; references to
+/// dropped pages become null.
+pub(crate) fn select_pages(input: &Path, output: &Path, pages: &[u32]) -> Result<()> {
+    let mut doc = load_document(input)?;
+    let root = doc
+        .catalog()
+        .and_then(|catalog| catalog.get(b"Pages"))
+        .and_then(Object::as_reference)
+        .map_err(|_| PdfError::InvalidArgument("document has no page tree".to_string()))?;
+    let all = doc.get_pages();
+    let mut kept = Vec::with_capacity(pages.len());
+    for number in pages {
+        let id = *all
+            .get(number)
+            .ok_or_else(|| PdfError::InvalidArgument(format!("page {number} does not exist")))?;
+        let mut page = standalone_page(&doc, id)?;
+        page.set("Parent", root);
+        kept.push((id, page));
+    }
+    for id in all.values() {
+        doc.objects.remove(id);
+    }
+    let kids: Vec<Object> = kept.iter().map(|&(id, _)| Object::Reference(id)).collect();
+    let count = i64::try_from(kids.len()).unwrap_or(i64::MAX);
+    for (id, page) in kept {
+        doc.objects.insert(id, Object::Dictionary(page));
+    }
+    let tree = doc
+        .get_dictionary_mut(root)
+        .map_err(|_| PdfError::InvalidArgument("document has no page tree".to_string()))?;
+    tree.set("Kids", Object::Array(kids));
+    tree.set("Count", count);
+    doc.prune_objects();
+    save_document(&mut doc, output)
+}
+
+/// The text of each page with runs of whitespace collapsed and control
+/// characters (which a terminal would take for escape sequences) removed,
+/// cut to at most `max_chars` characters.
+pub(crate) fn page_previews(input: &Path, max_chars: usize) -> Result<Vec<String>> {
+    let doc = load_document(input)?;
+    Ok(doc
+        .get_pages()
+        .into_values()
+        .map(|page_id| {
+            let Ok(content) = doc.get_and_decode_page_content(page_id) else {
+                return String::new();
+            };
+            let (text, _) = text::layout(&doc, page_id, &content.operations).text_with_origins();
+            let text = text.replace(char::is_control, " ");
+            let words: Vec<&str> = text.split_whitespace().collect();
+            words.join(" ").chars().take(max_chars).collect()
+        })
+        .collect())
+}
+
+/// A copy of a page dictionary with inherited attributes made explicit and
+/// the link to its parent removed.
+fn standalone_page(doc: &Document, page_id: ObjectId) -> Result<Dictionary> {
+    let mut page = doc
+        .get_dictionary(page_id)
+        .map_err(|_| PdfError::InvalidArgument(format!("page object {page_id:?} not found")))?
+        .clone();
+    for key in INHERITABLE {
+        if !page.has(key) {
+            if let Some(value) = inherited(doc, &page, key) {
+                page.set(key, value.clone());
+            }
+        }
+    }
+    page.remove(b"Parent");
+    Ok(page)
+}
+
+pub(crate) fn inherited<'a>(
+    doc: &'a Document,
+    page: &'a Dictionary,
+    key: &[u8],
+) -> Option<&'a Object> {
+    let mut node = page;
+    for _ in 0..32 {
+        if let Ok(value) = node.get(key) {
+            return Some(value);
+        }
+        node = node
+            .get(b"Parent")
+            .and_then(Object::as_reference)
+            .and_then(|id| doc.get_dictionary(id))
+            .ok()?;
+    }
+    None
+}
+
+/// Add `degrees` to the rotation of the selected pages.
+pub(crate) fn rotate(
+    input: &Path,
+    output: &Path,
+    degrees: u16,
+    pages: &PageSelection,
+) -> Result<()> {
+    rotate_spec(input, output, &[(pages.clone(), degrees)])
+}
+
+/// Add a rotation to each page selected in `spec`.
+pub(crate) fn rotate_spec(
+    input: &Path,
+    output: &Path,
+    spec: &[(PageSelection, u16)],
+) -> Result<()> {
+    let mut doc = load_document(input)?;
+    for (number, page_id) in doc.get_pages() {
+        let Some(&(_, degrees)) = spec.iter().find(|(pages, _)| pages.contains(number)) else {
+            continue;
+        };
+        let current = doc
+            .get_dictionary(page_id)
+            .ok()
+            .and_then(|page| inherited(&doc, page, b"Rotate"))
+            .and_then(|r| r.as_i64().ok())
+            .unwrap_or(0);
+        let rotation = (current + i64::from(degrees)).rem_euclid(360);
+        if let Ok(page) = doc.get_dictionary_mut(page_id) {
+            page.set("Rotate", rotation);
+        }
+    }
+    save_document(&mut doc, output)
+}
+
+/// Page text in reading order as laid out on the page, one form feed after
+/// each page (like `pdftotext`).
+pub(crate) fn extract_text(input: &Path) -> Result<String> {
+    let mut out = String::new();
+    for page_text in page_texts(input)? {
+        out.push_str(&page_text);
+        out.push('\x0c');
+    }
+    Ok(out)
+}
+
+/// The text of each page, each ending with a newline unless it is empty.
+pub(crate) fn page_texts(input: &Path) -> Result<Vec<String>> {
+    let doc = load_document(input)?;
+    let mut pages = Vec::new();
+    for page_id in doc.get_pages().into_values() {
+        let mut page_text = String::new();
+        if let Ok(content) = doc.get_and_decode_page_content(page_id) {
+            (page_text, _) = text::layout(&doc, page_id, &content.operations).text_with_origins();
+            if !page_text.ends_with('\n') {
+                page_text.push('\n');
+            }
+        }
+        pages.push(page_text);
+    }
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn one_page(text: &str) -> Document {
+        let mut doc = Document::with_version("1.5");
+        let pages = doc.new_object_id();
+        let content = doc.add_object(lopdf::Stream::new(
+            Dictionary::new(),
+            format!("BT /F1 12 Tf 72 720 Td ({text}) Tj ET").into_bytes(),
+        ));
+        let font = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type1".to_vec())),
+            ("BaseFont", Object::Name(b"Courier".to_vec())),
+        ]));
+        let page = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Page".to_vec())),
+            ("Parent", Object::Reference(pages)),
+            ("Contents", Object::Reference(content)),
+        ]));
+        doc.objects.insert(
+            pages,
+            Object::Dictionary(Dictionary::from_iter([
+                ("Type", Object::Name(b"Pages".to_vec())),
+                ("Kids", Object::Array(vec![Object::Reference(page)])),
+                ("Count", Object::Integer(1)),
+                ("Rotate", Object::Integer(90)),
+                (
+                    "Resources",
+                    Object::Dictionary(Dictionary::from_iter([(
+                        "Font",
+                        Object::Dictionary(Dictionary::from_iter([(
+                            "F1",
+                            Object::Reference(font),
+                        )])),
+                    )])),
+                ),
+            ])),
+        );
+        let catalog = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Catalog".to_vec())),
+            ("Pages", Object::Reference(pages)),
+        ]));
+        doc.trailer.set("Root", catalog);
+        doc
+    }
+
+    #[test]
+    fn merge_rotate_and_extract_text() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let a = dir.path().join("a.pdf");
+        let b = dir.path().join("b.pdf");
+        one_page("first").save(&a)?;
+        one_page("second").save(&b)?;
+
+        let merged = dir.path().join("merged.pdf");
+        merge(&[&a, &b], &merged)?;
+        assert_eq!(extract_text(&merged)?, "first\n\x0csecond\n\x0c");
+
+        let rotated = dir.path().join("rotated.pdf");
+        rotate(
+            &merged,
+            &rotated,
+            270,
+            &PageSelection::Range { start: 2, end: 2 },
+        )?;
+        let doc = load_document(&rotated)?;
+        let rotations: Vec<i64> = doc
+            .get_pages()
+            .into_values()
+            .map(|id| {
+                doc.get_dictionary(id)
+                    .and_then(|p| p.get(b"Rotate"))
+                    .and_then(Object::as_i64)
+                    .unwrap_or(-1)
+            })
+            .collect();
+        // The inherited rotation was made explicit when merging.
+        assert_eq!(rotations, vec![90, 0]);
+
+        let respun = dir.path().join("respun.pdf");
+        rotate_spec(
+            &rotated,
+            &respun,
+            &[
+                (PageSelection::Range { start: 1, end: 1 }, 180),
+                (PageSelection::From { start: 2 }, 90),
+            ],
+        )?;
+        let doc = load_document(&respun)?;
+        let rotations: Vec<i64> = doc
+            .get_pages()
+            .into_values()
+            .filter_map(|id| {
+                doc.get_dictionary(id)
+                    .ok()?
+                    .get(b"Rotate")
+                    .ok()?
+                    .as_i64()
+                    .ok()
+            })
+            .collect();
+        assert_eq!(rotations, vec![270, 90]);
+        Ok(())
+    }
+
+    #[test]
+    fn pages_are_selected_in_the_given_order() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let inputs: Vec<_> = ["first", "second", "third  page"]
+            .iter()
+            .enumerate()
+            .map(|(i, text)| {
+                let path = dir.path().join(format!("{i}.pdf"));
+                one_page(text).save(&path).map(|_| path)
+            })
+            .collect::<std::io::Result<_>>()?;
+        let inputs: Vec<&Path> = inputs.iter().map(std::path::PathBuf::as_path).collect();
+        let merged = dir.path().join("merged.pdf");
+        merge(&inputs, &merged)?;
+        assert_eq!(page_previews(&merged, 8)?, ["first", "second", "third pa"]);
+
+        let picked = dir.path().join("picked.pdf");
+        select_pages(&merged, &picked, &[3, 1])?;
+        assert_eq!(extract_text(&picked)?, "third  page\n\x0cfirst\n\x0c");
+        // The dropped page's content went with it.
+        let bytes = std::fs::read(&picked)?;
+        assert!(!bytes.windows(6).any(|w| w == b"second"));
+        Ok(())
+    }
+}
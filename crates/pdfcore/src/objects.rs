@@ -0,0 +1,363 @@
+//! Low-level object inspection for debugging malformed files.
+
+use std::{collections::BTreeMap, fmt::Write as _, path::Path};
+
+use crate::{load_document, PdfError, Result};
+
+/// One indirect object, as listed by [`objects`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectSummary {
+    /// Object number and generation.
+    pub id: (u32, u16),
+    /// `/Type` (and `/Subtype`, if any) for dictionaries and streams, e.g.
+    /// `XObject/Image`; otherwise the object kind, e.g. `array`.
+    pub kind: String,
+    /// Stored (undecoded) stream length, for streams.
+    pub stream_length: Option<usize>,
+}
+
+/// A single object with its stream data, as returned by [`show_object`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectDump {
+    /// Object number and generation.
+    pub id: (u32, u16),
+    /// The object (for streams, the stream dictionary) in PDF syntax.
+    pub text: String,
+    /// Stream data, if the object is a stream.
+    pub stream: Option<Vec<u8>>,
+    /// Whether `stream` has had its filters removed.
+    pub decoded: bool,
+}
+
+/// List every indirect object in a PDF, in object-number order (pure Rust).
+pub fn objects(path: impl AsRef<Path>) -> Result<Vec<ObjectSummary>> {
+    let doc = load_document(path.as_ref())?;
+    Ok(doc
+        .objects
+        .iter()
+        .map(|(&id, obj)| ObjectSummary {
+            id,
+            kind: object_kind(obj),
+            stream_length: obj.as_stream().ok().map(|s| s.content.len()),
+        })
+        .collect())
+}
+
+/// Dump one object by number (pure Rust).
+///
+/// Streams are decoded unless `raw` is set. If a stream uses a filter lopdf
+/// cannot decode, the stored bytes are returned and `decoded` is `false`.
+pub fn show_object(path: impl AsRef<Path>, number: u32, raw: bool) -> Result<ObjectDump> {
+    let doc = load_document(path.as_ref())?;
+    let (&id, obj) = doc
+        .objects
+        .iter()
+        .find(|((n, _), _)| *n == number)
+        .ok_or_else(|| PdfError::InvalidArgument(format!("object {number} not found")))?;
+
+    let mut text = String::new();
+    let (stream, decoded) = match obj {
+        lopdf::Object::Stream(s) => {
+            write_dict(&mut text, &s.dict);
+            let unfiltered = !s.dict.has(b"Filter");
+            if raw || unfiltered {
+                (Some(s.content.clone()), unfiltered)
+            } else {
+                match s.decompressed_content() {
+                    Ok(bytes) => (Some(bytes), true),
+                    Err(_) => (Some(s.content.clone()), false),
+                }
+            }
+        }
+        other => {
+            write_object(&mut text, other);
+            (None, false)
+        }
+    };
+    Ok(ObjectDump {
+        id,
+        text,
+        stream,
+        decoded,
+    })
+}
+
+/// Decode a page's content streams and print one operator per line,
+/// indented by `q`/`Q`, `BT`/`ET`, and marked-content nesting (pure Rust).
+///
+/// `page` is 1-based.
+pub fn content_dump(path: impl AsRef<Path>, page: u32) -> Result<String> {
+    let doc = load_document(path.as_ref())?;
+    let pages = doc.get_pages();
+    let page_id = *pages.get(&page).ok_or_else(|| {
+        PdfError::InvalidArgument(format!(
+            "page {page} out of range (document has {} pages)",
+            pages.len()
+        ))
+    })?;
+    let content = doc
+        .get_and_decode_page_content(page_id)
+        .map_err(|e| PdfError::InvalidArgument(format!("decoding page {page} content: {e}")))?;
+    Ok(format_operations(&content.operations))
+}
+
+fn format_operations(ops: &[lopdf::content::Operation]) -> String {
+    let mut out = String::new();
+    let mut depth = 0_usize;
+    for op in ops {
+        if matches!(op.operator.as_str(), "Q" | "ET" | "EMC") {
+            depth = depth.saturating_sub(1);
+        }
+        out.push_str(&"  ".repeat(depth));
+        for operand in &op.operands {
+            let mut text = String::new();
+            write_object(&mut text, operand);
+            // Inline dictionaries (e.g. BDC properties) stay on one line.
+            out.push_str(&text.replace("\n  ", " ").replace('\n', " "));
+            out.push(' ');
+        }
+        out.push_str(&op.operator);
+        out.push('\n');
+        if matches!(op.operator.as_str(), "q" | "BT" | "BDC" | "BMC") {
+            depth += 1;
+        }
+    }
+    out
+}
+
+/// Call `f` on every dictionary in `obj`, including stream dictionaries and
+/// dictionaries nested directly (not via references) inside it.
+pub(crate) fn visit_dicts_mut(obj: &mut lopdf::Object, f: &mut impl FnMut(&mut lopdf::Dictionary)) {
+    visit_dicts_mut_at(obj, f, 0);
+}
+
+fn visit_dicts_mut_at(
+    obj: &mut lopdf::Object,
+    f: &mut impl FnMut(&mut lopdf::Dictionary),
+    depth: usize,
+) {
+    if depth > 32 {
+        return;
+    }
+    let dict = match obj {
+        lopdf::Object::Dictionary(d) => d,
+        lopdf::Object::Stream(s) => &mut s.dict,
+        lopdf::Object::Array(items) => {
+            for item in items {
+                visit_dicts_mut_at(item, f, depth + 1);
+            }
+            return;
+        }
+        _ => return,
+    };
+    f(dict);
+    for (_, value) in dict.iter_mut() {
+        visit_dicts_mut_at(value, f, depth + 1);
+    }
+}
+
+/// Deep-copy `obj` from `src` into `dst`, adding every object it references
+/// to `dst` under a new number. `map` records objects already copied so
+/// shared and cyclic references are preserved.
+pub(crate) fn import_object(
+    dst: &mut lopdf::Document,
+    src: &lopdf::Document,
+    obj: &lopdf::Object,
+    map: &mut BTreeMap<lopdf::ObjectId, lopdf::ObjectId>,
+) -> lopdf::Object {
+    match obj {
+        lopdf::Object::Reference(id) => {
+            if let Some(&new_id) = map.get(id) {
+                return lopdf::Object::Reference(new_id);
+            }
+            let new_id = dst.new_object_id();
+            map.insert(*id, new_id);
+            let copied = src.get_object(*id).map_or(lopdf::Object::Null, |target| {
+                import_object(dst, src, target, map)
+            });
+            dst.objects.insert(new_id, copied);
+            lopdf::Object::Reference(new_id)
+        }
+        lopdf::Object::Array(items) => lopdf::Object::Array(
+            items
+                .iter()
+                .map(|item| import_object(dst, src, item, map))
+                .collect(),
+        ),
+        lopdf::Object::Dictionary(dict) => {
+            lopdf::Object::Dictionary(import_dict(dst, src, dict, map))
+        }
+        lopdf::Object::Stream(stream) => {
+            let dict = import_dict(dst, src, &stream.dict, map);
+            lopdf::Object::Stream(
+                lopdf::Stream::new(dict, stream.content.clone()).with_compression(false),
+            )
+        }
+        other => other.clone(),
+    }
+}
+
+fn import_dict(
+    dst: &mut lopdf::Document,
+    src: &lopdf::Document,
+    dict: &lopdf::Dictionary,
+    map: &mut BTreeMap<lopdf::ObjectId, lopdf::ObjectId>,
+) -> lopdf::Dictionary {
+    dict.iter()
+        .map(|(key, value)| (key.clone(), import_object(dst, src, value, map)))
+        .collect()
+}
+
+fn object_kind(obj: &lopdf::Object) -> String {
+    let dict = match obj {
+        lopdf::Object::Dictionary(d) => d,
+        lopdf::Object::Stream(s) => &s.dict,
+        lopdf::Object::Null => return "null".to_string(),
+        lopdf::Object::Boolean(_) => return "boolean".to_string(),
+        lopdf::Object::Integer(_) => return "integer".to_string(),
+        lopdf::Object::Real(_) => return "real".to_string(),
+        lopdf::Object::Name(_) => return "name".to_string(),
+        lopdf::Object::String(..) => return "string".to_string(),
+        lopdf::Object::Array(_) => return "array".to_string(),
+        lopdf::Object::Reference(_) => return "reference".to_string(),
+    };
+    let name = |key: &[u8]| {
+        dict.get(key)
+            .and_then(lopdf::Object::as_name)
+            .ok()
+            .map(|n| String::from_utf8_lossy(n).to_string())
+    };
+    let fallback = if obj.as_stream().is_ok() {
+        "stream"
+    } else {
+        "dictionary"
+    };
+    match (name(b"Type"), name(b"Subtype")) {
+        (Some(t), Some(s)) => format!("{t}/{s}"),
+        (Some(t), None) => t,
+        (None, Some(s)) => format!("{fallback}/{s}"),
+        (None, None) => fallback.to_string(),
+    }
+}
+
+/// Serialize an object in PDF syntax (dictionaries one entry per line).
+fn write_object(out: &mut String, obj: &lopdf::Object) {
+    match obj {
+        lopdf::Object::Null => out.push_str("null"),
+        lopdf::Object::Boolean(b) => {
+            let _ = write!(out, "{b}");
+        }
+        lopdf::Object::Integer(i) => {
+            let _ = write!(out, "{i}");
+        }
+        lopdf::Object::Real(r) => {
+            let _ = write!(out, "{r}");
+        }
+        lopdf::Object::Name(n) => {
+            out.push('/');
+            for &b in n {
+                if b.is_ascii_graphic() && !b"()<>[]{}/%#".contains(&b) {
+                    out.push(char::from(b));
+                } else {
+                    let _ = write!(out, "#{b:02X}");
+                }
+            }
+        }
+        lopdf::Object::String(s, _) => write_string(out, s),
+        lopdf::Object::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(' ');
+                }
+                write_object(out, item);
+            }
+            out.push(']');
+        }
+        lopdf::Object::Dictionary(d) => write_dict(out, d),
+        lopdf::Object::Stream(s) => write_dict(out, &s.dict),
+        lopdf::Object::Reference((n, g)) => {
+            let _ = write!(out, "{n} {g} R");
+        }
+    }
+}
+
+fn write_dict(out: &mut String, dict: &lopdf::Dictionary) {
+    // Indent nested dictionaries by re-indenting their lines.
+    out.push_str("<<\n");
+    for (key, value) in dict {
+        let mut nested = String::new();
+        write_object(&mut nested, value);
+        let _ = writeln!(
+            out,
+            "  /{} {}",
+            String::from_utf8_lossy(key),
+            nested.replace('\n', "\n  ")
+        );
+    }
+    out.push_str(">>");
+}
+
+/// Printable strings as literals, anything else as hex.
+fn write_string(out: &mut String, bytes: &[u8]) {
+    let printable = bytes.iter().all(|b| b.is_ascii_graphic() || *b == b' ');
+    if printable {
+        out.push('(');
+        for &b in bytes {
+            if matches!(b, b'(' | b')' | b'\\') {
+                out.push('\\');
+            }
+            out.push(char::from(b));
+        }
+        out.push(')');
+    } else {
+        out.push('<');
+        for b in bytes {
+            let _ = write!(out, "{b:02X}");
+        }
+        out.push('>');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn objects_render_in_pdf_syntax() {
+        let dict = lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"XObject".to_vec())),
+            ("Subtype", lopdf::Object::Name(b"Image".to_vec())),
+            ("Title", lopdf::Object::string_literal("a (b)")),
+            (
+                "Kids",
+                lopdf::Object::Array(vec![lopdf::Object::Reference((3, 0)), 1.into()]),
+            ),
+        ]);
+        let obj = lopdf::Object::Dictionary(dict);
+        assert_eq!(object_kind(&obj), "XObject/Image");
+
+        let mut text = String::new();
+        write_object(&mut text, &obj);
+        assert!(text.starts_with("<<\n"));
+        assert!(text.contains("  /Title (a \\(b\\))\n"));
+        assert!(text.contains("  /Kids [3 0 R 1]\n"));
+        assert!(text.ends_with(">>"));
+
+        let mut hex = String::new();
+        write_string(&mut hex, &[0xFE, 0xFF]);
+        assert_eq!(hex, "<FEFF>");
+    }
+
+    #[test]
+    fn content_operators_are_indented_by_nesting() {
+        let content =
+            lopdf::content::Content::decode(b"q 1 0 0 1 0 0 cm BT /F1 12 Tf (Hi) Tj ET Q")
+                .map(|c| c.operations)
+                .unwrap_or_default();
+        assert_eq!(
+            format_operations(&content),
+            "q\n  1 0 0 1 0 0 cm\n  BT\n    /F1 12 Tf\n    (Hi) Tj\n  ET\nQ\n"
+        );
+    }
+}
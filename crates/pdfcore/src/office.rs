@@ -0,0 +1,112 @@
+//! Converting office documents (Word, Excel, `PowerPoint`, `OpenDocument`, RTF,
+//! ...) to PDF with `LibreOffice`.
+//!
+//! `soffice --convert-to pdf` cannot be told the name of its output: it
+//! writes `<input stem>.pdf` into `--outdir`. Each conversion therefore
+//! writes into its own scratch directory, and the file is moved to the
+//! requested output afterwards. `LibreOffice` also hands work over to a
+//! running instance that uses the same user profile, and then exits at
+//! once without converting anything (the desktop application, or another
+//! conversion running in parallel). Each conversion gets a private profile
+//! so that conversions can run side by side.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    command_to_string, find_tool, html::file_url, plan, run_tool_writing, tempspace::TempSpace,
+    validate_input_file, PdfError, Plan, Result, Tool,
+};
+
+/// Convert the office document `input` to the PDF `output` with
+/// `LibreOffice`. Any format `LibreOffice` opens can be converted; it tells the
+/// format from the file's content.
+pub fn office_to_pdf(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<()> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_input_file(input)?;
+    let scratch = TempSpace::new("convert")?;
+    let out_dir = scratch.file("out");
+    fs::create_dir(&out_dir)?;
+    let cmd = soffice_command(input, &out_dir, &file_url(&scratch.file("profile")))?;
+    let command = command_to_string(&cmd);
+    run_tool_writing(Tool::Soffice, cmd, &out_dir)?;
+    let converted = converted_path(input, &out_dir);
+    // LibreOffice exits with success when it could not open the input, too.
+    if !converted.exists() {
+        return Err(PdfError::ToolFailed {
+            tool: Tool::Soffice.name(),
+            command,
+            status: 0,
+            stdout: String::new(),
+            stderr: format!("{} was not converted", input.display()),
+        });
+    }
+    // The scratch directory may be on another file system.
+    if fs::rename(&converted, output).is_err() {
+        fs::copy(&converted, output)?;
+    }
+    Ok(())
+}
+
+/// What [`office_to_pdf`] would run.
+pub fn office_to_pdf_plan(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<Plan> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_input_file(input)?;
+    let out_dir = plan::temp_path("out");
+    let profile = format!("file://{}", plan::temp_path("profile").display());
+    Ok(Plan::new()
+        .run(Tool::Soffice, &soffice_command(input, &out_dir, &profile)?)
+        .native(format!(
+            "move {} to {}",
+            converted_path(input, &out_dir).display(),
+            output.display()
+        ))
+        .write(output))
+}
+
+fn soffice_command(input: &Path, out_dir: &Path, profile_url: &str) -> Result<Command> {
+    let mut cmd = Command::new(find_tool(Tool::Soffice)?);
+    cmd.arg("--headless")
+        .arg("--norestore")
+        .arg("--nolockcheck")
+        .arg(format!("-env:UserInstallation={profile_url}"))
+        .arg("--convert-to")
+        .arg("pdf")
+        .arg("--outdir")
+        .arg(out_dir.as_os_str())
+        .arg(input.as_os_str());
+    Ok(cmd)
+}
+
+/// Where `LibreOffice` writes the PDF of `input`: its name with the last
+/// extension replaced by `.pdf`, in `out_dir`.
+fn converted_path(input: &Path, out_dir: &Path) -> PathBuf {
+    let mut name = input
+        .file_stem()
+        .unwrap_or(input.as_os_str())
+        .to_os_string();
+    name.push(".pdf");
+    out_dir.join(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converted_files_are_named_after_the_input() {
+        let out = Path::new("/tmp/out");
+        assert_eq!(
+            converted_path(Path::new("docs/Q3 report.v2.docx"), out),
+            out.join("Q3 report.v2.pdf")
+        );
+        assert_eq!(
+            converted_path(Path::new("README"), out),
+            out.join("README.pdf")
+        );
+        assert_eq!(converted_path(Path::new("a.pdf"), out), out.join("a.pdf"));
+    }
+}
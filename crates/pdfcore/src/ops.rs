@@ -0,0 +1,459 @@
+//! Builders for the multi-engine operations.
+//!
+//! Each builder takes the required input up front, optional settings through
+//! chainable methods, and carries out the operation with `run` (or describes
+//! it with `plan`):
+//!
+//! ```no_run
+//! # fn main() -> pdfcore::Result<()> {
+//! use pdfcore::{PageSelection, Rotate};
+//!
+//! Rotate::new("in.pdf")
+//!     .degrees(90)
+//!     .pages(PageSelection::Range { start: 1, end: 3 })
+//!     .output("out.pdf")
+//!     .run()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! New options are added as methods, so existing callers keep compiling.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    batch::{for_each_input, InputResult},
+    dedupe, engine, extract_text_command, gs_pdfwrite, gs_pdfwrite_command, load_document,
+    load_structure, merge_command, merge_inputs, mutool_merge_command, mutool_text_command, native,
+    rotate_command, run_tool_capture, run_tool_writing, save_document, select_engine,
+    validate_input_file, validate_rotation, CompressPreset, CompressReport, Engine, LineEnding,
+    Operation, PageSelection, PdfError, Plan, Result, TextOutput, Tool,
+};
+
+/// The output path, which every writing operation needs.
+fn required_output<'a>(output: Option<&'a Path>, op: &str) -> Result<&'a Path> {
+    output.ok_or_else(|| PdfError::InvalidArgument(format!("{op} requires an output path")))
+}
+
+/// Concatenate PDFs (with `qpdf`, `mutool`, or natively; see
+/// [`select_engine`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct Merge {
+    inputs: Vec<PathBuf>,
+    output: Option<PathBuf>,
+    normalize: bool,
+    engine: Option<Engine>,
+}
+
+impl Merge {
+    /// Merge `inputs`, in order.
+    pub fn new(inputs: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        Self {
+            inputs: inputs.into_iter().map(Into::into).collect(),
+            output: None,
+            normalize: false,
+            engine: None,
+        }
+    }
+
+    /// Append another input.
+    pub fn input(mut self, input: impl Into<PathBuf>) -> Self {
+        self.inputs.push(input.into());
+        self
+    }
+
+    /// Where to write the merged PDF (required).
+    pub fn output(mut self, output: impl Into<PathBuf>) -> Self {
+        self.output = Some(output.into());
+        self
+    }
+
+    /// Rewrite the merged PDF natively (default: off): with the highest PDF
+    /// version of the inputs, a rebuilt cross-reference table, and fonts
+    /// and images that several inputs embed identically stored once.
+    pub fn normalize(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    /// Merge with `engine` rather than the one [`select_engine`] picks, for
+    /// this merge only.
+    pub fn engine(mut self, engine: Engine) -> Self {
+        self.engine = Some(engine);
+        self
+    }
+
+    fn select_engine(&self) -> Result<Engine> {
+        match self.engine {
+            Some(engine) => engine.check_available().map(|()| engine),
+            None => select_engine(Operation::Merge),
+        }
+    }
+
+    /// Write the merged PDF.
+    pub fn run(&self) -> Result<()> {
+        let inputs = merge_inputs(&self.inputs)?;
+        let output = required_output(self.output.as_deref(), "merge")?;
+        match self.select_engine()? {
+            Engine::Qpdf => run_tool_writing(Tool::Qpdf, merge_command(&inputs, output)?, output),
+            Engine::Mutool => {
+                run_tool_writing(Tool::Mutool, mutool_merge_command(&inputs, output)?, output)
+            }
+            Engine::Native => native::merge(&inputs, output),
+            other => Err(engine::unsupported(Operation::Merge, other)),
+        }?;
+        if self.normalize {
+            normalize_merged(&inputs, output)?;
+        }
+        Ok(())
+    }
+
+    /// Merge the inputs that can be read, skipping the others, and return a
+    /// result per input. Nothing is written if no input can be read; an
+    /// error is returned only if the merge itself fails.
+    pub fn run_each(&self) -> Result<Vec<InputResult<()>>> {
+        required_output(self.output.as_deref(), "merge")?;
+        if self.inputs.is_empty() {
+            merge_inputs(&self.inputs)?;
+        }
+        let results = for_each_input(&self.inputs, true, |input| {
+            validate_input_file(input)?;
+            load_document(input).map(drop)
+        });
+        let readable: Vec<PathBuf> = results
+            .iter()
+            .filter(|r| r.result.is_ok())
+            .map(|r| r.input.clone())
+            .collect();
+        if !readable.is_empty() {
+            Self {
+                inputs: readable,
+                ..self.clone()
+            }
+            .run()?;
+        }
+        Ok(results)
+    }
+
+    /// What [`Merge::run`] would do.
+    pub fn plan(&self) -> Result<Plan> {
+        let inputs = merge_inputs(&self.inputs)?;
+        let output = required_output(self.output.as_deref(), "merge")?;
+        let plan = match self.select_engine()? {
+            Engine::Qpdf => Plan::new().run(Tool::Qpdf, &merge_command(&inputs, output)?),
+            Engine::Mutool => {
+                Plan::new().run(Tool::Mutool, &mutool_merge_command(&inputs, output)?)
+            }
+            Engine::Native => {
+                Plan::new().native(format!("merge the pages of {} file(s)", inputs.len()))
+            }
+            other => return Err(engine::unsupported(Operation::Merge, other)),
+        };
+        let plan = if self.normalize {
+            plan.native("set the highest PDF version of the inputs")
+                .native("store identical fonts and images once")
+                .native("rewrite the cross-reference table")
+        } else {
+            plan
+        };
+        Ok(plan.write(output))
+    }
+}
+
+/// Rewrite the merged `output` of `inputs` for [`Merge::normalize`].
+fn normalize_merged(inputs: &[&Path], output: &Path) -> Result<()> {
+    let mut doc = load_document(output)?;
+    for input in inputs {
+        let version = load_structure(input)?.version;
+        if version > doc.version {
+            doc.version = version;
+        }
+    }
+    let removed = dedupe::dedupe_objects(&mut doc);
+    tracing::debug!(removed, version = %doc.version, "normalized merged pdf");
+    save_document(&mut doc, output)
+}
+
+/// Rotate pages (with `qpdf`, or natively; see [`select_engine`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct Rotate {
+    input: PathBuf,
+    output: Option<PathBuf>,
+    degrees: u16,
+    pages: PageSelection,
+}
+
+impl Rotate {
+    /// Rotate pages of `input`; by default no rotation on all pages.
+    pub fn new(input: impl Into<PathBuf>) -> Self {
+        Self {
+            input: input.into(),
+            output: None,
+            degrees: 0,
+            pages: PageSelection::All,
+        }
+    }
+
+    /// Clockwise rotation to add: 0, 90, 180, or 270.
+    pub fn degrees(mut self, degrees: u16) -> Self {
+        self.degrees = degrees;
+        self
+    }
+
+    /// Pages to rotate (default: all).
+    pub fn pages(mut self, pages: PageSelection) -> Self {
+        self.pages = pages;
+        self
+    }
+
+    /// Where to write the rotated PDF (required).
+    pub fn output(mut self, output: impl Into<PathBuf>) -> Self {
+        self.output = Some(output.into());
+        self
+    }
+
+    /// Write the rotated PDF.
+    pub fn run(&self) -> Result<()> {
+        let (input, output, degrees, pages) = self.checked()?;
+        match select_engine(Operation::Rotate)? {
+            Engine::Qpdf => run_tool_writing(
+                Tool::Qpdf,
+                rotate_command(input, output, degrees, pages)?,
+                output,
+            ),
+            Engine::Native => native::rotate(input, output, degrees, pages),
+            other => Err(engine::unsupported(Operation::Rotate, other)),
+        }
+    }
+
+    /// What [`Rotate::run`] would do.
+    pub fn plan(&self) -> Result<Plan> {
+        let (input, output, degrees, pages) = self.checked()?;
+        let plan = match select_engine(Operation::Rotate)? {
+            Engine::Qpdf => {
+                Plan::new().run(Tool::Qpdf, &rotate_command(input, output, degrees, pages)?)
+            }
+            Engine::Native => {
+                Plan::new().native(format!("rotate pages {pages} by {degrees} degrees"))
+            }
+            other => return Err(engine::unsupported(Operation::Rotate, other)),
+        };
+        Ok(plan.write(output))
+    }
+
+    fn checked(&self) -> Result<(&Path, &Path, u16, &PageSelection)> {
+        validate_rotation(&self.input, self.degrees)?;
+        let output = required_output(self.output.as_deref(), "rotate")?;
+        Ok((&self.input, output, self.degrees, &self.pages))
+    }
+}
+
+/// Compress a PDF with Ghostscript.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct Compress {
+    input: PathBuf,
+    output: Option<PathBuf>,
+    preset: CompressPreset,
+}
+
+impl Compress {
+    /// Compress `input` with [`CompressPreset::Default`] unless another
+    /// preset is set.
+    pub fn new(input: impl Into<PathBuf>) -> Self {
+        Self {
+            input: input.into(),
+            output: None,
+            preset: CompressPreset::Default,
+        }
+    }
+
+    /// Ghostscript `-dPDFSETTINGS` preset.
+    pub fn preset(mut self, preset: CompressPreset) -> Self {
+        self.preset = preset;
+        self
+    }
+
+    /// Where to write the compressed PDF (required).
+    pub fn output(mut self, output: impl Into<PathBuf>) -> Self {
+        self.output = Some(output.into());
+        self
+    }
+
+    /// Compress, returning a comparison of the input and output sizes.
+    pub fn run(&self) -> Result<CompressReport> {
+        validate_input_file(&self.input)?;
+        let output = required_output(self.output.as_deref(), "compress")?;
+        gs_pdfwrite(&self.input, output, Some(self.preset), &[])?;
+
+        let input_bytes = fs::metadata(&self.input)?.len();
+        let output_bytes = fs::metadata(output)?.len();
+        Ok(CompressReport::from_sizes(input_bytes, output_bytes))
+    }
+
+    /// What [`Compress::run`] would do.
+    pub fn plan(&self) -> Result<Plan> {
+        validate_input_file(&self.input)?;
+        let output = required_output(self.output.as_deref(), "compress")?;
+        let cmd = gs_pdfwrite_command(&self.input, output, Some(self.preset), &[])?;
+        Ok(Plan::new().run(Tool::Ghostscript, &cmd).write(output))
+    }
+}
+
+/// Extract text (with Poppler's `pdftotext`, `mutool`, `PDFium`, or
+/// natively; see [`select_engine`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct ExtractText {
+    input: PathBuf,
+    output: Option<PathBuf>,
+    text_output: TextOutput,
+}
+
+impl ExtractText {
+    /// Extract the text of `input`.
+    pub fn new(input: impl Into<PathBuf>) -> Self {
+        Self {
+            input: input.into(),
+            output: None,
+            text_output: TextOutput::default(),
+        }
+    }
+
+    /// Write the text to a file instead of returning it.
+    pub fn output(mut self, output: impl Into<PathBuf>) -> Self {
+        self.output = Some(output.into());
+        self
+    }
+
+    /// Encoding, byte order mark, and line endings of the output file (only
+    /// the line endings apply to returned text).
+    pub fn text_output(mut self, text_output: TextOutput) -> Self {
+        self.text_output = text_output;
+        self
+    }
+
+    /// The extracted text, or an empty string if it was written to the
+    /// output file.
+    pub fn run(&self) -> Result<String> {
+        let (input, output) = (self.input.as_path(), self.output.as_deref());
+        validate_input_file(input)?;
+        self.text_output.validate()?;
+        if self.text_output != TextOutput::default() {
+            // Extract as UTF-8, then convert.
+            let text = Self {
+                output: None,
+                text_output: TextOutput::default(),
+                ..self.clone()
+            }
+            .run()?;
+            let Some(out) = output else {
+                return Ok(self.text_output.convert_eol(&text).into_owned());
+            };
+            fs::write(out, self.text_output.encode(&text)?)?;
+            return Ok(String::new());
+        }
+        let captured = |tool: Tool, cmd| {
+            if let Some(output) = output {
+                run_tool_writing(tool, cmd, output).map(|()| String::new())
+            } else {
+                run_tool_capture(tool, cmd)
+            }
+        };
+        let text = match select_engine(Operation::ExtractText)? {
+            Engine::Pdftotext => {
+                return captured(Tool::Pdftotext, extract_text_command(input, output)?)
+            }
+            Engine::Mutool => return captured(Tool::Mutool, mutool_text_command(input, output)?),
+            #[cfg(feature = "pdfium")]
+            Engine::Pdfium => crate::pdfium::extract_text(input)?,
+            Engine::Native => native::extract_text(input)?,
+            other => return Err(engine::unsupported(Operation::ExtractText, other)),
+        };
+        match output {
+            Some(out) => {
+                fs::write(out, text)?;
+                Ok(String::new())
+            }
+            None => Ok(text),
+        }
+    }
+
+    /// What [`ExtractText::run`] would do.
+    pub fn plan(&self) -> Result<Plan> {
+        let (input, output) = (self.input.as_path(), self.output.as_deref());
+        validate_input_file(input)?;
+        self.text_output.validate()?;
+        let converted = self.text_output != TextOutput::default();
+        let engine_output = if converted { None } else { output };
+        let plan = match select_engine(Operation::ExtractText)? {
+            Engine::Pdftotext => Plan::new().run(
+                Tool::Pdftotext,
+                &extract_text_command(input, engine_output)?,
+            ),
+            Engine::Mutool => {
+                Plan::new().run(Tool::Mutool, &mutool_text_command(input, engine_output)?)
+            }
+            Engine::Pdfium => Plan::new().native("extract the text of every page with pdfium"),
+            Engine::Native => Plan::new().native("extract the text of every page"),
+            other => return Err(engine::unsupported(Operation::ExtractText, other)),
+        };
+        let plan = if converted {
+            let TextOutput { encoding, bom, eol } = self.text_output;
+            let mut what = format!("encode the text as {encoding}");
+            if bom {
+                what.push_str(" with a byte order mark");
+            }
+            match eol {
+                Some(LineEnding::Lf) => what.push_str(", with LF line endings"),
+                Some(LineEnding::Crlf) => what.push_str(", with CRLF line endings"),
+                None => {}
+            }
+            plan.native(what)
+        } else {
+            plan
+        };
+        Ok(match output {
+            Some(out) => plan.write(out),
+            None => plan,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builders_validate_before_selecting_an_engine() {
+        let input = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+        let missing_output = Rotate::new(&input).degrees(90).plan();
+        assert!(
+            matches!(missing_output, Err(PdfError::InvalidArgument(ref msg)) if msg.contains("output")),
+            "{missing_output:?}"
+        );
+        assert!(matches!(
+            Rotate::new(&input).degrees(45).output("out.pdf").run(),
+            Err(PdfError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            Merge::new(Vec::<PathBuf>::new()).output("out.pdf").plan(),
+            Err(PdfError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            Compress::new("missing.pdf").output("out.pdf").plan(),
+            Err(PdfError::InputNotFound(_))
+        ));
+        let skipped = Merge::new(["missing.pdf"]).output("out.pdf").run_each();
+        assert!(
+            matches!(skipped.as_deref(), Ok([only]) if only.result.is_err()),
+            "{skipped:?}"
+        );
+        assert!(!Path::new("out.pdf").exists());
+    }
+}
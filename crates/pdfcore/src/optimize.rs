@@ -0,0 +1,313 @@
+//! Native size optimizations that rewrite a document's objects without
+//! touching what its pages look like.
+
+use std::{collections::BTreeSet, fs, path::Path, process::Command};
+
+use lopdf::{Document, Object, ObjectId};
+
+use crate::{
+    dedupe, engine, find_tool, load_document, run_tool_writing, save_document, select_engine, size,
+    tempspace::TempSpace, validate_input_file, CompressReport, Engine, Operation, PdfError, Plan,
+    Result, Tool,
+};
+
+/// Options for [`optimize`]; at least one pass must be chosen.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct OptimizeOptions {
+    /// Store fonts and images with identical contents once.
+    pub dedupe: bool,
+    /// Drop objects that cannot be reached from the trailer (with `qpdf`,
+    /// or natively; see [`select_engine`] and [`Operation::Optimize`]).
+    pub gc: bool,
+}
+
+/// What [`optimize`] did.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptimizeReport {
+    /// Duplicate font and image objects removed.
+    pub duplicates_removed: usize,
+    /// Unreferenced objects removed.
+    pub unreferenced_removed: usize,
+    /// Approximate serialized size of the unreferenced objects, in bytes.
+    pub unreferenced_bytes: u64,
+    /// Input and output file sizes.
+    pub sizes: CompressReport,
+}
+
+/// Rewrite `input` with the passes chosen in `options`: deduplication
+/// first, so that the copies it leaves unreferenced are not counted as
+/// garbage.
+pub fn optimize(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &OptimizeOptions,
+) -> Result<OptimizeReport> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_options(options)?;
+    let gc_engine = options
+        .gc
+        .then(|| select_engine(Operation::Optimize))
+        .transpose()?;
+    let mut doc = load_document(input)?;
+    let duplicates_removed = if options.dedupe {
+        dedupe::dedupe_objects(&mut doc)
+    } else {
+        0
+    };
+    let mut report = OptimizeReport {
+        duplicates_removed,
+        unreferenced_removed: 0,
+        unreferenced_bytes: 0,
+        sizes: CompressReport::from_sizes(0, 0),
+    };
+    if options.gc {
+        let unreachable = unreachable_objects(&doc);
+        report.unreferenced_removed = unreachable.len();
+        report.unreferenced_bytes = unreachable
+            .iter()
+            .filter_map(|id| doc.objects.get(id))
+            .map(|obj| size::object_len(obj) + size::OBJ_WRAPPER_LEN)
+            .sum();
+        tracing::debug!(
+            objects = report.unreferenced_removed,
+            bytes = report.unreferenced_bytes,
+            "found unreferenced objects"
+        );
+        if gc_engine == Some(Engine::Native) {
+            for id in &unreachable {
+                doc.objects.remove(id);
+            }
+        }
+    }
+    match gc_engine {
+        None | Some(Engine::Native) => save_document(&mut doc, output)?,
+        Some(Engine::Qpdf) => {
+            // qpdf writes only the objects it reaches from the trailer.
+            let scratch = TempSpace::new("optimize")?;
+            let passed = scratch.file("passes.pdf");
+            save_document(&mut doc, &passed)?;
+            run_tool_writing(Tool::Qpdf, qpdf_rewrite_command(&passed, output)?, output)?;
+        }
+        Some(other) => return Err(engine::unsupported(Operation::Optimize, other)),
+    }
+    report.sizes =
+        CompressReport::from_sizes(fs::metadata(input)?.len(), fs::metadata(output)?.len());
+    Ok(report)
+}
+
+/// What [`optimize`] would do.
+pub fn optimize_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &OptimizeOptions,
+) -> Result<Plan> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_input_file(input)?;
+    validate_options(options)?;
+    let mut plan = Plan::new();
+    if options.dedupe {
+        plan = plan.native("store identical fonts and images once");
+    }
+    if options.gc {
+        plan = plan.native("find the objects unreachable from the trailer");
+        plan = match select_engine(Operation::Optimize)? {
+            Engine::Native => plan.native("drop the unreachable objects"),
+            Engine::Qpdf => {
+                let passed = Path::new("<temp>/passes.pdf");
+                plan.native(format!("write {}", passed.display()))
+                    .run(Tool::Qpdf, &qpdf_rewrite_command(passed, output)?)
+            }
+            other => return Err(engine::unsupported(Operation::Optimize, other)),
+        };
+    }
+    Ok(plan.write(output))
+}
+
+/// Store fonts and images that `input` holds more than once with identical
+/// contents once, pointing every reference at the copy kept (pure Rust).
+/// Shorthand for [`optimize`] with only [`OptimizeOptions::dedupe`].
+///
+/// Font programs, `ToUnicode` maps, and width arrays are compared by their
+/// decoded data, so copies compressed differently still match; images are
+/// compared as stored.
+pub fn dedupe_resources(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+) -> Result<OptimizeReport> {
+    let options = OptimizeOptions {
+        dedupe: true,
+        ..OptimizeOptions::default()
+    };
+    optimize(input, output, &options)
+}
+
+fn validate_options(options: &OptimizeOptions) -> Result<()> {
+    if !options.dedupe && !options.gc {
+        return Err(PdfError::InvalidArgument(
+            "no optimization chosen".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn qpdf_rewrite_command(input: &Path, output: &Path) -> Result<Command> {
+    let mut cmd = Command::new(find_tool(Tool::Qpdf)?);
+    cmd.arg(input.as_os_str()).arg(output.as_os_str());
+    Ok(cmd)
+}
+
+/// The objects of `doc` that no chain of references from the trailer
+/// reaches. Object and cross-reference streams are left out: they only
+/// package other objects and are never written back as they were read.
+fn unreachable_objects(doc: &Document) -> BTreeSet<ObjectId> {
+    let mut reached = BTreeSet::new();
+    let mut pending: Vec<ObjectId> = Vec::new();
+    for (_, value) in &doc.trailer {
+        collect_references(value, &mut pending);
+    }
+    while let Some(id) = pending.pop() {
+        if !reached.insert(id) {
+            continue;
+        }
+        if let Some(obj) = doc.objects.get(&id) {
+            collect_references(obj, &mut pending);
+        }
+    }
+    doc.objects
+        .iter()
+        .filter(|(id, obj)| !reached.contains(*id) && !size::is_structural(obj))
+        .map(|(&id, _)| id)
+        .collect()
+}
+
+/// Push the objects `obj` refers to directly onto `out`.
+fn collect_references(obj: &Object, out: &mut Vec<ObjectId>) {
+    match obj {
+        Object::Reference(id) => out.push(*id),
+        Object::Array(items) => {
+            for item in items {
+                collect_references(item, out);
+            }
+        }
+        Object::Dictionary(dict) => {
+            for (_, value) in dict {
+                collect_references(value, out);
+            }
+        }
+        Object::Stream(stream) => {
+            for (_, value) in &stream.dict {
+                collect_references(value, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::{Dictionary, Stream};
+
+    use super::*;
+
+    /// Two pages, each drawing its own copy of the same image.
+    fn duplicate_images_pdf() -> Document {
+        let mut doc = Document::with_version("1.4");
+        let pages_id = doc.new_object_id();
+        let mut kids = Vec::new();
+        for _ in 0..2 {
+            let image = doc.add_object(Stream::new(
+                Dictionary::from_iter([
+                    ("Type", Object::Name(b"XObject".to_vec())),
+                    ("Subtype", Object::Name(b"Image".to_vec())),
+                    ("Width", Object::Integer(64)),
+                    ("Height", Object::Integer(64)),
+                    ("ColorSpace", Object::Name(b"DeviceGray".to_vec())),
+                    ("BitsPerComponent", Object::Integer(8)),
+                ]),
+                (0..=255u8).cycle().take(64 * 64).collect(),
+            ));
+            let content = doc.add_object(Stream::new(
+                Dictionary::new(),
+                b"q 64 0 0 64 0 0 cm /Im0 Do Q".to_vec(),
+            ));
+            let resources = Dictionary::from_iter([(
+                "XObject",
+                Object::Dictionary(Dictionary::from_iter([("Im0", Object::Reference(image))])),
+            )]);
+            kids.push(Object::Reference(doc.add_object(Dictionary::from_iter([
+                ("Type", Object::Name(b"Page".to_vec())),
+                ("Parent", Object::Reference(pages_id)),
+                (
+                    "MediaBox",
+                    Object::Array(vec![0.into(), 0.into(), 200.into(), 200.into()]),
+                ),
+                ("Resources", Object::Dictionary(resources)),
+                ("Contents", Object::Reference(content)),
+            ]))));
+        }
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(Dictionary::from_iter([
+                ("Type", Object::Name(b"Pages".to_vec())),
+                ("Count", Object::Integer(2)),
+                ("Kids", Object::Array(kids)),
+            ])),
+        );
+        let catalog = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Catalog".to_vec())),
+            ("Pages", Object::Reference(pages_id)),
+        ]));
+        doc.trailer.set("Root", Object::Reference(catalog));
+        doc
+    }
+
+    #[test]
+    fn duplicate_images_are_stored_once() -> Result<()> {
+        let scratch = TempSpace::new("optimize-test")?;
+        let input = scratch.file("in.pdf");
+        duplicate_images_pdf().save(&input)?;
+        let output = scratch.file("out.pdf");
+
+        let report = dedupe_resources(&input, &output)?;
+        assert_eq!(report.duplicates_removed, 1);
+        assert_eq!(report.unreferenced_removed, 0);
+        assert_eq!(report.sizes.input_bytes, fs::metadata(&input)?.len());
+        assert_eq!(report.sizes.output_bytes, fs::metadata(&output)?.len());
+        assert!(
+            report.sizes.output_bytes + 64 * 64 <= report.sizes.input_bytes,
+            "{:?}",
+            report.sizes
+        );
+        assert!(report.sizes.ratio < 1.0);
+        assert_eq!(load_document(&output)?.get_pages().len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn objects_out_of_reach_of_the_trailer_are_garbage() {
+        let mut doc = Document::with_version("1.4");
+        let info = doc.add_object(Object::string_literal("kept"));
+        let orphan_child = doc.add_object(Object::Integer(1));
+        let orphan = doc.add_object(Dictionary::from_iter([(
+            "Child",
+            Object::Reference(orphan_child),
+        )]));
+        // A cycle that nothing else refers to is garbage too.
+        let cycle = doc.new_object_id();
+        doc.objects.insert(
+            cycle,
+            Object::Dictionary(Dictionary::from_iter([("Self", Object::Reference(cycle))])),
+        );
+        let catalog = doc.add_object(Dictionary::from_iter([(
+            "Type",
+            Object::Name(b"Catalog".to_vec()),
+        )]));
+        doc.trailer.set("Root", Object::Reference(catalog));
+        doc.trailer.set("Info", Object::Reference(info));
+
+        assert_eq!(
+            unreachable_objects(&doc),
+            BTreeSet::from([orphan_child, orphan, cycle])
+        );
+    }
+}
@@ -0,0 +1,243 @@
+//! Page orientation from the direction of the text, for turning sideways
+//! and upside-down pages upright.
+//!
+//! Each page's glyphs vote with the direction of their baseline, rounded to
+//! a multiple of 90 degrees. Pages without text (such as scans that have
+//! not been through OCR) cannot be detected and are left as they are.
+
+use std::path::Path;
+
+use lopdf::Document;
+
+use crate::{
+    load_document,
+    native::inherited,
+    plan::Plan,
+    save_document,
+    text::{self, Glyph},
+    PageSelection, Result,
+};
+
+/// Pages with fewer glyphs than this are not judged.
+const MIN_GLYPHS: usize = 3;
+
+/// Orientation of one page, as found by [`detect_orientation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageOrientation {
+    /// 1-based page number.
+    pub page: u32,
+    /// The page's `/Rotate` value: 0, 90, 180, or 270.
+    pub rotation: u16,
+    /// The `/Rotate` value that shows the text upright, or `None` if the page
+    /// has too little text to tell.
+    pub upright: Option<u16>,
+}
+
+impl PageOrientation {
+    /// Whether the page is shown sideways or upside down.
+    #[must_use]
+    pub fn needs_rotation(&self) -> bool {
+        self.upright.is_some_and(|upright| upright != self.rotation)
+    }
+}
+
+/// Detect the orientation of every page from its text (pure Rust).
+pub fn detect_orientation(input: impl AsRef<Path>) -> Result<Vec<PageOrientation>> {
+    let doc = load_document(input.as_ref())?;
+    Ok(orientations(&doc, &PageSelection::All))
+}
+
+/// Rotate the selected pages so their text is upright, and return their
+/// orientation as it was before.
+pub fn auto_rotate(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    pages: &PageSelection,
+) -> Result<Vec<PageOrientation>> {
+    let mut doc = load_document(input.as_ref())?;
+    let found = orientations(&doc, pages);
+    let page_ids = doc.get_pages();
+    for orientation in found.iter().filter(|o| o.needs_rotation()) {
+        let (Some(upright), Some(&id)) = (orientation.upright, page_ids.get(&orientation.page))
+        else {
+            continue;
+        };
+        if let Ok(page) = doc.get_dictionary_mut(id) {
+            page.set("Rotate", i64::from(upright));
+        }
+    }
+    save_document(&mut doc, output.as_ref())?;
+    Ok(found)
+}
+
+/// What [`auto_rotate`] would do.
+pub fn auto_rotate_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    pages: &PageSelection,
+) -> Result<Plan> {
+    let doc = load_document(input.as_ref())?;
+    let plan = orientations(&doc, pages)
+        .into_iter()
+        .filter(PageOrientation::needs_rotation)
+        .fold(Plan::new(), |plan, o| {
+            plan.native(format!(
+                "rotate page {} from {} to {} degrees",
+                o.page,
+                o.rotation,
+                o.upright.unwrap_or_default()
+            ))
+        });
+    Ok(plan.write(output.as_ref()))
+}
+
+fn orientations(doc: &Document, pages: &PageSelection) -> Vec<PageOrientation> {
+    let mut out = Vec::new();
+    for (page, page_id) in doc.get_pages() {
+        if !pages.contains(page) {
+            continue;
+        }
+        let rotation = doc
+            .get_dictionary(page_id)
+            .ok()
+            .and_then(|dict| inherited(doc, dict, b"Rotate"))
+            .and_then(|r| r.as_i64().ok())
+            .and_then(|r| u16::try_from(r.rem_euclid(360)).ok())
+            .unwrap_or(0);
+        let upright = doc
+            .get_and_decode_page_content(page_id)
+            .ok()
+            .and_then(|content| {
+                text_direction(&text::layout(doc, page_id, &content.operations).glyphs)
+            });
+        out.push(PageOrientation {
+            page,
+            rotation,
+            upright,
+        });
+    }
+    out
+}
+
+/// The prevailing baseline direction of `glyphs`, in degrees
+/// counterclockwise (0, 90, 180, or 270). This is also the `/Rotate` value
+/// that shows them upright, since `/Rotate` turns the page clockwise.
+fn text_direction(glyphs: &[Glyph]) -> Option<u16> {
+    let visible: Vec<&Glyph> = glyphs
+        .iter()
+        .filter(|g| !g.text.trim().is_empty())
+        .collect();
+    if visible.len() < MIN_GLYPHS {
+        return None;
+    }
+    let mut votes = [0_usize; 4];
+    for glyph in visible {
+        votes[usize::from(quarter_turns(glyph.angle))] += 1;
+    }
+    let (turns, _) = votes
+        .iter()
+        .enumerate()
+        .max_by_key(|(turns, count)| (**count, std::cmp::Reverse(*turns)))?;
+    u16::try_from(turns * 90).ok()
+}
+
+/// `degrees` rounded to a whole number of quarter turns, 0 to 3.
+fn quarter_turns(degrees: f32) -> u16 {
+    // The value is in 0..4 after rem_euclid, so the cast is exact.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let turns = (degrees / 90.0).round().rem_euclid(4.0) as u16;
+    turns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{Dictionary, Object, Stream};
+
+    /// Pages with a line of text drawn by each text matrix, the second page
+    /// already rotated by 90 degrees.
+    fn document(matrices: &[&str]) -> Document {
+        let mut doc = Document::with_version("1.5");
+        let pages = doc.new_object_id();
+        let font = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type1".to_vec())),
+            ("BaseFont", Object::Name(b"Helvetica".to_vec())),
+        ]));
+        let mut kids = Vec::new();
+        for (index, matrix) in matrices.iter().enumerate() {
+            let content = format!("BT /F1 12 Tf {matrix} Tm (Quarterly report) Tj ET");
+            let content = doc.add_object(Stream::new(Dictionary::new(), content.into_bytes()));
+            let mut page = Dictionary::from_iter([
+                ("Type", Object::Name(b"Page".to_vec())),
+                ("Parent", Object::Reference(pages)),
+                ("Contents", Object::Reference(content)),
+                (
+                    "Resources",
+                    Object::Dictionary(Dictionary::from_iter([(
+                        "Font",
+                        Object::Dictionary(Dictionary::from_iter([(
+                            "F1",
+                            Object::Reference(font),
+                        )])),
+                    )])),
+                ),
+            ]);
+            if index == 1 {
+                page.set("Rotate", 90);
+            }
+            kids.push(Object::Reference(doc.add_object(page)));
+        }
+        let count = i64::try_from(kids.len()).unwrap_or_default();
+        doc.objects.insert(
+            pages,
+            Object::Dictionary(Dictionary::from_iter([
+                ("Type", Object::Name(b"Pages".to_vec())),
+                ("Kids", Object::Array(kids)),
+                ("Count", Object::Integer(count)),
+            ])),
+        );
+        let catalog = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Catalog".to_vec())),
+            ("Pages", Object::Reference(pages)),
+        ]));
+        doc.trailer.set("Root", catalog);
+        doc
+    }
+
+    #[test]
+    fn sideways_and_upside_down_pages_are_turned_upright() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("in.pdf");
+        // Upright; upright but wrongly rotated; reading upwards; upside down.
+        document(&[
+            "1 0 0 1 72 720",
+            "1 0 0 1 72 720",
+            "0 1 -1 0 300 72",
+            "-1 0 0 -1 500 72",
+        ])
+        .save(&input)?;
+
+        let found = detect_orientation(&input)?;
+        let summary: Vec<(u16, Option<u16>)> =
+            found.iter().map(|o| (o.rotation, o.upright)).collect();
+        assert_eq!(
+            summary,
+            [(0, Some(0)), (90, Some(0)), (0, Some(90)), (0, Some(180))]
+        );
+
+        let output = dir.path().join("out.pdf");
+        auto_rotate(&input, &output, &PageSelection::All)?;
+        assert!(detect_orientation(&output)?
+            .iter()
+            .all(|o| !o.needs_rotation()));
+        Ok(())
+    }
+
+    #[test]
+    fn pages_without_text_are_not_judged() {
+        assert_eq!(text_direction(&[]), None);
+        assert_eq!(quarter_turns(-90.0), 3);
+        assert_eq!(quarter_turns(359.0), 0);
+    }
+}
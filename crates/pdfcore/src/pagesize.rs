@@ -0,0 +1,347 @@
+//! Page sizes: grouping the pages of a document by the size they are shown
+//! at, finding the odd ones out, and scaling pages to a paper size.
+
+use std::path::Path;
+
+use lopdf::{Document, Object, ObjectId};
+
+use crate::{
+    geometry::{self, Matrix, PageBox, PaperSize, Rect},
+    load_document, load_for_reading,
+    native::inherited,
+    save_document, stamp, validate_input_file, PdfError, Plan, Result,
+};
+
+/// Difference in points below which two page sizes count as the same.
+const TOLERANCE: f32 = 2.0;
+
+/// Pages of one size and orientation, for [`page_sizes`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageSizeGroup {
+    /// Width as shown (after `/Rotate`), in points.
+    pub width: f32,
+    /// Height as shown, in points.
+    pub height: f32,
+    /// The named paper size, if the size is one.
+    pub paper: Option<PaperSize>,
+    /// 1-based page numbers.
+    pub pages: Vec<u32>,
+}
+
+impl PageSizeGroup {
+    /// Whether the pages are shown wider than they are high.
+    #[must_use]
+    pub fn is_landscape(&self) -> bool {
+        self.width > self.height + TOLERANCE
+    }
+
+    /// A short description, e.g. `a4 portrait` or `500 x 300 pt landscape`.
+    #[must_use]
+    pub fn label(&self) -> String {
+        let orientation = if self.is_landscape() {
+            "landscape"
+        } else {
+            "portrait"
+        };
+        match self.paper {
+            Some(paper) => format!("{paper} {orientation}"),
+            None => format!("{:.0} x {:.0} pt {orientation}", self.width, self.height),
+        }
+    }
+}
+
+/// The page sizes of a document, from [`page_sizes`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageSizeReport {
+    /// Number of pages.
+    pub pages: u32,
+    /// Groups of pages of one size and orientation, the largest first.
+    pub groups: Vec<PageSizeGroup>,
+}
+
+impl PageSizeReport {
+    /// Whether every page has the same size and orientation.
+    #[must_use]
+    pub fn is_uniform(&self) -> bool {
+        self.groups.len() <= 1
+    }
+
+    /// The pages outside the largest group, each with a sentence such as
+    /// `p. 37 is letter portrait in an a4 portrait document`.
+    #[must_use]
+    pub fn outliers(&self) -> Vec<(u32, String)> {
+        let Some((main, others)) = self.groups.split_first() else {
+            return Vec::new();
+        };
+        let mut outliers: Vec<(u32, String)> = others
+            .iter()
+            .flat_map(|group| {
+                group.pages.iter().map(|&page| {
+                    (
+                        page,
+                        format!(
+                            "p. {page} is {} in {} {} document",
+                            group.label(),
+                            article(&main.label()),
+                            main.label()
+                        ),
+                    )
+                })
+            })
+            .collect();
+        outliers.sort_by_key(|(page, _)| *page);
+        outliers
+    }
+}
+
+/// `a` or `an`, whichever goes before `label`.
+fn article(label: &str) -> &'static str {
+    match label.chars().next() {
+        Some('a' | 'e' | 'i' | 'o' | 'u' | '8') => "an",
+        _ => "a",
+    }
+}
+
+/// Group the pages of `input` by the size of their crop box as shown
+/// (pure Rust).
+pub fn page_sizes(input: impl AsRef<Path>) -> Result<PageSizeReport> {
+    let doc = load_for_reading(input.as_ref())?;
+    let mut groups: Vec<PageSizeGroup> = Vec::new();
+    let pages = doc.get_pages();
+    for (&page, &page_id) in &pages {
+        let (width, height) = shown_size(&doc, page_id);
+        if let Some(group) = groups.iter_mut().find(|g| {
+            (g.width - width).abs() <= TOLERANCE && (g.height - height).abs() <= TOLERANCE
+        }) {
+            group.pages.push(page);
+        } else {
+            groups.push(PageSizeGroup {
+                width,
+                height,
+                paper: PaperSize::matching(&Rect::from_size(width, height), TOLERANCE),
+                pages: vec![page],
+            });
+        }
+    }
+    // Stable, so that of equally large groups the first in the document
+    // comes first.
+    groups.sort_by_key(|g| std::cmp::Reverse(g.pages.len()));
+    Ok(PageSizeReport {
+        pages: u32::try_from(pages.len()).unwrap_or(u32::MAX),
+        groups,
+    })
+}
+
+/// Scale the pages of `input` that are not `paper` (in either orientation)
+/// to fit it, centered, keeping the orientation they are shown in (pure
+/// Rust). Returns the pages changed; pages already `paper` are left alone.
+pub fn fit_pages_to_paper(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    paper: PaperSize,
+) -> Result<Vec<u32>> {
+    let mut doc = load_document(input.as_ref())?;
+    let mut changed = Vec::new();
+    for (page, page_id) in doc.get_pages() {
+        let (width, height) = shown_size(&doc, page_id);
+        if PaperSize::matching(&Rect::from_size(width, height), TOLERANCE) == Some(paper) {
+            continue;
+        }
+        fit_page(&mut doc, page_id, paper)?;
+        changed.push(page);
+    }
+    save_document(&mut doc, output.as_ref())?;
+    Ok(changed)
+}
+
+/// What [`fit_pages_to_paper`] would do.
+pub fn fit_pages_to_paper_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    paper: PaperSize,
+) -> Result<Plan> {
+    let input = input.as_ref();
+    validate_input_file(input)?;
+    let report = page_sizes(input)?;
+    let pages: Vec<u32> = report
+        .groups
+        .iter()
+        .filter(|g| g.paper != Some(paper))
+        .flat_map(|g| g.pages.iter().copied())
+        .collect();
+    let plan = if pages.is_empty() {
+        Plan::new().native(format!("leave every page as it is (all are {paper})"))
+    } else {
+        Plan::new().native(format!("scale pages {pages:?} to fit {paper}"))
+    };
+    Ok(plan.write(output.as_ref()))
+}
+
+/// The page's `/Rotate`, in degrees from 0 to 270.
+fn rotation(doc: &Document, page_id: ObjectId) -> i64 {
+    doc.get_dictionary(page_id)
+        .ok()
+        .and_then(|page| inherited(doc, page, b"Rotate"))
+        .and_then(|r| r.as_i64().ok())
+        .unwrap_or(0)
+        .rem_euclid(360)
+}
+
+/// The width and height of the page's crop box as shown.
+fn shown_size(doc: &Document, page_id: ObjectId) -> (f32, f32) {
+    let visible = geometry::effective_page_box(doc, page_id, PageBox::Crop);
+    if rotation(doc, page_id) % 180 == 90 {
+        (visible.height(), visible.width())
+    } else {
+        (visible.width(), visible.height())
+    }
+}
+
+/// Scale and center the visible part of a page on `paper`, in the
+/// orientation it is shown in. The page's boxes are replaced by a media box
+/// of the paper's size, and its annotations are moved with the content.
+fn fit_page(doc: &mut Document, page_id: ObjectId, paper: PaperSize) -> Result<()> {
+    let visible = geometry::effective_page_box(doc, page_id, PageBox::Crop).normalized();
+    let (shown_width, shown_height) = shown_size(doc, page_id);
+    let (mut width, mut height) = if shown_width > shown_height {
+        (paper.height, paper.width)
+    } else {
+        (paper.width, paper.height)
+    };
+    // Boxes are in the page's own space, before `/Rotate` turns it.
+    if rotation(doc, page_id) % 180 == 90 {
+        std::mem::swap(&mut width, &mut height);
+    }
+    if visible.width() <= 0.0 || visible.height() <= 0.0 {
+        return Err(PdfError::InvalidArgument(format!(
+            "page object {page_id:?} has an empty crop box"
+        )));
+    }
+    let scale = (width / visible.width()).min(height / visible.height());
+    let matrix = Matrix([
+        scale,
+        0.0,
+        0.0,
+        scale,
+        (width - visible.width() * scale) / 2.0 - visible.llx * scale,
+        (height - visible.height() * scale) / 2.0 - visible.lly * scale,
+    ]);
+    let m = matrix.0;
+    let before = format!(
+        "q\n{} {} {} {} {} {} cm\n{} {} {} {} re W n\n",
+        m[0],
+        m[1],
+        m[2],
+        m[3],
+        m[4],
+        m[5],
+        visible.llx,
+        visible.lly,
+        visible.width(),
+        visible.height()
+    );
+    stamp::wrap_page_content(doc, page_id, before.into_bytes(), b"\nQ\n".to_vec())?;
+
+    let annots: Vec<ObjectId> = doc
+        .get_dictionary(page_id)
+        .ok()
+        .and_then(|page| page.get(b"Annots").ok())
+        .and_then(|annots| match annots {
+            Object::Reference(id) => doc.get_object(*id).ok(),
+            other => Some(other),
+        })
+        .and_then(|annots| annots.as_array().ok())
+        .map(|annots| {
+            annots
+                .iter()
+                .filter_map(|a| a.as_reference().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    for id in annots {
+        let rect = doc
+            .get_object(id)
+            .ok()
+            .and_then(|annot| annot.as_dict().ok())
+            .and_then(|annot| annot.get(b"Rect").ok())
+            .and_then(|rect| Rect::from_object(doc, rect));
+        if let (Some(rect), Ok(annot)) = (rect, doc.get_dictionary_mut(id)) {
+            annot.set("Rect", matrix.apply_rect(&rect).to_object());
+        }
+    }
+
+    let page = doc
+        .get_dictionary_mut(page_id)
+        .map_err(|_| PdfError::InvalidArgument(format!("page object {page_id:?} not found")))?;
+    for which in [PageBox::Crop, PageBox::Bleed, PageBox::Trim, PageBox::Art] {
+        page.remove(which.key().as_bytes());
+    }
+    page.set(
+        PageBox::Media.key(),
+        Rect::from_size(width, height).to_object(),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(paper: Option<PaperSize>, width: f32, height: f32, pages: Vec<u32>) -> PageSizeGroup {
+        PageSizeGroup {
+            width,
+            height,
+            paper,
+            pages,
+        }
+    }
+
+    #[test]
+    fn outliers_are_described_against_the_largest_group() {
+        let report = PageSizeReport {
+            pages: 5,
+            groups: vec![
+                group(Some(PaperSize::A4), 595.0, 842.0, vec![1, 2, 4]),
+                group(Some(PaperSize::LETTER), 612.0, 792.0, vec![5]),
+                group(None, 500.0, 300.0, vec![3]),
+            ],
+        };
+        assert!(!report.is_uniform());
+        assert_eq!(
+            report.outliers(),
+            [
+                (
+                    3,
+                    "p. 3 is 500 x 300 pt landscape in an a4 portrait document".to_string()
+                ),
+                (
+                    5,
+                    "p. 5 is letter portrait in an a4 portrait document".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn pages_are_scaled_into_the_paper_in_their_orientation() -> Result<()> {
+        let mut doc = Document::with_version("1.4");
+        let page_id = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", Object::Name(b"Page".to_vec())),
+            ("MediaBox", Rect::from_size(792.0, 612.0).to_object()),
+        ]));
+        fit_page(&mut doc, page_id, PaperSize::A4)?;
+
+        let media = geometry::effective_page_box(&doc, page_id, PageBox::Media);
+        assert_eq!((media.width(), media.height()), (842.0, 595.0));
+        let content = doc
+            .get_page_content(page_id)
+            .map_err(|e| PdfError::InvalidArgument(e.to_string()))?;
+        let text = String::from_utf8_lossy(&content);
+        let scale = 595.0_f32 / 612.0;
+        assert!(
+            text.starts_with(&format!("q\n{scale} 0 0 {scale} ")),
+            "{text}"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+//! Extracted text by page, so that indexing systems can attribute text to
+//! page numbers.
+
+use std::{path::Path, process::Command};
+
+use crate::{
+    engine, find_tool, native, page_count, run_tool_capture, select_engine, validate_input_file,
+    Engine, LineEnding, Operation, PageSelection, PdfError, Result, TextOutput, Tool,
+};
+
+/// The text of one page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageText {
+    /// 1-based page number.
+    pub page: u32,
+    /// The page's text, ending with a newline unless it is empty.
+    pub text: String,
+}
+
+/// Options for [`extract_text_pages`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextPagesOptions {
+    /// The pages to extract.
+    pub pages: PageSelection,
+    /// Convert every line ending to this; `None` keeps them.
+    pub eol: Option<LineEnding>,
+}
+
+impl Default for TextPagesOptions {
+    fn default() -> Self {
+        Self {
+            pages: PageSelection::All,
+            eol: None,
+        }
+    }
+}
+
+/// Extract the text of the selected pages of `input`, one entry per page in
+/// page order (with Poppler's `pdftotext`, `mutool`, `PDFium`, or natively;
+/// see [`select_engine`]).
+///
+/// The tools' output is split at the form feed that ends each page. If the
+/// number of pages found that way does not match the document, because a
+/// page's text holds a form feed of its own, each page is extracted on its
+/// own instead.
+pub fn extract_text_pages(
+    input: impl AsRef<Path>,
+    options: &TextPagesOptions,
+) -> Result<Vec<PageText>> {
+    let input = input.as_ref();
+    validate_input_file(input)?;
+    let count = page_count(input)?;
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    let (first, last) = match options.pages {
+        PageSelection::All => (1, count),
+        PageSelection::Range { start, end } => (start, end),
+        PageSelection::From { start } => (start, count),
+    };
+    if first > count || last > count {
+        return Err(PdfError::InvalidArgument(format!(
+            "pages {first}-{last} are out of range (document has {count} pages)"
+        )));
+    }
+    let texts = match select_engine(Operation::ExtractText)? {
+        engine @ (Engine::Pdftotext | Engine::Mutool) => {
+            let texts = split_pages(&tool_text(engine, input, first, last)?);
+            if texts.len() == (first..=last).count() {
+                texts
+            } else {
+                (first..=last)
+                    .map(|page| {
+                        let text = tool_text(engine, input, page, page)?;
+                        Ok(text.strip_suffix('\x0c').unwrap_or(&text).to_string())
+                    })
+                    .collect::<Result<_>>()?
+            }
+        }
+        #[cfg(feature = "pdfium")]
+        Engine::Pdfium => selected(crate::pdfium::page_texts(input)?, first, last),
+        Engine::Native => selected(native::page_texts(input)?, first, last),
+        other => return Err(engine::unsupported(Operation::ExtractText, other)),
+    };
+    let eol = TextOutput {
+        eol: options.eol,
+        ..TextOutput::default()
+    };
+    Ok((first..)
+        .zip(texts)
+        .map(|(page, text)| PageText {
+            page,
+            text: eol.convert_eol(&text).into_owned(),
+        })
+        .collect())
+}
+
+/// The texts of pages `first..=last` (1-based) of all the document's pages.
+fn selected(texts: Vec<String>, first: u32, last: u32) -> Vec<String> {
+    (1..)
+        .zip(texts)
+        .filter(|(page, _)| (first..=last).contains(page))
+        .map(|(_, text)| text)
+        .collect()
+}
+
+/// Text ending each page with a form feed, split into pages.
+fn split_pages(text: &str) -> Vec<String> {
+    if text.is_empty() {
+        return Vec::new();
+    }
+    text.strip_suffix('\x0c')
+        .unwrap_or(text)
+        .split('\x0c')
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// The text of pages `first..=last` from pdftotext or `mutool`.
+fn tool_text(engine: Engine, input: &Path, first: u32, last: u32) -> Result<String> {
+    let (tool, cmd) = if engine == Engine::Mutool {
+        let mut cmd = Command::new(find_tool(Tool::Mutool)?);
+        cmd.arg("draw")
+            .arg("-q")
+            .arg("-F")
+            .arg("txt")
+            .arg(input.as_os_str())
+            .arg(format!("{first}-{last}"));
+        (Tool::Mutool, cmd)
+    } else {
+        let mut cmd = Command::new(find_tool(Tool::Pdftotext)?);
+        cmd.arg("-enc")
+            .arg("UTF-8")
+            .arg("-f")
+            .arg(first.to_string())
+            .arg("-l")
+            .arg(last.to_string())
+            .arg(input.as_os_str())
+            .arg("-");
+        (Tool::Pdftotext, cmd)
+    };
+    run_tool_capture(tool, cmd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tool_text_splits_at_form_feeds() {
+        assert_eq!(
+            split_pages("one\n\x0c\x0cthree\n\x0c"),
+            ["one\n", "", "three\n"]
+        );
+        assert_eq!(split_pages("no feed"), ["no feed"]);
+        assert!(split_pages("").is_empty());
+        assert_eq!(
+            selected(vec!["a".into(), "b".into(), "c".into()], 2, 3),
+            ["b", "c"]
+        );
+    }
+}
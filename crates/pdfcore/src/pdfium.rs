@@ -0,0 +1,240 @@
+//! In-process rendering and text extraction with `PDFium` (the `pdfium` feature).
+//!
+//! The `PDFium` shared library is loaded at run time, from `PDFCLI_PDFIUM` (the
+//! library file, or a directory containing it) if set, else from the system
+//! library search path. Nothing is spawned.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use flate2::{write::ZlibEncoder, Compression};
+use pdfium_render::prelude::{
+    PdfDocument, PdfPage, PdfRenderConfig, Pdfium, PdfiumLibraryBindings,
+};
+
+use crate::{raster::GrayImage, PdfError, Result};
+
+/// Environment variable naming the `PDFium` library (or its directory).
+const ENV_VAR: &str = "PDFCLI_PDFIUM";
+
+/// Fails with [`PdfError::MissingTool`] unless the `PDFium` library loads.
+pub(crate) fn check_available() -> Result<()> {
+    bindings().map(drop)
+}
+
+fn bindings() -> Result<Box<dyn PdfiumLibraryBindings>> {
+    let bound = match std::env::var_os(ENV_VAR) {
+        Some(path) => {
+            let path = PathBuf::from(path);
+            if path.is_dir() {
+                Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path(&path))
+            } else {
+                Pdfium::bind_to_library(path)
+            }
+        }
+        None => Pdfium::bind_to_system_library(),
+    };
+    bound.map_err(|e| {
+        tracing::debug!(error = %e, "failed to load pdfium");
+        PdfError::MissingTool {
+            tool: "pdfium",
+            hint: format!(
+                "Install the PDFium shared library ({}), e.g. from \
+                 https://github.com/bblanchon/pdfium-binaries, and put it on the library \
+                 search path or set {ENV_VAR} to the library or its directory.",
+                Pdfium::pdfium_platform_library_name().to_string_lossy()
+            ),
+        }
+    })
+}
+
+fn load<'a>(pdfium: &'a Pdfium, input: &Path) -> Result<PdfDocument<'a>> {
+    pdfium
+        .load_pdf_from_file(input, None)
+        .map_err(|e| PdfError::Pdfium(format!("{}: {e}", input.display())))
+}
+
+/// Render every page to `out_dir/page-<n>.png` at `dpi`.
+pub(crate) fn render(input: &Path, out_dir: &Path, dpi: u32) -> Result<()> {
+    let pdfium = Pdfium::new(bindings()?);
+    let doc = load(&pdfium, input)?;
+    let config = render_config(dpi)?;
+    for (index, page) in doc.pages().iter().enumerate() {
+        let number = index + 1;
+        render_page(
+            &page,
+            number,
+            &config,
+            &out_dir.join(format!("page-{number}.png")),
+        )?;
+    }
+    Ok(())
+}
+
+/// Render the first page to `output` (a PNG file) at `dpi`.
+pub(crate) fn render_first_page(input: &Path, output: &Path, dpi: u32) -> Result<()> {
+    let pdfium = Pdfium::new(bindings()?);
+    let doc = load(&pdfium, input)?;
+    let page = doc
+        .pages()
+        .first()
+        .map_err(|e| PdfError::Pdfium(format!("page 1: {e}")))?;
+    render_page(&page, 1, &render_config(dpi)?, output)
+}
+
+/// Render every page at `dpi` and hand the grayscale images to `each`, in
+/// order.
+pub(crate) fn for_each_gray_page(
+    input: &Path,
+    dpi: u32,
+    mut each: impl FnMut(u32, GrayImage) -> Result<()>,
+) -> Result<()> {
+    let pdfium = Pdfium::new(bindings()?);
+    let doc = load(&pdfium, input)?;
+    let config = render_config(dpi)?;
+    for (number, page) in (1..).zip(doc.pages().iter()) {
+        let bitmap = page
+            .render_with_config(&config)
+            .map_err(|e| PdfError::Pdfium(format!("page {number}: {e}")))?;
+        let (Ok(width), Ok(height)) = (
+            usize::try_from(bitmap.width()),
+            usize::try_from(bitmap.height()),
+        ) else {
+            return Err(PdfError::Pdfium(format!(
+                "page {number}: invalid bitmap size"
+            )));
+        };
+        // Rec. 601 luma.
+        let pixels = bitmap
+            .as_rgba_bytes()
+            .chunks_exact(4)
+            .map(|p| {
+                let luma =
+                    (299 * u32::from(p[0]) + 587 * u32::from(p[1]) + 114 * u32::from(p[2])) / 1000;
+                u8::try_from(luma).unwrap_or(u8::MAX)
+            })
+            .collect();
+        each(
+            number,
+            GrayImage {
+                width,
+                height,
+                pixels,
+            },
+        )?;
+    }
+    Ok(())
+}
+
+fn render_config(dpi: u32) -> Result<PdfRenderConfig> {
+    let dpi = u16::try_from(dpi)
+        .map_err(|_| PdfError::InvalidArgument(format!("dpi out of range: {dpi}")))?;
+    Ok(PdfRenderConfig::new().scale_page_by_factor(f32::from(dpi) / 72.0))
+}
+
+fn render_page(
+    page: &PdfPage<'_>,
+    number: usize,
+    config: &PdfRenderConfig,
+    path: &Path,
+) -> Result<()> {
+    let bitmap = page
+        .render_with_config(config)
+        .map_err(|e| PdfError::Pdfium(format!("page {number}: {e}")))?;
+    let (Ok(width), Ok(height)) = (
+        u32::try_from(bitmap.width()),
+        u32::try_from(bitmap.height()),
+    ) else {
+        return Err(PdfError::Pdfium(format!(
+            "page {number}: invalid bitmap size"
+        )));
+    };
+    write_png(path, width, height, &bitmap.as_rgba_bytes())?;
+    tracing::debug!(page = number, width, height, "rendered page");
+    Ok(())
+}
+
+/// Page text, one form feed after each page (like `pdftotext`).
+pub(crate) fn extract_text(input: &Path) -> Result<String> {
+    let mut out = String::new();
+    for text in page_texts(input)? {
+        out.push_str(&text);
+        out.push('\x0c');
+    }
+    Ok(out)
+}
+
+/// The text of each page, each ending with a newline unless it is empty.
+pub(crate) fn page_texts(input: &Path) -> Result<Vec<String>> {
+    let pdfium = Pdfium::new(bindings()?);
+    let doc = load(&pdfium, input)?;
+    let mut pages = Vec::new();
+    for (index, page) in doc.pages().iter().enumerate() {
+        let mut text = page
+            .text()
+            .map_err(|e| PdfError::Pdfium(format!("page {}: {e}", index + 1)))?
+            .all()
+            .replace("\r\n", "\n");
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        pages.push(text);
+    }
+    Ok(pages)
+}
+
+/// Write 8-bit RGBA pixels as a PNG file.
+fn write_png(path: &Path, width: u32, height: u32, rgba: &[u8]) -> Result<()> {
+    let row_len = rgba.len() / usize::try_from(height.max(1)).unwrap_or(usize::MAX);
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // Bit depth 8, color type 6 (RGBA), default compression, filter, no interlace.
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    write_chunk(&mut out, *b"IHDR", &header)?;
+
+    let mut data = ZlibEncoder::new(Vec::new(), Compression::default());
+    for row in rgba.chunks_exact(row_len.max(1)) {
+        // Filter type 0 (none) for every scanline.
+        data.write_all(&[0])?;
+        data.write_all(row)?;
+    }
+    write_chunk(&mut out, *b"IDAT", &data.finish()?)?;
+    write_chunk(&mut out, *b"IEND", &[])?;
+    out.flush()?;
+    Ok(())
+}
+
+fn write_chunk(out: &mut impl Write, kind: [u8; 4], data: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(data.len()).map_err(|_| io::Error::other("PNG chunk too large"))?;
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(&kind);
+    crc.update(data);
+    out.write_all(&len.to_be_bytes())?;
+    out.write_all(&kind)?;
+    out.write_all(data)?;
+    out.write_all(&crc.finalize().to_be_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn png_has_header_and_checksummed_chunks() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("pixel.png");
+        write_png(&path, 1, 1, &[255, 0, 0, 255])?;
+        let bytes = std::fs::read(&path)?;
+        assert!(bytes.starts_with(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\x01\0\0\0\x01\x08\x06"));
+        // IEND with its well-known CRC.
+        assert!(bytes.ends_with(b"\0\0\0\0IEND\xae\x42\x60\x82"));
+        Ok(())
+    }
+}
@@ -0,0 +1,454 @@
+//! Multi-step pipelines: several operations applied one after another, with
+//! the intermediate files kept in a [`TempSpace`].
+//!
+//! Pipelines are written as steps separated by `|`, each a command name with
+//! its options:
+//!
+//! ```text
+//! decrypt | flatten | rotate --degrees 90 --pages 1-3 | compress --preset ebook | linearize
+//! flatten | print-ready --paper letter --bleed 9 --cmyk true --crop-marks true
+//! ```
+
+use std::{fmt, path::Path, str::FromStr};
+
+use crate::{
+    convert_colorspace, encryption, engine, flatten_annotations, flatten_annotations_command,
+    gs_pdfwrite_command, linearize, linearize_command, plan, print_ready, profiles, rotate_command,
+    select_engine, tempspace::TempSpace, validate_input_file, ColorSpace, Compress, CompressPreset,
+    Engine, Operation, PageSelection, PaperSize, PdfError, Plan, PrintReadyOptions, Result, Rotate,
+    Tool,
+};
+
+/// One operation of a [`Pipeline`], taking one PDF to another.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PipelineStep {
+    /// `decrypt [--password <password>]`: decrypt with the password, or the
+    /// empty user password, and drop the encryption.
+    Decrypt { password: Option<String> },
+    /// `rotate --degrees <0|90|180|270> [--pages <start-end>]`
+    Rotate { degrees: u16, pages: PageSelection },
+    /// `compress [--preset <preset>]`
+    Compress { preset: CompressPreset },
+    /// `convert-color --to <gray|cmyk|rgb>`
+    ConvertColor { to: ColorSpace },
+    /// `flatten`: flatten form fields and annotations.
+    Flatten,
+    /// `linearize`
+    Linearize,
+    /// `print-ready [--paper <size>] [--bleed <points>] [--cmyk <bool>]
+    /// [--crop-marks <bool>] [--registration-marks <bool>]
+    /// [--keep-transparency <bool>]`: the `print-ready` profile. Its
+    /// preflight report is not checked.
+    PrintReady(PrintReadyOptions),
+}
+
+impl PipelineStep {
+    /// Command names accepted by [`PipelineStep::from_str`].
+    pub const NAMES: [&'static str; 7] = [
+        "decrypt",
+        "rotate",
+        "compress",
+        "convert-color",
+        "flatten",
+        "linearize",
+        "print-ready",
+    ];
+
+    /// The step `name` with its `options` as `(key, value)` pairs, keys
+    /// without the leading `--`: what [`PipelineStep::from_str`] parses a
+    /// step into. Values are taken as they are, spaces included.
+    pub fn from_options(name: &str, options: &[(&str, &str)]) -> Result<Self> {
+        let invalid = |msg: String| PdfError::InvalidArgument(msg);
+        let mut options = options.to_vec();
+        let mut take = |key: &str| {
+            options
+                .iter()
+                .position(|(k, _)| *k == key)
+                .map(|i| options.remove(i).1)
+        };
+
+        let step = match name {
+            "decrypt" => Self::Decrypt {
+                password: take("password").map(str::to_string),
+            },
+            "rotate" => {
+                let degrees = take("degrees")
+                    .ok_or_else(|| invalid("rotate: --degrees is required".to_string()))?;
+                let Ok(degrees @ (0 | 90 | 180 | 270)) = degrees.parse::<u16>() else {
+                    return Err(invalid(format!(
+                        "rotate: degrees must be 0, 90, 180, or 270, got {degrees}"
+                    )));
+                };
+                let pages = take("pages").map_or(Ok(PageSelection::All), str::parse)?;
+                Self::Rotate { degrees, pages }
+            }
+            "compress" => Self::Compress {
+                preset: take("preset").map_or(Ok(CompressPreset::Default), str::parse)?,
+            },
+            "convert-color" => Self::ConvertColor {
+                to: take("to")
+                    .ok_or_else(|| invalid("convert-color: --to is required".to_string()))?
+                    .parse()?,
+            },
+            "flatten" => Self::Flatten,
+            "linearize" => Self::Linearize,
+            "print-ready" => {
+                let paper = take("paper").map_or(Ok(PaperSize::A4), PaperSize::from_name)?;
+                let bleed = take("bleed").map_or(Ok(0.0), |bleed| {
+                    bleed.parse::<f32>().map_err(|_| {
+                        invalid(format!("print-ready: bleed must be in points, got {bleed}"))
+                    })
+                })?;
+                let mut flag = |key: &str| {
+                    take(key).map_or(Ok(false), |value| {
+                        value.parse::<bool>().map_err(|_| {
+                            invalid(format!(
+                                "print-ready: --{key} must be true or false, got {value}"
+                            ))
+                        })
+                    })
+                };
+                Self::PrintReady(PrintReadyOptions {
+                    paper,
+                    bleed,
+                    cmyk: flag("cmyk")?,
+                    crop_marks: flag("crop-marks")?,
+                    registration_marks: flag("registration-marks")?,
+                    flatten_transparency: !flag("keep-transparency")?,
+                })
+            }
+            other => {
+                return Err(invalid(format!(
+                    "unknown pipeline step: {other} (expected one of: {})",
+                    Self::NAMES.join(", ")
+                )))
+            }
+        };
+        if let Some((key, _)) = options.first() {
+            return Err(invalid(format!("{name}: unknown option --{key}")));
+        }
+        Ok(step)
+    }
+
+    fn run(&self, input: &Path, output: &Path) -> Result<()> {
+        match self {
+            Self::Decrypt { password } => {
+                encryption::decrypt_file(input, output, password.as_deref())
+            }
+            Self::Rotate { degrees, pages } => Rotate::new(input)
+                .degrees(*degrees)
+                .pages(pages.clone())
+                .output(output)
+                .run(),
+            Self::Compress { preset } => Compress::new(input)
+                .preset(*preset)
+                .output(output)
+                .run()
+                .map(drop),
+            Self::ConvertColor { to } => convert_colorspace(input, output, *to),
+            Self::Flatten => flatten_annotations(input, output),
+            Self::Linearize => linearize(input, output),
+            Self::PrintReady(options) => print_ready(input, output, options).map(drop),
+        }
+    }
+
+    /// Like the operations' own plans, but without requiring `input` to
+    /// exist yet: it may be the output of an earlier step.
+    fn plan(&self, input: &Path, output: &Path) -> Result<Plan> {
+        let plan = match self {
+            Self::Decrypt { password } => Plan::new().native(match password {
+                Some(_) => "decrypt with the given password",
+                None => "decrypt with the empty user password",
+            }),
+            Self::Rotate { degrees, pages } => match select_engine(Operation::Rotate)? {
+                Engine::Qpdf => {
+                    Plan::new().run(Tool::Qpdf, &rotate_command(input, output, *degrees, pages)?)
+                }
+                Engine::Native => {
+                    Plan::new().native(format!("rotate pages {pages} by {degrees} degrees"))
+                }
+                other => return Err(engine::unsupported(Operation::Rotate, other)),
+            },
+            Self::Compress { preset } => Plan::new().run(
+                Tool::Ghostscript,
+                &gs_pdfwrite_command(input, output, Some(*preset), &[])?,
+            ),
+            Self::ConvertColor { to } => Plan::new().run(
+                Tool::Ghostscript,
+                &gs_pdfwrite_command(input, output, None, &to.as_gs_args())?,
+            ),
+            Self::Flatten => {
+                Plan::new().run(Tool::Qpdf, &flatten_annotations_command(input, output)?)
+            }
+            Self::Linearize => Plan::new().run(Tool::Qpdf, &linearize_command(input, output)?),
+            Self::PrintReady(options) => profiles::print_ready_steps(input, options)?,
+        };
+        Ok(plan)
+    }
+}
+
+impl FromStr for PipelineStep {
+    type Err = PdfError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = |msg: String| PdfError::InvalidArgument(msg);
+        let mut words = s.split_whitespace();
+        let name = words
+            .next()
+            .ok_or_else(|| invalid("empty pipeline step".to_string()))?;
+        let mut options = Vec::new();
+        while let Some(flag) = words.next() {
+            let key = flag
+                .strip_prefix("--")
+                .ok_or_else(|| invalid(format!("{name}: expected an option, got {flag:?}")))?;
+            let (key, value) = match key.split_once('=') {
+                Some((key, value)) => (key, value),
+                None => (
+                    key,
+                    words
+                        .next()
+                        .ok_or_else(|| invalid(format!("{name}: --{key} needs a value")))?,
+                ),
+            };
+            options.push((key, value));
+        }
+        Self::from_options(name, &options)
+    }
+}
+
+impl fmt::Display for PipelineStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Decrypt { password: None } => f.write_str("decrypt"),
+            Self::Decrypt {
+                password: Some(password),
+            } => write!(f, "decrypt --password {password}"),
+            Self::Rotate { degrees, pages } => {
+                write!(f, "rotate --degrees {degrees} --pages {pages}")
+            }
+            Self::Compress { preset } => write!(f, "compress --preset {preset}"),
+            Self::ConvertColor { to } => {
+                let to = match to {
+                    ColorSpace::Gray => "gray",
+                    ColorSpace::Cmyk => "cmyk",
+                    ColorSpace::Rgb => "rgb",
+                };
+                write!(f, "convert-color --to {to}")
+            }
+            Self::Flatten => f.write_str("flatten"),
+            Self::Linearize => f.write_str("linearize"),
+            Self::PrintReady(options) => {
+                write!(
+                    f,
+                    "print-ready --paper {} --bleed {}",
+                    options.paper.name, options.bleed
+                )?;
+                for (key, set) in [
+                    ("cmyk", options.cmyk),
+                    ("crop-marks", options.crop_marks),
+                    ("registration-marks", options.registration_marks),
+                    ("keep-transparency", !options.flatten_transparency),
+                ] {
+                    if set {
+                        write!(f, " --{key} true")?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A sequence of [`PipelineStep`]s.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pipeline {
+    steps: Vec<PipelineStep>,
+}
+
+impl Pipeline {
+    /// A pipeline of `steps`, in order; there must be at least one.
+    pub fn new(steps: Vec<PipelineStep>) -> Result<Self> {
+        if steps.is_empty() {
+            return Err(PdfError::InvalidArgument(
+                "a pipeline needs at least one step".to_string(),
+            ));
+        }
+        Ok(Self { steps })
+    }
+
+    /// The steps, in order.
+    #[must_use]
+    pub fn steps(&self) -> &[PipelineStep] {
+        &self.steps
+    }
+
+    /// Run every step, feeding each one's output to the next, and write the
+    /// last step's output to `output`.
+    pub fn run(&self, input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<()> {
+        let (input, output) = (input.as_ref(), output.as_ref());
+        validate_input_file(input)?;
+        let scratch = TempSpace::new("pipeline")?;
+        let mut current = input.to_path_buf();
+        for (index, step) in self.steps.iter().enumerate() {
+            let next = if index + 1 == self.steps.len() {
+                output.to_path_buf()
+            } else {
+                scratch.file(&format!("step-{}.pdf", index + 1))
+            };
+            tracing::debug!(step = index + 1, %step, "running pipeline step");
+            step.run(&current, &next).map_err(|e| match e {
+                PdfError::InvalidArgument(msg) => {
+                    PdfError::InvalidArgument(format!("step {} ({step}): {msg}", index + 1))
+                }
+                other => other,
+            })?;
+            current = next;
+        }
+        Ok(())
+    }
+
+    /// What [`Pipeline::run`] would do.
+    pub fn plan(&self, input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<Plan> {
+        let (input, output) = (input.as_ref(), output.as_ref());
+        validate_input_file(input)?;
+        let mut plan = Plan::new();
+        let mut current = input.to_path_buf();
+        for (index, step) in self.steps.iter().enumerate() {
+            let next = if index + 1 == self.steps.len() {
+                output.to_path_buf()
+            } else {
+                plan::temp_path(&format!("step-{}.pdf", index + 1))
+            };
+            plan.steps.extend(step.plan(&current, &next)?.steps);
+            current = next;
+        }
+        Ok(plan.write(output))
+    }
+}
+
+impl FromStr for Pipeline {
+    type Err = PdfError;
+
+    /// Steps separated by `|`.
+    fn from_str(s: &str) -> Result<Self> {
+        Self::new(
+            s.split('|')
+                .map(str::parse)
+                .collect::<Result<Vec<PipelineStep>>>()?,
+        )
+    }
+}
+
+impl fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, step) in self.steps.iter().enumerate() {
+            if index > 0 {
+                f.write_str(" | ")?;
+            }
+            write!(f, "{step}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pipelines_parse_and_round_trip() -> Result<()> {
+        let pipeline: Pipeline =
+            "decrypt --password secret | flatten | rotate --degrees 90 --pages=1-3 | compress --preset ebook"
+                .parse()?;
+        assert_eq!(
+            pipeline.steps(),
+            [
+                PipelineStep::Decrypt {
+                    password: Some("secret".to_string())
+                },
+                PipelineStep::Flatten,
+                PipelineStep::Rotate {
+                    degrees: 90,
+                    pages: PageSelection::Range { start: 1, end: 3 }
+                },
+                PipelineStep::Compress {
+                    preset: CompressPreset::Ebook
+                },
+            ]
+        );
+        assert_eq!(pipeline.to_string().parse::<Pipeline>()?, pipeline);
+        let decrypt: Pipeline = "decrypt | rotate --degrees 90".parse()?;
+        assert_eq!(decrypt.steps()[0], PipelineStep::Decrypt { password: None });
+        assert_eq!(decrypt.to_string().parse::<Pipeline>()?, decrypt);
+
+        for bad in [
+            "",
+            "rotate",
+            "rotate --degrees 45",
+            "compress --preset ebook --colour red",
+            "compress | | linearize",
+            "decrypt --password",
+            "convert-color gray",
+            "print-ready --bleed 3mm",
+            "print-ready --cmyk yes",
+            "print-ready --paper b52",
+        ] {
+            assert!(
+                matches!(bad.parse::<Pipeline>(), Err(PdfError::InvalidArgument(_))),
+                "{bad:?}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn print_ready_steps_parse_and_run() -> Result<()> {
+        let pipeline: Pipeline =
+            "flatten | print-ready --paper letter --bleed 9 --crop-marks true".parse()?;
+        assert_eq!(
+            pipeline.steps()[1],
+            PipelineStep::PrintReady(PrintReadyOptions {
+                paper: PaperSize::LETTER,
+                bleed: 9.0,
+                cmyk: false,
+                flatten_transparency: true,
+                crop_marks: true,
+                registration_marks: false,
+            })
+        );
+        assert_eq!(pipeline.to_string().parse::<Pipeline>()?, pipeline);
+        let default: Pipeline = "print-ready".parse()?;
+        assert_eq!(default.to_string(), "print-ready --paper a4 --bleed 0");
+
+        let scratch = TempSpace::new("pipeline-test")?;
+        let text = scratch.write("in.txt", b"Print me.\n")?;
+        let input = scratch.file("in.pdf");
+        crate::text_to_pdf(&text, &input, &crate::TextToPdfOptions::default())?;
+        let output = scratch.file("out.pdf");
+        let steps: Pipeline = "print-ready --paper letter --bleed 9 --crop-marks true".parse()?;
+        let plan = match steps.plan(&input, &output) {
+            Err(PdfError::MissingTool { .. }) => {
+                eprintln!("skipping: ghostscript missing");
+                return Ok(());
+            }
+            plan => plan?,
+        };
+        assert!(matches!(
+            plan.steps.first(),
+            Some(plan::PlanStep::Run {
+                tool: "ghostscript",
+                ..
+            })
+        ));
+        steps.run(&input, &output)?;
+        let doc = crate::load_document(&output)?;
+        let page = doc.get_pages().get(&1).copied();
+        let trim =
+            page.map(|id| crate::geometry::effective_page_box(&doc, id, crate::PageBox::Trim));
+        assert_eq!(
+            trim.map(|trim| (trim.width().round(), trim.height().round())),
+            Some((612.0, 792.0))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+//! Dry-run plans: what an operation would do, without doing it.
+//!
+//! Each mutating operation has a `*_plan` counterpart that validates its
+//! inputs and resolves external tools exactly as the real operation does,
+//! then describes the commands it would run instead of running them.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{command_to_string, Tool};
+
+/// One step of a [`Plan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanStep {
+    /// Run an external tool.
+    Run {
+        /// Tool name, e.g. `qpdf`.
+        tool: &'static str,
+        /// The full command line.
+        command: String,
+    },
+    /// An in-process (pure Rust) operation.
+    Native(String),
+    /// Write an output file or directory.
+    Write(PathBuf),
+}
+
+impl fmt::Display for PlanStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Run { command, .. } => write!(f, "run: {command}"),
+            Self::Native(what) => write!(f, "native: {what}"),
+            Self::Write(path) => write!(f, "write: {}", path.display()),
+        }
+    }
+}
+
+/// The steps an operation would perform, in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Plan {
+    pub steps: Vec<PlanStep>,
+}
+
+impl Plan {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn run(mut self, tool: Tool, cmd: &Command) -> Self {
+        self.steps.push(PlanStep::Run {
+            tool: tool.name(),
+            command: command_to_string(cmd),
+        });
+        self
+    }
+
+    pub(crate) fn native(mut self, what: impl Into<String>) -> Self {
+        self.steps.push(PlanStep::Native(what.into()));
+        self
+    }
+
+    pub(crate) fn write(mut self, path: &Path) -> Self {
+        self.steps.push(PlanStep::Write(path.to_path_buf()));
+        self
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            writeln!(f, "{step}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Placeholder for an intermediate file that only exists while an operation
+/// runs (it lives in a temporary directory).
+pub(crate) fn temp_path(name: &str) -> PathBuf {
+    Path::new("<tmp>").join(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plan_lists_one_step_per_line() {
+        let mut cmd = Command::new("qpdf");
+        cmd.arg("--linearize").arg("in put.pdf").arg("out.pdf");
+        let plan = Plan::new()
+            .run(Tool::Qpdf, &cmd)
+            .native("check fonts")
+            .write(Path::new("out.pdf"));
+        assert_eq!(
+            plan.to_string(),
+            "run: qpdf --linearize \"in put.pdf\" out.pdf\nnative: check fonts\nwrite: out.pdf\n"
+        );
+    }
+}
@@ -0,0 +1,405 @@
+//! Declarative intake policies: preflight, security, and metadata rules read
+//! from a TOML file and checked against individual PDFs.
+//!
+//! ```toml
+//! [preflight]
+//! max_pages = 500
+//! max_bytes = 26214400
+//! paper = "a4"
+//! fonts_embedded = true
+//! tagged = true
+//!
+//! [security]
+//! forbid_javascript = true
+//! forbid_embedded_files = true
+//! forbid_launch_actions = true
+//! forbid_encryption = true
+//!
+//! [metadata]
+//! required = ["Title", "Author"]
+//! lang = true
+//! ```
+
+use std::{fs, path::Path};
+
+use serde::Deserialize;
+
+use crate::{
+    a11y, fonts,
+    geometry::{self, PageBox, PaperSize},
+    load_document, metadata, profiles, validate_input_file, ComplianceCheck, PdfError, Result,
+};
+
+/// A set of rules every conforming PDF must satisfy.
+///
+/// Every rule is optional; an empty policy accepts any readable PDF.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+    /// Page, size, and print-production rules.
+    pub preflight: PreflightRules,
+    /// Active-content and encryption rules.
+    pub security: SecurityRules,
+    /// Document information requirements.
+    pub metadata: MetadataRules,
+}
+
+/// `[preflight]` section of a [`Policy`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PreflightRules {
+    /// Maximum number of pages.
+    pub max_pages: Option<u32>,
+    /// Maximum file size in bytes.
+    pub max_bytes: Option<u64>,
+    /// Required paper size name (e.g. `a4`, `letter`); either orientation.
+    pub paper: Option<String>,
+    /// Require every font to be embedded.
+    pub fonts_embedded: bool,
+    /// Require a structure tree (tagged PDF).
+    pub tagged: bool,
+}
+
+/// `[security]` section of a [`Policy`].
+#[allow(clippy::struct_excessive_bools)] // mirrors the TOML keys one-to-one
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SecurityRules {
+    /// Reject JavaScript actions.
+    pub forbid_javascript: bool,
+    /// Reject embedded files and file attachment annotations.
+    pub forbid_embedded_files: bool,
+    /// Reject `/Launch` actions.
+    pub forbid_launch_actions: bool,
+    /// Reject encrypted files.
+    pub forbid_encryption: bool,
+}
+
+/// `[metadata]` section of a [`Policy`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetadataRules {
+    /// Info dictionary keys that must be present and non-empty.
+    pub required: Vec<String>,
+    /// Require a document language (catalog `/Lang`).
+    pub lang: bool,
+}
+
+impl Policy {
+    /// Parse a policy from TOML text.
+    pub fn from_toml_str(text: &str) -> Result<Self> {
+        let policy: Self = toml::from_str(text)
+            .map_err(|e| PdfError::InvalidArgument(format!("invalid policy: {e}")))?;
+        if let Some(paper) = &policy.preflight.paper {
+            PaperSize::from_name(paper)?;
+        }
+        Ok(policy)
+    }
+
+    /// Read and parse a policy file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        validate_input_file(path)?;
+        Self::from_toml_str(&fs::read_to_string(path)?)
+    }
+}
+
+/// Result of [`check_policy`] for one file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyReport {
+    /// One check per rule enabled in the policy.
+    pub checks: Vec<ComplianceCheck>,
+}
+
+impl PolicyReport {
+    /// `true` if every check passed.
+    #[must_use]
+    pub fn is_compliant(&self) -> bool {
+        self.checks.iter().all(|c| c.passed)
+    }
+
+    /// Checks that failed.
+    pub fn violations(&self) -> impl Iterator<Item = &ComplianceCheck> {
+        self.checks.iter().filter(|c| !c.passed)
+    }
+}
+
+/// Check a PDF against a policy (pure Rust; no external tools).
+///
+/// Fails only if the file cannot be read or parsed; rule violations are
+/// reported in the returned [`PolicyReport`].
+pub fn check_policy(path: impl AsRef<Path>, policy: &Policy) -> Result<PolicyReport> {
+    let path = path.as_ref();
+    let doc = load_document(path)?;
+    let mut checks = Vec::new();
+    preflight_checks(
+        &doc,
+        fs::metadata(path)?.len(),
+        &policy.preflight,
+        &mut checks,
+    )?;
+    security_checks(&doc, &policy.security, &mut checks);
+    metadata_checks(&doc, &policy.metadata, &mut checks);
+    Ok(PolicyReport { checks })
+}
+
+fn preflight_checks(
+    doc: &lopdf::Document,
+    file_bytes: u64,
+    rules: &PreflightRules,
+    checks: &mut Vec<ComplianceCheck>,
+) -> Result<()> {
+    const TOLERANCE: f32 = 1.0;
+
+    if let Some(max) = rules.max_pages {
+        let pages = doc.get_pages().len();
+        checks.push(ComplianceCheck {
+            name: "max-pages",
+            passed: u32::try_from(pages).is_ok_and(|p| p <= max),
+            detail: format!("{pages} page(s) (limit {max})"),
+        });
+    }
+    if let Some(max) = rules.max_bytes {
+        checks.push(ComplianceCheck {
+            name: "max-bytes",
+            passed: file_bytes <= max,
+            detail: format!("{file_bytes} bytes (limit {max})"),
+        });
+    }
+    if let Some(name) = &rules.paper {
+        let paper = PaperSize::from_name(name)?;
+        let wrong: Vec<u32> = doc
+            .get_pages()
+            .into_iter()
+            .filter(|(_, id)| {
+                let trim = geometry::effective_page_box(doc, *id, PageBox::Trim);
+                PaperSize::matching(&trim, TOLERANCE) != Some(paper)
+            })
+            .map(|(no, _)| no)
+            .collect();
+        checks.push(ComplianceCheck {
+            name: "paper",
+            passed: wrong.is_empty(),
+            detail: if wrong.is_empty() {
+                format!("all pages are {paper}")
+            } else {
+                format!("pages not {paper}: {wrong:?}")
+            },
+        });
+    }
+    if rules.fonts_embedded {
+        let missing: Vec<String> = fonts::collect_fonts(doc)
+            .into_iter()
+            .filter(|f| !f.embedded)
+            .map(|f| f.name)
+            .collect();
+        checks.push(ComplianceCheck {
+            name: "fonts-embedded",
+            passed: missing.is_empty(),
+            detail: if missing.is_empty() {
+                "all fonts embedded".to_string()
+            } else {
+                format!("not embedded: {}", missing.join(", "))
+            },
+        });
+    }
+    if rules.tagged {
+        let tagged = a11y::is_tagged(doc);
+        checks.push(ComplianceCheck {
+            name: "tagged",
+            passed: tagged,
+            detail: if tagged {
+                "structure tree present"
+            } else {
+                "no structure tree"
+            }
+            .to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn security_checks(
+    doc: &lopdf::Document,
+    rules: &SecurityRules,
+    checks: &mut Vec<ComplianceCheck>,
+) {
+    if rules.forbid_javascript {
+        let count = profiles::count_javascript(doc);
+        checks.push(ComplianceCheck {
+            name: "no-javascript",
+            passed: count == 0,
+            detail: format!("{count} JavaScript action(s)"),
+        });
+    }
+    if rules.forbid_embedded_files {
+        let count = count_embedded_files(doc);
+        checks.push(ComplianceCheck {
+            name: "no-embedded-files",
+            passed: count == 0,
+            detail: format!("{count} embedded file(s)"),
+        });
+    }
+    if rules.forbid_launch_actions {
+        let count = count_actions(doc, b"Launch");
+        checks.push(ComplianceCheck {
+            name: "no-launch-actions",
+            passed: count == 0,
+            detail: format!("{count} launch action(s)"),
+        });
+    }
+    if rules.forbid_encryption {
+        let encrypted = doc.is_encrypted();
+        checks.push(ComplianceCheck {
+            name: "not-encrypted",
+            passed: !encrypted,
+            detail: if encrypted {
+                "encrypted"
+            } else {
+                "not encrypted"
+            }
+            .to_string(),
+        });
+    }
+}
+
+fn metadata_checks(
+    doc: &lopdf::Document,
+    rules: &MetadataRules,
+    checks: &mut Vec<ComplianceCheck>,
+) {
+    if !rules.required.is_empty() {
+        let missing: Vec<&str> = rules
+            .required
+            .iter()
+            .map(String::as_str)
+            .filter(|key| metadata::info_entry(doc, key).is_none_or(|v| v.trim().is_empty()))
+            .collect();
+        checks.push(ComplianceCheck {
+            name: "metadata",
+            passed: missing.is_empty(),
+            detail: if missing.is_empty() {
+                format!("present: {}", rules.required.join(", "))
+            } else {
+                format!("missing: {}", missing.join(", "))
+            },
+        });
+    }
+    if rules.lang {
+        let lang = doc
+            .catalog()
+            .ok()
+            .and_then(|c| c.get(b"Lang").ok())
+            .and_then(|o| o.as_str().ok())
+            .map(metadata::decode_text_string)
+            .filter(|l| !l.trim().is_empty());
+        checks.push(ComplianceCheck {
+            name: "lang",
+            passed: lang.is_some(),
+            detail: lang.map_or_else(
+                || "no document language".to_string(),
+                |l| format!("/Lang {l}"),
+            ),
+        });
+    }
+}
+
+/// Embedded file specifications plus file attachment annotations.
+fn count_embedded_files(doc: &lopdf::Document) -> usize {
+    doc.objects
+        .values()
+        .filter_map(|obj| match obj {
+            lopdf::Object::Dictionary(d) => Some(d),
+            lopdf::Object::Stream(s) => Some(&s.dict),
+            _ => None,
+        })
+        .filter(|d| {
+            d.type_is(b"EmbeddedFile")
+                || d.get(b"Subtype")
+                    .and_then(lopdf::Object::as_name)
+                    .is_ok_and(|s| s == b"FileAttachment" || s == b"EmbeddedFile")
+        })
+        .count()
+}
+
+/// Action dictionaries (direct or indirect) of the given `/S` type.
+fn count_actions(doc: &lopdf::Document, kind: &[u8]) -> usize {
+    let is_kind = |d: &lopdf::Dictionary| {
+        d.get(b"S")
+            .and_then(lopdf::Object::as_name)
+            .is_ok_and(|s| s == kind)
+    };
+    let mut count = 0;
+    for obj in doc.objects.values() {
+        let Ok(dict) = obj.as_dict() else {
+            continue;
+        };
+        if is_kind(dict) {
+            count += 1;
+        }
+        // Actions are often written inline under /A or /OpenAction.
+        for key in [b"A".as_slice(), b"OpenAction".as_slice()] {
+            if let Ok(lopdf::Object::Dictionary(inline)) = dict.get(key) {
+                count += usize::from(is_kind(inline));
+            }
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy_parses_and_rejects_unknown_rules() -> Result<()> {
+        let policy = Policy::from_toml_str(
+            "[preflight]\nmax_pages = 10\npaper = \"A4\"\n\n[metadata]\nrequired = [\"Title\"]\n",
+        )?;
+        assert_eq!(policy.preflight.max_pages, Some(10));
+        assert_eq!(policy.metadata.required, vec!["Title".to_string()]);
+        assert!(!policy.security.forbid_javascript);
+
+        assert!(Policy::from_toml_str("[security]\nforbid_macros = true\n").is_err());
+        assert!(Policy::from_toml_str("[preflight]\npaper = \"b7\"\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn security_and_metadata_rules_report_violations() {
+        let mut doc = lopdf::Document::with_version("1.4");
+        let launch = doc.add_object(lopdf::Dictionary::from_iter([
+            ("S", lopdf::Object::Name(b"Launch".to_vec())),
+            ("F", lopdf::Object::string_literal("calc.exe")),
+        ]));
+        let catalog_id = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"Catalog".to_vec())),
+            ("OpenAction", lopdf::Object::Reference(launch)),
+        ]));
+        doc.trailer.set("Root", catalog_id);
+
+        let mut checks = Vec::new();
+        security_checks(
+            &doc,
+            &SecurityRules {
+                forbid_javascript: true,
+                forbid_launch_actions: true,
+                ..SecurityRules::default()
+            },
+            &mut checks,
+        );
+        metadata_checks(
+            &doc,
+            &MetadataRules {
+                required: vec!["Title".to_string()],
+                lang: true,
+            },
+            &mut checks,
+        );
+        let report = PolicyReport { checks };
+
+        let failed: Vec<&str> = report.violations().map(|c| c.name).collect();
+        assert_eq!(failed, vec!["no-launch-actions", "metadata", "lang"]);
+        assert!(!report.is_compliant());
+    }
+}
@@ -0,0 +1,191 @@
+//! Converting to and from PostScript and EPS, for print workflows that
+//! still use PostScript.
+
+use std::{fs::File, io::Read, path::Path, process::Command};
+
+use crate::{
+    find_tool, gs_pdfwrite, gs_pdfwrite_command, page_count, run_tool_writing, validate_input_file,
+    CompressPreset, PageSelection, PdfError, Plan, Result, Tool,
+};
+
+/// Options for [`to_ps`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToPsOptions {
+    /// The pages to export.
+    pub pages: PageSelection,
+    /// Write Encapsulated PostScript (one page, with a bounding box) rather
+    /// than a PostScript document.
+    pub eps: bool,
+}
+
+impl Default for ToPsOptions {
+    fn default() -> Self {
+        Self {
+            pages: PageSelection::All,
+            eps: false,
+        }
+    }
+}
+
+/// Convert the selected pages of `input` to PostScript level 2, or with
+/// `options.eps` to EPS, written to `output` (with Ghostscript's
+/// `ps2write` or `eps2write`). EPS holds a single page, so it needs a
+/// selection of one page (or a one-page document).
+pub fn to_ps(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &ToPsOptions,
+) -> Result<()> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    let (first, last) = page_range(input, options)?;
+    run_tool_writing(
+        Tool::Ghostscript,
+        gs_command(input, output, first, last, options.eps)?,
+        output,
+    )
+}
+
+/// What [`to_ps`] would run.
+pub fn to_ps_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &ToPsOptions,
+) -> Result<Plan> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    let (first, last) = page_range(input, options)?;
+    Ok(Plan::new()
+        .run(
+            Tool::Ghostscript,
+            &gs_command(input, output, first, last, options.eps)?,
+        )
+        .write(output))
+}
+
+/// The first and last page selected, checked against the document.
+fn page_range(input: &Path, options: &ToPsOptions) -> Result<(u32, u32)> {
+    validate_input_file(input)?;
+    let pages = page_count(input)?;
+    let (first, last) = match options.pages {
+        PageSelection::All => (1, pages),
+        PageSelection::Range { start, end } => (start, end),
+        PageSelection::From { start } => (start, pages),
+    };
+    if first > pages || last > pages {
+        return Err(PdfError::InvalidArgument(format!(
+            "pages {first}-{last} are out of range (document has {pages} pages)"
+        )));
+    }
+    if options.eps && first != last {
+        return Err(PdfError::InvalidArgument(format!(
+            "EPS holds a single page, but pages {first}-{last} are selected; select one page"
+        )));
+    }
+    Ok((first, last))
+}
+
+fn gs_command(input: &Path, output: &Path, first: u32, last: u32, eps: bool) -> Result<Command> {
+    let mut cmd = Command::new(find_tool(Tool::Ghostscript)?);
+    cmd.arg(if eps {
+        "-sDEVICE=eps2write"
+    } else {
+        "-sDEVICE=ps2write"
+    })
+    .arg("-dNOPAUSE")
+    .arg("-dBATCH")
+    .arg("-dSAFER")
+    .arg(format!("-dFirstPage={first}"))
+    .arg(format!("-dLastPage={last}"))
+    .arg(format!("-sOutputFile={}", output.display()))
+    .arg(input.as_os_str());
+    Ok(cmd)
+}
+
+/// Convert the PostScript or EPS file `input` to PDF with Ghostscript, like
+/// `ps2pdf`, using `preset` as [`compress`](crate::compress) does. An EPS
+/// file's page is cropped to its bounding box.
+pub fn from_ps(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    preset: CompressPreset,
+) -> Result<()> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    let extra = from_ps_args(input)?;
+    gs_pdfwrite(input, output, Some(preset), extra)
+}
+
+/// What [`from_ps`] would run.
+pub fn from_ps_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    preset: CompressPreset,
+) -> Result<Plan> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    let extra = from_ps_args(input)?;
+    let cmd = gs_pdfwrite_command(input, output, Some(preset), extra)?;
+    Ok(Plan::new().run(Tool::Ghostscript, &cmd).write(output))
+}
+
+/// Checks that `input` is PostScript, and returns the extra Ghostscript
+/// arguments it needs.
+fn from_ps_args(input: &Path) -> Result<&'static [&'static str]> {
+    validate_input_file(input)?;
+    let mut head = Vec::new();
+    File::open(input)?.take(64).read_to_end(&mut head)?;
+    match postscript_kind(&head) {
+        Some(PostScriptKind::Eps) => Ok(&["-dEPSCrop"]),
+        Some(PostScriptKind::Document) => Ok(&[]),
+        None => Err(PdfError::InvalidArgument(format!(
+            "{} is not a PostScript file (it does not start with %!)",
+            input.display()
+        ))),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PostScriptKind {
+    Document,
+    Eps,
+}
+
+/// What the first bytes of a file say it is: PostScript starts with `%!`,
+/// EPS says so on that line (`%!PS-Adobe-3.0 EPSF-3.0`), and DOS EPS files
+/// start with a binary header.
+fn postscript_kind(head: &[u8]) -> Option<PostScriptKind> {
+    if head.starts_with(&[0xC5, 0xD0, 0xD3, 0xC6]) {
+        return Some(PostScriptKind::Eps);
+    }
+    if !head.starts_with(b"%!") {
+        return None;
+    }
+    let line = head
+        .split(|&b| b == b'\n' || b == b'\r')
+        .next()
+        .unwrap_or_default();
+    if line.windows(4).any(|w| w == b"EPSF") {
+        Some(PostScriptKind::Eps)
+    } else {
+        Some(PostScriptKind::Document)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn postscript_is_recognized_by_its_header() {
+        assert_eq!(
+            postscript_kind(b"%!PS-Adobe-3.0\n%%Pages: 2\n"),
+            Some(PostScriptKind::Document)
+        );
+        assert_eq!(
+            postscript_kind(b"%!PS-Adobe-3.0 EPSF-3.0\r\n%%BoundingBox: 0 0 10 10"),
+            Some(PostScriptKind::Eps)
+        );
+        assert_eq!(
+            postscript_kind(&[0xC5, 0xD0, 0xD3, 0xC6, 0x20]),
+            Some(PostScriptKind::Eps)
+        );
+        assert_eq!(postscript_kind(b"%PDF-1.7\n"), None);
+    }
+}
@@ -0,0 +1,243 @@
+//! Prepress: bleed, trim, and printer's marks for pages going to a print
+//! shop.
+
+use std::{fmt::Write as _, path::Path};
+
+use crate::{
+    geometry::{self, Rect},
+    load_document, save_document, stamp, validate_input_file, PdfError, Plan, Result,
+};
+
+/// Length of crop marks in points.
+const CROP_MARK_LENGTH: f32 = 18.0;
+/// Minimum gap between the trim corner and the start of a crop mark.
+const CROP_MARK_MIN_OFFSET: f32 = 6.0;
+
+/// Options for [`add_bleed`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BleedOptions {
+    /// Bleed in points added around the trim box on every side.
+    pub bleed: f32,
+    /// Draw crop marks at the corners, outside the bleed area.
+    pub crop_marks: bool,
+    /// Draw registration targets at the middle of each edge, outside the
+    /// bleed area.
+    pub registration_marks: bool,
+}
+
+/// Set the `TrimBox` and `BleedBox` of every page, enlarge its media box
+/// to hold the bleed and any marks, and draw the marks (pure Rust).
+///
+/// The trim box is the page's existing `TrimBox`, or its crop box if it has
+/// none. Content is not moved or scaled; artwork meant to bleed must
+/// already extend past the trim box.
+pub fn add_bleed(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &BleedOptions,
+) -> Result<()> {
+    let input = input.as_ref();
+    validate_bleed(options)?;
+    let mut doc = load_document(input)?;
+    apply_bleed(&mut doc, options)?;
+    save_document(&mut doc, output.as_ref())
+}
+
+/// What [`add_bleed`] would do.
+pub fn add_bleed_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &BleedOptions,
+) -> Result<Plan> {
+    validate_input_file(input.as_ref())?;
+    validate_bleed(options)?;
+    Ok(bleed_plan(Plan::new(), options).write(output.as_ref()))
+}
+
+/// The native steps of [`apply_bleed`], added to `plan`.
+pub(crate) fn bleed_plan(plan: Plan, options: &BleedOptions) -> Plan {
+    let mut plan = plan.native(format!(
+        "set TrimBox and BleedBox ({} pt bleed)",
+        options.bleed
+    ));
+    if options.crop_marks {
+        plan = plan.native("draw crop marks");
+    }
+    if options.registration_marks {
+        plan = plan.native("draw registration marks");
+    }
+    plan
+}
+
+pub(crate) fn validate_bleed(options: &BleedOptions) -> Result<()> {
+    if options.bleed < 0.0 || !options.bleed.is_finite() {
+        return Err(PdfError::InvalidArgument(
+            "bleed must not be negative".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Add the bleed and marks of `options` to every page of `doc`.
+pub(crate) fn apply_bleed(doc: &mut lopdf::Document, options: &BleedOptions) -> Result<()> {
+    let mark_offset = options.bleed.max(CROP_MARK_MIN_OFFSET);
+    let slug = if options.crop_marks || options.registration_marks {
+        mark_offset + CROP_MARK_LENGTH + 2.0
+    } else {
+        options.bleed
+    };
+    for page_id in doc.get_pages().into_values() {
+        let trim = geometry::set_bleed_boxes(doc, page_id, options.bleed, slug)?;
+        let mut marks = Vec::new();
+        if options.crop_marks {
+            marks.extend(crop_marks_content(&trim, mark_offset, CROP_MARK_LENGTH));
+        }
+        if options.registration_marks {
+            marks.extend(registration_marks_content(
+                &trim,
+                mark_offset,
+                CROP_MARK_LENGTH,
+            ));
+        }
+        if !marks.is_empty() {
+            stamp::overlay_page_content(doc, page_id, &marks)?;
+        }
+    }
+    Ok(())
+}
+
+/// Content stream drawing crop marks at the corners of `trim`.
+///
+/// Marks start `offset` points away from the trim corner (so they stay clear
+/// of any bleed) and are `length` points long, stroked in registration color.
+fn crop_marks_content(trim: &Rect, offset: f32, length: f32) -> Vec<u8> {
+    let mut out = String::from("q\n0.25 w\n1 1 1 1 K\n");
+    for (x, sx) in [(trim.llx, -1.0), (trim.urx, 1.0)] {
+        for (y, sy) in [(trim.lly, -1.0), (trim.ury, 1.0)] {
+            let _ = writeln!(
+                out,
+                "{:.2} {:.2} m {:.2} {:.2} l S",
+                x + sx * offset,
+                y,
+                x + sx * (offset + length),
+                y
+            );
+            let _ = writeln!(
+                out,
+                "{:.2} {:.2} m {:.2} {:.2} l S",
+                x,
+                y + sy * offset,
+                x,
+                y + sy * (offset + length)
+            );
+        }
+    }
+    out.push_str("Q\n");
+    out.into_bytes()
+}
+
+/// Content stream drawing a registration target (a circle with cross
+/// hairs) beside the middle of each edge of `trim`, centered `offset +
+/// length / 2` points out and `length` points across, in registration
+/// color.
+fn registration_marks_content(trim: &Rect, offset: f32, length: f32) -> Vec<u8> {
+    // Control point distance for a quarter circle drawn as a Bézier curve.
+    const KAPPA: f32 = 0.552_284_8;
+
+    let distance = offset + length / 2.0;
+    let (mid_x, mid_y) = (
+        f32::midpoint(trim.llx, trim.urx),
+        f32::midpoint(trim.lly, trim.ury),
+    );
+    let centers = [
+        (mid_x, trim.ury + distance),
+        (mid_x, trim.lly - distance),
+        (trim.llx - distance, mid_y),
+        (trim.urx + distance, mid_y),
+    ];
+    let (half, r) = (length / 2.0, length / 4.0);
+    let k = r * KAPPA;
+    let mut out = String::from("q\n0.25 w\n1 1 1 1 K\n");
+    for (x, y) in centers {
+        let _ = writeln!(out, "{:.2} {y:.2} m {:.2} {y:.2} l S", x - half, x + half);
+        let _ = writeln!(out, "{x:.2} {:.2} m {x:.2} {:.2} l S", y - half, y + half);
+        let quarters = [
+            [x + r, y + k, x + k, y + r, x, y + r],
+            [x - k, y + r, x - r, y + k, x - r, y],
+            [x - r, y - k, x - k, y - r, x, y - r],
+            [x + k, y - r, x + r, y - k, x + r, y],
+        ];
+        let _ = writeln!(out, "{:.2} {y:.2} m", x + r);
+        for [x1, y1, x2, y2, x3, y3] in quarters {
+            let _ = writeln!(out, "{x1:.2} {y1:.2} {x2:.2} {y2:.2} {x3:.2} {y3:.2} c");
+        }
+        out.push_str("S\n");
+    }
+    out.push_str("Q\n");
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::{Dictionary, Document, Object};
+
+    use super::*;
+    use crate::PageBox;
+
+    #[test]
+    fn bleed_grows_the_page_around_the_trim_box_once() -> Result<()> {
+        let mut doc = Document::with_version("1.4");
+        let page_id = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Page".to_vec())),
+            ("MediaBox", Rect::from_size(600.0, 800.0).to_object()),
+            (
+                "CropBox",
+                Rect {
+                    llx: 10.0,
+                    lly: 10.0,
+                    urx: 590.0,
+                    ury: 790.0,
+                }
+                .to_object(),
+            ),
+        ]));
+        let pages = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Pages".to_vec())),
+            ("Kids", Object::Array(vec![Object::Reference(page_id)])),
+            ("Count", Object::Integer(1)),
+        ]));
+        if let Ok(page) = doc.get_dictionary_mut(page_id) {
+            page.set("Parent", Object::Reference(pages));
+        }
+        let catalog = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Catalog".to_vec())),
+            ("Pages", Object::Reference(pages)),
+        ]));
+        doc.trailer.set("Root", Object::Reference(catalog));
+
+        let options = BleedOptions {
+            bleed: 9.0,
+            crop_marks: true,
+            registration_marks: true,
+        };
+        apply_bleed(&mut doc, &options)?;
+        apply_bleed(&mut doc, &options)?;
+
+        let trim = geometry::effective_page_box(&doc, page_id, PageBox::Trim);
+        let bleed = geometry::effective_page_box(&doc, page_id, PageBox::Bleed);
+        let media = geometry::effective_page_box(&doc, page_id, PageBox::Media);
+        assert_eq!((trim.llx, trim.ury), (10.0, 790.0));
+        assert_eq!((bleed.llx, bleed.ury), (1.0, 799.0));
+        let slug = 9.0 + CROP_MARK_LENGTH + 2.0;
+        assert_eq!((media.llx, media.ury), (10.0 - slug, 790.0 + slug));
+
+        let content = doc
+            .get_page_content(page_id)
+            .map_err(|e| PdfError::InvalidArgument(e.to_string()))?;
+        let text = String::from_utf8_lossy(&content);
+        // Corner marks start clear of the bleed.
+        assert!(text.contains("1.00 10.00 m -17.00 10.00 l S"), "{text}");
+        assert!(text.contains(" c\n"));
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+//! Printing: handing a PDF to the system's print spooler, with CUPS `lp`
+//! (or `lpr`) on Unix and `SumatraPDF` on Windows.
+
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    find_tool, page_count, run_tool_capture, validate_input_file, PageSelection, PdfError, Plan,
+    Result, Tool,
+};
+
+/// Options for [`print`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrintOptions {
+    /// The printer (queue) to print on; `None` uses the default printer.
+    pub printer: Option<String>,
+    /// Number of copies.
+    pub copies: u32,
+    /// The pages to print.
+    pub pages: PageSelection,
+    /// Print on both sides of the paper (flipped on the long edge); `false`
+    /// leaves the printer's setting alone.
+    pub duplex: bool,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self {
+            printer: None,
+            copies: 1,
+            pages: PageSelection::All,
+            duplex: false,
+        }
+    }
+}
+
+/// Send `input` to a printer (with `lp` or `lpr` on Unix, `SumatraPDF` on
+/// Windows). Returns the print job's id when the spooler reports one, as
+/// `lp` does.
+pub fn print(input: impl AsRef<Path>, options: &PrintOptions) -> Result<Option<String>> {
+    let input = input.as_ref();
+    let (tool, cmd) = command(input, options)?;
+    let output = run_tool_capture(tool, cmd)?;
+    Ok(job_id(&output))
+}
+
+/// What [`print`] would run.
+pub fn print_plan(input: impl AsRef<Path>, options: &PrintOptions) -> Result<Plan> {
+    let (tool, cmd) = command(input.as_ref(), options)?;
+    Ok(Plan::new().run(tool, &cmd))
+}
+
+/// The spoolers [`print`] drives, by their command-line conventions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Spooler {
+    Lp,
+    Lpr,
+    Sumatra,
+}
+
+fn command(input: &Path, options: &PrintOptions) -> Result<(Tool, Command)> {
+    let pages = page_range(input, options)?;
+    let (tool, exe, spooler) = if cfg!(windows) {
+        let exe = find_tool(Tool::Sumatrapdf)?;
+        (Tool::Sumatrapdf, exe, Spooler::Sumatra)
+    } else {
+        let exe = find_tool(Tool::Lp)?;
+        let spooler = if exe.file_stem().is_some_and(|stem| stem == "lpr") {
+            Spooler::Lpr
+        } else {
+            Spooler::Lp
+        };
+        (Tool::Lp, exe, spooler)
+    };
+    let mut cmd = Command::new(exe);
+    cmd.args(args(spooler, input, options, pages.as_deref()));
+    Ok((tool, cmd))
+}
+
+/// The selected pages as `first-last`, checked against the document; `None`
+/// when every page is printed.
+fn page_range(input: &Path, options: &PrintOptions) -> Result<Option<String>> {
+    validate_input_file(input)?;
+    if options.copies == 0 {
+        return Err(PdfError::InvalidArgument(
+            "copies must be at least 1".to_string(),
+        ));
+    }
+    if options.printer.as_deref().is_some_and(str::is_empty) {
+        return Err(PdfError::InvalidArgument(
+            "the printer name is empty".to_string(),
+        ));
+    }
+    let pages = page_count(input)?;
+    let (first, last) = match options.pages {
+        PageSelection::All => return Ok(None),
+        PageSelection::Range { start, end } => (start, end),
+        PageSelection::From { start } => (start, pages),
+    };
+    if first > pages || last > pages {
+        return Err(PdfError::InvalidArgument(format!(
+            "pages {first}-{last} are out of range (document has {pages} pages)"
+        )));
+    }
+    Ok(Some(format!("{first}-{last}")))
+}
+
+fn args(
+    spooler: Spooler,
+    input: &Path,
+    options: &PrintOptions,
+    pages: Option<&str>,
+) -> Vec<OsString> {
+    let mut args: Vec<OsString> = Vec::new();
+    let mut push = |arg: String| args.push(arg.into());
+    match spooler {
+        Spooler::Lp => {
+            if let Some(printer) = &options.printer {
+                push("-d".to_string());
+                push(printer.clone());
+            }
+            push("-n".to_string());
+            push(options.copies.to_string());
+            if let Some(pages) = pages {
+                push("-P".to_string());
+                push(pages.to_string());
+            }
+            if options.duplex {
+                push("-o".to_string());
+                push("sides=two-sided-long-edge".to_string());
+            }
+            push("--".to_string());
+        }
+        Spooler::Lpr => {
+            if let Some(printer) = &options.printer {
+                push("-P".to_string());
+                push(printer.clone());
+            }
+            push("-#".to_string());
+            push(options.copies.to_string());
+            if let Some(pages) = pages {
+                push("-o".to_string());
+                push(format!("page-ranges={pages}"));
+            }
+            if options.duplex {
+                push("-o".to_string());
+                push("sides=two-sided-long-edge".to_string());
+            }
+        }
+        Spooler::Sumatra => {
+            match &options.printer {
+                Some(printer) => {
+                    push("-print-to".to_string());
+                    push(printer.clone());
+                }
+                None => push("-print-to-default".to_string()),
+            }
+            let mut settings: Vec<String> = pages.map(ToString::to_string).into_iter().collect();
+            settings.push(format!("{}x", options.copies));
+            if options.duplex {
+                settings.push("duplexlong".to_string());
+            }
+            push("-print-settings".to_string());
+            push(settings.join(","));
+            push("-silent".to_string());
+        }
+    }
+    args.push(PathBuf::from(input).into_os_string());
+    args
+}
+
+/// The job id in `lp`'s `request id is <id> (1 file(s))` report.
+fn job_id(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let rest = line.trim().strip_prefix("request id is ")?;
+        let id = rest.split_whitespace().next()?;
+        Some(id.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spooler_arguments() {
+        let options = PrintOptions {
+            printer: Some("OfficeLaser".to_string()),
+            copies: 2,
+            pages: PageSelection::Range { start: 1, end: 4 },
+            duplex: true,
+        };
+        let input = Path::new("report.pdf");
+        let args = |spooler| -> Vec<String> {
+            args(spooler, input, &options, Some("1-4"))
+                .iter()
+                .map(|a| a.to_string_lossy().into_owned())
+                .collect()
+        };
+        assert_eq!(
+            args(Spooler::Lp),
+            [
+                "-d",
+                "OfficeLaser",
+                "-n",
+                "2",
+                "-P",
+                "1-4",
+                "-o",
+                "sides=two-sided-long-edge",
+                "--",
+                "report.pdf"
+            ]
+        );
+        assert_eq!(
+            args(Spooler::Lpr),
+            [
+                "-P",
+                "OfficeLaser",
+                "-#",
+                "2",
+                "-o",
+                "page-ranges=1-4",
+                "-o",
+                "sides=two-sided-long-edge",
+                "report.pdf"
+            ]
+        );
+        assert_eq!(
+            args(Spooler::Sumatra),
+            [
+                "-print-to",
+                "OfficeLaser",
+                "-print-settings",
+                "1-4,2x,duplexlong",
+                "-silent",
+                "report.pdf"
+            ]
+        );
+        assert_eq!(
+            job_id("request id is OfficeLaser-12 (1 file(s))\n").as_deref(),
+            Some("OfficeLaser-12")
+        );
+    }
+}
@@ -0,0 +1,1515 @@
+//! Persona-specific composite operations ("profiles").
+//!
+//! A profile chains several primitive operations into one call and checks
+//! the result, returning a report instead of leaving users to script the
+//! steps by hand.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    a11y, find_tool, flatten_annotations, flatten_annotations_command, fonts,
+    geometry::{self, PageBox, PaperSize},
+    gs_pdfwrite, gs_pdfwrite_command, linearize, linearize_command, load_document, metadata,
+    plan::{self, Plan},
+    prepress, run_tool, save_document,
+    tempspace::TempSpace,
+    validate_input_file, ColorSpace, CompressPreset, PdfError, Result, Tool,
+};
+
+/// Options for [`court_ready`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CourtReadyOptions {
+    /// Maximum output size in bytes, if the court enforces one.
+    pub max_bytes: Option<u64>,
+    /// Highest-quality preset to try first. Lower-quality presets are tried
+    /// in turn until the output fits `max_bytes`.
+    pub preset: CompressPreset,
+}
+
+impl Default for CourtReadyOptions {
+    fn default() -> Self {
+        Self {
+            max_bytes: None,
+            preset: CompressPreset::Printer,
+        }
+    }
+}
+
+/// Outcome of a single compliance check in a [`CourtReadyReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComplianceCheck {
+    /// Short, stable identifier (e.g. `size-limit`).
+    pub name: &'static str,
+    /// Whether the output satisfies the check.
+    pub passed: bool,
+    /// Human-readable detail.
+    pub detail: String,
+}
+
+/// Result of [`court_ready`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CourtReadyReport {
+    /// Input file size in bytes.
+    pub input_bytes: u64,
+    /// Output file size in bytes.
+    pub output_bytes: u64,
+    /// Preset used for the final distillation.
+    pub preset: CompressPreset,
+    /// Number of JavaScript actions removed.
+    pub javascript_removed: usize,
+    /// Checks run against the final output.
+    pub checks: Vec<ComplianceCheck>,
+}
+
+impl CourtReadyReport {
+    /// `true` if every check passed.
+    #[must_use]
+    pub fn is_compliant(&self) -> bool {
+        self.checks.iter().all(|c| c.passed)
+    }
+}
+
+/// Presets in descending quality order; target-size compression walks this
+/// list starting at the requested preset.
+const PRESET_LADDER: [CompressPreset; 5] = [
+    CompressPreset::Prepress,
+    CompressPreset::Printer,
+    CompressPreset::Default,
+    CompressPreset::Ebook,
+    CompressPreset::Screen,
+];
+
+/// Prepare a PDF for electronic court filing (requires qpdf and ghostscript).
+///
+/// Steps: flatten forms and annotations, remove JavaScript, re-distill with
+/// all fonts embedded, and linearize. If `max_bytes` is set, progressively
+/// lower-quality presets are tried until the output fits.
+///
+/// The output is always written; callers should consult
+/// [`CourtReadyReport::is_compliant`] to decide whether it can be filed.
+pub fn court_ready(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &CourtReadyOptions,
+) -> Result<CourtReadyReport> {
+    let input = input.as_ref();
+    let output = output.as_ref();
+    validate_input_file(input)?;
+
+    let scratch = TempSpace::new("court-ready")?;
+    let flattened = scratch.file("flattened.pdf");
+    let scrubbed = scratch.file("scrubbed.pdf");
+    let distilled = scratch.file("distilled.pdf");
+    let candidate = scratch.file("candidate.pdf");
+
+    flatten_annotations(input, &flattened)?;
+
+    let mut doc = load_document(&flattened)?;
+    let javascript_removed = strip_javascript(&mut doc);
+    save_document(&mut doc, &scrubbed)?;
+
+    let start = PRESET_LADDER
+        .iter()
+        .position(|p| *p == options.preset)
+        .unwrap_or(0);
+    let mut preset = options.preset;
+    let mut output_bytes = 0;
+    for &p in &PRESET_LADDER[start..] {
+        preset = p;
+        gs_pdfwrite(&scrubbed, &distilled, Some(p), &fonts::EMBED_ALL_FONTS_ARGS)?;
+        linearize(&distilled, &candidate)?;
+        output_bytes = fs::metadata(&candidate)?.len();
+        if options.max_bytes.is_none_or(|max| output_bytes <= max) {
+            break;
+        }
+    }
+    fs::copy(&candidate, output)?;
+
+    let final_doc = load_document(output)?;
+    let mut checks = Vec::new();
+    if let Some(max) = options.max_bytes {
+        checks.push(ComplianceCheck {
+            name: "size-limit",
+            passed: output_bytes <= max,
+            detail: format!("{output_bytes} bytes (limit {max})"),
+        });
+    }
+    let remaining_js = count_javascript(&final_doc);
+    checks.push(ComplianceCheck {
+        name: "no-javascript",
+        passed: remaining_js == 0,
+        detail: format!("{remaining_js} JavaScript action(s) remaining"),
+    });
+    let remaining_annots = count_flattenable_annotations(&final_doc);
+    checks.push(ComplianceCheck {
+        name: "flattened",
+        passed: remaining_annots == 0,
+        detail: format!("{remaining_annots} form/markup annotation(s) remaining"),
+    });
+    checks.push(ComplianceCheck {
+        name: "linearized",
+        passed: is_linearized(&final_doc),
+        detail: "fast web view".to_string(),
+    });
+
+    Ok(CourtReadyReport {
+        input_bytes: fs::metadata(input)?.len(),
+        output_bytes,
+        preset,
+        javascript_removed,
+        checks,
+    })
+}
+
+/// What [`court_ready`] would run.
+pub fn court_ready_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &CourtReadyOptions,
+) -> Result<Plan> {
+    let input = input.as_ref();
+    validate_input_file(input)?;
+    let flattened = plan::temp_path("flattened.pdf");
+    let scrubbed = plan::temp_path("scrubbed.pdf");
+    let distilled = plan::temp_path("distilled.pdf");
+    let candidate = plan::temp_path("candidate.pdf");
+
+    let mut plan = Plan::new()
+        .run(Tool::Qpdf, &flatten_annotations_command(input, &flattened)?)
+        .native(format!("remove JavaScript, writing {}", scrubbed.display()));
+    let distill = gs_pdfwrite_command(
+        &scrubbed,
+        &distilled,
+        Some(options.preset),
+        &fonts::EMBED_ALL_FONTS_ARGS,
+    )?;
+    plan = plan
+        .run(Tool::Ghostscript, &distill)
+        .run(Tool::Qpdf, &linearize_command(&distilled, &candidate)?);
+    if let Some(max) = options.max_bytes {
+        let start = PRESET_LADDER
+            .iter()
+            .position(|p| *p == options.preset)
+            .unwrap_or(0);
+        let lower: Vec<String> = PRESET_LADDER[start + 1..]
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        if !lower.is_empty() {
+            plan = plan.native(format!(
+                "while larger than {max} bytes, repeat with preset {}",
+                lower.join(", then ")
+            ));
+        }
+    }
+    Ok(plan.write(output.as_ref()))
+}
+
+/// Options for [`print_ready`].
+#[allow(clippy::struct_excessive_bools)] // independent prepress steps
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrintReadyOptions {
+    /// Finished (trim) page size; content is scaled to fit.
+    pub paper: PaperSize,
+    /// Bleed in points added around the trim box on every side.
+    pub bleed: f32,
+    /// Convert all content to CMYK.
+    pub cmyk: bool,
+    /// Flatten transparency (distills at PDF 1.3).
+    pub flatten_transparency: bool,
+    /// Draw crop marks outside the bleed area.
+    pub crop_marks: bool,
+    /// Draw registration marks outside the bleed area.
+    pub registration_marks: bool,
+}
+
+impl Default for PrintReadyOptions {
+    fn default() -> Self {
+        Self {
+            paper: PaperSize::A4,
+            bleed: 0.0,
+            cmyk: true,
+            flatten_transparency: true,
+            crop_marks: false,
+            registration_marks: false,
+        }
+    }
+}
+
+/// Result of [`print_ready`]: a preflight of the produced file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrintReadyReport {
+    /// Number of pages in the output.
+    pub pages: u32,
+    /// Preflight checks run against the output.
+    pub checks: Vec<ComplianceCheck>,
+}
+
+impl PrintReadyReport {
+    /// `true` if every check passed.
+    #[must_use]
+    pub fn is_compliant(&self) -> bool {
+        self.checks.iter().all(|c| c.passed)
+    }
+}
+
+/// Normalize a PDF for a print shop (requires ghostscript).
+///
+/// Steps: scale pages to `paper`, optionally convert to CMYK, embed all fonts,
+/// flatten transparency, then natively add bleed (`TrimBox`/`BleedBox`) and
+/// crop marks. A preflight of the output is returned; the output is always
+/// written.
+pub fn print_ready(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &PrintReadyOptions,
+) -> Result<PrintReadyReport> {
+    let input = input.as_ref();
+    let output = output.as_ref();
+    validate_input_file(input)?;
+    prepress::validate_bleed(&bleed_options(options))?;
+
+    let scratch = TempSpace::new("print-ready")?;
+    let distilled = scratch.file("distilled.pdf");
+
+    let args = print_ready_gs_args(options);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    gs_pdfwrite(input, &distilled, None, &args)?;
+
+    let mut doc = load_document(&distilled)?;
+    prepress::apply_bleed(&mut doc, &bleed_options(options))?;
+    save_document(&mut doc, output)?;
+
+    let final_doc = load_document(output)?;
+    Ok(PrintReadyReport {
+        pages: u32::try_from(final_doc.get_pages().len()).unwrap_or(u32::MAX),
+        checks: preflight_print(&final_doc, options),
+    })
+}
+
+/// What [`print_ready`] would run.
+pub fn print_ready_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &PrintReadyOptions,
+) -> Result<Plan> {
+    validate_input_file(input.as_ref())?;
+    Ok(print_ready_steps(input.as_ref(), options)?.write(output.as_ref()))
+}
+
+/// The steps of [`print_ready_plan`], without checking that `input` exists:
+/// in a pipeline it is the output of an earlier step.
+pub(crate) fn print_ready_steps(input: &Path, options: &PrintReadyOptions) -> Result<Plan> {
+    prepress::validate_bleed(&bleed_options(options))?;
+
+    let distilled = plan::temp_path("distilled.pdf");
+    let args = print_ready_gs_args(options);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let cmd = gs_pdfwrite_command(input, &distilled, None, &args)?;
+    let plan = Plan::new().run(Tool::Ghostscript, &cmd);
+    Ok(prepress::bleed_plan(plan, &bleed_options(options)))
+}
+
+fn bleed_options(options: &PrintReadyOptions) -> prepress::BleedOptions {
+    prepress::BleedOptions {
+        bleed: options.bleed,
+        crop_marks: options.crop_marks,
+        registration_marks: options.registration_marks,
+    }
+}
+
+fn print_ready_gs_args(options: &PrintReadyOptions) -> Vec<String> {
+    let mut args = vec![
+        format!("-dDEVICEWIDTHPOINTS={}", options.paper.width),
+        format!("-dDEVICEHEIGHTPOINTS={}", options.paper.height),
+        "-dFIXEDMEDIA".to_string(),
+        "-dPDFFitPage".to_string(),
+    ];
+    args.extend(fonts::EMBED_ALL_FONTS_ARGS.map(str::to_string));
+    if options.cmyk {
+        args.extend(ColorSpace::Cmyk.as_gs_args().map(str::to_string));
+    }
+    if options.flatten_transparency {
+        // pdfwrite flattens transparency when targeting PDF 1.3.
+        args.push("-dCompatibilityLevel=1.3".to_string());
+    }
+    args
+}
+
+fn preflight_print(doc: &lopdf::Document, options: &PrintReadyOptions) -> Vec<ComplianceCheck> {
+    const TOLERANCE: f32 = 1.0;
+
+    let mut wrong_size = Vec::new();
+    let mut short_bleed = Vec::new();
+    for (page_no, page_id) in doc.get_pages() {
+        let trim = geometry::effective_page_box(doc, page_id, PageBox::Trim);
+        if (trim.width() - options.paper.width).abs() > TOLERANCE
+            || (trim.height() - options.paper.height).abs() > TOLERANCE
+        {
+            wrong_size.push(page_no);
+        }
+        let bleed = geometry::effective_page_box(doc, page_id, PageBox::Bleed);
+        if bleed.width() + TOLERANCE < trim.width() + 2.0 * options.bleed {
+            short_bleed.push(page_no);
+        }
+    }
+
+    let mut checks = vec![ComplianceCheck {
+        name: "page-size",
+        passed: wrong_size.is_empty(),
+        detail: if wrong_size.is_empty() {
+            format!("all trim boxes are {}", options.paper)
+        } else {
+            format!("pages not {}: {wrong_size:?}", options.paper)
+        },
+    }];
+    if options.bleed > 0.0 {
+        checks.push(ComplianceCheck {
+            name: "bleed",
+            passed: short_bleed.is_empty(),
+            detail: format!("{:.1}pt required", options.bleed),
+        });
+    }
+
+    checks.push(fonts_embedded_check(doc));
+
+    if options.flatten_transparency {
+        let transparent = count_transparency(doc);
+        checks.push(ComplianceCheck {
+            name: "no-transparency",
+            passed: transparent == 0,
+            detail: format!("{transparent} transparency group(s)/soft mask(s)"),
+        });
+    }
+    if options.cmyk {
+        let rgb = count_color_space_refs(doc, b"DeviceRGB");
+        checks.push(ComplianceCheck {
+            name: "cmyk",
+            passed: rgb == 0,
+            detail: format!("{rgb} DeviceRGB color space reference(s)"),
+        });
+    }
+    checks
+}
+
+fn fonts_embedded_check(doc: &lopdf::Document) -> ComplianceCheck {
+    let unembedded: Vec<String> = fonts::collect_fonts(doc)
+        .into_iter()
+        .filter(|f| !f.embedded)
+        .map(|f| f.name)
+        .collect();
+    ComplianceCheck {
+        name: "fonts-embedded",
+        passed: unembedded.is_empty(),
+        detail: if unembedded.is_empty() {
+            "all fonts embedded".to_string()
+        } else {
+            format!("not embedded: {}", unembedded.join(", "))
+        },
+    }
+}
+
+fn count_transparency(doc: &lopdf::Document) -> usize {
+    doc.objects
+        .values()
+        .filter_map(|obj| match obj {
+            lopdf::Object::Dictionary(d) => Some(d),
+            lopdf::Object::Stream(s) => Some(&s.dict),
+            _ => None,
+        })
+        .filter(|d| {
+            let group = fonts::resolve_dict(doc, d.get(b"Group").ok()).is_some_and(|g| {
+                g.get(b"S")
+                    .and_then(lopdf::Object::as_name)
+                    .is_ok_and(|s| s == b"Transparency")
+            });
+            let smask = d
+                .get(b"SMask")
+                .is_ok_and(|m| m.as_name().map_or(true, |n| n != b"None"));
+            group || smask
+        })
+        .count()
+}
+
+fn count_color_space_refs(doc: &lopdf::Document, space: &[u8]) -> usize {
+    let is_space = |o: &lopdf::Object| o.as_name().is_ok_and(|n| n == space);
+    doc.objects
+        .values()
+        .filter_map(|obj| match obj {
+            lopdf::Object::Dictionary(d) => Some(d),
+            lopdf::Object::Stream(s) => Some(&s.dict),
+            _ => None,
+        })
+        .filter_map(|d| d.get(b"ColorSpace").ok())
+        .map(|cs| match cs {
+            lopdf::Object::Dictionary(named) => named.iter().filter(|(_, v)| is_space(v)).count(),
+            other => usize::from(is_space(other)),
+        })
+        .sum()
+}
+
+/// A PDF/X standard for [`to_pdfx`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PdfxStandard {
+    /// PDF/X-1a:2001: CMYK only, all fonts embedded, transparency flattened
+    /// (PDF 1.3).
+    X1a,
+    /// PDF/X-4: CMYK with live transparency (PDF 1.6).
+    X4,
+}
+
+impl PdfxStandard {
+    /// The version as recorded in the document (`GTS_PDFXVersion`), e.g.
+    /// `PDF/X-4`.
+    #[must_use]
+    pub fn version(self) -> &'static str {
+        match self {
+            Self::X1a => "PDF/X-1a:2001",
+            Self::X4 => "PDF/X-4",
+        }
+    }
+
+    fn compatibility_arg(self) -> &'static str {
+        match self {
+            // pdfwrite flattens transparency when targeting PDF 1.3.
+            Self::X1a => "-dCompatibilityLevel=1.3",
+            Self::X4 => "-dCompatibilityLevel=1.6",
+        }
+    }
+}
+
+impl std::fmt::Display for PdfxStandard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.version())
+    }
+}
+
+/// Options for [`to_pdfx`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdfxOptions {
+    /// Standard to convert to.
+    pub standard: PdfxStandard,
+    /// CMYK ICC profile of the printing condition, as supplied by the print
+    /// vendor (e.g. ISO Coated v2 or `GRACoL`).
+    pub icc_profile: PathBuf,
+    /// Name of the printing condition (`OutputConditionIdentifier`), e.g.
+    /// `FOGRA39`; by default the profile's description.
+    pub condition: Option<String>,
+}
+
+/// Result of [`to_pdfx`]: a preflight of the produced file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdfxReport {
+    /// Number of pages in the output.
+    pub pages: u32,
+    /// Preflight checks run against the output.
+    pub checks: Vec<ComplianceCheck>,
+}
+
+impl PdfxReport {
+    /// `true` if every check passed.
+    #[must_use]
+    pub fn is_compliant(&self) -> bool {
+        self.checks.iter().all(|c| c.passed)
+    }
+}
+
+/// Convert a PDF to PDF/X for print delivery (requires ghostscript).
+///
+/// Steps: re-distill with all fonts embedded and all color converted to
+/// CMYK (flattening transparency for PDF/X-1a), then natively add the
+/// output intent with the ICC profile, a `TrimBox` on pages without one,
+/// and the PDF/X version in the document information (and XMP metadata,
+/// for PDF/X-4). A preflight of the output is returned; the output is
+/// always written.
+pub fn to_pdfx(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &PdfxOptions,
+) -> Result<PdfxReport> {
+    let input = input.as_ref();
+    let output = output.as_ref();
+    validate_input_file(input)?;
+    let profile = OutputProfile::read(&options.icc_profile)?;
+
+    let scratch = TempSpace::new("to-pdfx")?;
+    let distilled = scratch.file("distilled.pdf");
+    gs_pdfwrite(input, &distilled, None, &pdfx_gs_args(options.standard))?;
+
+    let mut doc = load_document(&distilled)?;
+    let title = metadata::info_entry(&doc, "Title")
+        .filter(|title| !title.trim().is_empty())
+        .or_else(|| input.file_stem().map(|s| s.to_string_lossy().to_string()))
+        .unwrap_or_default();
+    let condition = options.condition.as_deref().unwrap_or(&profile.description);
+    mark_pdfx(&mut doc, options.standard, &profile, condition, &title)?;
+    save_document(&mut doc, output)?;
+
+    let final_doc = load_document(output)?;
+    Ok(PdfxReport {
+        pages: u32::try_from(final_doc.get_pages().len()).unwrap_or(u32::MAX),
+        checks: preflight_pdfx(&final_doc, options.standard),
+    })
+}
+
+/// What [`to_pdfx`] would run.
+pub fn to_pdfx_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &PdfxOptions,
+) -> Result<Plan> {
+    let input = input.as_ref();
+    validate_input_file(input)?;
+    let profile = OutputProfile::read(&options.icc_profile)?;
+    let distilled = plan::temp_path("distilled.pdf");
+    let cmd = gs_pdfwrite_command(input, &distilled, None, &pdfx_gs_args(options.standard))?;
+    Ok(Plan::new()
+        .run(Tool::Ghostscript, &cmd)
+        .native(format!(
+            "add a {} output intent for {} ({}), and a TrimBox to pages without one",
+            options.standard,
+            options.condition.as_deref().unwrap_or(&profile.description),
+            options.icc_profile.display()
+        ))
+        .write(output.as_ref()))
+}
+
+fn pdfx_gs_args(standard: PdfxStandard) -> Vec<&'static str> {
+    let mut args = fonts::EMBED_ALL_FONTS_ARGS.to_vec();
+    args.extend(ColorSpace::Cmyk.as_gs_args());
+    args.push(standard.compatibility_arg());
+    args
+}
+
+/// A CMYK ICC profile for an output intent.
+struct OutputProfile {
+    data: Vec<u8>,
+    /// The profile's description, or `Custom` if it has none.
+    description: String,
+}
+
+impl OutputProfile {
+    fn read(path: &Path) -> Result<Self> {
+        validate_input_file(path)?;
+        Self::parse(fs::read(path)?)
+            .map_err(|why| PdfError::InvalidArgument(format!("{}: {why}", path.display())))
+    }
+
+    fn parse(data: Vec<u8>) -> std::result::Result<Self, String> {
+        if data.len() < 132 || data[36..40] != *b"acsp" {
+            return Err("not an ICC profile".to_string());
+        }
+        if data[16..20] != *b"CMYK" {
+            return Err(format!(
+                "a PDF/X output intent needs a CMYK profile, not {}",
+                String::from_utf8_lossy(&data[16..20]).trim()
+            ));
+        }
+        let description = icc_description(&data).unwrap_or_else(|| "Custom".to_string());
+        Ok(Self { data, description })
+    }
+}
+
+/// The text of an ICC profile's `desc` tag (`textDescriptionType` in
+/// version 2 profiles, the first record of a `multiLocalizedUnicodeType` in
+/// version 4).
+fn icc_description(icc: &[u8]) -> Option<String> {
+    let u32_at = |at: usize| -> Option<usize> {
+        let bytes = icc.get(at..at.checked_add(4)?)?;
+        usize::try_from(u32::from_be_bytes(bytes.try_into().ok()?)).ok()
+    };
+    let tags = u32_at(128)?;
+    let tag = (0..tags.min(1024)).find_map(|i| {
+        let entry = 132 + 12 * i;
+        if icc.get(entry..entry + 4)? == b"desc" {
+            u32_at(entry + 4)
+        } else {
+            None
+        }
+    })?;
+    let text = match icc.get(tag..tag.checked_add(4)?)? {
+        b"desc" => {
+            let len = u32_at(tag + 8)?;
+            let ascii = icc.get(tag + 12..(tag + 12).checked_add(len)?)?;
+            String::from_utf8_lossy(ascii)
+                .trim_end_matches('\0')
+                .to_string()
+        }
+        b"mluc" => {
+            let (len, offset) = (u32_at(tag + 20)?, u32_at(tag + 24)?);
+            let start = tag.checked_add(offset)?;
+            let units: Vec<u16> = icc
+                .get(start..start.checked_add(len)?)?
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => return None,
+    };
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Add what makes a distilled document PDF/X: the output intent, trim
+/// boxes, and the version and other required entries in the document
+/// information (and XMP metadata, which PDF/X-4 requires).
+fn mark_pdfx(
+    doc: &mut lopdf::Document,
+    standard: PdfxStandard,
+    profile: &OutputProfile,
+    condition: &str,
+    title: &str,
+) -> Result<()> {
+    for page_id in doc.get_pages().into_values() {
+        let trimmed = geometry::explicit_page_box(doc, page_id, PageBox::Trim).is_some()
+            || geometry::explicit_page_box(doc, page_id, PageBox::Art).is_some();
+        if !trimmed {
+            let crop = geometry::effective_page_box(doc, page_id, PageBox::Crop);
+            geometry::set_page_box(doc, page_id, PageBox::Trim, crop)?;
+        }
+    }
+
+    let mut icc = lopdf::Stream::new(
+        lopdf::Dictionary::from_iter([("N", lopdf::Object::Integer(4))]),
+        profile.data.clone(),
+    );
+    // Left uncompressed if it does not get smaller.
+    let _ = icc.compress();
+    let icc_id = doc.add_object(icc);
+    let intent = lopdf::Dictionary::from_iter([
+        ("Type", lopdf::Object::Name(b"OutputIntent".to_vec())),
+        ("S", lopdf::Object::Name(b"GTS_PDFX".to_vec())),
+        (
+            "OutputConditionIdentifier",
+            metadata::text_string(condition),
+        ),
+        ("Info", metadata::text_string(&profile.description)),
+        ("DestOutputProfile", lopdf::Object::Reference(icc_id)),
+    ]);
+    a11y::catalog_mut(doc)?.set(
+        "OutputIntents",
+        lopdf::Object::Array(vec![lopdf::Object::Dictionary(intent)]),
+    );
+
+    metadata::set_info_entry(doc, "Title", title)?;
+    metadata::set_info_entry(doc, "GTS_PDFXVersion", standard.version())?;
+    let info = metadata::info_dict_mut(doc)?;
+    match standard {
+        PdfxStandard::X1a => info.set(
+            "GTS_PDFXConformance",
+            metadata::text_string("PDF/X-1a:2001"),
+        ),
+        PdfxStandard::X4 => {
+            info.remove(b"GTS_PDFXConformance");
+        }
+    }
+    let trapped = info
+        .get(b"Trapped")
+        .and_then(lopdf::Object::as_name)
+        .is_ok_and(|name| name == b"True" || name == b"False");
+    if !trapped {
+        info.set("Trapped", lopdf::Object::Name(b"False".to_vec()));
+    }
+
+    if standard == PdfxStandard::X4 {
+        let xmp = lopdf::Stream::new(
+            lopdf::Dictionary::from_iter([
+                ("Type", lopdf::Object::Name(b"Metadata".to_vec())),
+                ("Subtype", lopdf::Object::Name(b"XML".to_vec())),
+            ]),
+            pdfx_xmp(doc, standard).into_bytes(),
+        );
+        let xmp_id = doc.add_object(xmp);
+        a11y::catalog_mut(doc)?.set("Metadata", xmp_id);
+    }
+    Ok(())
+}
+
+/// An XMP packet with the document information of `doc` and its PDF/X
+/// version.
+fn pdfx_xmp(doc: &lopdf::Document, standard: PdfxStandard) -> String {
+    use std::fmt::Write as _;
+
+    let escape = |s: &str| {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    };
+    let info = |key: &str| metadata::info_entry(doc, key);
+    let mut props = String::new();
+    if let Some(title) = info("Title") {
+        let _ = write!(
+            props,
+            "<dc:title><rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt></dc:title>",
+            escape(&title)
+        );
+    }
+    let dates = [
+        ("CreationDate", "xmp:CreateDate"),
+        ("ModDate", "xmp:ModifyDate"),
+        ("ModDate", "xmp:MetadataDate"),
+    ];
+    for (key, property) in dates {
+        if let Some(date) = info(key).as_deref().and_then(xmp_date) {
+            let _ = write!(props, "<{property}>{date}</{property}>");
+        }
+    }
+    if let Some(creator) = info("Creator") {
+        let _ = write!(
+            props,
+            "<xmp:CreatorTool>{}</xmp:CreatorTool>",
+            escape(&creator)
+        );
+    }
+    if let Some(producer) = info("Producer") {
+        let _ = write!(props, "<pdf:Producer>{}</pdf:Producer>", escape(&producer));
+    }
+    let ids = doc
+        .trailer
+        .get(b"ID")
+        .and_then(lopdf::Object::as_array)
+        .ok();
+    let uuid = |i: usize| match ids.and_then(|ids| ids.get(i)) {
+        Some(lopdf::Object::String(id, _)) if id.len() == 16 => {
+            let mut hex = String::new();
+            for byte in id {
+                let _ = write!(hex, "{byte:02x}");
+            }
+            Some(format!(
+                "uuid:{}-{}-{}-{}-{}",
+                &hex[..8],
+                &hex[8..12],
+                &hex[12..16],
+                &hex[16..20],
+                &hex[20..]
+            ))
+        }
+        _ => None,
+    };
+    if let (Some(document), Some(instance)) = (uuid(0), uuid(1)) {
+        let _ = write!(
+            props,
+            "<xmpMM:DocumentID>{document}</xmpMM:DocumentID>\
+             <xmpMM:InstanceID>{instance}</xmpMM:InstanceID>"
+        );
+    }
+    format!(
+        "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n\
+         <x:xmpmeta xmlns:x=\"adobe:ns:meta/\"><rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\
+         <rdf:Description rdf:about=\"\" \
+         xmlns:dc=\"http://purl.org/dc/elements/1.1/\" \
+         xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\" \
+         xmlns:xmpMM=\"http://ns.adobe.com/xap/1.0/mm/\" \
+         xmlns:pdf=\"http://ns.adobe.com/pdf/1.3/\" \
+         xmlns:pdfxid=\"http://www.npes.org/pdfx/ns/id/\">\
+         {props}<xmpMM:VersionID>1</xmpMM:VersionID><xmpMM:RenditionClass>default</xmpMM:RenditionClass>\
+         <pdf:Trapped>False</pdf:Trapped><pdfxid:GTS_PDFXVersion>{}</pdfxid:GTS_PDFXVersion>\
+         </rdf:Description></rdf:RDF></x:xmpmeta>\n<?xpacket end=\"w\"?>",
+        standard.version()
+    )
+}
+
+/// A PDF date (`D:20240131120000+01'00'`) as an XMP date
+/// (`2024-01-31T12:00:00+01:00`).
+fn xmp_date(date: &str) -> Option<String> {
+    let date = date.strip_prefix("D:").unwrap_or(date);
+    let digits = |at: usize, default: &'static str| match date.get(at..at + 2) {
+        Some(d) if d.bytes().all(|b| b.is_ascii_digit()) => Some(d),
+        Some(_) => None,
+        None => Some(default),
+    };
+    let year = date
+        .get(..4)
+        .filter(|y| y.bytes().all(|b| b.is_ascii_digit()))?;
+    let (month, day) = (digits(4, "01")?, digits(6, "01")?);
+    let (hour, minute, second) = (digits(8, "00")?, digits(10, "00")?, digits(12, "00")?);
+    let zone = match date.get(14..15) {
+        Some("Z") => "Z".to_string(),
+        Some(sign @ ("+" | "-")) => {
+            let hours = date.get(15..17)?;
+            let minutes = date.get(18..20).unwrap_or("00");
+            format!("{sign}{hours}:{minutes}")
+        }
+        _ => String::new(),
+    };
+    Some(format!(
+        "{year}-{month}-{day}T{hour}:{minute}:{second}{zone}"
+    ))
+}
+
+fn preflight_pdfx(doc: &lopdf::Document, standard: PdfxStandard) -> Vec<ComplianceCheck> {
+    let intent = fonts::resolve_dict(doc, doc.trailer.get(b"Root").ok())
+        .and_then(|catalog| catalog.get(b"OutputIntents").ok())
+        .and_then(|intents| match intents {
+            lopdf::Object::Reference(id) => doc.get_object(*id).ok(),
+            other => Some(other),
+        })
+        .and_then(|intents| intents.as_array().ok())
+        .is_some_and(|intents| {
+            intents.iter().any(|intent| {
+                fonts::resolve_dict(doc, Some(intent)).is_some_and(|intent| {
+                    intent
+                        .get(b"S")
+                        .and_then(lopdf::Object::as_name)
+                        .is_ok_and(|s| s == b"GTS_PDFX")
+                        && intent.has(b"DestOutputProfile")
+                })
+            })
+        });
+    let untrimmed: Vec<u32> = doc
+        .get_pages()
+        .into_iter()
+        .filter(|&(_, page_id)| {
+            geometry::explicit_page_box(doc, page_id, PageBox::Trim).is_none()
+                && geometry::explicit_page_box(doc, page_id, PageBox::Art).is_none()
+        })
+        .map(|(page_no, _)| page_no)
+        .collect();
+
+    let mut checks = vec![
+        ComplianceCheck {
+            name: "output-intent",
+            passed: intent,
+            detail: if intent {
+                "PDF/X output intent with an ICC profile".to_string()
+            } else {
+                "no PDF/X output intent".to_string()
+            },
+        },
+        ComplianceCheck {
+            name: "trim-box",
+            passed: untrimmed.is_empty(),
+            detail: if untrimmed.is_empty() {
+                "every page has a TrimBox".to_string()
+            } else {
+                format!("pages without a TrimBox: {untrimmed:?}")
+            },
+        },
+        fonts_embedded_check(doc),
+    ];
+    let rgb = count_color_space_refs(doc, b"DeviceRGB");
+    checks.push(ComplianceCheck {
+        name: "cmyk",
+        passed: rgb == 0,
+        detail: format!("{rgb} DeviceRGB color space reference(s)"),
+    });
+    if standard == PdfxStandard::X1a {
+        let transparent = count_transparency(doc);
+        checks.push(ComplianceCheck {
+            name: "no-transparency",
+            passed: transparent == 0,
+            detail: format!("{transparent} transparency group(s)/soft mask(s)"),
+        });
+    }
+    checks
+}
+
+/// Options for [`a11y_prep`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct A11yPrepOptions {
+    /// Document language (BCP 47, e.g. `en-US`) written to the catalog `/Lang`.
+    pub lang: Option<String>,
+    /// Document title; if `None`, an existing title is kept.
+    pub title: Option<String>,
+    /// Run OCR (via `ocrmypdf`) when pages appear to be scanned images.
+    pub ocr: bool,
+    /// Tesseract language(s) for OCR, e.g. `eng` or `deu+eng`.
+    pub ocr_language: Option<String>,
+}
+
+impl Default for A11yPrepOptions {
+    fn default() -> Self {
+        Self {
+            lang: None,
+            title: None,
+            ocr: true,
+            ocr_language: None,
+        }
+    }
+}
+
+/// Result of [`a11y_prep`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct A11yPrepReport {
+    /// Whether OCR was run.
+    pub ocr_applied: bool,
+    /// Text blocks tagged as headings.
+    pub headings: usize,
+    /// Text blocks tagged as paragraphs.
+    pub paragraphs: usize,
+    /// Remediation steps performed, in order.
+    pub actions: Vec<String>,
+    /// Problems that still need manual attention.
+    pub issues: Vec<String>,
+}
+
+/// Give accessibility remediation a head start.
+///
+/// Steps: OCR scanned pages (if `ocrmypdf` is available), set language and
+/// title, tag text blocks as headings/paragraphs by font size, and set tab
+/// order on pages with annotations. Anything that could not be fixed
+/// automatically is listed in [`A11yPrepReport::issues`].
+///
+/// Existing structure trees are left untouched.
+pub fn a11y_prep(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &A11yPrepOptions,
+) -> Result<A11yPrepReport> {
+    let input = input.as_ref();
+    let output = output.as_ref();
+    let mut report = A11yPrepReport::default();
+
+    let scratch = TempSpace::new("a11y-prep")?;
+    let mut doc = load_document(input)?;
+    if let Some(ocred) = ocr_scanned_pages(input, &doc, options, scratch.path(), &mut report)? {
+        doc = ocred;
+    }
+
+    if let Some(lang) = &options.lang {
+        a11y::set_language(&mut doc, lang)?;
+        report.actions.push(format!("set language to {lang}"));
+    } else if !doc.catalog().is_ok_and(|c| c.has(b"Lang")) {
+        report
+            .issues
+            .push("document language is not set".to_string());
+    }
+
+    if let Some(title) = &options.title {
+        a11y::set_title(&mut doc, title)?;
+        report.actions.push(format!("set title to {title:?}"));
+    } else if let Some(title) = metadata::info_entry(&doc, "Title").filter(|t| !t.trim().is_empty())
+    {
+        a11y::set_title(&mut doc, &title)?;
+        report
+            .actions
+            .push("display existing title in viewer".to_string());
+    } else {
+        report.issues.push("document title is not set".to_string());
+    }
+
+    if a11y::is_tagged(&doc) {
+        report
+            .actions
+            .push("document already tagged; structure left unchanged".to_string());
+    } else {
+        let summary = a11y::tag_text_blocks(&mut doc)?;
+        report.headings = summary.headings;
+        report.paragraphs = summary.paragraphs;
+        report.actions.push(format!(
+            "tagged {} heading(s) and {} paragraph(s)",
+            summary.headings, summary.paragraphs
+        ));
+        if summary.skipped_pages > 0 {
+            report.issues.push(format!(
+                "{} page(s) could not be tagged (undecodable content)",
+                summary.skipped_pages
+            ));
+        }
+        report
+            .issues
+            .push("automatic tags need manual review (reading order, heading levels)".to_string());
+    }
+
+    let mut tab_fixed = 0;
+    for page_id in doc.get_pages().into_values() {
+        if let Ok(page) = doc.get_dictionary_mut(page_id) {
+            if page.has(b"Annots") && !page.has(b"Tabs") {
+                page.set("Tabs", lopdf::Object::Name(b"S".to_vec()));
+                tab_fixed += 1;
+            }
+        }
+    }
+    if tab_fixed > 0 {
+        report
+            .actions
+            .push(format!("set structure tab order on {tab_fixed} page(s)"));
+    }
+
+    residual_a11y_issues(&doc, &mut report);
+    save_document(&mut doc, output)?;
+    Ok(report)
+}
+
+/// What [`a11y_prep`] would do.
+pub fn a11y_prep_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &A11yPrepOptions,
+) -> Result<Plan> {
+    let input = input.as_ref();
+    let doc = load_document(input)?;
+    let mut plan = Plan::new();
+
+    let scanned = scanned_pages(&doc);
+    if !scanned.is_empty() && options.ocr {
+        match find_tool(Tool::Ocrmypdf) {
+            Ok(ocrmypdf) => {
+                let ocred = plan::temp_path("ocr.pdf");
+                plan = plan.run(
+                    Tool::Ocrmypdf,
+                    &ocr_command(ocrmypdf, input, &ocred, options),
+                );
+            }
+            Err(PdfError::MissingTool { .. }) => {
+                plan = plan.native(format!(
+                    "skip OCR of {} scanned page(s): ocrmypdf not found",
+                    scanned.len()
+                ));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    if let Some(lang) = &options.lang {
+        plan = plan.native(format!("set language to {lang}"));
+    }
+    if let Some(title) = &options.title {
+        plan = plan.native(format!("set title to {title:?}"));
+    }
+    if !a11y::is_tagged(&doc) {
+        plan = plan.native("tag text blocks as headings and paragraphs");
+    }
+    plan = plan.native("set structure tab order on pages with annotations");
+    Ok(plan.write(output.as_ref()))
+}
+
+fn ocr_command(
+    ocrmypdf: std::path::PathBuf,
+    input: &Path,
+    output: &Path,
+    options: &A11yPrepOptions,
+) -> Command {
+    let mut cmd = Command::new(ocrmypdf);
+    cmd.arg("--skip-text");
+    if let Some(lang) = &options.ocr_language {
+        cmd.arg("-l").arg(lang);
+    }
+    cmd.arg(input.as_os_str()).arg(output.as_os_str());
+    cmd
+}
+
+/// Run `ocrmypdf` if the document has scanned pages, returning the OCR'd
+/// document. A missing `ocrmypdf` is reported as an issue, not an error.
+fn ocr_scanned_pages(
+    input: &Path,
+    doc: &lopdf::Document,
+    options: &A11yPrepOptions,
+    scratch: &Path,
+    report: &mut A11yPrepReport,
+) -> Result<Option<lopdf::Document>> {
+    let scanned = scanned_pages(doc);
+    if scanned.is_empty() {
+        return Ok(None);
+    }
+    if !options.ocr {
+        report.issues.push(format!(
+            "{} scanned page(s) have no text layer (OCR disabled)",
+            scanned.len()
+        ));
+        return Ok(None);
+    }
+
+    let ocrmypdf = match find_tool(Tool::Ocrmypdf) {
+        Ok(p) => p,
+        Err(PdfError::MissingTool { .. }) => {
+            report.issues.push(format!(
+                "{} scanned page(s) have no text layer; install ocrmypdf to OCR them",
+                scanned.len()
+            ));
+            return Ok(None);
+        }
+        Err(e) => return Err(e),
+    };
+
+    let ocred = scratch.join("ocr.pdf");
+    run_tool(
+        Tool::Ocrmypdf,
+        ocr_command(ocrmypdf, input, &ocred, options),
+    )?;
+
+    report.ocr_applied = true;
+    report
+        .actions
+        .push(format!("ran OCR on {} scanned page(s)", scanned.len()));
+    load_document(&ocred).map(Some)
+}
+
+/// Problems that automatic remediation cannot fix.
+fn residual_a11y_issues(doc: &lopdf::Document, report: &mut A11yPrepReport) {
+    let images = a11y::page_images(doc);
+    if !images.is_empty() {
+        report
+            .issues
+            .push(format!("{} image(s) need alternative text", images.len()));
+    }
+
+    // Composite and Type3 fonts need a ToUnicode map for text to be read aloud.
+    let unmapped: Vec<String> = doc
+        .objects
+        .values()
+        .filter_map(|o| o.as_dict().ok())
+        .filter(|d| d.type_is(b"Font") && !d.has(b"ToUnicode"))
+        .filter(|d| {
+            d.get(b"Subtype")
+                .and_then(lopdf::Object::as_name)
+                .is_ok_and(|s| s == b"Type0" || s == b"Type3")
+        })
+        .map(|d| {
+            d.get(b"BaseFont")
+                .and_then(lopdf::Object::as_name)
+                .map_or_else(
+                    |_| "(unnamed)".to_string(),
+                    |n| String::from_utf8_lossy(n).to_string(),
+                )
+        })
+        .collect();
+    if !unmapped.is_empty() {
+        report.issues.push(format!(
+            "font(s) without a ToUnicode map (text may not be readable): {}",
+            unmapped.join(", ")
+        ));
+    }
+}
+
+/// 1-based pages that have images but no fonts, i.e. likely scans without a
+/// text layer.
+fn scanned_pages(doc: &lopdf::Document) -> Vec<u32> {
+    let with_text: std::collections::BTreeSet<u32> = fonts::collect_fonts(doc)
+        .into_iter()
+        .flat_map(|f| f.pages)
+        .collect();
+    let mut with_images: Vec<u32> = a11y::page_images(doc).into_values().collect();
+    with_images.sort_unstable();
+    with_images.dedup();
+    with_images
+        .into_iter()
+        .filter(|p| !with_text.contains(p))
+        .collect()
+}
+
+/// Remove JavaScript from a document in place, returning the number of
+/// actions/entries removed.
+///
+/// Covers the document-level `/Names /JavaScript` tree, `/OpenAction`,
+/// `/A` entries pointing at JavaScript actions, and all `/AA`
+/// (additional-actions) dictionaries.
+pub(crate) fn strip_javascript(doc: &mut lopdf::Document) -> usize {
+    let js_ids: Vec<lopdf::ObjectId> = doc
+        .objects
+        .iter()
+        .filter(|(_, obj)| obj.as_dict().is_ok_and(is_javascript_action))
+        .map(|(id, _)| *id)
+        .collect();
+
+    let mut removed = 0;
+    for obj in doc.objects.values_mut() {
+        let Ok(dict) = obj_dict_mut(obj) else {
+            continue;
+        };
+        if dict.remove(b"AA").is_some() {
+            removed += 1;
+        }
+        for key in [b"A".as_slice(), b"OpenAction".as_slice()] {
+            let is_js = match dict.get(key) {
+                Ok(lopdf::Object::Reference(id)) => js_ids.contains(id),
+                Ok(lopdf::Object::Dictionary(d)) => is_javascript_action(d),
+                _ => false,
+            };
+            if is_js {
+                dict.remove(key);
+                removed += 1;
+            }
+        }
+        // Name dictionaries are not typed; recognise them by their entries.
+        if dict.has(b"JavaScript") && !dict.has(b"S") && dict.remove(b"JavaScript").is_some() {
+            removed += 1;
+        }
+    }
+
+    for id in js_ids {
+        doc.objects.remove(&id);
+    }
+    removed
+}
+
+/// Count JavaScript actions and `/AA` dictionaries still present.
+pub(crate) fn count_javascript(doc: &lopdf::Document) -> usize {
+    doc.objects
+        .values()
+        .filter_map(|obj| match obj {
+            lopdf::Object::Dictionary(d) => Some(d),
+            lopdf::Object::Stream(s) => Some(&s.dict),
+            _ => None,
+        })
+        .map(|d| {
+            usize::from(is_javascript_action(d))
+                + usize::from(d.has(b"AA"))
+                + d.get(b"A")
+                    .ok()
+                    .and_then(|a| a.as_dict().ok())
+                    .map_or(0, |a| usize::from(is_javascript_action(a)))
+        })
+        .sum()
+}
+
+fn is_javascript_action(dict: &lopdf::Dictionary) -> bool {
+    dict.get(b"S")
+        .and_then(lopdf::Object::as_name)
+        .is_ok_and(|s| s == b"JavaScript")
+}
+
+fn obj_dict_mut(obj: &mut lopdf::Object) -> Result<&mut lopdf::Dictionary> {
+    match obj {
+        lopdf::Object::Dictionary(d) => Ok(d),
+        lopdf::Object::Stream(s) => Ok(&mut s.dict),
+        _ => Err(PdfError::InvalidArgument("not a dictionary".to_string())),
+    }
+}
+
+/// Widget and markup annotations that flattening should have removed.
+/// Links and popups legitimately survive.
+fn count_flattenable_annotations(doc: &lopdf::Document) -> usize {
+    doc.objects
+        .values()
+        .filter_map(|obj| obj.as_dict().ok())
+        .filter(|d| d.type_is(b"Annot"))
+        .filter(|d| {
+            d.get(b"Subtype")
+                .and_then(lopdf::Object::as_name)
+                .is_ok_and(|s| s != b"Link" && s != b"Popup")
+        })
+        .count()
+}
+
+fn is_linearized(doc: &lopdf::Document) -> bool {
+    doc.objects
+        .values()
+        .filter_map(|obj| obj.as_dict().ok())
+        .any(|d| d.has(b"Linearized"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_javascript_removes_actions_and_name_tree() {
+        let mut doc = lopdf::Document::with_version("1.4");
+        let js_id = doc.add_object(lopdf::Dictionary::from_iter([
+            ("S", lopdf::Object::Name(b"JavaScript".to_vec())),
+            ("JS", lopdf::Object::string_literal("app.alert(1)")),
+        ]));
+        let names_id = doc.add_object(lopdf::Dictionary::from_iter([(
+            "JavaScript",
+            lopdf::Object::Reference(js_id),
+        )]));
+        doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"Catalog".to_vec())),
+            ("OpenAction", lopdf::Object::Reference(js_id)),
+            ("Names", lopdf::Object::Reference(names_id)),
+        ]));
+        doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"Annot".to_vec())),
+            ("AA", lopdf::Object::Dictionary(lopdf::Dictionary::new())),
+        ]));
+
+        assert!(count_javascript(&doc) > 0);
+        let removed = strip_javascript(&mut doc);
+        assert_eq!(removed, 3);
+        assert_eq!(count_javascript(&doc), 0);
+    }
+
+    #[test]
+    fn a11y_prep_tags_text_and_sets_metadata() -> std::result::Result<(), Box<dyn std::error::Error>>
+    {
+        let mut doc = lopdf::Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"Font".to_vec())),
+            ("Subtype", lopdf::Object::Name(b"Type1".to_vec())),
+            ("BaseFont", lopdf::Object::Name(b"Helvetica".to_vec())),
+        ]));
+        let content_id = doc.add_object(lopdf::Stream::new(
+            lopdf::Dictionary::new(),
+            b"BT /F1 24 Tf 72 720 Td (Title) Tj ET\nBT /F1 11 Tf 72 680 Td (Body) Tj ET\nBT /F1 11 Tf 72 660 Td (More) Tj ET"
+                .to_vec(),
+        ));
+        let single_page_id = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"Page".to_vec())),
+            ("Parent", pages_id.into()),
+            (
+                "MediaBox",
+                vec![0.into(), 0.into(), 612.into(), 792.into()].into(),
+            ),
+            ("Contents", content_id.into()),
+            (
+                "Resources",
+                lopdf::Dictionary::from_iter([(
+                    "Font",
+                    lopdf::Object::Dictionary(lopdf::Dictionary::from_iter([(
+                        "F1",
+                        lopdf::Object::Reference(font_id),
+                    )])),
+                )])
+                .into(),
+            ),
+        ]));
+        doc.objects.insert(
+            pages_id,
+            lopdf::Dictionary::from_iter([
+                ("Type", lopdf::Object::Name(b"Pages".to_vec())),
+                ("Kids", vec![single_page_id.into()].into()),
+                ("Count", 1.into()),
+            ])
+            .into(),
+        );
+        let catalog_id = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"Catalog".to_vec())),
+            ("Pages", pages_id.into()),
+        ]));
+        doc.trailer.set("Root", catalog_id);
+
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("in.pdf");
+        let output = dir.path().join("out.pdf");
+        doc.save(&input)?;
+
+        let options = A11yPrepOptions {
+            lang: Some("en-US".to_string()),
+            title: Some("Quarterly".to_string()),
+            ..A11yPrepOptions::default()
+        };
+        let report = a11y_prep(&input, &output, &options)?;
+        assert_eq!(report.headings, 1);
+        assert_eq!(report.paragraphs, 2);
+
+        let out = load_document(&output)?;
+        assert!(a11y::is_tagged(&out));
+        assert_eq!(
+            metadata::info_entry(&out, "Title").as_deref(),
+            Some("Quarterly")
+        );
+        let content = String::from_utf8_lossy(&out.get_page_content(single_page_id)?).to_string();
+        assert!(content.contains("/H1"));
+        assert_eq!(content.matches("EMC").count(), 3);
+        Ok(())
+    }
+
+    /// A minimal ICC profile of color space `space` with the `desc` tag
+    /// `desc`.
+    fn icc(space: [u8; 4], desc: &[u8]) -> Vec<u8> {
+        let mut data = vec![0; 144];
+        data[16..20].copy_from_slice(&space);
+        data[36..40].copy_from_slice(b"acsp");
+        data[128..132].copy_from_slice(&1u32.to_be_bytes());
+        data[132..136].copy_from_slice(b"desc");
+        data[136..140].copy_from_slice(&144u32.to_be_bytes());
+        data.extend_from_slice(desc);
+        data
+    }
+
+    #[test]
+    fn output_profiles_are_checked_and_described() {
+        let v2 = [
+            b"desc".as_slice(),
+            &[0; 4],
+            &16u32.to_be_bytes(),
+            b"Coated FOGRA39 \0",
+        ]
+        .concat();
+        let profile = OutputProfile::parse(icc(*b"CMYK", &v2));
+        assert_eq!(
+            profile.map(|p| p.description).as_deref(),
+            Ok("Coated FOGRA39")
+        );
+
+        let text: Vec<u8> = "GRACoL2006 €"
+            .encode_utf16()
+            .flat_map(u16::to_be_bytes)
+            .collect();
+        let v4 = [
+            b"mluc".as_slice(),
+            &[0; 4],
+            &1u32.to_be_bytes(),
+            &12u32.to_be_bytes(),
+            b"enUS",
+            &u32::try_from(text.len()).unwrap_or_default().to_be_bytes(),
+            &28u32.to_be_bytes(),
+            &text,
+        ]
+        .concat();
+        let profile = OutputProfile::parse(icc(*b"CMYK", &v4));
+        assert_eq!(
+            profile.map(|p| p.description).as_deref(),
+            Ok("GRACoL2006 €")
+        );
+
+        let profile = OutputProfile::parse(icc(*b"CMYK", b"XYZ "));
+        assert_eq!(profile.map(|p| p.description).as_deref(), Ok("Custom"));
+        assert!(OutputProfile::parse(icc(*b"RGB ", &v2)).is_err());
+        assert!(OutputProfile::parse(b"not a profile".to_vec()).is_err());
+    }
+
+    #[test]
+    fn pdfx_entries_are_added() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let mut doc = lopdf::Document::with_version("1.6");
+        let pages_id = doc.new_object_id();
+        let page = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"Page".to_vec())),
+            ("Parent", lopdf::Object::Reference(pages_id)),
+            (
+                "MediaBox",
+                lopdf::Object::Array(vec![0.into(), 0.into(), 595.into(), 842.into()]),
+            ),
+        ]));
+        doc.objects.insert(
+            pages_id,
+            lopdf::Object::Dictionary(lopdf::Dictionary::from_iter([
+                ("Type", lopdf::Object::Name(b"Pages".to_vec())),
+                ("Kids", lopdf::Object::Array(vec![page.into()])),
+                ("Count", lopdf::Object::Integer(1)),
+            ])),
+        );
+        let catalog_id = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"Catalog".to_vec())),
+            ("Pages", lopdf::Object::Reference(pages_id)),
+        ]));
+        doc.trailer.set("Root", catalog_id);
+        let info_id = doc.add_object(lopdf::Dictionary::from_iter([(
+            "CreationDate",
+            lopdf::Object::string_literal("D:20240131120000+01'00'"),
+        )]));
+        doc.trailer.set("Info", info_id);
+        assert!(!preflight_pdfx(&doc, PdfxStandard::X4)[0].passed);
+
+        let profile = OutputProfile {
+            data: b"profile".to_vec(),
+            description: "Coated FOGRA39".to_string(),
+        };
+        mark_pdfx(&mut doc, PdfxStandard::X4, &profile, "FOGRA39", "Flyer")?;
+        let checks = preflight_pdfx(&doc, PdfxStandard::X4);
+        assert!(checks.iter().all(|c| c.passed), "{checks:?}");
+        assert_eq!(
+            metadata::info_entry(&doc, "GTS_PDFXVersion").as_deref(),
+            Some("PDF/X-4")
+        );
+        assert_eq!(
+            metadata::info_entry(&doc, "Title").as_deref(),
+            Some("Flyer")
+        );
+        let catalog = doc.get_dictionary(catalog_id)?;
+        let xmp = doc
+            .get_object(catalog.get(b"Metadata")?.as_reference()?)?
+            .as_stream()?;
+        let xmp = String::from_utf8_lossy(&xmp.content);
+        assert!(xmp.contains("<pdfxid:GTS_PDFXVersion>PDF/X-4</pdfxid:GTS_PDFXVersion>"));
+        assert!(xmp.contains("<xmp:CreateDate>2024-01-31T12:00:00+01:00</xmp:CreateDate>"));
+        assert!(xmp.contains("<rdf:li xml:lang=\"x-default\">Flyer</rdf:li>"));
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+//! Progress of runs over many inputs, for callers that show it while the
+//! run goes on (progress bars, dashboards) rather than when it is over.
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+/// Something that happened to one input of a run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgressEvent {
+    /// The input is waiting to be processed.
+    Queued {
+        /// The input.
+        input: PathBuf,
+    },
+    /// A worker started on the input.
+    Started {
+        /// The input.
+        input: PathBuf,
+        /// The worker, numbered from 0.
+        worker: usize,
+    },
+    /// The worker finished the input.
+    Finished {
+        /// The input.
+        input: PathBuf,
+        /// The worker, numbered from 0.
+        worker: usize,
+        /// Size of the input in bytes.
+        bytes: u64,
+        /// How long the worker took.
+        elapsed: Duration,
+    },
+    /// The input was left alone after all (for example, because it had
+    /// been processed before).
+    Skipped {
+        /// The input.
+        input: PathBuf,
+    },
+    /// Processing the input failed.
+    Failed {
+        /// The input.
+        input: PathBuf,
+        /// The worker, numbered from 0.
+        worker: usize,
+        /// What went wrong.
+        error: String,
+        /// How long the worker took.
+        elapsed: Duration,
+    },
+}
+
+/// Where the [`ProgressEvent`]s of a run go: a callback that may be called
+/// from several worker threads, or nowhere ([`Progress::default`]). Cheap to
+/// clone.
+#[derive(Clone, Default)]
+pub struct Progress {
+    report: Option<Arc<dyn Fn(ProgressEvent) + Send + Sync>>,
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Progress")
+            .field("reporting", &self.report.is_some())
+            .finish()
+    }
+}
+
+impl Progress {
+    /// Progress reported to `report`.
+    pub fn new(report: impl Fn(ProgressEvent) + Send + Sync + 'static) -> Self {
+        Self {
+            report: Some(Arc::new(report)),
+        }
+    }
+
+    /// Report `event`.
+    pub fn report(&self, event: ProgressEvent) {
+        if let Some(report) = &self.report {
+            report(event);
+        }
+    }
+
+    /// Report that `input` is waiting.
+    pub fn queued(&self, input: &Path) {
+        self.report(ProgressEvent::Queued {
+            input: input.to_path_buf(),
+        });
+    }
+
+    /// Report that `worker` started on `input`.
+    pub fn started(&self, input: &Path, worker: usize) {
+        self.report(ProgressEvent::Started {
+            input: input.to_path_buf(),
+            worker,
+        });
+    }
+
+    /// Report that `input` was left alone.
+    pub fn skipped(&self, input: &Path) {
+        self.report(ProgressEvent::Skipped {
+            input: input.to_path_buf(),
+        });
+    }
+
+    /// Report how `worker` did on `input`, which took `elapsed`: finished
+    /// when `result` is `Ok`, else failed with its error.
+    pub fn done<T, E: fmt::Display>(
+        &self,
+        input: &Path,
+        worker: usize,
+        elapsed: Duration,
+        result: &std::result::Result<T, E>,
+    ) {
+        if self.report.is_none() {
+            return;
+        }
+        let input = input.to_path_buf();
+        self.report(match result {
+            Ok(_) => ProgressEvent::Finished {
+                bytes: fs::metadata(&input).map_or(0, |m| m.len()),
+                input,
+                worker,
+                elapsed,
+            },
+            Err(e) => ProgressEvent::Failed {
+                input,
+                worker,
+                error: format!("{e:#}"),
+                elapsed,
+            },
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn events_reach_the_callback() {
+        let (tx, rx) = mpsc::channel();
+        let progress = Progress::new(move |event| {
+            let _ = tx.send(event);
+        });
+        let input = Path::new("missing.pdf");
+        progress.queued(input);
+        progress.started(input, 1);
+        progress.done(input, 1, Duration::ZERO, &Err::<(), _>("broken"));
+        progress.done(input, 1, Duration::ZERO, &Ok::<_, String>(()));
+
+        let seen: Vec<ProgressEvent> = rx.try_iter().collect();
+        assert_eq!(seen.len(), 4);
+        assert!(matches!(&seen[2], ProgressEvent::Failed { error, .. } if error == "broken"));
+        assert!(matches!(
+            &seen[3],
+            ProgressEvent::Finished {
+                bytes: 0,
+                worker: 1,
+                ..
+            }
+        ));
+
+        // Reporting nowhere is allowed.
+        Progress::default().queued(input);
+    }
+}
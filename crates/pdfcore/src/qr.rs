@@ -0,0 +1,1090 @@
+//! Reading QR codes in page images, such as the codes on the separator
+//! sheets of a scanned batch.
+//!
+//! This covers what separator sheets need: a model 2 code, upright or
+//! turned by any angle but not mirrored or seen in strong perspective, in
+//! numeric, alphanumeric, or byte mode. The code is found by its three
+//! finder patterns, its grid is sampled between them, and damage is
+//! repaired with the code's Reed-Solomon error correction.
+
+use crate::raster::GrayImage;
+
+/// Finder pattern candidates tried, most often seen first.
+const MAX_FINDERS: usize = 12;
+
+/// Error correction codewords per block, by level (L, M, Q, H) and version.
+const ECC_PER_BLOCK: [[u8; 41]; 4] = [
+    [
+        0, 7, 10, 15, 20, 26, 18, 20, 24, 30, 18, 20, 24, 26, 30, 22, 24, 28, 30, 28, 28, 28, 28,
+        30, 30, 26, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30,
+    ],
+    [
+        0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26, 26, 28,
+        28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28,
+    ],
+    [
+        0, 13, 22, 18, 26, 18, 24, 18, 22, 20, 24, 28, 26, 24, 20, 30, 24, 28, 28, 26, 30, 28, 30,
+        30, 30, 30, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30,
+    ],
+    [
+        0, 17, 28, 22, 16, 22, 28, 26, 26, 24, 28, 24, 28, 22, 24, 24, 30, 28, 28, 26, 28, 30, 24,
+        30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30,
+    ],
+];
+
+/// Error correction blocks, by level (L, M, Q, H) and version.
+const NUM_BLOCKS: [[u8; 41]; 4] = [
+    [
+        0, 1, 1, 1, 1, 1, 2, 2, 2, 2, 4, 4, 4, 4, 4, 6, 6, 6, 6, 7, 8, 8, 9, 9, 10, 12, 12, 12, 13,
+        14, 15, 16, 17, 18, 19, 19, 20, 21, 22, 24, 25,
+    ],
+    [
+        0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16, 17, 17, 18, 20, 21,
+        23, 25, 26, 28, 29, 31, 33, 35, 37, 38, 40, 43, 45, 47, 49,
+    ],
+    [
+        0, 1, 1, 2, 2, 4, 4, 6, 6, 8, 8, 8, 10, 12, 16, 12, 17, 16, 18, 21, 20, 23, 23, 25, 27, 29,
+        34, 34, 35, 38, 40, 43, 45, 48, 51, 53, 56, 59, 62, 65, 68,
+    ],
+    [
+        0, 1, 1, 2, 4, 4, 4, 5, 6, 8, 8, 11, 11, 16, 16, 18, 16, 19, 21, 25, 25, 25, 34, 30, 32,
+        35, 37, 40, 42, 45, 48, 51, 54, 57, 60, 63, 66, 70, 74, 77, 81,
+    ],
+];
+
+/// A QR code read from an image.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FoundQr {
+    pub(crate) text: String,
+    /// Left, top, right, and bottom edges of the code in pixels.
+    pub(crate) bounds: [f64; 4],
+}
+
+/// The text of a QR code in `image`, if there is one that can be read.
+pub(crate) fn read_qr(image: &GrayImage) -> Option<String> {
+    read_qr_codes(image)
+        .into_iter()
+        .next()
+        .map(|code| code.text)
+}
+
+/// The QR codes in `image` that can be read.
+pub(crate) fn read_qr_codes(image: &GrayImage) -> Vec<FoundQr> {
+    let bits = Bits::new(image);
+    let finders = find_finders(&bits);
+    let mut used = vec![false; finders.len()];
+    let mut codes = Vec::new();
+    for corners in finder_triples(&finders) {
+        // Each finder belongs to one code.
+        if corners.iter().any(|&i| used[i]) {
+            continue;
+        }
+        if let Some(code) = read_at(&bits, corners.map(|i| finders[i])) {
+            for i in corners {
+                used[i] = true;
+            }
+            codes.push(code);
+        }
+    }
+    codes
+}
+
+/// The code with its top-left, top-right, and bottom-left finders at
+/// `corners`, if it can be read.
+fn read_at(bits: &Bits, corners: [Finder; 3]) -> Option<FoundQr> {
+    let [tl, tr, bl] = corners;
+    let widths: Vec<f64> = [(tl, tr), (tr, tl), (tl, bl), (bl, tl)]
+        .iter()
+        .filter_map(|(from, to)| module_along(bits, from, to))
+        .collect();
+    let module = if widths.is_empty() {
+        (tl.module + tr.module + bl.module) / 3.0
+    } else {
+        widths.iter().sum::<f64>() / to_f64(widths.len())
+    };
+    // Finder centres are 3.5 modules in from the edges.
+    let across = f64::midpoint(tl.distance(&tr), tl.distance(&bl)) / module + 7.0;
+    let estimate = ((across - 17.0) / 4.0).round().clamp(1.0, 40.0);
+    // Within 1..=40, so this fits.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let estimate = estimate as usize;
+    [estimate, estimate + 1, estimate - 1]
+        .into_iter()
+        .filter(|version| (1..=40).contains(version))
+        .find_map(|version| {
+            let frame = Frame::new(corners, version * 4 + 17);
+            let text = Grid::sample(bits, &frame).decode()?;
+            Some(FoundQr {
+                text,
+                bounds: frame.bounds(),
+            })
+        })
+}
+
+#[allow(clippy::cast_precision_loss)] // pixel counts are far below 2^52
+fn to_f64(n: usize) -> f64 {
+    n as f64
+}
+
+/// The image in black and white.
+struct Bits {
+    width: usize,
+    height: usize,
+    dark: Vec<bool>,
+}
+
+impl Bits {
+    fn new(image: &GrayImage) -> Self {
+        let threshold = image.dark_threshold();
+        Self {
+            width: image.width,
+            height: image.height,
+            dark: image.pixels.iter().map(|&p| p <= threshold).collect(),
+        }
+    }
+
+    /// Whether the pixel at column `x`, row `y` is dark; outside is light.
+    fn get(&self, x: i64, y: i64) -> bool {
+        match (usize::try_from(x), usize::try_from(y)) {
+            (Ok(x), Ok(y)) if x < self.width && y < self.height => self.dark[y * self.width + x],
+            _ => false,
+        }
+    }
+
+    /// Whether the pixel under the point (`x`, `y`) is dark.
+    #[allow(clippy::cast_possible_truncation)] // far outside is light either way
+    fn at(&self, x: f64, y: f64) -> bool {
+        self.get(x.floor() as i64, y.floor() as i64)
+    }
+}
+
+/// A finder pattern: a dark square ring around a dark square, 7 x 7
+/// modules, at three corners of every code.
+#[derive(Debug, Clone, Copy)]
+struct Finder {
+    x: f64,
+    y: f64,
+    /// Module size estimated along the rows and columns.
+    module: f64,
+    /// Rows it was seen on.
+    hits: u32,
+}
+
+impl Finder {
+    fn distance(&self, other: &Self) -> f64 {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+}
+
+/// Run lengths that look like a line through the middle of a finder
+/// pattern: dark, light, dark, light, dark in the ratio 1:1:3:1:1.
+fn is_finder_ratio(counts: [usize; 5]) -> bool {
+    let total: usize = counts.iter().sum();
+    if total < 7 || counts.contains(&0) {
+        return false;
+    }
+    let module = to_f64(total) / 7.0;
+    let slack = module / 2.0;
+    counts
+        .iter()
+        .zip([1.0, 1.0, 3.0, 1.0, 1.0])
+        .all(|(&count, modules)| (to_f64(count) - module * modules).abs() < slack * modules)
+}
+
+/// Finder patterns in the image, most often seen first. Each row is
+/// scanned for the 1:1:3:1:1 pattern, and each hit is checked along its
+/// column and row again to find the centre.
+fn find_finders(bits: &Bits) -> Vec<Finder> {
+    let mut found: Vec<Finder> = Vec::new();
+    let (width, height) = (bits.width, bits.height);
+    for y in 0..height {
+        let row = &bits.dark[y * width..(y + 1) * width];
+        // (dark, start, length) of each run of the row.
+        let mut runs: Vec<(bool, usize, usize)> = Vec::new();
+        for (x, &dark) in row.iter().enumerate() {
+            match runs.last_mut() {
+                Some(run) if run.0 == dark => run.2 += 1,
+                _ => runs.push((dark, x, 1)),
+            }
+        }
+        for window in runs.windows(5) {
+            let counts = [
+                window[0].2,
+                window[1].2,
+                window[2].2,
+                window[3].2,
+                window[4].2,
+            ];
+            if !window[0].0 || !is_finder_ratio(counts) {
+                continue;
+            }
+            let total: usize = counts.iter().sum();
+            let x = window[2].1 + window[2].2 / 2;
+            let (Ok(x), Ok(y)) = (i64::try_from(x), i64::try_from(y)) else {
+                continue;
+            };
+            let Some((cy, down)) = cross_check(bits, (x, y), (0, 1), total) else {
+                continue;
+            };
+            #[allow(clippy::cast_possible_truncation)] // inside the image
+            let row = cy.floor() as i64;
+            let Some((cx, across)) = cross_check(bits, (x, row), (1, 0), total) else {
+                continue;
+            };
+            add_finder(
+                &mut found,
+                Finder {
+                    x: cx,
+                    y: cy,
+                    module: to_f64(across + down) / 14.0,
+                    hits: 1,
+                },
+            );
+        }
+    }
+    found.sort_by_key(|f| std::cmp::Reverse(f.hits));
+    found
+}
+
+/// Along the line through pixel `at` in direction `step`: the centre of a
+/// finder pattern there (the coordinate that changes along the line) and
+/// its width, if the runs fit one about `expected` pixels wide.
+fn cross_check(
+    bits: &Bits,
+    at: (i64, i64),
+    step: (i64, i64),
+    expected: usize,
+) -> Option<(f64, usize)> {
+    let dark = |k: i64| bits.get(at.0 + k * step.0, at.1 + k * step.1);
+    if !dark(0) {
+        return None;
+    }
+    let limit = i64::try_from(expected).ok()?;
+    // Outwards from `at`: the centre, the gap, and the ring, both ways.
+    let walk = |direction: i64, first: i64| {
+        let mut runs = [0i64; 3];
+        let mut k = first;
+        for (run, want) in runs.iter_mut().zip([true, false, true]) {
+            while dark(k) == want && *run < limit {
+                *run += 1;
+                k += direction;
+            }
+        }
+        runs
+    };
+    let (back, ahead) = (walk(-1, 0), walk(1, 1));
+    let counts = [back[2], back[1], back[0] + ahead[0], ahead[1], ahead[2]]
+        .map(|count| usize::try_from(count).unwrap_or(0));
+    let total: usize = counts.iter().sum();
+    // Much wider or narrower than along the row is something else.
+    if !is_finder_ratio(counts) || 5 * total.abs_diff(expected) >= 2 * expected {
+        return None;
+    }
+    let base = if step.0 == 0 { at.1 } else { at.0 };
+    // The centre run covers `1 - back[0]..=ahead[0]` from `at`.
+    #[allow(clippy::cast_precision_loss)] // small pixel offsets
+    let centre = base as f64 + (ahead[0] - back[0] + 2) as f64 / 2.0;
+    Some((centre, total))
+}
+
+/// Count `new` towards the finder it was seen on before, if any.
+fn add_finder(found: &mut Vec<Finder>, new: Finder) {
+    let same = found.iter_mut().find(|f| {
+        (f.x - new.x).abs() <= f.module
+            && (f.y - new.y).abs() <= f.module
+            && (f.module - new.module).abs() <= f.module.max(1.0)
+    });
+    match same {
+        Some(f) => {
+            let n = f64::from(f.hits);
+            f.x = (f.x * n + new.x) / (n + 1.0);
+            f.y = (f.y * n + new.y) / (n + 1.0);
+            f.module = (f.module * n + new.module) / (n + 1.0);
+            f.hits += 1;
+        }
+        None => found.push(new),
+    }
+}
+
+/// Indices of three finders that could be the top-left, top-right, and
+/// bottom-left corners of one code, the most square first.
+fn finder_triples(found: &[Finder]) -> Vec<[usize; 3]> {
+    let count = found.len().min(MAX_FINDERS);
+    let mut triples: Vec<(f64, [usize; 3])> = Vec::new();
+    for i in 0..count {
+        for j in i + 1..count {
+            for k in j + 1..count {
+                let (a, b, c) = (found[i], found[j], found[k]);
+                let modules = [a.module, b.module, c.module];
+                let smallest = modules.iter().copied().fold(f64::INFINITY, f64::min);
+                let largest = modules.iter().copied().fold(0.0, f64::max);
+                if largest > smallest * 1.5 {
+                    continue;
+                }
+                // The top left is the corner opposite the longest side.
+                let (ab, bc, ca) = (a.distance(&b), b.distance(&c), c.distance(&a));
+                let ((corner, arm1, arm2), (ci, i1, i2)) = if bc >= ab && bc >= ca {
+                    ((a, b, c), (i, j, k))
+                } else if ca >= ab {
+                    ((b, c, a), (j, k, i))
+                } else {
+                    ((c, a, b), (k, i, j))
+                };
+                let (leg1, leg2, long) = (
+                    corner.distance(&arm1),
+                    corner.distance(&arm2),
+                    arm1.distance(&arm2),
+                );
+                let skew = (leg1 - leg2).abs() / leg1.max(leg2);
+                let bend = (long - leg1.hypot(leg2)).abs() / long;
+                // Even version 1 has 14 modules between finder centres.
+                if skew > 0.2 || bend > 0.1 || leg1.min(leg2) < 10.0 * smallest {
+                    continue;
+                }
+                // Clockwise from the top left (with y pointing down): top
+                // right, then bottom left.
+                let turn = (arm1.x - corner.x) * (arm2.y - corner.y)
+                    - (arm1.y - corner.y) * (arm2.x - corner.x);
+                let corners = if turn > 0.0 {
+                    [ci, i1, i2]
+                } else {
+                    [ci, i2, i1]
+                };
+                triples.push((skew + bend, corners));
+            }
+        }
+    }
+    triples.sort_by(|a, b| a.0.total_cmp(&b.0));
+    triples.into_iter().map(|(_, corners)| corners).collect()
+}
+
+/// Module size from the width of the finder at `from` along the line
+/// towards `to`, which is 7 modules whatever the angle.
+fn module_along(bits: &Bits, from: &Finder, to: &Finder) -> Option<f64> {
+    let length = from.distance(to);
+    let (ux, uy) = ((to.x - from.x) / length, (to.y - from.y) / length);
+    // Row and column scans overestimate turned codes by up to sqrt(2).
+    let limit = from.module * 8.0;
+    // From the centre out: the dark centre, the gap, the ring, then light.
+    let reach = |sign: f64| {
+        let (mut want, mut changes, mut t) = (true, 0, 0.0);
+        while t <= limit {
+            let dark = bits.at(from.x + sign * t * ux, from.y + sign * t * uy);
+            if dark != want {
+                changes += 1;
+                if changes == 3 {
+                    return Some(t);
+                }
+                want = dark;
+            }
+            t += 0.5;
+        }
+        None
+    };
+    Some((reach(1.0)? + reach(-1.0)?) / 7.0)
+}
+
+/// Where the modules of a `size` x `size` code are in the image, from the
+/// centres of its finders (top left, top right, bottom left).
+struct Frame {
+    size: usize,
+    origin: (f64, f64),
+    across: (f64, f64),
+    down: (f64, f64),
+}
+
+impl Frame {
+    fn new([tl, tr, bl]: [Finder; 3], size: usize) -> Self {
+        let span = to_f64(size - 7);
+        Self {
+            size,
+            origin: (tl.x, tl.y),
+            across: ((tr.x - tl.x) / span, (tr.y - tl.y) / span),
+            down: ((bl.x - tl.x) / span, (bl.y - tl.y) / span),
+        }
+    }
+
+    /// The point `u` modules across and `v` down from the top-left corner.
+    fn point(&self, u: f64, v: f64) -> (f64, f64) {
+        // The top-left finder centre is 3.5 modules in.
+        let (u, v) = (u - 3.5, v - 3.5);
+        (
+            self.origin.0 + u * self.across.0 + v * self.down.0,
+            self.origin.1 + u * self.across.1 + v * self.down.1,
+        )
+    }
+
+    /// Left, top, right, and bottom edges of the code.
+    fn bounds(&self) -> [f64; 4] {
+        let n = to_f64(self.size);
+        let corners = [(0.0, 0.0), (n, 0.0), (0.0, n), (n, n)].map(|(u, v)| self.point(u, v));
+        let xs = corners.map(|(x, _)| x);
+        let ys = corners.map(|(_, y)| y);
+        [
+            xs.into_iter().fold(f64::INFINITY, f64::min),
+            ys.into_iter().fold(f64::INFINITY, f64::min),
+            xs.into_iter().fold(f64::NEG_INFINITY, f64::max),
+            ys.into_iter().fold(f64::NEG_INFINITY, f64::max),
+        ]
+    }
+}
+
+/// The modules of a code, dark or light.
+struct Grid {
+    size: usize,
+    dark: Vec<bool>,
+}
+
+impl Grid {
+    /// The grid of `frame`, sampled at the middle of each module.
+    fn sample(bits: &Bits, frame: &Frame) -> Self {
+        let size = frame.size;
+        let mut dark = Vec::with_capacity(size * size);
+        for row in 0..size {
+            for col in 0..size {
+                let (x, y) = frame.point(to_f64(col) + 0.5, to_f64(row) + 0.5);
+                dark.push(bits.at(x, y));
+            }
+        }
+        Self { size, dark }
+    }
+
+    fn get(&self, row: usize, col: usize) -> bool {
+        self.dark[row * self.size + col]
+    }
+
+    fn decode(&self) -> Option<String> {
+        let version = (self.size - 17) / 4;
+        let (level, mask) = self.format()?;
+        let data = correct(&self.codewords(version, mask), version, level)?;
+        parse_segments(&data, version)
+    }
+
+    /// Error correction level (0 to 3 for L, M, Q, H) and mask pattern,
+    /// from whichever copy of the format information reads better.
+    fn format(&self) -> Option<(usize, u32)> {
+        let n = self.size;
+        let read = |cells: &[(usize, usize)]| {
+            cells
+                .iter()
+                .fold(0u32, |bits, &(r, c)| bits << 1 | u32::from(self.get(r, c)))
+        };
+        // Most significant bit first, around the top-left finder...
+        let mut first: Vec<(usize, usize)> = (0..6).map(|c| (8, c)).collect();
+        first.extend([(8, 7), (8, 8), (7, 8)]);
+        first.extend((0..6).rev().map(|r| (r, 8)));
+        // ... and split between the other two.
+        let mut second: Vec<(usize, usize)> = (n - 7..n).rev().map(|r| (r, 8)).collect();
+        second.extend((n - 8..n).map(|c| (8, c)));
+        let copies = [read(&first), read(&second)];
+        let (errors, data) = (0..32)
+            .map(|data| {
+                let code = format_bits(data);
+                let errors = copies.iter().map(|&bits| (bits ^ code).count_ones()).min();
+                (errors.unwrap_or(u32::MAX), data)
+            })
+            .min()?;
+        if errors > 3 {
+            return None;
+        }
+        // The level's bits are 01, 00, 11, 10 for L, M, Q, H.
+        let level = [1, 0, 3, 2][usize::try_from(data >> 3).ok()?];
+        Some((level, data & 7))
+    }
+
+    /// The codewords, unmasked, in the order they are placed: up and down
+    /// two columns at a time from the right, around the function patterns.
+    fn codewords(&self, version: usize, mask: u32) -> Vec<u8> {
+        let size = self.size;
+        let function = function_modules(version);
+        let mut out = Vec::new();
+        let (mut byte, mut bits) = (0u8, 0);
+        let mut upward = true;
+        let mut right = size - 1;
+        loop {
+            for count in 0..size {
+                let row = if upward { size - 1 - count } else { count };
+                for col in [right, right - 1] {
+                    if function[row * size + col] {
+                        continue;
+                    }
+                    byte = byte << 1 | u8::from(self.get(row, col) ^ masked(mask, row, col));
+                    bits += 1;
+                    if bits == 8 {
+                        out.push(byte);
+                        (byte, bits) = (0, 0);
+                    }
+                }
+            }
+            upward = !upward;
+            if right < 3 {
+                break;
+            }
+            right -= 2;
+            // The vertical timing pattern is skipped over.
+            if right == 6 {
+                right = 5;
+            }
+        }
+        out.truncate(raw_codewords(version));
+        out
+    }
+}
+
+/// The 15 format information bits for 5 data bits: a BCH code, masked.
+fn format_bits(data: u32) -> u32 {
+    let mut rem = data;
+    for _ in 0..10 {
+        rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+    }
+    (data << 10 | rem) ^ 0x5412
+}
+
+/// Whether mask pattern `mask` flips the module at `row`, `col`.
+fn masked(mask: u32, row: usize, col: usize) -> bool {
+    let (i, j) = (row, col);
+    match mask {
+        0 => (i + j) % 2 == 0,
+        1 => i % 2 == 0,
+        2 => j % 3 == 0,
+        3 => (i + j) % 3 == 0,
+        4 => (i / 2 + j / 3) % 2 == 0,
+        5 => (i * j) % 2 + (i * j) % 3 == 0,
+        6 => ((i * j) % 2 + (i * j) % 3) % 2 == 0,
+        _ => ((i + j) % 2 + (i * j) % 3) % 2 == 0,
+    }
+}
+
+/// Rows and columns of the alignment pattern centres of `version`.
+fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let count = version / 7 + 2;
+    let step = (version * 8 + count * 3 + 5) / (count * 4 - 4) * 2;
+    let size = version * 4 + 17;
+    let mut positions: Vec<usize> = (0..count - 1).map(|i| size - 7 - i * step).collect();
+    positions.push(6);
+    positions.reverse();
+    positions
+}
+
+/// Which modules of a `version` code are not data: finder patterns with
+/// their separators and format information, timing patterns, alignment
+/// patterns, and version information.
+fn function_modules(version: usize) -> Vec<bool> {
+    let size = version * 4 + 17;
+    let mut function = vec![false; size * size];
+    let mut mark = |rows: std::ops::Range<usize>, cols: std::ops::Range<usize>| {
+        for row in rows {
+            for col in cols.clone() {
+                function[row * size + col] = true;
+            }
+        }
+    };
+    mark(0..9, 0..9);
+    mark(0..9, size - 8..size);
+    mark(size - 8..size, 0..9);
+    mark(6..7, 0..size);
+    mark(0..size, 6..7);
+    let positions = alignment_positions(version);
+    let last = positions.len().saturating_sub(1);
+    for (i, &row) in positions.iter().enumerate() {
+        for (j, &col) in positions.iter().enumerate() {
+            // Not on top of the finders.
+            if (i, j) == (0, 0) || (i, j) == (0, last) || (i, j) == (last, 0) {
+                continue;
+            }
+            mark(row - 2..row + 3, col - 2..col + 3);
+        }
+    }
+    if version >= 7 {
+        mark(0..6, size - 11..size - 8);
+        mark(size - 11..size - 8, 0..6);
+    }
+    function
+}
+
+/// Codewords (data and error correction) in a `version` code.
+fn raw_codewords(version: usize) -> usize {
+    let mut modules = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let alignment = version / 7 + 2;
+        modules -= (25 * alignment - 10) * alignment - 55;
+        if version >= 7 {
+            modules -= 36;
+        }
+    }
+    modules / 8
+}
+
+/// The data codewords, with errors corrected, from the interleaved
+/// `codewords` of a `version` code at error correction `level`.
+fn correct(codewords: &[u8], version: usize, level: usize) -> Option<Vec<u8>> {
+    let blocks = usize::from(NUM_BLOCKS[level][version]);
+    let ecc = usize::from(ECC_PER_BLOCK[level][version]);
+    let raw = raw_codewords(version);
+    // The first blocks are one data codeword shorter than the rest.
+    let short_len = raw / blocks;
+    let short_blocks = blocks - raw % blocks;
+    let short_data = short_len.checked_sub(ecc)?;
+    let mut split: Vec<Vec<u8>> = vec![Vec::with_capacity(short_len + 1); blocks];
+    let mut next = codewords.iter().copied();
+    // Data codewords go round the blocks, then error correction codewords.
+    for i in 0..=short_data {
+        for (b, block) in split.iter_mut().enumerate() {
+            if i < short_data || b >= short_blocks {
+                block.push(next.next()?);
+            }
+        }
+    }
+    for _ in 0..ecc {
+        for block in &mut split {
+            block.push(next.next()?);
+        }
+    }
+    let mut data = Vec::with_capacity(raw);
+    for mut block in split {
+        reed_solomon(&mut block, ecc)?;
+        data.extend_from_slice(&block[..block.len() - ecc]);
+    }
+    Some(data)
+}
+
+/// Powers of the generator 2 in GF(256) with the QR code polynomial,
+/// repeated so that sums of logarithms can index it directly.
+const EXP: [u8; 512] = {
+    let mut table = [0; 512];
+    let mut x: u16 = 1;
+    let mut i = 0;
+    while i < 512 {
+        // Below 256 by construction.
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            table[i] = x as u8;
+        }
+        x <<= 1;
+        if x & 0x100 != 0 {
+            x ^= 0x11d;
+        }
+        i += 1;
+    }
+    table
+};
+
+const LOG: [u8; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 255 {
+        // Below 255.
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            table[EXP[i] as usize] = i as u8;
+        }
+        i += 1;
+    }
+    table
+};
+
+fn mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        0
+    } else {
+        EXP[usize::from(LOG[usize::from(a)]) + usize::from(LOG[usize::from(b)])]
+    }
+}
+
+/// `a / b`, for nonzero `b`.
+fn div(a: u8, b: u8) -> u8 {
+    if a == 0 {
+        0
+    } else {
+        EXP[usize::from(LOG[usize::from(a)]) + 255 - usize::from(LOG[usize::from(b)])]
+    }
+}
+
+/// 2 to the power `n`.
+fn exp(n: usize) -> u8 {
+    EXP[n % 255]
+}
+
+/// The polynomial with coefficients `poly`, lowest power first, at `x`.
+fn eval(poly: &[u8], x: u8) -> u8 {
+    poly.iter().rev().fold(0, |acc, &c| mul(acc, x) ^ c)
+}
+
+/// Correct up to `ecc / 2` wrong codewords in `block`, whose last `ecc`
+/// codewords are error correction; `None` if there are more.
+fn reed_solomon(block: &mut [u8], ecc: usize) -> Option<()> {
+    let n = block.len();
+    // The block is a polynomial with its first codeword as the highest
+    // power; the syndromes are its values at 2^0 .. 2^(ecc - 1).
+    let syndrome = |block: &[u8], i: usize| block.iter().fold(0, |acc, &c| mul(acc, exp(i)) ^ c);
+    let syndromes: Vec<u8> = (0..ecc).map(|i| syndrome(block, i)).collect();
+    if syndromes.iter().all(|&s| s == 0) {
+        return Some(());
+    }
+
+    // Berlekamp-Massey: the error locator polynomial, lowest power first.
+    let (mut locator, mut previous) = (vec![1u8], vec![1u8]);
+    let (mut errors, mut shift, mut last) = (0, 1, 1u8);
+    for k in 0..ecc {
+        let mut discrepancy = syndromes[k];
+        for i in 1..=errors.min(locator.len() - 1) {
+            discrepancy ^= mul(locator[i], syndromes[k - i]);
+        }
+        if discrepancy == 0 {
+            shift += 1;
+            continue;
+        }
+        let factor = div(discrepancy, last);
+        let mut next = locator.clone();
+        next.resize(next.len().max(previous.len() + shift), 0);
+        for (i, &p) in previous.iter().enumerate() {
+            next[i + shift] ^= mul(factor, p);
+        }
+        if 2 * errors <= k {
+            previous = std::mem::replace(&mut locator, next);
+            errors = k + 1 - errors;
+            last = discrepancy;
+            shift = 1;
+        } else {
+            locator = next;
+            shift += 1;
+        }
+    }
+    if 2 * errors > ecc {
+        return None;
+    }
+
+    // Chien search: the codeword at index j is wrong when the locator has
+    // a root at 2^-(n - 1 - j).
+    let wrong: Vec<usize> = (0..n)
+        .filter(|&j| eval(&locator, exp(255 - (n - 1 - j) % 255)) == 0)
+        .collect();
+    if wrong.len() != errors {
+        return None;
+    }
+
+    // Forney: magnitudes from the error evaluator S(x) * locator(x) mod x^ecc.
+    let evaluator: Vec<u8> = (0..ecc)
+        .map(|i| {
+            (0..=i).fold(0, |acc, j| {
+                acc ^ mul(syndromes[j], locator.get(i - j).copied().unwrap_or(0))
+            })
+        })
+        .collect();
+    for j in wrong {
+        let power = (n - 1 - j) % 255;
+        let inverse = exp(255 - power);
+        // The formal derivative keeps the odd powers.
+        let derivative = locator
+            .iter()
+            .enumerate()
+            .skip(1)
+            .step_by(2)
+            .fold(0, |acc, (i, &c)| acc ^ mul(c, exp((255 - power) * (i - 1))));
+        if derivative == 0 {
+            return None;
+        }
+        block[j] ^= mul(exp(power), div(eval(&evaluator, inverse), derivative));
+    }
+    (0..ecc).all(|i| syndrome(block, i) == 0).then_some(())
+}
+
+/// Bits of the data codewords, most significant first.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl BitReader<'_> {
+    fn left(&self) -> usize {
+        self.data.len() * 8 - self.pos
+    }
+
+    fn read(&mut self, count: usize) -> Option<u32> {
+        if count > self.left() {
+            return None;
+        }
+        let mut value = 0;
+        for _ in 0..count {
+            let bit = self.data[self.pos / 8] >> (7 - self.pos % 8) & 1;
+            value = value << 1 | u32::from(bit);
+            self.pos += 1;
+        }
+        Some(value)
+    }
+}
+
+/// The text of the data segments of a `version` code. Byte segments are
+/// taken as UTF-8 if they are valid, else as Latin-1.
+fn parse_segments(data: &[u8], version: usize) -> Option<String> {
+    const ALPHANUMERIC: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+    let class = match version {
+        1..=9 => 0,
+        10..=26 => 1,
+        _ => 2,
+    };
+    let mut reader = BitReader { data, pos: 0 };
+    let mut text = Vec::new();
+    let digits = |text: &mut Vec<u8>, value: u32, count: usize| {
+        let value = format!("{value:0count$}");
+        text.extend_from_slice(value.as_bytes());
+    };
+    while reader.left() >= 4 {
+        match reader.read(4)? {
+            // Terminator.
+            0 => break,
+            // Numeric: three digits in 10 bits.
+            1 => {
+                let mut count = reader.read([10, 12, 14][class])?;
+                while count > 0 {
+                    let (n, bits, max) = match count {
+                        1 => (1, 4, 10),
+                        2 => (2, 7, 100),
+                        _ => (3, 10, 1000),
+                    };
+                    let value = reader.read(bits)?;
+                    if value >= max {
+                        return None;
+                    }
+                    digits(&mut text, value, n);
+                    count -= u32::try_from(n).ok()?;
+                }
+            }
+            // Alphanumeric: two characters in 11 bits.
+            2 => {
+                let mut count = reader.read([9, 11, 13][class])?;
+                while count >= 2 {
+                    let value = usize::try_from(reader.read(11)?).ok()?;
+                    text.push(*ALPHANUMERIC.get(value / 45)?);
+                    text.push(ALPHANUMERIC[value % 45]);
+                    count -= 2;
+                }
+                if count == 1 {
+                    let value = usize::try_from(reader.read(6)?).ok()?;
+                    text.push(*ALPHANUMERIC.get(value)?);
+                }
+            }
+            // Bytes.
+            4 => {
+                let count = reader.read([8, 16, 16][class])?;
+                for _ in 0..count {
+                    text.push(u8::try_from(reader.read(8)?).ok()?);
+                }
+            }
+            // Structured append header: which part of how many.
+            3 => {
+                reader.read(16)?;
+            }
+            // FNC1 in the first position.
+            5 => {}
+            // FNC1 in the second position, with its application indicator.
+            9 => {
+                reader.read(8)?;
+            }
+            // ECI designator, in one to three bytes; the encoding of byte
+            // segments is guessed anyway.
+            7 => {
+                let first = reader.read(8)?;
+                match first >> 5 {
+                    0..=3 => {}
+                    4 | 5 => {
+                        reader.read(8)?;
+                    }
+                    6 => {
+                        reader.read(16)?;
+                    }
+                    _ => return None,
+                }
+            }
+            // Kanji and anything else.
+            _ => return None,
+        }
+    }
+    Some(
+        String::from_utf8(text)
+            .unwrap_or_else(|e| e.into_bytes().into_iter().map(char::from).collect()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qrcode::{Color, EcLevel, QrCode, Version};
+
+    /// `code` drawn `scale` pixels per module with a 4-module quiet zone,
+    /// turned a quarter clockwise `turns` times.
+    fn picture(code: &QrCode, scale: usize, turns: usize) -> GrayImage {
+        let modules = code.width();
+        let dark: Vec<bool> = code
+            .to_colors()
+            .into_iter()
+            .map(|c| c == Color::Dark)
+            .collect();
+        let n = modules + 8;
+        let size = n * scale;
+        let mut pixels = vec![255; size * size];
+        for y in 0..size {
+            for x in 0..size {
+                let (mx, my) = (x / scale, y / scale);
+                let (mx, my) = match turns % 4 {
+                    0 => (mx, my),
+                    1 => (my, n - 1 - mx),
+                    2 => (n - 1 - mx, n - 1 - my),
+                    _ => (n - 1 - my, mx),
+                };
+                if (4..4 + modules).contains(&mx)
+                    && (4..4 + modules).contains(&my)
+                    && dark[(my - 4) * modules + mx - 4]
+                {
+                    pixels[y * size + x] = 0;
+                }
+            }
+        }
+        GrayImage {
+            width: size,
+            height: size,
+            pixels,
+        }
+    }
+
+    /// `image` turned `degrees` clockwise about its centre on a white page
+    /// half as large again, with some gray noise.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_possible_wrap
+    )]
+    fn tilted(image: &GrayImage, degrees: f64) -> GrayImage {
+        let size = image.width * 3 / 2;
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let (c, c0) = (to_f64(size) / 2.0, to_f64(image.width) / 2.0);
+        let mut pixels = Vec::with_capacity(size * size);
+        for y in 0..size {
+            for x in 0..size {
+                let (dx, dy) = (to_f64(x) + 0.5 - c, to_f64(y) + 0.5 - c);
+                let (sx, sy) = (cos * dx + sin * dy + c0, -sin * dx + cos * dy + c0);
+                let inside = sx >= 0.0
+                    && sy >= 0.0
+                    && (sx as usize) < image.width
+                    && (sy as usize) < image.height;
+                let value = if inside {
+                    image.pixels[sy as usize * image.width + sx as usize]
+                } else {
+                    255
+                };
+                let noise = ((x * 7 + y * 13) % 31) as u8;
+                pixels.push(if value > 127 {
+                    value - noise
+                } else {
+                    value + noise
+                });
+            }
+        }
+        GrayImage {
+            width: size,
+            height: size,
+            pixels,
+        }
+    }
+
+    fn code(data: &str, version: i16, level: EcLevel) -> QrCode {
+        QrCode::with_version(data, Version::Normal(version), level)
+            .unwrap_or_else(|e| panic!("{data} in version {version}: {e}"))
+    }
+
+    #[test]
+    fn every_version_is_read() {
+        let levels = [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H];
+        for (version, level) in (1..=40).zip(levels.into_iter().cycle()) {
+            let data = format!("JOB {version}");
+            let image = picture(&code(&data, version, level), 2, 0);
+            assert_eq!(read_qr(&image), Some(data), "version {version}, {level:?}");
+        }
+    }
+
+    #[test]
+    fn turned_and_tilted_codes_are_read() {
+        for (i, data) in [
+            "2024-0042",
+            "INVOICE BATCH 7",
+            "https://example.com/jobs?id=42&name=Grüße",
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let code = QrCode::new(data).unwrap_or_else(|e| panic!("{e}"));
+            for turns in 0..4 {
+                let image = picture(&code, 3 + i, turns);
+                assert_eq!(read_qr(&image).as_deref(), Some(data), "{turns} turns");
+            }
+            for degrees in [7.0, -20.0, 45.0] {
+                let image = tilted(&picture(&code, 4, 0), degrees);
+                assert_eq!(read_qr(&image).as_deref(), Some(data), "{degrees} degrees");
+            }
+        }
+    }
+
+    #[test]
+    fn damage_is_repaired_up_to_the_level() {
+        let data = "SEPARATOR";
+        let code = code(data, 3, EcLevel::H);
+        let mut image = picture(&code, 3, 0);
+        // A stain over the bottom right corner, well away from the finders.
+        let size = image.width;
+        for y in size * 5 / 8..size * 6 / 8 {
+            for x in size * 5 / 8..size * 6 / 8 {
+                image.pixels[y * size + x] = 0;
+            }
+        }
+        assert_eq!(read_qr(&image).as_deref(), Some(data));
+    }
+
+    #[test]
+    fn pages_without_codes_have_no_text() {
+        let blank = GrayImage {
+            width: 200,
+            height: 300,
+            pixels: vec![250; 200 * 300],
+        };
+        assert_eq!(read_qr(&blank), None);
+        let stripes = GrayImage {
+            width: 200,
+            height: 300,
+            pixels: (0..200 * 300)
+                .map(|i| if i % 200 / 5 % 2 == 0 { 0 } else { 255 })
+                .collect(),
+        };
+        assert_eq!(read_qr(&stripes), None);
+    }
+
+    #[test]
+    fn reed_solomon_corrects_up_to_half_the_ecc() {
+        // Version 1-M: one block of 16 data and 10 error correction codewords.
+        let code = code("HELLO", 1, EcLevel::M);
+        let grid = Grid {
+            size: code.width(),
+            dark: code
+                .to_colors()
+                .into_iter()
+                .map(|c| c == Color::Dark)
+                .collect(),
+        };
+        let (level, mask) = grid.format().unwrap_or((9, 9));
+        assert_eq!(level, 1);
+        let good = grid.codewords(1, mask);
+        let mut block = good.clone();
+        for i in [0, 7, 12, 20, 25] {
+            block[i] ^= 0x5a;
+        }
+        assert_eq!(reed_solomon(&mut block, 10), Some(()));
+        assert_eq!(block, good);
+        block[3] ^= 1;
+        for i in [0, 7, 12, 20, 25] {
+            block[i] ^= 0x5a;
+        }
+        assert_eq!(reed_solomon(&mut block, 10), None);
+    }
+}
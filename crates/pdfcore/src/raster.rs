@@ -0,0 +1,217 @@
+//! Pages as grayscale images, for looking at what is printed on them rather
+//! than at their content streams (blank pages, barcodes on scans).
+
+use std::{fs, path::Path, process::Command};
+
+use crate::{
+    engine, find_tool, run_tool_writing, select_engine, tempspace::TempSpace, validate_render,
+    Engine, Operation, PdfError, Result, Tool,
+};
+
+/// An 8-bit grayscale image: 0 is black, 255 is white.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct GrayImage {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    /// Row by row, from the top left.
+    pub(crate) pixels: Vec<u8>,
+}
+
+impl GrayImage {
+    /// Fraction of the pixels darker than `threshold`, leaving out a
+    /// `margin` (a fraction of each side) where scanners leave shadows.
+    #[allow(clippy::cast_precision_loss)] // a ratio
+    pub(crate) fn ink_coverage(&self, threshold: u8, margin: f64) -> f64 {
+        let inset = |size: usize| {
+            let n = (size as f64 * margin).floor();
+            // `margin` is below one half, so this fits.
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let n = n as usize;
+            n..size.saturating_sub(n)
+        };
+        let (xs, ys) = (inset(self.width), inset(self.height));
+        let area = xs.len() * ys.len();
+        if area == 0 {
+            return 0.0;
+        }
+        let dark = ys
+            .flat_map(|y| self.pixels[y * self.width..][xs.clone()].iter())
+            .filter(|&&p| p < threshold)
+            .count();
+        dark as f64 / area as f64
+    }
+
+    /// The gray level at or below which pixels are dark: Otsu's threshold,
+    /// which best splits the pixels into two classes, kept away from the
+    /// extremes so that a page of only paper and noise stays light.
+    #[allow(clippy::cast_precision_loss)] // pixel counts are far below 2^52
+    pub(crate) fn dark_threshold(&self) -> u8 {
+        let mut histogram = [0usize; 256];
+        for &p in &self.pixels {
+            histogram[usize::from(p)] += 1;
+        }
+        let total = self.pixels.len() as f64;
+        let sum: f64 = (0..256).map(|i| (i * histogram[i]) as f64).sum();
+        let (mut weight, mut below, mut best, mut threshold) = (0.0, 0.0, 0.0, 127);
+        for (level, &count) in (0u8..=255).zip(&histogram) {
+            weight += count as f64;
+            if weight == 0.0 {
+                continue;
+            }
+            let above = total - weight;
+            if above == 0.0 {
+                break;
+            }
+            below += f64::from(level) * count as f64;
+            let spread = (below / weight - (sum - below) / above).powi(2) * weight * above;
+            if spread > best {
+                best = spread;
+                threshold = level;
+            }
+        }
+        threshold.clamp(64, 192)
+    }
+}
+
+/// Render every page of `input` at `dpi` and hand the images to `each`,
+/// one page at a time and in order (with Ghostscript, `mutool`, or
+/// `PDFium`, like [`crate::render`]).
+pub(crate) fn for_each_gray_page(
+    input: &Path,
+    dpi: u32,
+    mut each: impl FnMut(u32, GrayImage) -> Result<()>,
+) -> Result<()> {
+    validate_render(input, dpi)?;
+    let scratch = TempSpace::new("raster")?;
+    match select_engine(Operation::Render)? {
+        Engine::Ghostscript => run_tool_writing(
+            Tool::Ghostscript,
+            gs_command(input, scratch.path(), dpi)?,
+            scratch.path(),
+        )?,
+        Engine::Mutool => run_tool_writing(
+            Tool::Mutool,
+            mutool_command(input, scratch.path(), dpi)?,
+            scratch.path(),
+        )?,
+        #[cfg(feature = "pdfium")]
+        Engine::Pdfium => return crate::pdfium::for_each_gray_page(input, dpi, each),
+        other => return Err(engine::unsupported(Operation::Render, other)),
+    }
+    for page in 1.. {
+        let path = scratch.file(&format!("page-{page}.pgm"));
+        if !path.exists() {
+            break;
+        }
+        each(page, parse_pgm(&fs::read(&path)?)?)?;
+    }
+    Ok(())
+}
+
+fn gs_command(input: &Path, out_dir: &Path, dpi: u32) -> Result<Command> {
+    let mut cmd = Command::new(find_tool(Tool::Ghostscript)?);
+    cmd.arg("-sDEVICE=pgmraw")
+        .arg(format!("-r{dpi}"))
+        .arg("-dNOPAUSE")
+        .arg("-dBATCH")
+        .arg("-dSAFER")
+        .arg(format!(
+            "-sOutputFile={}",
+            out_dir.join("page-%d.pgm").display()
+        ))
+        .arg(input.as_os_str());
+    Ok(cmd)
+}
+
+fn mutool_command(input: &Path, out_dir: &Path, dpi: u32) -> Result<Command> {
+    let mut cmd = Command::new(find_tool(Tool::Mutool)?);
+    cmd.arg("draw")
+        .arg("-q")
+        .arg("-F")
+        .arg("pgm")
+        .arg("-c")
+        .arg("gray")
+        .arg("-r")
+        .arg(dpi.to_string())
+        .arg("-o")
+        .arg(out_dir.join("page-%d.pgm").as_os_str())
+        .arg(input.as_os_str());
+    Ok(cmd)
+}
+
+/// A binary (`P5`) PGM image.
+pub(crate) fn parse_pgm(data: &[u8]) -> Result<GrayImage> {
+    let invalid = |what: &str| PdfError::InvalidArgument(format!("invalid PGM image: {what}"));
+    let mut pos = 0;
+    let mut header = [0usize; 4];
+    for (i, field) in header.iter_mut().enumerate() {
+        // Whitespace and `#` comments separate the fields.
+        loop {
+            match data.get(pos) {
+                Some(b'#') => {
+                    while data.get(pos).is_some_and(|&b| b != b'\n') {
+                        pos += 1;
+                    }
+                }
+                Some(b) if b.is_ascii_whitespace() => pos += 1,
+                _ => break,
+            }
+        }
+        let start = pos;
+        while data.get(pos).is_some_and(|b| !b.is_ascii_whitespace()) {
+            pos += 1;
+        }
+        let token = std::str::from_utf8(&data[start..pos]).map_err(|_| invalid("bad header"))?;
+        if i == 0 {
+            if token != "P5" {
+                return Err(invalid("not a binary PGM"));
+            }
+        } else {
+            *field = token.parse().map_err(|_| invalid("bad header"))?;
+        }
+    }
+    let [_, width, height, max] = header;
+    if !(1..=65535).contains(&max) {
+        return Err(invalid("bad maximum value"));
+    }
+    // One whitespace byte ends the header.
+    let body = data.get(pos + 1..).unwrap_or_default();
+    let bytes_per_pixel = if max > 255 { 2 } else { 1 };
+    let count = width
+        .checked_mul(height)
+        .filter(|&n| body.len() >= n * bytes_per_pixel)
+        .ok_or_else(|| invalid("truncated"))?;
+    let pixels = body
+        .chunks_exact(bytes_per_pixel)
+        .take(count)
+        // Scaled to 0-255; two-byte samples are big-endian.
+        .map(|sample| {
+            let value = sample.iter().fold(0, |v, &b| v << 8 | usize::from(b));
+            u8::try_from(value * 255 / max).unwrap_or(u8::MAX)
+        })
+        .collect();
+    Ok(GrayImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pgm_images_are_read() -> Result<()> {
+        let image = parse_pgm(b"P5\n# made by hand\n3 2\n255\n\x00\x80\xff\xff\xff\xff")?;
+        assert_eq!((image.width, image.height), (3, 2));
+        assert_eq!(image.pixels, [0, 128, 255, 255, 255, 255]);
+        assert!((image.ink_coverage(128, 0.0) - 1.0 / 6.0).abs() < 1e-9);
+
+        let deep = parse_pgm(b"P5 1 1 65535 \xff\xff")?;
+        assert_eq!(deep.pixels, [255]);
+        assert!(parse_pgm(b"P5\n3 2\n255\n\x00").is_err());
+        assert!(parse_pgm(b"P6\n1 1\n255\n\x00\x00\x00").is_err());
+        Ok(())
+    }
+}
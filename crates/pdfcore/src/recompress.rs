@@ -0,0 +1,431 @@
+//! Recompressing images in place: downsampling those drawn at more than a
+//! given resolution and re-encoding them as JPEG, one image at a time, so
+//! that nothing else in the document is rewritten (unlike a Ghostscript
+//! pass, which redistills every page and can lose annotations).
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+};
+
+use image::{
+    codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, ExtendedColorType, GrayImage,
+    ImageFormat, RgbImage,
+};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+
+use crate::{
+    images::placements, load_document, save_document, validate_input_file, CompressReport,
+    PdfError, Plan, Result,
+};
+
+/// Options for [`recompress_images`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecompressOptions {
+    /// Downsample images drawn at a higher resolution than this, in pixels
+    /// per inch. Without it, images keep their size.
+    pub max_dpi: Option<f32>,
+    /// JPEG quality, from 1 to 100.
+    pub jpeg_quality: u8,
+    /// Leave 1-bit (black and white) images as they are, rather than
+    /// turning them into grayscale JPEGs.
+    pub skip_monochrome: bool,
+}
+
+impl Default for RecompressOptions {
+    fn default() -> Self {
+        Self {
+            max_dpi: None,
+            jpeg_quality: 75,
+            skip_monochrome: false,
+        }
+    }
+}
+
+/// What [`recompress_images`] did.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecompressReport {
+    /// Images replaced with a smaller JPEG.
+    pub recompressed: usize,
+    /// Of those, the images that were also downsampled.
+    pub downsampled: usize,
+    /// Images left as they were: in a format this cannot decode, or not
+    /// made smaller by recompressing.
+    pub unchanged: usize,
+    /// Input and output file sizes.
+    pub sizes: CompressReport,
+}
+
+/// Re-encode the images of `input` as JPEG at `options.jpeg_quality`,
+/// downsampling those drawn above `options.max_dpi` first (pure Rust).
+///
+/// An image is only replaced if the result is smaller. Images drawn more
+/// than once are downsampled for their largest drawing. Stencil masks,
+/// soft masks, images with color-key masks or `/Decode` arrays, and images
+/// in CMYK, indexed, or other color spaces are left as they are, as are
+/// images in filters `lopdf` cannot decode (other than JPEG).
+pub fn recompress_images(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &RecompressOptions,
+) -> Result<RecompressReport> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_options(options)?;
+    let mut doc = load_document(input)?;
+
+    // The lowest resolution each image is drawn at: its largest drawing.
+    let mut dpi: BTreeMap<ObjectId, f32> = BTreeMap::new();
+    for (id, image) in placements(&doc) {
+        let lowest = dpi.entry(id).or_insert(f32::INFINITY);
+        *lowest = lowest.min(image.dpi());
+    }
+    let masks = mask_images(&doc);
+    let mut report = RecompressReport {
+        recompressed: 0,
+        downsampled: 0,
+        unchanged: 0,
+        sizes: CompressReport::from_sizes(0, 0),
+    };
+    let ids: Vec<ObjectId> = doc
+        .objects
+        .iter()
+        .filter(|(_, obj)| is_image(obj))
+        .map(|(&id, _)| id)
+        .collect();
+    for id in ids {
+        let replacement = match doc.objects.get(&id) {
+            Some(Object::Stream(stream)) if !masks.contains(&id) => {
+                recompress(&doc, stream, dpi.get(&id).copied(), options)
+            }
+            _ => None,
+        };
+        match replacement {
+            Some((stream, downsampled)) => {
+                report.recompressed += 1;
+                report.downsampled += usize::from(downsampled);
+                doc.objects.insert(id, Object::Stream(stream));
+            }
+            None => report.unchanged += 1,
+        }
+    }
+    tracing::debug!(
+        recompressed = report.recompressed,
+        downsampled = report.downsampled,
+        "recompressed images"
+    );
+    save_document(&mut doc, output)?;
+    report.sizes =
+        CompressReport::from_sizes(fs::metadata(input)?.len(), fs::metadata(output)?.len());
+    Ok(report)
+}
+
+/// What [`recompress_images`] would do.
+pub fn recompress_images_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &RecompressOptions,
+) -> Result<Plan> {
+    validate_input_file(input.as_ref())?;
+    validate_options(options)?;
+    let mut plan = Plan::new().native("find the images and the resolution they are drawn at");
+    if let Some(max_dpi) = options.max_dpi {
+        plan = plan.native(format!("downsample images drawn above {max_dpi} DPI"));
+    }
+    if options.skip_monochrome {
+        plan = plan.native("leave 1-bit images as they are");
+    }
+    Ok(plan
+        .native(format!(
+            "re-encode images as JPEG (quality {}), keeping whichever is smaller",
+            options.jpeg_quality
+        ))
+        .write(output.as_ref()))
+}
+
+fn validate_options(options: &RecompressOptions) -> Result<()> {
+    if !(1..=100).contains(&options.jpeg_quality) {
+        return Err(PdfError::InvalidArgument(format!(
+            "JPEG quality must be from 1 to 100, not {}",
+            options.jpeg_quality
+        )));
+    }
+    if let Some(max_dpi) = options.max_dpi {
+        if !(max_dpi.is_finite() && max_dpi > 0.0) {
+            return Err(PdfError::InvalidArgument(format!(
+                "the maximum resolution must be positive, not {max_dpi}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn is_image(obj: &Object) -> bool {
+    matches!(obj, Object::Stream(stream) if stream
+        .dict
+        .get(b"Subtype")
+        .and_then(Object::as_name)
+        .is_ok_and(|subtype| subtype == b"Image"))
+}
+
+/// Images that serve as another image's `/SMask` or `/Mask`.
+fn mask_images(doc: &Document) -> BTreeSet<ObjectId> {
+    doc.objects
+        .values()
+        .filter(|obj| is_image(obj))
+        .filter_map(|obj| obj.as_stream().ok())
+        .flat_map(|stream| [b"SMask".as_slice(), b"Mask"].map(|key| stream.dict.get(key)))
+        .filter_map(|mask| mask.and_then(Object::as_reference).ok())
+        .collect()
+}
+
+/// `stream` re-encoded as JPEG, downsampled if it is drawn at `dpi` above
+/// the maximum, with whether it was downsampled; `None` to keep it.
+fn recompress(
+    doc: &Document,
+    stream: &Stream,
+    dpi: Option<f32>,
+    options: &RecompressOptions,
+) -> Option<(Stream, bool)> {
+    let mut pixels = decode(doc, stream, options)?;
+    let mut downsampled = false;
+    if let (Some(max_dpi), Some(dpi)) = (options.max_dpi, dpi) {
+        if dpi.is_finite() && dpi > max_dpi {
+            let scale = max_dpi / dpi;
+            let size = |n: u32| {
+                #[allow(
+                    clippy::cast_possible_truncation,
+                    clippy::cast_sign_loss,
+                    clippy::cast_precision_loss
+                )]
+                // Image dimensions are far below f32's exact integer range.
+                let scaled = (n as f32 * scale).round().max(1.0) as u32;
+                scaled
+            };
+            let (width, height) = (size(pixels.width()), size(pixels.height()));
+            pixels = pixels.resize_exact(width, height, FilterType::Triangle);
+            downsampled = true;
+        }
+    }
+
+    let jpeg = encode_jpeg(&pixels, options.jpeg_quality)?;
+    if jpeg.len() >= stream.content.len() {
+        return None;
+    }
+
+    let mut dict = stream.dict.clone();
+    for key in [b"DecodeParms".as_slice(), b"Length"] {
+        dict.remove(key);
+    }
+    dict.set("Width", i64::from(pixels.width()));
+    dict.set("Height", i64::from(pixels.height()));
+    dict.set("BitsPerComponent", 8);
+    dict.set("Filter", Object::Name(b"DCTDecode".to_vec()));
+    if matches!(pixels, DynamicImage::ImageLuma8(_)) && bits_per_component(stream) == Some(1) {
+        dict.set("ColorSpace", Object::Name(b"DeviceGray".to_vec()));
+    }
+    Some((Stream::new(dict, jpeg).with_compression(false), downsampled))
+}
+
+/// `pixels` (8-bit gray or RGB) as a JPEG file.
+pub(crate) fn encode_jpeg(pixels: &DynamicImage, quality: u8) -> Option<Vec<u8>> {
+    let (color, data) = match pixels {
+        DynamicImage::ImageLuma8(gray) => (ExtendedColorType::L8, gray.as_raw()),
+        DynamicImage::ImageRgb8(rgb) => (ExtendedColorType::Rgb8, rgb.as_raw()),
+        _ => return None,
+    };
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, quality)
+        .encode(data, pixels.width(), pixels.height(), color)
+        .ok()?;
+    Some(jpeg)
+}
+
+fn bits_per_component(stream: &Stream) -> Option<i64> {
+    stream
+        .dict
+        .get(b"BitsPerComponent")
+        .and_then(Object::as_i64)
+        .ok()
+}
+
+/// The pixels of an image this can re-encode: 8-bit gray or RGB (or 1-bit
+/// gray, as 8-bit), stored as JPEG or in a filter `lopdf` decodes.
+fn decode(doc: &Document, stream: &Stream, options: &RecompressOptions) -> Option<DynamicImage> {
+    let dict = &stream.dict;
+    let is_true = |key: &[u8]| dict.get(key).and_then(Object::as_bool).unwrap_or(false);
+    if is_true(b"ImageMask")
+        || dict.has(b"Decode")
+        || matches!(dict.get(b"Mask"), Ok(Object::Array(_)))
+    {
+        return None;
+    }
+    let components = color_components(doc, dict)?;
+    let dimension = |key: &[u8]| {
+        dict.get(key)
+            .and_then(Object::as_i64)
+            .ok()
+            .and_then(|n| u32::try_from(n).ok())
+            .filter(|&n| n > 0)
+    };
+    let (width, height) = (dimension(b"Width")?, dimension(b"Height")?);
+
+    let filters = stream.filters().unwrap_or_default();
+    if filters == ["DCTDecode"] {
+        let decoded =
+            image::load_from_memory_with_format(&stream.content, ImageFormat::Jpeg).ok()?;
+        return Some(match components {
+            1 => DynamicImage::ImageLuma8(decoded.to_luma8()),
+            _ => DynamicImage::ImageRgb8(decoded.to_rgb8()),
+        });
+    }
+    let data = if filters.is_empty() {
+        stream.content.clone()
+    } else {
+        // lopdf refuses to decode streams marked as images.
+        let mut probe = stream.clone();
+        probe.dict.remove(b"Subtype");
+        probe.decompressed_content().ok()?
+    };
+    let (width_px, height_px) = (usize::try_from(width).ok()?, usize::try_from(height).ok()?);
+    match (bits_per_component(stream)?, components) {
+        (8, 1) => {
+            let len = width_px * height_px;
+            GrayImage::from_raw(width, height, data.get(..len)?.to_vec())
+                .map(DynamicImage::ImageLuma8)
+        }
+        (8, 3) => {
+            let len = width_px * height_px * 3;
+            RgbImage::from_raw(width, height, data.get(..len)?.to_vec())
+                .map(DynamicImage::ImageRgb8)
+        }
+        (1, 1) if !options.skip_monochrome => {
+            let row_len = width_px.div_ceil(8);
+            if data.len() < row_len * height_px {
+                return None;
+            }
+            let gray = (0..height_px)
+                .flat_map(|row| {
+                    let bits = &data[row * row_len..(row + 1) * row_len];
+                    (0..width_px).map(move |col| {
+                        if bits[col / 8] & (0x80 >> (col % 8)) == 0 {
+                            0
+                        } else {
+                            255
+                        }
+                    })
+                })
+                .collect();
+            GrayImage::from_raw(width, height, gray).map(DynamicImage::ImageLuma8)
+        }
+        _ => None,
+    }
+}
+
+/// 1 or 3 for gray and RGB color spaces (device, calibrated, or ICC based),
+/// `None` for the rest.
+fn color_components(doc: &Document, dict: &Dictionary) -> Option<u8> {
+    let space = dict.get(b"ColorSpace").ok()?;
+    let (_, space) = doc.dereference(space).ok()?;
+    match space {
+        Object::Name(name) => match name.as_slice() {
+            b"DeviceGray" | b"G" => Some(1),
+            b"DeviceRGB" | b"RGB" => Some(3),
+            _ => None,
+        },
+        Object::Array(items) => match items.first()?.as_name().ok()? {
+            b"CalGray" => Some(1),
+            b"CalRGB" => Some(3),
+            b"ICCBased" => {
+                let (_, profile) = doc.dereference(items.get(1)?).ok()?;
+                match profile
+                    .as_stream()
+                    .ok()?
+                    .dict
+                    .get(b"N")
+                    .and_then(Object::as_i64)
+                {
+                    Ok(1) => Some(1),
+                    Ok(3) => Some(3),
+                    _ => None,
+                }
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray_stream(width: u32, height: u32, bits: i64, data: Vec<u8>) -> Stream {
+        Stream::new(
+            Dictionary::from_iter([
+                ("Type", Object::Name(b"XObject".to_vec())),
+                ("Subtype", Object::Name(b"Image".to_vec())),
+                ("Width", Object::Integer(width.into())),
+                ("Height", Object::Integer(height.into())),
+                ("ColorSpace", Object::Name(b"DeviceGray".to_vec())),
+                ("BitsPerComponent", Object::Integer(bits)),
+            ]),
+            data,
+        )
+    }
+
+    #[test]
+    fn images_drawn_above_the_limit_are_downsampled() {
+        let doc = Document::with_version("1.5");
+        // A smooth gradient: large as raw pixels, small as JPEG.
+        let pixels = (0..200u32 * 200).map(|i| (i % 200) as u8).collect();
+        let stream = gray_stream(200, 200, 8, pixels);
+        let options = RecompressOptions {
+            max_dpi: Some(150.0),
+            ..RecompressOptions::default()
+        };
+
+        let Some((jpeg, downsampled)) = recompress(&doc, &stream, Some(300.0), &options) else {
+            panic!("the image was not recompressed");
+        };
+        assert!(downsampled);
+        assert_eq!(
+            jpeg.dict.get(b"Width").and_then(Object::as_i64).ok(),
+            Some(100)
+        );
+        assert_eq!(
+            jpeg.dict.get(b"Height").and_then(Object::as_i64).ok(),
+            Some(100)
+        );
+        assert!(jpeg
+            .dict
+            .get(b"Filter")
+            .and_then(Object::as_name)
+            .is_ok_and(|f| f == b"DCTDecode"));
+
+        // Drawn below the limit: re-encoded at full size.
+        let kept = recompress(&doc, &stream, Some(100.0), &options);
+        assert!(matches!(kept, Some((_, false))));
+    }
+
+    #[test]
+    fn monochrome_images_can_be_skipped() {
+        let doc = Document::with_version("1.5");
+        let stream = gray_stream(16, 2, 1, vec![0b1010_1010, 0xff, 0x00, 0x0f]);
+        let options = RecompressOptions {
+            skip_monochrome: true,
+            ..RecompressOptions::default()
+        };
+        assert!(decode(&doc, &stream, &options).is_none());
+        let Some(DynamicImage::ImageLuma8(gray)) =
+            decode(&doc, &stream, &RecompressOptions::default())
+        else {
+            panic!("the image was not decoded");
+        };
+        assert_eq!(&gray.as_raw()[..4], &[255, 0, 255, 0]);
+        assert_eq!(
+            &gray.as_raw()[16..],
+            &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 255, 255, 255, 255]
+        );
+    }
+}
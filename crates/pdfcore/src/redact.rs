@@ -0,0 +1,733 @@
+//! True redaction: removing text and image data, not just covering it.
+//!
+//! Matched glyphs are deleted from the content stream (with spacing kept so
+//! the rest of the line does not move), image pixels under a redaction area
+//! are overwritten, and overlapping annotations are dropped. A black box is
+//! then drawn over each area. Content that cannot be edited in place (form
+//! `XObject`s, images in unsupported encodings, undecodable content streams)
+//! causes the page to be rasterized with Ghostscript after the edits above.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write as _,
+    path::Path,
+    process::Command,
+};
+
+use lopdf::{
+    content::{Content, Operation},
+    Document, Object, ObjectId,
+};
+
+use crate::{
+    find_tool,
+    geometry::{Matrix, Rect},
+    load_document, objects,
+    plan::Plan,
+    run_tool, save_document, stamp,
+    tempspace::TempSpace,
+    text::{self, Glyph},
+    PdfError, Result, Tool,
+};
+
+/// Resolution used when a page has to be rasterized.
+const RASTER_DPI: u32 = 150;
+
+/// A rectangle to redact on one page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RedactRegion {
+    /// 1-based page number.
+    pub page: u32,
+    /// Area in default user space (points, origin at the bottom left).
+    pub rect: Rect,
+}
+
+/// What [`redact`] should remove.
+#[derive(Debug, Clone, Default)]
+pub struct RedactOptions {
+    /// Explicit areas to redact.
+    pub regions: Vec<RedactRegion>,
+    /// Text to search for on every page.
+    pub patterns: Vec<String>,
+    /// Treat `patterns` as regular expressions instead of literal text.
+    pub regex: bool,
+}
+
+/// Result of [`redact`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RedactReport {
+    /// Number of text matches found for `patterns`.
+    pub matches: usize,
+    /// Glyphs removed from content streams.
+    pub glyphs_removed: usize,
+    /// Images whose pixels were blacked out in place.
+    pub images_redacted: usize,
+    /// Annotations removed because they overlapped a redaction area.
+    pub annotations_removed: usize,
+    /// Pages replaced by a rasterized image, in page order.
+    pub rasterized_pages: Vec<u32>,
+    /// Pages whose content could not be decoded, so were not searched.
+    pub unsearched_pages: Vec<u32>,
+}
+
+/// Remove text and image content matching `options` from `input`.
+///
+/// Pure Rust, except that pages containing content that cannot be edited in
+/// place are rasterized with Ghostscript ([`PdfError::MissingTool`] if it is
+/// not installed). Objects orphaned by the edits are dropped from the output,
+/// so the original data does not survive in the file.
+pub fn redact(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &RedactOptions,
+) -> Result<RedactReport> {
+    let mut doc = load_document(input.as_ref())?;
+    let pages = doc.get_pages();
+    let (mut report, rasterize) = redact_document(&mut doc, &pages, options)?;
+
+    if !rasterize.is_empty() {
+        rasterize_pages(&mut doc, &pages, &rasterize)?;
+        report.rasterized_pages = rasterize;
+    }
+    doc.prune_objects();
+    save_document(&mut doc, output.as_ref())?;
+    Ok(report)
+}
+
+/// What [`redact`] would do. The redaction is carried out in memory to
+/// count what would be removed; nothing is written.
+pub fn redact_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &RedactOptions,
+) -> Result<Plan> {
+    let mut doc = load_document(input.as_ref())?;
+    let pages = doc.get_pages();
+    let (report, rasterize) = redact_document(&mut doc, &pages, options)?;
+
+    let mut plan = Plan::new().native(format!(
+        "remove {} glyph(s) ({} text match(es)), black out {} image(s), remove {} annotation(s)",
+        report.glyphs_removed, report.matches, report.images_redacted, report.annotations_removed
+    ));
+    if !rasterize.is_empty() {
+        find_tool(Tool::Ghostscript)?;
+        let numbers: Vec<String> = rasterize.iter().map(ToString::to_string).collect();
+        plan = plan.native(format!(
+            "rasterize page(s) {} at {RASTER_DPI} dpi with ghostscript",
+            numbers.join(", ")
+        ));
+    }
+    Ok(plan.write(output.as_ref()))
+}
+
+/// Redact `doc` in memory, returning the report and the pages that still
+/// need rasterizing.
+fn redact_document(
+    doc: &mut Document,
+    pages: &BTreeMap<u32, ObjectId>,
+    options: &RedactOptions,
+) -> Result<(RedactReport, Vec<u32>)> {
+    if options.regions.is_empty() && options.patterns.is_empty() {
+        return Err(PdfError::InvalidArgument(
+            "nothing to redact: give at least one region or search pattern".to_string(),
+        ));
+    }
+    let matcher = build_matcher(&options.patterns, options.regex)?;
+
+    if let Some(region) = options
+        .regions
+        .iter()
+        .find(|r| !pages.contains_key(&r.page))
+    {
+        return Err(PdfError::InvalidArgument(format!(
+            "page {} out of range (document has {} pages)",
+            region.page,
+            pages.len()
+        )));
+    }
+
+    let mut report = RedactReport::default();
+    let mut rasterize = Vec::new();
+    for (&number, &page_id) in pages {
+        let areas: Vec<Rect> = options
+            .regions
+            .iter()
+            .filter(|r| r.page == number)
+            .map(|r| r.rect.normalized())
+            .collect();
+        if areas.is_empty() && matcher.is_none() {
+            continue;
+        }
+        if redact_page(doc, page_id, number, areas, matcher.as_ref(), &mut report)? {
+            rasterize.push(number);
+        }
+    }
+    Ok((report, rasterize))
+}
+
+/// One regular expression matching any of the patterns.
+fn build_matcher(patterns: &[String], regex: bool) -> Result<Option<regex::Regex>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    if let Some(empty) = patterns.iter().find(|p| p.is_empty()) {
+        return Err(PdfError::InvalidArgument(format!(
+            "empty search pattern {empty:?}"
+        )));
+    }
+    let alternatives: Vec<String> = patterns
+        .iter()
+        .map(|p| {
+            if regex {
+                format!("(?:{p})")
+            } else {
+                regex::escape(p)
+            }
+        })
+        .collect();
+    regex::Regex::new(&alternatives.join("|"))
+        .map(Some)
+        .map_err(|e| PdfError::InvalidArgument(format!("invalid pattern: {e}")))
+}
+
+/// Redact one page in place. Returns `true` if the page still needs to be
+/// rasterized.
+fn redact_page(
+    doc: &mut Document,
+    page_id: ObjectId,
+    number: u32,
+    mut areas: Vec<Rect>,
+    matcher: Option<&regex::Regex>,
+    report: &mut RedactReport,
+) -> Result<bool> {
+    let Ok(content) = doc.get_and_decode_page_content(page_id) else {
+        // Inline images and malformed streams stop the decoder. Regions can
+        // still be honoured by rasterizing; text search cannot.
+        if matcher.is_some() {
+            report.unsearched_pages.push(number);
+        }
+        if areas.is_empty() {
+            return Ok(false);
+        }
+        redact_annotations(doc, page_id, &areas, report)?;
+        stamp::overlay_page_content(doc, page_id, &black_boxes(&areas))?;
+        return Ok(true);
+    };
+    let layout = text::layout(doc, page_id, &content.operations);
+    let regions = areas.clone();
+
+    let mut remove = vec![false; layout.glyphs.len()];
+    if let Some(matcher) = matcher {
+        let (page_text, origins) = layout.text_with_origins();
+        for found in matcher.find_iter(&page_text) {
+            let hits: BTreeSet<usize> = origins[found.range()].iter().flatten().copied().collect();
+            if hits.is_empty() {
+                continue;
+            }
+            report.matches += 1;
+            for &index in &hits {
+                remove[index] = true;
+            }
+            areas.extend(line_boxes(&layout.glyphs, &hits));
+        }
+    }
+    // Only explicit regions select glyphs geometrically; boxes around matches
+    // would also catch neighbours whose edges touch because of rounding.
+    for (flag, glyph) in remove.iter_mut().zip(&layout.glyphs) {
+        if regions.iter().any(|a| a.intersects(&glyph.bbox)) {
+            *flag = true;
+        }
+    }
+    if areas.is_empty() {
+        return Ok(false);
+    }
+
+    let removed = remove.iter().filter(|r| **r).count();
+    if removed > 0 {
+        let operations = remove_glyphs(content.operations, &layout.glyphs, &remove);
+        let bytes = Content { operations }
+            .encode()
+            .map_err(|e| PdfError::InvalidArgument(format!("encoding page {number}: {e}")))?;
+        stamp::replace_page_content(doc, page_id, bytes)?;
+        report.glyphs_removed += removed;
+    }
+
+    let needs_raster = redact_xobjects(doc, page_id, &layout.xobjects, &areas, report)?;
+    redact_annotations(doc, page_id, &areas, report)?;
+    stamp::overlay_page_content(doc, page_id, &black_boxes(&areas))?;
+    Ok(needs_raster)
+}
+
+/// Bounding boxes of the matched glyphs, one per line of text.
+fn line_boxes(glyphs: &[Glyph], hits: &BTreeSet<usize>) -> Vec<Rect> {
+    let mut boxes: Vec<Rect> = Vec::new();
+    for &index in hits {
+        let bbox = glyphs[index].bbox;
+        match boxes.last_mut() {
+            Some(line) if (bbox.lly - line.lly).abs() < (line.ury - line.lly) / 2.0 => {
+                *line = line.union(&bbox);
+            }
+            _ => boxes.push(bbox),
+        }
+    }
+    boxes
+}
+
+/// Rewrite text-showing operators so the flagged glyphs are no longer drawn.
+///
+/// Each removed glyph becomes a `TJ` adjustment equal to its advance, so the
+/// glyphs after it stay where they were.
+fn remove_glyphs(ops: Vec<Operation>, glyphs: &[Glyph], remove: &[bool]) -> Vec<Operation> {
+    let mut by_op: BTreeMap<usize, Vec<&Glyph>> = BTreeMap::new();
+    for (glyph, _) in glyphs.iter().zip(remove).filter(|(_, r)| **r) {
+        by_op.entry(glyph.op).or_default().push(glyph);
+    }
+
+    let mut out = Vec::with_capacity(ops.len());
+    for (index, op) in ops.into_iter().enumerate() {
+        let Some(removed) = by_op.get(&index) else {
+            out.push(op);
+            continue;
+        };
+        let mut operands = op.operands;
+        let elements = match op.operator.as_str() {
+            "Tj" => operands.into_iter().take(1).collect(),
+            "'" => {
+                out.push(Operation::new("T*", vec![]));
+                operands.into_iter().take(1).collect()
+            }
+            "\"" if operands.len() == 3 => {
+                let string = operands.pop().into_iter().collect();
+                let spacing = operands.pop().unwrap_or(Object::Integer(0));
+                let word = operands.pop().unwrap_or(Object::Integer(0));
+                out.push(Operation::new("Tw", vec![word]));
+                out.push(Operation::new("Tc", vec![spacing]));
+                out.push(Operation::new("T*", vec![]));
+                string
+            }
+            "TJ" => match operands.pop() {
+                Some(Object::Array(items)) => items,
+                _ => Vec::new(),
+            },
+            _ => {
+                out.push(Operation::new(&op.operator, operands));
+                continue;
+            }
+        };
+
+        let mut items = Vec::with_capacity(elements.len());
+        for (element, item) in elements.into_iter().enumerate() {
+            let Object::String(bytes, format) = item else {
+                items.push(item);
+                continue;
+            };
+            let mut position = 0;
+            for glyph in removed.iter().filter(|g| g.element == element) {
+                if glyph.bytes.start > position {
+                    items.push(Object::String(
+                        bytes[position..glyph.bytes.start].to_vec(),
+                        format,
+                    ));
+                }
+                push_adjustment(&mut items, -glyph.advance);
+                position = glyph.bytes.end;
+            }
+            if position < bytes.len() {
+                items.push(Object::String(bytes[position..].to_vec(), format));
+            }
+        }
+        out.push(Operation::new("TJ", vec![Object::Array(items)]));
+    }
+    out
+}
+
+/// Append a `TJ` adjustment, merging it with a preceding one.
+fn push_adjustment(items: &mut Vec<Object>, amount: f32) {
+    if let Some(last) = items.last_mut() {
+        if let Ok(previous) = last.as_float() {
+            *last = Object::Real(previous + amount);
+            return;
+        }
+    }
+    items.push(Object::Real(amount));
+}
+
+/// Black out image pixels under `areas`. Returns `true` if something on the
+/// page (a form, or an image this cannot edit) still needs rasterizing.
+fn redact_xobjects(
+    doc: &mut Document,
+    page_id: ObjectId,
+    placements: &[text::Placement],
+    areas: &[Rect],
+    report: &mut RedactReport,
+) -> Result<bool> {
+    let mut resources = stamp::effective_resources(doc, page_id);
+    let Some(mut xobjects) = resources
+        .get(b"XObject")
+        .ok()
+        .and_then(|o| doc.dereference(o).ok())
+        .and_then(|(_, o)| o.as_dict().ok())
+        .cloned()
+    else {
+        return Ok(false);
+    };
+
+    let unit = Rect::from_size(1.0, 1.0);
+    let mut needs_raster = false;
+    let mut edited: BTreeMap<Vec<u8>, lopdf::Stream> = BTreeMap::new();
+    for placement in placements {
+        let Some(stream) = xobjects
+            .get(&placement.name)
+            .ok()
+            .and_then(|o| doc.dereference(o).ok())
+            .and_then(|(_, o)| o.as_stream().ok())
+        else {
+            continue;
+        };
+        let subtype = stream
+            .dict
+            .get(b"Subtype")
+            .and_then(Object::as_name)
+            .unwrap_or_default();
+        if subtype == b"Form" {
+            let bbox = stream
+                .dict
+                .get(b"BBox")
+                .ok()
+                .and_then(|o| Rect::from_object(doc, o))
+                .unwrap_or(unit);
+            let matrix = stream
+                .dict
+                .get(b"Matrix")
+                .and_then(Object::as_array)
+                .ok()
+                .and_then(|m| Matrix::from_operands(m))
+                .unwrap_or(Matrix::IDENTITY);
+            let placed = matrix.then(&placement.ctm).apply_rect(&bbox);
+            needs_raster |= areas.iter().any(|a| a.intersects(&placed));
+        } else if subtype == b"Image" {
+            let placed = placement.ctm.apply_rect(&unit);
+            if !areas.iter().any(|a| a.intersects(&placed)) {
+                continue;
+            }
+            let current = edited.get(&placement.name).unwrap_or(stream);
+            match blackout_image(current, &placement.ctm, areas) {
+                Some(redacted) => {
+                    edited.insert(placement.name.clone(), redacted);
+                }
+                None => needs_raster = true,
+            }
+        }
+    }
+
+    if edited.is_empty() {
+        return Ok(needs_raster);
+    }
+    // The image may be shared with other pages, so the page gets its own copy
+    // (and its own resource dictionary pointing at it).
+    report.images_redacted += edited.len();
+    for (name, stream) in edited {
+        let id = doc.add_object(stream);
+        xobjects.set(name, Object::Reference(id));
+    }
+    resources.set("XObject", Object::Dictionary(xobjects));
+    let page = doc
+        .get_dictionary_mut(page_id)
+        .map_err(|_| PdfError::InvalidArgument(format!("page object {page_id:?} not found")))?;
+    page.set("Resources", Object::Dictionary(resources));
+    Ok(needs_raster)
+}
+
+/// A copy of `image` with the pixels under `areas` painted black, or `None`
+/// if the image is not 8-bit Gray/RGB/CMYK in a filter lopdf can decode.
+fn blackout_image(image: &lopdf::Stream, ctm: &Matrix, areas: &[Rect]) -> Option<lopdf::Stream> {
+    let dict = &image.dict;
+    let int = |key: &[u8]| {
+        dict.get(key)
+            .and_then(Object::as_i64)
+            .ok()
+            .and_then(|n| usize::try_from(n).ok())
+    };
+    if int(b"BitsPerComponent") != Some(8) || dict.get(b"ImageMask").is_ok() {
+        return None;
+    }
+    let (width, height) = (int(b"Width")?, int(b"Height")?);
+    let components = match dict.get(b"ColorSpace").and_then(Object::as_name).ok()? {
+        b"DeviceGray" => 1,
+        b"DeviceRGB" => 3,
+        b"DeviceCMYK" => 4,
+        _ => return None,
+    };
+
+    let mut pixels = if dict.has(b"Filter") {
+        // lopdf refuses to decode streams marked as images.
+        let mut probe = image.clone();
+        probe.dict.remove(b"Subtype");
+        probe.decompressed_content().ok()?
+    } else {
+        image.content.clone()
+    };
+    let row_len = width * components;
+    if pixels.len() < row_len * height {
+        return None;
+    }
+
+    let inverse = ctm.invert()?;
+    let black: &[u8] = match components {
+        4 => &[0, 0, 0, 255],
+        3 => &[0, 0, 0],
+        _ => &[0],
+    };
+    for area in areas {
+        // Image space: (0, 0) is the bottom left, rows are stored top down.
+        let unit = inverse.apply_rect(area);
+        #[allow(
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            clippy::cast_precision_loss
+        )]
+        let span = |lo: f32, hi: f32, n: usize| {
+            // Image dimensions are far below f32's exact integer range.
+            let scale = n as f32;
+            let clamp = |v: f32| v.clamp(0.0, scale) as usize;
+            (clamp((lo * scale).floor()), clamp((hi * scale).ceil()))
+        };
+        let (x0, x1) = span(unit.llx, unit.urx, width);
+        let (y0, y1) = span(1.0 - unit.ury, 1.0 - unit.lly, height);
+        for row in y0..y1 {
+            for col in x0..x1 {
+                let at = row * row_len + col * components;
+                pixels[at..at + components].copy_from_slice(black);
+            }
+        }
+    }
+
+    let mut redacted = image.clone();
+    redacted.set_plain_content(pixels);
+    redacted.compress().ok()?;
+    Some(redacted)
+}
+
+/// Drop annotations (including form widgets and their values) whose
+/// rectangle overlaps a redaction area.
+fn redact_annotations(
+    doc: &mut Document,
+    page_id: ObjectId,
+    areas: &[Rect],
+    report: &mut RedactReport,
+) -> Result<()> {
+    let Ok(page) = doc.get_dictionary(page_id) else {
+        return Ok(());
+    };
+    let Some(annots) = page
+        .get(b"Annots")
+        .ok()
+        .and_then(|o| doc.dereference(o).ok())
+        .and_then(|(_, o)| o.as_array().ok())
+    else {
+        return Ok(());
+    };
+
+    let overlaps = |annot: &Object| {
+        let dict = match annot {
+            Object::Reference(id) => doc.get_dictionary(*id).ok(),
+            Object::Dictionary(d) => Some(d),
+            _ => None,
+        };
+        dict.and_then(|d| d.get(b"Rect").ok())
+            .and_then(|o| Rect::from_object(doc, o))
+            .is_some_and(|r| areas.iter().any(|a| a.intersects(&r)))
+    };
+    let (gone, kept): (Vec<Object>, Vec<Object>) =
+        annots.iter().cloned().partition(|a| overlaps(a));
+    if gone.is_empty() {
+        return Ok(());
+    }
+
+    report.annotations_removed += gone.len();
+    let page = doc
+        .get_dictionary_mut(page_id)
+        .map_err(|_| PdfError::InvalidArgument(format!("page object {page_id:?} not found")))?;
+    page.set("Annots", kept);
+    // Deleting the objects also removes them from AcroForm field lists, so
+    // widget values do not survive in the form.
+    for annot in gone {
+        if let Object::Reference(id) = annot {
+            doc.delete_object(id);
+        }
+    }
+    Ok(())
+}
+
+/// Content stream filling each area with black.
+fn black_boxes(areas: &[Rect]) -> Vec<u8> {
+    let mut out = String::from("q\n0 g\n");
+    for area in areas {
+        let r = area.normalized();
+        let _ = writeln!(
+            out,
+            "{} {} {} {} re f",
+            r.llx,
+            r.lly,
+            r.urx - r.llx,
+            r.ury - r.lly
+        );
+    }
+    out.push_str("Q\n");
+    out.into_bytes()
+}
+
+/// Replace the given pages with Ghostscript renderings of their current
+/// (already redacted) state.
+fn rasterize_pages(
+    doc: &mut Document,
+    pages: &BTreeMap<u32, ObjectId>,
+    numbers: &[u32],
+) -> Result<()> {
+    let gs = find_tool(Tool::Ghostscript)?;
+    let dir = TempSpace::new("redact")?;
+    let source = dir.file("redacted.pdf");
+    save_document(doc, &source)?;
+
+    for &number in numbers {
+        let raster_path = dir.file(&format!("page-{number}.pdf"));
+        let mut cmd = Command::new(&gs);
+        cmd.arg("-sDEVICE=pdfimage24")
+            .arg(format!("-r{RASTER_DPI}"))
+            .arg("-dNOPAUSE")
+            .arg("-dBATCH")
+            .arg("-dSAFER")
+            .arg(format!("-dFirstPage={number}"))
+            .arg(format!("-dLastPage={number}"))
+            .arg(format!("-sOutputFile={}", raster_path.display()))
+            .arg(&source);
+        run_tool(Tool::Ghostscript, cmd)?;
+
+        let raster = load_document(&raster_path)?;
+        let raster_page = raster
+            .get_pages()
+            .get(&1)
+            .and_then(|id| raster.get_dictionary(*id).ok())
+            .ok_or_else(|| {
+                PdfError::InvalidArgument(format!("ghostscript produced no image of page {number}"))
+            })?;
+        let mut map = BTreeMap::new();
+        let mut imported = Vec::new();
+        for key in ["Resources", "Contents", "MediaBox"] {
+            if let Ok(value) = raster_page.get(key.as_bytes()) {
+                imported.push((key, objects::import_object(doc, &raster, value, &mut map)));
+            }
+        }
+
+        let Some(&page_id) = pages.get(&number) else {
+            continue;
+        };
+        let page = doc
+            .get_dictionary_mut(page_id)
+            .map_err(|_| PdfError::InvalidArgument(format!("page object {page_id:?} not found")))?;
+        for (key, value) in imported {
+            page.set(key, value);
+        }
+        // The rendering already shows the page rotated, cropped, and with its
+        // annotation appearances.
+        for key in [
+            "CropBox", "BleedBox", "TrimBox", "ArtBox", "Rotate", "Annots",
+        ] {
+            page.remove(key.as_bytes());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page_with_text(content: &[u8]) -> (Document, ObjectId) {
+        let mut doc = Document::with_version("1.4");
+        let font = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type1".to_vec())),
+            ("BaseFont", Object::Name(b"Courier".to_vec())),
+        ]));
+        let contents = doc.add_object(lopdf::Stream::new(
+            lopdf::Dictionary::new(),
+            content.to_vec(),
+        ));
+        let resources = lopdf::Dictionary::from_iter([(
+            "Font",
+            Object::Dictionary(lopdf::Dictionary::from_iter([(
+                "F1",
+                Object::Reference(font),
+            )])),
+        )]);
+        let page = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", Object::Name(b"Page".to_vec())),
+            ("Resources", Object::Dictionary(resources)),
+            ("Contents", Object::Reference(contents)),
+        ]));
+        (doc, page)
+    }
+
+    fn shown_text(doc: &Document, page: ObjectId) -> String {
+        let ops = doc
+            .get_and_decode_page_content(page)
+            .map(|c| c.operations)
+            .unwrap_or_default();
+        text::layout(doc, page, &ops)
+            .glyphs
+            .iter()
+            .map(|g| g.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn matched_text_is_removed_and_spacing_kept() -> Result<()> {
+        let (mut doc, page) = page_with_text(b"BT /F1 10 Tf 0 0 Td (SSN: 123-45-6789 ok) Tj ET");
+        let matcher = build_matcher(&[r"\d{3}-\d{2}-\d{4}".to_string()], true)?;
+        let mut report = RedactReport::default();
+        let raster = redact_page(&mut doc, page, 1, Vec::new(), matcher.as_ref(), &mut report)?;
+
+        assert!(!raster);
+        assert_eq!(report.matches, 1);
+        assert_eq!(report.glyphs_removed, 11);
+        assert_eq!(shown_text(&doc, page), "SSN:  ok");
+
+        // "ok" is still drawn where it was: 17 Courier glyphs (6pt each) in.
+        let ops = doc.get_and_decode_page_content(page).map(|c| c.operations);
+        let layout = text::layout(&doc, page, &ops.unwrap_or_default());
+        let ok = layout
+            .glyphs
+            .iter()
+            .find(|g| g.text == "o")
+            .map(|g| g.bbox.llx);
+        assert!(ok.is_some_and(|x| (x - 102.0).abs() < 0.01));
+        Ok(())
+    }
+
+    #[test]
+    fn image_pixels_under_region_are_blacked_out() {
+        let image = lopdf::Stream::new(
+            lopdf::Dictionary::from_iter([
+                ("Subtype", Object::Name(b"Image".to_vec())),
+                ("Width", Object::Integer(4)),
+                ("Height", Object::Integer(2)),
+                ("BitsPerComponent", Object::Integer(8)),
+                ("ColorSpace", Object::Name(b"DeviceGray".to_vec())),
+            ]),
+            vec![255; 8],
+        );
+        // Drawn at 40x20 points; redact its top-left quarter-width column.
+        let ctm = Matrix([40.0, 0.0, 0.0, 20.0, 0.0, 0.0]);
+        let area = Rect {
+            llx: 0.0,
+            lly: 10.0,
+            urx: 10.0,
+            ury: 20.0,
+        };
+        let pixels = blackout_image(&image, &ctm, &[area])
+            .and_then(|s| s.get_plain_content().ok())
+            .unwrap_or_default();
+        assert_eq!(pixels, vec![0, 255, 255, 255, 255, 255, 255, 255]);
+    }
+}
@@ -0,0 +1,248 @@
+//! Remote inputs: `https://` and `s3://` URLs downloaded with curl into a
+//! scratch directory, so they can be processed like local files.
+//!
+//! `s3://bucket/key` is fetched over HTTPS with curl's `--aws-sigv4`
+//! signing, using the credentials in `AWS_ACCESS_KEY_ID`,
+//! `AWS_SECRET_ACCESS_KEY`, and (for temporary credentials)
+//! `AWS_SESSION_TOKEN`, the region in `AWS_REGION` or `AWS_DEFAULT_REGION`
+//! (default `us-east-1`), and, for S3-compatible stores, the endpoint in
+//! `AWS_ENDPOINT_URL_S3` or `AWS_ENDPOINT_URL`. The credentials are handed
+//! to curl in a config file, never on its command line.
+
+use std::{
+    env,
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    find_tool, find_tool_min, plan::temp_path, run_tool_writing, tempspace::TempSpace, PdfError,
+    Plan, Result, Tool,
+};
+
+/// A downloaded input, removed when this is dropped.
+pub struct RemoteInput {
+    _space: TempSpace,
+    path: PathBuf,
+}
+
+impl RemoteInput {
+    /// Where the input was downloaded to; the file keeps the URL's file name.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Whether `input` is a URL that [`fetch`] downloads rather than a path.
+#[must_use]
+pub fn is_remote(input: &str) -> bool {
+    input.starts_with("https://") || input.starts_with("s3://")
+}
+
+/// Download `url` (`https://…` or `s3://bucket/key`) into a scratch
+/// directory with curl. Redirects are followed, but only to HTTPS.
+pub fn fetch(url: &str) -> Result<RemoteInput> {
+    let source = Source::parse(url)?;
+    let space = TempSpace::new("fetch")?;
+    let path = space.file(&file_name(url));
+    let config = match &source {
+        Source::Https(_) => None,
+        Source::S3 { .. } => Some(space.write("curl.conf", s3_config()?)?),
+    };
+    run_tool_writing(
+        Tool::Curl,
+        command(&source, config.as_deref(), &path)?,
+        &path,
+    )?;
+    Ok(RemoteInput {
+        _space: space,
+        path,
+    })
+}
+
+/// What [`fetch`] would run.
+pub fn fetch_plan(url: &str) -> Result<Plan> {
+    let source = Source::parse(url)?;
+    let path = temp_path(&file_name(url));
+    let config = match &source {
+        Source::Https(_) => None,
+        Source::S3 { .. } => {
+            s3_config()?;
+            Some(temp_path("curl.conf"))
+        }
+    };
+    Ok(Plan::new()
+        .run(Tool::Curl, &command(&source, config.as_deref(), &path)?)
+        .write(&path))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Source {
+    Https(String),
+    S3 {
+        /// The object's HTTP(S) URL.
+        url: String,
+        region: String,
+    },
+}
+
+impl Source {
+    fn parse(url: &str) -> Result<Self> {
+        if url.starts_with("https://") {
+            return Ok(Self::Https(url.to_string()));
+        }
+        let Some(location) = url.strip_prefix("s3://") else {
+            return Err(PdfError::InvalidArgument(format!(
+                "{url}: only https:// and s3:// URLs are supported"
+            )));
+        };
+        let region = env::var("AWS_REGION")
+            .or_else(|_| env::var("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|_| "us-east-1".to_string());
+        let endpoint = env::var("AWS_ENDPOINT_URL_S3")
+            .or_else(|_| env::var("AWS_ENDPOINT_URL"))
+            .ok();
+        let url = s3_object_url(location, &region, endpoint.as_deref())
+            .ok_or_else(|| PdfError::InvalidArgument(format!("{url}: expected s3://bucket/key")))?;
+        Ok(Self::S3 { url, region })
+    }
+}
+
+/// The URL of `bucket/key` in `region`: virtual-hosted on AWS (path-style
+/// for bucket names with dots, which the wildcard certificate does not
+/// cover), path-style on a custom `endpoint`.
+fn s3_object_url(location: &str, region: &str, endpoint: Option<&str>) -> Option<String> {
+    let (bucket, key) = location.split_once('/')?;
+    if bucket.is_empty() || key.is_empty() {
+        return None;
+    }
+    let key = encode_key(key);
+    Some(match endpoint {
+        Some(endpoint) => format!("{}/{bucket}/{key}", endpoint.trim_end_matches('/')),
+        None if bucket.contains('.') => format!("https://s3.{region}.amazonaws.com/{bucket}/{key}"),
+        None => format!("https://{bucket}.s3.{region}.amazonaws.com/{key}"),
+    })
+}
+
+/// Percent-encode an object key as S3 signs it: everything but unreserved
+/// characters and `/`.
+fn encode_key(key: &str) -> String {
+    let mut encoded = String::with_capacity(key.len());
+    for byte in key.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~/".contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            let _ = write!(encoded, "%{byte:02X}");
+        }
+    }
+    encoded
+}
+
+/// A curl config file holding the credentials for an S3 request.
+fn s3_config() -> Result<String> {
+    let (Ok(key), Ok(secret)) = (
+        env::var("AWS_ACCESS_KEY_ID"),
+        env::var("AWS_SECRET_ACCESS_KEY"),
+    ) else {
+        return Err(PdfError::InvalidArgument(
+            "s3:// inputs need AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY".to_string(),
+        ));
+    };
+    let quote = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut config = format!("user = \"{}:{}\"\n", quote(&key), quote(&secret));
+    if let Ok(token) = env::var("AWS_SESSION_TOKEN") {
+        let _ = writeln!(
+            config,
+            "header = \"x-amz-security-token: {}\"",
+            quote(&token)
+        );
+    }
+    Ok(config)
+}
+
+/// The name to download `url` as: its last path segment, or `input`.
+fn file_name(url: &str) -> String {
+    let path = url
+        .split_once("://")
+        .map_or(url, |(_, rest)| rest)
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default();
+    let name = path
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control() && *c != '\\')
+        .collect::<String>();
+    match name.as_str() {
+        "" | "." | ".." | "curl.conf" => "input".to_string(),
+        _ if !path.contains('/') => "input".to_string(),
+        _ => name,
+    }
+}
+
+fn command(source: &Source, config: Option<&Path>, output: &Path) -> Result<Command> {
+    let mut cmd = match source {
+        Source::Https(_) => Command::new(find_tool(Tool::Curl)?),
+        Source::S3 { .. } => Command::new(find_tool_min(Tool::Curl, &[7, 75], "s3:// inputs")?),
+    };
+    cmd.arg("--silent")
+        .arg("--show-error")
+        .arg("--fail")
+        .arg("--location");
+    let url = match source {
+        Source::Https(url) => url,
+        Source::S3 { url, region } => {
+            if let Some(config) = config {
+                cmd.arg("--config").arg(config.as_os_str());
+            }
+            cmd.arg("--aws-sigv4").arg(format!("aws:amz:{region}:s3"));
+            url
+        }
+    };
+    if url.starts_with("https://") {
+        cmd.arg("--proto")
+            .arg("=https")
+            .arg("--proto-redir")
+            .arg("=https");
+    }
+    cmd.arg("--output").arg(output.as_os_str()).arg(url);
+    Ok(cmd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn s3_locations_map_to_object_urls() {
+        assert_eq!(
+            s3_object_url("inbox/2024/scan 1.pdf", "eu-west-1", None).as_deref(),
+            Some("https://inbox.s3.eu-west-1.amazonaws.com/2024/scan%201.pdf")
+        );
+        assert_eq!(
+            s3_object_url("my.bucket/a+b.pdf", "us-east-1", None).as_deref(),
+            Some("https://s3.us-east-1.amazonaws.com/my.bucket/a%2Bb.pdf")
+        );
+        assert_eq!(
+            s3_object_url("inbox/a.pdf", "us-east-1", Some("http://localhost:9000/")).as_deref(),
+            Some("http://localhost:9000/inbox/a.pdf")
+        );
+        assert_eq!(s3_object_url("inbox", "us-east-1", None), None);
+        assert_eq!(s3_object_url("inbox/", "us-east-1", None), None);
+    }
+
+    #[test]
+    fn downloads_keep_the_url_file_name() {
+        assert_eq!(
+            file_name("https://example.com/docs/report.pdf?x=1"),
+            "report.pdf"
+        );
+        assert_eq!(file_name("s3://inbox/2024/scan.pdf"), "scan.pdf");
+        assert_eq!(file_name("https://example.com"), "input");
+        assert_eq!(file_name("https://example.com/docs/"), "input");
+    }
+}
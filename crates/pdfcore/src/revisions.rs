@@ -0,0 +1,189 @@
+//! Incremental-update history: the revisions a file was saved in, and the
+//! bytes of each earlier version.
+//!
+//! An incremental update appends changed objects, a new cross-reference
+//! section, and a trailer ending in `%%EOF` to the file, leaving the earlier
+//! bytes untouched. Cutting the file after the `%%EOF` of a revision
+//! therefore gives back that version exactly, which is how a signed
+//! document can be compared with what was later added to it.
+
+use std::{fs, io::Read, path::Path};
+
+use crate::{
+    features::{eof_marker_ends, is_linearized},
+    load_document,
+    plan::Plan,
+    validate_input_file, PdfError, Result,
+};
+
+/// One revision of a file, as returned by [`revisions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Revision {
+    /// 0 for the original document, then 1, 2, ... for each update.
+    pub number: u32,
+    /// Byte offset where the revision starts.
+    pub offset: u64,
+    /// Length of the revision in bytes.
+    pub size: u64,
+}
+
+impl Revision {
+    /// Byte offset just past the end of the revision: the size of the file
+    /// as it was when this revision was saved.
+    #[must_use]
+    pub fn end(&self) -> u64 {
+        self.offset + self.size
+    }
+}
+
+/// List the revisions of a file, oldest first (pure Rust). A file without
+/// incremental updates has one revision. Bytes after the last `%%EOF`
+/// marker (such as trailing garbage) belong to the last revision.
+pub fn revisions(path: impl AsRef<Path>) -> Result<Vec<Revision>> {
+    let path = path.as_ref();
+    let doc = load_document(path)?;
+    let len = fs::metadata(path)?.len();
+    let mut ends: Vec<u64> = eof_marker_ends(path)?
+        .into_iter()
+        .map(|end| end + eol_len(path, end).unwrap_or(0))
+        .collect();
+    // A linearized file ends its first-page section with `%%EOF` too.
+    if is_linearized(&doc) && ends.len() > 1 {
+        ends.remove(0);
+    }
+    match ends.last_mut() {
+        Some(last) => *last = len,
+        None => ends.push(len),
+    }
+
+    let mut offset = 0;
+    let mut out = Vec::with_capacity(ends.len());
+    for (number, end) in (0..).zip(ends) {
+        out.push(Revision {
+            number,
+            offset,
+            size: end - offset,
+        });
+        offset = end;
+    }
+    Ok(out)
+}
+
+/// Write revision `number` (0 for the original document) of `input` to
+/// `output`, byte for byte as it was saved.
+pub fn extract_revision(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    number: u32,
+) -> Result<Revision> {
+    let input = input.as_ref();
+    let revision = find_revision(input, number)?;
+    let mut bytes = Vec::new();
+    fs::File::open(input)?
+        .take(revision.end())
+        .read_to_end(&mut bytes)?;
+    fs::write(output.as_ref(), bytes)?;
+    Ok(revision)
+}
+
+/// What [`extract_revision`] would do.
+pub fn extract_revision_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    number: u32,
+) -> Result<Plan> {
+    let revision = find_revision(input.as_ref(), number)?;
+    Ok(Plan::new()
+        .native(format!(
+            "copy the first {} bytes (revision {number})",
+            revision.end()
+        ))
+        .write(output.as_ref()))
+}
+
+fn find_revision(input: &Path, number: u32) -> Result<Revision> {
+    validate_input_file(input)?;
+    let all = revisions(input)?;
+    let count = all.len();
+    all.into_iter().find(|r| r.number == number).ok_or_else(|| {
+        PdfError::InvalidArgument(format!(
+            "revision {number} does not exist (the file has {count} revision(s), numbered from 0)"
+        ))
+    })
+}
+
+/// Length of the end-of-line marker at `offset` (`\r\n`, `\n`, or `\r`).
+fn eol_len(path: &Path, offset: u64) -> Result<u64> {
+    use std::io::{Seek, SeekFrom};
+
+    let mut file = fs::File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut next = [0; 2];
+    let read = file.read(&mut next)?;
+    Ok(match &next[..read] {
+        [b'\r', b'\n'] => 2,
+        [b'\r' | b'\n', ..] => 1,
+        _ => 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{Dictionary, Document, IncrementalDocument, Object};
+
+    #[test]
+    fn earlier_revisions_are_recovered_exactly() -> Result<()> {
+        let mut doc = Document::with_version("1.4");
+        let pages = doc.new_object_id();
+        doc.objects.insert(
+            pages,
+            Object::Dictionary(Dictionary::from_iter([
+                ("Type", Object::Name(b"Pages".to_vec())),
+                ("Kids", Object::Array(Vec::new())),
+                ("Count", Object::Integer(0)),
+            ])),
+        );
+        let catalog = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Catalog".to_vec())),
+            ("Pages", Object::Reference(pages)),
+        ]));
+        doc.trailer.set("Root", catalog);
+        doc.reference_table.cross_reference_type = lopdf::xref::XrefType::CrossReferenceTable;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("signed.pdf");
+        doc.save(&path)?;
+        let original = fs::read(&path)?;
+
+        let loaded = Document::load_mem(&original).map_err(|source| PdfError::PdfParse {
+            path: path.clone(),
+            source,
+        })?;
+        let mut update = IncrementalDocument::create_from(original.clone(), loaded);
+        update.new_document.objects.insert(
+            catalog,
+            Object::Dictionary(Dictionary::from_iter([
+                ("Type", Object::Name(b"Catalog".to_vec())),
+                ("Pages", Object::Reference(pages)),
+                ("Lang", Object::string_literal("en")),
+            ])),
+        );
+        update.save(&path)?;
+
+        let found = revisions(&path)?;
+        assert_eq!(found.len(), 2);
+        assert_eq!((found[0].offset, found[1].offset), (0, found[0].end()));
+        assert_eq!(found[1].end(), fs::metadata(&path)?.len());
+
+        // The update starts on a new line, which counts as the end of the
+        // first revision's `%%EOF` line.
+        let extracted = dir.path().join("original.pdf");
+        extract_revision(&path, &extracted, 0)?;
+        assert_eq!(fs::read(&extracted)?, [original.as_slice(), b"\n"].concat());
+        assert!(matches!(
+            extract_revision(&path, &extracted, 2),
+            Err(PdfError::InvalidArgument(_))
+        ));
+        Ok(())
+    }
+}
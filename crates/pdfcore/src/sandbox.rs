@@ -0,0 +1,134 @@
+//! Limits for external tools run on untrusted input ([`ToolRunOptions`]).
+//!
+//! On Unix the resource limits are set with `ulimit` by `/bin/sh`, which then
+//! `exec`s the tool: they are in force from the tool's first instruction and
+//! are inherited by anything it starts. Windows has no equivalent without
+//! Job Objects, which are not supported yet, so asking for resource limits
+//! there is an error rather than a silently unlimited run.
+
+use std::{process::Command, time::Duration};
+
+use crate::{tempspace::TempSpace, Result};
+
+/// How external tools are run (see [`crate::set_tool_run_options`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolRunOptions {
+    /// Kill a tool (and, on Unix, its process group) that runs longer.
+    pub timeout: Option<Duration>,
+    /// Largest address space a tool may use, in bytes.
+    pub max_memory: Option<u64>,
+    /// CPU time after which a tool is killed.
+    pub max_cpu_time: Option<Duration>,
+    /// Largest file a tool may write, in bytes.
+    pub max_file_size: Option<u64>,
+    /// Give each tool a fresh temporary directory (`TMPDIR`, `TMP`, `TEMP`)
+    /// that only the current user can access, removed when the tool exits.
+    pub private_tmp: bool,
+}
+
+impl ToolRunOptions {
+    /// Limits suitable for untrusted uploads: 2 GiB of memory, 5 minutes of
+    /// CPU, 1 GiB per written file, and a private temporary directory.
+    #[must_use]
+    pub fn sandboxed() -> Self {
+        Self {
+            timeout: None,
+            max_memory: Some(2 << 30),
+            max_cpu_time: Some(Duration::from_mins(5)),
+            max_file_size: Some(1 << 30),
+            private_tmp: true,
+        }
+    }
+
+    fn has_limits(&self) -> bool {
+        self.max_memory.is_some() || self.max_cpu_time.is_some() || self.max_file_size.is_some()
+    }
+}
+
+/// `cmd` adjusted to run under `options`, with the private temporary
+/// directory (if any), which must outlive the tool.
+pub(crate) fn prepare(
+    mut cmd: Command,
+    options: &ToolRunOptions,
+) -> Result<(Command, Option<TempSpace>)> {
+    let tmp = if options.private_tmp {
+        let dir = TempSpace::new("tool")?;
+        for var in ["TMPDIR", "TMP", "TEMP"] {
+            cmd.env(var, dir.path());
+        }
+        Some(dir)
+    } else {
+        None
+    };
+    if options.has_limits() {
+        cmd = with_limits(&cmd, options)?;
+    }
+    Ok((cmd, tmp))
+}
+
+#[cfg(unix)]
+#[allow(clippy::unnecessary_wraps)]
+fn with_limits(cmd: &Command, options: &ToolRunOptions) -> Result<Command> {
+    let mut steps = Vec::new();
+    if let Some(bytes) = options.max_memory {
+        steps.push(format!("ulimit -v {}", bytes.div_ceil(1024)));
+    }
+    if let Some(cpu) = options.max_cpu_time {
+        steps.push(format!("ulimit -t {}", cpu.as_secs().max(1)));
+    }
+    if let Some(bytes) = options.max_file_size {
+        // POSIX shells count file sizes in 512-byte blocks.
+        steps.push(format!("ulimit -f {}", bytes.div_ceil(512)));
+    }
+    steps.push("exec \"$0\" \"$@\"".to_string());
+    let script = steps.join(" && ");
+
+    let mut wrapped = Command::new("/bin/sh");
+    wrapped
+        .arg("-c")
+        .arg(script)
+        .arg(cmd.get_program())
+        .args(cmd.get_args());
+    for (key, value) in cmd.get_envs() {
+        match value {
+            Some(value) => wrapped.env(key, value),
+            None => wrapped.env_remove(key),
+        };
+    }
+    if let Some(dir) = cmd.get_current_dir() {
+        wrapped.current_dir(dir);
+    }
+    Ok(wrapped)
+}
+
+#[cfg(not(unix))]
+fn with_limits(_cmd: &Command, _options: &ToolRunOptions) -> Result<Command> {
+    Err(crate::PdfError::InvalidArgument(
+        "memory, CPU, and file size limits for external tools are only supported on Unix"
+            .to_string(),
+    ))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::PdfError;
+
+    #[test]
+    fn tools_run_with_limits_in_a_private_tmp() -> Result<()> {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "ulimit -v; ulimit -t; ulimit -f; echo \"$TMPDIR\""]);
+        let (mut cmd, tmp) = prepare(cmd, &ToolRunOptions::sandboxed())?;
+        let out = cmd.output()?;
+        let text = String::from_utf8_lossy(&out.stdout);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[..3], ["2097152", "300", "2097152"], "{text}");
+
+        let tmp = tmp.ok_or_else(|| PdfError::InvalidArgument("no private tmp".to_string()))?;
+        assert_eq!(lines.get(3).copied(), tmp.path().to_str());
+        let path = tmp.path().to_path_buf();
+        drop(tmp);
+        assert!(!path.exists());
+        Ok(())
+    }
+}
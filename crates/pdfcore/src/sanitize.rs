@@ -0,0 +1,424 @@
+//! Active-content audit and removal: scripts, launch actions, embedded
+//! files, and references to external resources.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    path::Path,
+};
+
+use crate::{
+    fonts::resolve_dict, links, load_document, metadata, objects, plan::Plan, save_document, Result,
+};
+
+/// Kinds of content removed by [`sanitize`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ActiveContent {
+    /// JavaScript actions and document-level scripts.
+    JavaScript,
+    /// `/Launch` actions (run a program or open a file).
+    LaunchAction,
+    /// Embedded files and file attachment annotations.
+    EmbeddedFile,
+    /// Actions that reach outside the document: URIs, other PDFs, form
+    /// submission, and data import.
+    ExternalReference,
+}
+
+impl ActiveContent {
+    /// Lower-case identifier, e.g. `launch-action`.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::JavaScript => "javascript",
+            Self::LaunchAction => "launch-action",
+            Self::EmbeddedFile => "embedded-file",
+            Self::ExternalReference => "external-reference",
+        }
+    }
+}
+
+impl fmt::Display for ActiveContent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// One removed (or, when auditing, removable) item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SanitizeFinding {
+    /// What kind of content it is.
+    pub kind: ActiveContent,
+    /// Human-readable detail (script name, URI, file name, …).
+    pub detail: String,
+}
+
+/// Result of [`sanitize`] or [`audit_active_content`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SanitizeReport {
+    /// Everything removed (or found), in document order.
+    pub findings: Vec<SanitizeFinding>,
+}
+
+impl SanitizeReport {
+    /// Number of findings of one kind.
+    #[must_use]
+    pub fn count(&self, kind: ActiveContent) -> usize {
+        self.findings.iter().filter(|f| f.kind == kind).count()
+    }
+
+    /// `true` if nothing was found.
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+}
+
+/// Remove JavaScript, launch actions, embedded files, and external
+/// references, reporting what was removed (pure Rust).
+pub fn sanitize(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<SanitizeReport> {
+    let mut doc = load_document(input.as_ref())?;
+    let report = sanitize_document(&mut doc);
+    save_document(&mut doc, output.as_ref())?;
+    Ok(report)
+}
+
+/// Report what [`sanitize`] would remove, without writing anything.
+pub fn audit_active_content(path: impl AsRef<Path>) -> Result<SanitizeReport> {
+    let mut doc = load_document(path.as_ref())?;
+    Ok(sanitize_document(&mut doc))
+}
+
+/// What [`sanitize`] would do: one step per item it would remove.
+pub fn sanitize_plan(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<Plan> {
+    let report = audit_active_content(input)?;
+    let plan = report.findings.iter().fold(Plan::new(), |plan, f| {
+        plan.native(format!("remove {}: {}", f.kind, f.detail))
+    });
+    Ok(plan.write(output.as_ref()))
+}
+
+pub(crate) fn sanitize_document(doc: &mut lopdf::Document) -> SanitizeReport {
+    let mut findings = Vec::new();
+    remove_name_trees(doc, &mut findings);
+    remove_file_attachments(doc, &mut findings);
+    remove_actions(doc, &mut findings);
+
+    // Drop the now-unreferenced payloads.
+    doc.objects.retain(|_, obj| {
+        let dict = match obj {
+            lopdf::Object::Dictionary(d) => d,
+            lopdf::Object::Stream(s) => &s.dict,
+            _ => return true,
+        };
+        !(dict.type_is(b"EmbeddedFile") || (dict.type_is(b"Filespec") && dict.has(b"EF")))
+    });
+    SanitizeReport { findings }
+}
+
+/// Classify an action dictionary, with a short description.
+///
+/// Without `doc`, indirect file specifications and strings cannot be
+/// resolved and are left out of the description.
+fn classify_action(
+    doc: Option<&lopdf::Document>,
+    action: &lopdf::Dictionary,
+) -> Option<(ActiveContent, String)> {
+    let kind = action.get(b"S").and_then(lopdf::Object::as_name).ok()?;
+    let text = |key: &[u8]| {
+        action
+            .get(key)
+            .ok()
+            .and_then(|o| match (o, doc) {
+                (lopdf::Object::Reference(_), Some(doc)) => doc.dereference(o).ok().map(|(_, o)| o),
+                (lopdf::Object::Reference(_), None) => None,
+                _ => Some(o),
+            })
+            .and_then(|o| match o {
+                lopdf::Object::String(s, _) => Some(metadata::decode_text_string(s)),
+                lopdf::Object::Dictionary(spec) => ["UF", "F"].iter().find_map(|k| {
+                    spec.get(k.as_bytes())
+                        .and_then(lopdf::Object::as_str)
+                        .ok()
+                        .map(metadata::decode_text_string)
+                }),
+                _ => None,
+            })
+            .unwrap_or_default()
+    };
+    let found = match kind {
+        b"JavaScript" => (ActiveContent::JavaScript, "JavaScript action".to_string()),
+        b"Launch" => (
+            ActiveContent::LaunchAction,
+            format!("launch {}", text(b"F")),
+        ),
+        b"URI" => (
+            ActiveContent::ExternalReference,
+            format!("URI {}", text(b"URI")),
+        ),
+        b"GoToR" | b"GoToE" => (
+            ActiveContent::ExternalReference,
+            format!("link to file {}", text(b"F")),
+        ),
+        b"SubmitForm" => (
+            ActiveContent::ExternalReference,
+            format!("form submission to {}", text(b"F")),
+        ),
+        b"ImportData" => (
+            ActiveContent::ExternalReference,
+            format!("data import from {}", text(b"F")),
+        ),
+        _ => return None,
+    };
+    Some(found)
+}
+
+/// Remove `/A`, `/OpenAction`, and `/AA` entries that trigger flagged actions.
+fn remove_actions(doc: &mut lopdf::Document, findings: &mut Vec<SanitizeFinding>) {
+    let flagged: BTreeMap<lopdf::ObjectId, (ActiveContent, String)> = doc
+        .objects
+        .iter()
+        .filter_map(|(id, obj)| Some((*id, classify_action(Some(doc), obj.as_dict().ok()?)?)))
+        .collect();
+    let check = |value: &lopdf::Object| -> Option<(ActiveContent, String)> {
+        match value {
+            lopdf::Object::Reference(id) => flagged.get(id).cloned(),
+            lopdf::Object::Dictionary(action) => classify_action(None, action),
+            _ => None,
+        }
+    };
+    for obj in doc.objects.values_mut() {
+        objects::visit_dicts_mut(obj, &mut |dict| {
+            for key in [b"A".as_slice(), b"OpenAction"] {
+                if let Some((kind, detail)) = dict.get(key).ok().and_then(check) {
+                    dict.remove(key);
+                    findings.push(SanitizeFinding { kind, detail });
+                }
+            }
+            let Ok(lopdf::Object::Dictionary(aa)) = dict.get_mut(b"AA") else {
+                return;
+            };
+            let triggers: Vec<Vec<u8>> = aa.iter().map(|(k, _)| k.clone()).collect();
+            for trigger in triggers {
+                if let Some((kind, detail)) = aa.get(&trigger).ok().and_then(check) {
+                    aa.remove(&trigger);
+                    findings.push(SanitizeFinding {
+                        kind,
+                        detail: format!("{detail} (on {})", String::from_utf8_lossy(&trigger)),
+                    });
+                }
+            }
+            if aa.is_empty() {
+                dict.remove(b"AA");
+            }
+        });
+    }
+
+    let ids: BTreeSet<lopdf::ObjectId> = flagged.into_keys().collect();
+    doc.objects.retain(|id, _| !ids.contains(id));
+}
+
+/// Remove the document-level `/JavaScript` and `/EmbeddedFiles` name trees.
+fn remove_name_trees(doc: &mut lopdf::Document, findings: &mut Vec<SanitizeFinding>) {
+    let Some((names_id, names)) = doc.catalog().ok().and_then(|c| {
+        let entry = c.get(b"Names").ok()?;
+        Some((entry.as_reference().ok(), resolve_dict(doc, Some(entry))?))
+    }) else {
+        return;
+    };
+
+    let mut remove = Vec::new();
+    for (key, kind, label) in [
+        (
+            b"JavaScript".as_slice(),
+            ActiveContent::JavaScript,
+            "document script",
+        ),
+        (
+            b"EmbeddedFiles",
+            ActiveContent::EmbeddedFile,
+            "embedded file",
+        ),
+    ] {
+        let Some(tree) = resolve_dict(doc, names.get(key).ok()) else {
+            continue;
+        };
+        let mut entries = BTreeMap::new();
+        links::walk_name_tree(doc, tree, &mut entries, 0);
+        findings.extend(entries.keys().map(|name| SanitizeFinding {
+            kind,
+            detail: format!("{label} {}", metadata::decode_text_string(name)),
+        }));
+        remove.push(key);
+    }
+
+    let names = match names_id {
+        Some(id) => doc.get_dictionary_mut(id).ok(),
+        None => doc
+            .catalog_mut()
+            .ok()
+            .and_then(|c| c.get_mut(b"Names").ok())
+            .and_then(|n| n.as_dict_mut().ok()),
+    };
+    if let Some(names) = names {
+        for key in remove {
+            names.remove(key);
+        }
+    }
+    if let Ok(catalog) = doc.catalog_mut() {
+        catalog.remove(b"AF");
+    }
+}
+
+/// Remove `/FileAttachment` annotations from every page.
+fn remove_file_attachments(doc: &mut lopdf::Document, findings: &mut Vec<SanitizeFinding>) {
+    let mut edits = Vec::new();
+    for page_id in doc.get_pages().into_values() {
+        let Some(annots) = doc
+            .get_dictionary(page_id)
+            .ok()
+            .and_then(|page| page.get(b"Annots").ok())
+        else {
+            continue;
+        };
+        let Ok((_, lopdf::Object::Array(items))) = doc.dereference(annots) else {
+            continue;
+        };
+        let mut keep = Vec::with_capacity(items.len());
+        for item in items {
+            let attachment = resolve_dict(doc, Some(item)).filter(|a| {
+                a.get(b"Subtype")
+                    .and_then(lopdf::Object::as_name)
+                    .is_ok_and(|s| s == b"FileAttachment")
+            });
+            match attachment {
+                Some(annot) => {
+                    let name = resolve_dict(doc, annot.get(b"FS").ok())
+                        .and_then(|fs| {
+                            ["UF", "F"].iter().find_map(|k| {
+                                fs.get(k.as_bytes()).and_then(lopdf::Object::as_str).ok()
+                            })
+                        })
+                        .map(metadata::decode_text_string)
+                        .unwrap_or_default();
+                    findings.push(SanitizeFinding {
+                        kind: ActiveContent::EmbeddedFile,
+                        detail: format!("file attachment annotation {name}"),
+                    });
+                }
+                None => keep.push(item.clone()),
+            }
+        }
+        if keep.len() < items.len() {
+            edits.push((page_id, annots.as_reference().ok(), keep));
+        }
+    }
+
+    for (page_id, annots_id, keep) in edits {
+        match annots_id {
+            Some(id) => {
+                doc.objects.insert(id, lopdf::Object::Array(keep));
+            }
+            None => {
+                if let Ok(page) = doc.get_dictionary_mut(page_id) {
+                    page.set("Annots", keep);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_removes_actions_scripts_and_attachments() {
+        let mut doc = lopdf::Document::with_version("1.4");
+        let launch = doc.add_object(lopdf::Dictionary::from_iter([
+            ("S", lopdf::Object::Name(b"Launch".to_vec())),
+            ("F", lopdf::Object::string_literal("cmd.exe")),
+        ]));
+        let script = doc.add_object(lopdf::Dictionary::from_iter([
+            ("S", lopdf::Object::Name(b"JavaScript".to_vec())),
+            ("JS", lopdf::Object::string_literal("app.alert(1)")),
+        ]));
+        let file = doc.add_object(lopdf::Stream::new(
+            lopdf::Dictionary::from_iter([("Type", lopdf::Object::Name(b"EmbeddedFile".to_vec()))]),
+            b"payload".to_vec(),
+        ));
+        let uri_link = lopdf::Dictionary::from_iter([
+            ("Subtype", lopdf::Object::Name(b"Link".to_vec())),
+            (
+                "A",
+                lopdf::Object::Dictionary(lopdf::Dictionary::from_iter([
+                    ("S", lopdf::Object::Name(b"URI".to_vec())),
+                    ("URI", lopdf::Object::string_literal("https://example.com")),
+                ])),
+            ),
+        ]);
+        let attachment = lopdf::Dictionary::from_iter([
+            ("Subtype", lopdf::Object::Name(b"FileAttachment".to_vec())),
+            (
+                "FS",
+                lopdf::Object::Dictionary(lopdf::Dictionary::from_iter([
+                    ("Type", lopdf::Object::Name(b"Filespec".to_vec())),
+                    ("F", lopdf::Object::string_literal("invoice.exe")),
+                    (
+                        "EF",
+                        lopdf::Object::Dictionary(lopdf::Dictionary::from_iter([(
+                            "F",
+                            lopdf::Object::Reference(file),
+                        )])),
+                    ),
+                ])),
+            ),
+        ]);
+        let pages_id = doc.new_object_id();
+        let single_page_id = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"Page".to_vec())),
+            ("Parent", lopdf::Object::Reference(pages_id)),
+            (
+                "Annots",
+                lopdf::Object::Array(vec![uri_link.into(), attachment.into()]),
+            ),
+            (
+                "AA",
+                lopdf::Object::Dictionary(lopdf::Dictionary::from_iter([(
+                    "O",
+                    lopdf::Object::Reference(script),
+                )])),
+            ),
+        ]));
+        doc.objects.insert(
+            pages_id,
+            lopdf::Object::Dictionary(lopdf::Dictionary::from_iter([
+                ("Type", lopdf::Object::Name(b"Pages".to_vec())),
+                ("Kids", lopdf::Object::Array(vec![single_page_id.into()])),
+                ("Count", lopdf::Object::Integer(1)),
+            ])),
+        );
+        let catalog_id = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"Catalog".to_vec())),
+            ("Pages", lopdf::Object::Reference(pages_id)),
+            ("OpenAction", lopdf::Object::Reference(launch)),
+        ]));
+        doc.trailer.set("Root", catalog_id);
+
+        let report = sanitize_document(&mut doc);
+        assert_eq!(report.count(ActiveContent::LaunchAction), 1);
+        assert_eq!(report.count(ActiveContent::JavaScript), 1);
+        assert_eq!(report.count(ActiveContent::ExternalReference), 1);
+        assert_eq!(report.count(ActiveContent::EmbeddedFile), 1);
+
+        assert!(doc.get_object(launch).is_err());
+        assert!(doc.get_object(file).is_err());
+        let page = doc.get_dictionary(single_page_id).ok();
+        assert!(page.is_some_and(|p| !p.has(b"AA")));
+        assert!(audit_report_is_clean(&mut doc));
+    }
+
+    fn audit_report_is_clean(doc: &mut lopdf::Document) -> bool {
+        sanitize_document(doc).is_clean()
+    }
+}
@@ -0,0 +1,107 @@
+//! Picking and reordering pages: a new document made of chosen pages of one
+//! input, in the chosen order.
+
+use std::{collections::BTreeSet, path::Path, process::Command};
+
+use crate::{
+    engine, find_tool, native, page_count, plan::Plan, run_tool_writing, select_engine,
+    validate_input_file, Engine, Operation, PdfError, Result, Tool,
+};
+
+/// Write `pages` (1-based) of `input` to `output` in the order given (with
+/// `qpdf`, `mutool`, or natively; the engine is chosen as for
+/// [`crate::merge`]). Each page may be chosen once.
+pub fn select_pages(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    pages: &[u32],
+) -> Result<()> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_pages(input, pages)?;
+    match select_engine(Operation::Merge)? {
+        Engine::Qpdf => run_tool_writing(Tool::Qpdf, qpdf_command(input, output, pages)?, output),
+        Engine::Mutool => {
+            run_tool_writing(Tool::Mutool, mutool_command(input, output, pages)?, output)
+        }
+        Engine::Native => native::select_pages(input, output, pages),
+        other => Err(engine::unsupported(Operation::Merge, other)),
+    }
+}
+
+/// What [`select_pages`] would run.
+pub fn select_pages_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    pages: &[u32],
+) -> Result<Plan> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_pages(input, pages)?;
+    let plan = match select_engine(Operation::Merge)? {
+        Engine::Qpdf => Plan::new().run(Tool::Qpdf, &qpdf_command(input, output, pages)?),
+        Engine::Mutool => Plan::new().run(Tool::Mutool, &mutool_command(input, output, pages)?),
+        Engine::Native => Plan::new().native(format!("keep pages {}", page_list(pages))),
+        other => return Err(engine::unsupported(Operation::Merge, other)),
+    };
+    Ok(plan.write(output))
+}
+
+/// A short preview of each page's text (pure Rust): whitespace collapsed,
+/// at most `max_chars` characters. Pages without text get an empty string.
+pub fn page_previews(input: impl AsRef<Path>, max_chars: usize) -> Result<Vec<String>> {
+    native::page_previews(input.as_ref(), max_chars)
+}
+
+fn validate_pages(input: &Path, pages: &[u32]) -> Result<()> {
+    validate_input_file(input)?;
+    if pages.is_empty() {
+        return Err(PdfError::InvalidArgument(
+            "select at least one page".to_string(),
+        ));
+    }
+    let count = page_count(input)?;
+    let mut seen = BTreeSet::new();
+    for &page in pages {
+        if page == 0 || page > count {
+            return Err(PdfError::InvalidArgument(format!(
+                "page {page} does not exist (the document has {count} page(s))"
+            )));
+        }
+        if !seen.insert(page) {
+            return Err(PdfError::InvalidArgument(format!(
+                "page {page} is selected twice"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Pages as `qpdf` and `mutool` take them: `3,1,2`.
+fn page_list(pages: &[u32]) -> String {
+    pages
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// `qpdf` keeping the document structure of `input` (outlines, forms).
+fn qpdf_command(input: &Path, output: &Path, pages: &[u32]) -> Result<Command> {
+    let mut cmd = Command::new(find_tool(Tool::Qpdf)?);
+    cmd.arg(input.as_os_str())
+        .arg("--pages")
+        .arg(".")
+        .arg(page_list(pages))
+        .arg("--")
+        .arg(output.as_os_str());
+    Ok(cmd)
+}
+
+fn mutool_command(input: &Path, output: &Path, pages: &[u32]) -> Result<Command> {
+    let mut cmd = Command::new(find_tool(Tool::Mutool)?);
+    cmd.arg("merge")
+        .arg("-o")
+        .arg(output.as_os_str())
+        .arg(input.as_os_str())
+        .arg(page_list(pages));
+    Ok(cmd)
+}
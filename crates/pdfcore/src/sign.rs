@@ -0,0 +1,895 @@
+//! Digital signatures: signing a document with a certificate and private key
+//! from a PKCS #12 file, as a `PAdES` signature (a detached `CAdES` signature
+//! in `/Contents`, covering the whole file except itself as given by
+//! `/ByteRange`). The CMS signature is made with `openssl cms`; an RFC 3161
+//! timestamp of it can be requested from a timestamping authority with
+//! `openssl ts` and `curl`, and is added to it as an unsigned attribute.
+//!
+//! The document is rewritten before it is signed, so documents that are
+//! already signed are refused: rewriting them would break their signatures.
+
+use std::{
+    fmt::Write as _,
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream, StringFormat};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    a11y::catalog_mut, cms, compose, find_tool, find_tool_min, header_footer::civil_date,
+    load_document, metadata::text_string, run_tool_capture, run_tool_writing, save_document,
+    tempspace::TempSpace, validate_input_file, PdfError, Plan, Rect, Result, Tool,
+};
+
+/// Bytes reserved in `/Contents` for the CMS signature, enough for a
+/// certificate chain of several certificates.
+const SIGNATURE_SPACE: usize = 16_384;
+
+/// `/ByteRange` as written before the offsets are known; each placeholder
+/// is as wide as any offset it is replaced with.
+const BYTE_RANGE_PLACEHOLDER: i64 = 9_999_999_999;
+
+/// Environment variable the PKCS #12 password is passed to `openssl` in,
+/// so that it never appears on a command line.
+const PASSWORD_ENV: &str = "PDFCLI_PKCS12_PASSWORD";
+
+/// Changes a certified document allows without invalidating the signature
+/// (its `DocMDP` permissions).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Certification {
+    /// No changes at all.
+    NoChanges,
+    /// Filling in forms and signing.
+    FormFilling,
+    /// Filling in forms, signing, and annotating.
+    Annotations,
+}
+
+impl Certification {
+    fn permissions(self) -> i64 {
+        match self {
+            Self::NoChanges => 1,
+            Self::FormFilling => 2,
+            Self::Annotations => 3,
+        }
+    }
+}
+
+/// Where a visible signature is drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SignatureAppearance {
+    /// 1-based page number.
+    pub page: u32,
+    /// The signature's rectangle, in the page's default user space.
+    pub rect: Rect,
+}
+
+/// Options for [`sign`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SignOptions {
+    /// PKCS #12 file with the signer's certificate, private key, and
+    /// (optionally) the certificates of its chain.
+    pub pkcs12: PathBuf,
+    /// Password of the PKCS #12 file (empty if it has none).
+    pub password: String,
+    /// Draw the signature on a page; without it the signature is invisible.
+    pub appearance: Option<SignatureAppearance>,
+    /// Why the document is signed.
+    pub reason: Option<String>,
+    /// Where it is signed.
+    pub location: Option<String>,
+    /// Certify the document (the first signature of a document can do so),
+    /// allowing only these changes afterwards.
+    pub certify: Option<Certification>,
+    /// URL of an RFC 3161 timestamping authority to timestamp the signature
+    /// with (requires `curl`).
+    pub tsa_url: Option<String>,
+}
+
+/// What [`sign`] did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignReport {
+    /// The signer: the common name of the certificate's subject, or the
+    /// whole subject if it has none.
+    pub signer: String,
+    /// Name of the signature field.
+    pub field: String,
+    /// Size of the CMS signature, in bytes.
+    pub signature_len: usize,
+    /// Time of the signature's timestamp (`GeneralizedTime`, e.g.
+    /// `20261016093000Z`), if one was requested.
+    pub timestamp: Option<String>,
+}
+
+/// Sign `input` with the certificate and key in `options.pkcs12` (requires
+/// `openssl`), writing a `PAdES` signature (`ETSI.CAdES.detached`).
+///
+/// `openssl cms` adds a signing-time attribute, which `PAdES` baseline
+/// signatures leave to `/M`; validators such as Acrobat accept it.
+pub fn sign(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &SignOptions,
+) -> Result<SignReport> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_options(options)?;
+    let doc = load_document(input)?;
+    if is_signed(&doc) {
+        return Err(PdfError::InvalidArgument(format!(
+            "{} is already signed; signing it again would break the existing signature",
+            input.display()
+        )));
+    }
+    let scratch = TempSpace::new("sign")?;
+    sign_with(doc, output, options, &SigningFiles::new(&scratch))
+}
+
+/// [`sign`], with its intermediate files at `files`.
+fn sign_with(
+    mut doc: Document,
+    output: &Path,
+    options: &SignOptions,
+    files: &SigningFiles,
+) -> Result<SignReport> {
+    for cmd in pkcs12_commands(&options.pkcs12, &options.password, files)? {
+        run_tool_capture(Tool::Openssl, cmd)?;
+    }
+    let subject = run_tool_capture(Tool::Openssl, subject_command(&files.cert)?)?;
+    let signer = common_name(&subject);
+
+    let field = add_signature_field(&mut doc, options, &signer)?;
+    save_document(&mut doc, &files.unsigned)?;
+    let mut bytes = fs::read(&files.unsigned)?;
+    let contents = fill_byte_range(&mut bytes)?;
+    let mut covered = bytes[..contents.start].to_vec();
+    covered.extend_from_slice(&bytes[contents.end..]);
+    fs::write(&files.signed_bytes, covered)?;
+    let chain = fs::metadata(&files.chain).is_ok_and(|m| m.len() > 0);
+    let cms = cms_command(files, &options.pkcs12, &options.password, chain)?;
+    run_tool_writing(Tool::Openssl, cms, &files.signature)?;
+    let mut signature = fs::read(&files.signature)?;
+    let mut timestamp = None;
+    if let Some(url) = &options.tsa_url {
+        let (stamped, time) = add_timestamp(&signature, url, files)?;
+        signature = stamped;
+        timestamp = Some(time);
+    }
+    embed_signature(&mut bytes, contents, &signature)?;
+    fs::write(output, bytes)?;
+    tracing::debug!(signer = %signer, bytes = signature.len(), "signed");
+    Ok(SignReport {
+        signer,
+        field,
+        signature_len: signature.len(),
+        timestamp,
+    })
+}
+
+/// What [`sign`] would do.
+pub fn sign_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &SignOptions,
+) -> Result<Plan> {
+    validate_input_file(input.as_ref())?;
+    validate_options(options)?;
+    let files = SigningFiles::in_dir(Path::new("<temp>"));
+    let mut plan = Plan::new();
+    for cmd in pkcs12_commands(&options.pkcs12, &options.password, &files)? {
+        plan = plan.run(Tool::Openssl, &cmd);
+    }
+    plan = plan.run(Tool::Openssl, &subject_command(&files.cert)?);
+    let field = match options.appearance {
+        Some(appearance) => format!(
+            "add a signature field on page {} at {}",
+            appearance.page, appearance.rect
+        ),
+        None => "add an invisible signature field".to_string(),
+    };
+    plan = plan.native(field);
+    if let Some(certify) = options.certify {
+        plan = plan.native(format!("certify the document ({certify:?})"));
+    }
+    plan = plan
+        .native(format!(
+            "write {} with {SIGNATURE_SPACE} bytes reserved for the signature",
+            files.unsigned.display()
+        ))
+        .run(
+            Tool::Openssl,
+            &cms_command(&files, &options.pkcs12, &options.password, true)?,
+        );
+    if let Some(url) = &options.tsa_url {
+        plan = plan
+            .native(format!(
+                "write the signature value to {}",
+                files.signature_value.display()
+            ))
+            .run(Tool::Openssl, &ts_query_command(&files)?)
+            .run(Tool::Curl, &ts_request_command(url, &files)?)
+            .run(Tool::Openssl, &ts_token_command(&files)?)
+            .native("add the timestamp token to the signature");
+    }
+    Ok(plan
+        .native("put the signature in /Contents")
+        .write(output.as_ref()))
+}
+
+fn validate_options(options: &SignOptions) -> Result<()> {
+    validate_input_file(&options.pkcs12)?;
+    if let Some(appearance) = options.appearance {
+        if appearance.page == 0 {
+            return Err(PdfError::InvalidArgument(
+                "page numbers start at 1".to_string(),
+            ));
+        }
+        if appearance.rect.width() <= 0.0 || appearance.rect.height() <= 0.0 {
+            return Err(PdfError::InvalidArgument(format!(
+                "the signature rectangle {} has no area",
+                appearance.rect
+            )));
+        }
+    }
+    if let Some(url) = &options.tsa_url {
+        if !(url.starts_with("http://") || url.starts_with("https://")) {
+            return Err(PdfError::InvalidArgument(format!(
+                "the timestamping authority URL {url} is not an http:// or https:// URL"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Whether any signature dictionary in `doc` has been filled in.
+fn is_signed(doc: &Document) -> bool {
+    doc.objects.values().any(|obj| match obj {
+        Object::Dictionary(dict) => dict.has(b"ByteRange"),
+        _ => false,
+    })
+}
+
+/// The intermediate files of [`sign`].
+struct SigningFiles {
+    cert: PathBuf,
+    chain: PathBuf,
+    unsigned: PathBuf,
+    signed_bytes: PathBuf,
+    signature: PathBuf,
+    signature_value: PathBuf,
+    ts_query: PathBuf,
+    ts_reply: PathBuf,
+    ts_token: PathBuf,
+}
+
+impl SigningFiles {
+    fn new(scratch: &TempSpace) -> Self {
+        Self {
+            cert: scratch.file("cert.pem"),
+            chain: scratch.file("chain.pem"),
+            unsigned: scratch.file("unsigned.pdf"),
+            signed_bytes: scratch.file("signed-bytes.bin"),
+            signature: scratch.file("signature.der"),
+            signature_value: scratch.file("signature-value.bin"),
+            ts_query: scratch.file("request.tsq"),
+            ts_reply: scratch.file("reply.tsr"),
+            ts_token: scratch.file("token.der"),
+        }
+    }
+
+    fn in_dir(dir: &Path) -> Self {
+        Self {
+            cert: dir.join("cert.pem"),
+            chain: dir.join("chain.pem"),
+            unsigned: dir.join("unsigned.pdf"),
+            signed_bytes: dir.join("signed-bytes.bin"),
+            signature: dir.join("signature.der"),
+            signature_value: dir.join("signature-value.bin"),
+            ts_query: dir.join("request.tsq"),
+            ts_reply: dir.join("reply.tsr"),
+            ts_token: dir.join("token.der"),
+        }
+    }
+}
+
+/// `openssl pkcs12` commands extracting the signer's certificate and the
+/// rest of its chain. The private key is never extracted: `openssl cms`
+/// reads it from the PKCS #12 file itself.
+fn pkcs12_commands(pkcs12: &Path, password: &str, files: &SigningFiles) -> Result<Vec<Command>> {
+    let openssl = find_tool(Tool::Openssl)?;
+    let parts: [(&[&str], &Path); 2] = [
+        (&["-clcerts", "-nokeys"], &files.cert),
+        (&["-cacerts", "-nokeys"], &files.chain),
+    ];
+    Ok(parts
+        .into_iter()
+        .map(|(args, out)| {
+            let mut cmd = Command::new(&openssl);
+            cmd.arg("pkcs12")
+                .arg("-in")
+                .arg(pkcs12.as_os_str())
+                .arg("-passin")
+                .arg(format!("env:{PASSWORD_ENV}"))
+                .args(args)
+                .arg("-out")
+                .arg(out.as_os_str())
+                .env(PASSWORD_ENV, password);
+            cmd
+        })
+        .collect())
+}
+
+fn subject_command(cert: &Path) -> Result<Command> {
+    let mut cmd = Command::new(find_tool(Tool::Openssl)?);
+    cmd.arg("x509")
+        .arg("-in")
+        .arg(cert.as_os_str())
+        .arg("-noout")
+        .arg("-subject")
+        .arg("-nameopt")
+        .arg("RFC2253");
+    Ok(cmd)
+}
+
+/// `openssl cms` making a detached `CAdES` signature of the signed bytes,
+/// with the key decrypted from `pkcs12` in memory (`-keyform P12`, OpenSSL
+/// 3.0 or later).
+fn cms_command(
+    files: &SigningFiles,
+    pkcs12: &Path,
+    password: &str,
+    chain: bool,
+) -> Result<Command> {
+    let mut cmd = Command::new(find_tool_min(Tool::Openssl, &[3, 0], "signing")?);
+    cmd.arg("cms")
+        .arg("-sign")
+        .arg("-binary")
+        .arg("-cades")
+        .arg("-nosmimecap")
+        .arg("-md")
+        .arg("sha256")
+        .arg("-in")
+        .arg(files.signed_bytes.as_os_str())
+        .arg("-signer")
+        .arg(files.cert.as_os_str())
+        .arg("-inkey")
+        .arg(pkcs12.as_os_str())
+        .arg("-keyform")
+        .arg("P12")
+        .arg("-passin")
+        .arg(format!("env:{PASSWORD_ENV}"))
+        .env(PASSWORD_ENV, password);
+    if chain {
+        cmd.arg("-certfile").arg(files.chain.as_os_str());
+    }
+    cmd.arg("-outform")
+        .arg("DER")
+        .arg("-out")
+        .arg(files.signature.as_os_str());
+    Ok(cmd)
+}
+
+/// The SHA-256 digest of `data`.
+pub(crate) fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+/// Timestamp the CMS `signature` at the timestamping authority `url`,
+/// returning the signature with the token added and the token's time.
+fn add_timestamp(signature: &[u8], url: &str, files: &SigningFiles) -> Result<(Vec<u8>, String)> {
+    let value = cms::signature_value(signature)?;
+    fs::write(&files.signature_value, &value)?;
+    run_tool_writing(Tool::Openssl, ts_query_command(files)?, &files.ts_query)?;
+    run_tool_writing(Tool::Curl, ts_request_command(url, files)?, &files.ts_reply)?;
+    run_tool_writing(Tool::Openssl, ts_token_command(files)?, &files.ts_token)?;
+    let token = fs::read(&files.ts_token)?;
+    let info = cms::tst_info(&token)?;
+    if info.hash_algorithm != cms::SHA256 || info.hashed_message != sha256(&value)[..] {
+        return Err(PdfError::InvalidArgument(format!(
+            "the timestamping authority at {url} timestamped something other than the signature"
+        )));
+    }
+    let stamped = cms::add_unsigned_attribute(signature, cms::SIGNATURE_TIMESTAMP_TOKEN, &token)?;
+    tracing::debug!(time = %info.gen_time, bytes = token.len(), "timestamped");
+    Ok((stamped, info.gen_time))
+}
+
+/// `openssl ts -query` making a timestamp request for the signature value.
+fn ts_query_command(files: &SigningFiles) -> Result<Command> {
+    let mut cmd = Command::new(find_tool(Tool::Openssl)?);
+    cmd.arg("ts")
+        .arg("-query")
+        .arg("-data")
+        .arg(files.signature_value.as_os_str())
+        .arg("-sha256")
+        .arg("-cert")
+        .arg("-out")
+        .arg(files.ts_query.as_os_str());
+    Ok(cmd)
+}
+
+/// `curl` sending the timestamp request to the authority at `url`.
+fn ts_request_command(url: &str, files: &SigningFiles) -> Result<Command> {
+    let mut cmd = Command::new(find_tool(Tool::Curl)?);
+    cmd.arg("--silent")
+        .arg("--show-error")
+        .arg("--fail")
+        .arg("--header")
+        .arg("Content-Type: application/timestamp-query")
+        .arg("--data-binary")
+        .arg(format!("@{}", files.ts_query.display()))
+        .arg("--output")
+        .arg(files.ts_reply.as_os_str())
+        .arg(url);
+    Ok(cmd)
+}
+
+/// `openssl ts -reply` taking the token out of the authority's reply; it
+/// fails if the request was not granted.
+fn ts_token_command(files: &SigningFiles) -> Result<Command> {
+    let mut cmd = Command::new(find_tool(Tool::Openssl)?);
+    cmd.arg("ts")
+        .arg("-reply")
+        .arg("-in")
+        .arg(files.ts_reply.as_os_str())
+        .arg("-token_out")
+        .arg("-out")
+        .arg(files.ts_token.as_os_str());
+    Ok(cmd)
+}
+
+/// The common name in `openssl x509 -subject -nameopt RFC2253` output, e.g.
+/// `subject=CN=Jane Doe,O=Acme`, or the whole subject if it has none.
+fn common_name(output: &str) -> String {
+    let subject = output
+        .trim()
+        .strip_prefix("subject=")
+        .unwrap_or(output.trim())
+        .trim();
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut chars = subject.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            ',' => parts.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    parts.push(current);
+    parts
+        .iter()
+        .find_map(|part| part.trim().strip_prefix("CN="))
+        .map_or_else(|| subject.to_string(), ToString::to_string)
+}
+
+/// Add an unsigned signature dictionary and its field to `doc`, returning
+/// the field's name.
+fn add_signature_field(doc: &mut Document, options: &SignOptions, signer: &str) -> Result<String> {
+    let (date, shown_date) = now();
+    let signature = doc.add_object(signature_dictionary(options, signer, date));
+
+    let pages = doc.get_pages();
+    let page_number = options.appearance.map_or(1, |a| a.page);
+    let &page_id = pages.get(&page_number).ok_or_else(|| {
+        PdfError::InvalidArgument(format!(
+            "page {page_number} is out of range (document has {} pages)",
+            pages.len()
+        ))
+    })?;
+    let name = field_name(doc);
+    let mut widget = Dictionary::from_iter([
+        ("Type", Object::Name(b"Annot".to_vec())),
+        ("Subtype", Object::Name(b"Widget".to_vec())),
+        ("FT", Object::Name(b"Sig".to_vec())),
+        ("T", Object::string_literal(name.clone())),
+        ("V", Object::Reference(signature)),
+        ("P", Object::Reference(page_id)),
+        // Print and Locked.
+        ("F", Object::Integer(132)),
+    ]);
+    match options.appearance {
+        Some(appearance) => {
+            let lines = [
+                format!("Digitally signed by {signer}"),
+                format!("Date: {shown_date}"),
+            ]
+            .into_iter()
+            .chain(options.reason.iter().map(|r| format!("Reason: {r}")))
+            .collect::<Vec<_>>();
+            let form = appearance_stream(doc, &appearance.rect, &lines);
+            widget.set("Rect", appearance.rect.to_object());
+            widget.set(
+                "AP",
+                Dictionary::from_iter([("N", Object::Reference(form))]),
+            );
+        }
+        None => widget.set("Rect", Rect::from_size(0.0, 0.0).to_object()),
+    }
+    let widget = doc.add_object(widget);
+    push_reference(doc, page_id, b"Annots", widget)?;
+
+    let acroform_id = match catalog_mut(doc)?.get(b"AcroForm") {
+        Ok(Object::Reference(id)) => *id,
+        Ok(Object::Dictionary(dict)) => {
+            let dict = dict.clone();
+            doc.add_object(dict)
+        }
+        _ => doc.add_object(Dictionary::new()),
+    };
+    catalog_mut(doc)?.set("AcroForm", Object::Reference(acroform_id));
+    push_reference(doc, acroform_id, b"Fields", widget)?;
+    doc.get_dictionary_mut(acroform_id)
+        .map_err(|_| PdfError::InvalidArgument("the form dictionary is damaged".to_string()))?
+        // SignaturesExist and AppendOnly.
+        .set("SigFlags", Object::Integer(3));
+    if options.certify.is_some() {
+        catalog_mut(doc)?.set(
+            "Perms",
+            Dictionary::from_iter([("DocMDP", Object::Reference(signature))]),
+        );
+    }
+    Ok(name)
+}
+
+/// The signature dictionary, with placeholders for `/ByteRange` and
+/// `/Contents`.
+fn signature_dictionary(options: &SignOptions, signer: &str, date: String) -> Dictionary {
+    let mut signature = Dictionary::from_iter([
+        ("Type", Object::Name(b"Sig".to_vec())),
+        ("Filter", Object::Name(b"Adobe.PPKLite".to_vec())),
+        ("SubFilter", Object::Name(b"ETSI.CAdES.detached".to_vec())),
+        (
+            "ByteRange",
+            Object::Array(vec![
+                Object::Integer(0),
+                Object::Integer(BYTE_RANGE_PLACEHOLDER),
+                Object::Integer(BYTE_RANGE_PLACEHOLDER),
+                Object::Integer(BYTE_RANGE_PLACEHOLDER),
+            ]),
+        ),
+        (
+            "Contents",
+            Object::String(vec![0; SIGNATURE_SPACE], StringFormat::Hexadecimal),
+        ),
+        ("M", Object::string_literal(date)),
+        ("Name", text_string(signer)),
+    ]);
+    if let Some(reason) = &options.reason {
+        signature.set("Reason", text_string(reason));
+    }
+    if let Some(location) = &options.location {
+        signature.set("Location", text_string(location));
+    }
+    if let Some(certify) = options.certify {
+        signature.set(
+            "Reference",
+            vec![Object::Dictionary(Dictionary::from_iter([
+                ("Type", Object::Name(b"SigRef".to_vec())),
+                ("TransformMethod", Object::Name(b"DocMDP".to_vec())),
+                (
+                    "TransformParams",
+                    Object::Dictionary(Dictionary::from_iter([
+                        ("Type", Object::Name(b"TransformParams".to_vec())),
+                        ("P", Object::Integer(certify.permissions())),
+                        ("V", Object::Name(b"1.2".to_vec())),
+                    ])),
+                ),
+            ]))],
+        );
+    }
+    signature
+}
+
+/// `Signature1`, or the first `SignatureN` no field of `doc` is named.
+fn field_name(doc: &Document) -> String {
+    let taken: Vec<&[u8]> = doc
+        .objects
+        .values()
+        .filter_map(|obj| obj.as_dict().ok())
+        .filter_map(|dict| dict.get(b"T").ok())
+        .filter_map(|name| name.as_str().ok())
+        .collect();
+    (1..=taken.len() + 1)
+        .map(|n| format!("Signature{n}"))
+        .find(|name| !taken.contains(&name.as_bytes()))
+        .unwrap_or_default()
+}
+
+/// Append a reference to `item` to the array `key` of the dictionary
+/// `owner`, creating the array or following a reference to it.
+fn push_reference(doc: &mut Document, owner: ObjectId, key: &[u8], item: ObjectId) -> Result<()> {
+    let damaged = || PdfError::InvalidArgument(format!("object {owner:?} is damaged"));
+    let existing = doc.get_dictionary(owner).map_err(|_| damaged())?.get(key);
+    if let Ok(&Object::Reference(array)) = existing {
+        if let Ok(Object::Array(items)) = doc.get_object_mut(array) {
+            items.push(Object::Reference(item));
+            return Ok(());
+        }
+    }
+    let dict = doc.get_dictionary_mut(owner).map_err(|_| damaged())?;
+    match dict.get_mut(key) {
+        Ok(Object::Array(items)) => items.push(Object::Reference(item)),
+        _ => dict.set(key.to_vec(), vec![Object::Reference(item)]),
+    }
+    Ok(())
+}
+
+/// A form `XObject` showing `lines` in a frame the size of `rect`.
+fn appearance_stream(doc: &mut Document, rect: &Rect, lines: &[String]) -> ObjectId {
+    let (width, height) = (rect.width(), rect.height());
+    let encoded: Vec<Vec<u8>> = lines
+        .iter()
+        .map(|line| compose::encode(line, &mut 0))
+        .collect();
+    #[allow(clippy::cast_precision_loss)] // a handful of lines
+    let count = lines.len() as f32;
+    let widest = encoded
+        .iter()
+        .map(|line| compose::helvetica_width(line, 1.0))
+        .fold(0.0_f32, f32::max);
+    let size = (height / (count * 1.3 + 0.5))
+        .min((width - 8.0) / widest.max(1.0))
+        .min(10.0);
+    let mut content = format!(
+        "q 0.5 G 0.5 w 0.25 0.25 {} {} re S Q\nBT /F1 {size} Tf 0 g\n4 {} Td\n",
+        width - 0.5,
+        height - 0.5,
+        height - size * 1.3
+    );
+    for (i, line) in encoded.iter().enumerate() {
+        if i > 0 {
+            let _ = writeln!(content, "0 {} Td", -size * 1.3);
+        }
+        let mut literal = Vec::new();
+        for &byte in line {
+            if matches!(byte, b'(' | b')' | b'\\') {
+                literal.push(b'\\');
+            }
+            literal.push(byte);
+        }
+        content.push('(');
+        content.push_str(&String::from_utf8_lossy(&literal));
+        content.push_str(") Tj\n");
+    }
+    content.push_str("ET\n");
+    let font = doc.add_object(compose::helvetica());
+    doc.add_object(Stream::new(
+        Dictionary::from_iter([
+            ("Type", Object::Name(b"XObject".to_vec())),
+            ("Subtype", Object::Name(b"Form".to_vec())),
+            ("BBox", Rect::from_size(width, height).to_object()),
+            (
+                "Resources",
+                Object::Dictionary(Dictionary::from_iter([(
+                    "Font",
+                    Object::Dictionary(Dictionary::from_iter([("F1", Object::Reference(font))])),
+                )])),
+            ),
+        ]),
+        content.into_bytes(),
+    ))
+}
+
+/// The current time as a PDF date (`D:20261016093000Z`) and as shown in a
+/// visible signature (`2026-10-16 09:30 UTC`).
+fn now() -> (String, String) {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (year, month, day) = civil_date(seconds / 86_400);
+    let time = seconds % 86_400;
+    let (hour, minute, second) = (time / 3600, time / 60 % 60, time % 60);
+    (
+        format!("D:{year:04}{month:02}{day:02}{hour:02}{minute:02}{second:02}Z"),
+        format!("{year:04}-{month:02}-{day:02} {hour:02}:{minute:02} UTC"),
+    )
+}
+
+/// Replace the `/ByteRange` placeholder in the saved document `bytes` with
+/// the ranges around the `/Contents` placeholder, returning the position
+/// of that placeholder (from `<` to `>` inclusive).
+///
+/// The `/ByteRange` filled in is the last one before `/Contents` in the same
+/// object, so the bytes `/ByteRange` elsewhere in the file (in a content
+/// stream, say, or an embedded signed PDF) are left alone.
+fn fill_byte_range(bytes: &mut [u8]) -> Result<Range<usize>> {
+    let not_found = || {
+        PdfError::InvalidArgument(
+            "the signature placeholder was not written as expected".to_string(),
+        )
+    };
+    let mut placeholder = vec![b'<'];
+    placeholder.resize(1 + SIGNATURE_SPACE * 2, b'0');
+    placeholder.push(b'>');
+    let start = find(bytes, &placeholder, 0).ok_or_else(not_found)?;
+    let contents = start..start + placeholder.len();
+
+    let before = &bytes[..contents.start];
+    let key = before
+        .windows(b"/ByteRange".len())
+        .rposition(|w| w == b"/ByteRange")
+        .ok_or_else(not_found)?;
+    if find(before, b"endobj", key).is_some() {
+        return Err(not_found());
+    }
+    let open = key
+        + before[key..]
+            .iter()
+            .position(|&b| b == b'[')
+            .ok_or_else(not_found)?;
+    let close = open
+        + before[open..]
+            .iter()
+            .position(|&b| b == b']')
+            .ok_or_else(not_found)?;
+    if find(
+        &before[open..close],
+        BYTE_RANGE_PLACEHOLDER.to_string().as_bytes(),
+        0,
+    )
+    .is_none()
+    {
+        return Err(not_found());
+    }
+    let ranges = format!(
+        "[0 {} {} {}]",
+        contents.start,
+        contents.end,
+        bytes.len() - contents.end
+    );
+    let slot = &mut bytes[open..=close];
+    if ranges.len() > slot.len() {
+        return Err(not_found());
+    }
+    slot.fill(b' ');
+    slot[..ranges.len()].copy_from_slice(ranges.as_bytes());
+    Ok(contents)
+}
+
+/// Write the DER `signature` into the `/Contents` placeholder at `contents`.
+fn embed_signature(bytes: &mut [u8], contents: Range<usize>, signature: &[u8]) -> Result<()> {
+    if signature.len() > SIGNATURE_SPACE {
+        return Err(PdfError::InvalidArgument(format!(
+            "the signature is {} bytes, more than the {SIGNATURE_SPACE} reserved for it",
+            signature.len()
+        )));
+    }
+    let mut hex = String::with_capacity(signature.len() * 2);
+    for byte in signature {
+        let _ = write!(hex, "{byte:02X}");
+    }
+    bytes[contents.start + 1..contents.start + 1 + hex.len()].copy_from_slice(hex.as_bytes());
+    Ok(())
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|i| i + from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_range_covers_everything_but_the_signature() -> Result<()> {
+        let mut bytes =
+            b"%PDF-1.7\n1 0 obj\n<</ByteRange[0 9999999999 9999999999 9999999999]/Contents<"
+                .to_vec();
+        bytes.resize(bytes.len() + SIGNATURE_SPACE * 2, b'0');
+        bytes.extend_from_slice(b">>>\nendobj\ntrailer\n%%EOF\n");
+        let len = bytes.len();
+
+        let contents = fill_byte_range(&mut bytes)?;
+        let text = String::from_utf8_lossy(&bytes);
+        let expected = format!(
+            "/ByteRange[0 {} {} {}",
+            contents.start,
+            contents.end,
+            len - contents.end
+        );
+        assert!(text.contains(&expected), "{text}");
+        assert_eq!(bytes.len(), len);
+        assert_eq!(bytes[contents.start], b'<');
+        assert_eq!(bytes[contents.end - 1], b'>');
+
+        embed_signature(&mut bytes, contents.clone(), &[0x30, 0x82, 0xAB])?;
+        assert!(bytes[contents.start..].starts_with(b"<3082AB000"));
+        assert!(embed_signature(&mut bytes, contents, &vec![0; SIGNATURE_SPACE + 1]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn only_the_signature_byte_range_is_filled_in() -> Result<()> {
+        let stream = b"%PDF-1.7\n1 0 obj\n<</Length 30>>stream\n(/ByteRange[0 1 2 3]) Tj\nendstream\nendobj\n";
+        let mut bytes = stream.to_vec();
+        bytes.extend_from_slice(
+            b"2 0 obj\n<</ByteRange[0 9999999999 9999999999 9999999999]/Contents<",
+        );
+        bytes.resize(bytes.len() + SIGNATURE_SPACE * 2, b'0');
+        bytes.extend_from_slice(b">>>\nendobj\ntrailer\n%%EOF\n");
+
+        let contents = fill_byte_range(&mut bytes)?;
+        assert!(bytes.starts_with(stream));
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(!text.contains("9999999999"), "{text}");
+        assert!(text.contains(&format!("/ByteRange[0 {} ", contents.start)));
+
+        // A `/ByteRange` only in another object is not the signature's.
+        let mut unsigned = stream.to_vec();
+        unsigned.extend_from_slice(b"2 0 obj\n<</Contents<");
+        unsigned.resize(unsigned.len() + SIGNATURE_SPACE * 2, b'0');
+        unsigned.extend_from_slice(b">>>\nendobj\n");
+        assert!(fill_byte_range(&mut unsigned).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn private_key_never_reaches_the_disk() -> Result<()> {
+        let Ok(openssl) = find_tool(Tool::Openssl) else {
+            return Ok(());
+        };
+        let dir = tempfile::tempdir()?;
+        let path = |name: &str| dir.path().join(name);
+        let openssl_run = |args: &str| -> Result<()> {
+            let mut cmd = Command::new(&openssl);
+            cmd.args(args.split_whitespace()).current_dir(dir.path());
+            run_tool_capture(Tool::Openssl, cmd).map(drop)
+        };
+        openssl_run(
+            "req -x509 -newkey rsa:2048 -nodes -keyout signer.key -out signer.pem \
+             -subj /CN=Signer -days 1",
+        )?;
+        openssl_run(
+            "pkcs12 -export -in signer.pem -inkey signer.key -out signer.p12 \
+             -passout pass:secret",
+        )?;
+        fs::remove_file(path("signer.key"))?;
+        fs::write(path("notes.txt"), "Signed without a key file.")?;
+        compose::text_to_pdf(
+            path("notes.txt"),
+            path("input.pdf"),
+            &compose::TextToPdfOptions::default(),
+        )?;
+
+        let scratch = tempfile::tempdir()?;
+        let options = SignOptions {
+            pkcs12: path("signer.p12"),
+            password: "secret".to_string(),
+            ..SignOptions::default()
+        };
+        let report = sign_with(
+            load_document(&path("input.pdf"))?,
+            &path("signed.pdf"),
+            &options,
+            &SigningFiles::in_dir(scratch.path()),
+        )?;
+        assert_eq!(report.signer, "Signer");
+        assert!(fs::metadata(path("signed.pdf"))?.len() > 0);
+        for entry in fs::read_dir(scratch.path())? {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            assert!(!name.contains("key"), "{name} was written");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn signer_is_the_common_name() {
+        assert_eq!(common_name("subject=O=Acme,CN=Jane Doe\n"), "Jane Doe");
+        assert_eq!(common_name("subject=CN=Doe\\, Jane,O=Acme"), "Doe, Jane");
+        assert_eq!(common_name("subject=O=Acme"), "O=Acme");
+    }
+}
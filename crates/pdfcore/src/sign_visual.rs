@@ -0,0 +1,586 @@
+//! Visual signatures: a picture of a handwritten signature placed on a page,
+//! with the date written below it. This is a mark for the eye only; it
+//! does not sign the document cryptographically.
+
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use lopdf::{
+    content::{Content, Operation},
+    Dictionary, Document, Object, Stream, StringFormat,
+};
+
+use crate::{
+    header_footer::today_civil, load_document, save_document, stamp, validate_input_file, PdfError,
+    Plan, Result,
+};
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Options for [`sign_visual`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignVisualOptions {
+    /// The signature picture: a JPEG, or a PNG without interlacing (8-bit
+    /// if it has an alpha channel).
+    pub image: PathBuf,
+    /// 1-based page number.
+    pub page: u32,
+    /// Where the bottom left corner of the picture goes, in points from the
+    /// bottom left corner of the page as shown (after its `/Rotate`).
+    pub at: (f32, f32),
+    /// Width of the picture on the page, in points; its height follows
+    /// from its aspect ratio.
+    pub width: f32,
+    /// Write today's date (UTC) below the picture in this format: `%Y`,
+    /// `%y`, `%m`, `%d`, `%e`, `%B`, `%b`, and `%%` are replaced.
+    pub date_format: Option<String>,
+    /// Font size of the date, in points.
+    pub font_size: f32,
+}
+
+impl Default for SignVisualOptions {
+    fn default() -> Self {
+        Self {
+            image: PathBuf::new(),
+            page: 1,
+            at: (72.0, 72.0),
+            width: 144.0,
+            date_format: None,
+            font_size: 10.0,
+        }
+    }
+}
+
+/// Place the signature picture of `options` on a page of `input`, with the
+/// date below it (pure Rust).
+pub fn sign_visual(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &SignVisualOptions,
+) -> Result<()> {
+    let image = read_image(options)?;
+    let date = date_text(options)?;
+    let mut doc = load_document(input.as_ref())?;
+    let pages = doc.get_pages();
+    let &page_id = pages.get(&options.page).ok_or_else(|| {
+        PdfError::InvalidArgument(format!(
+            "page {} is out of range (document has {} pages)",
+            options.page,
+            pages.len()
+        ))
+    })?;
+    let (page_width, page_height, matrix) = stamp::shown_space(&doc, page_id);
+    let (x, y) = options.at;
+    let height = image.height(options.width);
+    let below = if date.is_some() {
+        options.font_size * 1.2
+    } else {
+        0.0
+    };
+    if x < 0.0 || y - below < 0.0 || x + options.width > page_width || y + height > page_height {
+        return Err(PdfError::InvalidArgument(format!(
+            "the signature ({:.0} x {:.0} pt at {x},{y}) does not fit on page {} ({page_width:.0} x {page_height:.0} pt)",
+            options.width,
+            height + below,
+            options.page
+        )));
+    }
+
+    let xobject = image.add_to(&mut doc);
+    let name = stamp::add_resource(&mut doc, page_id, "XObject", xobject, "PdfcliSignature")?;
+    let mut operations = vec![
+        Operation::new("q", vec![]),
+        Operation::new("cm", matrix.iter().map(|&v| v.into()).collect()),
+        Operation::new("q", vec![]),
+        Operation::new(
+            "cm",
+            vec![
+                options.width.into(),
+                0.into(),
+                0.into(),
+                height.into(),
+                x.into(),
+                y.into(),
+            ],
+        ),
+        Operation::new("Do", vec![Object::Name(name.into_bytes())]),
+        Operation::new("Q", vec![]),
+    ];
+    if let Some(date) = date {
+        let font = stamp::add_stamp_font(&mut doc);
+        let font = stamp::add_resource(&mut doc, page_id, "Font", font, "PdfcliStamp")?;
+        operations.extend([
+            Operation::new("BT", vec![]),
+            Operation::new(
+                "Tf",
+                vec![Object::Name(font.into_bytes()), options.font_size.into()],
+            ),
+            Operation::new("g", vec![0.into()]),
+            Operation::new("Td", vec![x.into(), (y - below).into()]),
+            Operation::new("Tj", vec![Object::String(date, StringFormat::Literal)]),
+            Operation::new("ET", vec![]),
+        ]);
+    }
+    operations.push(Operation::new("Q", vec![]));
+    let content = Content { operations }
+        .encode()
+        .map_err(|e| PdfError::InvalidArgument(format!("encoding content: {e}")))?;
+    stamp::overlay_page_content(&mut doc, page_id, &content)?;
+    save_document(&mut doc, output.as_ref())
+}
+
+/// What [`sign_visual`] would do.
+pub fn sign_visual_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &SignVisualOptions,
+) -> Result<Plan> {
+    validate_input_file(input.as_ref())?;
+    let image = read_image(options)?;
+    let date = date_text(options)?;
+    let (x, y) = options.at;
+    let mut plan = Plan::new().native(format!(
+        "place {} ({} x {} px) on page {} at {x},{y}, {:.0} x {:.0} pt",
+        options.image.display(),
+        image.width,
+        image.height,
+        options.page,
+        options.width,
+        image.height(options.width)
+    ));
+    if let Some(date) = date {
+        plan = plan.native(format!(
+            "write {:?} below it",
+            String::from_utf8_lossy(&date)
+        ));
+    }
+    Ok(plan.write(output.as_ref()))
+}
+
+/// Check `options` and read its picture.
+fn read_image(options: &SignVisualOptions) -> Result<Picture> {
+    if options.page == 0 {
+        return Err(PdfError::InvalidArgument(
+            "page numbers start at 1".to_string(),
+        ));
+    }
+    if options.width <= 0.0 || options.font_size <= 0.0 {
+        return Err(PdfError::InvalidArgument(
+            "the width and font size must be positive".to_string(),
+        ));
+    }
+    validate_input_file(&options.image)?;
+    let bytes = fs::read(&options.image)?;
+    Picture::decode(&bytes).map_err(|e| match e {
+        PdfError::InvalidArgument(message) => {
+            PdfError::InvalidArgument(format!("{}: {message}", options.image.display()))
+        }
+        other => other,
+    })
+}
+
+/// The date to write, in `WinAnsiEncoding`, if `options` asks for one.
+fn date_text(options: &SignVisualOptions) -> Result<Option<Vec<u8>>> {
+    let Some(format) = &options.date_format else {
+        return Ok(None);
+    };
+    let text = format_date(format, today_civil())?;
+    stamp::stamp_text_bytes(&text, "the date").map(Some)
+}
+
+/// `format` with the date fields of (year, month, day) filled in.
+fn format_date(format: &str, (year, month, day): (u64, u64, u64)) -> Result<String> {
+    let month_name = usize::try_from(month)
+        .ok()
+        .and_then(|m| MONTHS.get(m.wrapping_sub(1)))
+        .copied()
+        .unwrap_or_default();
+    let mut out = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => {
+                let _ = write!(out, "{year:04}");
+            }
+            Some('y') => {
+                let _ = write!(out, "{:02}", year % 100);
+            }
+            Some('m') => {
+                let _ = write!(out, "{month:02}");
+            }
+            Some('d') => {
+                let _ = write!(out, "{day:02}");
+            }
+            Some('e') => out.push_str(&day.to_string()),
+            Some('B') => out.push_str(month_name),
+            Some('b') => out.push_str(month_name.get(..3).unwrap_or_default()),
+            Some('%') => out.push('%'),
+            Some(other) => {
+                return Err(PdfError::InvalidArgument(format!(
+                    "unsupported date field %{other} (use %Y, %y, %m, %d, %e, %B, %b, or %%)"
+                )))
+            }
+            None => {
+                return Err(PdfError::InvalidArgument(
+                    "the date format ends with a lone %".to_string(),
+                ))
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// A picture ready to embed as an image `XObject`.
+#[derive(Debug)]
+struct Picture {
+    width: u32,
+    height: u32,
+    image: Stream,
+    /// The alpha channel, as a soft mask.
+    mask: Option<Stream>,
+}
+
+impl Picture {
+    /// Decode a JPEG or PNG file.
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        if bytes.starts_with(b"\xff\xd8") {
+            Self::jpeg(bytes)
+        } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Self::png(bytes)
+        } else {
+            Err(PdfError::InvalidArgument(
+                "not a JPEG or PNG image".to_string(),
+            ))
+        }
+    }
+
+    /// The height on the page of the picture `width` points wide.
+    #[allow(clippy::cast_precision_loss)] // pixel counts are far below 2^24
+    fn height(&self, width: f32) -> f32 {
+        width * self.height as f32 / self.width as f32
+    }
+
+    /// Add the picture to `doc`, returning the image `XObject`.
+    fn add_to(self, doc: &mut Document) -> lopdf::ObjectId {
+        let mut image = self.image;
+        if let Some(mask) = self.mask {
+            let mask = doc.add_object(mask);
+            image.dict.set("SMask", Object::Reference(mask));
+        }
+        doc.add_object(image)
+    }
+
+    /// A JPEG, embedded as it is; only its frame header is read.
+    fn jpeg(bytes: &[u8]) -> Result<Self> {
+        let invalid = || PdfError::InvalidArgument("truncated or invalid JPEG image".to_string());
+        let mut pos = 2;
+        loop {
+            while bytes.get(pos) == Some(&0xff) && bytes.get(pos + 1) == Some(&0xff) {
+                pos += 1;
+            }
+            let [0xff, marker] = *bytes.get(pos..pos + 2).ok_or_else(invalid)? else {
+                return Err(invalid());
+            };
+            if marker == 0x01 || (0xd0..=0xd7).contains(&marker) {
+                pos += 2;
+                continue;
+            }
+            let segment = bytes.get(pos + 2..pos + 4).ok_or_else(invalid)?;
+            let length = usize::from(u16::from_be_bytes([segment[0], segment[1]]));
+            // Start of frame markers, other than DHT, JPG, and DAC.
+            if (0xc0..=0xcf).contains(&marker) && ![0xc4, 0xc8, 0xcc].contains(&marker) {
+                let frame = bytes.get(pos + 4..pos + 10).ok_or_else(invalid)?;
+                let height = u32::from(u16::from_be_bytes([frame[1], frame[2]]));
+                let width = u32::from(u16::from_be_bytes([frame[3], frame[4]]));
+                let mut dict = image_dict(width, height, 8);
+                match frame[5] {
+                    1 => dict.set("ColorSpace", Object::Name(b"DeviceGray".to_vec())),
+                    3 => dict.set("ColorSpace", Object::Name(b"DeviceRGB".to_vec())),
+                    4 => {
+                        // CMYK JPEGs are almost all written by Adobe
+                        // software, which stores the inks inverted.
+                        dict.set("ColorSpace", Object::Name(b"DeviceCMYK".to_vec()));
+                        dict.set(
+                            "Decode",
+                            [1, 0, 1, 0, 1, 0, 1, 0].map(Object::Integer).to_vec(),
+                        );
+                    }
+                    n => {
+                        return Err(PdfError::InvalidArgument(format!(
+                            "JPEG images with {n} color components are not supported"
+                        )))
+                    }
+                }
+                dict.set("Filter", Object::Name(b"DCTDecode".to_vec()));
+                if width == 0 || height == 0 {
+                    return Err(invalid());
+                }
+                return Ok(Self {
+                    width,
+                    height,
+                    image: Stream::new(dict, bytes.to_vec()).with_compression(false),
+                    mask: None,
+                });
+            }
+            if marker == 0xd9 || marker == 0xda {
+                return Err(invalid());
+            }
+            pos += 2 + length;
+        }
+    }
+
+    /// A PNG. Gray, RGB, and palette images keep their compressed data;
+    /// images with an alpha channel are decoded to split it off.
+    fn png(bytes: &[u8]) -> Result<Self> {
+        let invalid = || PdfError::InvalidArgument("truncated or invalid PNG image".to_string());
+        let mut header = None;
+        let mut palette = Vec::new();
+        let mut data = Vec::new();
+        let mut pos = 8;
+        while let Some(length) = bytes.get(pos..pos + 4) {
+            let length = usize::try_from(u32::from_be_bytes([
+                length[0], length[1], length[2], length[3],
+            ]))
+            .map_err(|_| invalid())?;
+            let kind = bytes.get(pos + 4..pos + 8).ok_or_else(invalid)?;
+            let chunk = bytes.get(pos + 8..pos + 8 + length).ok_or_else(invalid)?;
+            match kind {
+                b"IHDR" => header = Some(chunk.get(..13).ok_or_else(invalid)?),
+                b"PLTE" => palette = chunk.to_vec(),
+                b"IDAT" => data.extend_from_slice(chunk),
+                b"IEND" => break,
+                _ => {}
+            }
+            pos += 12 + length;
+        }
+        let header = header.ok_or_else(invalid)?;
+        let width = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        let height = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+        let (depth, color_type, interlace) = (header[8], header[9], header[12]);
+        if width == 0 || height == 0 || data.is_empty() {
+            return Err(invalid());
+        }
+        if interlace != 0 {
+            return Err(PdfError::InvalidArgument(
+                "interlaced PNG images are not supported; save it without interlacing".to_string(),
+            ));
+        }
+        let colors: u8 = match color_type {
+            0 | 3 => 1,
+            2 => 3,
+            4 => 2,
+            6 => 4,
+            _ => return Err(invalid()),
+        };
+        let mut dict = image_dict(width, height, depth);
+        dict.set("Filter", Object::Name(b"FlateDecode".to_vec()));
+        dict.set(
+            "DecodeParms",
+            Dictionary::from_iter([
+                ("Predictor", Object::Integer(15)),
+                ("Colors", Object::Integer(colors.into())),
+                ("BitsPerComponent", Object::Integer(depth.into())),
+                ("Columns", Object::Integer(width.into())),
+            ]),
+        );
+        match color_type {
+            0 => dict.set("ColorSpace", Object::Name(b"DeviceGray".to_vec())),
+            2 => dict.set("ColorSpace", Object::Name(b"DeviceRGB".to_vec())),
+            3 => {
+                if palette.is_empty() {
+                    return Err(invalid());
+                }
+                dict.set(
+                    "ColorSpace",
+                    vec![
+                        Object::Name(b"Indexed".to_vec()),
+                        Object::Name(b"DeviceRGB".to_vec()),
+                        Object::Integer(i64::try_from(palette.len() / 3).unwrap_or(1) - 1),
+                        Object::String(palette, StringFormat::Hexadecimal),
+                    ],
+                );
+            }
+            _ => {
+                if depth != 8 {
+                    return Err(PdfError::InvalidArgument(format!(
+                        "{depth}-bit PNG images with an alpha channel are not supported"
+                    )));
+                }
+                // lopdf only decodes streams that are not images.
+                dict.remove(b"Subtype");
+                let pixels = Stream::new(dict, data)
+                    .decompressed_content()
+                    .map_err(|e| PdfError::InvalidArgument(format!("decoding the PNG: {e}")))?;
+                return Self::with_alpha(width, height, &pixels, colors);
+            }
+        }
+        Ok(Self {
+            width,
+            height,
+            image: Stream::new(dict, data).with_compression(false),
+            mask: None,
+        })
+    }
+
+    /// 8-bit pixels of `colors` samples, the last of them alpha, as an
+    /// image with a soft mask.
+    fn with_alpha(width: u32, height: u32, pixels: &[u8], colors: u8) -> Result<Self> {
+        let (color, alpha) = split_alpha(pixels, usize::from(colors));
+        let mut image = image_dict(width, height, 8);
+        let space: &[u8] = if colors == 2 {
+            b"DeviceGray"
+        } else {
+            b"DeviceRGB"
+        };
+        image.set("ColorSpace", Object::Name(space.to_vec()));
+        let mut mask = image_dict(width, height, 8);
+        mask.set("ColorSpace", Object::Name(b"DeviceGray".to_vec()));
+        let mut image = Stream::new(image, color);
+        let mut mask = Stream::new(mask, alpha);
+        image
+            .compress()
+            .and_then(|()| mask.compress())
+            .map_err(|e| PdfError::InvalidArgument(format!("compressing the PNG: {e}")))?;
+        Ok(Self {
+            width,
+            height,
+            image,
+            mask: Some(mask),
+        })
+    }
+}
+
+/// The entries every image `XObject` has.
+fn image_dict(width: u32, height: u32, bits: u8) -> Dictionary {
+    Dictionary::from_iter([
+        ("Type", Object::Name(b"XObject".to_vec())),
+        ("Subtype", Object::Name(b"Image".to_vec())),
+        ("Width", Object::Integer(width.into())),
+        ("Height", Object::Integer(height.into())),
+        ("BitsPerComponent", Object::Integer(bits.into())),
+    ])
+}
+
+/// Split pixels of `channels` samples, the last of them alpha, into their
+/// color and alpha samples.
+fn split_alpha(pixels: &[u8], channels: usize) -> (Vec<u8>, Vec<u8>) {
+    let mut color = Vec::with_capacity(pixels.len() / channels * (channels - 1));
+    let mut alpha = Vec::with_capacity(pixels.len() / channels);
+    for pixel in pixels.chunks_exact(channels) {
+        color.extend_from_slice(&pixel[..channels - 1]);
+        alpha.push(pixel[channels - 1]);
+    }
+    (color, alpha)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A PNG of `rows` (each starting with its filter byte), compressed
+    /// with stored deflate blocks. The checksums are not checked.
+    fn png(width: u32, color_type: u8, rows: &[u8]) -> Vec<u8> {
+        let mut zlib = vec![0x78, 0x01, 0x01];
+        let len = u16::try_from(rows.len()).unwrap_or_default();
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(rows);
+        let mut a: u32 = 1;
+        let mut b: u32 = 0;
+        for &byte in rows {
+            a = (a + u32::from(byte)) % 65_521;
+            b = (b + a) % 65_521;
+        }
+        zlib.extend_from_slice(&((b << 16) | a).to_be_bytes());
+
+        let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+        let mut chunk = |kind: &[u8], data: &[u8]| {
+            let len = u32::try_from(data.len()).unwrap_or_default();
+            out.extend_from_slice(&len.to_be_bytes());
+            out.extend_from_slice(kind);
+            out.extend_from_slice(data);
+            out.extend_from_slice(&[0; 4]);
+        };
+        let mut header = width.to_be_bytes().to_vec();
+        header.extend_from_slice(&1_u32.to_be_bytes());
+        header.extend_from_slice(&[8, color_type, 0, 0, 0]);
+        chunk(b"IHDR", &header);
+        chunk(b"IDAT", &zlib);
+        chunk(b"IEND", b"");
+        out
+    }
+
+    #[test]
+    fn png_alpha_becomes_a_soft_mask() -> Result<()> {
+        // Two gray pixels with alpha, the second row-filtered with "Sub".
+        let picture = Picture::decode(&png(2, 4, &[1, 10, 200, 5, 55]))?;
+        assert_eq!((picture.width, picture.height), (2, 1));
+        let decoded = |stream: &Stream| {
+            let mut stream = stream.clone();
+            stream.dict.remove(b"Subtype");
+            if !stream.dict.has(b"Filter") {
+                return Ok(stream.content);
+            }
+            stream
+                .decompressed_content()
+                .map_err(|e| PdfError::InvalidArgument(e.to_string()))
+        };
+        let color = decoded(&picture.image)?;
+        let mask = picture.mask.as_ref().map(decoded).transpose()?;
+        assert_eq!(color, [10, 15]);
+        assert_eq!(mask, Some(vec![200, 255]));
+
+        let opaque = Picture::decode(&png(1, 2, &[0, 1, 2, 3]))?;
+        assert!(opaque.mask.is_none());
+        assert!(opaque.image.dict.has(b"DecodeParms"));
+        assert!((opaque.height(50.0) - 50.0).abs() < f32::EPSILON);
+        assert!(Picture::decode(b"GIF89a").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn jpeg_size_comes_from_the_frame_header() -> Result<()> {
+        let jpeg = [
+            0xff, 0xd8, 0xff, 0xe0, 0, 4, 0, 0, 0xff, 0xc0, 0, 11, 8, 0, 20, 0, 30, 3, 1, 2, 3,
+        ];
+        let picture = Picture::decode(&jpeg)?;
+        assert_eq!((picture.width, picture.height), (30, 20));
+        assert_eq!(
+            picture.image.dict.get(b"ColorSpace").ok(),
+            Some(&Object::Name(b"DeviceRGB".to_vec()))
+        );
+        assert!(Picture::decode(&jpeg[..10]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn dates_are_formatted_with_strftime_fields() -> Result<()> {
+        let date = (2026, 3, 7);
+        assert_eq!(format_date("%Y-%m-%d", date)?, "2026-03-07");
+        assert_eq!(format_date("%e %B %Y", date)?, "7 March 2026");
+        assert_eq!(format_date("%d %b %y, 100%%", date)?, "07 Mar 26, 100%");
+        assert!(format_date("%H:%M", date).is_err());
+        assert!(format_date("50%", date).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,417 @@
+//! Verifying the digital signatures in a document: that the bytes each one
+//! covers are unchanged, and that the RFC 3161 timestamp a signature carries
+//! was issued for it. The cryptography is left to `openssl`; which bytes are
+//! covered and which timestamp belongs to which signature are worked out
+//! here.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use lopdf::{Dictionary, Document, Object};
+
+use crate::{
+    cms, find_tool, load_document, metadata::decode_text_string, run_tool_capture, sign::sha256,
+    tempspace::TempSpace, validate_input_file, PdfError, Result, Tool,
+};
+
+/// Options for [`signatures`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SignaturesOptions {
+    /// PEM file with the trusted root certificates. Without it, only the
+    /// integrity of signatures and timestamps is checked, not who made them.
+    pub ca_file: Option<PathBuf>,
+}
+
+/// One signature, as reported by [`signatures`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureInfo {
+    /// Name of the signature field.
+    pub field: String,
+    /// The signer's name as recorded in the signature (`/Name`).
+    pub signer: Option<String>,
+    /// The signing time the signer claims (`/M`, a PDF date).
+    pub signed_at: Option<String>,
+    /// The signature format (`/SubFilter`), e.g. `ETSI.CAdES.detached`.
+    pub sub_filter: String,
+    /// The signed byte ranges, as offset and length pairs.
+    pub byte_range: Vec<(u64, u64)>,
+    /// Whether the signature covers the whole file; if not, the file was
+    /// updated after it was signed.
+    pub covers_whole_file: bool,
+    /// Whether the signed bytes are unchanged (and, with
+    /// [`SignaturesOptions::ca_file`], the signer's certificate is trusted).
+    pub valid: bool,
+    /// Why the signature is not valid.
+    pub problem: Option<String>,
+    /// The signature's timestamp, if it has one.
+    pub timestamp: Option<TimestampInfo>,
+}
+
+/// An RFC 3161 timestamp of a signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimestampInfo {
+    /// When the signature was timestamped, as a `GeneralizedTime` (e.g.
+    /// `20261016093000Z`).
+    pub time: String,
+    /// Whether the token was issued for this signature and is intact (and,
+    /// with [`SignaturesOptions::ca_file`], the authority is trusted).
+    pub valid: bool,
+    /// Why the timestamp is not valid.
+    pub problem: Option<String>,
+}
+
+/// Verify the signatures of `input` (requires `openssl`), in the order
+/// their fields appear in the file. A document without signatures gives an
+/// empty list.
+pub fn signatures(
+    input: impl AsRef<Path>,
+    options: &SignaturesOptions,
+) -> Result<Vec<SignatureInfo>> {
+    let input = input.as_ref();
+    if let Some(ca_file) = &options.ca_file {
+        validate_input_file(ca_file)?;
+    }
+    let doc = load_document(input)?;
+    let bytes = fs::read(input)?;
+    let fields = signature_fields(&doc);
+    if fields.is_empty() {
+        return Ok(Vec::new());
+    }
+    let scratch = TempSpace::new("signatures")?;
+    fields
+        .into_iter()
+        .enumerate()
+        .map(|(i, (field, signature))| {
+            let dir = scratch.file(&format!("signature-{}", i + 1));
+            fs::create_dir_all(&dir)?;
+            verify(&bytes, field, signature, &dir, options)
+        })
+        .collect()
+}
+
+/// The signature fields of `doc` that have been signed, with their names
+/// and signature dictionaries.
+fn signature_fields(doc: &Document) -> Vec<(String, &Dictionary)> {
+    doc.objects
+        .values()
+        .filter_map(|obj| obj.as_dict().ok())
+        .filter(|dict| {
+            dict.get(b"FT")
+                .and_then(Object::as_name)
+                .is_ok_and(|ft| ft == b"Sig")
+        })
+        .filter_map(|field| {
+            let signature = field
+                .get_deref(b"V", doc)
+                .and_then(Object::as_dict)
+                .ok()
+                .filter(|signature| signature.has(b"ByteRange"))?;
+            let name = field
+                .get(b"T")
+                .and_then(Object::as_str)
+                .map(decode_text_string)
+                .unwrap_or_default();
+            Some((name, signature))
+        })
+        .collect()
+}
+
+fn verify(
+    bytes: &[u8],
+    field: String,
+    signature: &Dictionary,
+    dir: &Path,
+    options: &SignaturesOptions,
+) -> Result<SignatureInfo> {
+    let text = |key: &[u8]| {
+        signature
+            .get(key)
+            .and_then(Object::as_str)
+            .ok()
+            .map(decode_text_string)
+    };
+    let sub_filter = signature
+        .get(b"SubFilter")
+        .and_then(Object::as_name)
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .unwrap_or_default();
+    let mut info = SignatureInfo {
+        signer: text(b"Name"),
+        signed_at: text(b"M"),
+        sub_filter,
+        byte_range: byte_range(signature),
+        covers_whole_file: false,
+        valid: false,
+        problem: None,
+        timestamp: None,
+        field,
+    };
+    let Some(covered) = covered_bytes(bytes, &info.byte_range) else {
+        info.problem = Some("the byte range lies outside the file".to_string());
+        return Ok(info);
+    };
+    info.covers_whole_file = info
+        .byte_range
+        .last()
+        .is_some_and(|&(offset, len)| offset + len == bytes.len() as u64);
+    if info.sub_filter == "adbe.pkcs7.sha1" || info.sub_filter == "adbe.x509.rsa_sha1" {
+        info.problem = Some(format!("{} signatures are not supported", info.sub_filter));
+        return Ok(info);
+    }
+    let contents = signature
+        .get(b"Contents")
+        .and_then(Object::as_str)
+        .unwrap_or_default();
+    let Ok(len) = cms::element_len(contents) else {
+        info.problem = Some("the signature is not a CMS signature".to_string());
+        return Ok(info);
+    };
+    let der = &contents[..len];
+
+    let files = VerifyFiles::in_dir(dir);
+    fs::write(&files.signature, der)?;
+    fs::write(&files.content, covered)?;
+    let problem = tool_problem(run_tool_capture(
+        Tool::Openssl,
+        verify_command(&files, options.ca_file.as_deref())?,
+    ))?;
+    info.valid = problem.is_none();
+    info.problem = problem;
+    info.timestamp = match cms::timestamp_token(der) {
+        Ok(Some(token)) => Some(verify_timestamp(der, &token, &files, options)?),
+        Ok(None) => None,
+        Err(e) => Some(TimestampInfo {
+            time: String::new(),
+            valid: false,
+            problem: Some(e.to_string()),
+        }),
+    };
+    Ok(info)
+}
+
+fn verify_timestamp(
+    signature: &[u8],
+    token: &[u8],
+    files: &VerifyFiles,
+    options: &SignaturesOptions,
+) -> Result<TimestampInfo> {
+    let mut stamp = TimestampInfo {
+        time: String::new(),
+        valid: false,
+        problem: None,
+    };
+    let tst = match cms::tst_info(token) {
+        Ok(tst) => tst,
+        Err(e) => {
+            stamp.problem = Some(e.to_string());
+            return Ok(stamp);
+        }
+    };
+    stamp.time = tst.gen_time;
+    let value = cms::signature_value(signature)?;
+    if tst.hash_algorithm != cms::SHA256 {
+        stamp.problem = Some("only SHA-256 timestamps are supported".to_string());
+        return Ok(stamp);
+    }
+    if tst.hashed_message != sha256(&value)[..] {
+        stamp.problem = Some("the timestamp was issued for a different signature".to_string());
+        return Ok(stamp);
+    }
+    fs::write(&files.token, token)?;
+    fs::write(&files.signature_value, &value)?;
+    stamp.problem = tool_problem(run_tool_capture(
+        Tool::Openssl,
+        timestamp_command(files, options.ca_file.as_deref())?,
+    ))?;
+    stamp.valid = stamp.problem.is_none();
+    Ok(stamp)
+}
+
+/// `/ByteRange` as offset and length pairs.
+fn byte_range(signature: &Dictionary) -> Vec<(u64, u64)> {
+    let numbers: Vec<u64> = signature
+        .get(b"ByteRange")
+        .and_then(Object::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.as_i64().ok())
+                .filter_map(|n| u64::try_from(n).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    numbers
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .collect()
+}
+
+/// The bytes of `file` in `ranges`, or `None` if a range is out of bounds.
+fn covered_bytes(file: &[u8], ranges: &[(u64, u64)]) -> Option<Vec<u8>> {
+    if ranges.is_empty() {
+        return None;
+    }
+    let mut out = Vec::new();
+    for &(offset, len) in ranges {
+        let start = usize::try_from(offset).ok()?;
+        let end = start.checked_add(usize::try_from(len).ok()?)?;
+        out.extend_from_slice(file.get(start..end)?);
+    }
+    Some(out)
+}
+
+/// The message of a failed `openssl` check, or `None` if it passed.
+fn tool_problem(result: Result<String>) -> Result<Option<String>> {
+    match result {
+        Ok(_) => Ok(None),
+        Err(PdfError::ToolFailed { stdout, stderr, .. }) => {
+            Ok(Some(failure_message(&format!("{stderr}\n{stdout}"))))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// The most telling line of `openssl` output about a failed check: a plain
+/// message if there is one, otherwise the reason in its first error line
+/// (`<code>:error:<n>:<library>:<function>:<reason>:<file>:<line>:<data>`).
+fn failure_message(output: &str) -> String {
+    let lines: Vec<&str> = output
+        .lines()
+        .map(str::trim)
+        .filter(|line| {
+            !line.is_empty()
+                && !line.starts_with("Using configuration")
+                && !line.starts_with("Verification: FAILED")
+        })
+        .collect();
+    if let Some(line) = lines.iter().find(|line| !line.contains(":error:")) {
+        return (*line).to_string();
+    }
+    let Some(line) = lines.first() else {
+        return "verification failed".to_string();
+    };
+    let fields: Vec<&str> = line.splitn(9, ':').collect();
+    match fields[..] {
+        [.., reason, _, _, data] if !data.is_empty() => format!("{reason}: {data}"),
+        [_, _, _, _, _, reason, ..] => reason.to_string(),
+        _ => (*line).to_string(),
+    }
+}
+
+/// The intermediate files of verifying one signature.
+struct VerifyFiles {
+    signature: PathBuf,
+    content: PathBuf,
+    verified: PathBuf,
+    token: PathBuf,
+    signature_value: PathBuf,
+}
+
+impl VerifyFiles {
+    fn in_dir(dir: &Path) -> Self {
+        Self {
+            signature: dir.join("signature.der"),
+            content: dir.join("signed-bytes.bin"),
+            verified: dir.join("verified.bin"),
+            token: dir.join("token.der"),
+            signature_value: dir.join("signature-value.bin"),
+        }
+    }
+}
+
+/// `openssl cms -verify` checking a detached signature of the signed bytes,
+/// and the signer's certificate against `ca_file` if one is given.
+fn verify_command(files: &VerifyFiles, ca_file: Option<&Path>) -> Result<Command> {
+    let mut cmd = Command::new(find_tool(Tool::Openssl)?);
+    cmd.arg("cms")
+        .arg("-verify")
+        .arg("-binary")
+        .arg("-inform")
+        .arg("DER")
+        .arg("-in")
+        .arg(files.signature.as_os_str())
+        .arg("-content")
+        .arg(files.content.as_os_str());
+    trust(&mut cmd, ca_file);
+    cmd.arg("-out").arg(files.verified.as_os_str());
+    Ok(cmd)
+}
+
+/// `openssl ts -verify` checking a timestamp token against the signature
+/// value with `ca_file`, or without one `openssl cms -verify` checking only
+/// that the token is intact.
+fn timestamp_command(files: &VerifyFiles, ca_file: Option<&Path>) -> Result<Command> {
+    let mut cmd = Command::new(find_tool(Tool::Openssl)?);
+    match ca_file {
+        Some(ca_file) => {
+            cmd.arg("ts")
+                .arg("-verify")
+                .arg("-in")
+                .arg(files.token.as_os_str())
+                .arg("-token_in")
+                .arg("-data")
+                .arg(files.signature_value.as_os_str())
+                .arg("-CAfile")
+                .arg(ca_file.as_os_str());
+        }
+        None => {
+            cmd.arg("cms")
+                .arg("-verify")
+                .arg("-inform")
+                .arg("DER")
+                .arg("-in")
+                .arg(files.token.as_os_str())
+                .arg("-noverify")
+                .arg("-out")
+                .arg(files.verified.as_os_str());
+        }
+    }
+    Ok(cmd)
+}
+
+fn trust(cmd: &mut Command, ca_file: Option<&Path>) {
+    match ca_file {
+        Some(ca_file) => {
+            cmd.arg("-CAfile").arg(ca_file.as_os_str());
+        }
+        None => {
+            cmd.arg("-noverify");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn covered_bytes_skip_the_signature() {
+        let file = b"0123456789";
+        assert_eq!(
+            covered_bytes(file, &[(0, 3), (7, 3)]),
+            Some(b"012789".to_vec())
+        );
+        assert_eq!(covered_bytes(file, &[(0, 3), (7, 4)]), None);
+        assert_eq!(covered_bytes(file, &[]), None);
+    }
+
+    #[test]
+    fn failures_are_told_by_their_reason() {
+        let ts = "Using configuration from /etc/ssl/openssl.cnf\n\
+            40B7:error:17800064:time stamp routines:ts_verify_cert:certificate verify error:\
+            crypto/ts/ts_rsp_verify.c:190:Verify error:self-signed certificate\n\
+            Verification: FAILED\n";
+        assert_eq!(
+            failure_message(ts),
+            "certificate verify error: Verify error:self-signed certificate"
+        );
+        let cms = "CMS Verification failure\n\
+            40B7:error:10800065:CMS routines:cms_signerinfo_verify_content:verification failure:\
+            crypto/cms/cms_sd.c:1043:\n";
+        assert_eq!(failure_message(cms), "CMS Verification failure");
+        assert_eq!(failure_message(""), "verification failed");
+    }
+}
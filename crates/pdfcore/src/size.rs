@@ -0,0 +1,286 @@
+//! Byte attribution: which kinds of objects make a PDF large.
+
+use std::{collections::BTreeSet, fmt, fs, path::Path};
+
+use crate::{fonts, load_document, Result};
+
+/// How many of the largest objects a [`SizeReport`] lists.
+const LARGEST_OBJECTS: usize = 10;
+
+/// Broad object categories used by [`size_report`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SizeCategory {
+    /// Image `XObject`s (including soft masks).
+    Images,
+    /// Font dictionaries, descriptors, font programs, and `ToUnicode` maps.
+    Fonts,
+    /// Page content streams and form `XObject`s.
+    ContentStreams,
+    /// XMP metadata streams and the document information dictionary.
+    Metadata,
+    /// Embedded files and their file specifications.
+    EmbeddedFiles,
+    /// Everything else, plus cross-reference tables and file structure.
+    Other,
+}
+
+impl SizeCategory {
+    /// Lower-case identifier, e.g. `content-streams`.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Images => "images",
+            Self::Fonts => "fonts",
+            Self::ContentStreams => "content-streams",
+            Self::Metadata => "metadata",
+            Self::EmbeddedFiles => "embedded-files",
+            Self::Other => "other",
+        }
+    }
+}
+
+impl fmt::Display for SizeCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Bytes attributed to one [`SizeCategory`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeBucket {
+    /// The category.
+    pub category: SizeCategory,
+    /// Approximate bytes used (stream data is exact; dictionaries are estimated).
+    pub bytes: u64,
+    /// Number of objects in the category.
+    pub objects: usize,
+}
+
+/// One of the largest individual objects in a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizedObject {
+    /// Object number and generation.
+    pub id: (u32, u16),
+    /// Category the object was attributed to.
+    pub category: SizeCategory,
+    /// Approximate bytes used.
+    pub bytes: u64,
+}
+
+/// Result of [`size_report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeReport {
+    /// Size of the file on disk.
+    pub file_bytes: u64,
+    /// Per-category totals, largest first. Categories with no bytes are omitted.
+    pub buckets: Vec<SizeBucket>,
+    /// The largest individual objects, largest first.
+    pub largest: Vec<SizedObject>,
+}
+
+/// Attribute a PDF's bytes to images, fonts, content streams, metadata, and
+/// embedded files (pure Rust; no external tools).
+///
+/// Stream sizes are the stored (compressed) lengths; non-stream objects are
+/// estimated from their serialized form. Whatever cannot be attributed to an
+/// object (cross-reference data, padding) is counted as
+/// [`SizeCategory::Other`].
+pub fn size_report(path: impl AsRef<Path>) -> Result<SizeReport> {
+    let path = path.as_ref();
+    let doc = load_document(path)?;
+    let file_bytes = fs::metadata(path)?.len();
+
+    let font_parts = font_owned_objects(&doc);
+    let content_ids: BTreeSet<lopdf::ObjectId> = doc
+        .get_pages()
+        .into_values()
+        .flat_map(|id| doc.get_page_contents(id))
+        .collect();
+    let info_id = doc
+        .trailer
+        .get(b"Info")
+        .and_then(lopdf::Object::as_reference)
+        .ok();
+
+    let mut totals = [0_u64; 6];
+    let mut counts = [0_usize; 6];
+    let mut sized = Vec::new();
+    for (&id, obj) in &doc.objects {
+        if is_structural(obj) {
+            continue;
+        }
+        let category = if content_ids.contains(&id) {
+            SizeCategory::ContentStreams
+        } else if font_parts.contains(&id) {
+            SizeCategory::Fonts
+        } else if Some(id) == info_id {
+            SizeCategory::Metadata
+        } else {
+            classify(obj)
+        };
+        let bytes = object_len(obj) + OBJ_WRAPPER_LEN;
+        totals[category as usize] += bytes;
+        counts[category as usize] += 1;
+        sized.push(SizedObject {
+            id,
+            category,
+            bytes,
+        });
+    }
+
+    let attributed: u64 = totals.iter().sum();
+    totals[SizeCategory::Other as usize] += file_bytes.saturating_sub(attributed);
+
+    let all = [
+        SizeCategory::Images,
+        SizeCategory::Fonts,
+        SizeCategory::ContentStreams,
+        SizeCategory::Metadata,
+        SizeCategory::EmbeddedFiles,
+        SizeCategory::Other,
+    ];
+    let mut buckets: Vec<SizeBucket> = all
+        .into_iter()
+        .map(|category| SizeBucket {
+            category,
+            bytes: totals[category as usize],
+            objects: counts[category as usize],
+        })
+        .filter(|b| b.bytes > 0)
+        .collect();
+    buckets.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.category.cmp(&b.category)));
+
+    sized.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.id.cmp(&b.id)));
+    sized.truncate(LARGEST_OBJECTS);
+
+    Ok(SizeReport {
+        file_bytes,
+        buckets,
+        largest: sized,
+    })
+}
+
+/// `N G obj\n` … `\nendobj\n` around every indirect object.
+pub(crate) const OBJ_WRAPPER_LEN: u64 = 20;
+
+/// Object and cross-reference streams only repackage other objects.
+pub(crate) fn is_structural(obj: &lopdf::Object) -> bool {
+    obj.as_stream()
+        .is_ok_and(|s| s.dict.type_is(b"ObjStm") || s.dict.type_is(b"XRef"))
+}
+
+fn classify(obj: &lopdf::Object) -> SizeCategory {
+    let dict = match obj {
+        lopdf::Object::Dictionary(d) => d,
+        lopdf::Object::Stream(s) => &s.dict,
+        _ => return SizeCategory::Other,
+    };
+    let subtype = dict
+        .get(b"Subtype")
+        .and_then(lopdf::Object::as_name)
+        .unwrap_or_default();
+    if subtype == b"Image" {
+        SizeCategory::Images
+    } else if subtype == b"Form" {
+        SizeCategory::ContentStreams
+    } else if dict.type_is(b"Font") || dict.type_is(b"FontDescriptor") {
+        SizeCategory::Fonts
+    } else if dict.type_is(b"Metadata") {
+        SizeCategory::Metadata
+    } else if dict.type_is(b"EmbeddedFile") || dict.type_is(b"Filespec") {
+        SizeCategory::EmbeddedFiles
+    } else {
+        SizeCategory::Other
+    }
+}
+
+/// Untyped objects that belong to fonts: font programs, `ToUnicode` maps,
+/// width arrays, and CID-to-GID maps.
+pub(crate) fn font_owned_objects(doc: &lopdf::Document) -> BTreeSet<lopdf::ObjectId> {
+    const FONT_KEYS: [&[u8]; 8] = [
+        b"FontFile",
+        b"FontFile2",
+        b"FontFile3",
+        b"ToUnicode",
+        b"Widths",
+        b"W",
+        b"CIDToGIDMap",
+        b"Encoding",
+    ];
+    let mut owned = BTreeSet::new();
+    for obj in doc.objects.values() {
+        let Ok(dict) = obj.as_dict() else {
+            continue;
+        };
+        if !(dict.type_is(b"Font") || dict.type_is(b"FontDescriptor")) {
+            continue;
+        }
+        for key in FONT_KEYS {
+            if let Ok(id) = dict.get(key).and_then(lopdf::Object::as_reference) {
+                owned.insert(id);
+            }
+        }
+        if let Some(desc) = fonts::resolve_dict(doc, dict.get(b"FontDescriptor").ok()) {
+            for key in FONT_KEYS {
+                if let Ok(id) = desc.get(key).and_then(lopdf::Object::as_reference) {
+                    owned.insert(id);
+                }
+            }
+        }
+    }
+    owned
+}
+
+/// Approximate serialized length of an object.
+pub(crate) fn object_len(obj: &lopdf::Object) -> u64 {
+    let len = |n: usize| u64::try_from(n).unwrap_or(u64::MAX);
+    match obj {
+        lopdf::Object::Null => 4,
+        lopdf::Object::Boolean(b) => 4 + u64::from(!b),
+        lopdf::Object::Integer(i) => len(i.to_string().len()),
+        lopdf::Object::Real(r) => len(r.to_string().len()),
+        lopdf::Object::Name(n) => len(n.len()) + 1,
+        lopdf::Object::String(s, _) => len(s.len()) + 2,
+        lopdf::Object::Array(items) => 2 + items.iter().map(|o| object_len(o) + 1).sum::<u64>(),
+        lopdf::Object::Dictionary(d) => dict_len(d),
+        lopdf::Object::Stream(s) => dict_len(&s.dict) + len(s.content.len()) + 18,
+        lopdf::Object::Reference((n, g)) => len(format!("{n} {g} R").len()),
+    }
+}
+
+fn dict_len(dict: &lopdf::Dictionary) -> u64 {
+    4 + dict
+        .iter()
+        .map(|(k, v)| u64::try_from(k.len()).unwrap_or(u64::MAX) + 2 + object_len(v))
+        .sum::<u64>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn objects_are_classified_by_type_and_role() {
+        let image = lopdf::Object::Stream(lopdf::Stream::new(
+            lopdf::Dictionary::from_iter([
+                ("Type", lopdf::Object::Name(b"XObject".to_vec())),
+                ("Subtype", lopdf::Object::Name(b"Image".to_vec())),
+            ]),
+            vec![0; 1000],
+        ));
+        assert_eq!(classify(&image), SizeCategory::Images);
+        assert!(object_len(&image) > 1000);
+
+        let mut doc = lopdf::Document::with_version("1.4");
+        let program = doc.add_object(lopdf::Stream::new(lopdf::Dictionary::new(), vec![0; 64]));
+        doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"FontDescriptor".to_vec())),
+            ("FontFile2", lopdf::Object::Reference(program)),
+        ]));
+        assert!(font_owned_objects(&doc).contains(&program));
+        assert_eq!(
+            classify(doc.get_object(program).unwrap_or(&lopdf::Object::Null)),
+            SizeCategory::Other
+        );
+    }
+}
@@ -0,0 +1,444 @@
+//! Splitting a document into files: named from its text, such as a batch
+//! of scanned invoices named by their invoice numbers, cut at separator
+//! sheets put between the documents of a scanned batch, or cut into parts
+//! small enough to send by email.
+
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use regex::Regex;
+
+use crate::{
+    page_count, qr::read_qr, raster::for_each_gray_page, select_pages, tempspace::TempSpace,
+    validate_input_file, ExtractText, PdfError, Plan, Result,
+};
+
+/// Resolution pages are rendered at to look for separator sheets.
+const SEPARATOR_DPI: u32 = 150;
+
+/// Gray levels below this count as ink on a rendered page.
+const INK_LEVEL: u8 = 128;
+
+/// Pages with less ink than this, as a fraction of the page inside a 5%
+/// margin, are blank: specks and scanner noise, but not a line of text.
+const BLANK_INK: f64 = 0.001;
+
+/// One file of a document split by [`split_parts_by_text`] or
+/// [`split_parts_by_separator`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitPart {
+    /// Where the part goes.
+    pub path: PathBuf,
+    /// 1-based pages of the input it holds, in order.
+    pub pages: Vec<u32>,
+    /// The text that named it; `None` for pages before the first match,
+    /// which are named after their first page, and for parts that are not
+    /// named from text.
+    pub matched: Option<String>,
+}
+
+/// What marks the start of the next document in a scanned batch, for
+/// [`split_parts_by_separator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Separator {
+    /// A blank sheet.
+    Blank,
+    /// A sheet with a QR code, whose text names the document after it.
+    Qr,
+}
+
+/// How to split `input` into files in `out_dir` named from its text: each
+/// page whose text matches `pattern` starts a new file, named from the
+/// first capture group of the match (or the whole match if the pattern has
+/// no group), and the pages after it that do not match belong to the same
+/// file. Names are made safe for the file system, and repeated names get a
+/// `-2`, `-3`, ... suffix.
+///
+/// The text is extracted with the engine chosen as for
+/// [`crate::extract_text`]; write the parts with [`crate::select_pages`].
+pub fn split_parts_by_text(
+    input: impl AsRef<Path>,
+    out_dir: impl AsRef<Path>,
+    pattern: &str,
+) -> Result<Vec<SplitPart>> {
+    let (input, out_dir) = (input.as_ref(), out_dir.as_ref());
+    let regex = Regex::new(pattern)
+        .map_err(|e| PdfError::InvalidArgument(format!("invalid pattern: {e}")))?;
+    let text = ExtractText::new(input).run()?;
+    // Every engine ends each page with a form feed.
+    let texts: Vec<&str> = text
+        .strip_suffix('\x0c')
+        .unwrap_or(&text)
+        .split('\x0c')
+        .collect();
+    Ok(group_pages(&texts, page_count(input)?, &regex)
+        .into_iter()
+        .map(|(name, matched, pages)| SplitPart {
+            path: out_dir.join(name),
+            pages,
+            matched,
+        })
+        .collect())
+}
+
+/// How to split `input` into files in `out_dir` at separator sheets: each
+/// separator page starts a new file and is left out of it. Parts after a QR
+/// code are named from its text (made safe for the file system, with a
+/// `-2`, `-3`, ... suffix if repeated); other parts are `part-<n>.pdf`.
+/// Separators next to each other start one file, and with QR codes, a
+/// blank page right after a separator (its back, in a duplex scan) is left
+/// out as well.
+///
+/// Pages are rendered with the engine chosen as for [`crate::render`];
+/// write the parts with [`crate::select_pages`].
+pub fn split_parts_by_separator(
+    input: impl AsRef<Path>,
+    out_dir: impl AsRef<Path>,
+    separator: Separator,
+) -> Result<Vec<SplitPart>> {
+    let (input, out_dir) = (input.as_ref(), out_dir.as_ref());
+    let mut sheets = Vec::new();
+    for_each_gray_page(input, SEPARATOR_DPI, |page, image| {
+        let blank = image.ink_coverage(INK_LEVEL, 0.05) < BLANK_INK;
+        let sheet = match separator {
+            Separator::Blank if blank => Sheet::Separator(None),
+            Separator::Qr if blank => Sheet::Blank,
+            Separator::Qr => {
+                read_qr(&image).map_or(Sheet::Content, |text| Sheet::Separator(Some(text)))
+            }
+            Separator::Blank => Sheet::Content,
+        };
+        tracing::debug!(page, ?sheet, "looked for a separator");
+        sheets.push(sheet);
+        Ok(())
+    })?;
+    Ok(group_at_separators(&sheets)
+        .into_iter()
+        .map(|(name, matched, pages)| SplitPart {
+            path: out_dir.join(name),
+            pages,
+            matched,
+        })
+        .collect())
+}
+
+/// Split `input` into parts of consecutive pages of at most `max_bytes`
+/// each, written to `out_dir` as `<stem>.part1.pdf`, `<stem>.part2.pdf`,
+/// ... Each part holds as many pages as fit.
+///
+/// The size of a part is only known once it is written, so candidate parts
+/// are written (with [`crate::select_pages`]) and measured: the first guess
+/// comes from the input's average page size, and the number of pages is
+/// doubled while the part fits and then narrowed down. A page that does not
+/// fit the limit on its own is an error.
+pub fn split_by_size(
+    input: impl AsRef<Path>,
+    out_dir: impl AsRef<Path>,
+    max_bytes: u64,
+) -> Result<Vec<SplitPart>> {
+    let (input, out_dir) = (input.as_ref(), out_dir.as_ref());
+    validate_input_file(input)?;
+    if max_bytes == 0 {
+        return Err(PdfError::InvalidArgument(
+            "the size limit must be more than 0 bytes".to_string(),
+        ));
+    }
+    let total = page_count(input)?;
+    let per_page = (fs::metadata(input)?.len() / u64::from(total.max(1))).max(1);
+    let guess = u32::try_from(max_bytes / per_page).unwrap_or(u32::MAX);
+    let scratch = TempSpace::new("split")?;
+    let (trial, best) = (scratch.file("trial.pdf"), scratch.file("best.pdf"));
+    fs::create_dir_all(out_dir)?;
+
+    let mut parts = Vec::new();
+    let mut start = 1;
+    while start <= total {
+        let mut alone = 0;
+        let count = largest_fitting(total - start + 1, guess, |count| {
+            let pages: Vec<u32> = (start..start + count).collect();
+            select_pages(input, &trial, &pages)?;
+            let bytes = fs::metadata(&trial)?.len();
+            tracing::debug!(start, count, bytes, "measured a part");
+            if count == 1 {
+                alone = bytes;
+            }
+            if bytes > max_bytes {
+                return Ok(false);
+            }
+            fs::rename(&trial, &best)?;
+            Ok(true)
+        })?;
+        if count == 0 {
+            return Err(PdfError::InvalidArgument(format!(
+                "page {start} alone takes {alone} bytes, more than the limit of {max_bytes}"
+            )));
+        }
+        let path = out_dir.join(size_part_name(input, parts.len() + 1));
+        // The scratch directory may be on another file system.
+        if fs::rename(&best, &path).is_err() {
+            fs::copy(&best, &path)?;
+        }
+        parts.push(SplitPart {
+            path,
+            pages: (start..start + count).collect(),
+            matched: None,
+        });
+        start += count;
+    }
+    Ok(parts)
+}
+
+/// What [`split_by_size`] would do.
+pub fn split_by_size_plan(
+    input: impl AsRef<Path>,
+    out_dir: impl AsRef<Path>,
+    max_bytes: u64,
+) -> Result<Plan> {
+    let (input, out_dir) = (input.as_ref(), out_dir.as_ref());
+    validate_input_file(input)?;
+    Ok(Plan::new()
+        .native(format!(
+            "write and measure runs of pages of {} until each part is as large as fits in {max_bytes} bytes",
+            input.display()
+        ))
+        .write(&out_dir.join(size_part_name(input, 1)))
+        .write(&out_dir.join(size_part_name(input, 2)))
+        .native("... and so on until every page is in a part"))
+}
+
+/// `<stem>.part<n>.pdf` for the `n`th part of `input`.
+fn size_part_name(input: &Path, n: usize) -> String {
+    let stem = input
+        .file_stem()
+        .map_or_else(|| "part".into(), |stem| stem.to_string_lossy());
+    format!("{stem}.part{n}.pdf")
+}
+
+/// The largest number of pages, up to `remaining`, for which `fits` holds
+/// (0 if not even one page fits), trying `guess` first, then doubling while
+/// parts fit and halving the gap once one does not. Parts are assumed to
+/// grow with their pages, so that fewer pages fit whenever more do.
+fn largest_fitting(
+    remaining: u32,
+    guess: u32,
+    mut fits: impl FnMut(u32) -> Result<bool>,
+) -> Result<u32> {
+    // `lo` pages are known to fit and `hi` pages not to.
+    let (mut lo, mut hi) = (0, remaining + 1);
+    let mut count = guess.clamp(1, remaining);
+    loop {
+        if fits(count)? {
+            lo = count;
+        } else {
+            hi = count;
+        }
+        if hi - lo <= 1 {
+            return Ok(lo);
+        }
+        count = if hi > remaining {
+            lo.saturating_mul(2).clamp(lo + 1, remaining)
+        } else {
+            lo + (hi - lo) / 2
+        };
+    }
+}
+
+/// A page of a scanned batch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Sheet {
+    Content,
+    /// A blank page that is not a separator itself.
+    Blank,
+    /// A separator, with the text of its QR code if it has one.
+    Separator(Option<String>),
+}
+
+/// File name, QR code text, and pages of each part of a batch with the
+/// given pages.
+fn group_at_separators(sheets: &[Sheet]) -> Vec<(String, Option<String>, Vec<u32>)> {
+    let mut parts = Vec::new();
+    let mut used = BTreeSet::new();
+    let (mut label, mut pages): (Option<String>, Vec<u32>) = (None, Vec::new());
+    let mut finish = |label: Option<String>, pages: Vec<u32>| {
+        if pages.is_empty() {
+            return;
+        }
+        let stem = label
+            .as_deref()
+            .map(safe_file_stem)
+            .filter(|stem| !stem.is_empty())
+            .unwrap_or_else(|| format!("part-{}", parts.len() + 1));
+        parts.push((unique_name(&mut used, &stem), label, pages));
+    };
+    for (page, sheet) in (1..).zip(sheets) {
+        match sheet {
+            // The back of a separator.
+            Sheet::Blank if pages.is_empty() && page > 1 => {}
+            Sheet::Content | Sheet::Blank => pages.push(page),
+            Sheet::Separator(text) => {
+                if !pages.is_empty() {
+                    finish(label, std::mem::take(&mut pages));
+                    label = None;
+                }
+                // Of separators next to each other, the last with a code
+                // names the part.
+                if text.is_some() {
+                    label.clone_from(text);
+                }
+            }
+        }
+    }
+    finish(label, pages);
+    parts
+}
+
+/// File name, matched text, and pages of each part of a document of
+/// `count` pages with the given page texts.
+fn group_pages(
+    texts: &[&str],
+    count: u32,
+    regex: &Regex,
+) -> Vec<(String, Option<String>, Vec<u32>)> {
+    let mut parts: Vec<(String, Option<String>, Vec<u32>)> = Vec::new();
+    let mut used = BTreeSet::new();
+    for page in 1..=count {
+        let text = usize::try_from(page - 1)
+            .ok()
+            .and_then(|i| texts.get(i))
+            .copied()
+            .unwrap_or_default();
+        let matched = regex.captures(text).and_then(|caps| {
+            caps.get(1)
+                .or_else(|| caps.get(0))
+                .map(|m| m.as_str().trim().to_string())
+        });
+        match (matched, parts.last_mut()) {
+            (None, Some(part)) => part.2.push(page),
+            (matched, _) => {
+                let stem = matched
+                    .as_deref()
+                    .map(safe_file_stem)
+                    .filter(|stem| !stem.is_empty())
+                    .unwrap_or_else(|| format!("page-{page}"));
+                parts.push((unique_name(&mut used, &stem), matched, vec![page]));
+            }
+        }
+    }
+    parts
+}
+
+/// `<stem>.pdf`, or `<stem>-2.pdf`, `<stem>-3.pdf`, ... if that is in
+/// `used` already; the name is added to `used`.
+pub(crate) fn unique_name(used: &mut BTreeSet<String>, stem: &str) -> String {
+    let mut name = format!("{stem}.pdf");
+    let mut n = 1;
+    while !used.insert(name.clone()) {
+        n += 1;
+        name = format!("{stem}-{n}.pdf");
+    }
+    name
+}
+
+/// `text` with path separators, characters that some file systems reject,
+/// and control characters replaced by `_`, and without leading dots.
+pub(crate) fn safe_file_stem(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_control() || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect::<String>()
+        .trim_start_matches('.')
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parts_take_as_many_pages_as_fit() -> Result<()> {
+        // Pages of 3 bytes, parts of at most 20 bytes: 6 pages fit.
+        let mut tried = Vec::new();
+        let count = largest_fitting(50, 2, |count| {
+            tried.push(count);
+            Ok(count * 3 <= 20)
+        })?;
+        assert_eq!(count, 6);
+        assert_eq!(tried, [2, 4, 8, 6, 7]);
+        assert_eq!(largest_fitting(5, 100, |_| Ok(true))?, 5);
+        assert_eq!(largest_fitting(5, 3, |_| Ok(false))?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn pages_are_grouped_under_the_text_that_names_them() -> Result<()> {
+        let regex = Regex::new(r"Invoice Number: (\S+)")
+            .map_err(|e| PdfError::InvalidArgument(e.to_string()))?;
+        let texts = [
+            "cover letter",
+            "Invoice Number: A-1\ntotal",
+            "page 2 of A-1",
+            "Invoice Number: ../B/2",
+            "Invoice Number: A-1",
+        ];
+        let parts = group_pages(&texts, 6, &regex);
+        let summary: Vec<(&str, Vec<u32>)> = parts
+            .iter()
+            .map(|(name, _, pages)| (name.as_str(), pages.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("page-1.pdf", vec![1]),
+                ("A-1.pdf", vec![2, 3]),
+                ("_B_2.pdf", vec![4]),
+                ("A-1-2.pdf", vec![5, 6]),
+            ]
+        );
+        assert_eq!(parts[2].1.as_deref(), Some("../B/2"));
+        assert_eq!(parts[0].1, None);
+        Ok(())
+    }
+
+    #[test]
+    fn batches_are_cut_at_separators() {
+        use Sheet::{Blank, Content, Separator};
+        let code = |text: &str| Separator(Some(text.to_string()));
+        let sheets = [
+            Content,
+            code("JOB-1"),
+            Blank,
+            Content,
+            Blank,
+            Separator(None),
+            code("JOB-2"),
+            Content,
+            code("JOB-1"),
+            code("empty"),
+            Separator(None),
+            Content,
+        ];
+        let summary: Vec<(String, Option<String>, Vec<u32>)> = group_at_separators(&sheets);
+        assert_eq!(
+            summary,
+            [
+                ("part-1.pdf".to_string(), None, vec![1]),
+                (
+                    "JOB-1.pdf".to_string(),
+                    Some("JOB-1".to_string()),
+                    vec![4, 5]
+                ),
+                ("JOB-2.pdf".to_string(), Some("JOB-2".to_string()), vec![8]),
+                ("empty.pdf".to_string(), Some("empty".to_string()), vec![12]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,311 @@
+//! Native content-stream stamping: drawing on top of existing pages.
+
+use lopdf::{
+    content::{Content, Operation},
+    Dictionary, Document, Object, ObjectId, StringFormat,
+};
+
+use crate::{compose, geometry::effective_page_box, native::inherited, PageBox, PdfError, Result};
+
+/// Where text is stamped on a page, as the page is shown (after its
+/// `/Rotate`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StampPosition {
+    /// Top left corner.
+    TopLeft,
+    /// Top edge, centered.
+    TopCenter,
+    /// Top right corner.
+    TopRight,
+    /// Bottom left corner.
+    BottomLeft,
+    /// Bottom edge, centered.
+    BottomCenter,
+    /// Bottom right corner.
+    BottomRight,
+}
+
+/// A line of text to stamp on a page, in Helvetica.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TextStamp {
+    /// The text, in `WinAnsiEncoding` (see [`stamp_text_bytes`]).
+    pub text: Vec<u8>,
+    /// Font size in points.
+    pub size: f32,
+    /// Distance from the edges of the visible page, in points.
+    pub margin: f32,
+    pub position: StampPosition,
+}
+
+/// `text` in `WinAnsiEncoding`, or an error naming `what` if it has
+/// characters Helvetica cannot show.
+pub(crate) fn stamp_text_bytes(text: &str, what: &str) -> Result<Vec<u8>> {
+    let mut lost = 0;
+    let bytes = compose::encode(text, &mut lost);
+    if lost > 0 || text.chars().any(char::is_control) {
+        return Err(PdfError::InvalidArgument(format!(
+            "{what} {text:?} has characters that cannot be stamped (only Windows-1252 text can)"
+        )));
+    }
+    Ok(bytes)
+}
+
+/// Add the Helvetica font that [`stamp_text`] uses to `doc`, once for all
+/// the pages it stamps.
+pub(crate) fn add_stamp_font(doc: &mut Document) -> ObjectId {
+    doc.add_object(compose::helvetica())
+}
+
+/// Draw `stamp` on top of the page, in the font `font` (see
+/// [`add_stamp_font`]).
+pub(crate) fn stamp_text(
+    doc: &mut Document,
+    page_id: ObjectId,
+    font: ObjectId,
+    stamp: &TextStamp,
+) -> Result<()> {
+    let resource = add_resource(doc, page_id, "Font", font, "PdfcliStamp")?;
+    let (width, height, matrix) = shown_space(doc, page_id);
+    let text_width = compose::helvetica_width(&stamp.text, stamp.size);
+    let x = match stamp.position {
+        StampPosition::TopLeft | StampPosition::BottomLeft => stamp.margin,
+        StampPosition::TopCenter | StampPosition::BottomCenter => (width - text_width) / 2.0,
+        StampPosition::TopRight | StampPosition::BottomRight => width - stamp.margin - text_width,
+    };
+    let y = match stamp.position {
+        // Helvetica's capitals are 0.72 of the size high.
+        StampPosition::TopLeft | StampPosition::TopCenter | StampPosition::TopRight => {
+            height - stamp.margin - stamp.size * 0.72
+        }
+        _ => stamp.margin,
+    };
+    let operations = vec![
+        Operation::new("q", vec![]),
+        Operation::new("cm", matrix.iter().map(|&v| v.into()).collect()),
+        Operation::new("BT", vec![]),
+        Operation::new(
+            "Tf",
+            vec![Object::Name(resource.into_bytes()), stamp.size.into()],
+        ),
+        Operation::new("g", vec![0.into()]),
+        Operation::new("Td", vec![x.into(), y.into()]),
+        Operation::new(
+            "Tj",
+            vec![Object::String(stamp.text.clone(), StringFormat::Literal)],
+        ),
+        Operation::new("ET", vec![]),
+        Operation::new("Q", vec![]),
+    ];
+    let content = Content { operations }
+        .encode()
+        .map_err(|e| PdfError::InvalidArgument(format!("encoding content: {e}")))?;
+    overlay_page_content(doc, page_id, &content)
+}
+
+/// The width and height of the page as shown (its crop box after
+/// `/Rotate`), and the matrix from that space, with the origin at the
+/// bottom left corner as shown, to the page's own.
+pub(crate) fn shown_space(doc: &Document, page_id: ObjectId) -> (f32, f32, [f32; 6]) {
+    let visible = effective_page_box(doc, page_id, PageBox::Crop).normalized();
+    let rotation = doc
+        .get_dictionary(page_id)
+        .ok()
+        .and_then(|page| inherited(doc, page, b"Rotate"))
+        .and_then(|r| r.as_i64().ok())
+        .unwrap_or(0)
+        .rem_euclid(360);
+    match rotation {
+        90 => (
+            visible.height(),
+            visible.width(),
+            [0.0, 1.0, -1.0, 0.0, visible.urx, visible.lly],
+        ),
+        180 => (
+            visible.width(),
+            visible.height(),
+            [-1.0, 0.0, 0.0, -1.0, visible.urx, visible.ury],
+        ),
+        270 => (
+            visible.height(),
+            visible.width(),
+            [0.0, -1.0, 1.0, 0.0, visible.llx, visible.ury],
+        ),
+        _ => (
+            visible.width(),
+            visible.height(),
+            [1.0, 0.0, 0.0, 1.0, visible.llx, visible.lly],
+        ),
+    }
+}
+
+/// The resource dictionary a page uses (its own, or the nearest inherited one).
+pub(crate) fn effective_resources(doc: &Document, page_id: ObjectId) -> Dictionary {
+    let Ok((inline, ids)) = doc.get_page_resources(page_id) else {
+        return Dictionary::new();
+    };
+    inline
+        .or_else(|| ids.first().and_then(|id| doc.get_dictionary(*id).ok()))
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Give the page its own resources with `id` in its `kind` category (e.g.
+/// `Font` or `XObject`), returning the resource name, which starts with
+/// `prefix` unless the page already refers to `id`.
+pub(crate) fn add_resource(
+    doc: &mut Document,
+    page_id: ObjectId,
+    kind: &str,
+    id: ObjectId,
+    prefix: &str,
+) -> Result<String> {
+    let mut resources = effective_resources(doc, page_id);
+    let mut entries = match resources.get(kind.as_bytes()) {
+        Ok(Object::Dictionary(entries)) => entries.clone(),
+        Ok(Object::Reference(entries)) => doc.get_dictionary(*entries).cloned().unwrap_or_default(),
+        _ => Dictionary::new(),
+    };
+    let existing = entries
+        .iter()
+        .find(|(_, value)| value.as_reference().ok() == Some(id))
+        .map(|(name, _)| String::from_utf8_lossy(name).into_owned());
+    let name = if let Some(name) = existing {
+        name
+    } else {
+        let name = (1..=entries.len() + 1)
+            .map(|n| format!("{prefix}{n}"))
+            .find(|name| !entries.has(name.as_bytes()))
+            .unwrap_or_default();
+        entries.set(name.clone(), Object::Reference(id));
+        name
+    };
+    resources.set(kind, Object::Dictionary(entries));
+    let page = doc
+        .get_dictionary_mut(page_id)
+        .map_err(|_| PdfError::InvalidArgument(format!("page object {page_id:?} not found")))?;
+    page.set("Resources", Object::Dictionary(resources));
+    Ok(name)
+}
+
+/// Append `content` to a page's content streams.
+///
+/// Existing content is wrapped in `q`/`Q` so any graphics state it leaves
+/// behind (transforms, colors, clipping) cannot leak into the overlay.
+pub(crate) fn overlay_page_content(
+    doc: &mut lopdf::Document,
+    page_id: lopdf::ObjectId,
+    content: &[u8],
+) -> Result<()> {
+    let mut after = b"\nQ\n".to_vec();
+    after.extend_from_slice(content);
+    wrap_page_content(doc, page_id, b"q\n".to_vec(), after)
+}
+
+/// Put `before` and `after` around a page's content streams, as streams of
+/// their own (the existing streams are left as they are).
+pub(crate) fn wrap_page_content(
+    doc: &mut lopdf::Document,
+    page_id: lopdf::ObjectId,
+    before: Vec<u8>,
+    after: Vec<u8>,
+) -> Result<()> {
+    let existing = doc.get_page_contents(page_id);
+
+    let head = doc.add_object(lopdf::Stream::new(lopdf::Dictionary::new(), before));
+    let tail = doc.add_object(lopdf::Stream::new(lopdf::Dictionary::new(), after));
+
+    let mut contents = Vec::with_capacity(existing.len() + 2);
+    contents.push(lopdf::Object::Reference(head));
+    contents.extend(existing.into_iter().map(lopdf::Object::Reference));
+    contents.push(lopdf::Object::Reference(tail));
+
+    let page = doc
+        .get_dictionary_mut(page_id)
+        .map_err(|_| PdfError::InvalidArgument(format!("page object {page_id:?} not found")))?;
+    page.set("Contents", contents);
+    Ok(())
+}
+
+/// Replace all of a page's content streams with a single new stream.
+pub(crate) fn replace_page_content(
+    doc: &mut lopdf::Document,
+    page_id: lopdf::ObjectId,
+    content: Vec<u8>,
+) -> Result<()> {
+    let stream = doc.add_object(lopdf::Stream::new(lopdf::Dictionary::new(), content));
+    let page = doc
+        .get_dictionary_mut(page_id)
+        .map_err(|_| PdfError::InvalidArgument(format!("page object {page_id:?} not found")))?;
+    page.set("Contents", stream);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Rect;
+
+    #[test]
+    fn overlay_wraps_existing_content() -> Result<()> {
+        let mut doc = lopdf::Document::with_version("1.4");
+        let body = doc.add_object(lopdf::Stream::new(
+            lopdf::Dictionary::new(),
+            b"1 0 0 RG".to_vec(),
+        ));
+        let page_id = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", lopdf::Object::Name(b"Page".to_vec())),
+            ("Contents", lopdf::Object::Reference(body)),
+        ]));
+
+        overlay_page_content(&mut doc, page_id, b"0 0 m 10 10 l S")?;
+
+        let content = doc
+            .get_page_content(page_id)
+            .map_err(|e| PdfError::InvalidArgument(e.to_string()))?;
+        let text = String::from_utf8_lossy(&content);
+        assert!(text.starts_with("q\n"));
+        assert!(text.contains("1 0 0 RG"));
+        assert!(text.trim_end().ends_with("0 0 m 10 10 l S"));
+        Ok(())
+    }
+
+    #[test]
+    fn text_is_stamped_upright_on_rotated_pages() -> Result<()> {
+        let mut doc = Document::with_version("1.4");
+        let page_id = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Page".to_vec())),
+            ("MediaBox", Rect::from_size(600.0, 800.0).to_object()),
+            ("Rotate", Object::Integer(90)),
+        ]));
+        let font = add_stamp_font(&mut doc);
+        let stamp = TextStamp {
+            text: stamp_text_bytes("ABC000001", "prefix")?,
+            size: 10.0,
+            margin: 20.0,
+            position: StampPosition::BottomRight,
+        };
+        stamp_text(&mut doc, page_id, font, &stamp)?;
+        stamp_text(&mut doc, page_id, font, &stamp)?;
+
+        let content = doc
+            .get_page_content(page_id)
+            .map_err(|e| PdfError::InvalidArgument(e.to_string()))?;
+        let text = String::from_utf8_lossy(&content);
+        // Shown 800 wide: the text ends 20 points from the right edge.
+        let x = 800.0 - 20.0 - compose::helvetica_width(b"ABC000001", 10.0);
+        assert!(text.contains("0 1 -1 0 600 0 cm"), "{text}");
+        assert!(text.contains(&format!("{x} 20 Td")), "{text}");
+        assert!(text.contains("/PdfcliStamp1 10 Tf"));
+        let fonts = doc
+            .get_dictionary(page_id)
+            .and_then(|page| page.get(b"Resources"))
+            .and_then(Object::as_dict)
+            .and_then(|resources| resources.get(b"Font"))
+            .and_then(Object::as_dict)
+            .map(Dictionary::len)
+            .ok();
+        assert_eq!(fonts, Some(1));
+        assert!(stamp_text_bytes("BATES\u{2192}", "prefix").is_err());
+        Ok(())
+    }
+}
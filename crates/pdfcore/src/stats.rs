@@ -0,0 +1,132 @@
+//! Text statistics for editors and publishers: word and character counts,
+//! reading time, and the language of each page.
+
+use std::path::Path;
+
+use crate::{
+    extract_text_pages,
+    language::{detect, words},
+    Language, PageSelection, PdfError, Result, TextPagesOptions,
+};
+
+/// Average silent reading speed of adult readers of non-fiction, in words
+/// per minute (Brysbaert, 2019).
+pub const READING_WORDS_PER_MINUTE: u32 = 238;
+
+/// Options for [`text_stats`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextStatsOptions {
+    /// The pages to count.
+    pub pages: PageSelection,
+    /// Reading speed the reading time is estimated at.
+    pub words_per_minute: u32,
+}
+
+impl Default for TextStatsOptions {
+    fn default() -> Self {
+        Self {
+            pages: PageSelection::All,
+            words_per_minute: READING_WORDS_PER_MINUTE,
+        }
+    }
+}
+
+/// Counts for one page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageStats {
+    /// 1-based page number.
+    pub page: u32,
+    /// Words: runs of non-whitespace holding a letter or digit.
+    pub words: u64,
+    /// Characters, spaces included and line breaks not.
+    pub characters: u64,
+    /// Characters other than whitespace.
+    pub characters_no_spaces: u64,
+    /// The page's language and the confidence in it, from 0 to 1, when
+    /// there is enough text to tell.
+    pub language: Option<(Language, f64)>,
+}
+
+/// Result of [`text_stats`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextStats {
+    /// Counts for each selected page, in page order.
+    pub pages: Vec<PageStats>,
+    /// Words on the selected pages.
+    pub words: u64,
+    /// Characters on the selected pages, spaces included.
+    pub characters: u64,
+    /// Characters on the selected pages other than whitespace.
+    pub characters_no_spaces: u64,
+    /// Estimated reading time of the selected pages.
+    pub reading_minutes: f64,
+    /// The language of the selected pages taken together, when there is
+    /// enough text to tell, with the confidence in it.
+    pub language: Option<(Language, f64)>,
+}
+
+/// Count the words and characters of the selected pages of `input`,
+/// estimate their reading time, and detect their language, page by page and
+/// in total (text from [`extract_text_pages`]; languages with `whatlang`).
+///
+/// A language is only reported when the detection is reliable, which takes
+/// more than a line or two of text.
+pub fn text_stats(input: impl AsRef<Path>, options: &TextStatsOptions) -> Result<TextStats> {
+    if options.words_per_minute == 0 {
+        return Err(PdfError::InvalidArgument(
+            "the reading speed must be at least 1 word per minute".to_string(),
+        ));
+    }
+    let texts = extract_text_pages(
+        input,
+        &TextPagesOptions {
+            pages: options.pages.clone(),
+            ..TextPagesOptions::default()
+        },
+    )?;
+    let pages: Vec<PageStats> = texts
+        .iter()
+        .map(|page| {
+            let (words, characters, characters_no_spaces) = count(&page.text);
+            PageStats {
+                page: page.page,
+                words,
+                characters,
+                characters_no_spaces,
+                language: detect(&page.text),
+            }
+        })
+        .collect();
+    let words = pages.iter().map(|p| p.words).sum();
+    let all: String = texts.iter().map(|page| page.text.as_str()).collect();
+    #[allow(clippy::cast_precision_loss)]
+    let reading_minutes = words as f64 / f64::from(options.words_per_minute);
+    Ok(TextStats {
+        words,
+        characters: pages.iter().map(|p| p.characters).sum(),
+        characters_no_spaces: pages.iter().map(|p| p.characters_no_spaces).sum(),
+        reading_minutes,
+        language: detect(&all),
+        pages,
+    })
+}
+
+/// Words, characters (without line breaks), and non-whitespace characters
+/// in `text`.
+fn count(text: &str) -> (u64, u64, u64) {
+    let characters = text.chars().filter(|c| !c.is_control()).count();
+    let no_spaces = text.chars().filter(|c| !c.is_whitespace()).count();
+    (words(text), characters as u64, no_spaces as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_words_and_characters() {
+        assert_eq!(count("Hello, world!\n— 42 \x0c"), (3, 18, 15));
+        assert_eq!(count(""), (0, 0, 0));
+        assert!(detect("ok").is_none());
+    }
+}
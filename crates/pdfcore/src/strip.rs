@@ -0,0 +1,290 @@
+//! Removal of identifying metadata before a document is published: the
+//! document information dictionary, XMP packets, the file identifier, and
+//! optionally the authors of annotations.
+
+use std::{collections::BTreeSet, path::Path};
+
+use lopdf::{Document, Object, ObjectId};
+
+use crate::{load_document, plan::Plan, save_document, Result};
+
+/// What [`strip_metadata`] should keep or also remove.
+#[derive(Debug, Clone, Default)]
+pub struct StripOptions {
+    /// Document information entries to keep, e.g. `Title`.
+    pub keep: Vec<String>,
+    /// Also remove the author (`/T`) of comments and other markup
+    /// annotations. Form fields keep their names.
+    pub annotation_authors: bool,
+}
+
+/// Result of [`strip_metadata`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StripReport {
+    /// Document information entries removed, in key order.
+    pub info_keys: Vec<String>,
+    /// XMP metadata streams removed (document, page, and image level).
+    pub xmp_streams: usize,
+    /// Whether the file identifier (`/ID`) was removed.
+    pub document_id: bool,
+    /// Annotations whose author was removed.
+    pub annotation_authors: usize,
+}
+
+impl StripReport {
+    /// `true` if nothing was removed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Remove the document information dictionary (except the entries in
+/// `options.keep`), every XMP packet, and the file identifier (pure Rust).
+/// The identifier of an encrypted file is kept, as its key depends on it.
+pub fn strip_metadata(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &StripOptions,
+) -> Result<StripReport> {
+    let mut doc = load_document(input.as_ref())?;
+    let report = strip_document(&mut doc, options);
+    doc.prune_objects();
+    save_document(&mut doc, output.as_ref())?;
+    Ok(report)
+}
+
+/// What [`strip_metadata`] would do: one step per kind of metadata it would
+/// remove.
+pub fn strip_metadata_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &StripOptions,
+) -> Result<Plan> {
+    let mut doc = load_document(input.as_ref())?;
+    let report = strip_document(&mut doc, options);
+    let mut plan = Plan::new();
+    if !report.info_keys.is_empty() {
+        plan = plan.native(format!(
+            "remove document info: {}",
+            report.info_keys.join(", ")
+        ));
+    }
+    if report.xmp_streams > 0 {
+        plan = plan.native(format!("remove {} XMP packet(s)", report.xmp_streams));
+    }
+    if report.document_id {
+        plan = plan.native("remove the document ID");
+    }
+    if report.annotation_authors > 0 {
+        plan = plan.native(format!(
+            "remove the author of {} annotation(s)",
+            report.annotation_authors
+        ));
+    }
+    Ok(plan.write(output.as_ref()))
+}
+
+fn strip_document(doc: &mut Document, options: &StripOptions) -> StripReport {
+    let mut report = StripReport {
+        info_keys: strip_info(doc, &options.keep),
+        xmp_streams: strip_xmp(doc),
+        ..StripReport::default()
+    };
+    if !doc.trailer.has(b"Encrypt") {
+        report.document_id = doc.trailer.remove(b"ID").is_some();
+    }
+    if options.annotation_authors {
+        report.annotation_authors = strip_annotation_authors(doc);
+    }
+    report
+}
+
+/// Remove the information entries not in `keep`, and the dictionary itself
+/// if nothing is kept. Returns the removed keys.
+fn strip_info(doc: &mut Document, keep: &[String]) -> Vec<String> {
+    let kept = |key: &[u8]| keep.iter().any(|k| k.as_bytes() == key);
+    let info = match doc.trailer.get(b"Info") {
+        Ok(Object::Reference(id)) => {
+            let id = *id;
+            doc.get_dictionary_mut(id).ok()
+        }
+        Ok(Object::Dictionary(_)) => doc
+            .trailer
+            .get_mut(b"Info")
+            .ok()
+            .and_then(|info| info.as_dict_mut().ok()),
+        _ => None,
+    };
+    let Some(info) = info else {
+        doc.trailer.remove(b"Info");
+        return Vec::new();
+    };
+    let removed: Vec<Vec<u8>> = info
+        .iter()
+        .map(|(key, _)| key.clone())
+        .filter(|key| !kept(key))
+        .collect();
+    for key in &removed {
+        info.remove(key);
+    }
+    if info.is_empty() {
+        doc.trailer.remove(b"Info");
+    }
+    let mut removed: Vec<String> = removed
+        .into_iter()
+        .map(|key| String::from_utf8_lossy(&key).into_owned())
+        .collect();
+    removed.sort();
+    removed
+}
+
+/// Remove every `/Type /Metadata` stream and the references to it.
+fn strip_xmp(doc: &mut Document) -> usize {
+    let streams: BTreeSet<ObjectId> = doc
+        .objects
+        .iter()
+        .filter(|(_, object)| {
+            object
+                .as_stream()
+                .is_ok_and(|stream| stream.dict.type_is(b"Metadata"))
+        })
+        .map(|(&id, _)| id)
+        .collect();
+    for object in doc.objects.values_mut() {
+        let dict = match object {
+            Object::Dictionary(dict) => dict,
+            Object::Stream(stream) => &mut stream.dict,
+            _ => continue,
+        };
+        if let Ok(Object::Reference(id)) = dict.get(b"Metadata") {
+            if streams.contains(id) {
+                dict.remove(b"Metadata");
+            }
+        }
+    }
+    doc.objects.retain(|id, _| !streams.contains(id));
+    streams.len()
+}
+
+/// Remove `/T` (the author) from the pages' annotations other than form
+/// field widgets, where it is the field name.
+fn strip_annotation_authors(doc: &mut Document) -> usize {
+    let annotations: Vec<ObjectId> = doc
+        .get_pages()
+        .into_values()
+        .filter_map(|page| doc.get_dictionary(page).ok()?.get(b"Annots").ok())
+        .filter_map(|annots| doc.dereference(annots).ok()?.1.as_array().ok())
+        .flatten()
+        .filter_map(|annot| annot.as_reference().ok())
+        .collect();
+    let mut count = 0;
+    for id in annotations {
+        if let Ok(annot) = doc.get_dictionary_mut(id) {
+            let widget = annot
+                .get(b"Subtype")
+                .and_then(Object::as_name)
+                .is_ok_and(|subtype| subtype == b"Widget");
+            if !widget && annot.remove(b"T").is_some() {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{Dictionary, Stream};
+
+    fn with_metadata() -> Document {
+        let mut doc = Document::with_version("1.5");
+        let pages = doc.new_object_id();
+        let xmp = doc.add_object(Stream::new(
+            Dictionary::from_iter([
+                ("Type", Object::Name(b"Metadata".to_vec())),
+                ("Subtype", Object::Name(b"XML".to_vec())),
+            ]),
+            b"<x:xmpmeta><dc:creator>Jane Doe</dc:creator></x:xmpmeta>".to_vec(),
+        ));
+        let note = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Annot".to_vec())),
+            ("Subtype", Object::Name(b"Text".to_vec())),
+            ("T", Object::string_literal("Jane Doe")),
+        ]));
+        let field = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Annot".to_vec())),
+            ("Subtype", Object::Name(b"Widget".to_vec())),
+            ("T", Object::string_literal("signature")),
+        ]));
+        let page = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Page".to_vec())),
+            ("Parent", Object::Reference(pages)),
+            (
+                "Annots",
+                Object::Array(vec![Object::Reference(note), Object::Reference(field)]),
+            ),
+        ]));
+        doc.objects.insert(
+            pages,
+            Object::Dictionary(Dictionary::from_iter([
+                ("Type", Object::Name(b"Pages".to_vec())),
+                ("Kids", Object::Array(vec![Object::Reference(page)])),
+                ("Count", Object::Integer(1)),
+            ])),
+        );
+        let catalog = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Catalog".to_vec())),
+            ("Pages", Object::Reference(pages)),
+            ("Metadata", Object::Reference(xmp)),
+        ]));
+        let info = doc.add_object(Dictionary::from_iter([
+            ("Title", Object::string_literal("Annual report")),
+            ("Author", Object::string_literal("Jane Doe")),
+            ("Producer", Object::string_literal("Word")),
+        ]));
+        doc.trailer.set("Root", catalog);
+        doc.trailer.set("Info", info);
+        doc.trailer.set(
+            "ID",
+            Object::Array(vec![
+                Object::string_literal("abc"),
+                Object::string_literal("abc"),
+            ]),
+        );
+        doc
+    }
+
+    #[test]
+    fn metadata_is_removed_except_kept_entries() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("in.pdf");
+        with_metadata().save(&input)?;
+        let output = dir.path().join("out.pdf");
+        let report = strip_metadata(
+            &input,
+            &output,
+            &StripOptions {
+                keep: vec!["Title".to_string()],
+                annotation_authors: true,
+            },
+        )?;
+        assert_eq!(report.info_keys, ["Author", "Producer"]);
+        assert_eq!((report.xmp_streams, report.annotation_authors), (1, 1));
+        assert!(report.document_id);
+
+        let bytes = std::fs::read(&output)?;
+        assert!(!bytes.windows(8).any(|w| w == b"Jane Doe"));
+        assert!(bytes.windows(9).any(|w| w == b"signature"));
+        assert_eq!(
+            crate::metadata::info_entry(&load_document(&output)?, "Title").as_deref(),
+            Some("Annual report")
+        );
+
+        let again = strip_metadata(&output, &output, &StripOptions::default())?;
+        assert_eq!(again.info_keys, ["Title"]);
+        assert!(load_document(&output)?.trailer.get(b"Info").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+//! Exporting a page as SVG, so that vector figures can be edited in a
+//! drawing program.
+
+use std::{path::Path, process::Command};
+
+use crate::{
+    engine, find_tool, page_count, run_tool_writing, select_engine, validate_input_file, Engine,
+    Operation, PdfError, Plan, Result, Tool,
+};
+
+/// Write page `page` (1-based) of `input` to the SVG file `output` (with
+/// pdftocairo or `mutool`; see [`select_engine`]). Paths stay vectors;
+/// text is drawn as glyph outlines, and images are embedded.
+pub fn to_svg(input: impl AsRef<Path>, output: impl AsRef<Path>, page: u32) -> Result<()> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate(input, page)?;
+    let (tool, cmd) = command(input, output, page)?;
+    run_tool_writing(tool, cmd, output)
+}
+
+/// What [`to_svg`] would run.
+pub fn to_svg_plan(input: impl AsRef<Path>, output: impl AsRef<Path>, page: u32) -> Result<Plan> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate(input, page)?;
+    let (tool, cmd) = command(input, output, page)?;
+    Ok(Plan::new().run(tool, &cmd).write(output))
+}
+
+fn validate(input: &Path, page: u32) -> Result<()> {
+    validate_input_file(input)?;
+    let pages = page_count(input)?;
+    if page == 0 || page > pages {
+        return Err(PdfError::InvalidArgument(format!(
+            "page {page} is out of range (document has {pages} pages)"
+        )));
+    }
+    Ok(())
+}
+
+fn command(input: &Path, output: &Path, page: u32) -> Result<(Tool, Command)> {
+    match select_engine(Operation::ToSvg)? {
+        Engine::Pdftocairo => {
+            let mut cmd = Command::new(find_tool(Tool::Pdftocairo)?);
+            cmd.arg("-svg")
+                .arg("-f")
+                .arg(page.to_string())
+                .arg("-l")
+                .arg(page.to_string())
+                .arg(input.as_os_str())
+                .arg(output.as_os_str());
+            Ok((Tool::Pdftocairo, cmd))
+        }
+        Engine::Mutool => {
+            let mut cmd = Command::new(find_tool(Tool::Mutool)?);
+            cmd.arg("draw")
+                .arg("-q")
+                .arg("-F")
+                .arg("svg")
+                .arg("-o")
+                .arg(output.as_os_str())
+                .arg(input.as_os_str())
+                .arg(page.to_string());
+            Ok((Tool::Mutool, cmd))
+        }
+        other => Err(engine::unsupported(Operation::ToSvg, other)),
+    }
+}
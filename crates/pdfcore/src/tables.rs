@@ -0,0 +1,298 @@
+//! Table extraction from ruled tables: grids drawn with horizontal and
+//! vertical lines.
+//!
+//! Lines are taken from the page's painted paths (strokes, and the edges of
+//! filled rectangles, so thin filled bars count as lines). Lines that touch
+//! each other form a grid; the distinct line positions give the rows and
+//! columns, and each glyph goes into the cell containing its centre.
+//! Tables without rulings, and content inside form `XObject`s, are not
+//! detected.
+
+use std::path::Path;
+
+use crate::{
+    geometry::Rect,
+    load_document,
+    plan::Plan,
+    text::{self, PageLayout, Segment},
+    validate_input_file, Result,
+};
+
+/// Lines closer than this (in points) are the same grid line, and lines
+/// shorter than this are ignored.
+const TOLERANCE: f32 = 2.0;
+
+/// A table found by [`extract_tables`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    /// 1-based page the table is on.
+    pub page: u32,
+    /// Outline of the table in default user space.
+    pub bbox: Rect,
+    /// Cell text, top row first; every row has the same number of cells.
+    pub rows: Vec<Vec<String>>,
+}
+
+/// Find the ruled tables on every page (pure Rust), in page order and, on a
+/// page, from the top.
+pub fn extract_tables(input: impl AsRef<Path>) -> Result<Vec<Table>> {
+    let input = input.as_ref();
+    validate_input_file(input)?;
+    let doc = load_document(input)?;
+    let mut tables = Vec::new();
+    for (page, page_id) in doc.get_pages() {
+        let Ok(content) = doc.get_and_decode_page_content(page_id) else {
+            continue;
+        };
+        let layout = text::layout(&doc, page_id, &content.operations);
+        tables.extend(page_tables(page, &layout));
+    }
+    Ok(tables)
+}
+
+/// What [`extract_tables`] would do, writing the tables into `out_dir`.
+pub fn extract_tables_plan(input: impl AsRef<Path>, out_dir: impl AsRef<Path>) -> Result<Plan> {
+    let input = input.as_ref();
+    validate_input_file(input)?;
+    let pages = load_document(input)?.get_pages().len();
+    Ok(Plan::new()
+        .native(format!("detect ruled tables on {pages} page(s)"))
+        .write(out_dir.as_ref()))
+}
+
+/// An axis-aligned line: `position` is y for horizontal lines and x for
+/// vertical ones, and `from..to` the extent along the line.
+#[derive(Debug, Clone, Copy)]
+struct Line {
+    horizontal: bool,
+    position: f32,
+    from: f32,
+    to: f32,
+}
+
+impl Line {
+    fn new(segment: &Segment) -> Option<Self> {
+        let ((x1, y1), (x2, y2)) = (segment.from, segment.to);
+        let (horizontal, position, from, to) = if (y1 - y2).abs() <= TOLERANCE / 2.0 {
+            (true, f32::midpoint(y1, y2), x1.min(x2), x1.max(x2))
+        } else if (x1 - x2).abs() <= TOLERANCE / 2.0 {
+            (false, f32::midpoint(x1, x2), y1.min(y2), y1.max(y2))
+        } else {
+            return None;
+        };
+        (to - from >= TOLERANCE).then_some(Self {
+            horizontal,
+            position,
+            from,
+            to,
+        })
+    }
+
+    fn touches(&self, other: &Self) -> bool {
+        let near =
+            |value: f32, from: f32, to: f32| value >= from - TOLERANCE && value <= to + TOLERANCE;
+        if self.horizontal == other.horizontal {
+            (self.position - other.position).abs() <= TOLERANCE
+                && self.from <= other.to + TOLERANCE
+                && other.from <= self.to + TOLERANCE
+        } else {
+            near(self.position, other.from, other.to) && near(other.position, self.from, self.to)
+        }
+    }
+}
+
+fn page_tables(page: u32, layout: &PageLayout) -> Vec<Table> {
+    let lines: Vec<Line> = layout.segments.iter().filter_map(Line::new).collect();
+
+    // Group touching lines (union-find); each group is a candidate grid.
+    let mut parent: Vec<usize> = (0..lines.len()).collect();
+    for i in 0..lines.len() {
+        for j in i + 1..lines.len() {
+            if lines[i].touches(&lines[j]) {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[a] = b;
+            }
+        }
+    }
+    let mut groups: Vec<Vec<Line>> = Vec::new();
+    let mut group_of = vec![usize::MAX; lines.len()];
+    for (i, line) in lines.iter().enumerate() {
+        let r = root(&mut parent, i);
+        if group_of[r] == usize::MAX {
+            group_of[r] = groups.len();
+            groups.push(Vec::new());
+        }
+        groups[group_of[r]].push(*line);
+    }
+
+    let mut tables: Vec<Table> = groups
+        .iter()
+        .filter_map(|group| grid_table(page, group, layout))
+        .collect();
+    tables.sort_by(|a, b| b.bbox.ury.total_cmp(&a.bbox.ury));
+    tables
+}
+
+/// The representative of `i`'s group in a union-find `parent` array.
+fn root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// The table drawn by one group of touching lines, if it has at least two
+/// cells.
+fn grid_table(page: u32, lines: &[Line], layout: &PageLayout) -> Option<Table> {
+    let positions = |horizontal: bool| {
+        cluster(
+            lines
+                .iter()
+                .filter(|line| line.horizontal == horizontal)
+                .map(|line| line.position)
+                .collect(),
+        )
+    };
+    let columns = positions(false);
+    let mut rows = positions(true);
+    rows.reverse();
+    if columns.len() < 2 || rows.len() < 2 || (columns.len() - 1) * (rows.len() - 1) < 2 {
+        return None;
+    }
+
+    let mut cells = vec![vec![Vec::new(); columns.len() - 1]; rows.len() - 1];
+    for glyph in &layout.glyphs {
+        let x = f32::midpoint(glyph.bbox.llx, glyph.bbox.urx);
+        let y = f32::midpoint(glyph.bbox.lly, glyph.bbox.ury);
+        let column = columns.windows(2).position(|w| x >= w[0] && x < w[1]);
+        let row = rows.windows(2).position(|w| y <= w[0] && y > w[1]);
+        if let (Some(row), Some(column)) = (row, column) {
+            cells[row][column].push(glyph.clone());
+        }
+    }
+    let rows_text = cells
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .map(|glyphs| {
+                    let (text, _) = PageLayout {
+                        glyphs,
+                        ..PageLayout::default()
+                    }
+                    .text_with_origins();
+                    text.trim().to_string()
+                })
+                .collect()
+        })
+        .collect();
+
+    Some(Table {
+        page,
+        bbox: Rect {
+            llx: columns[0],
+            lly: rows[rows.len() - 1],
+            urx: columns[columns.len() - 1],
+            ury: rows[0],
+        },
+        rows: rows_text,
+    })
+}
+
+/// Sorted positions, with positions within [`TOLERANCE`] of each other
+/// merged into their mean.
+fn cluster(mut positions: Vec<f32>) -> Vec<f32> {
+    positions.sort_by(f32::total_cmp);
+    let mut clusters: Vec<(f32, f32)> = Vec::new();
+    for position in positions {
+        match clusters.last_mut() {
+            Some((sum, count)) if position - *sum / *count <= TOLERANCE => {
+                *sum += position;
+                *count += 1.0;
+            }
+            _ => clusters.push((position, 1.0)),
+        }
+    }
+    clusters
+        .into_iter()
+        .map(|(sum, count)| sum / count)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{Dictionary, Document, Object, Stream};
+
+    /// One page with a 2×2 ruled table (stroked lines, and a thin filled bar
+    /// as the middle rule) and a line of text outside it.
+    fn ruled_table() -> Document {
+        let content = "\
+            0.5 w 72 700 m 272 700 l 72 660 m 272 660 l S \
+            72 679.75 200 0.5 re f \
+            72 700 m 72 660 l 172 700 m 172 660 l 272 700 m 272 660 l S \
+            BT /F1 10 Tf 80 686 Td (Name) Tj 100 0 Td (Qty) Tj ET \
+            BT /F1 10 Tf 80 666 Td (Red apples) Tj 100 0 Td (12) Tj ET \
+            BT /F1 10 Tf 72 600 Td (Not in a table) Tj ET";
+        let mut doc = Document::with_version("1.5");
+        let pages = doc.new_object_id();
+        let content = doc.add_object(Stream::new(Dictionary::new(), content.as_bytes().to_vec()));
+        let font = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type1".to_vec())),
+            ("BaseFont", Object::Name(b"Courier".to_vec())),
+        ]));
+        let page = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Page".to_vec())),
+            ("Parent", Object::Reference(pages)),
+            ("Contents", Object::Reference(content)),
+            (
+                "Resources",
+                Object::Dictionary(Dictionary::from_iter([(
+                    "Font",
+                    Object::Dictionary(Dictionary::from_iter([("F1", Object::Reference(font))])),
+                )])),
+            ),
+        ]));
+        doc.objects.insert(
+            pages,
+            Object::Dictionary(Dictionary::from_iter([
+                ("Type", Object::Name(b"Pages".to_vec())),
+                ("Kids", Object::Array(vec![Object::Reference(page)])),
+                ("Count", Object::Integer(1)),
+            ])),
+        );
+        let catalog = doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Catalog".to_vec())),
+            ("Pages", Object::Reference(pages)),
+        ]));
+        doc.trailer.set("Root", catalog);
+        doc
+    }
+
+    #[test]
+    fn ruled_grid_cells_get_their_text() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("table.pdf");
+        ruled_table().save(&path)?;
+
+        let tables = extract_tables(&path)?;
+        assert_eq!(tables.len(), 1);
+        let table = &tables[0];
+        assert_eq!(table.page, 1);
+        assert_eq!(
+            table.rows,
+            [["Name", "Qty"], ["Red apples", "12"]].map(|row| row.map(String::from))
+        );
+        assert!((table.bbox.llx - 72.0).abs() < 0.5 && (table.bbox.ury - 700.0).abs() < 0.5);
+        Ok(())
+    }
+
+    #[test]
+    fn nearby_positions_are_merged() {
+        assert_eq!(
+            cluster(vec![10.0, 100.0, 10.5, 99.5, 50.0]),
+            [10.25, 50.0, 99.75]
+        );
+    }
+}
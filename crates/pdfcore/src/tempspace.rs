@@ -0,0 +1,115 @@
+//! Scratch directories for intermediate files.
+//!
+//! Every operation that needs intermediate files creates one [`TempSpace`]
+//! and keeps its files inside it. The directory is created under
+//! `PDFCLI_TMPDIR` if set (for example to keep large intermediates off a
+//! small `/tmp`), else the system temporary directory, and is removed with
+//! everything in it when the `TempSpace` is dropped, including while
+//! unwinding from a panic.
+//!
+//! ```no_run
+//! # fn main() -> pdfcore::Result<()> {
+//! use pdfcore::tempspace::TempSpace;
+//!
+//! let scratch = TempSpace::new("rotate")?;
+//! let step = scratch.file("step-1.pdf");
+//! # let _ = step;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use tempfile::TempDir;
+
+use crate::Result;
+
+/// Environment variable naming the directory scratch space is created in.
+pub const TMPDIR_ENV: &str = "PDFCLI_TMPDIR";
+
+/// A scratch directory owned by one operation, removed on drop.
+#[derive(Debug)]
+pub struct TempSpace {
+    dir: TempDir,
+}
+
+impl TempSpace {
+    /// A new, empty scratch directory named after `operation`, under
+    /// [`root`].
+    pub fn new(operation: &str) -> Result<Self> {
+        Self::new_in(&root(), operation)
+    }
+
+    fn new_in(root: &Path, operation: &str) -> Result<Self> {
+        fs::create_dir_all(root)?;
+        let dir = tempfile::Builder::new()
+            .prefix(&format!("pdfcli-{operation}-"))
+            .tempdir_in(root)?;
+        tracing::trace!(path = %dir.path().display(), "created scratch directory");
+        Ok(Self { dir })
+    }
+
+    /// The directory.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Path for a scratch file called `name` (not created).
+    #[must_use]
+    pub fn file(&self, name: &str) -> PathBuf {
+        self.dir.path().join(name)
+    }
+
+    /// Write `contents` to a scratch file called `name`, returning its path.
+    pub fn write(&self, name: &str, contents: impl AsRef<[u8]>) -> Result<PathBuf> {
+        let path = self.file(name);
+        fs::write(&path, contents)?;
+        Ok(path)
+    }
+}
+
+/// Where scratch directories are created: `PDFCLI_TMPDIR` if set, else the
+/// system temporary directory.
+#[must_use]
+pub fn root() -> PathBuf {
+    root_from(std::env::var_os(TMPDIR_ENV))
+}
+
+fn root_from(var: Option<OsString>) -> PathBuf {
+    var.filter(|dir| !dir.is_empty())
+        .map_or_else(std::env::temp_dir, PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scratch_is_removed_on_drop_and_panic() -> Result<()> {
+        let parent = tempfile::tempdir()?;
+        let root = root_from(Some(parent.path().join("scratch").into()));
+
+        let scratch = TempSpace::new_in(&root, "merge")?;
+        let file = scratch.write("step.pdf", "%PDF")?;
+        assert!(file.starts_with(&root));
+        drop(scratch);
+        assert!(!file.exists());
+
+        let unwound = std::panic::catch_unwind(|| {
+            let Ok(scratch) = TempSpace::new_in(&root, "rotate") else {
+                return;
+            };
+            let _ = scratch.write("step.pdf", "%PDF");
+            panic!("operation failed");
+        });
+        assert!(unwound.is_err());
+        assert_eq!(fs::read_dir(&root)?.count(), 0);
+        assert_eq!(root_from(Some(OsString::new())), std::env::temp_dir());
+        Ok(())
+    }
+}
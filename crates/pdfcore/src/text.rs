@@ -0,0 +1,647 @@
+//! Positioned text: a minimal content-stream interpreter that tracks where
+//! each glyph is drawn.
+//!
+//! This is not a renderer. It follows the graphics and text state closely
+//! enough to give every glyph a user-space bounding box, a font size, and a
+//! Unicode string, which is what redaction, text search, and title
+//! inference need. Straight path segments are kept too, for finding table
+//! rulings.
+
+use std::collections::BTreeMap;
+
+use lopdf::{content::Operation, Document, Object, ObjectId};
+
+use crate::{
+    fonts,
+    geometry::{Matrix, Rect},
+};
+
+/// Width used when a font has no usable metrics (thousandths of an em).
+const FALLBACK_WIDTH: f32 = 500.0;
+
+/// Glyph boxes span from slightly below the baseline to near the cap height.
+const DESCENT: f32 = -0.2;
+const ASCENT: f32 = 0.8;
+
+/// One glyph drawn by a text-showing operator.
+#[derive(Debug, Clone)]
+pub(crate) struct Glyph {
+    /// Index of the operator in the page's operation list.
+    pub op: usize,
+    /// Index of the string within a `TJ` array (0 for other operators).
+    pub element: usize,
+    /// Byte range of the glyph's code within that string.
+    pub bytes: std::ops::Range<usize>,
+    /// Unicode text for the glyph (`U+FFFD` if the font has no mapping).
+    pub text: String,
+    /// User-space bounding box.
+    pub bbox: Rect,
+    /// Horizontal advance, in thousandths of text-space units, as a `TJ`
+    /// adjustment would express it.
+    pub advance: f32,
+    /// Direction of the baseline in user space, in degrees counterclockwise
+    /// from the x axis (`0` for ordinary horizontal text).
+    pub angle: f32,
+    /// Font size in user space: the height of an em, whatever the
+    /// direction of the baseline.
+    pub size: f32,
+}
+
+/// An `XObject` painted with `Do`, with the CTM in effect.
+#[derive(Debug, Clone)]
+pub(crate) struct Placement {
+    pub name: Vec<u8>,
+    pub ctm: Matrix,
+}
+
+/// A straight line of a painted (stroked or filled) path, in user space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Segment {
+    pub from: (f32, f32),
+    pub to: (f32, f32),
+}
+
+/// Everything [`layout`] found on a page.
+#[derive(Debug, Default)]
+pub(crate) struct PageLayout {
+    pub glyphs: Vec<Glyph>,
+    pub xobjects: Vec<Placement>,
+    pub segments: Vec<Segment>,
+}
+
+impl PageLayout {
+    /// Page text with spaces and newlines inferred from glyph positions,
+    /// plus, for each byte of that text, the glyph it came from (`None` for
+    /// inferred separators).
+    pub(crate) fn text_with_origins(&self) -> (String, Vec<Option<usize>>) {
+        let mut text = String::new();
+        let mut origins = Vec::new();
+        let mut previous: Option<&Rect> = None;
+        for (index, glyph) in self.glyphs.iter().enumerate() {
+            if let Some(prev) = previous {
+                let height = (prev.ury - prev.lly).max(1.0);
+                let separator = if (glyph.bbox.lly - prev.lly).abs() > height / 2.0 {
+                    Some('\n')
+                } else if glyph.bbox.llx - prev.urx > height * 0.15 {
+                    Some(' ')
+                } else {
+                    None
+                };
+                if let Some(c) = separator {
+                    if !(c == ' ' && text.ends_with(' ')) {
+                        text.push(c);
+                        origins.push(None);
+                    }
+                }
+            }
+            text.push_str(&glyph.text);
+            origins.resize(text.len(), Some(index));
+            previous = Some(&glyph.bbox);
+        }
+        (text, origins)
+    }
+}
+
+/// Lay out the text on a page whose content has already been decoded into
+/// `ops`. Fonts are looked up in the page's (possibly inherited) resources.
+#[allow(clippy::too_many_lines)] // one arm per operator
+pub(crate) fn layout(doc: &Document, page_id: ObjectId, ops: &[Operation]) -> PageLayout {
+    let fonts = page_fonts(doc, page_id);
+    let mut out = PageLayout::default();
+    let mut state = State::default();
+    let mut stack = Vec::new();
+    let (mut tm, mut tlm) = (Matrix::IDENTITY, Matrix::IDENTITY);
+    let mut path = PathBuilder::default();
+
+    for (index, op) in ops.iter().enumerate() {
+        let nums: Vec<f32> = op
+            .operands
+            .iter()
+            .filter_map(|o| o.as_float().ok())
+            .collect();
+        match op.operator.as_str() {
+            "q" => stack.push(state.clone()),
+            "Q" => state = stack.pop().unwrap_or_default(),
+            "cm" => {
+                if let Some(m) = Matrix::from_operands(&op.operands) {
+                    state.ctm = m.then(&state.ctm);
+                }
+            }
+            "BT" => (tm, tlm) = (Matrix::IDENTITY, Matrix::IDENTITY),
+            "Tf" => {
+                if let [Object::Name(name), size] = op.operands.as_slice() {
+                    state.font.clone_from(name);
+                    state.size = size.as_float().unwrap_or(0.0);
+                }
+            }
+            "Tc" => state.char_spacing = nums.first().copied().unwrap_or(0.0),
+            "Tw" => state.word_spacing = nums.first().copied().unwrap_or(0.0),
+            "Tz" => state.scale = nums.first().copied().unwrap_or(100.0) / 100.0,
+            "TL" => state.leading = nums.first().copied().unwrap_or(0.0),
+            "Ts" => state.rise = nums.first().copied().unwrap_or(0.0),
+            "Td" | "TD" => {
+                if let [tx, ty] = nums.as_slice() {
+                    if op.operator == "TD" {
+                        state.leading = -ty;
+                    }
+                    tlm = Matrix::translate(*tx, *ty).then(&tlm);
+                    tm = tlm;
+                }
+            }
+            "Tm" => {
+                if let Some(m) = Matrix::from_operands(&op.operands) {
+                    (tm, tlm) = (m, m);
+                }
+            }
+            "T*" | "'" | "\"" => {
+                if op.operator == "\"" {
+                    if let [aw, ac, ..] = nums.as_slice() {
+                        state.word_spacing = *aw;
+                        state.char_spacing = *ac;
+                    }
+                }
+                tlm = Matrix::translate(0.0, -state.leading).then(&tlm);
+                tm = tlm;
+                if let Some(Object::String(bytes, _)) = op.operands.last() {
+                    show(&fonts, &state, &mut tm, bytes, index, 0, &mut out.glyphs);
+                }
+            }
+            "Tj" => {
+                if let Some(Object::String(bytes, _)) = op.operands.first() {
+                    show(&fonts, &state, &mut tm, bytes, index, 0, &mut out.glyphs);
+                }
+            }
+            "TJ" => {
+                let Some(Object::Array(items)) = op.operands.first() else {
+                    continue;
+                };
+                for (element, item) in items.iter().enumerate() {
+                    match item {
+                        Object::String(bytes, _) => {
+                            show(
+                                &fonts,
+                                &state,
+                                &mut tm,
+                                bytes,
+                                index,
+                                element,
+                                &mut out.glyphs,
+                            );
+                        }
+                        other => {
+                            let adjust = other.as_float().unwrap_or(0.0);
+                            let tx = -adjust / 1000.0 * state.size * state.scale;
+                            tm = Matrix::translate(tx, 0.0).then(&tm);
+                        }
+                    }
+                }
+            }
+            "m" | "l" | "c" | "v" | "y" | "re" | "h" | "S" | "s" | "f" | "F" | "f*" | "B"
+            | "B*" | "b" | "b*" | "n" => {
+                path.apply(&op.operator, &nums, &state.ctm, &mut out.segments);
+            }
+            "Do" => {
+                if let Some(Object::Name(name)) = op.operands.first() {
+                    out.xobjects.push(Placement {
+                        name: name.clone(),
+                        ctm: state.ctm,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// The path under construction: its straight segments, current point, and
+/// subpath start, in user space.
+#[derive(Debug, Default)]
+struct PathBuilder {
+    segments: Vec<Segment>,
+    current: (f32, f32),
+    start: (f32, f32),
+}
+
+impl PathBuilder {
+    /// Apply a path construction or painting operator; painted segments are
+    /// moved to `painted`.
+    fn apply(&mut self, operator: &str, nums: &[f32], ctm: &Matrix, painted: &mut Vec<Segment>) {
+        match (operator, nums) {
+            ("m", [x, y]) => {
+                self.current = ctm.apply(*x, *y);
+                self.start = self.current;
+            }
+            ("l", [x, y]) => self.line_to(ctm.apply(*x, *y)),
+            // Curves are not rulings; only their end point matters.
+            ("c" | "v" | "y", [.., x, y]) => self.current = ctm.apply(*x, *y),
+            ("re", [x, y, w, h]) => {
+                let corners = [(*x, *y), (x + w, *y), (x + w, y + h), (*x, y + h)]
+                    .map(|(x, y)| ctm.apply(x, y));
+                (self.current, self.start) = (corners[0], corners[0]);
+                for corner in &corners[1..] {
+                    self.line_to(*corner);
+                }
+                self.line_to(corners[0]);
+            }
+            ("h", _) => self.line_to(self.start),
+            ("s" | "b" | "b*", _) => {
+                self.line_to(self.start);
+                painted.append(&mut self.segments);
+            }
+            ("S" | "f" | "F" | "f*" | "B" | "B*", _) => painted.append(&mut self.segments),
+            ("n", _) => self.segments.clear(),
+            _ => {}
+        }
+    }
+
+    fn line_to(&mut self, to: (f32, f32)) {
+        self.segments.push(Segment {
+            from: self.current,
+            to,
+        });
+        self.current = to;
+    }
+}
+
+/// Graphics and text state saved by `q` and restored by `Q`.
+#[derive(Debug, Clone)]
+struct State {
+    ctm: Matrix,
+    font: Vec<u8>,
+    size: f32,
+    char_spacing: f32,
+    word_spacing: f32,
+    scale: f32,
+    leading: f32,
+    rise: f32,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            ctm: Matrix::IDENTITY,
+            font: Vec::new(),
+            size: 0.0,
+            char_spacing: 0.0,
+            word_spacing: 0.0,
+            scale: 1.0,
+            leading: 0.0,
+            rise: 0.0,
+        }
+    }
+}
+
+fn show(
+    fonts: &BTreeMap<Vec<u8>, FontMetrics<'_>>,
+    state: &State,
+    tm: &mut Matrix,
+    bytes: &[u8],
+    op: usize,
+    element: usize,
+    glyphs: &mut Vec<Glyph>,
+) {
+    let font = fonts.get(&state.font);
+    let code_len = if font.is_some_and(|f| f.two_byte) {
+        2
+    } else {
+        1
+    };
+    let mut start = 0;
+    while start < bytes.len() {
+        let end = (start + code_len).min(bytes.len());
+        let code_bytes = &bytes[start..end];
+        let code = code_bytes
+            .iter()
+            .fold(0_u32, |acc, &b| (acc << 8) | u32::from(b));
+        let w0 = font.map_or(FALLBACK_WIDTH, |f| f.width(code)) / 1000.0;
+
+        let trm = Matrix([
+            state.size * state.scale,
+            0.0,
+            0.0,
+            state.size,
+            0.0,
+            state.rise,
+        ])
+        .then(tm)
+        .then(&state.ctm);
+        let bbox = trm.apply_rect(&Rect {
+            llx: 0.0,
+            lly: DESCENT,
+            urx: w0,
+            ury: ASCENT,
+        });
+
+        let word = if code_len == 1 && code == 32 {
+            state.word_spacing
+        } else {
+            0.0
+        };
+        let tx = (w0 * state.size + state.char_spacing + word) * state.scale;
+        let unit = state.size * state.scale;
+        let advance = if unit.abs() > f32::EPSILON {
+            tx / unit * 1000.0
+        } else {
+            0.0
+        };
+
+        glyphs.push(Glyph {
+            op,
+            element,
+            bytes: start..end,
+            text: font.map_or_else(|| latin1(code_bytes), |f| f.decode(code_bytes)),
+            bbox,
+            advance,
+            angle: trm.0[1].atan2(trm.0[0]).to_degrees(),
+            size: trm.0[2].hypot(trm.0[3]),
+        });
+        *tm = Matrix::translate(tx, 0.0).then(tm);
+        start = end;
+    }
+}
+
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| char::from(b)).collect()
+}
+
+/// What [`layout`] needs to know about a font.
+struct FontMetrics<'a> {
+    encoding: Option<lopdf::Encoding<'a>>,
+    two_byte: bool,
+    first_char: u32,
+    widths: Vec<f32>,
+    default_width: f32,
+    cid_widths: BTreeMap<u32, f32>,
+}
+
+impl FontMetrics<'_> {
+    fn width(&self, code: u32) -> f32 {
+        if self.two_byte {
+            return self
+                .cid_widths
+                .get(&code)
+                .copied()
+                .unwrap_or(self.default_width);
+        }
+        code.checked_sub(self.first_char)
+            .and_then(|i| self.widths.get(usize::try_from(i).ok()?))
+            .copied()
+            .unwrap_or(self.default_width)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> String {
+        match &self.encoding {
+            Some(encoding) => match encoding.bytes_to_string(bytes) {
+                Ok(text) if !text.is_empty() => text,
+                _ if self.two_byte => char::REPLACEMENT_CHARACTER.to_string(),
+                _ => latin1(bytes),
+            },
+            None if self.two_byte => char::REPLACEMENT_CHARACTER.to_string(),
+            None => latin1(bytes),
+        }
+    }
+}
+
+fn page_fonts(doc: &Document, page_id: ObjectId) -> BTreeMap<Vec<u8>, FontMetrics<'_>> {
+    doc.get_page_fonts(page_id)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, font)| (name, font_metrics(doc, font)))
+        .collect()
+}
+
+fn font_metrics<'a>(doc: &'a Document, font: &'a lopdf::Dictionary) -> FontMetrics<'a> {
+    // lopdf asserts (in debug builds) that it is only handed font dictionaries.
+    let encoding = if font.type_is(b"Font") {
+        font.get_font_encoding(doc).ok()
+    } else {
+        None
+    };
+    let number = |dict: &lopdf::Dictionary, key: &[u8]| {
+        dict.get(key)
+            .and_then(|o| doc.dereference(o))
+            .and_then(|(_, o)| o.as_float())
+            .ok()
+    };
+    let array = |dict: &'a lopdf::Dictionary, key: &[u8]| -> Vec<&'a Object> {
+        dict.get(key)
+            .and_then(|o| doc.dereference(o))
+            .and_then(|(_, o)| o.as_array())
+            .map(|items| items.iter().collect())
+            .unwrap_or_default()
+    };
+
+    let subtype = font
+        .get(b"Subtype")
+        .and_then(Object::as_name)
+        .unwrap_or_default();
+    if subtype == b"Type0" {
+        let descendant = array(font, b"DescendantFonts")
+            .first()
+            .and_then(|o| fonts::resolve_dict(doc, Some(o)));
+        let default_width = descendant.and_then(|d| number(d, b"DW")).unwrap_or(1000.0);
+        let cid_widths = descendant
+            .map(|d| cid_widths(doc, &array(d, b"W")))
+            .unwrap_or_default();
+        return FontMetrics {
+            encoding,
+            two_byte: true,
+            first_char: 0,
+            widths: Vec::new(),
+            default_width,
+            cid_widths,
+        };
+    }
+
+    let descriptor = fonts::resolve_dict(doc, font.get(b"FontDescriptor").ok());
+    let base = font
+        .get(b"BaseFont")
+        .and_then(Object::as_name)
+        .unwrap_or_default();
+    let default_width = descriptor
+        .and_then(|d| number(d, b"MissingWidth"))
+        .filter(|w| *w > 0.0)
+        .unwrap_or(if base.starts_with(b"Courier") {
+            600.0
+        } else {
+            FALLBACK_WIDTH
+        });
+    let widths: Vec<f32> = array(font, b"Widths")
+        .into_iter()
+        .map(|o| {
+            doc.dereference(o)
+                .and_then(|(_, o)| o.as_float())
+                .unwrap_or(0.0)
+        })
+        .collect();
+    if widths.is_empty() {
+        // Standard 14 fonts may omit /Widths; use the built-in metrics.
+        if let Some(table) = standard_widths(base) {
+            return FontMetrics {
+                encoding,
+                two_byte: false,
+                first_char: 32,
+                widths: table.iter().map(|&w| f32::from(w)).collect(),
+                default_width,
+                cid_widths: BTreeMap::new(),
+            };
+        }
+    }
+    FontMetrics {
+        encoding,
+        two_byte: false,
+        first_char: code(doc, font.get(b"FirstChar").ok()).unwrap_or(0),
+        widths,
+        default_width,
+        cid_widths: BTreeMap::new(),
+    }
+}
+
+/// ASCII (32–126) advance widths of the proportional standard fonts.
+/// Oblique and italic styles share their upright widths closely enough for
+/// layout purposes; Arial is metrically compatible with Helvetica.
+fn standard_widths(base_font: &[u8]) -> Option<&'static [u16; 95]> {
+    let bold = base_font.windows(4).any(|w| w == b"Bold");
+    if base_font.starts_with(b"Helvetica") || base_font.starts_with(b"Arial") {
+        Some(if bold { &HELVETICA_BOLD } else { &HELVETICA })
+    } else if base_font.starts_with(b"Times") {
+        Some(if bold { &TIMES_BOLD } else { &TIMES_ROMAN })
+    } else {
+        None
+    }
+}
+
+#[rustfmt::skip]
+const HELVETICA: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+#[rustfmt::skip]
+const HELVETICA_BOLD: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611,
+    975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556,
+    333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611,
+    611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+#[rustfmt::skip]
+const TIMES_ROMAN: [u16; 95] = [
+    250, 333, 408, 500, 500, 833, 778, 180, 333, 333, 500, 564, 250, 333, 250, 278,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 278, 278, 564, 564, 564, 444,
+    921, 722, 667, 667, 722, 611, 556, 722, 722, 333, 389, 722, 611, 889, 722, 722,
+    556, 722, 667, 556, 611, 722, 722, 944, 722, 722, 611, 333, 278, 333, 469, 500,
+    333, 444, 500, 444, 500, 444, 333, 500, 500, 278, 278, 500, 278, 778, 500, 500,
+    500, 500, 333, 389, 278, 500, 500, 722, 500, 500, 444, 480, 200, 480, 541,
+];
+
+#[rustfmt::skip]
+const TIMES_BOLD: [u16; 95] = [
+    250, 333, 555, 500, 500, 1000, 833, 278, 333, 333, 500, 570, 250, 333, 250, 278,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 570, 570, 570, 500,
+    930, 722, 667, 722, 722, 667, 611, 778, 778, 389, 500, 778, 667, 944, 722, 778,
+    611, 778, 722, 556, 667, 722, 722, 1000, 722, 722, 667, 333, 278, 333, 581, 500,
+    333, 500, 556, 444, 556, 444, 333, 500, 556, 278, 333, 556, 278, 833, 556, 500,
+    556, 556, 444, 389, 333, 556, 500, 722, 500, 500, 444, 394, 220, 394, 520,
+];
+
+/// Parse a CID font `/W` array: `c [w1 w2 …]` and `c_first c_last w` runs.
+fn cid_widths(doc: &Document, items: &[&Object]) -> BTreeMap<u32, f32> {
+    let float = |o: &Object| doc.dereference(o).and_then(|(_, o)| o.as_float()).ok();
+    let mut out = BTreeMap::new();
+    let mut i = 0;
+    while i + 1 < items.len() {
+        let Some(first) = code(doc, Some(items[i])) else {
+            break;
+        };
+        if let Ok(Object::Array(widths)) = doc.dereference(items[i + 1]).map(|(_, o)| o) {
+            for (cid, w) in (first..).zip(widths) {
+                out.insert(cid, float(w).unwrap_or(0.0));
+            }
+            i += 2;
+        } else {
+            let (Some(last), Some(w)) = (
+                code(doc, Some(items[i + 1])),
+                items.get(i + 2).and_then(|o| float(o)),
+            ) else {
+                break;
+            };
+            // Guard against absurd ranges in malformed fonts.
+            for cid in first..=last.min(first.saturating_add(0xFFFF)) {
+                out.insert(cid, w);
+            }
+            i += 3;
+        }
+    }
+    out
+}
+
+/// A non-negative integer such as a character code or CID.
+fn code(doc: &Document, obj: Option<&Object>) -> Option<u32> {
+    let (_, obj) = doc.dereference(obj?).ok()?;
+    u32::try_from(obj.as_i64().ok()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn one_font_page(content: &[u8]) -> (Document, ObjectId, Vec<Operation>) {
+        let mut doc = Document::with_version("1.4");
+        let font = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", Object::Name(b"Font".to_vec())),
+            ("Subtype", Object::Name(b"Type1".to_vec())),
+            ("BaseFont", Object::Name(b"Courier".to_vec())),
+            ("Encoding", Object::Name(b"WinAnsiEncoding".to_vec())),
+        ]));
+        let resources = lopdf::Dictionary::from_iter([(
+            "Font",
+            Object::Dictionary(lopdf::Dictionary::from_iter([(
+                "F1",
+                Object::Reference(font),
+            )])),
+        )]);
+        let page = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", Object::Name(b"Page".to_vec())),
+            ("Resources", Object::Dictionary(resources)),
+        ]));
+        let ops = lopdf::content::Content::decode(content)
+            .map(|c| c.operations)
+            .unwrap_or_default();
+        (doc, page, ops)
+    }
+
+    #[test]
+    fn glyphs_are_positioned_with_text_and_graphics_state() {
+        let (doc, page, ops) = one_font_page(
+            b"q 2 0 0 2 0 0 cm BT /F1 10 Tf 5 10 Td (AB) Tj ET Q BT /F1 10 Tf (C) Tj ET",
+        );
+        let layout = layout(&doc, page, &ops);
+        let boxes: Vec<(String, f32, f32)> = layout
+            .glyphs
+            .iter()
+            .map(|g| (g.text.clone(), g.bbox.llx, g.bbox.urx))
+            .collect();
+        // Courier is 600 units wide: 6pt per glyph at 10pt, doubled by the CTM.
+        assert_eq!(
+            boxes,
+            vec![
+                ("A".to_string(), 10.0, 22.0),
+                ("B".to_string(), 22.0, 34.0),
+                ("C".to_string(), 0.0, 6.0),
+            ]
+        );
+        assert!((layout.glyphs[0].advance - 600.0).abs() < 0.01);
+
+        let (text, origins) = layout.text_with_origins();
+        assert_eq!(text, "AB\nC");
+        assert_eq!(origins, vec![Some(0), Some(1), None, Some(2)]);
+    }
+}
@@ -0,0 +1,198 @@
+//! Encoding extracted text for the program that reads it: the character
+//! encoding, a byte order mark, and line endings.
+
+use std::{borrow::Cow, fmt, str::FromStr};
+
+use crate::{
+    warnings::{self, WarningKind},
+    PdfError, Result,
+};
+
+/// Character encoding of extracted text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextEncoding {
+    /// UTF-8.
+    #[default]
+    Utf8,
+    /// UTF-16, little-endian (what Windows calls Unicode).
+    Utf16Le,
+    /// UTF-16, big-endian.
+    Utf16Be,
+    /// ISO 8859-1; other characters become `?`.
+    Latin1,
+    /// 7-bit ASCII; other characters become `?`.
+    Ascii,
+}
+
+impl FromStr for TextEncoding {
+    type Err = PdfError;
+
+    /// Names as pdftotext's `-enc` takes them (`UTF-8`, `Latin1`, `ASCII7`,
+    /// `UCS-2`), and the usual aliases, in any case.
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Ok(Self::Utf8),
+            "utf-16le" | "utf16le" | "utf-16" | "ucs-2" | "ucs2" => Ok(Self::Utf16Le),
+            "utf-16be" | "utf16be" => Ok(Self::Utf16Be),
+            "latin1" | "latin-1" | "iso-8859-1" | "iso8859-1" => Ok(Self::Latin1),
+            "ascii" | "ascii7" | "us-ascii" => Ok(Self::Ascii),
+            other => Err(PdfError::InvalidArgument(format!(
+                "unknown text encoding: {other} (expected utf-8, utf-16le, utf-16be, latin1, or ascii)"
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for TextEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Utf8 => "utf-8",
+            Self::Utf16Le => "utf-16le",
+            Self::Utf16Be => "utf-16be",
+            Self::Latin1 => "latin1",
+            Self::Ascii => "ascii",
+        })
+    }
+}
+
+/// Line endings of extracted text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`, as on Unix.
+    Lf,
+    /// `\r\n`, as on Windows.
+    Crlf,
+}
+
+/// How extracted text is written: by default UTF-8 without a byte order
+/// mark, with line endings as the engine produced them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextOutput {
+    /// Character encoding.
+    pub encoding: TextEncoding,
+    /// Start with a byte order mark (UTF-8 and UTF-16 only).
+    pub bom: bool,
+    /// Convert every line ending to this; `None` keeps them.
+    pub eol: Option<LineEnding>,
+}
+
+impl TextOutput {
+    /// Check that the options go together.
+    pub(crate) fn validate(self) -> Result<()> {
+        if self.bom && matches!(self.encoding, TextEncoding::Latin1 | TextEncoding::Ascii) {
+            return Err(PdfError::InvalidArgument(format!(
+                "{} has no byte order mark",
+                self.encoding
+            )));
+        }
+        Ok(())
+    }
+
+    /// `text` with its line endings converted.
+    #[must_use]
+    pub fn convert_eol<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let Some(eol) = self.eol else {
+            return Cow::Borrowed(text);
+        };
+        let unix = if text.contains('\r') {
+            Cow::Owned(text.replace("\r\n", "\n").replace('\r', "\n"))
+        } else {
+            Cow::Borrowed(text)
+        };
+        match eol {
+            LineEnding::Lf => unix,
+            LineEnding::Crlf => Cow::Owned(unix.replace('\n', "\r\n")),
+        }
+    }
+
+    /// `text` as bytes: line endings converted, then encoded, after a byte
+    /// order mark if one was asked for. Characters Latin-1 or ASCII cannot
+    /// hold are replaced with `?`, with a warning.
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>> {
+        self.validate()?;
+        let text = self.convert_eol(text);
+        let mut out = Vec::with_capacity(text.len() + 3);
+        let mut replaced = 0;
+        match self.encoding {
+            TextEncoding::Utf8 => {
+                if self.bom {
+                    out.extend_from_slice(&[0xEF, 0xBB, 0xBF]);
+                }
+                out.extend_from_slice(text.as_bytes());
+            }
+            TextEncoding::Utf16Le | TextEncoding::Utf16Be => {
+                let little = self.encoding == TextEncoding::Utf16Le;
+                let units = self
+                    .bom
+                    .then_some(0xFEFF)
+                    .into_iter()
+                    .chain(text.encode_utf16());
+                for unit in units {
+                    out.extend_from_slice(&if little {
+                        unit.to_le_bytes()
+                    } else {
+                        unit.to_be_bytes()
+                    });
+                }
+            }
+            TextEncoding::Latin1 | TextEncoding::Ascii => {
+                let max = if self.encoding == TextEncoding::Latin1 {
+                    0xFF
+                } else {
+                    0x7F
+                };
+                for c in text.chars() {
+                    match u8::try_from(u32::from(c)) {
+                        Ok(byte) if u32::from(byte) <= max => out.push(byte),
+                        _ => {
+                            out.push(b'?');
+                            replaced += 1;
+                        }
+                    }
+                }
+            }
+        }
+        if replaced > 0 {
+            warnings::warn(
+                WarningKind::LossyText,
+                format!(
+                    "{replaced} characters cannot be written in {} and were replaced with ?",
+                    self.encoding
+                ),
+            );
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_is_encoded_with_bom_and_line_endings() -> Result<()> {
+        let crlf = TextOutput {
+            encoding: TextEncoding::Utf16Le,
+            bom: true,
+            eol: Some(LineEnding::Crlf),
+        };
+        assert_eq!(
+            crlf.encode("é\n\r\n")?,
+            [0xFF, 0xFE, 0xE9, 0, b'\r', 0, b'\n', 0, b'\r', 0, b'\n', 0]
+        );
+        let latin1 = TextOutput {
+            encoding: "Latin1".parse()?,
+            bom: false,
+            eol: Some(LineEnding::Lf),
+        };
+        let (bytes, warnings) = warnings::collect_warnings(|| latin1.encode("café ✓\r\n"));
+        assert_eq!(bytes?, b"caf\xE9 ?\n");
+        assert_eq!(warnings.len(), 1);
+        let bom = TextOutput {
+            bom: true,
+            ..latin1
+        };
+        assert!(bom.encode("x").is_err());
+        Ok(())
+    }
+}
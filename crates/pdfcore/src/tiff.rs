@@ -0,0 +1,373 @@
+//! Multi-page TIFF, as used by fax and archival systems: reading one into a
+//! PDF (pure Rust) and writing one from a PDF (Ghostscript).
+
+use std::{fs::File, io::BufReader, path::Path, process::Command};
+
+use lopdf::{Dictionary, Document, Object, Stream};
+use tiff::{
+    decoder::{ifd::Value, Decoder, DecodingResult, Limits},
+    tags::{ResolutionUnit, Tag},
+    ColorType,
+};
+
+use crate::{
+    find_tool, geometry::Rect, run_tool_writing, save_document, validate_input_file,
+    validate_render, PdfError, Plan, Result, Tool,
+};
+
+/// How [`to_tiff`] compresses pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TiffCompression {
+    /// CCITT Group 4: black and white, as fax systems expect.
+    G4,
+    /// LZW, in color.
+    Lzw,
+    /// `PackBits`, in color.
+    Packbits,
+    /// Uncompressed color.
+    None,
+}
+
+/// Options for [`to_tiff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToTiffOptions {
+    /// Resolution in dots per inch.
+    pub dpi: u32,
+    /// How pages are compressed.
+    pub compression: TiffCompression,
+}
+
+impl Default for ToTiffOptions {
+    fn default() -> Self {
+        Self {
+            dpi: 300,
+            compression: TiffCompression::Lzw,
+        }
+    }
+}
+
+/// Render every page of `input` into the multi-page TIFF `output` (with
+/// Ghostscript's `tiffg4` device for [`TiffCompression::G4`], else
+/// `tiff24nc`).
+pub fn to_tiff(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &ToTiffOptions,
+) -> Result<()> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_render(input, options.dpi)?;
+    run_tool_writing(
+        Tool::Ghostscript,
+        gs_command(input, output, *options)?,
+        output,
+    )
+}
+
+/// What [`to_tiff`] would run.
+pub fn to_tiff_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &ToTiffOptions,
+) -> Result<Plan> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_render(input, options.dpi)?;
+    Ok(Plan::new()
+        .run(Tool::Ghostscript, &gs_command(input, output, *options)?)
+        .write(output))
+}
+
+fn gs_command(input: &Path, output: &Path, options: ToTiffOptions) -> Result<Command> {
+    let mut cmd = Command::new(find_tool(Tool::Ghostscript)?);
+    match options.compression {
+        TiffCompression::G4 => cmd.arg("-sDEVICE=tiffg4"),
+        TiffCompression::Lzw => cmd.arg("-sDEVICE=tiff24nc").arg("-sCompression=lzw"),
+        TiffCompression::Packbits => cmd.arg("-sDEVICE=tiff24nc").arg("-sCompression=pack"),
+        TiffCompression::None => cmd.arg("-sDEVICE=tiff24nc").arg("-sCompression=none"),
+    };
+    cmd.arg(format!("-r{}", options.dpi))
+        .arg("-dNOPAUSE")
+        .arg("-dBATCH")
+        .arg("-dSAFER")
+        .arg(format!("-sOutputFile={}", output.display()))
+        .arg(input.as_os_str());
+    Ok(cmd)
+}
+
+/// Make a PDF of the TIFF file `input`, one page per image, each page the
+/// size of its image at the resolution the file records (72 DPI if it
+/// records none); returns the number of pages (pure Rust).
+///
+/// Black and white, gray, RGB, CMYK, and palette images are supported, in
+/// any compression the `tiff` crate reads (including CCITT Group 4, LZW,
+/// and `PackBits`); 16-bit samples are reduced to 8 bits, and alpha
+/// channels become soft masks.
+pub fn from_tiff(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<u32> {
+    let input = input.as_ref();
+    validate_input_file(input)?;
+    let invalid =
+        |e: tiff::TiffError| PdfError::InvalidArgument(format!("{}: {e}", input.display()));
+    let mut decoder = Decoder::new(BufReader::new(File::open(input)?))
+        .map_err(invalid)?
+        .with_limits(Limits::unlimited());
+    let mut frames = Vec::new();
+    loop {
+        frames.push(read_frame(&mut decoder).map_err(|e| match e {
+            PdfError::InvalidArgument(message) => PdfError::InvalidArgument(format!(
+                "{}: page {}: {message}",
+                input.display(),
+                frames.len() + 1
+            )),
+            other => other,
+        })?);
+        if !decoder.more_images() {
+            break;
+        }
+        decoder.next_image().map_err(invalid)?;
+    }
+    let count = u32::try_from(frames.len()).unwrap_or(u32::MAX);
+    let mut doc = build_document(frames);
+    save_document(&mut doc, output.as_ref())?;
+    Ok(count)
+}
+
+/// What [`from_tiff`] would do.
+pub fn from_tiff_plan(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<Plan> {
+    validate_input_file(input.as_ref())?;
+    Ok(Plan::new()
+        .native(format!(
+            "place each image of {} on a page of its own size",
+            input.as_ref().display()
+        ))
+        .write(output.as_ref()))
+}
+
+/// One image of a TIFF file, ready to be placed on a page.
+struct Frame {
+    image: Stream,
+    /// The alpha channel, as a soft mask.
+    mask: Option<Stream>,
+    /// Page size in points.
+    width: f32,
+    height: f32,
+}
+
+fn read_frame<R: std::io::Read + std::io::Seek>(decoder: &mut Decoder<R>) -> Result<Frame> {
+    let invalid = |e: tiff::TiffError| PdfError::InvalidArgument(e.to_string());
+    let (width, height) = decoder.dimensions().map_err(invalid)?;
+    let color = decoder.colortype().map_err(invalid)?;
+    let (dpi_x, dpi_y) = resolution(decoder);
+    let samples = match decoder.read_image().map_err(invalid)? {
+        DecodingResult::U8(samples) => samples,
+        // Big-endian order in memory would keep the high byte first; keep it.
+        DecodingResult::U16(samples) => samples.iter().map(|s| s.to_be_bytes()[0]).collect(),
+        _ => return Err(unsupported(color)),
+    };
+
+    let (color_space, channels, bits, alpha) = match color {
+        ColorType::Gray(1) => (Object::Name(b"DeviceGray".to_vec()), 1, 1, false),
+        ColorType::Gray(8 | 16) => (Object::Name(b"DeviceGray".to_vec()), 1, 8, false),
+        ColorType::GrayA(8 | 16) => (Object::Name(b"DeviceGray".to_vec()), 2, 8, true),
+        ColorType::RGB(8 | 16) => (Object::Name(b"DeviceRGB".to_vec()), 3, 8, false),
+        ColorType::RGBA(8 | 16) => (Object::Name(b"DeviceRGB".to_vec()), 4, 8, true),
+        ColorType::CMYK(8 | 16) => (Object::Name(b"DeviceCMYK".to_vec()), 4, 8, false),
+        ColorType::Palette(bits @ (1 | 2 | 4 | 8)) => (palette(decoder, bits)?, 1, bits, false),
+        other => return Err(unsupported(other)),
+    };
+    let (data, mask) = if alpha {
+        let (color, alpha) = split_alpha(&samples, channels);
+        (color, Some(alpha))
+    } else {
+        (samples, None)
+    };
+
+    let mut dict = image_dict(width, height, bits);
+    dict.set("ColorSpace", color_space);
+    let image = Stream::new(dict, data);
+    let mask = mask.map(|alpha| {
+        let mut dict = image_dict(width, height, 8);
+        dict.set("ColorSpace", Object::Name(b"DeviceGray".to_vec()));
+        Stream::new(dict, alpha)
+    });
+    #[allow(clippy::cast_precision_loss)] // pixel counts are far below 2^24
+    let (width, height) = (width as f32 * 72.0 / dpi_x, height as f32 * 72.0 / dpi_y);
+    Ok(Frame {
+        image,
+        mask,
+        width,
+        height,
+    })
+}
+
+fn unsupported(color: ColorType) -> PdfError {
+    PdfError::InvalidArgument(format!("unsupported TIFF color type: {color:?}"))
+}
+
+/// The horizontal and vertical resolution the image records, in DPI.
+#[allow(clippy::cast_possible_truncation)] // resolutions are small
+fn resolution<R: std::io::Read + std::io::Seek>(decoder: &mut Decoder<R>) -> (f32, f32) {
+    let per_inch = match decoder
+        .find_tag_unsigned::<u16>(Tag::ResolutionUnit)
+        .ok()
+        .flatten()
+        .and_then(ResolutionUnit::from_u16)
+    {
+        Some(ResolutionUnit::Centimeter) => 2.54,
+        Some(ResolutionUnit::None) => return (72.0, 72.0),
+        _ => 1.0,
+    };
+    let mut get = |tag| {
+        // Resolutions are rationals, which `get_tag_f64` does not convert.
+        let dpi = match decoder.get_tag(tag).ok()? {
+            Value::Rational(n, d) => f64::from(n) / f64::from(d),
+            value => value.into_f64().ok()?,
+        };
+        Some((dpi * per_inch) as f32).filter(|r| r.is_finite() && *r > 0.0)
+    };
+    let x = get(Tag::XResolution);
+    let y = get(Tag::YResolution);
+    match (x, y) {
+        (Some(x), Some(y)) => (x, y),
+        (Some(r), None) | (None, Some(r)) => (r, r),
+        (None, None) => (72.0, 72.0),
+    }
+}
+
+/// An `/Indexed` color space from the image's color map, whose 16-bit
+/// entries list every red, then every green, then every blue.
+fn palette<R: std::io::Read + std::io::Seek>(decoder: &mut Decoder<R>, bits: u8) -> Result<Object> {
+    let map = decoder.get_tag_u16_vec(Tag::ColorMap).map_err(|e| {
+        PdfError::InvalidArgument(format!("palette image without a color map: {e}"))
+    })?;
+    let entries = 1usize << bits;
+    if map.len() < entries * 3 {
+        return Err(PdfError::InvalidArgument(
+            "the color map is too short".to_string(),
+        ));
+    }
+    let lookup: Vec<u8> = (0..entries)
+        .flat_map(|i| [map[i], map[entries + i], map[2 * entries + i]].map(|v| v.to_be_bytes()[0]))
+        .collect();
+    Ok(Object::Array(vec![
+        Object::Name(b"Indexed".to_vec()),
+        Object::Name(b"DeviceRGB".to_vec()),
+        Object::Integer(i64::try_from(entries - 1).unwrap_or(255)),
+        Object::String(lookup, lopdf::StringFormat::Hexadecimal),
+    ]))
+}
+
+/// The entries every image `XObject` has.
+fn image_dict(width: u32, height: u32, bits: u8) -> Dictionary {
+    Dictionary::from_iter([
+        ("Type", Object::Name(b"XObject".to_vec())),
+        ("Subtype", Object::Name(b"Image".to_vec())),
+        ("Width", Object::Integer(width.into())),
+        ("Height", Object::Integer(height.into())),
+        ("BitsPerComponent", Object::Integer(bits.into())),
+    ])
+}
+
+/// Split pixels of `channels` samples, the last of them alpha, into their
+/// color and alpha samples.
+fn split_alpha(pixels: &[u8], channels: usize) -> (Vec<u8>, Vec<u8>) {
+    let mut color = Vec::with_capacity(pixels.len() / channels * (channels - 1));
+    let mut alpha = Vec::with_capacity(pixels.len() / channels);
+    for pixel in pixels.chunks_exact(channels) {
+        color.extend_from_slice(&pixel[..channels - 1]);
+        alpha.push(pixel[channels - 1]);
+    }
+    (color, alpha)
+}
+
+fn build_document(frames: Vec<Frame>) -> Document {
+    let mut doc = Document::with_version("1.4");
+    let tree = doc.new_object_id();
+    let mut kids = Vec::with_capacity(frames.len());
+    for frame in frames {
+        let mut image = frame.image;
+        if let Some(mask) = frame.mask {
+            let mask = doc.add_object(mask);
+            image.dict.set("SMask", mask);
+        }
+        let image = doc.add_object(image);
+        let (width, height) = (frame.width, frame.height);
+        let content = doc.add_object(Stream::new(
+            Dictionary::new(),
+            format!("q {width:.4} 0 0 {height:.4} 0 0 cm /Im0 Do Q").into_bytes(),
+        ));
+        kids.push(Object::Reference(doc.add_object(Dictionary::from_iter([
+            ("Type", Object::Name(b"Page".to_vec())),
+            ("Parent", Object::Reference(tree)),
+            ("MediaBox", Rect::from_size(width, height).to_object()),
+            ("Contents", Object::Reference(content)),
+            (
+                "Resources",
+                Object::Dictionary(Dictionary::from_iter([(
+                    "XObject",
+                    Object::Dictionary(Dictionary::from_iter([("Im0", Object::Reference(image))])),
+                )])),
+            ),
+        ]))));
+    }
+    let count = i64::try_from(kids.len()).unwrap_or(i64::MAX);
+    doc.objects.insert(
+        tree,
+        Object::Dictionary(Dictionary::from_iter([
+            ("Type", Object::Name(b"Pages".to_vec())),
+            ("Kids", Object::Array(kids)),
+            ("Count", Object::Integer(count)),
+        ])),
+    );
+    let catalog = doc.add_object(Dictionary::from_iter([
+        ("Type", Object::Name(b"Catalog".to_vec())),
+        ("Pages", Object::Reference(tree)),
+    ]));
+    doc.trailer.set("Root", catalog);
+    doc.compress();
+    doc
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use tiff::encoder::{colortype, Rational, TiffEncoder};
+
+    use super::*;
+
+    #[test]
+    fn pages_take_the_size_of_their_images() -> Result<()> {
+        let mut file = Cursor::new(Vec::new());
+        let mut encoder =
+            TiffEncoder::new(&mut file).map_err(|e| PdfError::InvalidArgument(e.to_string()))?;
+        // 300 × 150 pixels at 150 DPI, then 10 × 10 gray with no resolution.
+        let mut image = encoder
+            .new_image::<colortype::RGB8>(300, 150)
+            .map_err(|e| PdfError::InvalidArgument(e.to_string()))?;
+        image.resolution(ResolutionUnit::Inch, Rational { n: 150, d: 1 });
+        image
+            .write_data(&vec![200; 300 * 150 * 3])
+            .map_err(|e| PdfError::InvalidArgument(e.to_string()))?;
+        let mut gray = encoder
+            .new_image::<colortype::Gray8>(10, 10)
+            .map_err(|e| PdfError::InvalidArgument(e.to_string()))?;
+        gray.resolution_unit(ResolutionUnit::None);
+        gray.write_data(&[0; 100])
+            .map_err(|e| PdfError::InvalidArgument(e.to_string()))?;
+
+        file.set_position(0);
+        let mut decoder =
+            Decoder::new(file).map_err(|e| PdfError::InvalidArgument(e.to_string()))?;
+        let first = read_frame(&mut decoder)?;
+        assert!((first.width - 144.0).abs() < 0.01 && (first.height - 72.0).abs() < 0.01);
+        assert_eq!(first.image.content.len(), 300 * 150 * 3);
+        decoder
+            .next_image()
+            .map_err(|e| PdfError::InvalidArgument(e.to_string()))?;
+        let second = read_frame(&mut decoder)?;
+        assert!((second.width - 10.0).abs() < 0.01);
+
+        let doc = build_document(vec![first, second]);
+        assert_eq!(doc.get_pages().len(), 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,303 @@
+//! Title inference for documents whose metadata has none: the largest text
+//! on the first page, as a cover or title page shows it.
+//!
+//! Text is laid out natively (see [`crate::text`]), so only text drawn in
+//! the page's own content counts; text inside form `XObject`s and text in
+//! images (scans) is not seen.
+
+use std::path::Path;
+
+use regex::Regex;
+
+use crate::{
+    load_document, load_for_reading, metadata, save_document,
+    text::{self, Glyph},
+    validate_input_file, PdfError, Plan, Result,
+};
+
+/// Lines at least this much larger than the body text are set as a title.
+const TITLE_RATIO: f32 = 1.1;
+
+/// Lines within this fraction of the largest size are the same size.
+const SIZE_TOLERANCE: f32 = 0.05;
+
+/// A title runs over at most this many lines.
+const MAX_LINES: usize = 3;
+
+/// Glyphs turned further than this (in degrees) are not read as title text.
+const MAX_ANGLE: f32 = 5.0;
+
+/// A title found by [`infer_title`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct InferredTitle {
+    /// The title, its lines joined with spaces.
+    pub title: String,
+    /// Font size of the title, in points.
+    pub font_size: f32,
+    /// The document's current `/Title`, if it is not empty.
+    pub current: Option<String>,
+}
+
+/// Guess the title of `input` from page 1 (pure Rust): the largest text on
+/// the page, with the lines of the same size right below it (up to three
+/// lines), read from the top. When all the text is the same size, the first
+/// line is taken. `None` when page 1 has no text.
+pub fn infer_title(input: impl AsRef<Path>) -> Result<Option<InferredTitle>> {
+    let input = input.as_ref();
+    validate_input_file(input)?;
+    let doc = load_for_reading(input)?;
+    let current = metadata::info_entry(&doc, "Title").filter(|title| !title.trim().is_empty());
+    let Some(&page_id) = doc.get_pages().get(&1) else {
+        return Ok(None);
+    };
+    let Ok(content) = doc.get_and_decode_page_content(page_id) else {
+        return Ok(None);
+    };
+    let layout = text::layout(&doc, page_id, &content.operations);
+    Ok(
+        choose(&lines(&layout.glyphs)).map(|(title, font_size)| InferredTitle {
+            title,
+            font_size,
+            current,
+        }),
+    )
+}
+
+/// Set the `/Title` of `input` to `title` (pure Rust), in the document
+/// information dictionary and, when the document has one, in its XMP
+/// metadata (`dc:title`).
+pub fn set_document_title(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    title: &str,
+) -> Result<()> {
+    let title = check_title(title)?;
+    let mut doc = load_document(input.as_ref())?;
+    metadata::set_info_entry(&mut doc, "Title", title)?;
+    set_xmp_title(&mut doc, title);
+    save_document(&mut doc, output.as_ref())
+}
+
+/// What [`set_document_title`] would do.
+pub fn set_document_title_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    title: &str,
+) -> Result<Plan> {
+    validate_input_file(input.as_ref())?;
+    let title = check_title(title)?;
+    Ok(Plan::new()
+        .native(format!("set document info Title to {title:?}"))
+        .native("set dc:title in the XMP metadata, if there is any")
+        .write(output.as_ref()))
+}
+
+fn check_title(title: &str) -> Result<&str> {
+    let title = title.trim();
+    if title.is_empty() {
+        return Err(PdfError::InvalidArgument(
+            "the title must not be empty".to_string(),
+        ));
+    }
+    Ok(title)
+}
+
+/// A line of text at one size.
+#[derive(Debug, Clone, PartialEq)]
+struct TextLine {
+    text: String,
+    size: f32,
+    /// Top and bottom of the line's glyphs, in user space.
+    top: f32,
+    bottom: f32,
+    left: f32,
+}
+
+/// The glyphs, in drawing order, as lines: a glyph continues the line when
+/// it has the same size and sits on about the same baseline.
+fn lines(glyphs: &[Glyph]) -> Vec<TextLine> {
+    let mut lines: Vec<TextLine> = Vec::new();
+    let mut previous: Option<&Glyph> = None;
+    for glyph in glyphs
+        .iter()
+        .filter(|g| g.angle.abs() <= MAX_ANGLE && g.size > 0.0)
+    {
+        let same_line = previous.is_some_and(|p| {
+            (glyph.size - p.size).abs() <= p.size * SIZE_TOLERANCE
+                && (glyph.bbox.lly - p.bbox.lly).abs() <= p.size / 2.0
+        });
+        match (same_line, previous, lines.last_mut()) {
+            (true, Some(p), Some(line)) => {
+                if glyph.bbox.llx - p.bbox.urx > p.size * 0.15 {
+                    line.text.push(' ');
+                }
+                line.text.push_str(&glyph.text);
+                line.top = line.top.max(glyph.bbox.ury);
+                line.bottom = line.bottom.min(glyph.bbox.lly);
+                line.left = line.left.min(glyph.bbox.llx);
+            }
+            _ => lines.push(TextLine {
+                text: glyph.text.clone(),
+                size: glyph.size,
+                top: glyph.bbox.ury,
+                bottom: glyph.bbox.lly,
+                left: glyph.bbox.llx,
+            }),
+        }
+        previous = Some(glyph);
+    }
+    for line in &mut lines {
+        line.text = line.text.split_whitespace().collect::<Vec<_>>().join(" ");
+    }
+    // A line needs two letters or digits, so that drop caps, bullets, and
+    // rules drawn with characters are not taken for a title.
+    lines.retain(|line| line.text.chars().filter(|c| c.is_alphanumeric()).count() >= 2);
+    lines.sort_by(|a, b| b.top.total_cmp(&a.top).then(a.left.total_cmp(&b.left)));
+    lines
+}
+
+/// The title among `lines` (sorted from the top) and its size.
+fn choose(lines: &[TextLine]) -> Option<(String, f32)> {
+    let largest = lines.iter().map(|line| line.size).max_by(f32::total_cmp)?;
+    let body = body_size(lines);
+    let same_size = |line: &&TextLine| line.size >= largest * (1.0 - SIZE_TOLERANCE);
+    let mut title_lines = lines.iter().filter(same_size);
+    let first = title_lines.next()?;
+    let mut title = first.text.clone();
+    if largest >= body * TITLE_RATIO {
+        let mut last = first;
+        for line in title_lines.take(MAX_LINES - 1) {
+            if last.bottom - line.top > last.size {
+                break;
+            }
+            title.push(' ');
+            title.push_str(&line.text);
+            last = line;
+        }
+    }
+    Some((title, first.size))
+}
+
+/// The size most of the text on the page is set in.
+fn body_size(lines: &[TextLine]) -> f32 {
+    let mut sizes: Vec<(f32, usize)> = Vec::new();
+    for line in lines {
+        let chars = line.text.chars().count();
+        match sizes
+            .iter_mut()
+            .find(|(size, _)| (line.size - *size).abs() <= *size * SIZE_TOLERANCE)
+        {
+            Some((_, count)) => *count += chars,
+            None => sizes.push((line.size, chars)),
+        }
+    }
+    sizes
+        .into_iter()
+        .max_by_key(|&(_, count)| count)
+        .map_or(0.0, |(size, _)| size)
+}
+
+/// Set `dc:title` in the document's XMP metadata, replacing the one there
+/// or adding one. Documents without XMP metadata are left without.
+fn set_xmp_title(doc: &mut lopdf::Document, title: &str) {
+    let Some(id) = doc
+        .catalog()
+        .ok()
+        .and_then(|c| c.get(b"Metadata").ok())
+        .and_then(|o| o.as_reference().ok())
+    else {
+        return;
+    };
+    let Ok(stream) = doc
+        .get_object_mut(id)
+        .and_then(lopdf::Object::as_stream_mut)
+    else {
+        return;
+    };
+    let bytes = stream
+        .decompressed_content()
+        .unwrap_or_else(|_| stream.content.clone());
+    let Ok(xmp) = String::from_utf8(bytes) else {
+        return;
+    };
+    if let Some(updated) = xmp_with_title(&xmp, title) {
+        stream.set_plain_content(updated.into_bytes());
+    }
+}
+
+/// `xmp` with its `dc:title` set to `title`; `None` if it is not an XMP
+/// packet.
+fn xmp_with_title(xmp: &str, title: &str) -> Option<String> {
+    let escaped = title
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    let element = format!(
+        "<dc:title><rdf:Alt><rdf:li xml:lang=\"x-default\">{escaped}</rdf:li></rdf:Alt></dc:title>"
+    );
+    let existing = Regex::new(r"(?s)<dc:title\s*/>|<dc:title\b.*?</dc:title>").ok()?;
+    if existing.is_match(xmp) {
+        return Some(
+            existing
+                .replace(xmp, regex::NoExpand(&element))
+                .into_owned(),
+        );
+    }
+    let end = xmp.find("</rdf:RDF>")?;
+    Some(format!(
+        "{}<rdf:Description rdf:about=\"\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\">{element}</rdf:Description>{}",
+        &xmp[..end],
+        &xmp[end..]
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str, size: f32, top: f32) -> TextLine {
+        TextLine {
+            text: text.to_string(),
+            size,
+            top,
+            bottom: top - size,
+            left: 72.0,
+        }
+    }
+
+    #[test]
+    fn largest_lines_from_the_top_make_the_title() {
+        let lines = [
+            line("ACME Corp", 10.0, 800.0),
+            line("Annual Report", 24.0, 700.0),
+            line("and Accounts 2024", 24.0, 674.0),
+            line("Prepared for the board", 14.0, 640.0),
+            line("Body text that goes on for a while", 10.0, 600.0),
+            line("and on for another long line of text", 10.0, 588.0),
+            line("Footer note in the same large size", 24.0, 100.0),
+        ];
+        assert_eq!(
+            choose(&lines),
+            Some(("Annual Report and Accounts 2024".to_string(), 24.0))
+        );
+        let plain = [
+            line("Meeting notes", 11.0, 800.0),
+            line("Attendees: all", 11.0, 786.0),
+        ];
+        assert_eq!(choose(&plain), Some(("Meeting notes".to_string(), 11.0)));
+        assert_eq!(choose(&[]), None);
+    }
+
+    #[test]
+    fn xmp_title_is_replaced_or_added() {
+        let packet = "<x:xmpmeta><rdf:RDF><rdf:Description rdf:about=\"\">\
+                      <dc:title><rdf:Alt><rdf:li xml:lang=\"x-default\">Old</rdf:li></rdf:Alt></dc:title>\
+                      </rdf:Description></rdf:RDF></x:xmpmeta>";
+        let updated = xmp_with_title(packet, "Q&A $1").unwrap_or_default();
+        assert!(updated.contains(">Q&amp;A $1</rdf:li>"));
+        assert!(!updated.contains("Old"));
+        let added = xmp_with_title("<rdf:RDF></rdf:RDF>", "New").unwrap_or_default();
+        assert!(added.contains("<dc:title><rdf:Alt><rdf:li xml:lang=\"x-default\">New</rdf:li>"));
+        assert_eq!(xmp_with_title("not xmp", "New"), None);
+    }
+}
@@ -0,0 +1,518 @@
+//! Transcoding images stored in codecs some viewers cannot decode (JPEG 2000,
+//! JBIG2) into ones every viewer can (JPEG, Flate).
+//!
+//! Decoding those codecs takes a renderer: each image is drawn alone on a
+//! page of its own pixel size in a scratch document, rendered at 72 DPI
+//! (one pixel per point), and the pixels re-encoded in place of the
+//! original data.
+
+use std::{collections::BTreeMap, fmt, fs, path::Path, process::Command};
+
+use image::{DynamicImage, GrayImage, ImageFormat, RgbImage};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+
+use crate::{
+    engine, find_tool, load_document, objects::import_object, recompress::encode_jpeg,
+    run_tool_writing, save_document, select_engine, tempspace::TempSpace, validate_input_file,
+    CompressReport, Engine, Operation, PdfError, Plan, Result, Tool,
+};
+
+/// An image codec that [`transcode_images`] converts from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ImageCodec {
+    /// JPEG 2000 (`/JPXDecode`).
+    Jpx,
+    /// JBIG2 (`/JBIG2Decode`), common in black and white scans.
+    Jbig2,
+}
+
+impl ImageCodec {
+    /// The stream filter that stores images in this codec.
+    #[must_use]
+    pub fn filter(self) -> &'static str {
+        match self {
+            Self::Jpx => "JPXDecode",
+            Self::Jbig2 => "JBIG2Decode",
+        }
+    }
+}
+
+impl fmt::Display for ImageCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Jpx => "jpx",
+            Self::Jbig2 => "jbig2",
+        })
+    }
+}
+
+/// What [`transcode_images`] converts images into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscodeTarget {
+    /// JPEG (`/DCTDecode`): small, but lossy.
+    Jpeg,
+    /// Flate (`/FlateDecode`): lossless, the better choice for black and
+    /// white images. Images Flate would not make smaller are stored
+    /// uncompressed.
+    Flate,
+}
+
+/// Options for [`transcode_images`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscodeOptions {
+    /// The codecs to convert from.
+    pub from: Vec<ImageCodec>,
+    /// The codec to convert to.
+    pub to: TranscodeTarget,
+    /// JPEG quality, from 1 to 100, for [`TranscodeTarget::Jpeg`].
+    pub jpeg_quality: u8,
+}
+
+impl Default for TranscodeOptions {
+    fn default() -> Self {
+        Self {
+            from: vec![ImageCodec::Jpx, ImageCodec::Jbig2],
+            to: TranscodeTarget::Jpeg,
+            jpeg_quality: 85,
+        }
+    }
+}
+
+/// One image [`transcode_images`] converted, or left as it was.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscodedImage {
+    /// The image's object number.
+    pub object: u32,
+    /// The codec it was stored in.
+    pub codec: ImageCodec,
+    /// Width, in pixels.
+    pub width: u32,
+    /// Height, in pixels.
+    pub height: u32,
+    /// Why the image was left as it was (`None` if it was converted).
+    pub skipped: Option<String>,
+}
+
+/// What [`transcode_images`] did.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscodeReport {
+    /// Every image stored in one of the codecs converted from, in object
+    /// order.
+    pub images: Vec<TranscodedImage>,
+    /// Input and output file sizes.
+    pub sizes: CompressReport,
+}
+
+/// Convert the images of `input` stored in `options.from` codecs to
+/// `options.to` (mutool or Ghostscript, as the render engine).
+///
+/// Images come out as 8-bit gray or RGB: a color space other than those
+/// (including one given in the JPEG 2000 data) is converted by the
+/// renderer, and a `/Decode` array is applied to the pixels. Soft masks
+/// are kept. Stencil masks, JPEG 2000 images with their own alpha channel
+/// (`/SMaskInData`), and, for JPEG, images with a color-key mask are left
+/// as they are, and listed with the reason.
+pub fn transcode_images(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &TranscodeOptions,
+) -> Result<TranscodeReport> {
+    let (input, output) = (input.as_ref(), output.as_ref());
+    validate_options(options)?;
+    let mut doc = load_document(input)?;
+    let mut images = candidates(&doc, options);
+
+    let convert: Vec<(usize, ObjectId)> = images
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, image))| image.skipped.is_none())
+        .map(|(i, (id, _))| (i, *id))
+        .collect();
+    if !convert.is_empty() {
+        let scratch = TempSpace::new("transcode")?;
+        let sheet = scratch.file("images.pdf");
+        let ids: Vec<ObjectId> = convert.iter().map(|&(_, id)| id).collect();
+        save_document(&mut contact_sheet(&doc, &ids), &sheet)?;
+        render(&sheet, scratch.path())?;
+
+        for (page, (index, id)) in (1..).zip(convert) {
+            let rendered = fs::read(scratch.file(&format!("page-{page}.ppm")))?;
+            let image = &mut images[index].1;
+            let replacement = image::load_from_memory_with_format(&rendered, ImageFormat::Pnm)
+                .ok()
+                .and_then(|pixels| {
+                    let Ok(Object::Stream(stream)) = doc.get_object(id) else {
+                        return None;
+                    };
+                    reencode(stream, &pixels, image.width, image.height, options)
+                });
+            match replacement {
+                Some(stream) => {
+                    doc.objects.insert(id, Object::Stream(stream));
+                }
+                None => image.skipped = Some("the renderer could not decode it".to_string()),
+            }
+        }
+    }
+
+    let images: Vec<TranscodedImage> = images.into_iter().map(|(_, image)| image).collect();
+    tracing::debug!(
+        transcoded = images.iter().filter(|i| i.skipped.is_none()).count(),
+        skipped = images.iter().filter(|i| i.skipped.is_some()).count(),
+        "transcoded images"
+    );
+    save_document(&mut doc, output)?;
+    Ok(TranscodeReport {
+        images,
+        sizes: CompressReport::from_sizes(fs::metadata(input)?.len(), fs::metadata(output)?.len()),
+    })
+}
+
+/// What [`transcode_images`] would do.
+pub fn transcode_images_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &TranscodeOptions,
+) -> Result<Plan> {
+    validate_input_file(input.as_ref())?;
+    validate_options(options)?;
+    let from: Vec<String> = options.from.iter().map(ToString::to_string).collect();
+    let scratch = Path::new("<temp>");
+    let (tool, cmd) = render_command(&scratch.join("images.pdf"), scratch)?;
+    Ok(Plan::new()
+        .native(format!(
+            "copy the {} images into a scratch PDF, one page each",
+            from.join(" and ")
+        ))
+        .run(tool, &cmd)
+        .native(match options.to {
+            TranscodeTarget::Jpeg => format!(
+                "re-encode the rendered images as JPEG (quality {})",
+                options.jpeg_quality
+            ),
+            TranscodeTarget::Flate => "re-encode the rendered images with Flate".to_string(),
+        })
+        .write(output.as_ref()))
+}
+
+fn validate_options(options: &TranscodeOptions) -> Result<()> {
+    if options.from.is_empty() {
+        return Err(PdfError::InvalidArgument(
+            "no codecs to transcode from".to_string(),
+        ));
+    }
+    if !(1..=100).contains(&options.jpeg_quality) {
+        return Err(PdfError::InvalidArgument(format!(
+            "JPEG quality must be from 1 to 100, not {}",
+            options.jpeg_quality
+        )));
+    }
+    Ok(())
+}
+
+/// The images stored in one of the codecs converted from, with the reason
+/// to leave those that cannot be converted.
+fn candidates(doc: &Document, options: &TranscodeOptions) -> Vec<(ObjectId, TranscodedImage)> {
+    let mut out = Vec::new();
+    for (&id, obj) in &doc.objects {
+        let Object::Stream(stream) = obj else {
+            continue;
+        };
+        let dict = &stream.dict;
+        let name = |key: &[u8]| dict.get(key).and_then(Object::as_name).ok();
+        if name(b"Subtype") != Some(b"Image".as_slice()) {
+            continue;
+        }
+        let filters = stream.filters().unwrap_or_default();
+        let Some(codec) = options
+            .from
+            .iter()
+            .copied()
+            .find(|codec| filters.iter().any(|f| f == codec.filter()))
+        else {
+            continue;
+        };
+        let dimension = |key: &[u8]| {
+            dict.get(key)
+                .and_then(Object::as_i64)
+                .ok()
+                .and_then(|n| u32::try_from(n).ok())
+                .unwrap_or(0)
+        };
+        let is_true = |key: &[u8]| dict.get(key).and_then(Object::as_bool).unwrap_or(false);
+        let (width, height) = (dimension(b"Width"), dimension(b"Height"));
+        let skipped = if width == 0 || height == 0 {
+            Some("it has no size")
+        } else if is_true(b"ImageMask") {
+            Some("it is a stencil mask")
+        } else if dict
+            .get(b"SMaskInData")
+            .and_then(Object::as_i64)
+            .is_ok_and(|n| n != 0)
+        {
+            Some("its alpha channel is in the JPEG 2000 data")
+        } else if options.to == TranscodeTarget::Jpeg
+            && matches!(dict.get(b"Mask"), Ok(Object::Array(_)))
+        {
+            Some("JPEG would blur its color-key mask")
+        } else {
+            None
+        };
+        out.push((
+            id,
+            TranscodedImage {
+                object: id.0,
+                codec,
+                width,
+                height,
+                skipped: skipped.map(str::to_string),
+            },
+        ));
+    }
+    out
+}
+
+/// A document with one page per image in `ids`, each the image's pixel
+/// size in points with the image filling it. Masks are left out, so that
+/// the color data renders alone.
+fn contact_sheet(doc: &Document, ids: &[ObjectId]) -> Document {
+    let mut sheet = Document::with_version("1.7");
+    let pages_id = sheet.new_object_id();
+    let mut map = BTreeMap::new();
+    let mut kids = Vec::new();
+    for &id in ids {
+        let Ok(Object::Stream(stream)) = doc.get_object(id) else {
+            continue;
+        };
+        let mut image = stream.clone();
+        for key in [b"SMask".as_slice(), b"Mask", b"Interpolate"] {
+            image.dict.remove(key);
+        }
+        let image = import_object(&mut sheet, doc, &Object::Stream(image), &mut map);
+        let image = sheet.add_object(image);
+        let size = |key: &[u8]| stream.dict.get(key).and_then(Object::as_i64).unwrap_or(1);
+        let (width, height) = (size(b"Width"), size(b"Height"));
+        let content = sheet.add_object(Stream::new(
+            Dictionary::new(),
+            format!("q {width} 0 0 {height} 0 0 cm /Im0 Do Q").into_bytes(),
+        ));
+        kids.push(Object::Reference(sheet.add_object(Dictionary::from_iter(
+            [
+                ("Type", Object::Name(b"Page".to_vec())),
+                ("Parent", Object::Reference(pages_id)),
+                (
+                    "MediaBox",
+                    Object::Array(vec![0.into(), 0.into(), width.into(), height.into()]),
+                ),
+                ("Contents", Object::Reference(content)),
+                (
+                    "Resources",
+                    Object::Dictionary(Dictionary::from_iter([(
+                        "XObject",
+                        Object::Dictionary(Dictionary::from_iter([(
+                            "Im0",
+                            Object::Reference(image),
+                        )])),
+                    )])),
+                ),
+            ],
+        ))));
+    }
+    #[allow(clippy::cast_possible_wrap)] // page counts are far below i64::MAX
+    let count = kids.len() as i64;
+    sheet.objects.insert(
+        pages_id,
+        Object::Dictionary(Dictionary::from_iter([
+            ("Type", Object::Name(b"Pages".to_vec())),
+            ("Kids", Object::Array(kids)),
+            ("Count", Object::Integer(count)),
+        ])),
+    );
+    let catalog = sheet.add_object(Dictionary::from_iter([
+        ("Type", Object::Name(b"Catalog".to_vec())),
+        ("Pages", Object::Reference(pages_id)),
+    ]));
+    sheet.trailer.set("Root", Object::Reference(catalog));
+    sheet
+}
+
+/// Render each page of `sheet` to `page-N.ppm` in `out_dir`, one pixel per
+/// point.
+fn render(sheet: &Path, out_dir: &Path) -> Result<()> {
+    let (tool, cmd) = render_command(sheet, out_dir)?;
+    run_tool_writing(tool, cmd, out_dir)
+}
+
+fn render_command(sheet: &Path, out_dir: &Path) -> Result<(Tool, Command)> {
+    let pattern = out_dir.join("page-%d.ppm");
+    match select_engine(Operation::Render)? {
+        Engine::Mutool => {
+            let mut cmd = Command::new(find_tool(Tool::Mutool)?);
+            cmd.arg("draw")
+                .arg("-q")
+                .arg("-F")
+                .arg("ppm")
+                .arg("-c")
+                .arg("rgb")
+                .arg("-r")
+                .arg("72")
+                .arg("-o")
+                .arg(pattern.as_os_str())
+                .arg(sheet.as_os_str());
+            Ok((Tool::Mutool, cmd))
+        }
+        Engine::Ghostscript => {
+            let mut cmd = Command::new(find_tool(Tool::Ghostscript)?);
+            cmd.arg("-sDEVICE=ppmraw")
+                .arg("-r72")
+                .arg("-dNOPAUSE")
+                .arg("-dBATCH")
+                .arg("-dSAFER")
+                .arg(format!("-sOutputFile={}", pattern.display()))
+                .arg(sheet.as_os_str());
+            Ok((Tool::Ghostscript, cmd))
+        }
+        other => Err(engine::unsupported(Operation::Render, other)),
+    }
+}
+
+/// `stream` with its data replaced by `pixels`, as rendered from it, in
+/// the target codec; gray if every pixel is.
+fn reencode(
+    stream: &Stream,
+    pixels: &DynamicImage,
+    width: u32,
+    height: u32,
+    options: &TranscodeOptions,
+) -> Option<Stream> {
+    let mut rgb = pixels.to_rgb8();
+    if rgb.dimensions() != (width, height) {
+        // Renderers may round the page size; the image keeps its own.
+        rgb = image::imageops::resize(&rgb, width, height, image::imageops::FilterType::Triangle);
+    }
+    let pixels = as_gray(&rgb).map_or(DynamicImage::ImageRgb8(rgb), DynamicImage::ImageLuma8);
+    let gray = matches!(pixels, DynamicImage::ImageLuma8(_));
+
+    let mut dict = stream.dict.clone();
+    for key in [
+        b"DecodeParms".as_slice(),
+        b"Decode",
+        b"SMaskInData",
+        b"Length",
+        b"Filter",
+    ] {
+        dict.remove(key);
+    }
+    dict.set("Width", i64::from(width));
+    dict.set("Height", i64::from(height));
+    dict.set("BitsPerComponent", 8);
+    dict.set(
+        "ColorSpace",
+        Object::Name(if gray {
+            b"DeviceGray".to_vec()
+        } else {
+            b"DeviceRGB".to_vec()
+        }),
+    );
+    match options.to {
+        TranscodeTarget::Jpeg => {
+            let jpeg = encode_jpeg(&pixels, options.jpeg_quality)?;
+            dict.set("Filter", Object::Name(b"DCTDecode".to_vec()));
+            Some(Stream::new(dict, jpeg).with_compression(false))
+        }
+        TranscodeTarget::Flate => {
+            let mut stream = Stream::new(dict, pixels.into_bytes());
+            stream.compress().ok()?;
+            Some(stream)
+        }
+    }
+}
+
+/// `rgb` as gray, if every pixel is.
+fn as_gray(rgb: &RgbImage) -> Option<GrayImage> {
+    if !rgb.pixels().all(|p| p[0] == p[1] && p[1] == p[2]) {
+        return None;
+    }
+    let (width, height) = rgb.dimensions();
+    GrayImage::from_raw(width, height, rgb.pixels().map(|p| p[0]).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(filter: &str, extra: &[(&str, Object)]) -> Stream {
+        let mut dict = Dictionary::from_iter([
+            ("Type", Object::Name(b"XObject".to_vec())),
+            ("Subtype", Object::Name(b"Image".to_vec())),
+            ("Width", Object::Integer(4)),
+            ("Height", Object::Integer(2)),
+            ("Filter", Object::Name(filter.as_bytes().to_vec())),
+        ]);
+        for (key, value) in extra {
+            dict.set(*key, value.clone());
+        }
+        Stream::new(dict, vec![0; 64])
+    }
+
+    #[test]
+    fn only_the_requested_codecs_are_candidates() {
+        let mut doc = Document::with_version("1.7");
+        let jpx = doc.add_object(image("JPXDecode", &[]));
+        doc.add_object(image("DCTDecode", &[]));
+        let mask = doc.add_object(image(
+            "JBIG2Decode",
+            &[("ImageMask", Object::Boolean(true))],
+        ));
+
+        let found = candidates(&doc, &TranscodeOptions::default());
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].0, jpx);
+        assert_eq!(found[0].1.codec, ImageCodec::Jpx);
+        assert_eq!(found[0].1.skipped, None);
+        assert_eq!(found[1].0, mask);
+        assert_eq!(found[1].1.skipped.as_deref(), Some("it is a stencil mask"));
+
+        let jbig2_only = TranscodeOptions {
+            from: vec![ImageCodec::Jbig2],
+            ..TranscodeOptions::default()
+        };
+        assert_eq!(candidates(&doc, &jbig2_only).len(), 1);
+    }
+
+    #[test]
+    fn gray_renderings_are_stored_as_gray() {
+        let stream = image("JBIG2Decode", &[("BitsPerComponent", Object::Integer(1))]);
+        // Stripes, so that Flate makes the data smaller.
+        let rgb = RgbImage::from_fn(64, 32, |x, _| {
+            image::Rgb([0, 255, 0].map(|v| v * u8::from(x % 2 == 1)))
+        });
+        let options = TranscodeOptions {
+            to: TranscodeTarget::Flate,
+            ..TranscodeOptions::default()
+        };
+        let Some(flate) = reencode(&stream, &DynamicImage::ImageRgb8(rgb), 64, 32, &options) else {
+            panic!("the image was not re-encoded");
+        };
+        let name = |s: &Stream, key: &[u8]| {
+            s.dict
+                .get(key)
+                .and_then(Object::as_name)
+                .ok()
+                .map(<[u8]>::to_vec)
+        };
+        assert_eq!(name(&flate, b"Filter"), Some(b"FlateDecode".to_vec()));
+        assert_eq!(name(&flate, b"ColorSpace"), Some(b"DeviceRGB".to_vec()));
+
+        let gray = RgbImage::from_fn(64, 32, |x, _| image::Rgb([u8::from(x % 2 == 1) * 255; 3]));
+        let Some(mut flate) = reencode(&stream, &DynamicImage::ImageRgb8(gray), 64, 32, &options)
+        else {
+            panic!("the image was not re-encoded");
+        };
+        assert_eq!(name(&flate, b"ColorSpace"), Some(b"DeviceGray".to_vec()));
+        flate.dict.remove(b"Subtype");
+        let pixels = flate.decompressed_content().unwrap_or_default();
+        assert_eq!(pixels.len(), 64 * 32);
+        assert_eq!(&pixels[..4], &[0, 255, 0, 255]);
+    }
+}
@@ -0,0 +1,447 @@
+//! How viewers open a document: the panel shown next to the pages, the page
+//! layout, the page and zoom to open at (or another open action), and which
+//! parts of the viewer window to hide.
+
+use std::path::Path;
+
+use lopdf::{Dictionary, Document, Object};
+
+use crate::{
+    a11y::{catalog_mut, set_viewer_preference},
+    links::named_destinations,
+    load_document, save_document, PdfError, Plan, Result,
+};
+
+/// The panel a viewer shows next to the pages (`/PageMode`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageMode {
+    /// No panel.
+    None,
+    /// The bookmarks (outline).
+    Outlines,
+    /// Page thumbnails.
+    Thumbnails,
+    /// Full screen, without menus or panels.
+    FullScreen,
+    /// Optional content groups (layers).
+    Layers,
+    /// Attachments.
+    Attachments,
+}
+
+impl PageMode {
+    /// The `/PageMode` name.
+    #[must_use]
+    pub fn pdf_name(self) -> &'static str {
+        match self {
+            Self::None => "UseNone",
+            Self::Outlines => "UseOutlines",
+            Self::Thumbnails => "UseThumbs",
+            Self::FullScreen => "FullScreen",
+            Self::Layers => "UseOC",
+            Self::Attachments => "UseAttachments",
+        }
+    }
+}
+
+/// How pages are laid out (`/PageLayout`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageLayout {
+    /// One page at a time.
+    SinglePage,
+    /// One column of pages, scrolling continuously.
+    OneColumn,
+    /// Two pages side by side, one spread at a time, odd pages on the left.
+    TwoPageLeft,
+    /// Two pages side by side, one spread at a time, odd pages on the right
+    /// (the first page shown alone, as a cover).
+    TwoPageRight,
+    /// Two columns of pages, scrolling continuously, odd pages on the left.
+    TwoColumnLeft,
+    /// Two columns of pages, scrolling continuously, odd pages on the right.
+    TwoColumnRight,
+}
+
+impl PageLayout {
+    /// The `/PageLayout` name.
+    #[must_use]
+    pub fn pdf_name(self) -> &'static str {
+        match self {
+            Self::SinglePage => "SinglePage",
+            Self::OneColumn => "OneColumn",
+            Self::TwoPageLeft => "TwoPageLeft",
+            Self::TwoPageRight => "TwoPageRight",
+            Self::TwoColumnLeft => "TwoColumnLeft",
+            Self::TwoColumnRight => "TwoColumnRight",
+        }
+    }
+}
+
+/// The zoom of the page the document opens at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenFit {
+    /// The whole page fits the window (`/Fit`).
+    Page,
+    /// The page width fits the window (`/FitH`).
+    Width,
+    /// The page height fits the window (`/FitV`).
+    Height,
+    /// A zoom in percent, 100 being actual size (`/XYZ`).
+    Percent(u32),
+}
+
+/// What a document does when it is opened (`/OpenAction`), for
+/// [`set_open_action`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpenAction {
+    /// Show a 1-based page, at a zoom or at the viewer's current zoom.
+    Page {
+        /// 1-based page.
+        page: u32,
+        /// Zoom, or `None` to keep the viewer's.
+        zoom: Option<OpenFit>,
+    },
+    /// Go to a named destination of the document, e.g. the table of
+    /// contents of a merged handbook.
+    Destination(String),
+    /// Remove the open action, so the document opens at the first page.
+    /// Open actions can also run JavaScript or launch other files.
+    Remove,
+}
+
+/// Options for [`set_viewer_options`]. Settings left at `None` or `false`
+/// keep what the document has.
+#[allow(clippy::struct_excessive_bools)] // independent viewer preferences
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ViewerOptions {
+    /// Panel shown next to the pages.
+    pub page_mode: Option<PageMode>,
+    /// Page layout.
+    pub page_layout: Option<PageLayout>,
+    /// 1-based page to open at.
+    pub open_at: Option<u32>,
+    /// Zoom to open at (at page 1 unless `open_at` is set).
+    pub fit: Option<OpenFit>,
+    /// Hide the viewer's toolbars.
+    pub hide_toolbar: bool,
+    /// Hide the viewer's menu bar.
+    pub hide_menubar: bool,
+    /// Resize the window to the first page.
+    pub fit_window: bool,
+    /// Center the window on the screen.
+    pub center_window: bool,
+}
+
+impl ViewerOptions {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The `/ViewerPreferences` flags to set.
+    fn preferences(&self) -> impl Iterator<Item = &'static str> {
+        [
+            (self.hide_toolbar, "HideToolbar"),
+            (self.hide_menubar, "HideMenubar"),
+            (self.fit_window, "FitWindow"),
+            (self.center_window, "CenterWindow"),
+        ]
+        .into_iter()
+        .filter_map(|(set, key)| set.then_some(key))
+    }
+}
+
+/// Set how viewers open the document: `/PageMode`, `/PageLayout`, the
+/// `/OpenAction` page and zoom, and `/ViewerPreferences` flags (pure Rust).
+/// Viewers are free to ignore these, and many honor only some of them.
+pub fn set_viewer_options(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &ViewerOptions,
+) -> Result<()> {
+    let mut doc = load_document(input.as_ref())?;
+    let open_action = open_action(&doc, options)?;
+    let catalog = catalog_mut(&mut doc)?;
+    if let Some(mode) = options.page_mode {
+        catalog.set(
+            "PageMode",
+            Object::Name(mode.pdf_name().as_bytes().to_vec()),
+        );
+    }
+    if let Some(layout) = options.page_layout {
+        catalog.set(
+            "PageLayout",
+            Object::Name(layout.pdf_name().as_bytes().to_vec()),
+        );
+    }
+    if let Some(action) = open_action {
+        catalog.set("OpenAction", action);
+    }
+    for key in options.preferences() {
+        set_viewer_preference(&mut doc, key, Object::Boolean(true))?;
+    }
+    save_document(&mut doc, output.as_ref())
+}
+
+/// What [`set_viewer_options`] would do.
+pub fn set_viewer_options_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &ViewerOptions,
+) -> Result<Plan> {
+    let doc = load_document(input.as_ref())?;
+    open_action(&doc, options)?;
+    let mut plan = Plan::new();
+    if let Some(mode) = options.page_mode {
+        plan = plan.native(format!("set /PageMode to /{}", mode.pdf_name()));
+    }
+    if let Some(layout) = options.page_layout {
+        plan = plan.native(format!("set /PageLayout to /{}", layout.pdf_name()));
+    }
+    if options.open_at.is_some() || options.fit.is_some() {
+        plan = plan.native(format!(
+            "set /OpenAction to page {}{}",
+            options.open_at.unwrap_or(1),
+            options
+                .fit
+                .map_or_else(String::new, |fit| format!(", {}", zoom_label(fit)))
+        ));
+    }
+    for key in options.preferences() {
+        plan = plan.native(format!("set /ViewerPreferences /{key} to true"));
+    }
+    Ok(plan.write(output.as_ref()))
+}
+
+/// The `/OpenAction` destination for `options`, if it sets one, after
+/// checking that there is something to change.
+fn open_action(doc: &Document, options: &ViewerOptions) -> Result<Option<Object>> {
+    if options.is_empty() {
+        return Err(PdfError::InvalidArgument(
+            "no viewer setting given".to_string(),
+        ));
+    }
+    if options.open_at.is_none() && options.fit.is_none() {
+        return Ok(None);
+    }
+    page_destination(doc, options.open_at.unwrap_or(1), options.fit).map(Some)
+}
+
+/// Set or remove what the document does when it is opened (pure Rust).
+/// Returns a description of the open action it had, if any (`page 3`,
+/// `JavaScript action`, ...).
+pub fn set_open_action(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    action: &OpenAction,
+) -> Result<Option<String>> {
+    let mut doc = load_document(input.as_ref())?;
+    let previous = describe_open_action(&doc);
+    let value = open_action_object(&doc, action)?;
+    let catalog = catalog_mut(&mut doc)?;
+    match value {
+        Some(value) => catalog.set("OpenAction", value),
+        None => {
+            catalog.remove(b"OpenAction");
+        }
+    }
+    save_document(&mut doc, output.as_ref())?;
+    Ok(previous)
+}
+
+/// What [`set_open_action`] would do.
+pub fn set_open_action_plan(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    action: &OpenAction,
+) -> Result<Plan> {
+    let doc = load_document(input.as_ref())?;
+    open_action_object(&doc, action)?;
+    let step = match action {
+        OpenAction::Page { page, zoom } => format!(
+            "set /OpenAction to page {page}{}",
+            zoom.map_or_else(String::new, |zoom| format!(", {}", zoom_label(zoom)))
+        ),
+        OpenAction::Destination(name) => format!("set /OpenAction to destination {name:?}"),
+        OpenAction::Remove => match describe_open_action(&doc) {
+            Some(previous) => format!("remove /OpenAction ({previous})"),
+            None => "remove /OpenAction (there is none)".to_string(),
+        },
+    };
+    Ok(Plan::new().native(step).write(output.as_ref()))
+}
+
+/// The `/OpenAction` value for `action`, or `None` to remove it.
+fn open_action_object(doc: &Document, action: &OpenAction) -> Result<Option<Object>> {
+    match action {
+        OpenAction::Page { page, zoom } => page_destination(doc, *page, *zoom).map(Some),
+        OpenAction::Destination(name) => {
+            if !named_destinations(doc).contains_key(name.as_bytes()) {
+                return Err(PdfError::InvalidArgument(format!(
+                    "the document has no destination {name:?} (see dests list)"
+                )));
+            }
+            let mut go_to = Dictionary::new();
+            go_to.set("S", Object::Name(b"GoTo".to_vec()));
+            go_to.set("D", Object::string_literal(name.as_bytes()));
+            Ok(Some(Object::Dictionary(go_to)))
+        }
+        OpenAction::Remove => Ok(None),
+    }
+}
+
+/// A destination showing `page` (1-based) at `zoom`.
+fn page_destination(doc: &Document, page: u32, zoom: Option<OpenFit>) -> Result<Object> {
+    let pages = doc.get_pages();
+    let page_id = pages.get(&page).copied().ok_or_else(|| {
+        PdfError::InvalidArgument(format!(
+            "page {page} is out of range (document has {} pages)",
+            pages.len()
+        ))
+    })?;
+    let mut dest = vec![Object::Reference(page_id)];
+    match zoom {
+        Some(OpenFit::Page) => dest.push(Object::Name(b"Fit".to_vec())),
+        Some(OpenFit::Width) => dest.extend([Object::Name(b"FitH".to_vec()), Object::Null]),
+        Some(OpenFit::Height) => dest.extend([Object::Name(b"FitV".to_vec()), Object::Null]),
+        Some(OpenFit::Percent(0)) => {
+            return Err(PdfError::InvalidArgument(
+                "zoom must be more than 0%".to_string(),
+            ));
+        }
+        Some(OpenFit::Percent(percent)) => dest.extend([
+            Object::Name(b"XYZ".to_vec()),
+            Object::Null,
+            Object::Null,
+            #[allow(clippy::cast_precision_loss)] // zoom levels are small
+            Object::Real(percent as f32 / 100.0),
+        ]),
+        // Null coordinates and zoom keep the viewer's current zoom.
+        None => dest.extend([
+            Object::Name(b"XYZ".to_vec()),
+            Object::Null,
+            Object::Null,
+            Object::Null,
+        ]),
+    }
+    Ok(Object::Array(dest))
+}
+
+/// A short description of the document's `/OpenAction`, if it has one.
+fn describe_open_action(doc: &Document) -> Option<String> {
+    let action = doc.catalog().ok()?.get(b"OpenAction").ok()?;
+    let action = match action {
+        Object::Reference(id) => doc.get_object(*id).ok()?,
+        other => other,
+    };
+    Some(match action {
+        Object::Array(dest) => {
+            let page = dest.first().and_then(|p| p.as_reference().ok());
+            doc.get_pages()
+                .into_iter()
+                .find(|&(_, id)| Some(id) == page)
+                .map_or_else(
+                    || "a destination".to_string(),
+                    |(no, _)| format!("page {no}"),
+                )
+        }
+        Object::Dictionary(action) => match action.get(b"S").and_then(Object::as_name) {
+            Ok(kind) => format!("{} action", String::from_utf8_lossy(kind)),
+            Err(_) => "an action".to_string(),
+        },
+        _ => "an open action".to_string(),
+    })
+}
+
+fn zoom_label(zoom: OpenFit) -> String {
+    match zoom {
+        OpenFit::Page => "fit page".to_string(),
+        OpenFit::Width => "fit width".to_string(),
+        OpenFit::Height => "fit height".to_string(),
+        OpenFit::Percent(percent) => format!("{percent}%"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::dictionary;
+
+    use super::*;
+
+    fn two_pages() -> Document {
+        let mut doc = Document::with_version("1.7");
+        let pages_id = doc.new_object_id();
+        let kids: Vec<Object> = (0..2)
+            .map(|_| {
+                doc.add_object(dictionary! { "Type" => "Page", "Parent" => pages_id })
+                    .into()
+            })
+            .collect();
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! { "Type" => "Pages", "Count" => 2, "Kids" => kids }),
+        );
+        let catalog = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog);
+        doc
+    }
+
+    #[test]
+    fn open_action_points_at_the_page() -> Result<()> {
+        let doc = two_pages();
+        let second = doc.get_pages().get(&2).copied();
+        let options = ViewerOptions {
+            open_at: Some(2),
+            fit: Some(OpenFit::Width),
+            ..ViewerOptions::default()
+        };
+        let Some(Object::Array(dest)) = open_action(&doc, &options)? else {
+            panic!("no open action");
+        };
+        assert_eq!(dest.first().and_then(|p| p.as_reference().ok()), second);
+        assert_eq!(
+            dest.get(1).and_then(|f| f.as_name().ok()),
+            Some(&b"FitH"[..])
+        );
+
+        let options = ViewerOptions {
+            hide_toolbar: true,
+            ..ViewerOptions::default()
+        };
+        assert_eq!(open_action(&doc, &options)?, None);
+        assert!(open_action(&doc, &ViewerOptions::default()).is_err());
+        let options = ViewerOptions {
+            open_at: Some(3),
+            ..ViewerOptions::default()
+        };
+        assert!(open_action(&doc, &options).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn open_actions_are_described() -> Result<()> {
+        let mut doc = two_pages();
+        assert_eq!(describe_open_action(&doc), None);
+        let action = OpenAction::Page {
+            page: 2,
+            zoom: Some(OpenFit::Percent(125)),
+        };
+        let dest = open_action_object(&doc, &action)?;
+        if let Some(dest) = dest {
+            catalog_mut(&mut doc)?.set("OpenAction", dest);
+        }
+        assert_eq!(describe_open_action(&doc).as_deref(), Some("page 2"));
+
+        let script = doc.add_object(dictionary! {
+            "S" => "JavaScript",
+            "JS" => Object::string_literal("app.alert(1)"),
+        });
+        catalog_mut(&mut doc)?.set("OpenAction", script);
+        assert_eq!(
+            describe_open_action(&doc).as_deref(),
+            Some("JavaScript action")
+        );
+        let missing = OpenAction::Destination("toc".to_string());
+        assert!(open_action_object(&doc, &missing).is_err());
+        Ok(())
+    }
+}
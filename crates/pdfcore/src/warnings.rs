@@ -0,0 +1,118 @@
+//! Caveats of operations that succeeded: warnings from tools, files that
+//! had to be repaired to be read, and text that could not be decoded
+//! exactly.
+//!
+//! Warnings are collected per thread while [`collect_warnings`] runs;
+//! outside of it they are only logged.
+
+use std::{cell::RefCell, fmt};
+
+/// The kind of a [`Warning`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+    /// An external tool succeeded but reported a problem.
+    Tool,
+    /// Part of an input was damaged and was skipped or repaired.
+    Repaired,
+    /// A metadata value could not be decoded exactly; undecodable bytes
+    /// were replaced.
+    LossyMetadata,
+    /// Characters of a text file could not be set in the fonts of a
+    /// composed document, or extracted text could not be written in the
+    /// requested encoding, and were replaced.
+    LossyText,
+    /// A tool's version could not be determined, so a version requirement
+    /// was not checked.
+    UnknownToolVersion,
+}
+
+impl WarningKind {
+    /// Stable, machine-readable identifier of the warning kind.
+    #[must_use]
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Tool => "tool_warning",
+            Self::Repaired => "repaired",
+            Self::LossyMetadata => "lossy_metadata",
+            Self::LossyText => "lossy_text",
+            Self::UnknownToolVersion => "unknown_tool_version",
+        }
+    }
+}
+
+/// Something an operation that succeeded wants its caller to know.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    /// What kind of caveat this is.
+    pub kind: WarningKind,
+    /// Human-readable description, naming the file or tool concerned.
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+thread_local! {
+    static COLLECTED: RefCell<Option<Vec<Warning>>> = const { RefCell::new(None) };
+}
+
+/// Run `f` and return its result with the warnings raised on this thread
+/// while it ran. Calls may be nested; the outer call sees the warnings of
+/// the inner one too.
+pub fn collect_warnings<T>(f: impl FnOnce() -> T) -> (T, Vec<Warning>) {
+    let outer = COLLECTED.with(|c| c.borrow_mut().replace(Vec::new()));
+    let value = f();
+    let warnings = COLLECTED.with(|c| {
+        let mut c = c.borrow_mut();
+        let warnings = c.take().unwrap_or_default();
+        *c = outer.map(|mut outer| {
+            outer.extend(warnings.iter().cloned());
+            outer
+        });
+        warnings
+    });
+    (value, warnings)
+}
+
+/// Raise a warning: collected if [`collect_warnings`] is running on this
+/// thread, else logged.
+pub(crate) fn warn(kind: WarningKind, message: impl Into<String>) {
+    let warning = Warning {
+        kind,
+        message: message.into(),
+    };
+    let unclaimed = COLLECTED.with(|c| match c.borrow_mut().as_mut() {
+        Some(collected) => {
+            tracing::debug!(code = kind.code(), "{warning}");
+            collected.push(warning);
+            None
+        }
+        None => Some(warning),
+    });
+    if let Some(warning) = unclaimed {
+        tracing::warn!(code = kind.code(), "{warning}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warnings_are_collected_by_every_enclosing_call() {
+        let (((), inner), outer) = collect_warnings(|| {
+            warn(WarningKind::Tool, "first");
+            collect_warnings(|| warn(WarningKind::Repaired, "second"))
+        });
+        assert_eq!(inner.len(), 1);
+        assert_eq!(inner[0].kind, WarningKind::Repaired);
+        let messages: Vec<String> = outer.iter().map(ToString::to_string).collect();
+        assert_eq!(messages, ["first", "second"]);
+
+        let ((), none) = collect_warnings(|| ());
+        assert!(none.is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+//! XFA forms: detection and extraction of the embedded XML.
+//!
+//! XFA ("XML Forms Architecture") forms keep their layout and data in XML
+//! packets under `/AcroForm /XFA`. Dynamic XFA forms have no real page
+//! content, so text extraction and form tools see blank pages.
+
+use std::{fmt, path::Path};
+
+use lopdf::{Document, Object};
+
+use crate::{load_document, plan::Plan, PdfError, Result};
+
+/// Kind of XFA form in a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XfaForm {
+    /// The XFA data accompanies ordinary page content (a "static" form).
+    Static,
+    /// The pages are rendered from the XFA at view time (`/NeedsRendering`);
+    /// the PDF pages themselves are typically placeholders.
+    Dynamic,
+}
+
+impl XfaForm {
+    /// Lower-case identifier, e.g. `dynamic`.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Static => "static",
+            Self::Dynamic => "dynamic",
+        }
+    }
+}
+
+impl fmt::Display for XfaForm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Report whether a PDF contains an XFA form (pure Rust).
+pub fn xfa_form(path: impl AsRef<Path>) -> Result<Option<XfaForm>> {
+    let doc = load_document(path.as_ref())?;
+    Ok(detect(&doc))
+}
+
+/// Return the XFA XML of a form (pure Rust).
+///
+/// When the XFA is split into packets (`template`, `datasets`, …), they are
+/// concatenated in order, which yields the complete XDP document.
+pub fn xfa_xml(path: impl AsRef<Path>) -> Result<Vec<u8>> {
+    let path = path.as_ref();
+    let doc = load_document(path)?;
+    let xfa = xfa_entry(&doc)
+        .ok_or_else(|| PdfError::InvalidArgument(format!("{} has no XFA form", path.display())))?;
+
+    let streams: Vec<&Object> = match doc.dereference(xfa).map(|(_, o)| o) {
+        // Packets alternate name, stream: [(preamble) 10 0 R (template) 11 0 R …]
+        Ok(Object::Array(items)) => items.iter().skip(1).step_by(2).collect(),
+        Ok(other) => vec![other],
+        Err(_) => Vec::new(),
+    };
+    let mut xml = Vec::new();
+    for obj in streams {
+        let stream = doc
+            .dereference(obj)
+            .and_then(|(_, o)| o.as_stream())
+            .map_err(|_| PdfError::InvalidArgument("XFA packet is not a stream".to_string()))?;
+        let bytes = if stream.dict.has(b"Filter") {
+            stream
+                .decompressed_content()
+                .map_err(|e| PdfError::InvalidArgument(format!("decoding XFA packet: {e}")))?
+        } else {
+            stream.content.clone()
+        };
+        xml.extend_from_slice(&bytes);
+    }
+    Ok(xml)
+}
+
+/// What dumping the XFA XML of `path` would do; `output` is `None` for
+/// standard output.
+pub fn xfa_dump_plan(path: impl AsRef<Path>, output: Option<&Path>) -> Result<Plan> {
+    let path = path.as_ref();
+    let doc = load_document(path)?;
+    if xfa_entry(&doc).is_none() {
+        return Err(PdfError::InvalidArgument(format!(
+            "{} has no XFA form",
+            path.display()
+        )));
+    }
+    let plan = Plan::new().native("concatenate the XFA packets");
+    Ok(match output {
+        Some(output) => plan.write(output),
+        None => plan,
+    })
+}
+
+pub(crate) fn detect(doc: &Document) -> Option<XfaForm> {
+    xfa_entry(doc)?;
+    let dynamic = doc
+        .catalog()
+        .ok()
+        .and_then(|c| c.get(b"NeedsRendering").ok())
+        .and_then(|o| o.as_bool().ok())
+        .unwrap_or(false);
+    Some(if dynamic {
+        XfaForm::Dynamic
+    } else {
+        XfaForm::Static
+    })
+}
+
+fn xfa_entry(doc: &Document) -> Option<&Object> {
+    let catalog = doc.catalog().ok()?;
+    let (_, acroform) = doc.dereference(catalog.get(b"AcroForm").ok()?).ok()?;
+    acroform.as_dict().ok()?.get(b"XFA").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packets_are_detected_and_concatenated() -> Result<()> {
+        let mut doc = Document::with_version("1.7");
+        let mut packet = |xml: &[u8]| {
+            Object::Reference(
+                doc.add_object(lopdf::Stream::new(lopdf::Dictionary::new(), xml.to_vec())),
+            )
+        };
+        let packets = vec![
+            Object::string_literal("preamble"),
+            packet(b"<xdp:xdp>"),
+            Object::string_literal("datasets"),
+            packet(b"<xfa:datasets/>"),
+            Object::string_literal("postamble"),
+            packet(b"</xdp:xdp>"),
+        ];
+        let acroform = lopdf::Dictionary::from_iter([("XFA", Object::Array(packets))]);
+        let pages = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", Object::Name(b"Pages".to_vec())),
+            ("Kids", Object::Array(vec![])),
+            ("Count", Object::Integer(0)),
+        ]));
+        let catalog = doc.add_object(lopdf::Dictionary::from_iter([
+            ("Type", Object::Name(b"Catalog".to_vec())),
+            ("Pages", Object::Reference(pages)),
+            ("AcroForm", Object::Dictionary(acroform)),
+            ("NeedsRendering", Object::Boolean(true)),
+        ]));
+        doc.trailer.set("Root", catalog);
+        assert_eq!(detect(&doc), Some(XfaForm::Dynamic));
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("xfa.pdf");
+        doc.save(&path)?;
+        assert_eq!(xfa_xml(&path)?, b"<xdp:xdp><xfa:datasets/></xdp:xdp>");
+        Ok(())
+    }
+}